@@ -0,0 +1 @@
+ALTER TABLE guild_settings ADD COLUMN allowed_domains TEXT; -- JSON array of permitted media hosts
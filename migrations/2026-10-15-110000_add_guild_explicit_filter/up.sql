@@ -0,0 +1 @@
+ALTER TABLE guild_settings ADD COLUMN explicit_filter BOOLEAN NOT NULL DEFAULT FALSE;
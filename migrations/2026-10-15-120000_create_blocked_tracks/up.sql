@@ -0,0 +1,10 @@
+-- Per-guild track blacklist, keyed by a canonical track key (e.g. youtube:<video id>)
+CREATE TABLE blocked_tracks (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    track_key TEXT NOT NULL,
+    reason TEXT,
+    blocked_by TEXT NOT NULL, -- user ID
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(guild_id, track_key)
+);
@@ -0,0 +1,11 @@
+-- Members barred from playback commands in a guild
+CREATE TABLE music_bans (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    reason TEXT,
+    banned_by TEXT NOT NULL, -- user ID
+    expires_at TIMESTAMP, -- NULL = permanent
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(guild_id, user_id)
+);
@@ -0,0 +1 @@
+ALTER TABLE guild_settings ADD COLUMN blocked_keywords TEXT; -- JSON array of banned title keywords
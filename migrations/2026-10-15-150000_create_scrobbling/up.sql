@@ -0,0 +1,27 @@
+-- Linked scrobbling accounts (one per user per service)
+CREATE TABLE scrobble_accounts (
+    id INTEGER PRIMARY KEY,
+    user_id TEXT NOT NULL, -- Discord user ID
+    service TEXT NOT NULL, -- e.g. 'lastfm'
+    username TEXT,
+    token TEXT NOT NULL, -- service credential, e.g. a Last.fm session key
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, service)
+);
+
+-- Listens waiting to be submitted; rows are deleted once accepted
+CREATE TABLE scrobble_queue (
+    id INTEGER PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    service TEXT NOT NULL,
+    artist TEXT NOT NULL,
+    track TEXT NOT NULL,
+    duration INTEGER, -- in seconds
+    played_at TIMESTAMP NOT NULL, -- when playback started
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT
+);
+
+CREATE INDEX idx_scrobble_queue_next_attempt ON scrobble_queue(next_attempt_at);
@@ -0,0 +1,2 @@
+DROP TABLE saved_playlist_tracks;
+DROP TABLE saved_playlists;
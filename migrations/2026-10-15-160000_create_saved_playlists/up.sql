@@ -0,0 +1,21 @@
+-- Named track lists saved per guild, e.g. imported from Spotify with /playlist import
+CREATE TABLE saved_playlists (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    owner_id TEXT NOT NULL, -- user ID of whoever saved it
+    source_url TEXT, -- where the list was imported from, if anywhere
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(guild_id, name)
+);
+
+CREATE TABLE saved_playlist_tracks (
+    id INTEGER PRIMARY KEY,
+    playlist_id INTEGER NOT NULL REFERENCES saved_playlists(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    duration INTEGER -- in seconds
+);
+
+CREATE INDEX idx_saved_playlist_tracks_playlist ON saved_playlist_tracks(playlist_id, position);
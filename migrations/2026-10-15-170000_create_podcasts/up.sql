@@ -0,0 +1,24 @@
+-- Podcast feeds a guild has subscribed to with /podcast subscribe
+CREATE TABLE podcast_subscriptions (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    feed_url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    subscribed_by TEXT NOT NULL, -- user ID
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(guild_id, feed_url)
+);
+
+-- Episodes seen in each feed, shared by every guild subscribed to it
+CREATE TABLE podcast_episodes (
+    id INTEGER PRIMARY KEY,
+    feed_url TEXT NOT NULL,
+    url TEXT NOT NULL, -- enclosure (audio file) URL
+    title TEXT NOT NULL,
+    duration INTEGER, -- in seconds
+    published_at TIMESTAMP,
+    fetched_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(feed_url, url)
+);
+
+CREATE INDEX idx_podcast_episodes_feed_published ON podcast_episodes(feed_url, published_at);
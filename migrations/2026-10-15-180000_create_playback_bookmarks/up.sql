@@ -0,0 +1,10 @@
+-- Where a long track (audiobook, DJ set, podcast) was left off when skipped or stopped
+CREATE TABLE playback_bookmarks (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    position INTEGER NOT NULL, -- in seconds
+    duration INTEGER, -- in seconds
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(guild_id, url)
+);
@@ -0,0 +1,2 @@
+DROP TABLE queue_share_tracks;
+DROP TABLE queue_shares;
@@ -0,0 +1,20 @@
+-- Snapshots of a guild's queue exported with /queue share, importable elsewhere by token
+CREATE TABLE queue_shares (
+    id INTEGER PRIMARY KEY,
+    token TEXT NOT NULL UNIQUE,
+    guild_id TEXT NOT NULL, -- guild the queue was shared from
+    created_by TEXT NOT NULL, -- user ID of whoever shared it
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE TABLE queue_share_tracks (
+    id INTEGER PRIMARY KEY,
+    share_id INTEGER NOT NULL REFERENCES queue_shares(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT, -- as it was in the queue, if known
+    duration INTEGER -- in seconds
+);
+
+CREATE INDEX idx_queue_share_tracks_share ON queue_share_tracks(share_id, position);
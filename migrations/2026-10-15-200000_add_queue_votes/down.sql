@@ -0,0 +1,3 @@
+DROP TABLE queue_votes;
+ALTER TABLE current_queue DROP COLUMN track_uuid;
+ALTER TABLE current_queue DROP COLUMN votes;
@@ -0,0 +1,11 @@
+-- Listener upvotes on pending queue entries; the queue is reordered by votes between tracks
+ALTER TABLE current_queue ADD COLUMN votes INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE current_queue ADD COLUMN track_uuid TEXT; -- songbird track handle ID, to match rows to the live queue
+
+CREATE TABLE queue_votes (
+    id INTEGER PRIMARY KEY,
+    queue_entry_id INTEGER NOT NULL REFERENCES current_queue(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(queue_entry_id, user_id)
+);
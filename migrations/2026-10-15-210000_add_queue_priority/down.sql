@@ -0,0 +1,2 @@
+ALTER TABLE current_queue DROP COLUMN priority;
+ALTER TABLE guild_settings DROP COLUMN priority_roles;
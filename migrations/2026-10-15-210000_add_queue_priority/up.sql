@@ -0,0 +1,2 @@
+ALTER TABLE guild_settings ADD COLUMN priority_roles TEXT; -- JSON object of role ID -> queue priority
+ALTER TABLE current_queue ADD COLUMN priority INTEGER NOT NULL DEFAULT 0; -- requester's role priority when queued
@@ -0,0 +1,3 @@
+DROP TABLE pending_requests;
+ALTER TABLE guild_settings DROP COLUMN approval_channel_id;
+ALTER TABLE guild_settings DROP COLUMN require_approval;
@@ -0,0 +1,18 @@
+-- Moderation mode: requests from non-DJ members wait for a moderator before being queued
+ALTER TABLE guild_settings ADD COLUMN require_approval BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE guild_settings ADD COLUMN approval_channel_id TEXT; -- where review messages go; defaults to the request's channel
+
+CREATE TABLE pending_requests (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL, -- text channel the request was made in
+    user_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0, -- requester's queue priority, applied on approval
+    review_channel_id TEXT, -- where the approve/reject message was posted
+    review_message_id TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_pending_requests_guild ON pending_requests(guild_id);
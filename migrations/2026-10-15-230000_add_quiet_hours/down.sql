@@ -0,0 +1,4 @@
+ALTER TABLE guild_settings DROP COLUMN quiet_hours_volume;
+ALTER TABLE guild_settings DROP COLUMN quiet_hours_utc_offset;
+ALTER TABLE guild_settings DROP COLUMN quiet_hours_end;
+ALTER TABLE guild_settings DROP COLUMN quiet_hours_start;
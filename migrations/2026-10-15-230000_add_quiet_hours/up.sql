@@ -0,0 +1,5 @@
+-- Overnight quiet-hours window; playback is refused, or capped to quiet_hours_volume if set
+ALTER TABLE guild_settings ADD COLUMN quiet_hours_start TEXT; -- HH:MM local time, NULL = no quiet hours
+ALTER TABLE guild_settings ADD COLUMN quiet_hours_end TEXT; -- HH:MM local time
+ALTER TABLE guild_settings ADD COLUMN quiet_hours_utc_offset TEXT NOT NULL DEFAULT '+00:00';
+ALTER TABLE guild_settings ADD COLUMN quiet_hours_volume REAL;
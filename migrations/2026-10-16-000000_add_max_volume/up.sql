@@ -0,0 +1,2 @@
+-- Loudest volume (0.0-1.0) anyone may set in the guild; also applied to new tracks
+ALTER TABLE guild_settings ADD COLUMN max_volume REAL NOT NULL DEFAULT 1.0;
@@ -0,0 +1,10 @@
+-- Runtime toggles for experimental capabilities, set globally or per guild
+CREATE TABLE feature_flags (
+    id INTEGER PRIMARY KEY,
+    flag TEXT NOT NULL,
+    scope TEXT NOT NULL, -- 'global', or the guild ID the override applies to
+    enabled BOOLEAN NOT NULL,
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(flag, scope)
+);
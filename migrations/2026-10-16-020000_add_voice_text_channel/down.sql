@@ -0,0 +1 @@
+ALTER TABLE voice_connections DROP COLUMN text_channel_id;
@@ -0,0 +1,2 @@
+-- Text channel a session was started from, for notices about the session
+ALTER TABLE voice_connections ADD COLUMN text_channel_id TEXT;
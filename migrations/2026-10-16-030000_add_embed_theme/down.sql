@@ -0,0 +1,3 @@
+ALTER TABLE guild_settings DROP COLUMN embed_footer;
+ALTER TABLE guild_settings DROP COLUMN emoji_set;
+ALTER TABLE guild_settings DROP COLUMN embed_color;
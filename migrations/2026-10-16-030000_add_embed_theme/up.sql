@@ -0,0 +1,5 @@
+-- How playback embeds look in the guild: accent colour (0xRRGGBB, NULL keeps each embed's
+-- own colour), emoji set and extra footer text
+ALTER TABLE guild_settings ADD COLUMN embed_color INTEGER;
+ALTER TABLE guild_settings ADD COLUMN emoji_set TEXT NOT NULL DEFAULT 'classic';
+ALTER TABLE guild_settings ADD COLUMN embed_footer TEXT;
@@ -0,0 +1,2 @@
+-- Audio filters applied to newly queued tracks (JSON array of filter names)
+ALTER TABLE guild_settings ADD COLUMN audio_filters TEXT;
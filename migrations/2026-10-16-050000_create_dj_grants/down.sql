@@ -0,0 +1 @@
+DROP TABLE dj_grants;
@@ -0,0 +1,10 @@
+-- Members given DJ rights for a limited time with /dj grant
+CREATE TABLE dj_grants (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    granted_by TEXT NOT NULL, -- user ID
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(guild_id, user_id)
+);
@@ -0,0 +1 @@
+ALTER TABLE guild_settings DROP COLUMN announcement_channel_id;
@@ -0,0 +1,2 @@
+-- Text channel for the bot's notices to the guild, chosen with /setup
+ALTER TABLE guild_settings ADD COLUMN announcement_channel_id TEXT;
@@ -0,0 +1,6 @@
+DROP INDEX idx_queue_history_track;
+ALTER TABLE queue_history DROP COLUMN status;
+ALTER TABLE queue_history DROP COLUMN finished_at;
+ALTER TABLE queue_history DROP COLUMN started_at;
+ALTER TABLE queue_history DROP COLUMN queued_at;
+ALTER TABLE queue_history DROP COLUMN track_uuid;
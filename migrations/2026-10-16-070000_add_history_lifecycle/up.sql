@@ -0,0 +1,9 @@
+-- When each request was queued, started and finished, and how it ended. Rows from before
+-- this have no status, and their played_at (set when queued) doubles as queued_at.
+ALTER TABLE queue_history ADD COLUMN track_uuid TEXT;
+ALTER TABLE queue_history ADD COLUMN queued_at TIMESTAMP;
+ALTER TABLE queue_history ADD COLUMN started_at TIMESTAMP;
+ALTER TABLE queue_history ADD COLUMN finished_at TIMESTAMP;
+ALTER TABLE queue_history ADD COLUMN status TEXT;
+UPDATE queue_history SET queued_at = played_at;
+CREATE INDEX idx_queue_history_track ON queue_history(guild_id, track_uuid);
@@ -0,0 +1,14 @@
+-- Last synced copy of each guild's in-memory playback state (see src/playback_state.rs)
+CREATE TABLE playback_state (
+    guild_id TEXT PRIMARY KEY NOT NULL,
+    track_uuid TEXT, -- NULL while nothing is playing
+    title TEXT,
+    url TEXT,
+    duration INTEGER,
+    started_at TIMESTAMP,
+    paused_at TIMESTAMP, -- set while paused
+    paused_secs INTEGER NOT NULL DEFAULT 0, -- earlier pauses, in total
+    seek_offset_secs INTEGER NOT NULL DEFAULT 0, -- net jump from seeking
+    loop_mode TEXT NOT NULL DEFAULT 'off',
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,2 @@
+DROP INDEX idx_queue_history_guild_user;
+DROP INDEX idx_queue_history_guild_played;
@@ -0,0 +1,3 @@
+-- Newest-first history pages, optionally for one requester (GET /api/recent-tracks)
+CREATE INDEX idx_queue_history_guild_played ON queue_history(guild_id, played_at);
+CREATE INDEX idx_queue_history_guild_user ON queue_history(guild_id, user_id, played_at);
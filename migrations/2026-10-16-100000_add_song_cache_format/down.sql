@@ -0,0 +1 @@
+ALTER TABLE song_cache DROP COLUMN format;
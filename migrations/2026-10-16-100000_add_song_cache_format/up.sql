@@ -0,0 +1,2 @@
+-- Container format of the cached file as ffprobe reports it, e.g. mp3
+ALTER TABLE song_cache ADD COLUMN format TEXT;
@@ -0,0 +1 @@
+ALTER TABLE current_queue DROP COLUMN status;
@@ -0,0 +1,2 @@
+-- Where each entry is on its way to playing: pending_download, downloading, ready, playing or failed
+ALTER TABLE current_queue ADD COLUMN status TEXT NOT NULL DEFAULT 'ready';
@@ -0,0 +1,11 @@
+-- /play downloads in progress, so ones cut short by a restart can be picked up again
+CREATE TABLE download_jobs (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL, -- text channel /play was used in
+    user_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0, -- requester's queue priority
+    message_id TEXT, -- the /play reply showing the download's progress
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
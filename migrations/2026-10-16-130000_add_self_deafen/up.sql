@@ -0,0 +1,2 @@
+-- Whether the bot deafens itself in voice; NULL follows LYRE_SELF_DEAFEN
+ALTER TABLE guild_settings ADD COLUMN self_deafen BOOLEAN;
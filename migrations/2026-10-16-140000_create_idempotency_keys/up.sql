@@ -0,0 +1,12 @@
+-- Outcomes of queue-add API requests sent with an Idempotency-Key, replayed when a retry
+-- reuses the key
+CREATE TABLE idempotency_keys (
+    guild_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    request TEXT NOT NULL, -- what was asked for, so a key can't be reused for something else
+    status INTEGER NOT NULL, -- HTTP status of the original response
+    body TEXT NOT NULL, -- its JSON body
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (guild_id, user_id, idempotency_key)
+);
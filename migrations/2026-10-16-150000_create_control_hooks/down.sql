@@ -0,0 +1,2 @@
+DROP TABLE control_hook_calls;
+DROP TABLE control_hooks;
@@ -0,0 +1,28 @@
+-- Signed webhooks that let outside systems (stream decks, game servers) queue a predefined
+-- track or saved playlist in a guild
+CREATE TABLE control_hooks (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    token TEXT NOT NULL UNIQUE, -- public part, in the trigger URL
+    secret TEXT NOT NULL, -- HMAC key requests are signed with
+    url TEXT, -- track to queue, or
+    playlist TEXT, -- name of a saved playlist to queue
+    voice_channel_id TEXT NOT NULL, -- joined when the bot isn't in voice
+    rate_limit_per_minute INTEGER NOT NULL DEFAULT 6,
+    created_by TEXT NOT NULL, -- tracks are queued in their name
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMP
+);
+
+-- Audit trail of calls to each hook, accepted or not
+CREATE TABLE control_hook_calls (
+    id INTEGER PRIMARY KEY,
+    hook_id INTEGER NOT NULL REFERENCES control_hooks(id) ON DELETE CASCADE,
+    outcome TEXT NOT NULL, -- accepted, bad_signature, rate_limited or failed
+    detail TEXT,
+    remote_addr TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_control_hook_calls_hook ON control_hook_calls(hook_id, created_at);
@@ -0,0 +1,2 @@
+ALTER TABLE guild_settings DROP COLUMN listening_party_event;
+ALTER TABLE guild_settings DROP COLUMN stage_topic;
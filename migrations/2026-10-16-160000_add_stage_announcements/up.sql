@@ -0,0 +1,4 @@
+-- Show the playing track as the stage topic when the bot is in a Stage channel
+ALTER TABLE guild_settings ADD COLUMN stage_topic BOOLEAN NOT NULL DEFAULT 1;
+-- Keep a "listening party" scheduled event open for each session, describing the playing track
+ALTER TABLE guild_settings ADD COLUMN listening_party_event BOOLEAN NOT NULL DEFAULT 0;
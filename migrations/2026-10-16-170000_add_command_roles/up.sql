@@ -0,0 +1,3 @@
+-- Roles allowed to use each playback command (JSON object of command name -> role IDs);
+-- commands not listed are open to everyone Discord lets run them
+ALTER TABLE guild_settings ADD COLUMN command_roles TEXT;
@@ -0,0 +1,15 @@
+-- Audio files uploaded through the API in chunks, so a large file survives a dropped
+-- connection: the client asks where to carry on from and sends the rest
+CREATE TABLE uploads (
+    id TEXT PRIMARY KEY NOT NULL, -- random, also the file's name on disk
+    guild_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    filename TEXT NOT NULL, -- as the uploader named it, for display
+    size INTEGER NOT NULL, -- announced up front; counts against the guild's quota
+    received INTEGER NOT NULL DEFAULT 0,
+    content_type TEXT, -- sniffed from the file once it's complete
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP
+);
+
+CREATE INDEX idx_uploads_guild ON uploads(guild_id, created_at);
@@ -0,0 +1,3 @@
+-- Text channel the bot posts operational events to (track failures, permission problems,
+-- auto-disconnects); NULL posts none
+ALTER TABLE guild_settings ADD COLUMN log_channel_id TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE song_cache DROP COLUMN metadata_fetched_at;
+ALTER TABLE song_cache DROP COLUMN metadata_json;
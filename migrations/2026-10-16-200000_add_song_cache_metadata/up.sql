@@ -0,0 +1,4 @@
+-- The yt-dlp metadata lyre uses for the URL, as JSON, and when it was fetched; lookups within
+-- the TTL are answered from here instead of running yt-dlp again
+ALTER TABLE song_cache ADD COLUMN metadata_json TEXT;
+ALTER TABLE song_cache ADD COLUMN metadata_fetched_at TIMESTAMP;
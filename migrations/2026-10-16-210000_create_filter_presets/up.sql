@@ -0,0 +1,10 @@
+-- Named sets of audio filters a guild can switch to in one go, e.g. "movie night"
+CREATE TABLE filter_presets (
+    id INTEGER PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    filters TEXT NOT NULL, -- JSON array of filter names, as in guild_settings.audio_filters
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(guild_id, name)
+);
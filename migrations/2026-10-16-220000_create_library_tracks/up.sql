@@ -0,0 +1,12 @@
+-- Audio files found under LYRE_LIBRARY_DIR, with the tags read from them
+CREATE TABLE library_tracks (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE, -- relative to the library folder
+    title TEXT NOT NULL,
+    artist TEXT,
+    album TEXT,
+    duration_secs INTEGER,
+    modified_at TIMESTAMP NOT NULL, -- the file's mtime when it was last read
+    indexed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX idx_library_tracks_title ON library_tracks(title);
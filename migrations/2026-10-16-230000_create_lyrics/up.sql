@@ -0,0 +1,13 @@
+-- Lyrics looked up from the lyrics provider, including songs it had none for, so each song is
+-- only asked about once in a while
+CREATE TABLE lyrics (
+    id INTEGER PRIMARY KEY,
+    lookup_key TEXT NOT NULL UNIQUE, -- artist and title as searched for, lower case
+    title TEXT,
+    artist TEXT,
+    plain_lyrics TEXT,
+    synced_lyrics TEXT, -- LRC, one "[mm:ss.xx] line" per line
+    instrumental BOOLEAN NOT NULL DEFAULT 0,
+    source TEXT NOT NULL,
+    fetched_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
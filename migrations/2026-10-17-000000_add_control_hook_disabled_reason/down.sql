@@ -0,0 +1 @@
+ALTER TABLE control_hooks DROP COLUMN disabled_reason;
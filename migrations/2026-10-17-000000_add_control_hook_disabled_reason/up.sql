@@ -0,0 +1,2 @@
+-- Why a hook stopped working, e.g. its creator was banned from music; NULL while it's active
+ALTER TABLE control_hooks ADD COLUMN disabled_reason TEXT;
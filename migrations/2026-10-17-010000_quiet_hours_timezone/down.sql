@@ -0,0 +1,3 @@
+-- Zones don't turn back into a single offset, so windows fall back to UTC
+ALTER TABLE guild_settings ADD COLUMN quiet_hours_utc_offset TEXT NOT NULL DEFAULT '+00:00';
+ALTER TABLE guild_settings DROP COLUMN quiet_hours_timezone;
@@ -0,0 +1,22 @@
+-- Quiet hours follow an IANA time zone (e.g. Europe/Berlin) so daylight saving is handled.
+-- Whole-hour offsets become the matching Etc/GMT zone, whose sign is inverted POSIX-style;
+-- the half-hour ones map to the zone that keeps that offset all year.
+ALTER TABLE guild_settings ADD COLUMN quiet_hours_timezone TEXT NOT NULL DEFAULT 'UTC';
+UPDATE guild_settings SET quiet_hours_timezone = CASE
+    WHEN substr(quiet_hours_utc_offset, 2) = '00:00' THEN 'UTC'
+    WHEN substr(quiet_hours_utc_offset, 5, 2) = '00'
+        AND NOT (substr(quiet_hours_utc_offset, 1, 1) = '-' AND substr(quiet_hours_utc_offset, 2, 2) > '12')
+        THEN 'Etc/GMT'
+            || CASE substr(quiet_hours_utc_offset, 1, 1) WHEN '+' THEN '-' ELSE '+' END
+            || CAST(substr(quiet_hours_utc_offset, 2, 2) AS INTEGER)
+    WHEN quiet_hours_utc_offset = '+03:30' THEN 'Asia/Tehran'
+    WHEN quiet_hours_utc_offset = '+04:30' THEN 'Asia/Kabul'
+    WHEN quiet_hours_utc_offset = '+05:30' THEN 'Asia/Kolkata'
+    WHEN quiet_hours_utc_offset = '+05:45' THEN 'Asia/Kathmandu'
+    WHEN quiet_hours_utc_offset = '+06:30' THEN 'Asia/Yangon'
+    WHEN quiet_hours_utc_offset = '+08:45' THEN 'Australia/Eucla'
+    WHEN quiet_hours_utc_offset = '+09:30' THEN 'Australia/Darwin'
+    WHEN quiet_hours_utc_offset = '-09:30' THEN 'Pacific/Marquesas'
+    ELSE 'UTC'
+END;
+ALTER TABLE guild_settings DROP COLUMN quiet_hours_utc_offset;
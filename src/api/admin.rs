@@ -0,0 +1,313 @@
+use actix_web::{HttpRequest, HttpResponse, get, post, put};
+use serde::{Deserialize, Serialize};
+use serenity::all::GuildId;
+
+use super::error::{ApiError, ApiResult};
+use super::extract::{ValidJson, ValidQuery};
+use super::guard::require_admin;
+use super::types::ApiResponse;
+use crate::broadcast::{self, MAX_ANNOUNCEMENT_LEN};
+use crate::database::establish_connection;
+use crate::database::models::FeatureFlag;
+use crate::database::models::feature_flag::GLOBAL_SCOPE;
+use crate::features::{self, Feature};
+use crate::metrics::METRICS;
+use crate::validation::{Validate, ValidationError, validate_range, validate_snowflake};
+
+#[derive(Serialize)]
+pub struct ConfigReloadResponse {
+    /// Keys whose value changed; values are left out since the file may hold secrets
+    pub changed: Vec<String>,
+}
+
+/// Re-read the config file, the same as sending the process SIGHUP
+#[post("/api/admin/config/reload")]
+pub async fn reload_config(req: HttpRequest) -> ApiResult<HttpResponse> {
+    let user = require_admin(&req)?;
+    let changed = crate::config::reload().map_err(|e| ApiError::Internal(e.to_string()))?;
+    tracing::info!(
+        "Configuration reloaded by {} via the API; {} key(s) changed",
+        user.user.id,
+        changed.len()
+    );
+    Ok(HttpResponse::Ok().json(ApiResponse::success(ConfigReloadResponse { changed })))
+}
+
+#[derive(Deserialize)]
+pub struct FeatureFlagsQuery {
+    pub guild_id: Option<String>,
+}
+
+impl Validate for FeatureFlagsQuery {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(guild_id) = &self.guild_id {
+            validate_snowflake("guild_id", guild_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every feature flag with its global setting and, given `guild_id`, that guild's override
+#[get("/api/admin/feature-flags")]
+pub async fn get_feature_flags(
+    req: HttpRequest,
+    query: ValidQuery<FeatureFlagsQuery>,
+) -> ApiResult<HttpResponse> {
+    require_admin(&req)?;
+    let mut conn = establish_connection();
+    let states = features::states(&mut conn, query.guild_id.as_deref())
+        .map_err(|e| ApiError::Internal(format!("Failed to load feature flags: {}", e)))?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(states)))
+}
+
+#[derive(Deserialize)]
+pub struct UpdateFeatureFlagRequest {
+    pub flag: String,
+    /// Guild to override the flag for; omit to change the global setting
+    pub guild_id: Option<String>,
+    /// `null` drops the setting so the guild falls back to global, or global to off
+    pub enabled: Option<bool>,
+}
+
+impl Validate for UpdateFeatureFlagRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if Feature::from_key(&self.flag).is_none() {
+            return Err(ValidationError::InvalidFormat {
+                field: "flag",
+                expected: "a flag listed by GET /api/admin/feature-flags",
+            });
+        }
+        if let Some(guild_id) = &self.guild_id {
+            validate_snowflake("guild_id", guild_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[put("/api/admin/feature-flags")]
+pub async fn update_feature_flag(
+    req: HttpRequest,
+    body: ValidJson<UpdateFeatureFlagRequest>,
+) -> ApiResult<HttpResponse> {
+    let user = require_admin(&req)?;
+    let scope = body.guild_id.as_deref().unwrap_or(GLOBAL_SCOPE);
+    let mut conn = establish_connection();
+    let result = match body.enabled {
+        Some(enabled) => {
+            FeatureFlag::set(&mut conn, &body.flag, scope, enabled, &user.user.id).map(|_| ())
+        }
+        None => FeatureFlag::clear(&mut conn, &body.flag, scope).map(|_| ()),
+    };
+    result.map_err(|e| ApiError::Internal(format!("Failed to update feature flag: {}", e)))?;
+    tracing::info!(
+        "{} set feature {} for {} to {:?} via the API",
+        user.user.id,
+        body.flag,
+        scope,
+        body.enabled
+    );
+
+    let states = features::states(&mut conn, body.guild_id.as_deref())
+        .map_err(|e| ApiError::Internal(format!("Failed to load feature flags: {}", e)))?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(states)))
+}
+
+#[derive(Deserialize)]
+pub struct AnnounceRequest {
+    pub message: String,
+}
+
+impl Validate for AnnounceRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_range(
+            "message length",
+            self.message.trim().chars().count(),
+            1,
+            MAX_ANNOUNCEMENT_LEN,
+        )?;
+        Ok(())
+    }
+}
+
+/// Post a notice in every guild with an active voice session, e.g. before a restart
+#[post("/api/admin/announce")]
+pub async fn announce(
+    req: HttpRequest,
+    body: ValidJson<AnnounceRequest>,
+) -> ApiResult<HttpResponse> {
+    let user = require_admin(&req)?;
+    tracing::info!(
+        "{} is broadcasting an announcement via the API",
+        user.user.id
+    );
+    let report = broadcast::announce(body.message.trim())
+        .await
+        .map_err(|e| ApiError::Upstream(format!("Couldn't send the announcement: {}", e)))?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(report)))
+}
+
+#[derive(Serialize)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    /// Voice sessions still playing; safe to restart once this reaches 0
+    pub active_sessions: usize,
+}
+
+fn maintenance_status() -> MaintenanceStatus {
+    MaintenanceStatus {
+        enabled: METRICS.is_draining(),
+        active_sessions: METRICS.snapshot().active_voice_calls,
+    }
+}
+
+#[get("/api/admin/maintenance")]
+pub async fn get_maintenance_mode(req: HttpRequest) -> ApiResult<HttpResponse> {
+    require_admin(&req)?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(maintenance_status())))
+}
+
+#[derive(Deserialize)]
+pub struct MaintenanceRequest {
+    pub enabled: bool,
+}
+
+impl Validate for MaintenanceRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+/// Turn maintenance mode on or off. While on, new playback is refused, current tracks finish and
+/// `/k8s/readyz` reports "draining" so a rolling deploy can wait for sessions to end.
+#[put("/api/admin/maintenance")]
+pub async fn set_maintenance_mode(
+    req: HttpRequest,
+    body: ValidJson<MaintenanceRequest>,
+) -> ApiResult<HttpResponse> {
+    let user = require_admin(&req)?;
+    METRICS.set_draining(body.enabled);
+    tracing::warn!(
+        "Maintenance mode turned {} by {} via the API",
+        if body.enabled { "on" } else { "off" },
+        user.user.id
+    );
+    Ok(HttpResponse::Ok().json(ApiResponse::success(maintenance_status())))
+}
+
+#[derive(Serialize)]
+pub struct ToolStatus {
+    pub path: Option<String>,
+    pub version: Option<String>,
+    /// Why the version couldn't be read, if it couldn't
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ToolsReport {
+    pub yt_dlp: ToolStatus,
+    /// When `POST /api/admin/tools/update` last installed yt-dlp; `null` if it never has
+    pub yt_dlp_last_updated: Option<String>,
+    pub ffmpeg: ToolStatus,
+}
+
+async fn tool_status(path: Option<std::path::PathBuf>, flag: &str) -> ToolStatus {
+    let Some(path) = path else {
+        return ToolStatus {
+            path: None,
+            version: None,
+            error: Some("not installed".to_string()),
+        };
+    };
+    let (version, error) = match crate::preflight::command_version(path.as_os_str(), flag).await {
+        Ok(version) => (Some(version), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    ToolStatus {
+        path: Some(path.display().to_string()),
+        version,
+        error,
+    }
+}
+
+/// Installed yt-dlp and ffmpeg versions
+#[get("/api/admin/tools")]
+pub async fn get_tools(req: HttpRequest) -> ApiResult<HttpResponse> {
+    require_admin(&req)?;
+    let report = ToolsReport {
+        yt_dlp: tool_status(crate::audio::resolve_yt_dlp().await, "--version").await,
+        yt_dlp_last_updated: crate::audio::yt_dlp_last_updated().await,
+        ffmpeg: tool_status(crate::audio::resolve_ffmpeg().await, "-version").await,
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse::success(report)))
+}
+
+/// Install the latest yt-dlp release, for when a site change breaks extraction. The new binary
+/// is only swapped in once it runs, and takes precedence over a yt-dlp on PATH from then on.
+#[post("/api/admin/tools/update")]
+pub async fn update_tools(req: HttpRequest) -> ApiResult<HttpResponse> {
+    let user = require_admin(&req)?;
+    tracing::info!("yt-dlp update requested by {} via the API", user.user.id);
+    let update = crate::audio::update_yt_dlp()
+        .await
+        .map_err(|e| ApiError::Upstream(format!("Failed to update yt-dlp: {}", e)))?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(update)))
+}
+
+#[derive(Deserialize, Default)]
+pub struct SyncCommandsRequest {
+    /// Register in this guild only, where changes show up at once; global otherwise
+    pub guild_id: Option<String>,
+}
+
+impl Validate for SyncCommandsRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(guild_id) = &self.guild_id {
+            validate_snowflake("guild_id", guild_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct SyncCommandsResponse {
+    pub guild_id: Option<String>,
+    /// Names of the commands now registered
+    pub commands: Vec<String>,
+}
+
+/// Register the slash commands again without a restart, e.g. after a flag changes which ones
+/// should be offered. Discord's copy is replaced, so commands the bot no longer has go away.
+#[post("/api/admin/commands/sync")]
+pub async fn sync_commands(
+    req: HttpRequest,
+    body: ValidJson<SyncCommandsRequest>,
+) -> ApiResult<HttpResponse> {
+    let user = require_admin(&req)?;
+    let http = broadcast::http()
+        .ok_or_else(|| ApiError::Conflict("The bot isn't connected to Discord".to_string()))?;
+    let guild_id = body
+        .guild_id
+        .as_deref()
+        .map(|id| id.parse().map(GuildId::new))
+        .transpose()
+        .map_err(|_| ApiError::invalid_input("guild_id must be a snowflake"))?;
+
+    let commands = crate::commands::register(&http, guild_id)
+        .await
+        .map_err(|e| ApiError::Upstream(format!("Failed to register commands: {}", e)))?;
+    tracing::info!(
+        "{} re-registered {} command(s) {} via the API",
+        user.user.id,
+        commands.len(),
+        match &body.guild_id {
+            Some(guild_id) => format!("in guild {}", guild_id),
+            None => "globally".to_string(),
+        }
+    );
+    Ok(
+        HttpResponse::Ok().json(ApiResponse::success(SyncCommandsResponse {
+            guild_id: body.guild_id.clone(),
+            commands,
+        })),
+    )
+}
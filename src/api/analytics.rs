@@ -1,11 +1,32 @@
-use actix_web::{HttpRequest, HttpResponse, Result as ActixResult, get, put, web};
+use actix_web::{HttpRequest, HttpResponse, get, put};
+use chrono::{Datelike, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use serenity::all::GuildId;
+use std::collections::BTreeMap;
 
+use super::error::{ApiError, ApiResult};
+use super::extract::{ValidJson, ValidQuery};
+use super::guard::{require_guild_access, require_guild_manager};
 use super::types::ApiResponse;
 use crate::auth::AuthenticatedUser;
+use crate::broadcast;
+use crate::commands::PLAYBACK_COMMANDS;
 use crate::database::establish_connection;
 use crate::database::models::{GuildSettings, QueueHistory, SongCache};
+use crate::filters::{self, Filter};
+use crate::policy::{
+    MAX_BLOCKED_KEYWORDS, MAX_DJ_ROLES, MAX_KEYWORD_LEN, MAX_PRIORITY_ROLES, MAX_QUEUE_PRIORITY,
+    normalize_host, normalize_keywords, normalize_timezone, parse_clock_time,
+};
+use crate::settings_events;
+use crate::stats::{ListeningStats, MIN_WRAPPED_YEAR, summarize, year_bounds};
+use crate::theme::{EmojiSet, MAX_FOOTER_LEN, format_hex_color, parse_hex_color};
+use crate::validation::{
+    MAX_VOLUME, Validate, ValidationError, validate_host_list, validate_keyword_list,
+    validate_pagination, validate_range, validate_snowflake, validate_volume,
+};
+use crate::voice_manager;
 
 #[derive(Serialize)]
 pub struct RecentTrack {
@@ -14,33 +35,96 @@ pub struct RecentTrack {
     pub user_id: String,
     pub played_at: String,
     pub duration: Option<i32>,
+    pub queued_at: Option<String>,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// `queued`, `playing`, `completed`, `skipped`, `removed` or `failed`; absent for tracks
+    /// recorded before outcomes were
+    pub status: Option<String>,
 }
 
+/// Longest history search accepted, and the most words it may have
+const MAX_SEARCH_LEN: usize = 100;
+const MAX_SEARCH_WORDS: usize = 8;
+
 #[derive(Deserialize)]
 pub struct RecentTracksQuery {
     pub guild_id: String,
     pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// Words that must all appear in the title
+    pub q: Option<String>,
+    /// Only tracks this member requested
+    pub user_id: Option<String>,
+}
+
+impl RecentTracksQuery {
+    fn search_words(&self) -> Vec<String> {
+        self.q
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl Validate for RecentTracksQuery {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_snowflake("guild_id", &self.guild_id)?;
+        validate_pagination(self.limit, self.offset, 10, 50)?;
+        if let Some(user_id) = &self.user_id {
+            validate_snowflake("user_id", user_id)?;
+        }
+        if let Some(q) = &self.q
+            && q.chars().count() > MAX_SEARCH_LEN
+        {
+            return Err(ValidationError::InvalidEntry {
+                field: "q",
+                entry: q.clone(),
+                max: MAX_SEARCH_LEN,
+            });
+        }
+        validate_keyword_list("q", &self.search_words(), MAX_SEARCH_WORDS, MAX_SEARCH_LEN)?;
+        Ok(())
+    }
 }
 
 #[get("/api/recent-tracks")]
 pub async fn get_recent_tracks(
-    _req: HttpRequest,
+    req: HttpRequest,
     _user: AuthenticatedUser,
-    query: web::Query<RecentTracksQuery>,
-) -> ActixResult<HttpResponse> {
+    query: ValidQuery<RecentTracksQuery>,
+) -> ApiResult<HttpResponse> {
+    require_guild_access(&req, &query.guild_id)?;
     let mut conn = establish_connection();
-    let limit = query.limit.unwrap_or(10).min(50); // Cap at 50 tracks
+    let page = validate_pagination(query.limit, query.offset, 10, 50)?;
 
-    match QueueHistory::get_recent_for_guild(&mut conn, &query.guild_id, limit) {
+    match QueueHistory::get_recent_for_guild(
+        &mut conn,
+        &query.guild_id,
+        &query.search_words(),
+        query.user_id.as_deref(),
+        page.limit,
+        page.offset,
+    ) {
         Ok(history) => {
             let tracks: Vec<RecentTrack> = history
                 .into_iter()
-                .map(|h| RecentTrack {
-                    url: h.url,
-                    title: h.title,
-                    user_id: h.user_id,
-                    played_at: h.played_at.format("%Y-%m-%d %H:%M:%S").to_string(),
-                    duration: h.duration,
+                .map(|h| {
+                    let format =
+                        |t: chrono::NaiveDateTime| t.format("%Y-%m-%d %H:%M:%S").to_string();
+                    RecentTrack {
+                        url: h.url,
+                        title: h.title,
+                        user_id: h.user_id,
+                        played_at: format(h.played_at),
+                        duration: h.duration,
+                        queued_at: h.queued_at.map(format),
+                        started_at: h.started_at.map(format),
+                        finished_at: h.finished_at.map(format),
+                        status: h.status,
+                    }
                 })
                 .collect();
 
@@ -48,8 +132,9 @@ pub async fn get_recent_tracks(
         }
         Err(e) => {
             tracing::error!("Failed to get recent tracks: {}", e);
-            Ok(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Failed to get recent tracks")))
+            Err(ApiError::Internal(
+                "Failed to get recent tracks".to_string(),
+            ))
         }
     }
 }
@@ -58,10 +143,92 @@ pub async fn get_recent_tracks(
 pub struct GuildSettingsResponse {
     pub guild_id: String,
     pub default_volume: f32,
+    pub max_volume: f32,
     pub auto_disconnect_minutes: i32,
     pub max_queue_size: i32,
     pub allowed_roles: Vec<String>,
     pub blocked_domains: Vec<String>,
+    pub allowed_domains: Vec<String>,
+    pub explicit_filter: bool,
+    pub blocked_keywords: Vec<String>,
+    pub priority_roles: BTreeMap<String, i32>,
+    pub require_approval: bool,
+    pub approval_channel_id: Option<String>,
+    pub quiet_hours: Option<QuietHoursSettings>,
+    pub theme: ThemeSettings,
+    pub audio_filters: Vec<String>,
+    pub announcement_channel_id: Option<String>,
+    /// Where the bot posts operational events (track failures, permission problems,
+    /// auto-disconnects)
+    pub log_channel_id: Option<String>,
+    /// Whether the bot deafens itself in voice, after falling back to `LYRE_SELF_DEAFEN`
+    pub self_deafen: bool,
+    /// Whether the playing track is shown as the topic of the bot's Stage channel
+    pub stage_topic: bool,
+    /// Whether each session gets a "listening party" scheduled event naming the playing track
+    pub listening_party_event: bool,
+    /// Playback commands locked to roles: command name -> role IDs
+    pub command_roles: BTreeMap<String, Vec<String>>,
+}
+
+/// A guild's daily quiet-hours window; `volume` caps playback instead of refusing it
+#[derive(Serialize, Deserialize)]
+pub struct QuietHoursSettings {
+    pub start: String,
+    pub end: String,
+    /// IANA time zone the times are in, e.g. `Europe/Berlin`
+    pub timezone: String,
+    pub volume: Option<f32>,
+}
+
+/// How the guild's playback embeds look; a `null` accent keeps each embed's built-in colour
+#[derive(Serialize, Deserialize)]
+pub struct ThemeSettings {
+    /// `#RRGGBB`
+    pub accent_color: Option<String>,
+    pub emoji_set: String,
+    pub footer: Option<String>,
+}
+
+impl From<GuildSettings> for GuildSettingsResponse {
+    fn from(settings: GuildSettings) -> Self {
+        Self {
+            allowed_roles: settings.allowed_roles_list(),
+            blocked_domains: settings.blocked_domains_list(),
+            allowed_domains: settings.allowed_domains_list(),
+            blocked_keywords: settings.blocked_keywords_list(),
+            audio_filters: settings.audio_filters_list(),
+            priority_roles: settings.priority_roles_map(),
+            command_roles: settings.command_roles_map(),
+            explicit_filter: settings.explicit_filter,
+            require_approval: settings.require_approval,
+            approval_channel_id: settings.approval_channel_id,
+            announcement_channel_id: settings.announcement_channel_id,
+            log_channel_id: settings.log_channel_id,
+            self_deafen: voice_manager::self_deafen_for(settings.self_deafen),
+            stage_topic: settings.stage_topic,
+            listening_party_event: settings.listening_party_event,
+            quiet_hours: match (settings.quiet_hours_start, settings.quiet_hours_end) {
+                (Some(start), Some(end)) => Some(QuietHoursSettings {
+                    start,
+                    end,
+                    timezone: settings.quiet_hours_timezone,
+                    volume: settings.quiet_hours_volume,
+                }),
+                _ => None,
+            },
+            theme: ThemeSettings {
+                accent_color: settings.embed_color.map(|c| format_hex_color(c as u32)),
+                emoji_set: settings.emoji_set,
+                footer: settings.embed_footer,
+            },
+            guild_id: settings.guild_id,
+            default_volume: settings.default_volume,
+            max_volume: settings.max_volume,
+            auto_disconnect_minutes: settings.auto_disconnect_minutes,
+            max_queue_size: settings.max_queue_size,
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -69,64 +236,43 @@ pub struct GuildSettingsQuery {
     pub guild_id: String,
 }
 
+impl Validate for GuildSettingsQuery {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_snowflake("guild_id", &self.guild_id)?;
+        Ok(())
+    }
+}
+
 #[get("/api/guild-settings")]
 pub async fn get_guild_settings(
-    _req: HttpRequest,
-    _user: AuthenticatedUser,
-    query: web::Query<GuildSettingsQuery>,
-) -> ActixResult<HttpResponse> {
+    req: HttpRequest,
+    query: ValidQuery<GuildSettingsQuery>,
+) -> ApiResult<HttpResponse> {
+    require_guild_manager(&req, &query.guild_id)?;
     let mut conn = establish_connection();
 
     match GuildSettings::find_by_guild_id(&mut conn, &query.guild_id) {
-        Ok(Some(settings)) => {
-            let allowed_roles: Vec<String> = settings
-                .allowed_roles
-                .as_deref()
-                .and_then(|s| serde_json::from_str::<Vec<String>>(s).ok())
-                .unwrap_or_default();
-
-            let blocked_domains: Vec<String> = settings
-                .blocked_domains
-                .as_deref()
-                .and_then(|s| serde_json::from_str::<Vec<String>>(s).ok())
-                .unwrap_or_default();
-
-            let response = GuildSettingsResponse {
-                guild_id: settings.guild_id,
-                default_volume: settings.default_volume,
-                auto_disconnect_minutes: settings.auto_disconnect_minutes,
-                max_queue_size: settings.max_queue_size,
-                allowed_roles,
-                blocked_domains,
-            };
-
-            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
-        }
+        Ok(Some(settings)) => Ok(
+            HttpResponse::Ok().json(ApiResponse::success(GuildSettingsResponse::from(settings)))
+        ),
         Ok(None) => {
             // Create default settings if none exist
             match GuildSettings::create_or_update(&mut conn, &query.guild_id) {
-                Ok(settings) => {
-                    let response = GuildSettingsResponse {
-                        guild_id: settings.guild_id,
-                        default_volume: settings.default_volume,
-                        auto_disconnect_minutes: settings.auto_disconnect_minutes,
-                        max_queue_size: settings.max_queue_size,
-                        allowed_roles: vec![],
-                        blocked_domains: vec![],
-                    };
-                    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
-                }
+                Ok(settings) => Ok(HttpResponse::Ok()
+                    .json(ApiResponse::success(GuildSettingsResponse::from(settings)))),
                 Err(e) => {
                     tracing::error!("Failed to create guild settings: {}", e);
-                    Ok(HttpResponse::InternalServerError()
-                        .json(ApiResponse::<()>::error("Failed to get guild settings")))
+                    Err(ApiError::Internal(
+                        "Failed to get guild settings".to_string(),
+                    ))
                 }
             }
         }
         Err(e) => {
             tracing::error!("Failed to get guild settings: {}", e);
-            Ok(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Failed to get guild settings")))
+            Err(ApiError::Internal(
+                "Failed to get guild settings".to_string(),
+            ))
         }
     }
 }
@@ -142,122 +288,585 @@ pub struct CacheStats {
 pub async fn get_cache_stats(
     _req: HttpRequest,
     _user: AuthenticatedUser,
-) -> ActixResult<HttpResponse> {
-    let mut conn = establish_connection();
-
-    match SongCache::get_cache_size(&mut conn) {
-        Ok(total_size) => {
-            // Get count of cached songs
-            use crate::database::schema::song_cache;
-            use diesel::dsl::count;
-
-            let total_songs = song_cache::table
-                .select(count(song_cache::url))
-                .first::<i64>(&mut conn)
-                .unwrap_or(0);
-
-            let stats = CacheStats {
-                total_songs,
-                total_size_bytes: total_size,
-                total_size_mb: total_size as f64 / 1_048_576.0, // Convert to MB
-            };
-
-            Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
-        }
+) -> ApiResult<HttpResponse> {
+    match cache_stats(&mut establish_connection()) {
+        Ok(stats) => Ok(HttpResponse::Ok().json(ApiResponse::success(stats))),
         Err(e) => {
             tracing::error!("Failed to get cache stats: {}", e);
-            Ok(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Failed to get cache stats")))
+            Err(ApiError::Internal("Failed to get cache stats".to_string()))
         }
     }
 }
 
+/// How many songs are downloaded and how much space they take
+pub(super) fn cache_stats(conn: &mut SqliteConnection) -> QueryResult<CacheStats> {
+    use crate::database::schema::song_cache;
+    use diesel::dsl::count;
+
+    let total_size = SongCache::get_cache_size(conn)?;
+    // Entries only holding metadata aren't downloaded songs
+    let total_songs = song_cache::table
+        .filter(song_cache::file_path.is_not_null())
+        .select(count(song_cache::url))
+        .first::<i64>(conn)
+        .unwrap_or(0);
+
+    Ok(CacheStats {
+        total_songs,
+        total_size_bytes: total_size,
+        total_size_mb: total_size as f64 / 1_048_576.0, // Convert to MB
+    })
+}
+
 #[derive(Deserialize)]
 pub struct UpdateGuildSettingsRequest {
     pub guild_id: String,
     pub default_volume: Option<f32>,
+    /// Loudest volume anyone may set; lowers `default_volume` if it's above the new cap
+    pub max_volume: Option<f32>,
     pub auto_disconnect_minutes: Option<i32>,
     pub max_queue_size: Option<i32>,
+    /// Replaces the guild's media host allowlist; an empty list allows every host
+    pub allowed_domains: Option<Vec<String>>,
+    pub explicit_filter: Option<bool>,
+    /// Replaces the guild's banned title keywords; an empty list disables keyword blocking
+    pub blocked_keywords: Option<Vec<String>>,
+    /// Replaces the guild's role ID -> queue priority map; an empty map disables priority
+    pub priority_roles: Option<BTreeMap<String, i32>>,
+    /// Replaces the guild's DJ role IDs; DJs skip the approval queue
+    pub allowed_roles: Option<Vec<String>>,
+    /// Whether requests from non-DJs need a moderator's approval
+    pub require_approval: Option<bool>,
+    /// Where approval requests are posted; an empty string posts them where they were made
+    pub approval_channel_id: Option<String>,
+    /// Replaces the guild's quiet-hours window; an empty `start` turns quiet hours off
+    pub quiet_hours: Option<QuietHoursSettings>,
+    /// Replaces the guild's embed theme
+    pub theme: Option<ThemeSettings>,
+    /// Replaces the filters applied to newly queued tracks, e.g. `["karaoke", "bassboost:high"]`; an
+    /// empty list turns them all off
+    pub audio_filters: Option<Vec<String>>,
+    /// Where the bot posts notices for the guild; an empty string posts them where the session
+    /// was started
+    pub announcement_channel_id: Option<String>,
+    /// Where the bot posts operational events for the server's admins; an empty string stops
+    /// them
+    pub log_channel_id: Option<String>,
+    /// Whether the bot deafens itself in voice; applied straight away if it's connected
+    pub self_deafen: Option<bool>,
+    /// Whether the playing track is shown as the stage topic in Stage channels
+    pub stage_topic: Option<bool>,
+    /// Whether to open a "listening party" scheduled event for each session
+    pub listening_party_event: Option<bool>,
+    /// Replaces the playback commands locked to roles (command name -> role IDs); members who
+    /// can manage the server are never locked out. An empty map unlocks them all.
+    pub command_roles: Option<BTreeMap<String, Vec<String>>>,
+}
+
+impl Validate for UpdateGuildSettingsRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_snowflake("guild_id", &self.guild_id)?;
+        if let Some(volume) = self.default_volume {
+            validate_volume("default_volume", volume)?;
+        }
+        if let Some(volume) = self.max_volume {
+            validate_volume("max_volume", volume)?;
+        }
+        if let Some(minutes) = self.auto_disconnect_minutes {
+            validate_range("auto_disconnect_minutes", minutes, 1, 60)?;
+        }
+        if let Some(size) = self.max_queue_size {
+            validate_range("max_queue_size", size, 1, 100)?;
+        }
+        if let Some(domains) = &self.allowed_domains {
+            validate_host_list("allowed_domains", domains, 50)?;
+        }
+        if let Some(keywords) = &self.blocked_keywords {
+            validate_keyword_list(
+                "blocked_keywords",
+                keywords,
+                MAX_BLOCKED_KEYWORDS,
+                MAX_KEYWORD_LEN,
+            )?;
+        }
+        if let Some(roles) = &self.priority_roles {
+            if roles.len() > MAX_PRIORITY_ROLES {
+                return Err(ValidationError::TooManyEntries {
+                    field: "priority_roles",
+                    max: MAX_PRIORITY_ROLES,
+                });
+            }
+            for (role_id, priority) in roles {
+                validate_snowflake("priority_roles", role_id)?;
+                validate_range("priority_roles", *priority, 1, MAX_QUEUE_PRIORITY)?;
+            }
+        }
+        if let Some(commands) = &self.command_roles {
+            for (command, roles) in commands {
+                if !PLAYBACK_COMMANDS.contains(&command.as_str()) {
+                    return Err(ValidationError::InvalidFormat {
+                        field: "command_roles",
+                        expected: "keyed by playback commands (play, next, stop, podcast, boost, filter)",
+                    });
+                }
+                if roles.len() > MAX_DJ_ROLES {
+                    return Err(ValidationError::TooManyEntries {
+                        field: "command_roles",
+                        max: MAX_DJ_ROLES,
+                    });
+                }
+                for role_id in roles {
+                    validate_snowflake("command_roles", role_id)?;
+                }
+            }
+        }
+        if let Some(roles) = &self.allowed_roles {
+            if roles.len() > MAX_DJ_ROLES {
+                return Err(ValidationError::TooManyEntries {
+                    field: "allowed_roles",
+                    max: MAX_DJ_ROLES,
+                });
+            }
+            for role_id in roles {
+                validate_snowflake("allowed_roles", role_id)?;
+            }
+        }
+        if let Some(channel_id) = &self.approval_channel_id
+            && !channel_id.is_empty()
+        {
+            validate_snowflake("approval_channel_id", channel_id)?;
+        }
+        if let Some(channel_id) = &self.announcement_channel_id
+            && !channel_id.is_empty()
+        {
+            validate_snowflake("announcement_channel_id", channel_id)?;
+        }
+        if let Some(channel_id) = &self.log_channel_id
+            && !channel_id.is_empty()
+        {
+            validate_snowflake("log_channel_id", channel_id)?;
+        }
+        if let Some(quiet) = &self.quiet_hours
+            && !quiet.start.is_empty()
+        {
+            let (Some(start), Some(end)) =
+                (parse_clock_time(&quiet.start), parse_clock_time(&quiet.end))
+            else {
+                return Err(ValidationError::InvalidFormat {
+                    field: "quiet_hours",
+                    expected: "HH:MM start and end times",
+                });
+            };
+            if start == end {
+                return Err(ValidationError::InvalidFormat {
+                    field: "quiet_hours",
+                    expected: "a window whose start and end differ",
+                });
+            }
+            if normalize_timezone(&quiet.timezone).is_none() {
+                return Err(ValidationError::InvalidFormat {
+                    field: "quiet_hours",
+                    expected: "given an IANA time zone like Europe/Berlin",
+                });
+            }
+            if let Some(volume) = quiet.volume {
+                validate_volume("quiet_hours", volume)?;
+            }
+        }
+        if let Some(theme) = &self.theme {
+            if theme
+                .accent_color
+                .as_deref()
+                .is_some_and(|c| parse_hex_color(c).is_none())
+            {
+                return Err(ValidationError::InvalidFormat {
+                    field: "theme",
+                    expected: "an accent_color like #5865F2",
+                });
+            }
+            if EmojiSet::from_key(&theme.emoji_set).is_none() {
+                return Err(ValidationError::InvalidFormat {
+                    field: "theme",
+                    expected: "an emoji_set of classic, minimal or none",
+                });
+            }
+            if let Some(footer) = &theme.footer {
+                validate_range("theme", footer.chars().count(), 0, MAX_FOOTER_LEN)?;
+            }
+        }
+        if let Some(names) = &self.audio_filters
+            && names.iter().any(|n| Filter::from_key(n).is_none())
+        {
+            return Err(ValidationError::InvalidFormat {
+                field: "audio_filters",
+                expected: "a list of filter names (karaoke, 8d, loudnorm, bassboost:<low|medium|high|1-20>)",
+            });
+        }
+        Ok(())
+    }
 }
 
 #[put("/api/guild-settings")]
 pub async fn update_guild_settings(
-    _req: HttpRequest,
-    _user: AuthenticatedUser,
-    body: web::Json<UpdateGuildSettingsRequest>,
-) -> ActixResult<HttpResponse> {
+    http_req: HttpRequest,
+    body: ValidJson<UpdateGuildSettingsRequest>,
+) -> ApiResult<HttpResponse> {
+    require_guild_manager(&http_req, &body.guild_id)?;
     let mut conn = establish_connection();
     let req = body.into_inner();
 
     // Ensure guild settings exist first
-    if GuildSettings::find_by_guild_id(&mut conn, &req.guild_id).is_err()
-        && let Err(e) = GuildSettings::create_or_update(&mut conn, &req.guild_id)
+    if !matches!(
+        GuildSettings::find_by_guild_id(&mut conn, &req.guild_id),
+        Ok(Some(_))
+    ) && let Err(e) = GuildSettings::create_or_update(&mut conn, &req.guild_id)
     {
         tracing::error!("Failed to create guild settings: {}", e);
-        return Ok(HttpResponse::InternalServerError()
-            .json(ApiResponse::<()>::error("Failed to create guild settings")));
+        return Err(ApiError::Internal(
+            "Failed to create guild settings".to_string(),
+        ));
     }
 
-    // Update individual settings if provided
+    // The default volume can't exceed the cap, whether that's being set now or already stored
     if let Some(volume) = req.default_volume {
-        if !(0.0..=1.0).contains(&volume) {
-            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
-                "Volume must be between 0.0 and 1.0",
-            )));
+        let max_volume = req.max_volume.unwrap_or_else(|| {
+            GuildSettings::find_by_guild_id(&mut conn, &req.guild_id)
+                .ok()
+                .flatten()
+                .map(|s| s.max_volume)
+                .unwrap_or(MAX_VOLUME)
+        });
+        validate_range("default_volume", volume, 0.0, max_volume)?;
+    }
+
+    // Update individual settings if provided (ranges were checked by `Validate`)
+    if let Some(max_volume) = req.max_volume
+        && let Err(e) = GuildSettings::update_max_volume(&mut conn, &req.guild_id, max_volume)
+    {
+        tracing::error!("Failed to update max volume: {}", e);
+        return Err(ApiError::Internal(
+            "Failed to update max volume".to_string(),
+        ));
+    }
+
+    if let Some(volume) = req.default_volume
+        && let Err(e) = GuildSettings::update_volume(&mut conn, &req.guild_id, volume)
+    {
+        tracing::error!("Failed to update volume: {}", e);
+        return Err(ApiError::Internal("Failed to update volume".to_string()));
+    }
+
+    if let Some(minutes) = req.auto_disconnect_minutes
+        && let Err(e) = GuildSettings::update_auto_disconnect(&mut conn, &req.guild_id, minutes)
+    {
+        tracing::error!("Failed to update auto-disconnect: {}", e);
+        return Err(ApiError::Internal(
+            "Failed to update auto-disconnect".to_string(),
+        ));
+    }
+
+    if let Some(size) = req.max_queue_size
+        && let Err(e) = GuildSettings::update_max_queue_size(&mut conn, &req.guild_id, size)
+    {
+        tracing::error!("Failed to update max queue size: {}", e);
+        return Err(ApiError::Internal(
+            "Failed to update max queue size".to_string(),
+        ));
+    }
+
+    if let Some(domains) = &req.allowed_domains {
+        let domains: Vec<String> = domains.iter().filter_map(|d| normalize_host(d)).collect();
+        if let Err(e) = GuildSettings::update_allowed_domains(&mut conn, &req.guild_id, &domains) {
+            tracing::error!("Failed to update allowed domains: {}", e);
+            return Err(ApiError::Internal(
+                "Failed to update allowed domains".to_string(),
+            ));
         }
-        if let Err(e) = GuildSettings::update_volume(&mut conn, &req.guild_id, volume) {
-            tracing::error!("Failed to update volume: {}", e);
-            return Ok(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Failed to update volume")));
+    }
+
+    if let Some(enabled) = req.explicit_filter
+        && let Err(e) = GuildSettings::update_explicit_filter(&mut conn, &req.guild_id, enabled)
+    {
+        tracing::error!("Failed to update explicit filter: {}", e);
+        return Err(ApiError::Internal(
+            "Failed to update explicit filter".to_string(),
+        ));
+    }
+
+    if let Some(keywords) = &req.blocked_keywords
+        && let Err(e) = GuildSettings::update_blocked_keywords(
+            &mut conn,
+            &req.guild_id,
+            &normalize_keywords(keywords),
+        )
+    {
+        tracing::error!("Failed to update blocked keywords: {}", e);
+        return Err(ApiError::Internal(
+            "Failed to update blocked keywords".to_string(),
+        ));
+    }
+
+    if let Some(roles) = &req.priority_roles
+        && let Err(e) = GuildSettings::update_priority_roles(&mut conn, &req.guild_id, roles)
+    {
+        tracing::error!("Failed to update priority roles: {}", e);
+        return Err(ApiError::Internal(
+            "Failed to update priority roles".to_string(),
+        ));
+    }
+
+    if let Some(commands) = &req.command_roles {
+        // A command with no roles left is unlocked, not locked to nobody
+        let commands: BTreeMap<String, Vec<String>> = commands
+            .iter()
+            .filter(|(_, roles)| !roles.is_empty())
+            .map(|(command, roles)| (command.clone(), roles.clone()))
+            .collect();
+        if let Err(e) = GuildSettings::update_command_roles(&mut conn, &req.guild_id, &commands) {
+            tracing::error!("Failed to update command roles: {}", e);
+            return Err(ApiError::Internal(
+                "Failed to update command roles".to_string(),
+            ));
         }
     }
 
-    if let Some(minutes) = req.auto_disconnect_minutes {
-        if !(1..=60).contains(&minutes) {
-            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
-                "Auto-disconnect must be between 1 and 60 minutes",
-            )));
+    if let Some(roles) = &req.allowed_roles
+        && let Err(e) = GuildSettings::update_allowed_roles(&mut conn, &req.guild_id, roles)
+    {
+        tracing::error!("Failed to update DJ roles: {}", e);
+        return Err(ApiError::Internal("Failed to update DJ roles".to_string()));
+    }
+
+    if req.require_approval.is_some() || req.approval_channel_id.is_some() {
+        // Either field may be sent alone, so fill the other in from the current settings
+        let current = GuildSettings::find_by_guild_id(&mut conn, &req.guild_id)
+            .ok()
+            .flatten();
+        let enabled = req
+            .require_approval
+            .or(current.as_ref().map(|s| s.require_approval))
+            .unwrap_or(false);
+        let channel_id = match &req.approval_channel_id {
+            Some(id) if id.is_empty() => None,
+            Some(id) => Some(id.clone()),
+            None => current.and_then(|s| s.approval_channel_id),
+        };
+        if let Err(e) =
+            GuildSettings::update_approval(&mut conn, &req.guild_id, enabled, channel_id.as_deref())
+        {
+            tracing::error!("Failed to update approval settings: {}", e);
+            return Err(ApiError::Internal(
+                "Failed to update approval settings".to_string(),
+            ));
         }
-        if let Err(e) = GuildSettings::update_auto_disconnect(&mut conn, &req.guild_id, minutes) {
-            tracing::error!("Failed to update auto-disconnect: {}", e);
-            return Ok(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Failed to update auto-disconnect")));
+    }
+
+    if let Some(quiet) = &req.quiet_hours {
+        // Validated above, so the times and zone parse
+        let result = if quiet.start.is_empty() {
+            GuildSettings::update_quiet_hours(&mut conn, &req.guild_id, None, "UTC", None)
+        } else {
+            let start = parse_clock_time(&quiet.start)
+                .map(|t| t.format("%H:%M").to_string())
+                .unwrap_or_default();
+            let end = parse_clock_time(&quiet.end)
+                .map(|t| t.format("%H:%M").to_string())
+                .unwrap_or_default();
+            GuildSettings::update_quiet_hours(
+                &mut conn,
+                &req.guild_id,
+                Some((&start, &end)),
+                &normalize_timezone(&quiet.timezone).unwrap_or_default(),
+                quiet.volume,
+            )
+        };
+        if let Err(e) = result {
+            tracing::error!("Failed to update quiet hours: {}", e);
+            return Err(ApiError::Internal(
+                "Failed to update quiet hours".to_string(),
+            ));
         }
     }
 
-    if let Some(size) = req.max_queue_size {
-        if !(1..=100).contains(&size) {
-            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
-                "Max queue size must be between 1 and 100",
-            )));
+    if let Some(theme) = &req.theme {
+        let footer = theme
+            .footer
+            .as_deref()
+            .map(str::trim)
+            .filter(|f| !f.is_empty());
+        if let Err(e) = GuildSettings::update_theme(
+            &mut conn,
+            &req.guild_id,
+            theme
+                .accent_color
+                .as_deref()
+                .and_then(parse_hex_color)
+                .map(|c| c as i32),
+            &theme.emoji_set,
+            footer,
+        ) {
+            tracing::error!("Failed to update theme: {}", e);
+            return Err(ApiError::Internal("Failed to update theme".to_string()));
         }
-        if let Err(e) = GuildSettings::update_max_queue_size(&mut conn, &req.guild_id, size) {
-            tracing::error!("Failed to update max queue size: {}", e);
-            return Ok(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Failed to update max queue size")));
+    }
+
+    if let Some(names) = &req.audio_filters {
+        // Stored in the order they're applied, without duplicates
+        let names: Vec<String> = filters::parse_list(names)
+            .into_iter()
+            .map(Filter::key)
+            .collect();
+        if let Err(e) = GuildSettings::update_audio_filters(&mut conn, &req.guild_id, &names) {
+            tracing::error!("Failed to update audio filters: {}", e);
+            return Err(ApiError::Internal(
+                "Failed to update audio filters".to_string(),
+            ));
         }
     }
 
+    if let Some(channel_id) = &req.announcement_channel_id
+        && let Err(e) = GuildSettings::update_announcement_channel(
+            &mut conn,
+            &req.guild_id,
+            Some(channel_id.as_str()).filter(|id| !id.is_empty()),
+        )
+    {
+        tracing::error!("Failed to update announcement channel: {}", e);
+        return Err(ApiError::Internal(
+            "Failed to update announcement channel".to_string(),
+        ));
+    }
+
+    if let Some(channel_id) = &req.log_channel_id
+        && let Err(e) = GuildSettings::update_log_channel(
+            &mut conn,
+            &req.guild_id,
+            Some(channel_id.as_str()).filter(|id| !id.is_empty()),
+        )
+    {
+        tracing::error!("Failed to update log channel: {}", e);
+        return Err(ApiError::Internal(
+            "Failed to update log channel".to_string(),
+        ));
+    }
+
+    if let Some(enabled) = req.self_deafen {
+        if let Err(e) = GuildSettings::update_self_deafen(&mut conn, &req.guild_id, enabled) {
+            tracing::error!("Failed to update self-deafen: {}", e);
+            return Err(ApiError::Internal(
+                "Failed to update self-deafen".to_string(),
+            ));
+        }
+        if let (Some(manager), Ok(guild_id)) =
+            (broadcast::voice_manager(), req.guild_id.parse::<u64>())
+        {
+            voice_manager::refresh_voice_flags(&manager, GuildId::new(guild_id)).await;
+        }
+    }
+
+    if req.stage_topic.is_some() || req.listening_party_event.is_some() {
+        // Either may be sent alone, so fill the other in from the current settings
+        let current = GuildSettings::find_by_guild_id(&mut conn, &req.guild_id)
+            .ok()
+            .flatten();
+        let stage_topic = req
+            .stage_topic
+            .or(current.as_ref().map(|s| s.stage_topic))
+            .unwrap_or(true);
+        let listening_party_event = req
+            .listening_party_event
+            .or(current.as_ref().map(|s| s.listening_party_event))
+            .unwrap_or(false);
+        if let Err(e) = GuildSettings::update_stage_announcements(
+            &mut conn,
+            &req.guild_id,
+            stage_topic,
+            listening_party_event,
+        ) {
+            tracing::error!("Failed to update stage announcements: {}", e);
+            return Err(ApiError::Internal(
+                "Failed to update stage announcements".to_string(),
+            ));
+        }
+    }
+
+    settings_events::changed(&req.guild_id);
+
     // Return updated settings
     match GuildSettings::find_by_guild_id(&mut conn, &req.guild_id) {
-        Ok(Some(settings)) => {
-            let response = GuildSettingsResponse {
-                guild_id: settings.guild_id,
-                default_volume: settings.default_volume,
-                auto_disconnect_minutes: settings.auto_disconnect_minutes,
-                max_queue_size: settings.max_queue_size,
-                allowed_roles: vec![],   // TODO: Parse JSON if needed
-                blocked_domains: vec![], // TODO: Parse JSON if needed
-            };
-            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        Ok(Some(settings)) => Ok(
+            HttpResponse::Ok().json(ApiResponse::success(GuildSettingsResponse::from(settings)))
+        ),
+        Ok(None) => Err(ApiError::NotFound("Guild settings not found".to_string())),
+        Err(e) => {
+            tracing::error!("Failed to get updated guild settings: {}", e);
+            Err(ApiError::Internal(
+                "Failed to get updated settings".to_string(),
+            ))
         }
-        Ok(None) => {
-            Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error("Guild settings not found")))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WrappedQuery {
+    pub guild_id: String,
+    /// Narrow the recap to a single member of the guild
+    pub user_id: Option<String>,
+    pub year: Option<i32>,
+}
+
+impl Validate for WrappedQuery {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_snowflake("guild_id", &self.guild_id)?;
+        if let Some(user_id) = &self.user_id {
+            validate_snowflake("user_id", user_id)?;
+        }
+        if let Some(year) = self.year {
+            validate_range("year", year, MIN_WRAPPED_YEAR, Utc::now().year())?;
         }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct WrappedResponse {
+    pub guild_id: String,
+    pub user_id: Option<String>,
+    pub year: i32,
+    #[serde(flatten)]
+    pub stats: ListeningStats,
+}
+
+#[get("/api/wrapped")]
+pub async fn get_wrapped(
+    req: HttpRequest,
+    query: ValidQuery<WrappedQuery>,
+) -> ApiResult<HttpResponse> {
+    require_guild_access(&req, &query.guild_id)?;
+
+    let year = query.year.unwrap_or_else(|| Utc::now().year());
+    let period = year_bounds(year).ok_or_else(|| ApiError::invalid_input("Invalid year"))?;
+
+    let mut conn = establish_connection();
+    match QueueHistory::find_for_stats(
+        &mut conn,
+        Some(&query.guild_id),
+        query.user_id.as_deref(),
+        Some(period),
+    ) {
+        Ok(history) => Ok(
+            HttpResponse::Ok().json(ApiResponse::success(WrappedResponse {
+                guild_id: query.guild_id.clone(),
+                user_id: query.user_id.clone(),
+                year,
+                stats: summarize(&history, 10),
+            })),
+        ),
         Err(e) => {
-            tracing::error!("Failed to get updated guild settings: {}", e);
-            Ok(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Failed to get updated settings")))
+            tracing::error!("Failed to build wrapped recap: {}", e);
+            Err(ApiError::Internal(
+                "Failed to build wrapped recap".to_string(),
+            ))
         }
     }
 }
@@ -1,26 +1,23 @@
+use super::error::{ApiError, ApiResult};
 use super::types::{ApiResponse, AuthRequest};
-use crate::auth::{get_user_guilds, validate_discord_token};
-use actix_web::{HttpResponse, Result as ActixResult, post, web};
+use crate::auth::{RateLimited, refresh_session};
+use actix_web::{HttpResponse, post, web};
 
+/// Called by the dashboard on sign-in and reload; always asks Discord again, so it also
+/// refreshes the cached guild list other requests are checked against
 #[post("/api/auth/validate")]
-pub async fn validate_auth(req: web::Json<AuthRequest>) -> ActixResult<HttpResponse> {
-    match validate_discord_token(&req.access_token).await {
-        Ok(user) => match get_user_guilds(&req.access_token).await {
-            Ok(guilds) => {
-                let response = serde_json::json!({
-                    "user": user,
-                    "guilds": guilds
-                });
-                Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
-            }
-            Err(e) => Ok(
-                HttpResponse::BadRequest().json(ApiResponse::<()>::error(&format!(
-                    "Failed to get guilds: {}",
-                    e
-                ))),
-            ),
+pub async fn validate_auth(req: web::Json<AuthRequest>) -> ApiResult<HttpResponse> {
+    match refresh_session(&req.access_token).await {
+        Ok(session) => {
+            let response = serde_json::json!({
+                "user": session.user,
+                "guilds": session.guilds
+            });
+            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        }
+        Err(e) => match e.downcast_ref::<RateLimited>() {
+            Some(limited) => Err(ApiError::from(limited)),
+            None => Err(ApiError::InvalidToken(format!("Invalid token: {}", e))),
         },
-        Err(e) => Ok(HttpResponse::Unauthorized()
-            .json(ApiResponse::<()>::error(&format!("Invalid token: {}", e)))),
     }
 }
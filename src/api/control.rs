@@ -1,82 +1,151 @@
-use super::types::{ApiResponse, VolumeRequest};
-use crate::auth::{get_authenticated_user_from_extensions, user_can_control_guild};
-use actix_web::{
-    Error, HttpRequest, HttpResponse, Responder, Result as ActixResult, error::ErrorUnauthorized,
-    post, put, web,
+use super::error::{ApiError, ApiResult};
+use super::extract::{GuildPath, ValidJson};
+use super::guard::require_command_access;
+use super::types::{ApiResponse, SeekRequest, VolumeRequest};
+use crate::database::{
+    establish_connection,
+    models::{CurrentQueue, GuildSettings, VoiceConnection},
 };
+use crate::policy::check_not_draining;
+use crate::validation::{
+    MAX_VOLUME, Validate, ValidationError, validate_range, validate_snowflake,
+};
+use crate::voice_manager;
+use actix_web::{HttpRequest, HttpResponse, post, put};
+use std::time::Duration;
 
 #[post("/api/control/{guild_id}/play")]
-pub async fn next_track(
-    req: HttpRequest,
-    path: web::Path<String>,
-) -> Result<impl Responder, Error> {
+pub async fn next_track(req: HttpRequest, path: GuildPath) -> ApiResult<HttpResponse> {
     let guild_id = path.into_inner();
 
     // Get authenticated user from middleware
-    let user = get_authenticated_user_from_extensions(&req)
-        .map_err(|e| ErrorUnauthorized(format!("Authentication required: {}", e)))?;
+    require_command_access(&req, &guild_id, "next").await?;
 
-    if !user_can_control_guild(&user.guilds, &guild_id) {
-        return Err(ErrorUnauthorized("No permission for this guild"));
+    if crate::simulate::enabled() {
+        let mut db_conn = establish_connection();
+        CurrentQueue::advance_queue(&mut db_conn, &guild_id)
+            .map_err(|e| ApiError::Internal(format!("Failed to skip: {}", e)))?;
+    } else {
+        skip_playing(&guild_id).await?;
     }
 
-    // TODO: Implement next track functionality
-
     Ok(HttpResponse::Ok().json(ApiResponse::success("Next track requested")))
 }
 
 #[post("/api/control/{guild_id}/stop")]
-pub async fn stop_playback(path: web::Path<String>, req: HttpRequest) -> ActixResult<HttpResponse> {
+pub async fn stop_playback(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
     let guild_id = path.into_inner();
 
     // Get authenticated user from middleware
-    let user = match get_authenticated_user_from_extensions(&req) {
-        Ok(user) => user,
-        Err(_) => {
-            return Ok(HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("Authentication failed")));
-        }
-    };
+    require_command_access(&req, &guild_id, "stop").await?;
 
-    if !user_can_control_guild(&user.guilds, &guild_id) {
-        return Ok(HttpResponse::Forbidden()
-            .json(ApiResponse::<()>::error("No permission for this guild")));
+    if crate::simulate::enabled() {
+        let mut db_conn = establish_connection();
+        CurrentQueue::clear_guild_queue(&mut db_conn, &guild_id)
+            .and_then(|_| VoiceConnection::disconnect(&mut db_conn, &guild_id))
+            .map_err(|e| ApiError::Internal(format!("Failed to stop: {}", e)))?;
+    } else if !voice_manager::stop(&guild_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to stop: {}", e)))?
+    {
+        return Err(ApiError::NotFound("Not connected to voice".to_string()));
     }
 
-    // TODO: Implement stop functionality
-
     Ok(HttpResponse::Ok().json(ApiResponse::success("Playback stopped")))
 }
 
+/// Skip the playing track with the same fade-out as `/next`
+pub(super) async fn skip_playing(guild_id: &str) -> ApiResult<()> {
+    let skipped = voice_manager::skip(guild_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to skip: {}", e)))?;
+    if !skipped {
+        return Err(ApiError::NotFound("Not connected to voice".to_string()));
+    }
+    Ok(())
+}
+
+/// Pause the playing track and report where it stopped
+#[post("/api/control/{guild_id}/pause")]
+pub async fn pause_playback(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    set_paused(path.into_inner(), req, true).await
+}
+
+/// Resume a paused track
+#[post("/api/control/{guild_id}/resume")]
+pub async fn resume_playback(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    set_paused(path.into_inner(), req, false).await
+}
+
+async fn set_paused(guild_id: String, req: HttpRequest, paused: bool) -> ApiResult<HttpResponse> {
+    require_command_access(&req, &guild_id, if paused { "pause" } else { "resume" }).await?;
+
+    let state = voice_manager::set_paused(&guild_id, paused)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to change playback: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("Nothing is playing".to_string()))?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(state)))
+}
+
+/// Move the playing track to `seconds` in, for the dashboard's progress bar
+#[put("/api/control/{guild_id}/seek")]
+pub async fn seek_playback(
+    path: GuildPath,
+    req_body: ValidJson<SeekRequest>,
+    req: HttpRequest,
+) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+
+    require_command_access(&req, &guild_id, "seek").await?;
+
+    let duration = {
+        let mut db_conn = establish_connection();
+        CurrentQueue::get_current_track(&mut db_conn, &guild_id)
+            .map_err(|e| ApiError::Internal(format!("Failed to load the current track: {}", e)))?
+            .ok_or_else(|| ApiError::NotFound("Nothing is playing".to_string()))?
+            .duration
+    };
+    if let Some(duration) = duration
+        && req_body.seconds >= f64::from(duration)
+    {
+        return Err(ApiError::invalid_input(format!(
+            "seconds must be less than the track's length ({}s)",
+            duration
+        )));
+    }
+
+    let state = voice_manager::seek(&guild_id, Duration::from_secs_f64(req_body.seconds))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to seek: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("Nothing is playing".to_string()))?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(state)))
+}
+
 #[put("/api/control/{guild_id}/volume")]
 pub async fn set_volume(
-    path: web::Path<String>,
-    req_body: web::Json<VolumeRequest>,
+    path: GuildPath,
+    req_body: ValidJson<VolumeRequest>,
     req: HttpRequest,
-) -> ActixResult<HttpResponse> {
+) -> ApiResult<HttpResponse> {
     let guild_id = path.into_inner();
 
     // Get authenticated user from middleware
-    let user = match get_authenticated_user_from_extensions(&req) {
-        Ok(user) => user,
-        Err(_) => {
-            return Ok(HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("Authentication failed")));
-        }
-    };
+    require_command_access(&req, &guild_id, "volume").await?;
 
-    if !user_can_control_guild(&user.guilds, &guild_id) {
-        return Ok(HttpResponse::Forbidden()
-            .json(ApiResponse::<()>::error("No permission for this guild")));
-    }
-
-    if req_body.volume < 0.0 || req_body.volume > 1.0 {
-        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
-            "Volume must be between 0.0 and 1.0",
-        )));
-    }
+    // Admins can cap how loud anyone may turn the bot up
+    let max_volume = {
+        let mut db_conn = establish_connection();
+        GuildSettings::find_by_guild_id(&mut db_conn, &guild_id)
+            .ok()
+            .flatten()
+            .map(|s| s.max_volume)
+            .unwrap_or(MAX_VOLUME)
+    };
+    validate_range("volume", req_body.volume, 0.0, max_volume)?;
 
-    // TODO: Implement volume control
+    voice_manager::set_volume(&guild_id, req_body.volume)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to set the volume: {}", e)))?;
 
     Ok(HttpResponse::Ok().json(ApiResponse::success(format!(
         "Volume set to {}",
@@ -89,38 +158,29 @@ pub struct JoinRequest {
     pub channel_id: String,
 }
 
+impl Validate for JoinRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_snowflake("channel_id", &self.channel_id)?;
+        Ok(())
+    }
+}
+
 #[post("/api/control/{guild_id}/join")]
 pub async fn join_voice_channel(
-    path: web::Path<String>,
-    req_body: web::Json<JoinRequest>,
+    path: GuildPath,
+    req_body: ValidJson<JoinRequest>,
     req: HttpRequest,
-) -> ActixResult<HttpResponse> {
+) -> ApiResult<HttpResponse> {
     let guild_id = path.into_inner();
 
     // Get authenticated user from middleware
-    let user = match get_authenticated_user_from_extensions(&req) {
-        Ok(user) => user,
-        Err(_) => {
-            return Ok(HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("Authentication failed")));
-        }
-    };
-
-    if !user_can_control_guild(&user.guilds, &guild_id) {
-        return Ok(HttpResponse::Forbidden()
-            .json(ApiResponse::<()>::error("No permission for this guild")));
-    }
-
-    // Validate channel ID format (Discord snowflake)
-    if req_body.channel_id.is_empty() || !req_body.channel_id.chars().all(char::is_numeric) {
-        return Ok(
-            HttpResponse::BadRequest().json(ApiResponse::<()>::error("Invalid channel ID format"))
-        );
-    }
+    let user = require_command_access(&req, &guild_id, "play").await?;
+    // Capacity is checked when the join is processed, but maintenance can be refused right away
+    check_not_draining()?;
 
     // Update database to track the request (even if we can't join immediately)
     {
-        use crate::database::{establish_connection, models::VoiceConnection};
+        use crate::database::models::VoiceConnection;
         let mut db_conn = establish_connection();
         if let Err(e) =
             VoiceConnection::create_or_update(&mut db_conn, &guild_id, Some(&req_body.channel_id))
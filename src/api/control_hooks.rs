@@ -0,0 +1,502 @@
+//! Signed control hooks: `POST /api/hooks/{token}/play` lets an outside system (a stream deck,
+//! a game server event) queue a track or saved playlist chosen when the hook was created,
+//! without a Discord login. Each request is signed with the hook's secret:
+//!
+//! ```text
+//! X-Lyre-Timestamp: <unix seconds>
+//! X-Lyre-Signature: sha256=<hex HMAC-SHA256 of "<timestamp>.<body>">
+//! ```
+//!
+//! Timestamps more than five minutes off are refused, each hook has its own per-minute limit,
+//! and every call is kept in the hook's audit trail.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::{HttpRequest, HttpResponse, delete, get, post, web};
+use once_cell::sync::Lazy;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, GuildId, UserId};
+
+use super::error::{ApiError, ApiResult};
+use super::extract::{GuildPath, ValidJson};
+use super::guard::{require_command_access, require_guild_access};
+use super::queue::simulate_add;
+use super::types::ApiResponse;
+use crate::auth::user_can_manage_guild;
+use crate::broadcast;
+use crate::capacity;
+use crate::database::establish_connection;
+use crate::database::models::{
+    ControlHook, ControlHookCall, GuildSettings, HookCallOutcome, NewControlHookInput,
+    SavedPlaylist,
+};
+use crate::policy::{
+    check_not_draining, check_quiet_hours, check_user_command_roles, check_user_not_banned,
+    user_is_dj,
+};
+use crate::validation::{
+    Validate, ValidationError, validate_media_url, validate_range, validate_snowflake,
+};
+use crate::voice_manager;
+
+const TIMESTAMP_HEADER: &str = "X-Lyre-Timestamp";
+const SIGNATURE_HEADER: &str = "X-Lyre-Signature";
+/// How far a request's timestamp may be from our clock
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_RATE_LIMIT_PER_MINUTE: i32 = 6;
+const MAX_RATE_LIMIT_PER_MINUTE: i32 = 60;
+const MAX_HOOK_NAME_LEN: usize = 64;
+
+/// When each hook was last called, within the rate window
+static RECENT_CALLS: Lazy<Mutex<HashMap<i32, VecDeque<Instant>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Deserialize)]
+pub struct CreateHookRequest {
+    pub name: String,
+    /// Track to queue when the hook fires
+    pub url: Option<String>,
+    /// Or the name of one of the guild's saved playlists
+    pub playlist: Option<String>,
+    /// Voice channel to join when the bot isn't in voice
+    pub voice_channel_id: String,
+    pub rate_limit_per_minute: Option<i32>,
+}
+
+impl Validate for CreateHookRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let name_len = self.name.trim().chars().count();
+        if name_len == 0 || name_len > MAX_HOOK_NAME_LEN {
+            return Err(ValidationError::InvalidEntry {
+                field: "name",
+                entry: self.name.clone(),
+                max: MAX_HOOK_NAME_LEN,
+            });
+        }
+        match (&self.url, &self.playlist) {
+            (Some(url), None) => {
+                validate_media_url(url)?;
+            }
+            (None, Some(_)) => {}
+            _ => {
+                return Err(ValidationError::InvalidFormat {
+                    field: "url",
+                    expected: "set, or playlist set instead, but not both",
+                });
+            }
+        }
+        validate_snowflake("voice_channel_id", &self.voice_channel_id)?;
+        if let Some(limit) = self.rate_limit_per_minute {
+            validate_range("rate_limit_per_minute", limit, 1, MAX_RATE_LIMIT_PER_MINUTE)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct HookInfo {
+    #[serde(flatten)]
+    pub hook: ControlHook,
+    pub trigger_path: String,
+    pub recent_calls: Vec<ControlHookCall>,
+}
+
+/// A newly created hook; the only time its secret is shown
+#[derive(Serialize)]
+pub struct CreatedHook {
+    #[serde(flatten)]
+    pub hook: ControlHook,
+    pub secret: String,
+    pub trigger_path: String,
+}
+
+fn trigger_path(hook: &ControlHook) -> String {
+    format!("/api/hooks/{}/play", hook.token)
+}
+
+#[get("/api/guild-hooks/{guild_id}")]
+pub async fn list_hooks(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_guild_access(&req, &guild_id)?;
+
+    let mut db_conn = establish_connection();
+    let hooks = ControlHook::list_for_guild(&mut db_conn, &guild_id)
+        .and_then(|hooks| {
+            hooks
+                .into_iter()
+                .map(|hook| {
+                    Ok(HookInfo {
+                        trigger_path: trigger_path(&hook),
+                        recent_calls: hook.recent_calls(&mut db_conn)?,
+                        hook,
+                    })
+                })
+                .collect::<diesel::QueryResult<Vec<_>>>()
+        })
+        .map_err(|e| ApiError::Internal(format!("Failed to load hooks: {}", e)))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(hooks)))
+}
+
+#[post("/api/guild-hooks/{guild_id}")]
+pub async fn create_hook(
+    path: GuildPath,
+    body: ValidJson<CreateHookRequest>,
+    req: HttpRequest,
+) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    // Hooks queue tracks in their creator's name, so creating one takes what queueing does
+    let user = require_command_access(&req, &guild_id, "play").await?;
+    let body = body.0;
+
+    let mut db_conn = establish_connection();
+    let settings = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id)
+        .ok()
+        .flatten();
+    if settings.as_ref().is_some_and(|s| s.require_approval)
+        && !user_is_dj(
+            settings.as_ref(),
+            &guild_id,
+            &user.user.id,
+            user_can_manage_guild(&user.guilds, &guild_id),
+        )
+        .await
+    {
+        return Err(ApiError::Forbidden(
+            "Requests need a DJ's approval here, so only DJs can create hooks".to_string(),
+        ));
+    }
+    if let Some(name) = &body.playlist
+        && SavedPlaylist::find_by_name(&mut db_conn, &guild_id, name)
+            .map_err(|e| ApiError::Internal(format!("Failed to look up playlist: {}", e)))?
+            .is_none()
+    {
+        return Err(ApiError::NotFound(format!(
+            "No saved playlist named {}",
+            name
+        )));
+    }
+
+    let hook = ControlHook::create(
+        &mut db_conn,
+        NewControlHookInput {
+            guild_id,
+            name: body.name.trim().to_string(),
+            url: body.url.map(|url| url.trim().to_string()),
+            playlist: body.playlist,
+            voice_channel_id: body.voice_channel_id,
+            rate_limit_per_minute: body
+                .rate_limit_per_minute
+                .unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE),
+            created_by: user.user.id.clone(),
+        },
+    )
+    .map_err(|e| ApiError::Internal(format!("Failed to create hook: {}", e)))?;
+    tracing::info!(
+        "User {} created control hook {:?} in guild {}",
+        user.user.id,
+        hook.name,
+        hook.guild_id
+    );
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(CreatedHook {
+        secret: hook.secret.clone(),
+        trigger_path: trigger_path(&hook),
+        hook,
+    })))
+}
+
+#[delete("/api/guild-hooks/{guild_id}/{hook_id}")]
+pub async fn delete_hook(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    let user = require_guild_access(&req, &guild_id)?;
+    let hook_id: i32 = req
+        .match_info()
+        .query("hook_id")
+        .parse()
+        .map_err(|_| ApiError::invalid_input("hook_id must be a number"))?;
+
+    let mut db_conn = establish_connection();
+    let hook = ControlHook::find(&mut db_conn, &guild_id, hook_id)
+        .map_err(|e| ApiError::Internal(format!("Failed to look up hook: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("Hook not found".to_string()))?;
+    if hook.created_by != user.user.id && !user_can_manage_guild(&user.guilds, &guild_id) {
+        return Err(ApiError::Forbidden(
+            "Only the member who created this hook (or a server manager) can delete it".to_string(),
+        ));
+    }
+    let deleted = ControlHook::delete(&mut db_conn, &guild_id, hook_id)
+        .map_err(|e| ApiError::Internal(format!("Failed to delete hook: {}", e)))?;
+    if !deleted {
+        return Err(ApiError::NotFound("Hook not found".to_string()));
+    }
+    tracing::info!(
+        "User {} deleted control hook {} in guild {}",
+        user.user.id,
+        hook_id,
+        guild_id
+    );
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success("Hook deleted")))
+}
+
+/// Run a hook's action, if the request is signed with its secret and within its rate limit
+#[post("/api/hooks/{token}/play")]
+pub async fn trigger_hook(
+    path: web::Path<String>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> ApiResult<HttpResponse> {
+    // One answer for unknown hooks and bad signatures, so tokens can't be probed
+    let refused = || ApiError::Unauthorized("Unknown hook or invalid signature".to_string());
+    let token = path.into_inner();
+    let remote_addr = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(str::to_string);
+
+    let hook = ControlHook::find_by_token(&mut establish_connection(), &token)
+        .map_err(|e| ApiError::Internal(format!("Failed to look up hook: {}", e)))?;
+    let Some(hook) = hook else {
+        tracing::warn!(
+            "Call to unknown control hook from {}",
+            remote_addr.as_deref().unwrap_or("unknown address")
+        );
+        return Err(refused());
+    };
+    let audit = |outcome: HookCallOutcome, detail: &str| {
+        tracing::info!(
+            "Control hook {:?} in guild {} called from {}: {} ({})",
+            hook.name,
+            hook.guild_id,
+            remote_addr.as_deref().unwrap_or("unknown address"),
+            outcome.as_str(),
+            detail
+        );
+        if let Err(e) = hook.record_call(
+            &mut establish_connection(),
+            outcome,
+            Some(detail),
+            remote_addr.as_deref(),
+        ) {
+            tracing::warn!("Failed to record control hook call: {}", e);
+        }
+    };
+
+    // Every call counts against the limit, so a flood of unsigned junk is refused before it
+    // can crowd real calls out of the audit trail
+    let within_limit = take_rate_slot(&hook);
+    if let Err(reason) = verify_signature(&req, &hook.secret, &body) {
+        if within_limit.is_ok() {
+            audit(HookCallOutcome::BadSignature, reason);
+        }
+        return Err(refused());
+    }
+    if let Err(retry_after) = within_limit {
+        audit(HookCallOutcome::RateLimited, "over the per-minute limit");
+        return Err(ApiError::TooManyRequests {
+            message: format!(
+                "This hook may be called {} times a minute",
+                hook.rate_limit_per_minute
+            ),
+            retry_after_secs: retry_after.as_secs().max(1),
+        });
+    }
+
+    if let Some(reason) = &hook.disabled_reason {
+        audit(HookCallOutcome::Failed, "hook is disabled");
+        return Err(ApiError::Forbidden(format!(
+            "This hook has been disabled: {}",
+            reason
+        )));
+    }
+    if let Err(reason) = check_creator_may_queue(&hook).await {
+        audit(HookCallOutcome::Failed, &reason);
+        if let Err(e) = hook.disable(&mut establish_connection(), &reason) {
+            tracing::warn!("Failed to disable control hook {:?}: {}", hook.name, e);
+        }
+        return Err(ApiError::Forbidden(format!(
+            "This hook has been disabled: {}",
+            reason
+        )));
+    }
+
+    match run_hook(&hook).await {
+        Ok(tracks) => {
+            audit(
+                HookCallOutcome::Accepted,
+                &format!("queued {} track(s)", tracks),
+            );
+            Ok(
+                HttpResponse::Accepted().json(ApiResponse::success(serde_json::json!({
+                    "hook": hook.name,
+                    "tracks": tracks,
+                }))),
+            )
+        }
+        Err(e) => {
+            audit(HookCallOutcome::Failed, &e.to_string());
+            Err(e)
+        }
+    }
+}
+
+/// Check the request's timestamp and HMAC against `secret`; the error says why it failed, for
+/// the audit trail
+fn verify_signature(req: &HttpRequest, secret: &str, body: &[u8]) -> Result<(), &'static str> {
+    let header = |name| req.headers().get(name).and_then(|v| v.to_str().ok());
+    let timestamp = header(TIMESTAMP_HEADER).ok_or("missing timestamp")?;
+    let signed_at: i64 = timestamp
+        .trim()
+        .parse()
+        .map_err(|_| "malformed timestamp")?;
+    if chrono::Utc::now().timestamp().abs_diff(signed_at) > MAX_CLOCK_SKEW_SECS {
+        return Err("timestamp too far from the current time");
+    }
+    let signature = header(SIGNATURE_HEADER)
+        .and_then(|s| s.trim().strip_prefix("sha256="))
+        .ok_or("missing signature")?;
+    let tag = hex::decode(signature).map_err(|_| "malformed signature")?;
+
+    let mut message = Vec::with_capacity(timestamp.len() + 1 + body.len());
+    message.extend_from_slice(timestamp.trim().as_bytes());
+    message.push(b'.');
+    message.extend_from_slice(body);
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, &message, &tag).map_err(|_| "signature mismatch")
+}
+
+/// Count a call against the hook's per-minute limit, or say how long until it has room
+fn take_rate_slot(hook: &ControlHook) -> Result<(), Duration> {
+    let Ok(mut recent) = RECENT_CALLS.lock() else {
+        return Ok(());
+    };
+    let calls = recent.entry(hook.id.unwrap_or_default()).or_default();
+    while calls.front().is_some_and(|at| at.elapsed() >= RATE_WINDOW) {
+        calls.pop_front();
+    }
+    if calls.len() >= hook.rate_limit_per_minute.max(1) as usize {
+        let oldest = calls.front().map(Instant::elapsed).unwrap_or_default();
+        return Err(RATE_WINDOW.saturating_sub(oldest));
+    }
+    calls.push_back(Instant::now());
+    Ok(())
+}
+
+/// Hooks queue tracks in their creator's name, so they only run while the creator could queue
+/// them directly: not banned from music, allowed to use `/play`, and a DJ if requests need
+/// approval. Says why not.
+async fn check_creator_may_queue(hook: &ControlHook) -> Result<(), String> {
+    let mut db_conn = establish_connection();
+    check_user_not_banned(&mut db_conn, &hook.guild_id, &hook.created_by)
+        .map_err(|_| "its creator is banned from music".to_string())?;
+    let settings = GuildSettings::find_by_guild_id(&mut db_conn, &hook.guild_id)
+        .ok()
+        .flatten();
+    check_user_command_roles(
+        settings.as_ref(),
+        "play",
+        &hook.guild_id,
+        &hook.created_by,
+        false,
+    )
+    .await
+    .map_err(|_| "its creator isn't allowed to use /play".to_string())?;
+    if settings.as_ref().is_some_and(|s| s.require_approval)
+        && !user_is_dj(settings.as_ref(), &hook.guild_id, &hook.created_by, false).await
+    {
+        return Err("requests need a DJ's approval and its creator isn't a DJ".to_string());
+    }
+    Ok(())
+}
+
+/// Queue the hook's track or playlist, joining its voice channel if needed. Tracks are
+/// queued in the background; returns how many there are.
+async fn run_hook(hook: &ControlHook) -> ApiResult<usize> {
+    // Before joining voice, as for the queue API, so a refused hook doesn't leave the bot idling
+    let settings = GuildSettings::find_by_guild_id(&mut establish_connection(), &hook.guild_id)
+        .ok()
+        .flatten();
+    check_not_draining().and_then(|()| check_quiet_hours(settings.as_ref()))?;
+
+    let urls = match (&hook.url, &hook.playlist) {
+        (Some(url), _) => vec![url.clone()],
+        (None, Some(name)) => {
+            let mut db_conn = establish_connection();
+            SavedPlaylist::find_by_name(&mut db_conn, &hook.guild_id, name)
+                .and_then(|playlist| match playlist {
+                    Some(playlist) => playlist.tracks(&mut db_conn).map(Some),
+                    None => Ok(None),
+                })
+                .map_err(|e| ApiError::Internal(format!("Failed to load playlist: {}", e)))?
+                .ok_or_else(|| {
+                    ApiError::NotFound(format!("Saved playlist {} no longer exists", name))
+                })?
+                .into_iter()
+                .map(|track| track.url)
+                .collect()
+        }
+        (None, None) => Vec::new(),
+    };
+
+    if crate::simulate::enabled() {
+        for url in &urls {
+            simulate_add(
+                &hook.guild_id,
+                &hook.created_by,
+                url,
+                Some(&hook.voice_channel_id),
+            )
+            .await?;
+        }
+        return Ok(urls.len());
+    }
+
+    let ctx = broadcast::context()
+        .ok_or_else(|| ApiError::Upstream("Not connected to Discord".to_string()))?;
+    let parse = |raw: &str| {
+        raw.parse::<u64>()
+            .map_err(|_| ApiError::Internal(format!("Hook has a malformed ID: {}", raw)))
+    };
+    let guild_id = GuildId::new(parse(&hook.guild_id)?);
+    let channel_id = ChannelId::new(parse(&hook.voice_channel_id)?);
+    let user_id = UserId::new(parse(&hook.created_by)?);
+
+    let manager = songbird::get(&ctx)
+        .await
+        .ok_or_else(|| ApiError::Internal("Voice manager unavailable".to_string()))?;
+    if manager.get(guild_id).is_none() {
+        if !capacity::try_admit(guild_id, manager.iter().count()) {
+            return Err(ApiError::Upstream(
+                "The bot is at capacity right now".to_string(),
+            ));
+        }
+        voice_manager::join_voice_channel(&ctx, guild_id, channel_id)
+            .await
+            .map_err(|e| ApiError::Upstream(format!("Couldn't join voice: {}", e)))?;
+    }
+
+    let count = urls.len();
+    crate::metadata_cache::prefetch(guild_id.to_string(), urls.clone());
+    tokio::spawn(async move {
+        for url in &urls {
+            // Progress messages go to the voice channel's chat
+            if let Err(e) =
+                crate::commands::play::enqueue_quietly(&ctx, guild_id, channel_id, user_id, url, 0)
+                    .await
+            {
+                tracing::info!(
+                    "Control hook couldn't queue {} in guild {}: {}",
+                    url,
+                    guild_id,
+                    e
+                );
+            }
+        }
+    });
+    Ok(count)
+}
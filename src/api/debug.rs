@@ -0,0 +1,341 @@
+use actix_web::{HttpRequest, HttpResponse, get};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::error::{ApiError, ApiResult};
+use super::extract::ValidQuery;
+use super::guard::require_admin;
+use crate::auth::AuthenticatedUser;
+use crate::metrics::CLOCK_TICKS_PER_SEC;
+use crate::validation::{Validate, ValidationError, validate_range};
+
+/// Set to `1` to enable the `/api/admin/debug/*` endpoints; they 404 otherwise
+const DEBUG_ENDPOINTS_ENV: &str = "LYRE_DEBUG_ENDPOINTS";
+/// How often the stall probe asks the runtime to wake it
+const PROBE_INTERVAL: Duration = Duration::from_millis(10);
+/// Call stacks sampled per second for CPU profiles
+#[cfg(unix)]
+const CPU_SAMPLE_HZ: i32 = 99;
+/// How long a task dump may wait for every task to yield
+#[cfg(feature = "taskdump")]
+const TASK_DUMP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+pub struct ProfileQuery {
+    pub seconds: Option<u64>,
+}
+
+impl Validate for ProfileQuery {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(seconds) = self.seconds {
+            validate_range("seconds", seconds, 1, 30)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct RuntimeProfile {
+    pub captured_at: String,
+    pub window_secs: f64,
+    /// OS threads by CPU used during the window, busiest first (Linux only)
+    pub threads: Vec<ThreadSample>,
+    pub tokio: TokioSample,
+    /// How late the runtime woke a task that asked to sleep `PROBE_INTERVAL`; large values mean
+    /// something blocked a worker thread (e.g. a synchronous database call)
+    pub scheduler_lag: LagSample,
+}
+
+#[derive(Serialize)]
+pub struct ThreadSample {
+    pub tid: u32,
+    pub name: String,
+    pub state: String,
+    pub cpu_secs: f64,
+    /// Share of one core the thread used during the window
+    pub cpu_ratio: f64,
+}
+
+#[derive(Serialize)]
+pub struct TokioSample {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+    /// Share of the window each worker spent polling tasks rather than parked
+    pub worker_busy_ratio: Vec<f64>,
+}
+
+#[derive(Serialize)]
+pub struct LagSample {
+    pub samples: u64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Wake-ups more than 50ms late
+    pub stalls: u64,
+}
+
+/// Sample the process for `seconds` (default 5) and return what its threads and the async
+/// runtime were doing as a downloadable JSON artifact, for diagnosing audio stutter and
+/// event-loop stalls in production.
+#[get("/api/admin/debug/profile")]
+pub async fn capture_profile(
+    req: HttpRequest,
+    query: ValidQuery<ProfileQuery>,
+) -> ApiResult<HttpResponse> {
+    let user = require_debug_access(&req)?;
+    let window = Duration::from_secs(query.seconds.unwrap_or(5));
+    tracing::info!(
+        "User {} started a {}s runtime profile",
+        user.user.id,
+        window.as_secs()
+    );
+
+    let runtime = tokio::runtime::Handle::current().metrics();
+    let workers = runtime.num_workers();
+    let busy_before: Vec<Duration> = (0..workers)
+        .map(|w| runtime.worker_total_busy_duration(w))
+        .collect();
+    let threads_before = read_thread_times().await;
+    let started = Instant::now();
+
+    let scheduler_lag = probe_scheduler_lag(window).await;
+
+    let elapsed = started.elapsed().as_secs_f64();
+    let threads_after = read_thread_times().await;
+    let worker_busy_ratio = (0..workers)
+        .map(|w| {
+            let busy = runtime
+                .worker_total_busy_duration(w)
+                .saturating_sub(busy_before[w]);
+            busy.as_secs_f64() / elapsed
+        })
+        .collect();
+
+    let mut threads: Vec<ThreadSample> = threads_after
+        .into_iter()
+        .map(|(tid, (name, state, cpu_after))| {
+            let cpu_before = threads_before
+                .get(&tid)
+                .map(|(_, _, cpu)| *cpu)
+                .unwrap_or(0.0);
+            let cpu_secs = (cpu_after - cpu_before).max(0.0);
+            ThreadSample {
+                tid,
+                name,
+                state,
+                cpu_secs,
+                cpu_ratio: cpu_secs / elapsed,
+            }
+        })
+        .collect();
+    threads.sort_by(|a, b| b.cpu_secs.total_cmp(&a.cpu_secs));
+
+    let now = chrono::Utc::now();
+    let profile = RuntimeProfile {
+        captured_at: now.to_rfc3339(),
+        window_secs: elapsed,
+        threads,
+        tokio: TokioSample {
+            workers,
+            alive_tasks: runtime.num_alive_tasks(),
+            global_queue_depth: runtime.global_queue_depth(),
+            worker_busy_ratio,
+        },
+        scheduler_lag,
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"lyre-profile-{}.json\"",
+                now.timestamp()
+            ),
+        ))
+        .json(profile))
+}
+
+/// Sample call stacks for `seconds` (default 5) and return them as a flamegraph SVG, to see
+/// which code the CPU time went to. Unix only.
+#[get("/api/admin/debug/cpu")]
+pub async fn capture_cpu_profile(
+    req: HttpRequest,
+    query: ValidQuery<ProfileQuery>,
+) -> ApiResult<HttpResponse> {
+    let user = require_debug_access(&req)?;
+    let window = Duration::from_secs(query.seconds.unwrap_or(5));
+    tracing::info!(
+        "User {} started a {}s CPU profile",
+        user.user.id,
+        window.as_secs()
+    );
+    let svg = cpu_flamegraph(window).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("image/svg+xml")
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"lyre-cpu-{}.svg\"",
+                chrono::Utc::now().timestamp()
+            ),
+        ))
+        .body(svg))
+}
+
+/// Dump the stack of every async task, to see what each one is waiting on during a stall.
+/// Needs a Linux build with the `taskdump` feature and `--cfg tokio_unstable`.
+#[get("/api/admin/debug/tasks")]
+pub async fn dump_tasks(req: HttpRequest) -> ApiResult<HttpResponse> {
+    let user = require_debug_access(&req)?;
+    tracing::info!("User {} requested an async task dump", user.user.id);
+    let dump = task_dump().await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"lyre-tasks-{}.txt\"",
+                chrono::Utc::now().timestamp()
+            ),
+        ))
+        .body(dump))
+}
+
+/// The debug endpoints are only served to operators, and only when switched on
+fn require_debug_access(req: &HttpRequest) -> ApiResult<AuthenticatedUser> {
+    if crate::config::var(DEBUG_ENDPOINTS_ENV).as_deref() != Ok("1") {
+        return Err(ApiError::NotFound("Not found".to_string()));
+    }
+    require_admin(req)
+}
+
+#[cfg(unix)]
+async fn cpu_flamegraph(window: Duration) -> ApiResult<Vec<u8>> {
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(CPU_SAMPLE_HZ)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .map_err(|e| match e {
+            pprof::Error::Running => {
+                ApiError::Conflict("A CPU profile is already being captured".to_string())
+            }
+            e => ApiError::Internal(format!("Failed to start the profiler: {}", e)),
+        })?;
+    tokio::time::sleep(window).await;
+    let report = guard
+        .report()
+        .build()
+        .map_err(|e| ApiError::Internal(format!("Failed to build the profile: {}", e)))?;
+    if report.data.is_empty() {
+        return Err(ApiError::NotFound(
+            "Nothing used the CPU during the window".to_string(),
+        ));
+    }
+    let mut svg = Vec::new();
+    report
+        .flamegraph(&mut svg)
+        .map_err(|e| ApiError::Internal(format!("Failed to draw the flamegraph: {}", e)))?;
+    Ok(svg)
+}
+
+#[cfg(not(unix))]
+async fn cpu_flamegraph(_window: Duration) -> ApiResult<Vec<u8>> {
+    Err(ApiError::NotFound(
+        "CPU profiles are only available on Unix".to_string(),
+    ))
+}
+
+#[cfg(feature = "taskdump")]
+async fn task_dump() -> ApiResult<String> {
+    use std::fmt::Write;
+
+    let dump = tokio::time::timeout(TASK_DUMP_TIMEOUT, tokio::runtime::Handle::current().dump())
+        .await
+        .map_err(|_| ApiError::Internal("Timed out dumping tasks".to_string()))?;
+    let mut out = String::new();
+    for (i, task) in dump.tasks().iter().enumerate() {
+        let _ = writeln!(out, "task {}:\n{}\n", i, task.trace());
+    }
+    Ok(out)
+}
+
+#[cfg(not(feature = "taskdump"))]
+async fn task_dump() -> ApiResult<String> {
+    Err(ApiError::NotFound(
+        "Task dumps need a build with the taskdump feature".to_string(),
+    ))
+}
+
+/// Repeatedly sleep `PROBE_INTERVAL` for `window` and record how late each wake-up was
+async fn probe_scheduler_lag(window: Duration) -> LagSample {
+    let deadline = Instant::now() + window;
+    let (mut samples, mut total_ms, mut max_ms, mut stalls) = (0u64, 0.0f64, 0.0f64, 0u64);
+    while Instant::now() < deadline {
+        let asked_at = Instant::now();
+        tokio::time::sleep(PROBE_INTERVAL).await;
+        let late_ms = asked_at
+            .elapsed()
+            .saturating_sub(PROBE_INTERVAL)
+            .as_secs_f64()
+            * 1000.0;
+        samples += 1;
+        total_ms += late_ms;
+        max_ms = max_ms.max(late_ms);
+        if late_ms > 50.0 {
+            stalls += 1;
+        }
+    }
+    LagSample {
+        samples,
+        mean_ms: if samples > 0 {
+            total_ms / samples as f64
+        } else {
+            0.0
+        },
+        max_ms,
+        stalls,
+    }
+}
+
+/// Name, scheduler state and CPU seconds of every thread in the process, keyed by thread ID
+async fn read_thread_times() -> HashMap<u32, (String, String, f64)> {
+    let mut threads = HashMap::new();
+    let Ok(mut tasks) = tokio::fs::read_dir("/proc/self/task").await else {
+        return threads;
+    };
+    while let Ok(Some(task)) = tasks.next_entry().await {
+        let Some(tid) = task
+            .file_name()
+            .to_str()
+            .and_then(|t| t.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(stat) = tokio::fs::read_to_string(task.path().join("stat")).await else {
+            continue;
+        };
+        // stat is "tid (name) state ... utime stime ..."; the name may contain spaces
+        let (Some(open), Some(close)) = (stat.find('('), stat.rfind(')')) else {
+            continue;
+        };
+        let name = stat[open + 1..close].to_string();
+        let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+        let (Some(state), Some(utime), Some(stime)) = (
+            fields.first(),
+            fields.get(11).and_then(|v| v.parse::<u64>().ok()),
+            fields.get(12).and_then(|v| v.parse::<u64>().ok()),
+        ) else {
+            continue;
+        };
+        threads.insert(
+            tid,
+            (
+                name,
+                state.to_string(),
+                (utime + stime) as f64 / CLOCK_TICKS_PER_SEC,
+            ),
+        );
+    }
+    threads
+}
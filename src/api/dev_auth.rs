@@ -1,12 +1,13 @@
+use super::error::{ApiError, ApiResult};
 use super::types::ApiResponse;
-use actix_web::{HttpResponse, Result as ActixResult, get};
+use actix_web::{HttpResponse, get};
 
 /// Development-only endpoint to generate a test token
 /// WARNING: This should only be used in development!
 #[get("/api/dev/test-token")]
-pub async fn get_test_token() -> ActixResult<HttpResponse> {
-    // Only allow in development
-    if cfg!(debug_assertions) {
+pub async fn get_test_token() -> ApiResult<HttpResponse> {
+    // Only allow in development or simulation mode
+    if cfg!(debug_assertions) || crate::simulate::enabled() {
         // Generate a simple test token that the demo auth will accept
         let test_token = format!("demo_{}", chrono::Utc::now().timestamp());
 
@@ -18,6 +19,8 @@ pub async fn get_test_token() -> ActixResult<HttpResponse> {
             }))),
         )
     } else {
-        Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error("Not available in production")))
+        Err(ApiError::NotFound(
+            "Not available in production".to_string(),
+        ))
     }
 }
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use super::error::{ApiError, ApiResult};
+use super::extract::GuildPath;
+use super::guard::{require_admin, require_guild_access};
+use super::types::ApiResponse;
+use crate::downloads::{self, DownloadEvent};
+use actix_web::{HttpRequest, HttpResponse, delete, get, web::Bytes};
+use futures_util::stream;
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+
+/// Sent when nothing else has been, so proxies don't close an idle stream
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Every download in flight across all guilds, running or waiting for a slot, oldest first
+#[get("/api/downloads")]
+pub async fn list_downloads(req: HttpRequest) -> ApiResult<HttpResponse> {
+    require_admin(&req)?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(downloads::list())))
+}
+
+/// Cancel a download, killing its yt-dlp; the track it was for fails as a broken link would
+#[delete("/api/downloads/{download_id}")]
+pub async fn cancel_download(req: HttpRequest) -> ApiResult<HttpResponse> {
+    let user = require_admin(&req)?;
+    let download_id: u64 = req
+        .match_info()
+        .query("download_id")
+        .parse()
+        .map_err(|_| ApiError::invalid_input("download_id must be a number"))?;
+
+    let job = downloads::cancel(download_id)
+        .ok_or_else(|| ApiError::NotFound("Download not found".to_string()))?;
+    tracing::info!(
+        "User {} cancelled download {} of {} in guild {}",
+        user.user.id,
+        job.id,
+        job.url,
+        job.guild_id
+    );
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(job)))
+}
+
+/// Server-sent events with the progress of each download for the guild's queue: a `progress`
+/// event per update, carrying the URL, phase (`downloading`, `converting`, `ready`) and percent
+#[get("/api/downloads/{guild_id}/events")]
+pub async fn download_events(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+
+    require_guild_access(&req, &guild_id)?;
+
+    let events = stream::unfold(
+        (downloads::subscribe(), guild_id),
+        |(mut rx, guild_id)| async move {
+            let chunk = next_chunk(&mut rx, &guild_id).await?;
+            Some((Ok::<_, actix_web::Error>(chunk), (rx, guild_id)))
+        },
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events))
+}
+
+/// The next event for `guild_id` in SSE framing, or a keepalive comment if none arrives in
+/// time; `None` once the channel closes
+async fn next_chunk(rx: &mut Receiver<DownloadEvent>, guild_id: &str) -> Option<Bytes> {
+    loop {
+        match tokio::time::timeout(KEEPALIVE_INTERVAL, rx.recv()).await {
+            Err(_) => return Some(Bytes::from_static(b": keepalive\n\n")),
+            Ok(Ok(event)) if event.guild_id == guild_id => {
+                let data = serde_json::to_string(&event).ok()?;
+                return Some(Bytes::from(format!("event: progress\ndata: {}\n\n", data)));
+            }
+            // Missed events are superseded by the next one anyway
+            Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) => continue,
+            Ok(Err(RecvError::Closed)) => return None,
+        }
+    }
+}
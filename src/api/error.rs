@@ -0,0 +1,162 @@
+use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use serde::Serialize;
+use thiserror::Error;
+
+use super::types::ApiResponse;
+use crate::auth::RateLimited;
+use crate::policy::PolicyError;
+use crate::validation::ValidationError;
+
+/// Every error the HTTP API can return.
+///
+/// Each variant maps to a fixed HTTP status and a stable, machine-readable `code` so clients can
+/// branch on the failure without parsing the human-readable message.
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    InvalidToken(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{message}")]
+    InvalidInput {
+        message: String,
+        details: Option<serde_json::Value>,
+    },
+    #[error(transparent)]
+    Policy(#[from] PolicyError),
+    #[error("{0}")]
+    NotFound(String),
+    /// The request clashes with one still being handled
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    OAuth(String),
+    #[error("{0}")]
+    Upstream(String),
+    /// Discord is rate limiting us; the request can be retried after `retry_after_secs`
+    #[error("{message}")]
+    RateLimited {
+        message: String,
+        retry_after_secs: u64,
+    },
+    /// The caller is over its own request limit and may retry after `retry_after_secs`
+    #[error("{message}")]
+    TooManyRequests {
+        message: String,
+        retry_after_secs: u64,
+    },
+    #[error("{0}")]
+    Internal(String),
+}
+
+/// Structured error body returned in the `error` field of `ApiResponse`
+#[derive(Debug, Serialize)]
+pub struct ApiErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::InvalidInput {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn no_guild_permission() -> Self {
+        Self::Forbidden("No permission for this guild".to_string())
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Unauthorized(_) => "unauthorized",
+            Self::InvalidToken(_) => "invalid_token",
+            Self::Forbidden(_) => "forbidden",
+            Self::InvalidInput { .. } => "invalid_input",
+            Self::Policy(e) => e.code(),
+            Self::NotFound(_) => "not_found",
+            Self::Conflict(_) => "conflict",
+            Self::OAuth(_) => "oauth_failed",
+            Self::Upstream(_) => "upstream_error",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::TooManyRequests { .. } => "too_many_requests",
+            Self::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn body(&self) -> ApiErrorBody {
+        let details = match self {
+            Self::InvalidInput { details, .. } => details.clone(),
+            Self::Policy(e) => Some(e.details()),
+            _ => None,
+        };
+        ApiErrorBody {
+            code: self.code(),
+            message: self.to_string(),
+            details,
+        }
+    }
+}
+
+impl From<ValidationError> for ApiError {
+    fn from(err: ValidationError) -> Self {
+        let details = match &err {
+            ValidationError::OutOfRange { field, min, max } => {
+                Some(serde_json::json!({ "field": field, "min": min, "max": max }))
+            }
+            _ => err
+                .field()
+                .map(|field| serde_json::json!({ "field": field })),
+        };
+        Self::InvalidInput {
+            message: err.to_string(),
+            details,
+        }
+    }
+}
+
+impl From<&RateLimited> for ApiError {
+    fn from(err: &RateLimited) -> Self {
+        Self::RateLimited {
+            message: err.to_string(),
+            retry_after_secs: err.retry_after.as_secs().max(1),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized(_) | Self::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) | Self::Policy(_) => StatusCode::FORBIDDEN,
+            Self::InvalidInput { .. } | Self::OAuth(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Upstream(_) => StatusCode::BAD_GATEWAY,
+            Self::RateLimited { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Self::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let Self::RateLimited {
+            retry_after_secs, ..
+        }
+        | Self::TooManyRequests {
+            retry_after_secs, ..
+        } = self
+        {
+            response.insert_header(("Retry-After", retry_after_secs.to_string()));
+        }
+        response.json(ApiResponse::<()>::failure(self.body()))
+    }
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
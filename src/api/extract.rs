@@ -0,0 +1,99 @@
+use actix_web::{FromRequest, HttpRequest, dev::Payload, web};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use std::future::{Ready, ready};
+
+use super::error::ApiError;
+use crate::validation::{Validate, validate_snowflake};
+
+/// The `{guild_id}` path segment, checked to be a Discord snowflake
+#[derive(Debug, Clone)]
+pub struct GuildPath(pub String);
+
+impl GuildPath {
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl FromRequest for GuildPath {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let result = match req.match_info().get("guild_id") {
+            Some(raw) => validate_snowflake("guild_id", raw)
+                .map(|_| GuildPath(raw.to_string()))
+                .map_err(|e| ApiError::from(e).into()),
+            None => Err(ApiError::invalid_input("Missing guild_id path parameter").into()),
+        };
+        ready(result)
+    }
+}
+
+/// JSON body that has been deserialized and passed `Validate::validate`
+#[derive(Debug)]
+pub struct ValidJson<T>(pub T);
+
+impl<T> ValidJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for ValidJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> FromRequest for ValidJson<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            value.validate().map_err(ApiError::from)?;
+            Ok(ValidJson(value))
+        })
+    }
+}
+
+/// Query string that has been deserialized and passed `Validate::validate`
+#[derive(Debug)]
+pub struct ValidQuery<T>(pub T);
+
+impl<T> std::ops::Deref for ValidQuery<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> FromRequest for ValidQuery<T>
+where
+    T: DeserializeOwned + Validate,
+{
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let result = web::Query::<T>::from_query(req.query_string())
+            .map_err(|e| ApiError::invalid_input(format!("Invalid query string: {}", e)))
+            .and_then(|query| {
+                let value = query.into_inner();
+                value.validate()?;
+                Ok(ValidQuery(value))
+            })
+            .map_err(Into::into);
+        ready(result)
+    }
+}
@@ -0,0 +1,141 @@
+//! Named filter sets a guild saved with `/filter save`, for the dashboard
+
+use actix_web::{HttpRequest, HttpResponse, delete, get, post, put};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use super::error::{ApiError, ApiResult};
+use super::extract::{GuildPath, ValidJson};
+use super::guard::{require_command_access, require_guild_access};
+use super::types::ApiResponse;
+use crate::database::establish_connection;
+use crate::database::models::FilterPreset;
+use crate::filters::{self, Filter};
+use crate::validation::{Validate, ValidationError};
+
+const MAX_NAME_LEN: usize = 50;
+
+#[derive(Serialize)]
+pub struct PresetInfo {
+    pub name: String,
+    pub filters: Vec<String>,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<FilterPreset> for PresetInfo {
+    fn from(preset: FilterPreset) -> Self {
+        PresetInfo {
+            filters: preset.filters_list(),
+            name: preset.name,
+            created_by: preset.created_by,
+            created_at: preset.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SavePresetRequest {
+    pub filters: Vec<String>,
+}
+
+impl Validate for SavePresetRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if self.filters.is_empty() || self.filters.iter().any(|n| Filter::from_key(n).is_none()) {
+            return Err(ValidationError::InvalidFormat {
+                field: "filters",
+                expected: "a list of filter names (karaoke, 8d, loudnorm, bassboost:<low|medium|high|1-20>)",
+            });
+        }
+        Ok(())
+    }
+}
+
+fn preset_name(req: &HttpRequest) -> ApiResult<String> {
+    let name = req.match_info().query("name").trim().to_string();
+    let len = name.chars().count();
+    if len == 0 || len > MAX_NAME_LEN {
+        return Err(ApiError::invalid_input(format!(
+            "Preset names are 1 to {} characters",
+            MAX_NAME_LEN
+        )));
+    }
+    Ok(name)
+}
+
+#[get("/api/filter-presets/{guild_id}")]
+pub async fn list_filter_presets(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_guild_access(&req, &guild_id)?;
+
+    let presets = FilterPreset::list_for_guild(&mut establish_connection(), &guild_id)
+        .map_err(|e| ApiError::Internal(format!("Failed to list presets: {}", e)))?;
+    let presets: Vec<PresetInfo> = presets.into_iter().map(PresetInfo::from).collect();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(presets)))
+}
+
+/// Save a preset, replacing an existing one of the same name
+#[put("/api/filter-presets/{guild_id}/{name}")]
+pub async fn save_filter_preset(
+    path: GuildPath,
+    body: ValidJson<SavePresetRequest>,
+    req: HttpRequest,
+) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    let user = require_command_access(&req, &guild_id, "filter").await?;
+    let name = preset_name(&req)?;
+
+    // Stored in the order they're applied, without duplicates
+    let names: Vec<String> = filters::parse_list(&body.filters)
+        .into_iter()
+        .map(Filter::key)
+        .collect();
+    let preset = FilterPreset::save(
+        &mut establish_connection(),
+        &guild_id,
+        &name,
+        &names,
+        &user.user.id,
+    )
+    .map_err(|e| ApiError::Internal(format!("Failed to save preset: {}", e)))?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(PresetInfo::from(preset))))
+}
+
+#[delete("/api/filter-presets/{guild_id}/{name}")]
+pub async fn delete_filter_preset(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_command_access(&req, &guild_id, "filter").await?;
+    let name = preset_name(&req)?;
+
+    let deleted = FilterPreset::delete(&mut establish_connection(), &guild_id, &name)
+        .map_err(|e| ApiError::Internal(format!("Failed to delete preset: {}", e)))?;
+    if !deleted {
+        return Err(ApiError::NotFound(format!("No preset named {}", name)));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse::success("Preset deleted")))
+}
+
+/// Switch the guild's filters to the preset's, like `/filter preset`
+#[post("/api/filter-presets/{guild_id}/{name}/activate")]
+pub async fn activate_filter_preset(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    let user = require_command_access(&req, &guild_id, "filter").await?;
+    let name = preset_name(&req)?;
+    if !filters::available(&guild_id) {
+        return Err(ApiError::Forbidden(
+            "Audio filters are off in this server".to_string(),
+        ));
+    }
+
+    let enabled = filters::activate_preset(&guild_id, &name)
+        .map_err(|e| ApiError::Internal(format!("Failed to switch filters: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("No preset named {}", name)))?;
+    tracing::info!(
+        "User {} switched guild {} to filter preset {} via the API",
+        user.user.id,
+        guild_id,
+        name
+    );
+    let names: Vec<String> = enabled.into_iter().map(Filter::key).collect();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(names)))
+}
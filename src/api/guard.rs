@@ -0,0 +1,78 @@
+use actix_web::HttpRequest;
+
+use super::error::{ApiError, ApiResult};
+use crate::auth::{
+    AuthenticatedUser, get_authenticated_user_from_extensions, user_can_control_guild,
+    user_can_manage_guild,
+};
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+use crate::policy::{check_user_command_roles, check_user_not_banned, is_bot_operator};
+
+/// Fetch the user the auth middleware attached to this request
+pub fn require_user(req: &HttpRequest) -> ApiResult<AuthenticatedUser> {
+    get_authenticated_user_from_extensions(req)
+        .map_err(|e| ApiError::Unauthorized(format!("Authentication failed: {}", e)))
+}
+
+/// Fetch the authenticated user and ensure they're one of the operators in `LYRE_ADMIN_USER_IDS`
+pub fn require_admin(req: &HttpRequest) -> ApiResult<AuthenticatedUser> {
+    let user = require_user(req)?;
+    if !is_bot_operator(&user.user.id) {
+        return Err(ApiError::Forbidden(
+            "This endpoint is limited to bot operators".to_string(),
+        ));
+    }
+    Ok(user)
+}
+
+/// Fetch the authenticated user and ensure they may control the bot in `guild_id`
+pub fn require_guild_access(req: &HttpRequest, guild_id: &str) -> ApiResult<AuthenticatedUser> {
+    let user = require_user(req)?;
+    if !user_can_control_guild(&user.guilds, guild_id) {
+        return Err(ApiError::no_guild_permission());
+    }
+    Ok(user)
+}
+
+/// Fetch the authenticated user and ensure they own, administer or manage `guild_id`, as
+/// changing the guild's settings needs
+pub fn require_guild_manager(req: &HttpRequest, guild_id: &str) -> ApiResult<AuthenticatedUser> {
+    let user = require_user(req)?;
+    if !user_can_manage_guild(&user.guilds, guild_id) {
+        return Err(ApiError::Forbidden(
+            "Only the server's managers can change its settings".to_string(),
+        ));
+    }
+    Ok(user)
+}
+
+/// Like `require_guild_access`, but also refuses members banned from playback with `/musicban`
+pub fn require_playback_access(req: &HttpRequest, guild_id: &str) -> ApiResult<AuthenticatedUser> {
+    let user = require_guild_access(req, guild_id)?;
+    let mut conn = establish_connection();
+    check_user_not_banned(&mut conn, guild_id, &user.user.id)?;
+    Ok(user)
+}
+
+/// Like `require_playback_access`, but also refuses members without one of the roles the guild
+/// locked the slash command `command` to, so `command_roles` holds for the API as well
+pub async fn require_command_access(
+    req: &HttpRequest,
+    guild_id: &str,
+    command: &str,
+) -> ApiResult<AuthenticatedUser> {
+    let user = require_playback_access(req, guild_id)?;
+    let settings = GuildSettings::find_by_guild_id(&mut establish_connection(), guild_id)
+        .ok()
+        .flatten();
+    check_user_command_roles(
+        settings.as_ref(),
+        command,
+        guild_id,
+        &user.user.id,
+        user_can_manage_guild(&user.guilds, guild_id),
+    )
+    .await?;
+    Ok(user)
+}
@@ -1,46 +1,177 @@
+use super::error::{ApiError, ApiResult};
+use super::extract::GuildPath;
+use super::guard::{require_guild_access, require_user};
 use super::types::{ApiResponse, GuildInfo};
-use crate::auth::{AuthenticatedUser, get_authenticated_user_from_extensions};
+use crate::auth::{AuthenticatedUser, UserGuild};
+use crate::broadcast;
 use crate::database::establish_connection;
-use crate::database::models::VoiceConnection;
-use actix_web::{HttpRequest, HttpResponse, Result as ActixResult, get};
+use crate::database::models::{CurrentQueue, VoiceConnection};
+use crate::voice_permissions::VOICE_PERMISSIONS;
+use actix_web::{HttpRequest, HttpResponse, get};
+use serde::Serialize;
+use serenity::all::{Channel, ChannelId, ChannelType, GuildId, Permissions};
+use std::collections::BTreeMap;
 
 #[get("/api/guilds")]
-pub async fn get_guilds(req: HttpRequest, _user: AuthenticatedUser) -> ActixResult<HttpResponse> {
+pub async fn get_guilds(req: HttpRequest, _user: AuthenticatedUser) -> ApiResult<HttpResponse> {
     // Get authenticated user from middleware
-    let user = match get_authenticated_user_from_extensions(&req) {
-        Ok(user) => user,
+    let user = require_user(&req)?;
+
+    let mut guild_infos = Vec::with_capacity(user.guilds.len());
+    for guild in &user.guilds {
+        guild_infos.push(guild_info(guild).await);
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(guild_infos)))
+}
+
+/// Where the bot is in `guild` and what it's playing there
+pub(super) async fn guild_info(guild: &UserGuild) -> GuildInfo {
+    let voice_channel_id = connected_channel(&guild.id).await;
+    let (queue_length, current_track) = {
+        let mut conn = establish_connection();
+        let queue = CurrentQueue::get_guild_queue(&mut conn, &guild.id).unwrap_or_default();
+        let current_track = queue
+            .iter()
+            .find(|entry| entry.position == 0)
+            .map(|entry| entry.title.clone().unwrap_or_else(|| entry.url.clone()));
+        let pending = queue.iter().filter(|entry| entry.position > 0).count();
+        (pending, current_track)
+    };
+    let voice_channel = match &voice_channel_id {
+        Some(id) => Some(channel_name(id).await.unwrap_or_else(|| id.clone())),
+        None => None,
+    };
+
+    GuildInfo {
+        id: guild.id.clone(),
+        name: guild.name.clone(),
+        connected: voice_channel_id.is_some(),
+        voice_channel,
+        voice_channel_id,
+        queue_length,
+        current_track,
+    }
+}
+
+/// The voice channel the bot is in for `guild_id`: Songbird's call when connected to Discord,
+/// otherwise the connection record (as in simulation mode)
+async fn connected_channel(guild_id: &str) -> Option<String> {
+    if let Some(manager) = broadcast::voice_manager() {
+        let call_lock = manager.get(GuildId::new(guild_id.parse().ok()?))?;
+        let channel = call_lock.lock().await.current_channel();
+        return channel.map(|c| c.0.to_string());
+    }
+    let mut conn = establish_connection();
+    VoiceConnection::find_by_guild_id(&mut conn, guild_id)
+        .ok()
+        .flatten()
+        .and_then(|c| c.channel_id)
+}
+
+async fn channel_name(channel_id: &str) -> Option<String> {
+    let http = broadcast::http()?;
+    match ChannelId::new(channel_id.parse().ok()?)
+        .to_channel(&http)
+        .await
+    {
+        Ok(Channel::Guild(channel)) => Some(channel.name),
+        Ok(_) => None,
         Err(e) => {
-            return Ok(
-                HttpResponse::Unauthorized().json(ApiResponse::<()>::error(&format!(
-                    "Authentication failed: {}",
-                    e
-                ))),
-            );
+            tracing::debug!("Failed to look up voice channel {}: {}", channel_id, e);
+            None
         }
-    };
+    }
+}
+
+/// What the bot needs in a channel it posts in
+const TEXT_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::SEND_MESSAGES)
+    .union(Permissions::EMBED_LINKS);
+
+#[derive(Serialize)]
+pub struct ChannelPermissions {
+    pub id: String,
+    pub name: String,
+    /// `voice` (including stage channels) or `text`
+    pub kind: &'static str,
+    /// Each permission the bot needs in this kind of channel, and whether it has it
+    pub permissions: BTreeMap<&'static str, bool>,
+    /// Whether the bot has all of them
+    pub ok: bool,
+}
 
-    // Convert user guilds to GuildInfo with connection status
-    let guild_infos: Vec<GuildInfo> = user
-        .guilds
-        .iter()
-        .map(|guild| {
-            // Check if the bot is connected to this guild's voice channel using the database
-            let mut conn = establish_connection();
-            let connected = VoiceConnection::is_connected(&mut conn, &guild.id);
-
-            GuildInfo {
-                id: guild.id.clone(),
-                name: guild.name.clone(),
-                connected,
-                voice_channel: if connected {
-                    Some("Connected".to_string())
-                } else {
-                    None
+#[derive(Serialize)]
+pub struct PermissionsCheck {
+    pub guild_id: String,
+    pub channels: Vec<ChannelPermissions>,
+}
+
+/// Which of the permissions the bot needs it has in each of the guild's voice and text channels,
+/// so a "bot won't join" can be diagnosed from the dashboard
+#[get("/api/guilds/{guild_id}/permissions-check")]
+pub async fn check_permissions(req: HttpRequest, path: GuildPath) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_guild_access(&req, &guild_id)?;
+    let ctx = broadcast::context()
+        .ok_or_else(|| ApiError::Conflict("The bot isn't connected to Discord".to_string()))?;
+    let id = GuildId::new(
+        guild_id
+            .parse()
+            .map_err(|_| ApiError::invalid_input("guild_id must be a snowflake"))?,
+    );
+
+    // The cached member can be stale (role changes need the members intent), so ask Discord
+    let bot_id = ctx.cache.current_user().id;
+    let member = ctx.http.get_member(id, bot_id).await.map_err(|e| {
+        tracing::debug!("Couldn't fetch own member in guild {}: {}", guild_id, e);
+        ApiError::NotFound("The bot isn't in this server".to_string())
+    })?;
+    let guild = ctx
+        .cache
+        .guild(id)
+        .ok_or_else(|| ApiError::NotFound("The bot isn't in this server".to_string()))?;
+
+    let mut channels: Vec<_> = guild
+        .channels
+        .values()
+        .filter_map(|channel| {
+            let (kind, needed) = match channel.kind {
+                ChannelType::Voice | ChannelType::Stage => ("voice", VOICE_PERMISSIONS),
+                ChannelType::Text | ChannelType::News => ("text", TEXT_PERMISSIONS),
+                _ => return None,
+            };
+            let granted = guild.user_permissions_in(channel, &member);
+            let permissions: BTreeMap<_, _> = [
+                ("view_channel", Permissions::VIEW_CHANNEL),
+                ("connect", Permissions::CONNECT),
+                ("speak", Permissions::SPEAK),
+                ("send_messages", Permissions::SEND_MESSAGES),
+                ("embed_links", Permissions::EMBED_LINKS),
+            ]
+            .into_iter()
+            .filter(|(_, permission)| needed.contains(*permission))
+            .map(|(name, permission)| (name, granted.contains(permission)))
+            .collect();
+            Some((
+                channel.position,
+                ChannelPermissions {
+                    id: channel.id.to_string(),
+                    name: channel.name.clone(),
+                    kind,
+                    ok: granted.contains(needed),
+                    permissions,
                 },
-                queue_length: 0, // TODO: Get actual queue length from Songbird
-            }
+            ))
         })
         .collect();
+    drop(guild);
+    channels.sort_by_key(|(position, channel)| (channel.kind, *position));
 
-    Ok(HttpResponse::Ok().json(ApiResponse::success(guild_infos)))
+    Ok(
+        HttpResponse::Ok().json(ApiResponse::success(PermissionsCheck {
+            guild_id,
+            channels: channels.into_iter().map(|(_, channel)| channel).collect(),
+        })),
+    )
 }
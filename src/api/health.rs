@@ -1,10 +1,43 @@
+use super::error::ApiResult;
+use super::guard::require_admin;
 use super::types::ProbeResp;
-use crate::metrics::{METRICS, MetricsSnapshot};
-use actix_web::{HttpResponse, Responder, get};
+use crate::metrics::{HTTP_LATENCY_BUCKETS, METRICS, MetricsSnapshot};
+use actix_web::http::header::{AUTHORIZATION, HeaderMap};
+use actix_web::{HttpRequest, HttpResponse, Responder, get};
+use std::fmt::Write;
+
+/// Bearer token a scraper can fetch `/k8s/metrics` with instead of an operator's login
+const METRICS_TOKEN_ENV: &str = "LYRE_METRICS_TOKEN";
+
+/// Whether the request carries the scrape token from `LYRE_METRICS_TOKEN`, if one is set
+pub fn bears_metrics_token(headers: &HeaderMap) -> bool {
+    let Ok(expected) = crate::config::var(METRICS_TOKEN_ENV) else {
+        return false;
+    };
+    let expected = expected.trim();
+    let given = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        // Every byte is compared, so timing doesn't give away how much of a guess was right
+        Some(given) if !expected.is_empty() && given.len() == expected.len() => {
+            given
+                .bytes()
+                .zip(expected.bytes())
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0
+        }
+        _ => false,
+    }
+}
 
 #[get("/k8s/readyz")]
 pub async fn readyz() -> impl Responder {
-    if METRICS.is_ready() {
+    if METRICS.is_draining() {
+        // Take this instance out of rotation while its sessions finish
+        HttpResponse::ServiceUnavailable().json(ProbeResp { status: "draining" })
+    } else if METRICS.is_ready() {
         HttpResponse::Ok().json(ProbeResp { status: "ok" })
     } else {
         HttpResponse::ServiceUnavailable().json(ProbeResp { status: "starting" })
@@ -16,10 +49,15 @@ pub async fn livez() -> impl Responder {
     HttpResponse::Ok().json(ProbeResp { status: "ok" })
 }
 
+/// Metrics carry guild IDs in their labels, so unlike the probes they're only for bot
+/// operators and the scraper holding `LYRE_METRICS_TOKEN`
 #[get("/k8s/metrics")]
-pub async fn health_metrics() -> impl Responder {
+pub async fn health_metrics(req: HttpRequest) -> ApiResult<HttpResponse> {
+    if !bears_metrics_token(req.headers()) {
+        require_admin(&req)?;
+    }
     let m: MetricsSnapshot = METRICS.snapshot();
-    let body = format!(
+    let mut body = format!(
         concat!(
             "# HELP lyre_uptime_seconds Seconds since process start\n",
             "# TYPE lyre_uptime_seconds counter\n",
@@ -27,10 +65,13 @@ pub async fn health_metrics() -> impl Responder {
             "# HELP lyre_ready 1 if ready, 0 otherwise\n",
             "# TYPE lyre_ready gauge\n",
             "lyre_ready {}\n",
-            "# HELP lyre_active_voice_calls Number of active voice calls\n",
+            "# HELP lyre_draining 1 if in maintenance mode (refusing new playback), 0 otherwise\n",
+            "# TYPE lyre_draining gauge\n",
+            "lyre_draining {}\n",
+            "# HELP lyre_active_voice_calls Number of connected voice calls\n",
             "# TYPE lyre_active_voice_calls gauge\n",
             "lyre_active_voice_calls {}\n",
-            "# HELP lyre_connected_guilds Number of connected guilds (approx)\n",
+            "# HELP lyre_connected_guilds Number of guilds with a connected voice call\n",
             "# TYPE lyre_connected_guilds gauge\n",
             "lyre_connected_guilds {}\n",
             "# HELP lyre_total_queue_len Total tracks enqueued across calls (approx)\n",
@@ -45,13 +86,209 @@ pub async fn health_metrics() -> impl Responder {
         ),
         m.uptime_secs,
         if m.ready { 1 } else { 0 },
+        if m.draining { 1 } else { 0 },
         m.active_voice_calls,
         m.connected_guilds,
         m.total_queue_len,
         m.downloads_bytes,
         m.downloads_files,
     );
-    HttpResponse::Ok()
+    render_download_metrics(&m, &mut body);
+    render_process_metrics(&m, &mut body);
+    render_http_metrics(&m, &mut body);
+    render_voice_metrics(&mut body);
+    Ok(HttpResponse::Ok()
         .content_type("text/plain; version=0.0.4")
-        .body(body)
+        .body(body))
+}
+
+fn render_download_metrics(m: &MetricsSnapshot, out: &mut String) {
+    out.push_str(
+        "# HELP lyre_downloads_source_bytes Size of each source folder of the download cache\n",
+    );
+    out.push_str("# TYPE lyre_downloads_source_bytes gauge\n");
+    for (source, usage) in &m.download_sources {
+        let _ = writeln!(
+            out,
+            "lyre_downloads_source_bytes{{source=\"{}\"}} {}",
+            escape_label(source),
+            usage.bytes
+        );
+    }
+    out.push_str(
+        "# HELP lyre_downloads_source_files Files in each source folder of the download cache\n",
+    );
+    out.push_str("# TYPE lyre_downloads_source_files gauge\n");
+    for (source, usage) in &m.download_sources {
+        let _ = writeln!(
+            out,
+            "lyre_downloads_source_files{{source=\"{}\"}} {}",
+            escape_label(source),
+            usage.files
+        );
+    }
+}
+
+fn render_process_metrics(m: &MetricsSnapshot, out: &mut String) {
+    let p = &m.process;
+    if let Some(rss) = p.rss_bytes {
+        out.push_str("# HELP process_resident_memory_bytes Resident memory size in bytes\n");
+        out.push_str("# TYPE process_resident_memory_bytes gauge\n");
+        let _ = writeln!(out, "process_resident_memory_bytes {}", rss);
+    }
+    if let Some(cpu) = p.cpu_seconds {
+        out.push_str(
+            "# HELP process_cpu_seconds_total Total user and system CPU time in seconds\n",
+        );
+        out.push_str("# TYPE process_cpu_seconds_total counter\n");
+        let _ = writeln!(out, "process_cpu_seconds_total {}", cpu);
+    }
+    if let Some(usage) = p.cpu_usage {
+        out.push_str(
+            "# HELP lyre_process_cpu_usage_ratio CPU used over the last sample, in cores\n",
+        );
+        out.push_str("# TYPE lyre_process_cpu_usage_ratio gauge\n");
+        let _ = writeln!(out, "lyre_process_cpu_usage_ratio {:.4}", usage);
+    }
+    if let Some(fds) = p.open_fds {
+        out.push_str("# HELP process_open_fds Number of open file descriptors\n");
+        out.push_str("# TYPE process_open_fds gauge\n");
+        let _ = writeln!(out, "process_open_fds {}", fds);
+    }
+    let _ = write!(
+        out,
+        concat!(
+            "# HELP lyre_tokio_workers Tokio runtime worker threads\n",
+            "# TYPE lyre_tokio_workers gauge\n",
+            "lyre_tokio_workers {}\n",
+            "# HELP lyre_tokio_alive_tasks Tokio tasks currently alive\n",
+            "# TYPE lyre_tokio_alive_tasks gauge\n",
+            "lyre_tokio_alive_tasks {}\n",
+            "# HELP lyre_tokio_global_queue_depth Tasks waiting in the Tokio global queue\n",
+            "# TYPE lyre_tokio_global_queue_depth gauge\n",
+            "lyre_tokio_global_queue_depth {}\n"
+        ),
+        p.tokio_workers, p.tokio_alive_tasks, p.tokio_global_queue_depth,
+    );
+    out.push_str(
+        "# HELP lyre_tokio_worker_busy_ratio Share of the last sample interval each worker spent polling\n",
+    );
+    out.push_str("# TYPE lyre_tokio_worker_busy_ratio gauge\n");
+    for (worker, ratio) in p.tokio_worker_busy_ratio.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "lyre_tokio_worker_busy_ratio{{worker=\"{}\"}} {:.4}",
+            worker, ratio
+        );
+    }
+    let _ = write!(
+        out,
+        concat!(
+            "# HELP lyre_tokio_blocked_workers Workers busy for the whole last sample interval (likely blocked)\n",
+            "# TYPE lyre_tokio_blocked_workers gauge\n",
+            "lyre_tokio_blocked_workers {}\n",
+            "# HELP lyre_scheduler_lag_max_seconds Worst timer wake-up lateness over the last sample interval\n",
+            "# TYPE lyre_scheduler_lag_max_seconds gauge\n",
+            "lyre_scheduler_lag_max_seconds {:.6}\n",
+            "# HELP lyre_scheduler_lag_mean_seconds Mean timer wake-up lateness over the last sample interval\n",
+            "# TYPE lyre_scheduler_lag_mean_seconds gauge\n",
+            "lyre_scheduler_lag_mean_seconds {:.6}\n"
+        ),
+        p.tokio_blocked_workers, p.scheduler_lag_max_secs, p.scheduler_lag_mean_secs,
+    );
+}
+
+fn render_http_metrics(m: &MetricsSnapshot, out: &mut String) {
+    out.push_str("# HELP lyre_http_requests_total HTTP requests by method, route and status\n");
+    out.push_str("# TYPE lyre_http_requests_total counter\n");
+    for ((method, route), stats) in &m.http_routes {
+        for (status, count) in &stats.statuses {
+            let _ = writeln!(
+                out,
+                "lyre_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                method,
+                escape_label(route),
+                status,
+                count
+            );
+        }
+    }
+
+    out.push_str("# HELP lyre_http_request_duration_seconds HTTP request latency by route\n");
+    out.push_str("# TYPE lyre_http_request_duration_seconds histogram\n");
+    for ((method, route), stats) in &m.http_routes {
+        let route = escape_label(route);
+        let mut cumulative = 0;
+        for (le, count) in HTTP_LATENCY_BUCKETS.iter().zip(stats.buckets.iter()) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "lyre_http_request_duration_seconds_bucket{{method=\"{}\",route=\"{}\",le=\"{}\"}} {}",
+                method, route, le, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "lyre_http_request_duration_seconds_bucket{{method=\"{}\",route=\"{}\",le=\"+Inf\"}} {}",
+            method, route, stats.count
+        );
+        let _ = writeln!(
+            out,
+            "lyre_http_request_duration_seconds_sum{{method=\"{}\",route=\"{}\"}} {}",
+            method, route, stats.sum_secs
+        );
+        let _ = writeln!(
+            out,
+            "lyre_http_request_duration_seconds_count{{method=\"{}\",route=\"{}\"}} {}",
+            method, route, stats.count
+        );
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn render_voice_metrics(out: &mut String) {
+    let calls = crate::voice_stats::all();
+    out.push_str(
+        "# HELP lyre_voice_packet_loss_ratio Share of the bot's packets lost since Discord's last report\n",
+    );
+    out.push_str("# TYPE lyre_voice_packet_loss_ratio gauge\n");
+    for (guild_id, stats) in &calls {
+        let _ = writeln!(
+            out,
+            "lyre_voice_packet_loss_ratio{{guild=\"{}\"}} {:.4}",
+            guild_id, stats.fraction_lost
+        );
+    }
+    out.push_str(
+        "# HELP lyre_voice_jitter_ms Interarrival jitter of the bot's stream reported by Discord\n",
+    );
+    out.push_str("# TYPE lyre_voice_jitter_ms gauge\n");
+    for (guild_id, stats) in &calls {
+        let _ = writeln!(
+            out,
+            "lyre_voice_jitter_ms{{guild=\"{}\"}} {:.2}",
+            guild_id, stats.jitter_ms
+        );
+    }
+    out.push_str("# HELP lyre_voice_late_ticks_total Voice ticks delivered late (host stalls)\n");
+    out.push_str("# TYPE lyre_voice_late_ticks_total counter\n");
+    for (guild_id, stats) in &calls {
+        let _ = writeln!(
+            out,
+            "lyre_voice_late_ticks_total{{guild=\"{}\"}} {}",
+            guild_id, stats.late_ticks
+        );
+    }
+    out.push_str("# HELP lyre_voice_reconnects_total Voice driver reconnects\n");
+    out.push_str("# TYPE lyre_voice_reconnects_total counter\n");
+    for (guild_id, stats) in &calls {
+        let _ = writeln!(
+            out,
+            "lyre_voice_reconnects_total{{guild=\"{}\"}} {}",
+            guild_id, stats.reconnects
+        );
+    }
 }
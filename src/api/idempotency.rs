@@ -0,0 +1,138 @@
+//! `Idempotency-Key` support, so a client that retries a request after a timeout or dropped
+//! connection doesn't act twice. The first response for a key is stored for a day (see
+//! [`IdempotencyKey`]) and sent back as-is for repeats, marked `Idempotent-Replayed: true`.
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Mutex;
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+
+use super::error::{ApiError, ApiResult};
+use crate::database::establish_connection;
+use crate::database::models::IdempotencyKey;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+const MAX_KEY_LEN: usize = 255;
+
+/// Keys whose first request hasn't finished yet, as (guild, user, key)
+static IN_FLIGHT: Lazy<Mutex<HashSet<(String, String, String)>>> =
+    Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Releases an in-flight key however the request ends
+struct InFlight((String, String, String));
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = IN_FLIGHT.lock() {
+            in_flight.remove(&self.0);
+        }
+    }
+}
+
+/// Run `handler` for `user_id`'s request in `guild_id`, unless its `Idempotency-Key` was seen
+/// before, in which case the stored response is returned instead. `request` describes what was
+/// asked for: reusing a key for a different request is refused. Requests without the header
+/// just run. Server errors and rate limits aren't stored, so those can be retried with the
+/// same key.
+pub async fn run_once(
+    req: &HttpRequest,
+    guild_id: &str,
+    user_id: &str,
+    request: &str,
+    handler: impl Future<Output = ApiResult<HttpResponse>>,
+) -> ApiResult<HttpResponse> {
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return handler.await;
+    };
+    let key = key
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|k| !k.is_empty() && k.len() <= MAX_KEY_LEN)
+        .ok_or_else(|| {
+            ApiError::invalid_input(format!(
+                "{} must be 1 to {} visible ASCII characters",
+                IDEMPOTENCY_KEY_HEADER, MAX_KEY_LEN
+            ))
+        })?
+        .to_string();
+
+    if let Some(stored) = stored_response(guild_id, user_id, &key, request)? {
+        return Ok(stored);
+    }
+
+    let slot = (guild_id.to_string(), user_id.to_string(), key.clone());
+    let already_running = IN_FLIGHT
+        .lock()
+        .is_ok_and(|mut in_flight| !in_flight.insert(slot.clone()));
+    if already_running {
+        return Err(ApiError::Conflict(format!(
+            "A request with this {} is still being handled",
+            IDEMPOTENCY_KEY_HEADER
+        )));
+    }
+    let _in_flight = InFlight(slot);
+    // The first request may have finished between the lookup above and claiming the key
+    if let Some(stored) = stored_response(guild_id, user_id, &key, request)? {
+        return Ok(stored);
+    }
+
+    let response = match handler.await {
+        Ok(response) => response,
+        Err(e) => e.error_response(),
+    };
+    let status = response.status();
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        return Ok(response);
+    }
+    let body = actix_web::body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to read response: {}", e)))?;
+    let record = IdempotencyKey {
+        guild_id: guild_id.to_string(),
+        user_id: user_id.to_string(),
+        idempotency_key: key,
+        request: request.to_string(),
+        status: status.as_u16() as i32,
+        body: String::from_utf8_lossy(&body).into_owned(),
+        created_at: Utc::now().naive_utc(),
+    };
+    if let Err(e) = IdempotencyKey::record(&mut establish_connection(), &record) {
+        tracing::warn!("Failed to store idempotency key: {}", e);
+    }
+    Ok(HttpResponse::build(status)
+        .content_type("application/json")
+        .body(body))
+}
+
+/// The response stored for `key`, to send back for a repeat of `request`
+fn stored_response(
+    guild_id: &str,
+    user_id: &str,
+    key: &str,
+    request: &str,
+) -> ApiResult<Option<HttpResponse>> {
+    let stored = IdempotencyKey::find(&mut establish_connection(), guild_id, user_id, key)
+        .map_err(|e| ApiError::Internal(format!("Failed to look up idempotency key: {}", e)))?;
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+    if stored.request != request {
+        return Err(ApiError::invalid_input(format!(
+            "This {} was already used for a different request",
+            IDEMPOTENCY_KEY_HEADER
+        )));
+    }
+    let status = StatusCode::from_u16(stored.status as u16).unwrap_or(StatusCode::OK);
+    Ok(Some(
+        HttpResponse::build(status)
+            .insert_header((REPLAYED_HEADER, "true"))
+            .content_type("application/json")
+            .body(stored.body),
+    ))
+}
@@ -1,12 +1,14 @@
+use super::error::{ApiError, ApiResult};
 use super::types::ApiResponse;
 use crate::auth::AuthenticatedUser;
-use actix_web::{HttpResponse, Result as ActixResult, get, post, web};
+use crate::validation::validate_media_url;
+use actix_web::{HttpResponse, get, post, web};
 
 #[post("/api/search")]
 pub async fn search_songs(
     _req: web::Json<serde_json::Value>,
     _user: AuthenticatedUser,
-) -> ActixResult<HttpResponse> {
+) -> ApiResult<HttpResponse> {
     // TODO: Implement song search using yt-dlp
     Ok(HttpResponse::Ok().json(ApiResponse::success(
         "Search functionality not yet implemented",
@@ -17,11 +19,12 @@ pub async fn search_songs(
 pub async fn get_song_info(
     query: web::Query<std::collections::HashMap<String, String>>,
     _user: AuthenticatedUser,
-) -> ActixResult<HttpResponse> {
+) -> ApiResult<HttpResponse> {
     if let Some(url) = query.get("url") {
+        let url = validate_media_url(url)?;
         // TODO: Use yt-dlp to get song metadata
         Ok(HttpResponse::Ok().json(ApiResponse::success(format!("Song info for: {}", url))))
     } else {
-        Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error("Missing url parameter")))
+        Err(ApiError::invalid_input("Missing url parameter"))
     }
 }
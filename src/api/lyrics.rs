@@ -0,0 +1,61 @@
+//! Lyrics of a guild's playing track, for the dashboard
+
+use actix_web::{HttpRequest, HttpResponse, get};
+use serde::Serialize;
+
+use super::error::{ApiError, ApiResult};
+use super::extract::GuildPath;
+use super::guard::require_guild_access;
+use super::types::ApiResponse;
+use crate::playback_state;
+
+#[derive(Serialize)]
+pub struct LyricsInfo {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Lyrics as text; synced lyrics' timestamps are stripped when they're all there is
+    pub lyrics: Option<String>,
+    /// LRC (`[mm:ss.xx] line`), for highlighting along with `position_secs`
+    pub synced_lyrics: Option<String>,
+    pub instrumental: bool,
+    /// Where the lyrics came from
+    pub source: String,
+    /// How far into the track playback is
+    pub position_secs: u64,
+}
+
+#[get("/api/lyrics/{guild_id}")]
+pub async fn get_lyrics(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_guild_access(&req, &guild_id)?;
+
+    let playing = playback_state::now_playing(&guild_id)
+        .ok_or_else(|| ApiError::NotFound("Nothing is playing".to_string()))?;
+    let title = playing
+        .title
+        .clone()
+        .or_else(|| playing.url.clone())
+        .ok_or_else(|| ApiError::NotFound("The playing track has no title".to_string()))?;
+    let lyrics = crate::lyrics::for_track(&title, playing.url.as_deref())
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to look up lyrics for {:?}: {}", title, e);
+            ApiError::Upstream("Couldn't reach the lyrics provider".to_string())
+        })?;
+    if !lyrics.is_found() {
+        return Err(ApiError::NotFound(format!(
+            "No lyrics found for \"{}\"",
+            title
+        )));
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(LyricsInfo {
+        lyrics: crate::lyrics::text(&lyrics),
+        title: lyrics.title,
+        artist: lyrics.artist,
+        synced_lyrics: lyrics.synced_lyrics,
+        instrumental: lyrics.instrumental,
+        source: lyrics.source,
+        position_secs: playing.position_secs(chrono::Utc::now().naive_utc()),
+    })))
+}
@@ -1,10 +1,15 @@
-use actix_web::{HttpRequest, HttpResponse, Result as ActixResult, delete, get, web};
+use actix_web::{HttpRequest, HttpResponse, delete, get};
 use serde::{Deserialize, Serialize};
 
+use super::error::{ApiError, ApiResult};
+use super::extract::ValidQuery;
 use super::types::ApiResponse;
 use crate::auth::AuthenticatedUser;
 use crate::database::establish_connection;
 use crate::database::models::{QueueHistory, SongCache, VoiceConnection};
+use crate::validation::{
+    Validate, ValidationError, validate_pagination, validate_range, validate_snowflake,
+};
 
 #[derive(Serialize)]
 pub struct MaintenanceStats {
@@ -23,11 +28,20 @@ pub struct CleanupQuery {
     pub days_to_keep: Option<i32>,
 }
 
+impl Validate for CleanupQuery {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(days) = self.days_to_keep {
+            validate_range("days_to_keep", days, 1, 3650)?;
+        }
+        Ok(())
+    }
+}
+
 #[get("/api/maintenance/stats")]
 pub async fn get_maintenance_stats(
     _req: HttpRequest,
     _user: AuthenticatedUser,
-) -> ActixResult<HttpResponse> {
+) -> ApiResult<HttpResponse> {
     let mut conn = establish_connection();
 
     match VoiceConnection::get_all_connected(&mut conn) {
@@ -43,8 +57,9 @@ pub async fn get_maintenance_stats(
         }
         Err(e) => {
             tracing::error!("Failed to get maintenance stats: {}", e);
-            Ok(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Failed to get maintenance stats")))
+            Err(ApiError::Internal(
+                "Failed to get maintenance stats".to_string(),
+            ))
         }
     }
 }
@@ -53,8 +68,8 @@ pub async fn get_maintenance_stats(
 pub async fn cleanup_old_data(
     _req: HttpRequest,
     _user: AuthenticatedUser,
-    query: web::Query<CleanupQuery>,
-) -> ActixResult<HttpResponse> {
+    query: ValidQuery<CleanupQuery>,
+) -> ApiResult<HttpResponse> {
     let mut conn = establish_connection();
     let days_to_keep = query.days_to_keep.unwrap_or(30);
 
@@ -74,23 +89,31 @@ pub async fn cleanup_old_data(
 pub struct UserHistoryQuery {
     pub user_id: String,
     pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl Validate for UserHistoryQuery {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_snowflake("user_id", &self.user_id)?;
+        validate_pagination(self.limit, self.offset, 10, 50)?;
+        Ok(())
+    }
 }
 
 #[get("/api/maintenance/user-history")]
 pub async fn get_user_history(
     _req: HttpRequest,
     _user: AuthenticatedUser,
-    query: web::Query<UserHistoryQuery>,
-) -> ActixResult<HttpResponse> {
+    query: ValidQuery<UserHistoryQuery>,
+) -> ApiResult<HttpResponse> {
     let mut conn = establish_connection();
-    let limit = query.limit.unwrap_or(10).min(50);
+    let page = validate_pagination(query.limit, query.offset, 10, 50)?;
 
-    match QueueHistory::get_recent_for_user(&mut conn, &query.user_id, limit) {
+    match QueueHistory::get_recent_for_user(&mut conn, &query.user_id, page.limit, page.offset) {
         Ok(history) => Ok(HttpResponse::Ok().json(ApiResponse::success(history))),
         Err(e) => {
             tracing::error!("Failed to get user history: {}", e);
-            Ok(HttpResponse::InternalServerError()
-                .json(ApiResponse::<()>::error("Failed to get user history")))
+            Err(ApiError::Internal("Failed to get user history".to_string()))
         }
     }
 }
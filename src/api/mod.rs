@@ -1,26 +1,60 @@
+pub mod admin;
 pub mod analytics;
 pub mod auth;
 pub mod control;
+pub mod control_hooks;
 pub mod dashboard;
+pub mod debug;
 pub mod dev_auth;
+pub mod downloads;
+pub mod error;
+pub mod extract;
+pub mod filter_presets;
+pub mod guard;
 pub mod guilds;
 pub mod health;
+pub mod idempotency;
 pub mod info;
+pub mod lyrics;
 pub mod maintenance;
 pub mod oauth;
+pub mod overview;
+pub mod playlists;
 pub mod queue;
+pub mod settings_events;
+pub mod share;
 pub mod types;
+pub mod uploads;
 
+pub use admin::{
+    announce, get_feature_flags, get_maintenance_mode, get_tools, reload_config,
+    set_maintenance_mode, sync_commands, update_feature_flag, update_tools,
+};
 pub use analytics::{
-    get_cache_stats, get_guild_settings, get_recent_tracks, update_guild_settings,
+    get_cache_stats, get_guild_settings, get_recent_tracks, get_wrapped, update_guild_settings,
 };
 pub use auth::validate_auth;
-pub use control::{join_voice_channel, next_track, set_volume, stop_playback};
+pub use control::{
+    join_voice_channel, next_track, pause_playback, resume_playback, seek_playback, set_volume,
+    stop_playback,
+};
+pub use control_hooks::{create_hook, delete_hook, list_hooks, trigger_hook};
 pub use dashboard::dashboard_redirect;
+pub use debug::{capture_cpu_profile, capture_profile, dump_tasks};
 pub use dev_auth::get_test_token;
-pub use guilds::get_guilds;
+pub use downloads::{cancel_download, download_events, list_downloads};
+pub use filter_presets::{
+    activate_filter_preset, delete_filter_preset, list_filter_presets, save_filter_preset,
+};
+pub use guilds::{check_permissions, get_guilds};
 pub use health::{health_metrics, livez, readyz};
 pub use info::{get_song_info, search_songs};
+pub use lyrics::get_lyrics;
 pub use maintenance::{cleanup_old_data, get_maintenance_stats, get_user_history};
 pub use oauth::oauth_callback;
-pub use queue::{add_to_queue, clear_queue, get_queue, skip_track};
+pub use overview::get_overview;
+pub use playlists::{delete_playlist, get_playlist, list_playlists, save_playlist};
+pub use queue::{add_to_queue, clear_queue, dedupe_queue, get_queue, skip_track};
+pub use settings_events::guild_settings_events;
+pub use share::get_share;
+pub use uploads::{create_upload, delete_upload, get_upload, list_uploads, upload_chunk};
@@ -1,5 +1,5 @@
-use super::types::ApiResponse;
-use actix_web::{HttpResponse, Result as ActixResult, get, web};
+use super::error::{ApiError, ApiResult};
+use actix_web::{HttpResponse, get, web};
 
 #[derive(serde::Deserialize)]
 pub struct OAuthCallback {
@@ -10,17 +10,15 @@ pub struct OAuthCallback {
 }
 
 #[get("/auth/callback")]
-pub async fn oauth_callback(query: web::Query<OAuthCallback>) -> ActixResult<HttpResponse> {
+pub async fn oauth_callback(query: web::Query<OAuthCallback>) -> ApiResult<HttpResponse> {
     if let Some(error) = &query.error {
-        return Ok(HttpResponse::BadRequest()
-            .json(ApiResponse::<()>::error(&format!("OAuth error: {}", error))));
+        return Err(ApiError::OAuth(format!("OAuth error: {}", error)));
     }
 
     let code = match &query.code {
         Some(code) => code,
         None => {
-            return Ok(HttpResponse::BadRequest()
-                .json(ApiResponse::<()>::error("Missing authorization code")));
+            return Err(ApiError::invalid_input("Missing authorization code"));
         }
     };
 
@@ -58,12 +56,7 @@ pub async fn oauth_callback(query: web::Query<OAuthCallback>) -> ActixResult<Htt
 
             Ok(HttpResponse::Ok().content_type("text/html").body(html))
         }
-        Err(e) => Ok(
-            HttpResponse::BadRequest().json(ApiResponse::<()>::error(&format!(
-                "Failed to exchange code: {}",
-                e
-            ))),
-        ),
+        Err(e) => Err(ApiError::OAuth(format!("Failed to exchange code: {}", e))),
     }
 }
 
@@ -95,8 +88,7 @@ async fn exchange_code_for_token(code: &str) -> Result<TokenResponse, Box<dyn st
         ("redirect_uri", redirect_uri.as_str()),
     ];
 
-    let client = reqwest::Client::new();
-    let response = client
+    let response = crate::auth::http()
         .post("https://discord.com/api/oauth2/token")
         .header("Content-Type", "application/x-www-form-urlencoded")
         .form(&params)
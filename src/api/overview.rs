@@ -0,0 +1,97 @@
+use super::analytics::{CacheStats, cache_stats};
+use super::error::{ApiError, ApiResult};
+use super::guard::require_user;
+use super::guilds::guild_info;
+use super::types::{ApiResponse, GuildInfo};
+use crate::auth::AuthenticatedUser;
+use crate::broadcast;
+use crate::database::establish_connection;
+use crate::metrics::METRICS;
+use actix_web::{HttpRequest, HttpResponse, get};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct BotInfo {
+    pub id: String,
+    pub username: String,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ShardStatus {
+    pub id: u32,
+    /// `connected`, `connecting`, `handshaking`, `identifying`, `resuming` or `disconnected`
+    pub stage: String,
+    /// Round trip of the last heartbeat, once one has been acknowledged
+    pub latency_ms: Option<u128>,
+}
+
+#[derive(Serialize)]
+pub struct Overview {
+    /// The bot's own account; `None` until it's connected to Discord
+    pub bot: Option<BotInfo>,
+    pub shards: Vec<ShardStatus>,
+    /// The caller's servers the bot is in a voice channel in
+    pub sessions: Vec<GuildInfo>,
+    pub cache: CacheStats,
+    pub uptime_secs: u64,
+    pub ready: bool,
+    pub draining: bool,
+}
+
+/// Everything the dashboard's landing page shows, in one request
+#[get("/api/overview")]
+pub async fn get_overview(req: HttpRequest, _user: AuthenticatedUser) -> ApiResult<HttpResponse> {
+    let user = require_user(&req)?;
+
+    let mut sessions = Vec::new();
+    for guild in &user.guilds {
+        let info = guild_info(guild).await;
+        if info.connected {
+            sessions.push(info);
+        }
+    }
+
+    let cache = cache_stats(&mut establish_connection()).map_err(|e| {
+        tracing::error!("Failed to get cache stats: {}", e);
+        ApiError::Internal("Failed to get cache stats".to_string())
+    })?;
+    let metrics = METRICS.snapshot();
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(Overview {
+        bot: bot_info(),
+        shards: shard_statuses().await,
+        sessions,
+        cache,
+        uptime_secs: metrics.uptime_secs,
+        ready: metrics.ready,
+        draining: metrics.draining,
+    })))
+}
+
+fn bot_info() -> Option<BotInfo> {
+    let ctx = broadcast::context()?;
+    let user = ctx.cache.current_user();
+    Some(BotInfo {
+        id: user.id.to_string(),
+        username: user.name.clone(),
+        avatar_url: user.avatar_url(),
+    })
+}
+
+async fn shard_statuses() -> Vec<ShardStatus> {
+    let Some(shard_manager) = broadcast::shard_manager() else {
+        return Vec::new();
+    };
+    let runners = shard_manager.runners.lock().await;
+    let mut shards: Vec<ShardStatus> = runners
+        .iter()
+        .map(|(id, runner)| ShardStatus {
+            id: id.0,
+            stage: runner.stage.to_string(),
+            latency_ms: runner.latency.map(|l| l.as_millis()),
+        })
+        .collect();
+    shards.sort_by_key(|s| s.id);
+    shards
+}
@@ -0,0 +1,209 @@
+//! The guild's saved playlists, as `/playlist` manages them, for the dashboard
+
+use actix_web::{HttpRequest, HttpResponse, delete, get, put};
+use serde::{Deserialize, Serialize};
+
+use super::error::{ApiError, ApiResult};
+use super::extract::{GuildPath, ValidJson};
+use super::guard::require_guild_access;
+use super::types::ApiResponse;
+use crate::auth::{AuthenticatedUser, user_can_manage_guild};
+use crate::commands::playlist::MAX_NAME_LEN;
+use crate::database::establish_connection;
+use crate::database::models::SavedPlaylist;
+use crate::database::models::saved_playlist::{PlaylistTrackInput, SavedPlaylistTrack};
+use crate::validation::{Validate, ValidationError, validate_media_url};
+
+/// Most tracks a playlist saved through the API may hold
+const MAX_TRACKS: usize = 500;
+
+#[derive(Serialize)]
+pub struct PlaylistSummary {
+    #[serde(flatten)]
+    pub playlist: SavedPlaylist,
+    pub track_count: i64,
+}
+
+#[derive(Serialize)]
+pub struct PlaylistDetail {
+    #[serde(flatten)]
+    pub playlist: SavedPlaylist,
+    pub tracks: Vec<SavedPlaylistTrack>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistTrackRequest {
+    pub url: String,
+    /// Defaults to the URL
+    pub title: Option<String>,
+    /// Length in seconds
+    pub duration: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SavePlaylistRequest {
+    pub tracks: Vec<PlaylistTrackRequest>,
+}
+
+impl Validate for SavePlaylistRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if self.tracks.is_empty() || self.tracks.len() > MAX_TRACKS {
+            return Err(ValidationError::TooManyEntries {
+                field: "tracks",
+                max: MAX_TRACKS,
+            });
+        }
+        for track in &self.tracks {
+            validate_media_url(&track.url)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `{name}` path segment, checked against the length `/playlist` allows
+fn playlist_name(req: &HttpRequest) -> ApiResult<String> {
+    let name = req.match_info().query("name").trim().to_string();
+    let len = name.chars().count();
+    if len == 0 || len > MAX_NAME_LEN as usize {
+        return Err(ApiError::invalid_input(format!(
+            "Playlist names are 1 to {} characters",
+            MAX_NAME_LEN
+        )));
+    }
+    Ok(name)
+}
+
+fn find(guild_id: &str, name: &str) -> ApiResult<SavedPlaylist> {
+    SavedPlaylist::find_by_name(&mut establish_connection(), guild_id, name)
+        .map_err(|e| ApiError::Internal(format!("Failed to look up playlist: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("No playlist named {}", name)))
+}
+
+/// Like `/playlist`, only the member who saved a playlist or a server manager may change it
+fn require_editor(
+    user: &AuthenticatedUser,
+    guild_id: &str,
+    playlist: &SavedPlaylist,
+) -> ApiResult<()> {
+    if playlist.owner_id != user.user.id && !user_can_manage_guild(&user.guilds, guild_id) {
+        return Err(ApiError::Forbidden(
+            "Only the member who saved this playlist (or a server manager) can change it"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[get("/api/playlists/{guild_id}")]
+pub async fn list_playlists(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_guild_access(&req, &guild_id)?;
+
+    let mut db_conn = establish_connection();
+    let summaries = SavedPlaylist::list_for_guild(&mut db_conn, &guild_id)
+        .and_then(|playlists| {
+            playlists
+                .into_iter()
+                .map(|playlist| {
+                    let track_count = playlist.track_count(&mut db_conn)?;
+                    Ok(PlaylistSummary {
+                        playlist,
+                        track_count,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| ApiError::Internal(format!("Failed to list playlists: {}", e)))?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(summaries)))
+}
+
+#[get("/api/playlists/{guild_id}/{name}")]
+pub async fn get_playlist(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_guild_access(&req, &guild_id)?;
+    let name = playlist_name(&req)?;
+
+    let playlist = find(&guild_id, &name)?;
+    let tracks = playlist
+        .tracks(&mut establish_connection())
+        .map_err(|e| ApiError::Internal(format!("Failed to load playlist: {}", e)))?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(PlaylistDetail { playlist, tracks })))
+}
+
+/// Save a playlist, replacing the tracks of an existing one of the same name
+#[put("/api/playlists/{guild_id}/{name}")]
+pub async fn save_playlist(
+    path: GuildPath,
+    body: ValidJson<SavePlaylistRequest>,
+    req: HttpRequest,
+) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    let user = require_guild_access(&req, &guild_id)?;
+    let name = playlist_name(&req)?;
+
+    let mut db_conn = establish_connection();
+    let existing = SavedPlaylist::find_by_name(&mut db_conn, &guild_id, &name)
+        .map_err(|e| ApiError::Internal(format!("Failed to look up playlist: {}", e)))?;
+    if let Some(existing) = &existing {
+        require_editor(&user, &guild_id, existing)?;
+    }
+    let tracks: Vec<PlaylistTrackInput> = body
+        .tracks
+        .iter()
+        .map(|track| PlaylistTrackInput {
+            url: track.url.trim().to_string(),
+            title: track
+                .title
+                .as_deref()
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .unwrap_or(track.url.trim())
+                .to_string(),
+            duration: track.duration,
+        })
+        .collect();
+    // Keep whoever saved it first as the owner
+    let owner_id = existing
+        .as_ref()
+        .map_or(user.user.id.as_str(), |p| p.owner_id.as_str());
+    let source_url = existing.as_ref().and_then(|p| p.source_url.as_deref());
+    let playlist = SavedPlaylist::save(
+        &mut db_conn,
+        &guild_id,
+        &name,
+        owner_id,
+        source_url,
+        &tracks,
+    )
+    .map_err(|e| ApiError::Internal(format!("Failed to save playlist: {}", e)))?;
+    let tracks = playlist
+        .tracks(&mut db_conn)
+        .map_err(|e| ApiError::Internal(format!("Failed to load playlist: {}", e)))?;
+    tracing::info!(
+        "User {} saved playlist {} ({} tracks) in guild {} via the API",
+        user.user.id,
+        name,
+        tracks.len(),
+        guild_id
+    );
+    Ok(HttpResponse::Ok().json(ApiResponse::success(PlaylistDetail { playlist, tracks })))
+}
+
+#[delete("/api/playlists/{guild_id}/{name}")]
+pub async fn delete_playlist(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    let user = require_guild_access(&req, &guild_id)?;
+    let name = playlist_name(&req)?;
+
+    let playlist = find(&guild_id, &name)?;
+    require_editor(&user, &guild_id, &playlist)?;
+    SavedPlaylist::delete(&mut establish_connection(), &guild_id, &playlist.name)
+        .map_err(|e| ApiError::Internal(format!("Failed to delete playlist: {}", e)))?;
+    tracing::info!(
+        "User {} deleted playlist {} in guild {} via the API",
+        user.user.id,
+        playlist.name,
+        guild_id
+    );
+    Ok(HttpResponse::Ok().json(ApiResponse::success("Playlist deleted")))
+}
@@ -1,27 +1,35 @@
+use super::error::{ApiError, ApiResult};
+use super::extract::{GuildPath, ValidJson};
+use super::guard::{require_command_access, require_guild_access, require_playback_access};
+use super::idempotency::run_once;
 use super::types::{ApiResponse, PlayRequest, QueueInfo, TrackInfo};
-use crate::auth::{get_authenticated_user_from_extensions, user_can_control_guild};
+use crate::auth::user_can_manage_guild;
+use crate::broadcast;
+use crate::capacity;
+use crate::commands::{approval, queue::dedupe};
 use crate::database::{
     establish_connection,
-    models::{CurrentQueue, VoiceConnection},
+    models::{CurrentQueue, GuildSettings, LibraryTrack, QueueStatus, VoiceConnection},
 };
-use actix_web::{HttpRequest, HttpResponse, Result as ActixResult, delete, get, post, web};
+use crate::hooks::{self, HookEvent};
+use crate::playback_state;
+use crate::policy::{
+    check_duration, check_explicit_content, check_not_draining, check_quiet_hours,
+    check_source_allowed, check_title_keywords, check_track_not_blocked, check_upload_in_guild,
+    explicit_filter_enabled, has_blocked_keywords, max_duration_minutes, user_is_dj,
+};
+use crate::source;
+use crate::validation::validate_media_url;
+use crate::voice_manager;
+use crate::voice_state;
+use actix_web::{HttpRequest, HttpResponse, delete, get, post};
+use serenity::all::{ChannelId, GuildId, UserId};
 
 #[get("/api/queue/{guild_id}")]
-pub async fn get_queue(path: web::Path<String>, req: HttpRequest) -> ActixResult<HttpResponse> {
+pub async fn get_queue(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
     let guild_id = path.into_inner();
 
-    let user = match get_authenticated_user_from_extensions(&req) {
-        Ok(user) => user,
-        Err(_) => {
-            return Ok(HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("Authentication failed")));
-        }
-    };
-
-    if !user_can_control_guild(&user.guilds, &guild_id) {
-        return Ok(HttpResponse::Forbidden()
-            .json(ApiResponse::<()>::error("No permission for this guild")));
-    }
+    require_guild_access(&req, &guild_id)?;
 
     // Get actual queue from database
     let mut db_conn = establish_connection();
@@ -36,6 +44,7 @@ pub async fn get_queue(path: web::Path<String>, req: HttpRequest) -> ActixResult
         url: item.url.clone(),
         duration: item.duration.map(|d| d as u64),
         position: item.position as usize,
+        status: Some(item.status().as_str()),
     });
 
     let queue: Vec<TrackInfo> = queue_items
@@ -47,10 +56,16 @@ pub async fn get_queue(path: web::Path<String>, req: HttpRequest) -> ActixResult
             url: item.url.clone(),
             duration: item.duration.map(|d| d as u64),
             position: idx + 1,
+            status: Some(item.status().as_str()),
         })
         .collect();
 
-    let is_playing = voice_connection.map(|vc| vc.is_playing).unwrap_or(false);
+    // An update still waiting to be written is newer than the table
+    let is_playing = voice_connection
+        .map(|vc| voice_state::pending_playing(&guild_id).unwrap_or(vc.is_playing))
+        .unwrap_or(false);
+    // Answered from the playback state store rather than by locking the voice call
+    let now_playing = playback_state::now_playing(&guild_id);
 
     let queue_info = QueueInfo {
         guild_id: guild_id.clone(),
@@ -58,6 +73,11 @@ pub async fn get_queue(path: web::Path<String>, req: HttpRequest) -> ActixResult
         queue,
         position: 0,
         is_playing,
+        elapsed_secs: now_playing
+            .as_ref()
+            .map(|playing| playing.position_secs(chrono::Utc::now().naive_utc())),
+        paused: now_playing.as_ref().is_some_and(|playing| playing.paused()),
+        loop_mode: playback_state::loop_mode(&guild_id).as_str().to_string(),
     };
 
     Ok(HttpResponse::Ok().json(ApiResponse::success(queue_info)))
@@ -65,79 +85,318 @@ pub async fn get_queue(path: web::Path<String>, req: HttpRequest) -> ActixResult
 
 #[post("/api/queue/{guild_id}/add")]
 pub async fn add_to_queue(
-    path: web::Path<String>,
-    req_body: web::Json<PlayRequest>,
+    path: GuildPath,
+    req_body: ValidJson<PlayRequest>,
     req: HttpRequest,
-) -> ActixResult<HttpResponse> {
+) -> ApiResult<HttpResponse> {
     let guild_id = path.into_inner();
 
-    let user = match get_authenticated_user_from_extensions(&req) {
-        Ok(user) => user,
-        Err(_) => {
-            return Ok(HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("Authentication failed")));
-        }
+    let user = require_command_access(&req, &guild_id, "play").await?;
+    // Retried requests are answered from the first attempt, even if the checks would now fail
+    run_once(
+        &req,
+        &guild_id,
+        &user.user.id,
+        req_body.url.trim(),
+        queue_track(
+            &guild_id,
+            &user.user.id,
+            user_can_manage_guild(&user.guilds, &guild_id),
+            &req_body,
+        ),
+    )
+    .await
+}
+
+async fn queue_track(
+    guild_id: &str,
+    user_id: &str,
+    manages_guild: bool,
+    req_body: &PlayRequest,
+) -> ApiResult<HttpResponse> {
+    check_not_draining()?;
+
+    let url = validate_media_url(&req_body.url)?;
+    let settings = {
+        let mut db_conn = establish_connection();
+        check_track_not_blocked(&mut db_conn, guild_id, &url)?;
+        check_upload_in_guild(&mut db_conn, guild_id, &url)
+            .map_err(|e| ApiError::NotFound(e.to_string()))?;
+        GuildSettings::find_by_guild_id(&mut db_conn, guild_id).unwrap_or(None)
     };
+    // Checked again once downloaded, but refusing now saves joining voice for nothing
+    check_quiet_hours(settings.as_ref())?;
+    check_source_allowed(&url, settings.as_ref())?;
+    if let Some(id) = crate::library::track_id(url.as_str()) {
+        let indexed = LibraryTrack::find(&mut establish_connection(), id)
+            .map_err(|e| ApiError::Internal(format!("Failed to look up library track: {}", e)))?;
+        if indexed.is_none() {
+            return Err(ApiError::NotFound(format!(
+                "There's no track {} in the library",
+                id
+            )));
+        }
+    }
 
-    if !user_can_control_guild(&user.guilds, &guild_id) {
-        return Ok(HttpResponse::Forbidden()
-            .json(ApiResponse::<()>::error("No permission for this guild")));
+    let explicit_filter = explicit_filter_enabled(settings.as_ref());
+    if explicit_filter
+        || has_blocked_keywords(settings.as_ref())
+        || max_duration_minutes(&url).is_some()
+    {
+        let metadata = source::extract_metadata(url.as_str()).await.map_err(|e| {
+            tracing::warn!("Failed to fetch metadata for content filters: {}", e);
+            ApiError::Upstream("Couldn't fetch track metadata".to_string())
+        })?;
+        if explicit_filter {
+            check_explicit_content(&metadata)?;
+        }
+        check_duration(&url, &metadata)?;
+        if let Err(e) = check_title_keywords(&metadata.title, settings.as_ref()) {
+            tracing::info!(
+                "Rejected {} in guild {}: title {:?} matched a blocked keyword",
+                url,
+                guild_id,
+                metadata.title
+            );
+            return Err(e.into());
+        }
     }
 
-    // TODO: Implement actual queue addition
-    // This would need access to the Songbird manager
-    tracing::info!(
-        "Adding track {} to queue for guild {}",
-        req_body.url,
+    // In moderation mode, requests from non-DJs wait for a moderator as they do for /play
+    if settings.as_ref().is_some_and(|s| s.require_approval)
+        && !user_is_dj(settings.as_ref(), guild_id, user_id, manages_guild).await
+    {
+        return hold_for_approval(guild_id, user_id, req_body, settings.as_ref()).await;
+    }
+
+    if crate::simulate::enabled() {
+        return simulate_add(
+            guild_id,
+            user_id,
+            url.as_str(),
+            req_body.channel_id.as_deref(),
+        )
+        .await;
+    }
+
+    let ctx = broadcast::context()
+        .ok_or_else(|| ApiError::Upstream("Not connected to Discord".to_string()))?;
+    let guild = GuildId::new(
+        guild_id
+            .parse()
+            .map_err(|_| ApiError::invalid_input("Invalid guild ID"))?,
+    );
+    let user = UserId::new(
+        user_id
+            .parse()
+            .map_err(|_| ApiError::Internal(format!("Malformed user ID: {}", user_id)))?,
+    );
+    let manager = songbird::get(&ctx)
+        .await
+        .ok_or_else(|| ApiError::Internal("Voice manager unavailable".to_string()))?;
+
+    // Join the requested channel if not in voice yet; otherwise stay where the bot is
+    let connected_channel =
+        VoiceConnection::find_by_guild_id(&mut establish_connection(), guild_id)
+            .ok()
+            .flatten()
+            .and_then(|vc| vc.channel_id);
+    let channel_id = match (manager.get(guild).is_some(), connected_channel) {
+        (true, Some(channel_id)) => channel_id,
+        _ => {
+            let channel_id = req_body.channel_id.clone().ok_or_else(|| {
+                ApiError::invalid_input("Not in a voice channel; pass channel_id to join one")
+            })?;
+            if manager.get(guild).is_none() {
+                if !capacity::try_admit(guild, manager.iter().count()) {
+                    return Err(ApiError::Upstream(
+                        "The bot is at capacity right now".to_string(),
+                    ));
+                }
+                voice_manager::join_voice_channel(&ctx, guild, parse_channel(&channel_id)?)
+                    .await
+                    .map_err(|e| ApiError::Upstream(format!("Couldn't join voice: {}", e)))?;
+            }
+            channel_id
+        }
+    };
+    let channel = parse_channel(&channel_id)?;
+
+    // Downloading can take a while; the queue and download events show how it goes
+    let url = req_body.url.trim().to_string();
+    let guild_id = guild_id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) =
+            crate::commands::play::enqueue_quietly(&ctx, guild, channel, user, &url, 0).await
+        {
+            tracing::info!("Couldn't queue {} in guild {}: {}", url, guild_id, e);
+        }
+    });
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(
+        "Track is downloading and will be added to the queue",
+    )))
+}
+
+/// Put a request up for review. The requester hears back in the voice channel's chat: the one
+/// the bot is in, or the one they asked it to join.
+async fn hold_for_approval(
+    guild_id: &str,
+    user_id: &str,
+    req_body: &PlayRequest,
+    settings: Option<&GuildSettings>,
+) -> ApiResult<HttpResponse> {
+    let guild = GuildId::new(
         guild_id
+            .parse()
+            .map_err(|_| ApiError::invalid_input("Invalid guild ID"))?,
     );
-    if let Some(channel_id) = &req_body.channel_id {
-        tracing::info!("Using voice channel: {}", channel_id);
+    let user = UserId::new(
+        user_id
+            .parse()
+            .map_err(|_| ApiError::Internal(format!("Malformed user ID: {}", user_id)))?,
+    );
+    let connected_channel =
+        VoiceConnection::find_by_guild_id(&mut establish_connection(), guild_id)
+            .ok()
+            .flatten()
+            .and_then(|vc| vc.channel_id);
+    let channel = parse_channel(
+        &connected_channel
+            .or_else(|| req_body.channel_id.clone())
+            .ok_or_else(|| {
+                ApiError::invalid_input("Not in a voice channel; pass channel_id to join one")
+            })?,
+    )?;
+
+    let url = req_body.url.trim();
+    if crate::simulate::enabled() {
+        // There's nowhere to post the review; the request just waits to expire
+        approval::record(guild, channel, user, url, 0)
+            .map_err(|e| ApiError::Internal(format!("Failed to hold request: {}", e)))?;
+    } else {
+        let ctx = broadcast::context()
+            .ok_or_else(|| ApiError::Upstream("Not connected to Discord".to_string()))?;
+        let held = approval::hold(&ctx, guild, channel, user, url, 0, settings)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to hold request: {}", e)))?;
+        if !held {
+            return Err(ApiError::Upstream(
+                "Requests need a DJ's approval here, but the review channel couldn't be reached"
+                    .to_string(),
+            ));
+        }
     }
+    Ok(HttpResponse::Accepted().json(ApiResponse::success(
+        "Requests need a DJ's approval here; yours is waiting for one",
+    )))
+}
 
-    Ok(HttpResponse::Ok().json(ApiResponse::success("Track added to queue")))
+fn parse_channel(raw: &str) -> ApiResult<ChannelId> {
+    raw.parse()
+        .map(ChannelId::new)
+        .map_err(|_| ApiError::invalid_input("Invalid channel_id"))
 }
 
 #[post("/api/queue/{guild_id}/skip")]
-pub async fn skip_track(path: web::Path<String>, req: HttpRequest) -> ActixResult<HttpResponse> {
+pub async fn skip_track(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
     let guild_id = path.into_inner();
 
-    let user = match get_authenticated_user_from_extensions(&req) {
-        Ok(user) => user,
-        Err(_) => {
-            return Ok(HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("Authentication failed")));
-        }
-    };
+    require_command_access(&req, &guild_id, "next").await?;
 
-    if !user_can_control_guild(&user.guilds, &guild_id) {
-        return Ok(HttpResponse::Forbidden()
-            .json(ApiResponse::<()>::error("No permission for this guild")));
+    if crate::simulate::enabled() {
+        let mut db_conn = establish_connection();
+        CurrentQueue::advance_queue(&mut db_conn, &guild_id)
+            .map_err(|e| ApiError::Internal(format!("Failed to skip: {}", e)))?;
+    } else {
+        super::control::skip_playing(&guild_id).await?;
     }
 
-    // TODO: Implement actual skip functionality
-
     Ok(HttpResponse::Ok().json(ApiResponse::success("Track skipped")))
 }
 
 #[delete("/api/queue/{guild_id}")]
-pub async fn clear_queue(path: web::Path<String>, req: HttpRequest) -> ActixResult<HttpResponse> {
+pub async fn clear_queue(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
     let guild_id = path.into_inner();
 
-    let user = match get_authenticated_user_from_extensions(&req) {
-        Ok(user) => user,
-        Err(_) => {
-            return Ok(HttpResponse::Unauthorized()
-                .json(ApiResponse::<()>::error("Authentication failed")));
-        }
-    };
+    require_command_access(&req, &guild_id, "stop").await?;
 
-    if !user_can_control_guild(&user.guilds, &guild_id) {
-        return Ok(HttpResponse::Forbidden()
-            .json(ApiResponse::<()>::error("No permission for this guild")));
+    if crate::simulate::enabled() {
+        let mut db_conn = establish_connection();
+        CurrentQueue::clear_guild_queue(&mut db_conn, &guild_id)
+            .map_err(|e| ApiError::Internal(format!("Failed to clear queue: {}", e)))?;
     }
 
     // TODO: Implement actual queue clearing
 
     Ok(HttpResponse::Ok().json(ApiResponse::success("Queue cleared")))
 }
+
+/// Remove pending tracks that are already queued earlier, keeping the first of each
+#[post("/api/queue/{guild_id}/dedupe")]
+pub async fn dedupe_queue(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+
+    require_playback_access(&req, &guild_id)?;
+
+    let call_lock = match (broadcast::voice_manager(), guild_id.parse::<u64>()) {
+        (Some(manager), Ok(id)) => manager.get(GuildId::new(id)),
+        _ => None,
+    };
+    let removed = dedupe(call_lock.as_ref(), &guild_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to dedupe queue: {}", e)))?;
+
+    Ok(
+        HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+            "removed": removed,
+        }))),
+    )
+}
+
+/// Queue a track in the database for the simulated voice layer to play, "joining"
+/// `channel_id` if the guild has no voice connection yet
+pub async fn simulate_add(
+    guild_id: &str,
+    user_id: &str,
+    url: &str,
+    channel_id: Option<&str>,
+) -> ApiResult<HttpResponse> {
+    // Titles are nice to have; simulation still works offline without them
+    let metadata = source::extract_metadata(url).await.ok();
+    let mut db_conn = establish_connection();
+    if !VoiceConnection::is_connected(&mut db_conn, guild_id) {
+        let channel_id = channel_id.ok_or_else(|| {
+            ApiError::invalid_input("Not in a voice channel; pass channel_id to join one")
+        })?;
+        VoiceConnection::create_or_update(&mut db_conn, guild_id, Some(channel_id))
+            .map_err(|e| ApiError::Internal(format!("Failed to join: {}", e)))?;
+    }
+    let entry = CurrentQueue::add_to_queue(
+        &mut db_conn,
+        guild_id,
+        url,
+        metadata.as_ref().map(|m| m.title.as_str()),
+        metadata.as_ref().and_then(|m| m.duration_secs()),
+        user_id,
+        None,
+        0,
+        QueueStatus::PendingDownload,
+    )
+    .map_err(|e| ApiError::Internal(format!("Failed to queue track: {}", e)))?;
+    hooks::emit(
+        HookEvent::QueueAdd,
+        guild_id,
+        serde_json::json!({
+            "url": url,
+            "title": entry.title,
+            "duration": entry.duration,
+            "requested_by": user_id,
+            "position": entry.position,
+        }),
+    );
+    Ok(HttpResponse::Ok().json(ApiResponse::success(format!(
+        "Track added to queue at position {}",
+        entry.position
+    ))))
+}
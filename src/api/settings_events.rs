@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use super::analytics::GuildSettingsResponse;
+use super::error::ApiResult;
+use super::extract::GuildPath;
+use super::guard::require_guild_access;
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+use crate::settings_events::{self, SettingsChanged};
+use actix_web::{HttpRequest, HttpResponse, get, web::Bytes};
+use futures_util::stream;
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+
+/// Sent when nothing else has been, so proxies don't close an idle stream
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Server-sent events with the guild's settings: a `settings` event with all of them (as `GET
+/// /api/guild-settings` returns them) each time they're saved, from the API or a slash command
+#[get("/api/guild-settings/{guild_id}/events")]
+pub async fn guild_settings_events(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+
+    require_guild_access(&req, &guild_id)?;
+
+    let events = stream::unfold(
+        (settings_events::subscribe(), guild_id),
+        |(mut rx, guild_id)| async move {
+            let chunk = next_chunk(&mut rx, &guild_id).await?;
+            Some((Ok::<_, actix_web::Error>(chunk), (rx, guild_id)))
+        },
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events))
+}
+
+/// The guild's settings in SSE framing once they next change, or a keepalive comment if they
+/// don't in time; `None` once the channel closes
+async fn next_chunk(rx: &mut Receiver<SettingsChanged>, guild_id: &str) -> Option<Bytes> {
+    loop {
+        let send = match tokio::time::timeout(KEEPALIVE_INTERVAL, rx.recv()).await {
+            Err(_) => return Some(Bytes::from_static(b": keepalive\n\n")),
+            Ok(Ok(event)) => event.guild_id == guild_id,
+            // One of the missed changes may have been this guild's; the settings are read fresh
+            // either way, so sending them again is harmless
+            Ok(Err(RecvError::Lagged(_))) => true,
+            Ok(Err(RecvError::Closed)) => return None,
+        };
+        if !send {
+            continue;
+        }
+        if let Ok(Some(settings)) =
+            GuildSettings::find_by_guild_id(&mut establish_connection(), guild_id)
+        {
+            let data = serde_json::to_string(&GuildSettingsResponse::from(settings)).ok()?;
+            return Some(Bytes::from(format!("event: settings\ndata: {}\n\n", data)));
+        }
+    }
+}
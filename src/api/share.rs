@@ -0,0 +1,63 @@
+use super::error::{ApiError, ApiResult};
+use super::guard::require_user;
+use super::types::{ApiResponse, TrackInfo};
+use crate::commands::queue::is_share_token;
+use crate::database::{establish_connection, models::QueueShare};
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct SharedQueueResponse {
+    pub token: String,
+    pub guild_id: String,
+    pub created_by: String,
+    pub created_at: String,
+    pub expires_at: String,
+    pub tracks: Vec<TrackInfo>,
+}
+
+/// The track list behind a `/queue share` token. Any signed-in user may read it: knowing the
+/// token is what grants access, the same as importing it with `/play share:<token>`.
+#[get("/api/share/{token}")]
+pub async fn get_share(path: web::Path<String>, req: HttpRequest) -> ApiResult<HttpResponse> {
+    require_user(&req)?;
+
+    let token = path.into_inner();
+    if !is_share_token(&token) {
+        return Err(ApiError::invalid_input("Malformed share token"));
+    }
+
+    let mut db_conn = establish_connection();
+    let (share, tracks) = QueueShare::find_active(&mut db_conn, &token)
+        .and_then(|share| match share {
+            Some(share) => share
+                .tracks(&mut db_conn)
+                .map(|tracks| Some((share, tracks))),
+            None => Ok(None),
+        })
+        .map_err(|e| {
+            tracing::error!("Failed to load queue share: {}", e);
+            ApiError::Internal("Failed to load shared queue".to_string())
+        })?
+        .ok_or_else(|| ApiError::NotFound("Share not found or expired".to_string()))?;
+
+    let response = SharedQueueResponse {
+        token: share.token,
+        guild_id: share.guild_id,
+        created_by: share.created_by,
+        created_at: share.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        expires_at: share.expires_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        tracks: tracks
+            .into_iter()
+            .map(|t| TrackInfo {
+                title: t.title.unwrap_or_else(|| "Unknown".to_string()),
+                url: t.url,
+                duration: t.duration.map(|d| d as u64),
+                position: t.position as usize,
+                status: None,
+            })
+            .collect(),
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+}
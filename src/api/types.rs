@@ -1,5 +1,11 @@
 use serde::{Deserialize, Serialize};
 
+use super::error::ApiErrorBody;
+use crate::validation::{
+    Validate, ValidationError, validate_media_url, validate_range, validate_snowflake,
+    validate_volume,
+};
+
 #[derive(Serialize)]
 pub struct ProbeResp<'a> {
     pub status: &'a str,
@@ -9,7 +15,7 @@ pub struct ProbeResp<'a> {
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
-    pub error: Option<String>,
+    pub error: Option<ApiErrorBody>,
 }
 
 #[derive(Serialize)]
@@ -19,6 +25,11 @@ pub struct QueueInfo {
     pub queue: Vec<TrackInfo>,
     pub position: usize,
     pub is_playing: bool,
+    /// How far into the current track playback is, in seconds
+    pub elapsed_secs: Option<u64>,
+    pub paused: bool,
+    /// `off`, `track` or `queue`
+    pub loop_mode: String,
 }
 
 #[derive(Serialize)]
@@ -27,6 +38,8 @@ pub struct TrackInfo {
     pub url: String,
     pub duration: Option<u64>,
     pub position: usize,
+    /// Queue entries only: `pending_download`, `downloading`, `ready`, `playing` or `failed`
+    pub status: Option<&'static str>,
 }
 
 #[derive(Serialize)]
@@ -34,8 +47,12 @@ pub struct GuildInfo {
     pub id: String,
     pub name: String,
     pub connected: bool,
+    /// Name of the voice channel the bot is in, or its ID when Discord can't tell us the name
     pub voice_channel: Option<String>,
+    pub voice_channel_id: Option<String>,
+    /// Tracks waiting after the one that's playing
     pub queue_length: usize,
+    pub current_track: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -44,11 +61,43 @@ pub struct PlayRequest {
     pub channel_id: Option<String>,
 }
 
+impl Validate for PlayRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_media_url(&self.url)?;
+        if let Some(channel_id) = &self.channel_id {
+            validate_snowflake("channel_id", channel_id)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Deserialize)]
 pub struct VolumeRequest {
     pub volume: f32,
 }
 
+impl Validate for VolumeRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_volume("volume", self.volume)?;
+        Ok(())
+    }
+}
+
+/// Longest position a seek may ask for, well past any track the bot plays
+const MAX_SEEK_SECS: f64 = 24.0 * 60.0 * 60.0;
+
+#[derive(Deserialize)]
+pub struct SeekRequest {
+    pub seconds: f64,
+}
+
+impl Validate for SeekRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_range("seconds", self.seconds, 0.0, MAX_SEEK_SECS)?;
+        Ok(())
+    }
+}
+
 #[derive(Deserialize)]
 pub struct AuthRequest {
     pub access_token: String,
@@ -63,11 +112,11 @@ impl<T> ApiResponse<T> {
         }
     }
 
-    pub fn error(message: &str) -> Self {
+    pub fn failure(error: ApiErrorBody) -> Self {
         Self {
             success: false,
             data: None,
-            error: Some(message.to_string()),
+            error: Some(error),
         }
     }
 }
@@ -0,0 +1,396 @@
+//! Chunked, resumable uploads of audio files to a guild. A client announces the file, then sends
+//! it in pieces, each tagged with the offset it starts at:
+//!
+//! ```text
+//! POST  /api/uploads/{guild_id}              {"filename": "intro.mp3", "size": 31457280}
+//! PATCH /api/uploads/{guild_id}/{upload_id}  Upload-Offset: 0        <first chunk>
+//! PATCH /api/uploads/{guild_id}/{upload_id}  Upload-Offset: 8388608  <next chunk>
+//! ```
+//!
+//! After a dropped connection, `GET /api/uploads/{guild_id}/{upload_id}` says how much arrived
+//! (`received`) so the client carries on from there. Once the last byte is in, the file is
+//! checked to be audio and scanned before it's kept; unfinished uploads are dropped after a day.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use actix_web::{HttpRequest, HttpResponse, delete, get, patch, post, web};
+use chrono::Utc;
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::error::{ApiError, ApiResult};
+use super::extract::{GuildPath, ValidJson};
+use super::guard::{require_guild_access, require_playback_access};
+use super::types::ApiResponse;
+use crate::auth::user_can_manage_guild;
+use crate::database::establish_connection;
+use crate::database::models::Upload;
+use crate::uploads::{self, MAX_CHUNK_BYTES};
+use crate::validation::{Validate, ValidationError};
+
+const OFFSET_HEADER: &str = "Upload-Offset";
+const MAX_FILENAME_LEN: usize = 200;
+
+/// Uploads with a chunk being written right now
+static WRITING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Releases an upload's write slot however the request ends
+struct Writing(String);
+
+impl Drop for Writing {
+    fn drop(&mut self) {
+        if let Ok(mut writing) = WRITING.lock() {
+            writing.remove(&self.0);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUploadRequest {
+    pub filename: String,
+    /// The whole file's size in bytes
+    pub size: i64,
+}
+
+impl Validate for CreateUploadRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let name = self.filename.trim();
+        let len = name.chars().count();
+        if len == 0
+            || len > MAX_FILENAME_LEN
+            || name
+                .chars()
+                .any(|c| c.is_control() || c == '/' || c == '\\')
+        {
+            return Err(ValidationError::InvalidEntry {
+                field: "filename",
+                entry: self.filename.clone(),
+                max: MAX_FILENAME_LEN,
+            });
+        }
+        if self.size <= 0 {
+            return Err(ValidationError::InvalidFormat {
+                field: "size",
+                expected: "the file's size in bytes",
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct UploadList {
+    pub uploads: Vec<Upload>,
+    pub used_bytes: i64,
+    pub quota_bytes: u64,
+    pub max_file_bytes: u64,
+    pub max_chunk_bytes: usize,
+}
+
+fn upload_id(req: &HttpRequest) -> ApiResult<String> {
+    let id = req.match_info().query("upload_id");
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ApiError::invalid_input("upload_id isn't a valid upload ID"));
+    }
+    Ok(id.to_string())
+}
+
+fn find_upload(guild_id: &str, id: &str) -> ApiResult<Upload> {
+    Upload::find(&mut establish_connection(), guild_id, id)
+        .map_err(|e| ApiError::Internal(format!("Failed to look up upload: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("Upload not found".to_string()))
+}
+
+/// Drop unfinished uploads nobody came back for, along with what they'd received
+async fn clear_abandoned() {
+    let abandoned = match Upload::take_abandoned(&mut establish_connection()) {
+        Ok(abandoned) => abandoned,
+        Err(e) => {
+            tracing::warn!("Failed to clear abandoned uploads: {}", e);
+            return;
+        }
+    };
+    for upload in abandoned {
+        if let Ok(path) = uploads::partial_path(&upload.guild_id, &upload.id) {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+    }
+}
+
+#[get("/api/uploads/{guild_id}")]
+pub async fn list_uploads(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_guild_access(&req, &guild_id)?;
+
+    let mut db_conn = establish_connection();
+    let uploads = Upload::list_completed(&mut db_conn, &guild_id)
+        .map_err(|e| ApiError::Internal(format!("Failed to list uploads: {}", e)))?;
+    let used_bytes = Upload::guild_usage(&mut db_conn, &guild_id)
+        .map_err(|e| ApiError::Internal(format!("Failed to total uploads: {}", e)))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(UploadList {
+        uploads,
+        used_bytes,
+        quota_bytes: uploads::guild_quota_bytes(),
+        max_file_bytes: uploads::max_file_bytes(),
+        max_chunk_bytes: MAX_CHUNK_BYTES,
+    })))
+}
+
+/// Start an upload, reserving its size against the guild's quota
+#[post("/api/uploads/{guild_id}")]
+pub async fn create_upload(
+    path: GuildPath,
+    body: ValidJson<CreateUploadRequest>,
+    req: HttpRequest,
+) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    let user = require_playback_access(&req, &guild_id)?;
+    let body = body.0;
+
+    let max_file = uploads::max_file_bytes();
+    if body.size as u64 > max_file {
+        return Err(ApiError::InvalidInput {
+            message: format!("Files may be at most {} MB", max_file / (1024 * 1024)),
+            details: Some(serde_json::json!({ "max_file_bytes": max_file })),
+        });
+    }
+    clear_abandoned().await;
+    let mut db_conn = establish_connection();
+    let used = Upload::guild_usage(&mut db_conn, &guild_id)
+        .map_err(|e| ApiError::Internal(format!("Failed to total uploads: {}", e)))?;
+    let quota = uploads::guild_quota_bytes();
+    if (used + body.size) as u64 > quota {
+        return Err(ApiError::InvalidInput {
+            message: "This file would take the server over its upload quota; delete some \
+                      uploads first"
+                .to_string(),
+            details: Some(serde_json::json!({ "used_bytes": used, "quota_bytes": quota })),
+        });
+    }
+
+    let id = uploads::new_id().map_err(|e| ApiError::Internal(e.to_string()))?;
+    let upload = Upload {
+        id,
+        guild_id,
+        user_id: user.user.id.clone(),
+        filename: body.filename.trim().to_string(),
+        size: body.size as i32,
+        received: 0,
+        content_type: None,
+        created_at: Utc::now().naive_utc(),
+        completed_at: None,
+    };
+    Upload::create(&mut db_conn, &upload)
+        .map_err(|e| ApiError::Internal(format!("Failed to start upload: {}", e)))?;
+    tracing::info!(
+        "User {} started upload {} ({} bytes) in guild {}",
+        user.user.id,
+        upload.id,
+        upload.size,
+        upload.guild_id
+    );
+
+    Ok(HttpResponse::Created().json(ApiResponse::success(upload)))
+}
+
+/// How far an upload has got, to resume it
+#[get("/api/uploads/{guild_id}/{upload_id}")]
+pub async fn get_upload(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_guild_access(&req, &guild_id)?;
+    let upload = find_upload(&guild_id, &upload_id(&req)?)?;
+    Ok(HttpResponse::Ok()
+        .insert_header((OFFSET_HEADER, upload.received.to_string()))
+        .json(ApiResponse::success(upload)))
+}
+
+/// Append a chunk, starting at the `Upload-Offset` the client says it's sending from. That must
+/// be where the upload has got to, so a chunk sent twice isn't stored twice.
+#[patch("/api/uploads/{guild_id}/{upload_id}")]
+pub async fn upload_chunk(
+    path: GuildPath,
+    req: HttpRequest,
+    mut payload: web::Payload,
+) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    require_playback_access(&req, &guild_id)?;
+    let id = upload_id(&req)?;
+    let offset: i32 = req
+        .headers()
+        .get(OFFSET_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .ok_or_else(|| {
+            ApiError::invalid_input(format!("{} must be a byte offset", OFFSET_HEADER))
+        })?;
+
+    let claimed = WRITING
+        .lock()
+        .is_ok_and(|mut writing| writing.insert(id.clone()));
+    if !claimed {
+        return Err(ApiError::Conflict(
+            "Another chunk of this upload is still being written".to_string(),
+        ));
+    }
+    let _writing = Writing(id.clone());
+
+    let upload = find_upload(&guild_id, &id)?;
+    if upload.completed_at.is_some() {
+        return Err(ApiError::Conflict(
+            "This upload is already complete".to_string(),
+        ));
+    }
+    if offset != upload.received {
+        return Err(ApiError::Conflict(format!(
+            "This upload has {} bytes; send the chunk starting there",
+            upload.received
+        )));
+    }
+
+    let partial = uploads::partial_path(&guild_id, &id)
+        .map_err(|e| ApiError::Internal(format!("No upload folder: {}", e)))?;
+    let write_error =
+        |e: std::io::Error| ApiError::Internal(format!("Failed to store chunk: {}", e));
+    if let Some(dir) = partial.parent() {
+        tokio::fs::create_dir_all(dir).await.map_err(write_error)?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&partial)
+        .await
+        .map_err(write_error)?;
+    // Anything past what was recorded is from a chunk that never finished
+    file.set_len(upload.received as u64)
+        .await
+        .map_err(write_error)?;
+    file.seek(std::io::SeekFrom::End(0))
+        .await
+        .map_err(write_error)?;
+
+    let room = (upload.size - upload.received) as usize;
+    let mut written = 0usize;
+    let mut failure = None;
+    while let Some(piece) = payload.next().await {
+        let piece = match piece {
+            Ok(piece) => piece,
+            Err(e) => {
+                failure = Some(ApiError::invalid_input(format!(
+                    "The chunk was cut off: {}",
+                    e
+                )));
+                break;
+            }
+        };
+        if written + piece.len() > MAX_CHUNK_BYTES.min(room) {
+            failure = Some(ApiError::invalid_input(format!(
+                "Chunks may be at most {} bytes and mustn't run past the announced size",
+                MAX_CHUNK_BYTES
+            )));
+            break;
+        }
+        file.write_all(&piece).await.map_err(write_error)?;
+        written += piece.len();
+    }
+    file.flush().await.map_err(write_error)?;
+    drop(file);
+
+    // Keep what arrived even if the chunk was cut off, so the client can resume from there
+    let received = upload.received + written as i32;
+    Upload::set_received(&mut establish_connection(), &id, received)
+        .map_err(|e| ApiError::Internal(format!("Failed to record chunk: {}", e)))?;
+    if let Some(failure) = failure {
+        return Err(failure);
+    }
+    if received < upload.size {
+        return Ok(HttpResponse::Ok()
+            .insert_header((OFFSET_HEADER, received.to_string()))
+            .json(ApiResponse::success(Upload { received, ..upload })));
+    }
+
+    let finished = finish(&upload, &partial).await;
+    let mut db_conn = establish_connection();
+    match finished {
+        Ok(content_type) => {
+            Upload::complete(&mut db_conn, &id, content_type)
+                .map_err(|e| ApiError::Internal(format!("Failed to finish upload: {}", e)))?;
+            tracing::info!("Upload {} in guild {} is complete", id, guild_id);
+            let upload = find_upload(&guild_id, &id)?;
+            Ok(HttpResponse::Ok()
+                .insert_header((OFFSET_HEADER, received.to_string()))
+                .json(ApiResponse::success(upload)))
+        }
+        Err(reason) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            if let Err(e) = Upload::delete(&mut db_conn, &id) {
+                tracing::warn!("Failed to drop rejected upload {}: {}", id, e);
+            }
+            Err(reason)
+        }
+    }
+}
+
+/// Check a fully received file and move it into place, returning its sniffed MIME type
+async fn finish(upload: &Upload, partial: &std::path::Path) -> ApiResult<&'static str> {
+    let mut head = [0u8; 16];
+    let read_error =
+        |e: std::io::Error| ApiError::Internal(format!("Failed to read upload: {}", e));
+    let read = tokio::fs::File::open(partial)
+        .await
+        .map_err(read_error)?
+        .read(&mut head)
+        .await
+        .map_err(read_error)?;
+    let content_type = uploads::sniff_audio(&head[..read]).ok_or_else(|| {
+        ApiError::invalid_input(
+            "That isn't an audio file (MP3, Ogg, FLAC, WAV, M4A or WebM); it wasn't kept",
+        )
+    })?;
+    uploads::scan(partial)
+        .await
+        .map_err(|e| ApiError::invalid_input(format!("Rejected: {}; it wasn't kept", e)))?;
+    let path = uploads::file_path(&upload.guild_id, &upload.id)
+        .map_err(|e| ApiError::Internal(format!("No upload folder: {}", e)))?;
+    tokio::fs::rename(partial, &path)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to keep upload: {}", e)))?;
+    Ok(content_type)
+}
+
+#[delete("/api/uploads/{guild_id}/{upload_id}")]
+pub async fn delete_upload(path: GuildPath, req: HttpRequest) -> ApiResult<HttpResponse> {
+    let guild_id = path.into_inner();
+    let user = require_guild_access(&req, &guild_id)?;
+    let upload = find_upload(&guild_id, &upload_id(&req)?)?;
+    if upload.user_id != user.user.id && !user_can_manage_guild(&user.guilds, &guild_id) {
+        return Err(ApiError::Forbidden(
+            "Only the member who uploaded this file (or a server manager) can delete it"
+                .to_string(),
+        ));
+    }
+
+    Upload::delete(&mut establish_connection(), &upload.id)
+        .map_err(|e| ApiError::Internal(format!("Failed to delete upload: {}", e)))?;
+    for path in [
+        uploads::file_path(&guild_id, &upload.id),
+        uploads::partial_path(&guild_id, &upload.id),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    tracing::info!(
+        "User {} deleted upload {} in guild {}",
+        user.user.id,
+        upload.id,
+        guild_id
+    );
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success("Upload deleted")))
+}
@@ -1,4 +1,8 @@
-use std::{path::PathBuf, process::Stdio};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
 
 use anyhow::{Context as AnyhowContext, Result, anyhow};
 use once_cell::sync::Lazy;
@@ -6,9 +10,9 @@ use reqwest::header::{ACCEPT, USER_AGENT};
 use serde::Deserialize;
 use tokio::{
     fs,
-    io::{AsyncBufReadExt, BufReader},
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
     process::Command as TokioCommand,
-    sync::mpsc,
+    sync::{mpsc, watch},
     task::JoinHandle,
 };
 
@@ -20,6 +24,14 @@ static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
 });
 
 const GITHUB_RELEASES_API: &str = "https://api.github.com/repos/yt-dlp/yt-dlp/releases/latest";
+/// Asset of each yt-dlp release listing the SHA-256 of every other asset
+const YT_DLP_CHECKSUMS: &str = "SHA2-256SUMS";
+
+/// Written next to the managed yt-dlp by `update_yt_dlp`; holds the RFC 3339 time of the update
+const UPDATE_MARKER: &str = "last_update";
+
+/// Serializes updates so two admins can't swap the binary at the same time
+static UPDATE_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
 
 #[derive(Debug, Deserialize)]
 struct ReleaseAsset {
@@ -30,7 +42,6 @@ struct ReleaseAsset {
 #[derive(Debug, Deserialize)]
 struct ReleaseInfo {
     assets: Vec<ReleaseAsset>,
-    #[allow(dead_code)]
     tag_name: String,
 }
 
@@ -39,6 +50,14 @@ fn cache_dir() -> Result<PathBuf> {
     Ok(base.join("lyre").join("yt-dlp"))
 }
 
+fn managed_path(dir: &Path) -> PathBuf {
+    dir.join(if cfg!(target_os = "windows") {
+        "yt-dlp.exe"
+    } else {
+        "yt-dlp"
+    })
+}
+
 fn platform_asset_name() -> &'static str {
     if cfg!(target_os = "windows") {
         if cfg!(target_arch = "x86_64") {
@@ -55,109 +74,590 @@ fn platform_asset_name() -> &'static str {
     }
 }
 
-async fn ensure_yt_dlp() -> Result<PathBuf> {
+/// The yt-dlp that would be used, without downloading one. A copy installed by `update_yt_dlp`
+/// wins over one on PATH so a broken system install can be fixed without redeploying.
+pub async fn resolve_yt_dlp() -> Option<PathBuf> {
+    let local = cache_dir().ok().map(|dir| managed_path(&dir));
+    if let Some(local) = &local
+        && yt_dlp_last_updated().await.is_some()
+        && fs::try_exists(local).await.unwrap_or(false)
+    {
+        return Some(local.clone());
+    }
     if let Ok(p) = which::which("yt-dlp") {
+        return Some(p);
+    }
+    match local {
+        Some(local) if fs::try_exists(&local).await.unwrap_or(false) => Some(local),
+        _ => None,
+    }
+}
+
+pub async fn ensure_yt_dlp() -> Result<PathBuf> {
+    if let Some(p) = resolve_yt_dlp().await {
         return Ok(p);
     }
 
     let dir = cache_dir()?;
     fs::create_dir_all(&dir).await.ok();
+    let local = managed_path(&dir);
+    let release = latest_release().await?;
+    install_release(&release, &local).await?;
+    Ok(local)
+}
 
-    let local = dir.join(if cfg!(target_os = "windows") {
-        "yt-dlp.exe"
-    } else {
-        "yt-dlp"
-    });
-    if fs::try_exists(&local).await.unwrap_or(false) {
-        return Ok(local);
-    }
+/// When `update_yt_dlp` last installed a release, if ever
+pub async fn yt_dlp_last_updated() -> Option<String> {
+    let marker = cache_dir().ok()?.join(UPDATE_MARKER);
+    let stamp = fs::read_to_string(marker).await.ok()?;
+    Some(stamp.trim().to_string())
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct YtDlpUpdate {
+    /// Release tag on GitHub
+    pub release: String,
+    /// What the new binary reports for `--version`
+    pub version: String,
+    pub path: String,
+    pub updated_at: String,
+}
+
+/// Install the latest yt-dlp release into the cache dir: download it next to the current binary,
+/// check it runs, then rename it over the old one so in-flight downloads never see a partial file
+pub async fn update_yt_dlp() -> Result<YtDlpUpdate> {
+    let _guard = UPDATE_LOCK.lock().await;
+    let dir = cache_dir()?;
+    fs::create_dir_all(&dir).await?;
+    let local = managed_path(&dir);
 
+    let release = latest_release().await?;
+    let version = install_release(&release, &local).await?;
+    let updated_at = chrono::Utc::now().to_rfc3339();
+    fs::write(dir.join(UPDATE_MARKER), &updated_at).await?;
+    tracing::info!("Updated yt-dlp to {} ({})", release.tag_name, version);
+    Ok(YtDlpUpdate {
+        release: release.tag_name,
+        version,
+        path: local.display().to_string(),
+        updated_at,
+    })
+}
+
+async fn latest_release() -> Result<ReleaseInfo> {
     let resp = HTTP
         .get(GITHUB_RELEASES_API)
         .header(ACCEPT, "application/vnd.github+json")
         .send()
         .await?
         .error_for_status()?;
-    let rel: ReleaseInfo = resp.json().await?;
-
-    let wanted = platform_asset_name();
-    let asset = rel
-        .assets
-        .into_iter()
-        .find(|a| a.name == wanted)
-        .ok_or_else(|| anyhow!("no suitable yt-dlp asset for this platform: {}", wanted))?;
+    Ok(resp.json().await?)
+}
 
+async fn download_asset(asset: &ReleaseAsset) -> Result<Vec<u8>> {
     let bytes = HTTP
-        .get(asset.browser_download_url)
+        .get(&asset.browser_download_url)
         .header(USER_AGENT, "lyre-bot/0.1")
         .send()
         .await?
         .error_for_status()?
         .bytes()
         .await?;
+    Ok(Vec::from(bytes))
+}
+
+/// The hex SHA-256 a `sha256sum`-style listing gives for file `name`
+fn listed_sha256<'a>(sums: &'a str, name: &str) -> Option<&'a str> {
+    sums.lines().find_map(|line| {
+        let (hash, file) = line.trim().split_once(char::is_whitespace)?;
+        // `sha256sum --binary` marks names with a leading `*`
+        let file = file.trim_start();
+        let file = file.strip_prefix('*').unwrap_or(file);
+        (file == name && hash.len() == 64).then_some(hash)
+    })
+}
+
+/// Check `bytes` of `release`'s asset `name` against the checksum listed in its `sums_asset`,
+/// so nothing altered on the way is written to disk and run
+async fn verify_checksum(
+    release: &ReleaseInfo,
+    sums_asset: &str,
+    name: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    let sums = release
+        .assets
+        .iter()
+        .find(|a| a.name == sums_asset)
+        .ok_or_else(|| anyhow!("release {} has no {}", release.tag_name, sums_asset))?;
+    let sums = download_asset(sums).await?;
+    let sums = String::from_utf8_lossy(&sums);
+    let expected = listed_sha256(&sums, name).ok_or_else(|| {
+        anyhow!(
+            "{} of release {} doesn't list {}",
+            sums_asset,
+            release.tag_name,
+            name
+        )
+    })?;
+    let actual = hex::encode(ring::digest::digest(&ring::digest::SHA256, bytes));
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(anyhow!(
+            "{} from release {} doesn't match its published SHA-256 (expected {}, got {})",
+            name,
+            release.tag_name,
+            expected,
+            actual
+        ));
+    }
+    Ok(())
+}
 
-    fs::write(&local, &bytes).await?;
+/// Download this platform's asset from `release` to `dest` via a temp file, returning its version.
+/// The download is checked against the release's checksums before it's made executable.
+async fn install_release(release: &ReleaseInfo, dest: &Path) -> Result<String> {
+    let wanted = platform_asset_name();
+    let asset = release
+        .assets
+        .iter()
+        .find(|a| a.name == wanted)
+        .ok_or_else(|| anyhow!("no suitable yt-dlp asset for this platform: {}", wanted))?;
+
+    let bytes = download_asset(asset).await?;
+    verify_checksum(release, YT_DLP_CHECKSUMS, &asset.name, &bytes).await?;
+
+    let temp = dest.with_extension("download");
+    fs::write(&temp, &bytes).await?;
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(&local).await?.permissions();
+        let mut perms = fs::metadata(&temp).await?.permissions();
         perms.set_mode(0o755);
-        fs::set_permissions(&local, perms).await?;
+        fs::set_permissions(&temp, perms).await?;
     }
-    Ok(local)
+
+    let version = match crate::preflight::command_version(temp.as_os_str(), "--version").await {
+        Ok(version) if !version.is_empty() => version,
+        Ok(_) => {
+            fs::remove_file(&temp).await.ok();
+            return Err(anyhow!("downloaded yt-dlp printed no version"));
+        }
+        Err(e) => {
+            fs::remove_file(&temp).await.ok();
+            return Err(anyhow!("downloaded yt-dlp failed to run: {}", e));
+        }
+    };
+    fs::rename(&temp, dest)
+        .await
+        .with_context(|| format!("failed to replace {}", dest.display()))?;
+    Ok(version)
+}
+
+const FFMPEG_RELEASES_API: &str =
+    "https://api.github.com/repos/yt-dlp/FFmpeg-Builds/releases/latest";
+/// Asset of each FFmpeg-Builds release listing the SHA-256 of every archive
+const FFMPEG_CHECKSUMS: &str = "checksums.sha256";
+
+/// Serializes ffmpeg provisioning so concurrent downloads don't each fetch the archive
+static FFMPEG_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+fn ffmpeg_cache_dir() -> Result<PathBuf> {
+    let base = dirs::cache_dir().ok_or_else(|| anyhow!("no cache dir available on this system"))?;
+    Ok(base.join("lyre").join("ffmpeg"))
 }
 
-async fn ytdlp_extract_id(ytdlp: &PathBuf, url: &str) -> Result<String> {
-    let out = TokioCommand::new(ytdlp)
-        .arg("--print")
-        .arg("id")
+fn exe_name(name: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Static build published for this platform by yt-dlp's FFmpeg-Builds, if there is one
+fn ffmpeg_asset_name() -> Option<&'static str> {
+    if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        Some("ffmpeg-master-latest-linux64-gpl.tar.xz")
+    } else if cfg!(all(target_os = "linux", target_arch = "aarch64")) {
+        Some("ffmpeg-master-latest-linuxarm64-gpl.tar.xz")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("ffmpeg-master-latest-win64-gpl.zip")
+    } else {
+        None
+    }
+}
+
+/// The ffmpeg that would be used, without downloading one: PATH first, then the cached build
+pub async fn resolve_ffmpeg() -> Option<PathBuf> {
+    if let Ok(p) = which::which("ffmpeg") {
+        return Some(p);
+    }
+    let local = ffmpeg_cache_dir().ok()?.join(exe_name("ffmpeg"));
+    fs::try_exists(&local)
+        .await
+        .unwrap_or(false)
+        .then_some(local)
+}
+
+/// Find ffmpeg, downloading a static build (with ffprobe) into the cache dir if it isn't on PATH
+pub async fn ensure_ffmpeg() -> Result<PathBuf> {
+    if let Some(p) = resolve_ffmpeg().await {
+        return Ok(p);
+    }
+    let _guard = FFMPEG_LOCK.lock().await;
+    // Another task may have finished the download while we waited
+    if let Some(p) = resolve_ffmpeg().await {
+        return Ok(p);
+    }
+
+    let wanted = ffmpeg_asset_name().ok_or_else(|| {
+        anyhow!("ffmpeg is not on PATH and no static build is available for this platform")
+    })?;
+    let dir = ffmpeg_cache_dir()?;
+    fs::create_dir_all(&dir).await?;
+
+    let release: ReleaseInfo = HTTP
+        .get(FFMPEG_RELEASES_API)
+        .header(ACCEPT, "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let asset = release
+        .assets
+        .iter()
+        .find(|a| a.name == wanted)
+        .ok_or_else(|| anyhow!("no ffmpeg asset {} in release {}", wanted, release.tag_name))?;
+    tracing::info!("ffmpeg not found on PATH; downloading {}", asset.name);
+    let bytes = download_asset(asset).await?;
+    // Checked before anything is unpacked, let alone run
+    verify_checksum(&release, FFMPEG_CHECKSUMS, &asset.name, &bytes).await?;
+
+    // Unpack into a scratch dir beside the final location so the moves below are renames
+    let staging = dir.join("staging");
+    let _ = fs::remove_dir_all(&staging).await;
+    fs::create_dir_all(&staging).await?;
+    let archive = staging.join(wanted);
+    fs::write(&archive, &bytes).await?;
+    let result = unpack_ffmpeg(&archive, &staging, &dir).await;
+    let _ = fs::remove_dir_all(&staging).await;
+    result
+}
+
+/// Extract `archive` with the system `tar` (which reads .tar.xz and .zip alike) and move the
+/// ffmpeg and ffprobe binaries from it into `dest`
+async fn unpack_ffmpeg(archive: &Path, staging: &Path, dest: &Path) -> Result<PathBuf> {
+    let out = TokioCommand::new("tar")
+        .arg("-xf")
+        .arg(archive)
+        .arg("-C")
+        .arg(staging)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("running tar to unpack ffmpeg")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "failed to unpack ffmpeg: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+
+    let ffmpeg_name = exe_name("ffmpeg");
+    let unpacked = find_file(staging, &ffmpeg_name)
+        .ok_or_else(|| anyhow!("no {} in the downloaded archive", ffmpeg_name))?;
+    crate::preflight::command_version(unpacked.as_os_str(), "-version")
+        .await
+        .map_err(|e| anyhow!("downloaded ffmpeg failed to run: {}", e))?;
+
+    // yt-dlp looks for ffprobe next to ffmpeg; it only loses codec detection without it
+    let ffprobe_name = exe_name("ffprobe");
+    if let Some(ffprobe) = find_file(staging, &ffprobe_name) {
+        fs::rename(&ffprobe, dest.join(&ffprobe_name)).await?;
+    }
+    // ffmpeg goes last since its presence is what marks the install as complete
+    let installed = dest.join(&ffmpeg_name);
+    fs::rename(&unpacked, &installed).await?;
+    Ok(installed)
+}
+
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name) {
+                return Some(found);
+            }
+        } else if entry.file_name() == name {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Where a download is cached: `<source>/<id>.mp3` under the download folder. IDs are only
+/// unique per extractor (a Twitch VOD and a Mixcloud show could share one), so each extractor
+/// gets its own folder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CacheKey {
+    /// The extractor, lowercased, e.g. `youtube` or `mixcloud`
+    source: String,
+    id: String,
+}
+
+impl CacheKey {
+    fn path(&self, base: &Path) -> PathBuf {
+        base.join(&self.source).join(format!("{}.mp3", self.id))
+    }
+}
+
+/// Source folder for downloads whose ID couldn't be resolved
+const UNKNOWN_SOURCE: &str = "unknown";
+/// Folder under the download folder that running downloads work in, one subfolder each, so
+/// it's never mistaken for a source
+const JOBS_DIR: &str = ".jobs";
+
+/// Keep a name to characters that are safe in a file name everywhere
+fn file_safe(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn cache_key(extractor: &str, id: &str) -> Option<CacheKey> {
+    let id = file_safe(id);
+    let source = file_safe(extractor).to_ascii_lowercase();
+    if id.is_empty() {
+        return None;
+    }
+    Some(CacheKey {
+        source: if source.is_empty() {
+            UNKNOWN_SOURCE.to_string()
+        } else {
+            source
+        },
+        id,
+    })
+}
+
+/// A name no other download has used, even one started in the same instant
+fn unique_name(prefix: &str) -> String {
+    static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let n = NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    format!("{}-{}-{}-{}", prefix, std::process::id(), now, n)
+}
+
+/// Subset of yt-dlp's `--dump-json` output used for policy checks, display and naming the
+/// cached file; [`crate::metadata_cache`] keeps it in the song cache as JSON
+#[derive(Debug, Clone, Deserialize, serde::Serialize)]
+pub struct TrackMetadata {
+    pub title: String,
+    /// The extractor's ID for the media, e.g. a YouTube video ID
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Minimum viewer age reported by the extractor (18 for age-gated videos)
+    #[serde(default)]
+    pub age_limit: Option<u32>,
+    /// Length in seconds; absent for live streams
+    #[serde(default)]
+    pub duration: Option<f64>,
+    /// Music metadata, only set by some extractors (e.g. YouTube Music, Bandcamp)
+    #[serde(default)]
+    pub artist: Option<String>,
+    #[serde(default)]
+    pub track: Option<String>,
+    #[serde(default)]
+    pub album: Option<String>,
+    #[serde(default)]
+    pub uploader: Option<String>,
+    /// yt-dlp extractor that handled the URL, e.g. `Youtube`, `TwitchVod`, `Mixcloud`
+    #[serde(default)]
+    pub extractor_key: Option<String>,
+}
+
+impl TrackMetadata {
+    pub fn is_age_restricted(&self) -> bool {
+        self.age_limit.is_some_and(|age| age >= 18)
+    }
+
+    /// Whole streams and DJ sets rather than single songs
+    pub fn is_long_form(&self) -> bool {
+        matches!(
+            self.extractor_key.as_deref(),
+            Some("TwitchVod" | "Mixcloud")
+        )
+    }
+
+    /// Duration rounded to whole seconds, as stored in the database
+    pub fn duration_secs(&self) -> Option<i32> {
+        self.duration.map(|d| d.round() as i32)
+    }
+
+    fn cache_key(&self) -> Option<CacheKey> {
+        cache_key(self.extractor_key.as_deref()?, self.id.as_deref()?)
+    }
+}
+
+/// Run yt-dlp for `url`'s metadata. Most callers want [`crate::metadata_cache::lookup`], which
+/// only does this when the song cache has nothing fresh.
+pub async fn ytdlp_extract_metadata(url: &str) -> Result<TrackMetadata> {
+    let ytdlp = ensure_yt_dlp().await?;
+    let out = TokioCommand::new(&ytdlp)
+        .arg("--dump-json")
         .arg("--skip-download")
+        .arg("--no-playlist")
         .arg("-q")
         .arg(url)
         .stdin(Stdio::null())
         .output()
         .await
-        .context("running yt-dlp to extract id")?;
+        .context("running yt-dlp to extract metadata")?;
     if !out.status.success() {
         let stderr = String::from_utf8_lossy(&out.stderr);
         return Err(anyhow!(
-            "yt-dlp --print id failed with status: {}. Error: {}",
+            "yt-dlp --dump-json failed with status: {}. Error: {}",
             out.status,
             stderr.trim()
         ));
     }
-    let id = String::from_utf8_lossy(&out.stdout).trim().to_string();
-    if id.is_empty() {
-        return Err(anyhow!("empty id from yt-dlp"));
+    serde_json::from_slice(&out.stdout).context("parsing yt-dlp metadata")
+}
+
+/// One entry from a YouTube search, as listed by yt-dlp without resolving the video
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchResult {
+    pub url: String,
+    pub title: String,
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+/// Search YouTube for `query`, returning up to `limit` results in ranking order
+pub async fn ytdlp_search(query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+    let ytdlp = ensure_yt_dlp().await?;
+    let out = TokioCommand::new(&ytdlp)
+        .arg("--flat-playlist")
+        .arg("--dump-json")
+        .arg("-q")
+        .arg(format!("ytsearch{}:{}", limit, query))
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("running yt-dlp search")?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(anyhow!(
+            "yt-dlp search failed with status: {}. Error: {}",
+            out.status,
+            stderr.trim()
+        ));
     }
-    Ok(id)
+    // One JSON object per line
+    Ok(String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
 }
 
-pub async fn ytdlp_extract_title(url: &str) -> Result<String> {
+/// The first entries of a playlist, listed by yt-dlp without resolving each track
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaylistInfo {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub entries: Vec<PlaylistEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaylistEntry {
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+/// List up to `limit` entries of the playlist at `url`; a single track comes back with none
+pub async fn ytdlp_list_playlist(url: &str, limit: usize) -> Result<PlaylistInfo> {
     let ytdlp = ensure_yt_dlp().await?;
     let out = TokioCommand::new(&ytdlp)
-        .arg("--print")
-        .arg("title")
-        .arg("--skip-download")
+        .arg("--flat-playlist")
+        .arg("--dump-single-json")
+        .arg("--playlist-end")
+        .arg(limit.to_string())
         .arg("-q")
         .arg(url)
         .stdin(Stdio::null())
         .output()
         .await
-        .context("running yt-dlp to extract title")?;
+        .context("running yt-dlp to list playlist")?;
     if !out.status.success() {
         let stderr = String::from_utf8_lossy(&out.stderr);
         return Err(anyhow!(
-            "yt-dlp --print title failed with status: {}. Error: {}",
+            "yt-dlp --dump-single-json failed with status: {}. Error: {}",
             out.status,
             stderr.trim()
         ));
     }
-    let title = String::from_utf8_lossy(&out.stdout).trim().to_string();
-    if title.is_empty() {
-        return Err(anyhow!("empty title from yt-dlp"));
+    serde_json::from_slice(&out.stdout).context("parsing yt-dlp playlist")
+}
+
+/// A podcast RSS feed as read by yt-dlp's generic extractor
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedInfo {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedEntry {
+    /// Enclosure (audio file) URL
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Publication time as a Unix timestamp, from `pubDate`
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+    /// From `itunes:duration`
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+/// List the episodes in a podcast RSS feed without downloading any of them
+pub async fn ytdlp_extract_feed(url: &str) -> Result<FeedInfo> {
+    let ytdlp = ensure_yt_dlp().await?;
+    let out = TokioCommand::new(&ytdlp)
+        .arg("--flat-playlist")
+        .arg("--dump-single-json")
+        .arg("-q")
+        .arg(url)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("running yt-dlp to read feed")?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(anyhow!(
+            "yt-dlp --dump-single-json failed with status: {}. Error: {}",
+            out.status,
+            stderr.trim()
+        ));
     }
-    Ok(title)
+    serde_json::from_slice(&out.stdout).context("parsing yt-dlp feed")
 }
 
 fn download_base_dir() -> Result<PathBuf> {
@@ -178,50 +678,240 @@ pub fn resolved_download_base_dir() -> Result<PathBuf> {
     download_base_dir()
 }
 
+/// The folder running downloads work in; whatever is left there after a crash is an orphan
+pub fn jobs_dir() -> Result<PathBuf> {
+    Ok(download_base_dir()?.join(JOBS_DIR))
+}
+
+/// Where a file cached by older versions, which kept every download directly in the download
+/// folder, belongs now. YouTube downloads were named by their bare 11 character video ID and
+/// everything else `<extractor>-<id>`; filtered renders (`<id>.<filters>.mp3`) follow their
+/// download. Returns the source folder and the file's name within it.
+fn legacy_cache_location(file_name: &str) -> (String, String) {
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    if stem.len() == 11 {
+        return ("youtube".to_string(), file_name.to_string());
+    }
+    match stem.split_once('-') {
+        Some((source, id))
+            if source != "ts"
+                && !id.is_empty()
+                && source
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()) =>
+        {
+            (
+                source.to_string(),
+                file_name[source.len() + 1..].to_string(),
+            )
+        }
+        _ => (UNKNOWN_SOURCE.to_string(), file_name.to_string()),
+    }
+}
+
+/// Move downloads cached in the old flat layout into their source folders, and point the song
+/// cache at their new paths. Leftover `job-*` folders move under the jobs folder. Returns how
+/// many files were moved.
+pub async fn migrate_flat_cache() -> Result<usize> {
+    let base = download_base_dir()?;
+    let Ok(mut entries) = fs::read_dir(&base).await else {
+        return Ok(0);
+    };
+    let mut moved = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let from = entry.path();
+        let file_type = entry.file_type().await?;
+        if file_type.is_dir() && name.starts_with("job-") {
+            let jobs = base.join(JOBS_DIR);
+            fs::create_dir_all(&jobs).await?;
+            fs::rename(&from, jobs.join(&name)).await?;
+            continue;
+        }
+        if !file_type.is_file() || !name.ends_with(".mp3") {
+            continue;
+        }
+        let (source, file_name) = legacy_cache_location(&name);
+        let dir = base.join(&source);
+        fs::create_dir_all(&dir).await?;
+        let to = dir.join(&file_name);
+        if fs::try_exists(&to).await.unwrap_or(false) {
+            // Already downloaded again in the new layout
+            fs::remove_file(&from).await?;
+        } else {
+            fs::rename(&from, &to).await?;
+        }
+        moved.push((from, to));
+    }
+    if moved.is_empty() {
+        return Ok(0);
+    }
+
+    let mut db_conn = crate::database::establish_connection();
+    for (from, to) in &moved {
+        if let Err(e) = crate::database::models::SongCache::move_file(
+            &mut db_conn,
+            &from.to_string_lossy(),
+            &to.to_string_lossy(),
+        ) {
+            tracing::warn!("Failed to update song cache for {}: {}", to.display(), e);
+        }
+    }
+    Ok(moved.len())
+}
+
 // removed blocking download_mp3 in favor of spawn_download_mp3 used by /play
 
-#[derive(Clone, Debug)]
+/// Where a download is up to. yt-dlp's own bar reaches 100% before ffmpeg has transcoded the
+/// file, which can take a while for long tracks, so that part is reported separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadPhase {
+    Downloading,
+    /// ffmpeg is extracting and converting the audio
+    Converting,
+    /// The file is in the cache and has been probed
+    Ready,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct DownloadProgress {
+    pub phase: DownloadPhase,
+    /// Progress through the download; stays at 100 once it is converting
     pub percent: u8,
 }
 
+impl DownloadProgress {
+    const READY: DownloadProgress = DownloadProgress {
+        phase: DownloadPhase::Ready,
+        percent: 100,
+    };
+}
+
+/// A finished download and what was probed from the file itself
+#[derive(Clone, Debug)]
+pub struct DownloadResult {
+    pub path: PathBuf,
+    /// Length in seconds; `None` if ffprobe isn't available or couldn't read the file
+    pub duration: Option<f64>,
+    /// Size in bytes
+    pub file_size: u64,
+    /// Container format as ffprobe names it, e.g. `mp3`
+    pub format: Option<String>,
+}
+
+impl DownloadResult {
+    /// Duration rounded to whole seconds, as stored in the database
+    pub fn duration_secs(&self) -> Option<i32> {
+        self.duration.map(|d| d.round() as i32)
+    }
+
+    /// Size as stored in the song cache, which holds it in a 32-bit column
+    pub fn file_size_i32(&self) -> Option<i32> {
+        i32::try_from(self.file_size).ok()
+    }
+}
+
+/// Which downloads go first when several compete
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DownloadLane {
+    /// A track someone is waiting on: a `/play` request, or the next track in an idle guild
+    Now,
+    /// Tracks queued behind others, e.g. the rest of an imported share. These wait for a free
+    /// slot, don't start while a `Now` download runs, and are suspended if one starts.
+    Background,
+}
+
+/// Background downloads allowed at once
+const MAX_BACKGROUND_DOWNLOADS: usize = 2;
+
+static BACKGROUND_SLOTS: Lazy<tokio::sync::Semaphore> =
+    Lazy::new(|| tokio::sync::Semaphore::new(MAX_BACKGROUND_DOWNLOADS));
+
+/// How many `Now` downloads are running
+static URGENT_DOWNLOADS: Lazy<watch::Sender<usize>> = Lazy::new(|| watch::channel(0).0);
+
+/// Counts a `Now` download as running until dropped
+struct UrgentDownload;
+
+impl UrgentDownload {
+    fn begin() -> Self {
+        URGENT_DOWNLOADS.send_modify(|running| *running += 1);
+        UrgentDownload
+    }
+}
+
+impl Drop for UrgentDownload {
+    fn drop(&mut self) {
+        URGENT_DOWNLOADS.send_modify(|running| *running = running.saturating_sub(1));
+    }
+}
+
+/// The next change in the number of `Now` downloads; never resolves without a watch
+async fn urgent_changed(watch: &mut Option<watch::Receiver<usize>>) -> Option<usize> {
+    match watch {
+        Some(rx) => {
+            rx.changed().await.ok()?;
+            Some(*rx.borrow_and_update())
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Stop or continue a background yt-dlp process. Only Unix can suspend a process; elsewhere
+/// background downloads are just held back from starting.
+async fn set_suspended(pid: Option<u32>, suspended: bool) {
+    #[cfg(unix)]
+    if let Some(pid) = pid {
+        let signal = if suspended { "-STOP" } else { "-CONT" };
+        if let Err(e) = TokioCommand::new("kill")
+            .arg(signal)
+            .arg(pid.to_string())
+            .status()
+            .await
+        {
+            tracing::debug!("Failed to signal yt-dlp {}: {}", pid, e);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (pid, suspended);
+}
+
 pub fn spawn_download_mp3(
     url: String,
+    lane: DownloadLane,
 ) -> (
     mpsc::UnboundedReceiver<DownloadProgress>,
-    JoinHandle<Result<PathBuf>>,
+    JoinHandle<Result<DownloadResult>>,
 ) {
     let (tx, rx) = mpsc::unbounded_channel();
     let handle = tokio::spawn(async move {
         let ytdlp = ensure_yt_dlp().await?;
+        let ffmpeg = ensure_ffmpeg().await?;
         let base = download_base_dir()?;
         fs::create_dir_all(&base).await?;
-        // Resolve a stable video ID for caching; fall back to a timestamp if it fails.
-        let vid = match ytdlp_extract_id(&ytdlp, &url).await {
-            Ok(v) => v,
-            Err(_) => format!(
-                "ts-{}",
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_nanos()
-            ),
+        // Name the file after the media's ID, from the metadata `/play` has usually just looked
+        // up; fall back to a one-off name without one
+        let key = match crate::metadata_cache::lookup(&url)
+            .await
+            .ok()
+            .and_then(|metadata| metadata.cache_key())
+        {
+            Some(key) => key,
+            None => CacheKey {
+                source: UNKNOWN_SOURCE.to_string(),
+                id: unique_name("ts"),
+            },
         };
-        let cached = base.join(format!("{}.mp3", vid));
+        let cached = key.path(&base);
         if fs::try_exists(&cached).await.unwrap_or(false) {
-            let _ = tx.send(DownloadProgress { percent: 100 });
-            return Ok(cached);
+            let result = probe_download(cached).await;
+            let _ = tx.send(DownloadProgress::READY);
+            return result;
         }
+        fs::create_dir_all(base.join(&key.source)).await?;
         // Create a unique subdirectory for this download to avoid cross-task collisions.
-        let unique = {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_nanos();
-            format!("job-{}", now)
-        };
-        let dir = base.join(unique);
-        fs::create_dir_all(&dir).await?;
+        let dir = JobDir::create().await?;
 
         let mut cmd = TokioCommand::new(&ytdlp);
         cmd.arg("-f")
@@ -233,53 +923,81 @@ pub fn spawn_download_mp3(
             .arg("0") // Best quality
             .arg("--postprocessor-args")
             .arg("ffmpeg:-ar 48000 -ac 2") // Force 48kHz stereo (Discord's preferred format)
+            .arg("--ffmpeg-location")
+            .arg(&ffmpeg)
             .arg("--no-playlist")
             .arg("--newline")
             .arg("-o")
-            .arg(dir.join("%(id)s.%(ext)s").to_string_lossy().to_string())
+            .arg(dir.0.join("%(id)s.%(ext)s").to_string_lossy().to_string())
             .arg(url)
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+            .stderr(Stdio::piped())
+            // A cancelled download takes yt-dlp down with it
+            .kill_on_drop(true);
+
+        // Hold the lane for the rest of the download
+        let (_urgent, _slot, mut urgent_watch) = match lane {
+            DownloadLane::Now => (Some(UrgentDownload::begin()), None, None),
+            DownloadLane::Background => {
+                let slot = BACKGROUND_SLOTS.acquire().await?;
+                let mut urgent = URGENT_DOWNLOADS.subscribe();
+                urgent.wait_for(|running| *running == 0).await?;
+                (None, Some(slot), Some(urgent))
+            }
+        };
 
         let mut child = cmd.spawn().context("spawning yt-dlp")?;
+        let pid = child.id();
 
+        // Progress and post-processor lines go to stdout and errors to stderr, so read both
+        let (line_tx, mut lines) = mpsc::unbounded_channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, line_tx.clone());
+        }
         if let Some(stderr) = child.stderr.take() {
-            let mut reader = BufReader::new(stderr).lines();
-            let mut last_sent = 255u8; // impossible value to force first update
-            let mut error_lines = Vec::new();
-            while let Some(Ok(line)) = reader.next_line().await.transpose() {
-                if let Some(pct) = parse_percent(&line)
-                    && pct != last_sent
-                {
-                    let _ = tx.send(DownloadProgress { percent: pct });
-                    last_sent = pct;
-                } else if line.contains("ERROR") || line.contains("error") {
-                    error_lines.push(line);
+            forward_lines(stderr, line_tx.clone());
+        }
+        drop(line_tx);
+
+        let mut last_sent = None;
+        let mut error_lines = Vec::new();
+        loop {
+            let line = tokio::select! {
+                line = lines.recv() => line,
+                Some(running) = urgent_changed(&mut urgent_watch) => {
+                    set_suspended(pid, running > 0).await;
+                    continue;
+                }
+            };
+            let Some(line) = line else {
+                break;
+            };
+            if let Some(progress) = parse_progress(&line) {
+                if last_sent.as_ref() != Some(&progress) {
+                    let _ = tx.send(progress.clone());
+                    last_sent = Some(progress);
                 }
+            } else if line.contains("ERROR") || line.contains("error") {
+                error_lines.push(line);
             }
+        }
 
-            let status = child.wait().await.context("waiting for yt-dlp")?;
-            if !status.success() {
-                let error_msg = if error_lines.is_empty() {
-                    format!("yt-dlp failed with status: {status}")
-                } else {
-                    format!(
-                        "yt-dlp failed with status: {status}. Errors: {}",
-                        error_lines.join("; ")
-                    )
-                };
-                return Err(anyhow!(error_msg));
-            }
-        } else {
-            let status = child.wait().await.context("waiting for yt-dlp")?;
-            if !status.success() {
-                return Err(anyhow!("yt-dlp failed with status: {status}"));
-            }
+        let status = child.wait().await.context("waiting for yt-dlp")?;
+        if !status.success() {
+            let error_msg = if error_lines.is_empty() {
+                format!("yt-dlp failed with status: {status}")
+            } else {
+                format!(
+                    "yt-dlp failed with status: {status}. Errors: {}",
+                    error_lines.join("; ")
+                )
+            };
+            return Err(anyhow!(error_msg));
         }
 
         // Find produced mp3 in the unique dir
-        let mut entries = fs::read_dir(&dir).await?;
+        let mut entries = fs::read_dir(&dir.0).await?;
         let mut newest: Option<(PathBuf, std::time::SystemTime)> = None;
         while let Some(e) = entries.next_entry().await? {
             let p = e.path();
@@ -303,13 +1021,286 @@ pub fn spawn_download_mp3(
         } else {
             p.clone()
         };
-        let _ = fs::remove_dir_all(&dir).await;
-        Ok(final_path)
+        drop(dir);
+        let result = probe_download(final_path).await;
+        let _ = tx.send(DownloadProgress::READY);
+        result
     });
 
     (rx, handle)
 }
 
+/// A download's scratch directory, removed when the download finishes, fails or is cancelled
+pub struct JobDir(PathBuf);
+
+impl JobDir {
+    /// A fresh directory under the download folder's jobs folder
+    pub async fn create() -> Result<Self> {
+        let dir = JobDir(download_base_dir()?.join(JOBS_DIR).join(unique_name("job")));
+        fs::create_dir_all(&dir.0).await?;
+        Ok(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+/// The cached download of media `id` from `source`, for sources other than yt-dlp that name
+/// their own files
+pub async fn cached_download(source: &str, id: &str) -> Option<DownloadResult> {
+    let cached = cache_key(source, id)?.path(&download_base_dir().ok()?);
+    if !fs::try_exists(&cached).await.unwrap_or(false) {
+        return None;
+    }
+    probe_download(cached).await.ok()
+}
+
+/// Convert `input` to the 48 kHz stereo MP3 the player expects, like yt-dlp's downloads, and
+/// keep it in the cache as media `id` from `source`
+pub async fn cache_converted(input: &Path, source: &str, id: &str) -> Result<DownloadResult> {
+    let key = cache_key(source, id).ok_or_else(|| anyhow!("no cache name for {}", id))?;
+    let cached = key.path(&download_base_dir()?);
+    fs::create_dir_all(download_base_dir()?.join(&key.source)).await?;
+    let ffmpeg = ensure_ffmpeg().await?;
+    // Convert to a partial name so a half-written file is never picked up as cached, and never
+    // beside the input, which may be in a folder that isn't ours to write to
+    let partial = cached.with_extension("part.mp3");
+    let out = TokioCommand::new(&ffmpeg)
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-y")
+        .arg("-i")
+        .arg(input)
+        .arg("-vn")
+        .arg("-codec:a")
+        .arg("libmp3lame")
+        .arg("-q:a")
+        .arg("0")
+        .arg("-ar")
+        .arg("48000")
+        .arg("-ac")
+        .arg("2")
+        .arg(&partial)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("running ffmpeg to convert a download")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "ffmpeg couldn't convert the file: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    if fs::rename(&partial, &cached).await.is_err() {
+        fs::copy(&partial, &cached).await?;
+    }
+    probe_download(cached).await
+}
+
+impl Drop for JobDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The ffprobe to use: PATH first, then the one unpacked beside the cached ffmpeg build
+async fn resolve_ffprobe() -> Option<PathBuf> {
+    if let Ok(p) = which::which("ffprobe") {
+        return Some(p);
+    }
+    let local = ffmpeg_cache_dir().ok()?.join(exe_name("ffprobe"));
+    fs::try_exists(&local)
+        .await
+        .unwrap_or(false)
+        .then_some(local)
+}
+
+#[derive(Debug, Deserialize)]
+struct ProbeOutput {
+    format: ProbeFormat,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProbeFormat {
+    #[serde(default)]
+    format_name: Option<String>,
+    /// ffprobe prints numbers as strings
+    #[serde(default)]
+    duration: Option<String>,
+}
+
+/// Size, duration and format of a downloaded file. Only the size is required; a missing or
+/// failing ffprobe leaves the rest unknown so playback isn't held up by it.
+async fn probe_download(path: PathBuf) -> Result<DownloadResult> {
+    let file_size = fs::metadata(&path)
+        .await
+        .with_context(|| format!("reading {}", path.display()))?
+        .len();
+    let format = match resolve_ffprobe().await {
+        Some(ffprobe) => match ffprobe_format(&ffprobe, &path).await {
+            Ok(format) => Some(format),
+            Err(e) => {
+                tracing::debug!("Could not probe {}: {}", path.display(), e);
+                None
+            }
+        },
+        None => None,
+    };
+    Ok(DownloadResult {
+        duration: format
+            .as_ref()
+            .and_then(|f| f.duration.as_deref())
+            .and_then(|d| d.trim().parse::<f64>().ok())
+            .filter(|d| d.is_finite() && *d > 0.0),
+        format: format.and_then(|f| f.format_name),
+        file_size,
+        path,
+    })
+}
+
+async fn ffprobe_format(ffprobe: &Path, path: &Path) -> Result<ProbeFormat> {
+    let out = TokioCommand::new(ffprobe)
+        .arg("-v")
+        .arg("error")
+        .arg("-show_entries")
+        .arg("format=format_name,duration")
+        .arg("-of")
+        .arg("json")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("running ffprobe")?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(anyhow!(
+            "ffprobe failed with status: {}. Error: {}",
+            out.status,
+            stderr.trim()
+        ));
+    }
+    let probed: ProbeOutput = serde_json::from_slice(&out.stdout).context("parsing ffprobe")?;
+    Ok(probed.format)
+}
+
+/// Tags and length read from an audio file; any of them may be missing
+#[derive(Debug, Default)]
+pub struct FileTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagProbeOutput {
+    #[serde(default)]
+    format: Option<TagProbeSection>,
+    #[serde(default)]
+    streams: Vec<TagProbeSection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagProbeSection {
+    #[serde(default)]
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Read `path`'s title, artist, album and duration with ffprobe. Ogg files keep their tags on
+/// the audio stream rather than the container, so both are looked at.
+pub async fn probe_tags(path: &Path) -> Result<FileTags> {
+    let ffprobe = resolve_ffprobe()
+        .await
+        .ok_or_else(|| anyhow!("ffprobe isn't available"))?;
+    let out = TokioCommand::new(&ffprobe)
+        .arg("-v")
+        .arg("error")
+        .arg("-show_entries")
+        .arg("format=duration:format_tags:stream_tags")
+        .arg("-of")
+        .arg("json")
+        .arg(path)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("running ffprobe")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    let probed: TagProbeOutput = serde_json::from_slice(&out.stdout).context("parsing ffprobe")?;
+    let sections: Vec<&TagProbeSection> = probed.format.iter().chain(&probed.streams).collect();
+    // Tag names are upper case in Vorbis comments and lower case in ID3
+    let tag = |name: &str| {
+        sections.iter().find_map(|section| {
+            section
+                .tags
+                .iter()
+                .find(|(key, value)| key.eq_ignore_ascii_case(name) && !value.trim().is_empty())
+                .map(|(_, value)| value.trim().to_string())
+        })
+    };
+    Ok(FileTags {
+        title: tag("title"),
+        artist: tag("artist"),
+        album: tag("album"),
+        duration: sections
+            .iter()
+            .filter_map(|s| s.duration.as_deref())
+            .find_map(|d| d.trim().parse::<f64>().ok())
+            .filter(|d| d.is_finite() && *d > 0.0),
+    })
+}
+
+fn forward_lines<R>(reader: R, tx: mpsc::UnboundedSender<String>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// yt-dlp post-processors that run ffmpeg over the downloaded file
+const CONVERT_PREFIXES: &[&str] = &[
+    "[ExtractAudio]",
+    "[ffmpeg]",
+    "[FixupM4a]",
+    "[FixupM3u8]",
+    "[Merger]",
+    "[Metadata]",
+];
+
+fn parse_progress(line: &str) -> Option<DownloadProgress> {
+    let line = line.trim_start();
+    if CONVERT_PREFIXES
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+    {
+        return Some(DownloadProgress {
+            phase: DownloadPhase::Converting,
+            percent: 100,
+        });
+    }
+    parse_percent(line).map(|percent| DownloadProgress {
+        phase: DownloadPhase::Downloading,
+        percent,
+    })
+}
+
 fn parse_percent(line: &str) -> Option<u8> {
     // Try to find a pattern like "[download]   42.3%" and parse percent
     if let Some(idx) = line.find('%') {
@@ -322,3 +1313,19 @@ fn parse_percent(line: &str) -> Option<u8> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_listings_are_matched_by_exact_name() {
+        let linux = "a".repeat(64);
+        let macos = "b".repeat(64);
+        let sums = format!("{linux}  yt-dlp_linux\n{macos} *yt-dlp_macos\n");
+        assert_eq!(listed_sha256(&sums, "yt-dlp_linux"), Some(linux.as_str()));
+        assert_eq!(listed_sha256(&sums, "yt-dlp_macos"), Some(macos.as_str()));
+        assert_eq!(listed_sha256(&sums, "yt-dlp"), None);
+        assert_eq!(listed_sha256("abc  yt-dlp", "yt-dlp"), None);
+    }
+}
@@ -1,12 +1,59 @@
-use actix_web::{
-    Error as ActixError, FromRequest, HttpMessage, HttpRequest, dev::Payload,
-    error::ErrorUnauthorized,
-};
+use actix_web::{Error as ActixError, FromRequest, HttpMessage, HttpRequest, dev::Payload};
 use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::future::{Ready, ready};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::api::error::ApiError;
 
 const DISCORD_API_BASE: &str = "https://discord.com/api/v10";
+/// A token's user and guilds are reused this long before Discord is asked again
+const SESSION_TTL: Duration = Duration::from_secs(5 * 60);
+/// While Discord is rate limiting us, cached sessions up to this old are served instead
+const STALE_SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+/// A 429 asking us to wait no longer than this is waited out and retried once
+const MAX_INLINE_RETRY: Duration = Duration::from_secs(2);
+/// This many 429s in a row stop calls to Discord for a while
+const CIRCUIT_BREAK_AFTER: u32 = 3;
+const MIN_CIRCUIT_BREAK: Duration = Duration::from_secs(30);
+
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent("lyre-bot/0.1")
+        .timeout(Duration::from_secs(15))
+        .build()
+        .expect("client")
+});
+
+static LIMITER: Lazy<Mutex<RateLimiter>> = Lazy::new(|| Mutex::new(RateLimiter::default()));
+
+/// Users and guilds looked up per access token; held in memory only
+static SESSIONS: Lazy<Mutex<HashMap<String, CachedSession>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Default)]
+struct RateLimiter {
+    consecutive_429s: u32,
+    blocked_until: Option<Instant>,
+}
+
+struct CachedSession {
+    user: AuthenticatedUser,
+    fetched_at: Instant,
+}
+
+/// Discord answered 429, or we're holding off after several in a row
+#[derive(Debug, Error)]
+#[error("Discord is rate limiting requests; try again in {}s", .retry_after.as_secs().max(1))]
+pub struct RateLimited {
+    pub retry_after: Duration,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiscordUser {
@@ -28,7 +75,6 @@ pub struct UserGuild {
 
 #[derive(Debug, Clone)]
 pub struct AuthenticatedUser {
-    #[allow(dead_code)]
     pub user: DiscordUser,
     pub guilds: Vec<UserGuild>,
 }
@@ -46,23 +92,7 @@ impl FromRequest for AuthenticatedUser {
         {
             // For demo purposes, accept any token that starts with "demo_"
             if token.starts_with("demo_") {
-                let user = DiscordUser {
-                    id: "123456789".to_string(),
-                    username: "demouser".to_string(),
-                    discriminator: "0000".to_string(),
-                    avatar: None,
-                    global_name: Some("Demo User".to_string()),
-                };
-
-                let guilds = vec![UserGuild {
-                    id: "987654321".to_string(),
-                    name: "Demo Server".to_string(),
-                    icon: None,
-                    owner: true,
-                    permissions: "8".to_string(), // Administrator
-                }];
-
-                return ready(Ok(AuthenticatedUser { user, guilds }));
+                return ready(Ok(demo_user_for(token)));
             }
 
             // Store the token in the request extensions so endpoints can validate it
@@ -83,12 +113,51 @@ impl FromRequest for AuthenticatedUser {
             return ready(Ok(AuthenticatedUser { user, guilds }));
         }
 
-        ready(Err(ErrorUnauthorized(
-            "Missing or invalid Authorization header",
-        )))
+        ready(Err(ApiError::Unauthorized(
+            "Missing or invalid Authorization header".to_string(),
+        )
+        .into()))
     }
 }
 
+/// The stand-in user behind `demo_` tokens: owner of a single "Demo Server"
+pub fn demo_user() -> AuthenticatedUser {
+    let user = DiscordUser {
+        id: "123456789".to_string(),
+        username: "demouser".to_string(),
+        discriminator: "0000".to_string(),
+        avatar: None,
+        global_name: Some("Demo User".to_string()),
+    };
+
+    let guilds = vec![UserGuild {
+        id: "987654321".to_string(),
+        name: "Demo Server".to_string(),
+        icon: None,
+        owner: true,
+        permissions: "8".to_string(), // Administrator
+    }];
+
+    AuthenticatedUser { user, guilds }
+}
+
+/// The demo identity behind a `demo_` token: `demo_member_` tokens are an ordinary member of
+/// the demo server (voice only, can't manage it), any other `demo_` token its owner
+pub fn demo_user_for(token: &str) -> AuthenticatedUser {
+    if !token.starts_with("demo_member_") {
+        return demo_user();
+    }
+    let mut member = demo_user();
+    member.user.id = "223456789".to_string();
+    member.user.username = "demomember".to_string();
+    member.user.global_name = Some("Demo Member".to_string());
+    for guild in &mut member.guilds {
+        guild.owner = false;
+        guild.permissions = "1048576".to_string(); // Use Voice Activity
+    }
+    member
+}
+
 // Helper function to get authenticated user from request extensions (set by middleware)
 pub fn get_authenticated_user_from_extensions(req: &HttpRequest) -> Result<AuthenticatedUser> {
     req.extensions()
@@ -97,52 +166,144 @@ pub fn get_authenticated_user_from_extensions(req: &HttpRequest) -> Result<Authe
         .ok_or_else(|| anyhow!("No authenticated user found in request extensions"))
 }
 
-/// Validate a Discord access token by calling Discord's API
-pub async fn validate_discord_token(access_token: &str) -> Result<DiscordUser> {
-    let client = reqwest::Client::new();
+/// The HTTP client shared by every call to Discord's REST API
+pub fn http() -> &'static reqwest::Client {
+    &HTTP
+}
 
-    let response = client
-        .get(format!("{}/users/@me", DISCORD_API_BASE))
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("User-Agent", "lyre-bot/0.1")
-        .send()
-        .await
-        .map_err(|e| anyhow!("Failed to call Discord API: {}", e))?;
+/// The user and guilds behind an access token, from the cache while it's fresh
+pub async fn authenticate(access_token: &str) -> Result<AuthenticatedUser> {
+    if let Some(user) = cached_session(access_token, SESSION_TTL) {
+        return Ok(user);
+    }
+    refresh_session(access_token).await
+}
 
-    if !response.status().is_success() {
-        return Err(anyhow!("Discord API returned error: {}", response.status()));
+/// Look the token's user and guilds up again, e.g. after the user joins a server. If Discord is
+/// rate limiting us, a recent cached copy is returned instead.
+pub async fn refresh_session(access_token: &str) -> Result<AuthenticatedUser> {
+    let fetched = async {
+        let user = validate_discord_token(access_token).await?;
+        let guilds = get_user_guilds(access_token).await?;
+        Ok::<_, anyhow::Error>(AuthenticatedUser { user, guilds })
+    }
+    .await;
+    match fetched {
+        Ok(user) => {
+            if let Ok(mut sessions) = SESSIONS.lock() {
+                sessions.retain(|_, s| s.fetched_at.elapsed() < STALE_SESSION_TTL);
+                sessions.insert(
+                    access_token.to_string(),
+                    CachedSession {
+                        user: user.clone(),
+                        fetched_at: Instant::now(),
+                    },
+                );
+            }
+            Ok(user)
+        }
+        Err(e) if e.is::<RateLimited>() => cached_session(access_token, STALE_SESSION_TTL).ok_or(e),
+        Err(e) => Err(e),
     }
+}
 
-    let user: DiscordUser = response
-        .json()
-        .await
-        .map_err(|e| anyhow!("Failed to parse Discord user response: {}", e))?;
+fn cached_session(access_token: &str, max_age: Duration) -> Option<AuthenticatedUser> {
+    let sessions = SESSIONS.lock().ok()?;
+    let session = sessions.get(access_token)?;
+    (session.fetched_at.elapsed() < max_age).then(|| session.user.clone())
+}
 
-    Ok(user)
+/// Validate a Discord access token by calling Discord's API
+pub async fn validate_discord_token(access_token: &str) -> Result<DiscordUser> {
+    discord_get("/users/@me", access_token)
+        .await
+        .map_err(|e| e.context("Failed to fetch Discord user"))
 }
 
 /// Get user's guilds from Discord API
 pub async fn get_user_guilds(access_token: &str) -> Result<Vec<UserGuild>> {
-    let client = reqwest::Client::new();
-
-    let response = client
-        .get(format!("{}/users/@me/guilds", DISCORD_API_BASE))
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("User-Agent", "lyre-bot/0.1")
-        .send()
+    discord_get("/users/@me/guilds", access_token)
         .await
-        .map_err(|e| anyhow!("Failed to call Discord API: {}", e))?;
+        .map_err(|e| e.context("Failed to fetch Discord guilds"))
+}
+
+/// GET `path` from Discord as the token's user, waiting out a short 429 once and holding off
+/// entirely after several in a row
+async fn discord_get<T: DeserializeOwned>(path: &str, access_token: &str) -> Result<T> {
+    let mut retried = false;
+    loop {
+        if let Some(retry_after) = circuit_open() {
+            return Err(RateLimited { retry_after }.into());
+        }
+
+        let response = HTTP
+            .get(format!("{}{}", DISCORD_API_BASE, path))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to call Discord API: {}", e))?;
 
-    if !response.status().is_success() {
-        return Err(anyhow!("Discord API returned error: {}", response.status()));
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = retry_after(&response);
+            record_rate_limit(retry_after);
+            if !retried && retry_after <= MAX_INLINE_RETRY {
+                retried = true;
+                tokio::time::sleep(retry_after).await;
+                continue;
+            }
+            return Err(RateLimited { retry_after }.into());
+        }
+        if let Ok(mut limiter) = LIMITER.lock() {
+            limiter.consecutive_429s = 0;
+        }
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Discord API returned error: {}", response.status()));
+        }
+        return response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Discord response: {}", e));
     }
+}
 
-    let guilds: Vec<UserGuild> = response
-        .json()
-        .await
-        .map_err(|e| anyhow!("Failed to parse Discord guilds response: {}", e))?;
+/// How long Discord asked us to wait, from its `Retry-After` header (seconds, possibly
+/// fractional)
+fn retry_after(response: &reqwest::Response) -> Duration {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+        .unwrap_or(Duration::from_secs(1))
+}
 
-    Ok(guilds)
+fn record_rate_limit(retry_after: Duration) {
+    let Ok(mut limiter) = LIMITER.lock() else {
+        return;
+    };
+    limiter.consecutive_429s += 1;
+    if limiter.consecutive_429s >= CIRCUIT_BREAK_AFTER {
+        let pause = retry_after.max(MIN_CIRCUIT_BREAK);
+        tracing::warn!(
+            "Discord returned {} rate limits in a row; pausing API calls for {}s",
+            limiter.consecutive_429s,
+            pause.as_secs()
+        );
+        limiter.blocked_until = Some(Instant::now() + pause);
+        limiter.consecutive_429s = 0;
+    }
+}
+
+/// Time left before calls to Discord may resume, while the circuit is open
+fn circuit_open() -> Option<Duration> {
+    let limiter = LIMITER.lock().ok()?;
+    let remaining = limiter
+        .blocked_until?
+        .checked_duration_since(Instant::now())?;
+    (!remaining.is_zero()).then_some(remaining)
 }
 
 /// Check if user has permission to control bot in a specific guild
@@ -158,6 +319,16 @@ pub fn user_can_control_guild(user_guilds: &[UserGuild], guild_id: &str) -> bool
     })
 }
 
+/// Whether the user owns, administers or manages `guild_id`
+pub fn user_can_manage_guild(user_guilds: &[UserGuild], guild_id: &str) -> bool {
+    user_guilds.iter().any(|guild| {
+        guild.id == guild_id
+            && (guild.owner
+                || has_permission(&guild.permissions, 0x8)
+                || has_permission(&guild.permissions, 0x20))
+    })
+}
+
 fn has_permission(permissions_str: &str, permission_bit: u64) -> bool {
     if let Ok(permissions) = permissions_str.parse::<u64>() {
         (permissions & permission_bit) != 0
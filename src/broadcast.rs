@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use once_cell::sync::OnceCell;
+use serenity::all::{ChannelId, Context, CreateEmbed, CreateMessage, GuildId, ShardManager};
+use serenity::http::Http;
+use songbird::Songbird;
+
+use crate::database::establish_connection;
+use crate::database::models::{GuildSettings, VoiceConnection};
+
+/// Longest announcement accepted, leaving room in Discord's 4096 character embed description
+pub const MAX_ANNOUNCEMENT_LEN: usize = 2000;
+
+/// Discord handles for code outside the gateway event handlers (e.g. the HTTP API), set on `ready`
+static DISCORD: OnceCell<(Arc<Http>, Arc<Songbird>)> = OnceCell::new();
+/// The gateway context, for the few API paths that drive the same code as slash commands
+static CONTEXT: OnceCell<Context> = OnceCell::new();
+/// The client's shards, set before it connects
+static SHARDS: OnceCell<Arc<ShardManager>> = OnceCell::new();
+
+pub fn install(ctx: &Context, manager: Arc<Songbird>) {
+    let _ = DISCORD.set((ctx.http.clone(), manager));
+    let _ = CONTEXT.set(ctx.clone());
+}
+
+pub fn install_shards(shards: Arc<ShardManager>) {
+    let _ = SHARDS.set(shards);
+}
+
+/// The client's shard manager, when running with a Discord token
+pub fn shard_manager() -> Option<Arc<ShardManager>> {
+    SHARDS.get().cloned()
+}
+
+/// The gateway context, once connected to Discord
+pub fn context() -> Option<Context> {
+    CONTEXT.get().cloned()
+}
+
+/// The Discord HTTP client, once connected to Discord
+pub fn http() -> Option<Arc<Http>> {
+    DISCORD.get().map(|(http, _)| http.clone())
+}
+
+/// The voice manager, once connected to Discord
+pub fn voice_manager() -> Option<Arc<Songbird>> {
+    DISCORD.get().map(|(_, manager)| manager.clone())
+}
+
+/// How an announcement went: guilds reached and guilds it couldn't be delivered to
+#[derive(Debug, serde::Serialize)]
+pub struct BroadcastReport {
+    pub sent: usize,
+    pub failed: Vec<String>,
+}
+
+/// Post `message` from the bot's operators in every guild with an active voice session: in the
+/// guild's announcement channel, the text channel the session was last used from, or the voice
+/// channel's chat otherwise
+pub async fn announce(message: &str) -> Result<BroadcastReport> {
+    let (http, manager) = DISCORD
+        .get()
+        .ok_or_else(|| anyhow!("not connected to Discord"))?;
+
+    // Collect the calls first so the manager's map isn't held across the awaits below
+    let calls: Vec<_> = manager.iter().collect();
+    let mut sessions: Vec<(GuildId, Option<ChannelId>)> = Vec::new();
+    for (guild_id, call_lock) in calls {
+        let voice_channel = call_lock
+            .lock()
+            .await
+            .current_channel()
+            .map(|c| ChannelId::new(c.0.get()));
+        sessions.push((GuildId::new(guild_id.0.get()), voice_channel));
+    }
+
+    let mut report = BroadcastReport {
+        sent: 0,
+        failed: Vec::new(),
+    };
+    for (guild_id, voice_channel) in sessions {
+        let Some(channel_id) = session_channel(guild_id, voice_channel) else {
+            report.failed.push(guild_id.to_string());
+            continue;
+        };
+        let embed = CreateEmbed::new()
+            .title("📢 Announcement")
+            .description(message)
+            .colour(0xF1C40F);
+        match channel_id
+            .send_message(http, CreateMessage::new().embeds(vec![embed]))
+            .await
+        {
+            Ok(_) => report.sent += 1,
+            Err(e) => {
+                tracing::warn!("Failed to send announcement to guild {}: {}", guild_id, e);
+                report.failed.push(guild_id.to_string());
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Post a plain message in `guild_id`'s music channel, as `announce` picks it for one guild
+pub async fn post(guild_id: &str, message: &str) -> Result<()> {
+    let (http, manager) = DISCORD
+        .get()
+        .ok_or_else(|| anyhow!("not connected to Discord"))?;
+    let guild_id = GuildId::new(guild_id.parse()?);
+    let voice_channel = match manager.get(guild_id) {
+        Some(call_lock) => call_lock
+            .lock()
+            .await
+            .current_channel()
+            .map(|c| ChannelId::new(c.0.get())),
+        None => None,
+    };
+    let channel_id = session_channel(guild_id, voice_channel)
+        .ok_or_else(|| anyhow!("no channel to post in for guild {}", guild_id))?;
+    channel_id.say(http, message).await?;
+    Ok(())
+}
+
+/// The guild's announcement channel if it picked one in `/setup`, otherwise the text channel its
+/// session was last used from, or its voice channel's chat
+fn session_channel(guild_id: GuildId, voice_channel: Option<ChannelId>) -> Option<ChannelId> {
+    let mut db_conn = establish_connection();
+    let announcement_channel = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+        .ok()
+        .flatten()
+        .and_then(|s| s.announcement_channel_id);
+    announcement_channel
+        .or_else(|| {
+            VoiceConnection::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+                .ok()
+                .flatten()
+                .and_then(|c| c.text_channel_id)
+        })
+        .and_then(|id| id.parse::<u64>().ok())
+        .map(ChannelId::new)
+        .or(voice_channel)
+}
@@ -0,0 +1,157 @@
+//! Tidying the download cache after crashes: job folders a killed download left behind, filter
+//! renders that never finished, and song cache rows whose file has gone.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use tokio::fs;
+
+use crate::audio;
+use crate::database::establish_connection;
+use crate::database::models::SongCache;
+
+/// Hours a job folder or partial render may go untouched before it's removed. Long VODs can
+/// take a while, so keep this well above the longest download.
+const STALE_HOURS_ENV: &str = "LYRE_STALE_JOB_HOURS";
+const DEFAULT_STALE_HOURS: u64 = 6;
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+fn stale_after() -> Duration {
+    let hours = crate::config::var(STALE_HOURS_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|h| *h > 0)
+        .unwrap_or(DEFAULT_STALE_HOURS);
+    Duration::from_secs(hours * 60 * 60)
+}
+
+/// What one cleanup pass removed
+#[derive(Debug, Default)]
+pub struct CleanupReport {
+    pub jobs_removed: usize,
+    pub partials_removed: usize,
+    pub cache_rows_removed: usize,
+}
+
+/// Run a cleanup now and then every hour
+pub fn spawn_orphan_cleanup() {
+    tokio::spawn(async {
+        loop {
+            let report = run().await;
+            if report.jobs_removed + report.partials_removed + report.cache_rows_removed > 0 {
+                tracing::info!(
+                    "Cache cleanup removed {} stale job folders, {} partial renders and {} song cache rows without a file",
+                    report.jobs_removed,
+                    report.partials_removed,
+                    report.cache_rows_removed
+                );
+            }
+            tokio::time::sleep(CLEANUP_INTERVAL).await;
+        }
+    });
+}
+
+pub async fn run() -> CleanupReport {
+    let cutoff = SystemTime::now()
+        .checked_sub(stale_after())
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut report = CleanupReport::default();
+    if let Ok(jobs) = audio::jobs_dir() {
+        report.jobs_removed = remove_stale_jobs(&jobs, cutoff).await;
+    }
+    if let Ok(base) = audio::resolved_download_base_dir() {
+        report.partials_removed = remove_stale_partials(&base, cutoff).await;
+    }
+    report.cache_rows_removed = forget_missing_files().await;
+    report
+}
+
+/// When anything in `dir` last changed: yt-dlp keeps writing to one file for a long download,
+/// which doesn't touch the folder itself
+async fn last_activity(dir: &Path) -> Option<SystemTime> {
+    let mut latest = fs::metadata(dir).await.ok()?.modified().ok()?;
+    if let Ok(mut entries) = fs::read_dir(dir).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if let Ok(modified) = entry.metadata().await.and_then(|m| m.modified()) {
+                latest = latest.max(modified);
+            }
+        }
+    }
+    Some(latest)
+}
+
+async fn remove_stale_jobs(jobs: &Path, cutoff: SystemTime) -> usize {
+    let Ok(mut entries) = fs::read_dir(jobs).await else {
+        return 0;
+    };
+    let mut removed = 0;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if !entry.file_type().await.is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        if last_activity(&path).await.is_none_or(|t| t > cutoff) {
+            continue;
+        }
+        match fs::remove_dir_all(&path).await {
+            Ok(()) => removed += 1,
+            Err(e) => tracing::warn!("Failed to remove stale job {}: {}", path.display(), e),
+        }
+    }
+    removed
+}
+
+/// Filter renders (`*.part.mp3`) an interrupted ffmpeg left in the source folders
+async fn remove_stale_partials(base: &Path, cutoff: SystemTime) -> usize {
+    let Ok(mut sources) = fs::read_dir(base).await else {
+        return 0;
+    };
+    let mut removed = 0;
+    while let Ok(Some(source)) = sources.next_entry().await {
+        if source.file_name().to_string_lossy().starts_with('.')
+            || !source.file_type().await.is_ok_and(|t| t.is_dir())
+        {
+            continue;
+        }
+        let Ok(mut files) = fs::read_dir(source.path()).await else {
+            continue;
+        };
+        while let Ok(Some(file)) = files.next_entry().await {
+            if !file.file_name().to_string_lossy().ends_with(".part.mp3") {
+                continue;
+            }
+            let stale = file
+                .metadata()
+                .await
+                .and_then(|m| m.modified())
+                .is_ok_and(|t| t <= cutoff);
+            if stale && fs::remove_file(file.path()).await.is_ok() {
+                removed += 1;
+            }
+        }
+    }
+    removed
+}
+
+/// Drop song cache rows whose download was deleted, so cache stats match the disk
+async fn forget_missing_files() -> usize {
+    let mut db_conn = establish_connection();
+    let entries = match SongCache::with_files(&mut db_conn) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!("Failed to list song cache files: {}", e);
+            return 0;
+        }
+    };
+    let mut removed = 0;
+    for (url, file_path) in entries {
+        if fs::try_exists(&file_path).await.unwrap_or(true) {
+            continue;
+        }
+        match SongCache::delete_by_url(&mut db_conn, &url) {
+            Ok(n) => removed += n,
+            Err(e) => tracing::warn!("Failed to forget song cache row for {}: {}", url, e),
+        }
+    }
+    removed
+}
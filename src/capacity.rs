@@ -0,0 +1,150 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use serenity::all::{ChannelId, CreateMessage, GuildId};
+use serenity::http::Http;
+use songbird::Songbird;
+
+/// Most guilds that may have an active voice session at once (e.g. `25` on a small VPS).
+/// Unset or `0` means no limit.
+const MAX_SESSIONS_ENV: &str = "LYRE_MAX_ACTIVE_SESSIONS";
+/// How long a guild admitted from the waiting list has to start playing before its slot goes
+/// to the next guild in line
+const RESERVATION_TTL: Duration = Duration::from_secs(120);
+const WAITLIST_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+static WAITLIST: Lazy<Mutex<Waitlist>> = Lazy::new(|| Mutex::new(Waitlist::default()));
+static WORKER_STARTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+struct Waitlist {
+    /// Guilds waiting for a session, with the text channel to tell when a slot opens up
+    waiting: VecDeque<(GuildId, ChannelId)>,
+    /// Guilds admitted from the waiting list that haven't joined voice yet
+    reserved: HashMap<GuildId, Instant>,
+}
+
+/// Whether a guild without a voice session may start one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Admitted,
+    /// The bot is at capacity; the guild is `position` (1-based) in the waiting list
+    Waiting {
+        position: usize,
+    },
+}
+
+pub fn max_sessions() -> Option<usize> {
+    crate::config::var(MAX_SESSIONS_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+}
+
+/// Decide whether `guild_id`, which has no voice session yet, may start one while `active`
+/// sessions are running. Guilds turned away are put in line (or keep their place) and are
+/// told in `channel_id` when a slot frees up.
+pub fn admit(guild_id: GuildId, channel_id: ChannelId, active: usize) -> Admission {
+    let Some(limit) = max_sessions() else {
+        return Admission::Admitted;
+    };
+    let Ok(mut list) = WAITLIST.lock() else {
+        return Admission::Admitted;
+    };
+    list.reserved
+        .retain(|_, reserved_at| reserved_at.elapsed() < RESERVATION_TTL);
+
+    if list.reserved.remove(&guild_id).is_some() {
+        list.waiting.retain(|(g, _)| *g != guild_id);
+        return Admission::Admitted;
+    }
+    // Free slots go to guilds already in line first
+    if list.waiting.is_empty() && active + list.reserved.len() < limit {
+        return Admission::Admitted;
+    }
+
+    let position = match list.waiting.iter().position(|(g, _)| *g == guild_id) {
+        Some(index) => {
+            list.waiting[index].1 = channel_id;
+            index + 1
+        }
+        None => {
+            list.waiting.push_back((guild_id, channel_id));
+            list.waiting.len()
+        }
+    };
+    Admission::Waiting { position }
+}
+
+/// Like `admit`, but for sessions started on someone's behalf (e.g. an approved request) that
+/// can't usefully wait: takes a free slot if there is one, without joining the waiting list
+pub fn try_admit(guild_id: GuildId, active: usize) -> bool {
+    let Some(limit) = max_sessions() else {
+        return true;
+    };
+    let Ok(mut list) = WAITLIST.lock() else {
+        return true;
+    };
+    list.reserved
+        .retain(|_, reserved_at| reserved_at.elapsed() < RESERVATION_TTL);
+    if list.reserved.remove(&guild_id).is_some() {
+        list.waiting.retain(|(g, _)| *g != guild_id);
+        return true;
+    }
+    list.waiting.is_empty() && active + list.reserved.len() < limit
+}
+
+/// Admit guilds from the waiting list as voice sessions end. Safe to call on every `ready`.
+pub fn spawn_waitlist_worker(http: Arc<Http>, manager: Arc<Songbird>) {
+    if WORKER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(WAITLIST_POLL_INTERVAL).await;
+            let Some(limit) = max_sessions() else {
+                continue;
+            };
+            // Slots freed during maintenance can't be used until it's over
+            if crate::metrics::METRICS.is_draining() {
+                continue;
+            }
+            let active = manager.iter().count();
+            let admitted = {
+                let Ok(mut list) = WAITLIST.lock() else {
+                    continue;
+                };
+                list.reserved
+                    .retain(|_, reserved_at| reserved_at.elapsed() < RESERVATION_TTL);
+                let mut admitted = Vec::new();
+                while active + list.reserved.len() < limit {
+                    let Some((guild_id, channel_id)) = list.waiting.pop_front() else {
+                        break;
+                    };
+                    // A guild may already have got in since it was put in line
+                    if manager.get(guild_id).is_none() {
+                        list.reserved.insert(guild_id, Instant::now());
+                        admitted.push((guild_id, channel_id));
+                    }
+                }
+                admitted
+            };
+            for (guild_id, channel_id) in admitted {
+                tracing::info!("Admitted guild {} from the session waiting list", guild_id);
+                let notice = format!(
+                    "🎶 A playback slot just opened up! Use `/play` within {} minutes to claim it.",
+                    RESERVATION_TTL.as_secs() / 60
+                );
+                if let Err(e) = channel_id
+                    .send_message(&http, CreateMessage::new().content(notice))
+                    .await
+                {
+                    tracing::warn!("Failed to tell guild {} about a free slot: {}", guild_id, e);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,56 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction, Context as SerenityContext, CreateActionRow, CreateButton, CreateCommand,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use crate::metrics::METRICS;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("about").description("Show the bot's version, uptime and source code")
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let metrics = METRICS.snapshot();
+    let servers = ctx.cache.guild_count();
+    let embed = CreateEmbed::new()
+        .title(format!("🎶 Lyre v{}", env!("CARGO_PKG_VERSION")))
+        .description("A Discord music bot built on Serenity, Songbird and yt-dlp.")
+        .field("Uptime", format_uptime(metrics.uptime_secs), true)
+        .field("Servers", servers.to_string(), true)
+        .field("Playing in", metrics.active_voice_calls.to_string(), true)
+        .field(
+            "Cache",
+            format!(
+                "{} file(s), {:.1} MB",
+                metrics.downloads_files,
+                metrics.downloads_bytes as f64 / 1_048_576.0
+            ),
+            true,
+        )
+        .colour(0x1db954);
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(embed)
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new_link(env!("CARGO_PKG_REPOSITORY")).label("Source code"),
+                    CreateButton::new_link(super::invite::invite_url(cmd.application_id))
+                        .label("Invite"),
+                ])])
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, h) => format!("{}h {}m", h, minutes),
+        (d, h) => format!("{}d {}h", d, h),
+    }
+}
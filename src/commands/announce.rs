@@ -0,0 +1,64 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context as SerenityContext, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    EditInteractionResponse, InteractionContext, Permissions,
+};
+
+use crate::broadcast::{self, MAX_ANNOUNCEMENT_LEN};
+use crate::policy::is_bot_operator;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("announce")
+        .description("Send a notice to every server the bot is playing in (bot operators only)")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "message",
+                "Notice to send, e.g. \"Restarting for an update in 5 minutes\"",
+            )
+            .required(true)
+            .max_length(MAX_ANNOUNCEMENT_LEN as u16),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    if !is_bot_operator(&cmd.user.id.to_string()) {
+        return super::reject(ctx, cmd, "Only the bot's operators can send announcements").await;
+    }
+    let message = cmd
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == "message")
+        .and_then(|o| o.value.as_str())
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .ok_or_else(|| anyhow!("missing message argument"))?;
+
+    // Sending to many guilds can take longer than Discord's 3 second reply window
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true)),
+    )
+    .await?;
+
+    tracing::info!("{} is broadcasting an announcement", cmd.user.id);
+    let reply = match broadcast::announce(message).await {
+        Ok(report) if report.failed.is_empty() => {
+            format!("📢 Sent to {} active session(s)", report.sent)
+        }
+        Ok(report) => format!(
+            "📢 Sent to {} active session(s); couldn't reach {} ({})",
+            report.sent,
+            report.failed.len(),
+            report.failed.join(", ")
+        ),
+        Err(e) => format!("❌ Couldn't send the announcement: {}", e),
+    };
+    cmd.edit_response(&ctx.http, EditInteractionResponse::new().content(reply))
+        .await?;
+    Ok(())
+}
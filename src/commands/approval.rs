@@ -0,0 +1,500 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    ButtonStyle, ChannelId, ChannelType, CommandDataOptionValue, CommandInteraction,
+    CommandOptionType, ComponentInteraction, Context as SerenityContext, CreateActionRow,
+    CreateButton, CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    EditInteractionResponse, EditMessage, GuildId, InteractionContext, MessageId, Permissions,
+    UserId,
+};
+use std::time::Duration;
+
+use crate::database::establish_connection;
+use crate::database::models::{GuildSettings, PendingRequest};
+use crate::policy::is_dj;
+use crate::settings_events;
+
+/// How long a request waits for a DJ before it's rejected automatically
+const APPROVAL_TIMEOUT_MINUTES: i64 = 15;
+/// Custom ID prefix of the review buttons; followed by `approve:<id>` or `reject:<id>`
+pub const APPROVAL_BUTTON_PREFIX: &str = "approval:";
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("approval")
+        .description("Make track requests from non-DJs wait for a moderator's approval")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "on",
+                "Require approval for requests from members who aren't DJs",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Channel,
+                    "channel",
+                    "Where requests are reviewed (defaults to the channel they're made in)",
+                )
+                .channel_types(vec![ChannelType::Text]),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "off",
+            "Let everyone queue tracks directly again",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "status",
+            "Show the approval settings and how many requests are waiting",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    let mut db_conn = establish_connection();
+    let settings = GuildSettings::create_or_update(&mut db_conn, &guild_id)?;
+
+    let reply = match sub.name.as_str() {
+        "on" => {
+            let channel = args
+                .iter()
+                .find(|o| o.name == "channel")
+                .and_then(|o| o.value.as_channel_id())
+                .map(|c| c.to_string());
+            GuildSettings::update_approval(&mut db_conn, &guild_id, true, channel.as_deref())?;
+            settings_events::changed(&guild_id);
+            let mut reply = format!(
+                "🛡️ Requests from members who aren't DJs now wait for approval in {}. Unreviewed requests are rejected after {} minutes.",
+                channel
+                    .map(|c| format!("<#{}>", c))
+                    .unwrap_or_else(|| "the channel they're made in".to_string()),
+                APPROVAL_TIMEOUT_MINUTES
+            );
+            if settings.allowed_roles_list().is_empty() {
+                reply.push_str(
+                    "\nOnly members who can manage the server are DJs; add DJ roles with `/dj add`.",
+                );
+            }
+            reply
+        }
+        "off" => {
+            GuildSettings::update_approval(
+                &mut db_conn,
+                &guild_id,
+                false,
+                settings.approval_channel_id.as_deref(),
+            )?;
+            settings_events::changed(&guild_id);
+            "✅ Everyone can queue tracks directly again. Requests already waiting can still be reviewed.".to_string()
+        }
+        "status" => {
+            let pending = PendingRequest::count_for_guild(&mut db_conn, &guild_id)?;
+            format!(
+                "Approval required: **{}**\nReview channel: {}\nRequests waiting: **{}**",
+                if settings.require_approval {
+                    "on"
+                } else {
+                    "off"
+                },
+                settings
+                    .approval_channel_id
+                    .map(|c| format!("<#{}>", c))
+                    .unwrap_or_else(|| "where each request is made".to_string()),
+                pending
+            )
+        }
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(reply)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Hold `url` for review instead of playing it and answer the (not yet acknowledged)
+/// interaction. Called by `/play` in moderation mode.
+pub async fn submit(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    url: &str,
+    priority: i32,
+    settings: Option<&GuildSettings>,
+) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in guild"))?;
+    let in_voice = ctx.cache.guild(guild_id).is_some_and(|guild| {
+        guild
+            .voice_states
+            .get(&cmd.user.id)
+            .is_some_and(|vs| vs.channel_id.is_some())
+    });
+    if !in_voice {
+        return super::reject(ctx, cmd, "You must be in a voice channel").await;
+    }
+
+    let held = hold(
+        ctx,
+        guild_id,
+        cmd.channel_id,
+        cmd.user.id,
+        url,
+        priority,
+        settings,
+    )
+    .await?;
+    if !held {
+        return super::reject(
+            ctx,
+            cmd,
+            "Requests need a DJ's approval here, but I couldn't post to the review channel",
+        )
+        .await;
+    }
+
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(
+            format!("⏳ Your request is waiting for a DJ's approval: {}", url),
+        )),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Record a request from `user_id` and post approve/reject buttons for the guild's DJs. The
+/// requester hears back in `channel_id`. Returns false, dropping the request, if the review
+/// message couldn't be posted.
+pub async fn hold(
+    ctx: &SerenityContext,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    user_id: UserId,
+    url: &str,
+    priority: i32,
+    settings: Option<&GuildSettings>,
+) -> Result<bool> {
+    let request = record(guild_id, channel_id, user_id, url, priority)?;
+    let id = request.id.unwrap_or_default();
+
+    let review_channel = settings
+        .and_then(|s| s.approval_channel_id.as_deref())
+        .and_then(|c| c.parse::<u64>().ok())
+        .map(ChannelId::new)
+        .unwrap_or(channel_id);
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{}approve:{}", APPROVAL_BUTTON_PREFIX, id))
+            .label("Approve")
+            .style(ButtonStyle::Success),
+        CreateButton::new(format!("{}reject:{}", APPROVAL_BUTTON_PREFIX, id))
+            .label("Reject")
+            .style(ButtonStyle::Danger),
+    ]);
+    let posted = review_channel
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .embed(
+                    review_embed(&request).footer(CreateEmbedFooter::new(format!(
+                        "Rejected automatically if no DJ reviews it within {} minutes",
+                        APPROVAL_TIMEOUT_MINUTES
+                    ))),
+                )
+                .components(vec![buttons]),
+        )
+        .await;
+    let message = match posted {
+        Ok(message) => message,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to post approval request to channel {}: {}",
+                review_channel,
+                e
+            );
+            let mut db_conn = establish_connection();
+            PendingRequest::take_expired(&mut db_conn, id)?;
+            return Ok(false);
+        }
+    };
+    {
+        let mut db_conn = establish_connection();
+        PendingRequest::set_review_message(
+            &mut db_conn,
+            id,
+            &review_channel.to_string(),
+            &message.id.to_string(),
+        )?;
+    }
+
+    let ctx_clone = ctx.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(APPROVAL_TIMEOUT_MINUTES as u64 * 60)).await;
+        let request = {
+            let mut db_conn = establish_connection();
+            PendingRequest::take_expired(&mut db_conn, id)
+        };
+        match request {
+            Ok(Some(request)) => expire(&ctx_clone, &request).await,
+            Ok(None) => {} // Already approved or rejected
+            Err(e) => tracing::warn!("Failed to expire approval request {}: {}", id, e),
+        }
+    });
+    Ok(true)
+}
+
+/// Store a request that waits for a DJ, expiring after the usual timeout
+pub fn record(
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    user_id: UserId,
+    url: &str,
+    priority: i32,
+) -> Result<PendingRequest> {
+    let expires_at =
+        chrono::Utc::now().naive_utc() + chrono::Duration::minutes(APPROVAL_TIMEOUT_MINUTES);
+    let mut db_conn = establish_connection();
+    Ok(PendingRequest::create(
+        &mut db_conn,
+        &guild_id.to_string(),
+        &channel_id.to_string(),
+        &user_id.to_string(),
+        url,
+        priority,
+        expires_at,
+    )?)
+}
+
+/// Approve or reject a pending request from its review message. Only DJs may decide, and the
+/// first decision wins.
+pub async fn handle_review_button(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+) -> Result<()> {
+    let (action, id) = component
+        .data
+        .custom_id
+        .strip_prefix(APPROVAL_BUTTON_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .ok_or_else(|| anyhow!("malformed approval button id"))?;
+    let id: i32 = id.parse()?;
+    let guild_id = component
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?;
+
+    let request = {
+        let mut db_conn = establish_connection();
+        let settings = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+            .ok()
+            .flatten();
+        if !is_dj(settings.as_ref(), component.member.as_ref()) {
+            return reply_ephemeral(ctx, component, "Only DJs can review requests").await;
+        }
+        PendingRequest::take(&mut db_conn, &guild_id.to_string(), id)?
+    };
+    let Some(request) = request else {
+        return reply_ephemeral(
+            ctx,
+            component,
+            "That request was already reviewed or has expired",
+        )
+        .await;
+    };
+    let reviewer = component.user.id;
+
+    match action {
+        "reject" => {
+            component
+                .create_response(
+                    &ctx.http,
+                    CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::new()
+                            .embed(
+                                review_embed(&request)
+                                    .title("❌ Request rejected")
+                                    .colour(0xe74c3c)
+                                    .field("Reviewed by", format!("<@{}>", reviewer), true),
+                            )
+                            .components(vec![]),
+                    ),
+                )
+                .await?;
+            notify_requester(
+                ctx,
+                &request,
+                format!(
+                    "<@{}> your request for {} was rejected by a DJ.",
+                    request.user_id, request.url
+                ),
+            )
+            .await;
+        }
+        "approve" => {
+            // Acknowledge straight away; downloading can take longer than the interaction allows
+            component
+                .create_response(
+                    &ctx.http,
+                    CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::new()
+                            .embed(
+                                review_embed(&request)
+                                    .title("✅ Request approved")
+                                    .colour(0x2ecc71)
+                                    .field("Reviewed by", format!("<@{}>", reviewer), true)
+                                    .field("Status", "Queueing…", true),
+                            )
+                            .components(vec![]),
+                    ),
+                )
+                .await?;
+            let (status, notice) = match queue_approved(ctx, guild_id, &request).await {
+                Ok(title) => (
+                    format!("Queued **{}**", title),
+                    format!(
+                        "<@{}> your request **{}** was approved and queued.",
+                        request.user_id, title
+                    ),
+                ),
+                Err(e) => {
+                    tracing::warn!("Failed to queue approved request {}: {}", id, e);
+                    (
+                        format!("Couldn't queue it: {}", e),
+                        format!(
+                            "<@{}> your request for {} was approved but couldn't be queued: {}",
+                            request.user_id, request.url, e
+                        ),
+                    )
+                }
+            };
+            component
+                .edit_response(
+                    &ctx.http,
+                    EditInteractionResponse::new().embed(
+                        review_embed(&request)
+                            .title("✅ Request approved")
+                            .colour(0x2ecc71)
+                            .field("Reviewed by", format!("<@{}>", reviewer), true)
+                            .field("Status", status, true),
+                    ),
+                )
+                .await?;
+            notify_requester(ctx, &request, notice).await;
+        }
+        other => return Err(anyhow!("unknown approval action {other}")),
+    }
+    Ok(())
+}
+
+/// Join the requester's voice channel if needed and queue their track like `/play` would
+async fn queue_approved(
+    ctx: &SerenityContext,
+    guild_id: GuildId,
+    request: &PendingRequest,
+) -> Result<String> {
+    let user_id = UserId::new(request.user_id.parse()?);
+    let channel_id = ChannelId::new(request.channel_id.parse()?);
+    super::play::join_member_channel(ctx, guild_id, user_id).await?;
+    super::play::enqueue_quietly(
+        ctx,
+        guild_id,
+        channel_id,
+        user_id,
+        &request.url,
+        request.priority,
+    )
+    .await
+}
+
+/// Close a request nobody reviewed in time
+async fn expire(ctx: &SerenityContext, request: &PendingRequest) {
+    if let (Some(channel_id), Some(message_id)) = (
+        request
+            .review_channel_id
+            .as_deref()
+            .and_then(|c| c.parse::<u64>().ok()),
+        request
+            .review_message_id
+            .as_deref()
+            .and_then(|m| m.parse::<u64>().ok()),
+    ) {
+        let edit = EditMessage::new()
+            .embed(
+                review_embed(request)
+                    .title("⌛ Request expired")
+                    .colour(0x95a5a6),
+            )
+            .components(vec![]);
+        if let Err(e) = ChannelId::new(channel_id)
+            .edit_message(&ctx.http, MessageId::new(message_id), edit)
+            .await
+        {
+            tracing::warn!("Failed to mark approval request as expired: {}", e);
+        }
+    }
+    notify_requester(
+        ctx,
+        request,
+        format!(
+            "<@{}> your request for {} wasn't reviewed in time and has been dropped.",
+            request.user_id, request.url
+        ),
+    )
+    .await;
+}
+
+async fn notify_requester(ctx: &SerenityContext, request: &PendingRequest, content: String) {
+    let Ok(channel_id) = request.channel_id.parse::<u64>() else {
+        return;
+    };
+    if let Err(e) = ChannelId::new(channel_id)
+        .send_message(&ctx.http, CreateMessage::new().content(content))
+        .await
+    {
+        tracing::warn!("Failed to tell requester about their request: {}", e);
+    }
+}
+
+fn review_embed(request: &PendingRequest) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title("🎫 Track request")
+        .description(format!("<@{}> requested {}", request.user_id, request.url))
+        .colour(0xf39c12);
+    if request.priority > 0 {
+        embed = embed.field("Priority", request.priority.to_string(), true);
+    }
+    embed
+}
+
+async fn reply_ephemeral(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+    content: &str,
+) -> Result<()> {
+    component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,265 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::{BlockedTrack, GuildSettings};
+use crate::policy::{MAX_BLOCKED_KEYWORDS, MAX_KEYWORD_LEN, normalize_keywords, parse_track_ref};
+use crate::settings_events;
+
+/// Most entries shown by `/block list`; Discord caps embed descriptions at 4096 characters
+const LIST_LIMIT: usize = 25;
+
+pub fn definition() -> CreateCommand {
+    let target = || {
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "track",
+            "Track URL or YouTube video ID",
+        )
+        .required(true)
+    };
+    CreateCommand::new("block")
+        .description("Manage this server's blocked tracks")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "add", "Block a track")
+                .add_sub_option(target())
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "reason",
+                    "Shown to users who try to play it",
+                )),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "Unblock a track")
+                .add_sub_option(target()),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show blocked tracks",
+        ))
+        .add_option(keyword_group())
+}
+
+fn keyword_group() -> CreateCommandOption {
+    let keyword = || {
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "keyword",
+            "Word or phrase matched against track titles",
+        )
+        .required(true)
+        .max_length(MAX_KEYWORD_LEN as u16)
+    };
+    CreateCommandOption::new(
+        CommandOptionType::SubCommandGroup,
+        "keyword",
+        "Block tracks whose titles contain a keyword",
+    )
+    .add_sub_option(
+        CreateCommandOption::new(CommandOptionType::SubCommand, "add", "Block a keyword")
+            .add_sub_option(keyword()),
+    )
+    .add_sub_option(
+        CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "Unblock a keyword")
+            .add_sub_option(keyword()),
+    )
+    .add_sub_option(CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        "list",
+        "Show blocked keywords",
+    ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    if let CommandDataOptionValue::SubCommandGroup(group) = &sub.value {
+        return handle_keyword(ctx, cmd, &guild_id, group).await;
+    }
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    let mut db_conn = establish_connection();
+    let reply = match sub.name.as_str() {
+        "add" | "remove" => {
+            let Some(input) = string_arg(args, "track") else {
+                return Err(anyhow!("missing track argument"));
+            };
+            let Some(key) = parse_track_ref(input) else {
+                return super::reject(ctx, cmd, "That isn't a valid track URL or video ID").await;
+            };
+            if sub.name == "add" {
+                let reason = string_arg(args, "reason");
+                if BlockedTrack::add(
+                    &mut db_conn,
+                    &guild_id,
+                    &key,
+                    reason,
+                    &cmd.user.id.to_string(),
+                )? {
+                    format!("🚫 Blocked `{}`", key)
+                } else {
+                    format!("`{}` is already blocked", key)
+                }
+            } else if BlockedTrack::remove(&mut db_conn, &guild_id, &key)? {
+                format!("✅ Unblocked `{}`", key)
+            } else {
+                format!("`{}` isn't blocked", key)
+            }
+        }
+        "list" => {
+            let blocked = BlockedTrack::list_for_guild(&mut db_conn, &guild_id)?;
+            if blocked.is_empty() {
+                "No tracks are blocked on this server.".to_string()
+            } else {
+                let lines: Vec<String> = blocked
+                    .iter()
+                    .take(LIST_LIMIT)
+                    .map(|b| match &b.reason {
+                        Some(reason) => format!("• `{}` — {}", b.track_key, reason),
+                        None => format!("• `{}`", b.track_key),
+                    })
+                    .collect();
+                let mut embed = CreateEmbed::new()
+                    .title("🚫 Blocked Tracks")
+                    .description(lines.join("\n"))
+                    .colour(0xed4245);
+                if blocked.len() > LIST_LIMIT {
+                    embed = embed.footer(serenity::all::CreateEmbedFooter::new(format!(
+                        "Showing {} of {}",
+                        LIST_LIMIT,
+                        blocked.len()
+                    )));
+                }
+                return respond(
+                    ctx,
+                    cmd,
+                    CreateInteractionResponseMessage::new().embed(embed),
+                )
+                .await;
+            }
+        }
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+
+    respond(
+        ctx,
+        cmd,
+        CreateInteractionResponseMessage::new().content(reply),
+    )
+    .await
+}
+
+async fn handle_keyword(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    guild_id: &str,
+    group: &[CommandDataOption],
+) -> Result<()> {
+    let Some(sub) = group.first() else {
+        return Err(anyhow!("missing keyword subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected keyword subcommand"));
+    };
+
+    let mut db_conn = establish_connection();
+    let settings = GuildSettings::create_or_update(&mut db_conn, guild_id)?;
+    let mut keywords = settings.blocked_keywords_list();
+
+    let reply = match sub.name.as_str() {
+        "add" | "remove" => {
+            let Some(keyword) = string_arg(args, "keyword")
+                .and_then(|k| normalize_keywords(&[k.to_string()]).pop())
+            else {
+                return super::reject(ctx, cmd, "Keyword can't be empty").await;
+            };
+            if sub.name == "add" {
+                if keywords.contains(&keyword) {
+                    format!("`{}` is already blocked", keyword)
+                } else if keywords.len() >= MAX_BLOCKED_KEYWORDS {
+                    return super::reject(
+                        ctx,
+                        cmd,
+                        &format!("At most {} keywords can be blocked", MAX_BLOCKED_KEYWORDS),
+                    )
+                    .await;
+                } else {
+                    keywords.push(keyword.clone());
+                    GuildSettings::update_blocked_keywords(&mut db_conn, guild_id, &keywords)?;
+                    settings_events::changed(guild_id);
+                    format!("🚫 Tracks with `{}` in the title are now blocked", keyword)
+                }
+            } else if let Some(idx) = keywords.iter().position(|k| *k == keyword) {
+                keywords.remove(idx);
+                GuildSettings::update_blocked_keywords(&mut db_conn, guild_id, &keywords)?;
+                settings_events::changed(guild_id);
+                format!("✅ Unblocked keyword `{}`", keyword)
+            } else {
+                format!("`{}` isn't blocked", keyword)
+            }
+        }
+        "list" => {
+            if keywords.is_empty() {
+                "No title keywords are blocked on this server.".to_string()
+            } else {
+                let embed = CreateEmbed::new()
+                    .title("🚫 Blocked Keywords")
+                    .description(
+                        keywords
+                            .iter()
+                            .map(|k| format!("`{}`", k))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    )
+                    .colour(0xed4245);
+                return respond(
+                    ctx,
+                    cmd,
+                    CreateInteractionResponseMessage::new().embed(embed),
+                )
+                .await;
+            }
+        }
+        other => return Err(anyhow!("unknown keyword subcommand {other}")),
+    };
+
+    respond(
+        ctx,
+        cmd,
+        CreateInteractionResponseMessage::new().content(reply),
+    )
+    .await
+}
+
+fn string_arg<'a>(args: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    args.iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.value.as_str())
+}
+
+async fn respond(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    message: CreateInteractionResponseMessage,
+) -> Result<()> {
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(message.ephemeral(true)),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context as SerenityContext, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::CurrentQueue;
+use crate::database::models::current_queue::VoteOutcome;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("boost")
+        .description("Upvote a queued track so it plays sooner")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "position",
+                "Queue position of the track, as shown by /queue show",
+            )
+            .min_int_value(1)
+            .required(true),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let position = cmd
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == "position")
+        .and_then(|o| o.value.as_i64())
+        .ok_or_else(|| anyhow!("missing position"))?;
+
+    let (entry, outcome) = {
+        let mut db_conn = establish_connection();
+        let entry = CurrentQueue::get_guild_queue(&mut db_conn, &guild_id)?
+            .into_iter()
+            .find(|e| e.position as i64 == position);
+        let outcome = match &entry {
+            Some(CurrentQueue { id: Some(id), .. }) => Some(CurrentQueue::upvote(
+                &mut db_conn,
+                &guild_id,
+                *id,
+                &cmd.user.id.to_string(),
+            )?),
+            _ => None,
+        };
+        (entry, outcome)
+    };
+    let title = entry.map(|e| e.title.unwrap_or(e.url)).unwrap_or_default();
+
+    let content = match outcome {
+        Some(VoteOutcome::Counted(votes)) => format!(
+            "👍 Upvoted **{}** ({} vote{}). The queue is reordered by votes when the current track ends.",
+            title,
+            votes,
+            if votes == 1 { "" } else { "s" }
+        ),
+        Some(VoteOutcome::AlreadyVoted) => {
+            return super::reject(ctx, cmd, "You've already upvoted that track").await;
+        }
+        Some(VoteOutcome::NotPending) | None => {
+            return super::reject(
+                ctx,
+                cmd,
+                &format!("There's no track waiting at position {}", position),
+            )
+            .await;
+        }
+    };
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content),
+        ),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, ComponentInteraction, Context as SerenityContext, CreateCommand,
+    CreateInteractionResponse, CreateInteractionResponseMessage, GuildId, InteractionContext,
+    Member, Permissions, UserId,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+use crate::downloads::{self, DownloadJobInfo};
+use crate::policy::is_dj;
+
+/// Custom ID prefix of the "Cancel" button on a `/play` progress message; followed by the
+/// download's ID
+pub const CANCEL_BUTTON_PREFIX: &str = "cancel_download:";
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("cancel")
+        .description("Stop the track you asked for from downloading, if you queued the wrong one")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let in_guild: Vec<DownloadJobInfo> = downloads::list()
+        .into_iter()
+        .filter(|job| job.guild_id == guild_id.to_string())
+        .collect();
+    // Your own newest download; DJs with none of their own can cancel anyone's
+    let own = in_guild
+        .iter()
+        .rev()
+        .find(|job| job.requested_by == cmd.user.id.to_string());
+    let job = match own {
+        Some(job) => Some(job),
+        None if is_dj_in(guild_id, cmd.member.as_deref()) => in_guild.last(),
+        None => None,
+    };
+    let Some(job) = job else {
+        return super::reject(ctx, cmd, "You don't have a download in progress here").await;
+    };
+
+    let Some(job) = downloads::cancel(job.id) else {
+        return super::reject(ctx, cmd, "That download has just finished").await;
+    };
+    tracing::info!(
+        "{} cancelled the download of {} in guild {}",
+        cmd.user.id,
+        job.url,
+        guild_id
+    );
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(format!("🚫 Cancelled downloading <{}>", job.url)),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// The "Cancel" button under a download in progress: the member who asked for the track or a
+/// DJ can stop it, and `/play` reports the cancellation on the same message
+pub async fn handle_cancel_button(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+) -> Result<()> {
+    let id: u64 = component
+        .data
+        .custom_id
+        .strip_prefix(CANCEL_BUTTON_PREFIX)
+        .ok_or_else(|| anyhow!("malformed cancel button id"))?
+        .parse()?;
+    let guild_id = component
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?;
+
+    let job = downloads::list().into_iter().find(|job| job.id == id);
+    let refusal = match &job {
+        None => Some("That download has already finished".to_string()),
+        Some(job) if !may_cancel(guild_id, component.user.id, component.member.as_ref(), job) => {
+            Some(format!(
+                "Only <@{}> or a DJ can cancel this download",
+                job.requested_by
+            ))
+        }
+        Some(_) => None,
+    };
+    if let Some(refusal) = refusal {
+        component
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!("❌ {}", refusal))
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    if let Some(job) = downloads::cancel(id) {
+        tracing::info!(
+            "{} cancelled the download of {} in guild {}",
+            component.user.id,
+            job.url,
+            guild_id
+        );
+    }
+    component
+        .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+        .await?;
+    Ok(())
+}
+
+fn may_cancel(
+    guild_id: GuildId,
+    user_id: UserId,
+    member: Option<&Member>,
+    job: &DownloadJobInfo,
+) -> bool {
+    job.requested_by == user_id.to_string() || is_dj_in(guild_id, member)
+}
+
+fn is_dj_in(guild_id: GuildId, member: Option<&Member>) -> bool {
+    let settings =
+        GuildSettings::find_by_guild_id(&mut establish_connection(), &guild_id.to_string())
+            .ok()
+            .flatten();
+    is_dj(settings.as_ref(), member)
+}
@@ -0,0 +1,212 @@
+use anyhow::{Result, anyhow};
+use chrono::{Duration, Utc};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext, Permissions, RoleId, UserId,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::{DjGrant, GuildSettings};
+use crate::policy::MAX_DJ_ROLES;
+use crate::settings_events;
+
+/// Longest temporary grant, one week; lasting DJ rights belong on a role
+const MAX_GRANT_HOURS: u64 = 24 * 7;
+
+pub fn definition() -> CreateCommand {
+    let role = || {
+        CreateCommandOption::new(CommandOptionType::Role, "role", "Role to configure")
+            .required(true)
+    };
+    let member = || {
+        CreateCommandOption::new(CommandOptionType::User, "user", "Member to configure")
+            .required(true)
+    };
+    CreateCommand::new("dj")
+        .description("Choose which roles and members count as DJs")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "add",
+                "Make a role a DJ role",
+            )
+            .add_sub_option(role()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "remove",
+                "Stop treating a role as a DJ role",
+            )
+            .add_sub_option(role()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "grant",
+                "Make a member a DJ for a limited time",
+            )
+            .add_sub_option(member())
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "hours",
+                    "Revoke the grant automatically after this many hours",
+                )
+                .required(true)
+                .min_int_value(1)
+                .max_int_value(MAX_GRANT_HOURS),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "revoke",
+                "End a member's temporary DJ grant early",
+            )
+            .add_sub_option(member()),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show the DJ roles and temporary DJs",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    let mut db_conn = establish_connection();
+    let settings = GuildSettings::create_or_update(&mut db_conn, &guild_id)?;
+    let mut roles = settings.allowed_roles_list();
+
+    let reply = match sub.name.as_str() {
+        "add" => {
+            let role = role_arg(args)
+                .ok_or_else(|| anyhow!("missing role argument"))?
+                .to_string();
+            if roles.contains(&role) {
+                format!("<@&{}> is already a DJ role", role)
+            } else if roles.len() >= MAX_DJ_ROLES {
+                return super::reject(
+                    ctx,
+                    cmd,
+                    &format!("At most {} roles can be DJ roles", MAX_DJ_ROLES),
+                )
+                .await;
+            } else {
+                roles.push(role.clone());
+                GuildSettings::update_allowed_roles(&mut db_conn, &guild_id, &roles)?;
+                settings_events::changed(&guild_id);
+                format!("🎧 Members with <@&{}> are now DJs", role)
+            }
+        }
+        "remove" => {
+            let role = role_arg(args)
+                .ok_or_else(|| anyhow!("missing role argument"))?
+                .to_string();
+            let before = roles.len();
+            roles.retain(|r| *r != role);
+            if roles.len() < before {
+                GuildSettings::update_allowed_roles(&mut db_conn, &guild_id, &roles)?;
+                settings_events::changed(&guild_id);
+                format!("✅ <@&{}> is no longer a DJ role", role)
+            } else {
+                format!("<@&{}> isn't a DJ role", role)
+            }
+        }
+        "grant" => {
+            let user_id = user_arg(args)?;
+            let hours = args
+                .iter()
+                .find(|o| o.name == "hours")
+                .and_then(|o| o.value.as_i64())
+                .ok_or_else(|| anyhow!("missing hours argument"))?;
+            let expires_at = (Utc::now() + Duration::hours(hours)).naive_utc();
+            DjGrant::grant(
+                &mut db_conn,
+                &guild_id,
+                &user_id.to_string(),
+                &cmd.user.id.to_string(),
+                expires_at,
+            )?;
+            format!(
+                "🎧 <@{}> is a DJ until <t:{}:f>, so commands locked to roles (like /next, /stop and /volume) are open to them",
+                user_id,
+                expires_at.and_utc().timestamp()
+            )
+        }
+        "revoke" => {
+            let user_id = user_arg(args)?;
+            if DjGrant::revoke(&mut db_conn, &guild_id, &user_id.to_string())? {
+                format!("✅ <@{}> is no longer a temporary DJ", user_id)
+            } else {
+                format!("<@{}> doesn't have a temporary DJ grant", user_id)
+            }
+        }
+        "list" => {
+            let mut reply = if roles.is_empty() {
+                "No DJ roles are set; only members who can manage the server count as DJs."
+                    .to_string()
+            } else {
+                format!(
+                    "🎧 DJ roles: {}",
+                    roles
+                        .iter()
+                        .map(|r| format!("<@&{}>", r))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            let grants = DjGrant::list_active_for_guild(&mut db_conn, &guild_id)?;
+            if !grants.is_empty() {
+                reply.push_str("\nTemporary DJs:");
+                for grant in &grants {
+                    reply.push_str(&format!(
+                        "\n• <@{}> until <t:{}:f>",
+                        grant.user_id,
+                        grant.expires_at.and_utc().timestamp()
+                    ));
+                }
+            }
+            reply
+        }
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(reply)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn role_arg(args: &[CommandDataOption]) -> Option<RoleId> {
+    args.iter()
+        .find(|o| o.name == "role")
+        .and_then(|o| o.value.as_role_id())
+}
+
+fn user_arg(args: &[CommandDataOption]) -> Result<UserId> {
+    args.iter()
+        .find(|o| o.name == "user")
+        .and_then(|o| o.value.as_user_id())
+        .ok_or_else(|| anyhow!("missing user argument"))
+}
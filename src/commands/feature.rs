@@ -0,0 +1,165 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::FeatureFlag;
+use crate::database::models::feature_flag::GLOBAL_SCOPE;
+use crate::features::{self, Feature};
+use crate::policy::is_bot_operator;
+
+pub fn definition() -> CreateCommand {
+    let flag = || {
+        Feature::ALL.into_iter().fold(
+            CreateCommandOption::new(CommandOptionType::String, "flag", "Feature to change")
+                .required(true),
+            |option, feature| option.add_string_choice(feature.key(), feature.key()),
+        )
+    };
+    let scope = || {
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "scope",
+            "This server (default) or every server (bot operators only)",
+        )
+        .add_string_choice("server", "server")
+        .add_string_choice("global", "global")
+    };
+    let subcommand = |name: &str, description: &str| {
+        CreateCommandOption::new(CommandOptionType::SubCommand, name, description)
+            .add_sub_option(flag())
+            .add_sub_option(scope())
+    };
+    CreateCommand::new("feature")
+        .description("Turn experimental features on or off")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(subcommand("enable", "Turn a feature on"))
+        .add_option(subcommand("disable", "Turn a feature off"))
+        .add_option(subcommand(
+            "reset",
+            "Drop this level's setting so the next one applies",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show which features are on here",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    let mut db_conn = establish_connection();
+    if sub.name == "list" {
+        let lines: Vec<String> = features::states(&mut db_conn, Some(&guild_id))?
+            .into_iter()
+            .map(|state| {
+                let source = match (state.guild, state.global) {
+                    (Some(_), _) => "set for this server",
+                    (None, Some(_)) => "set globally",
+                    (None, None) => "default",
+                };
+                format!(
+                    "{} **{}** ({}): {}",
+                    if state.enabled { "🟢" } else { "⚪" },
+                    state.flag,
+                    source,
+                    state.description
+                )
+            })
+            .collect();
+        return respond(ctx, cmd, lines.join("\n")).await;
+    }
+
+    let feature = string_arg(args, "flag")
+        .and_then(Feature::from_key)
+        .ok_or_else(|| anyhow!("missing or unknown flag argument"))?;
+    let global = string_arg(args, "scope") == Some("global");
+    if global && !is_bot_operator(&cmd.user.id.to_string()) {
+        return super::reject(
+            ctx,
+            cmd,
+            "Only the bot's operators can change features for every server",
+        )
+        .await;
+    }
+    let (scope, where_) = if global {
+        (GLOBAL_SCOPE, "every server")
+    } else {
+        (guild_id.as_str(), "this server")
+    };
+
+    let reply = match sub.name.as_str() {
+        "enable" | "disable" => {
+            let enabled = sub.name == "enable";
+            FeatureFlag::set(
+                &mut db_conn,
+                feature.key(),
+                scope,
+                enabled,
+                &cmd.user.id.to_string(),
+            )?;
+            tracing::info!(
+                "{} {} feature {} for {}",
+                cmd.user.id,
+                sub.name,
+                feature.key(),
+                scope
+            );
+            format!(
+                "{} **{}** is now {} for {}",
+                if enabled { "🟢" } else { "⚪" },
+                feature.key(),
+                if enabled { "on" } else { "off" },
+                where_
+            )
+        }
+        "reset" => {
+            if FeatureFlag::clear(&mut db_conn, feature.key(), scope)? {
+                let now = features::is_enabled(&mut db_conn, feature, &guild_id);
+                format!(
+                    "✅ Cleared the **{}** setting for {}; it's now {} here",
+                    feature.key(),
+                    where_,
+                    if now { "on" } else { "off" }
+                )
+            } else {
+                format!("**{}** wasn't set for {}", feature.key(), where_)
+            }
+        }
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+    respond(ctx, cmd, reply).await
+}
+
+async fn respond(ctx: &SerenityContext, cmd: &CommandInteraction, content: String) -> Result<()> {
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn string_arg<'a>(args: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    args.iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.value.as_str())
+}
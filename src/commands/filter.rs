@@ -0,0 +1,300 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::FilterPreset;
+use crate::filters::{self, BassBoost, Filter, MAX_BASS_GAIN_DB};
+
+const MAX_PRESET_NAME_LEN: u16 = 50;
+
+pub fn definition() -> CreateCommand {
+    let preset_name = |description: &str| {
+        CreateCommandOption::new(CommandOptionType::String, "name", description)
+            .max_length(MAX_PRESET_NAME_LEN)
+            .required(true)
+    };
+    let level = BassBoost::PRESETS.into_iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "level", "How much to boost")
+            .required(true),
+        |option, preset| {
+            let label = format!("{} (+{} dB)", preset.key(), preset.gain_db());
+            option.add_string_choice(label, preset.key())
+        },
+    );
+    CreateCommand::new("filter")
+        .description("Change how tracks sound in this server")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "karaoke",
+            "Toggle vocal reduction for sing-alongs",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "8d",
+            "Toggle 8D audio that slowly pans around your head",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "bassboost",
+                "Boost the bass, or turn the boost off",
+            )
+            .add_sub_option(
+                level
+                    .add_string_choice("custom (set gain)", "custom")
+                    .add_string_choice("off", "off"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "gain",
+                    "Boost in dB for the custom level",
+                )
+                .min_int_value(1)
+                .max_int_value(MAX_BASS_GAIN_DB as u64),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "loudnorm",
+            "Toggle evening out loudness between tracks",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "show",
+            "List the filters that are on",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "preset",
+                "Switch to a saved set of filters",
+            )
+            .add_sub_option(preset_name("Preset name")),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "save",
+                "Save the filters that are on as a preset",
+            )
+            .add_sub_option(preset_name("Preset name, e.g. movie night")),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "presets",
+            "List saved presets",
+        ))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "forget", "Delete a preset")
+                .add_sub_option(preset_name("Preset name")),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "clear",
+            "Turn every filter off",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+
+    if !filters::available(&guild_id) {
+        return super::reject(
+            ctx,
+            cmd,
+            "Audio filters are off in this server; a server manager can turn them on with `/feature enable flag:filters`",
+        )
+        .await;
+    }
+
+    let mut enabled = filters::for_guild(&guild_id);
+    let args = match &sub.value {
+        CommandDataOptionValue::SubCommand(args) => args.as_slice(),
+        _ => &[],
+    };
+    let preset = string_arg(args, "name").map(str::trim).unwrap_or_default();
+    let content = match sub.name.as_str() {
+        "show" => return respond(ctx, cmd, summary(&enabled), true).await,
+        "presets" => return respond(ctx, cmd, presets(&guild_id)?, true).await,
+        "save" => {
+            if preset.is_empty() {
+                return super::reject(ctx, cmd, "Give the preset a name").await;
+            }
+            if enabled.is_empty() {
+                return super::reject(ctx, cmd, "No filters are on, so there's nothing to save")
+                    .await;
+            }
+            let names: Vec<String> = enabled.iter().map(|f| f.key()).collect();
+            FilterPreset::save(
+                &mut establish_connection(),
+                &guild_id,
+                preset,
+                &names,
+                &cmd.user.id.to_string(),
+            )?;
+            return respond(
+                ctx,
+                cmd,
+                format!(
+                    "✅ Saved preset **{}** ({}); switch to it with `/filter preset`.",
+                    preset,
+                    names.join(", ")
+                ),
+                false,
+            )
+            .await;
+        }
+        "forget" => {
+            if !FilterPreset::delete(&mut establish_connection(), &guild_id, preset)? {
+                return super::reject(ctx, cmd, &format!("No preset named `{}`", preset)).await;
+            }
+            return respond(
+                ctx,
+                cmd,
+                format!("🗑️ Deleted preset **{}**.", preset),
+                false,
+            )
+            .await;
+        }
+        "preset" => {
+            let Some(filters) =
+                FilterPreset::find_by_name(&mut establish_connection(), &guild_id, preset)?
+                    .map(|p| filters::parse_list(&p.filters_list()))
+            else {
+                return super::reject(ctx, cmd, &format!("No preset named `{}`", preset)).await;
+            };
+            enabled = filters;
+            format!(
+                "✅ Switched to preset **{}** for tracks queued from now on.",
+                preset
+            )
+        }
+        "clear" => {
+            enabled.clear();
+            "✅ Filters cleared; tracks queued from now on play unfiltered.".to_string()
+        }
+        "bassboost" => {
+            let level = string_arg(args, "level").unwrap_or("off");
+            let gain = args
+                .iter()
+                .find(|o| o.name == "gain")
+                .and_then(|o| o.value.as_i64());
+            enabled.retain(|f| !matches!(f, Filter::BassBoost(_)));
+            let boost = match (level, gain) {
+                ("off", _) => None,
+                ("custom", Some(gain)) => BassBoost::from_key(&gain.to_string()),
+                ("custom", None) => {
+                    return super::reject(ctx, cmd, "Pick a `gain` for the custom level").await;
+                }
+                (preset, _) => BassBoost::from_key(preset),
+            };
+            match boost {
+                Some(boost) => {
+                    enabled.push(Filter::BassBoost(boost));
+                    format!(
+                        "✅ Bass boost set to +{} dB for tracks queued from now on.",
+                        boost.gain_db()
+                    )
+                }
+                None if level == "off" => {
+                    "✅ Bass boost is off for tracks queued from now on.".to_string()
+                }
+                None => return Err(anyhow!("invalid bass boost {level} {gain:?}")),
+            }
+        }
+        key => {
+            let filter = Filter::from_key(key).ok_or_else(|| anyhow!("unknown filter {key}"))?;
+            if enabled.contains(&filter) {
+                enabled.retain(|f| *f != filter);
+                format!(
+                    "✅ **{}** is off for tracks queued from now on.",
+                    filter.key()
+                )
+            } else {
+                enabled.push(filter);
+                format!(
+                    "✅ **{}** is on for tracks queued from now on.",
+                    filter.key()
+                )
+            }
+        }
+    };
+
+    filters::save_for_guild(&guild_id, &enabled)?;
+    tracing::info!(
+        "{} set audio filters in guild {} to {:?}",
+        cmd.user.id,
+        guild_id,
+        enabled
+    );
+    // Changes are posted publicly so listeners know why tracks sound different
+    respond(
+        ctx,
+        cmd,
+        format!("{}\n{}", content, summary(&enabled)),
+        false,
+    )
+    .await
+}
+
+fn presets(guild_id: &str) -> Result<String> {
+    let presets = FilterPreset::list_for_guild(&mut establish_connection(), guild_id)?;
+    if presets.is_empty() {
+        return Ok(
+            "No presets saved yet. Turn some filters on and `/filter save` them!".to_string(),
+        );
+    }
+    let lines: Vec<String> = presets
+        .iter()
+        .map(|p| format!("• **{}**: {}", p.name, p.filters_list().join(", ")))
+        .collect();
+    Ok(format!("Saved presets:\n{}", lines.join("\n")))
+}
+
+fn summary(enabled: &[Filter]) -> String {
+    if enabled.is_empty() {
+        return "No filters are on.".to_string();
+    }
+    let lines: Vec<String> = enabled
+        .iter()
+        .map(|f| format!("• **{}**: {}", f.key(), f.description()))
+        .collect();
+    format!("Filters on:\n{}", lines.join("\n"))
+}
+
+async fn respond(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    content: String,
+    ephemeral: bool,
+) -> Result<()> {
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .ephemeral(ephemeral),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn string_arg<'a>(args: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    args.iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.value.as_str())
+}
@@ -0,0 +1,401 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, ComponentInteraction, ComponentInteractionDataKind,
+    Context as SerenityContext, CreateActionRow, CreateCommand, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu,
+    CreateSelectMenuKind, CreateSelectMenuOption,
+};
+
+use crate::database::establish_connection;
+use crate::features::{self, Feature};
+use crate::policy::is_bot_operator;
+
+/// Custom ID of the category select menu on `/help` replies
+pub const HELP_MENU_ID: &str = "help:category";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Category {
+    Playback,
+    Queue,
+    Settings,
+    Admin,
+    General,
+}
+
+impl Category {
+    const ALL: [Category; 5] = [
+        Category::Playback,
+        Category::Queue,
+        Category::Settings,
+        Category::Admin,
+        Category::General,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            Category::Playback => "playback",
+            Category::Queue => "queue",
+            Category::Settings => "settings",
+            Category::Admin => "admin",
+            Category::General => "general",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Category::Playback => "🎵 Playback",
+            Category::Queue => "📜 Queue",
+            Category::Settings => "⚙️ Settings",
+            Category::Admin => "🛠️ Admin",
+            Category::General => "ℹ️ General",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Category::Playback => "Play, pause, skip and stop music",
+            Category::Queue => "See and reorder what's coming up",
+            Category::Settings => "Server rules and look (Manage Server)",
+            Category::Admin => "Bot-wide controls",
+            Category::General => "Stats, scrobbling and bot info",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.key() == key)
+    }
+}
+
+struct HelpEntry {
+    category: Category,
+    usage: &'static str,
+    description: &'static str,
+    /// Hidden unless this feature is on for the guild
+    feature: Option<Feature>,
+    /// Hidden from everyone but the bot's operators
+    operator_only: bool,
+}
+
+const fn entry(category: Category, usage: &'static str, description: &'static str) -> HelpEntry {
+    HelpEntry {
+        category,
+        usage,
+        description,
+        feature: None,
+        operator_only: false,
+    }
+}
+
+const fn feature_entry(
+    category: Category,
+    usage: &'static str,
+    description: &'static str,
+    feature: Feature,
+) -> HelpEntry {
+    HelpEntry {
+        feature: Some(feature),
+        ..entry(category, usage, description)
+    }
+}
+
+const fn operator_entry(usage: &'static str, description: &'static str) -> HelpEntry {
+    HelpEntry {
+        operator_only: true,
+        ..entry(Category::Admin, usage, description)
+    }
+}
+
+const ENTRIES: &[HelpEntry] = &[
+    entry(
+        Category::Playback,
+        "/play url:https://youtu.be/dQw4w9WgXcQ",
+        "Join your voice channel and play a link, or queue it if something's playing. Add `resume:true` to continue a long track where it stopped",
+    ),
+    entry(
+        Category::Playback,
+        "/play url:<playlist> pick:true",
+        "Choose which tracks of a playlist to queue",
+    ),
+    entry(
+        Category::Playback,
+        "/play share:<token>",
+        "Queue every track from a shared queue",
+    ),
+    entry(Category::Playback, "/next", "Skip to the next track"),
+    entry(
+        Category::Playback,
+        "/pause",
+        "Pause the playing track; `/resume` carries on from the same spot",
+    ),
+    entry(
+        Category::Playback,
+        "/skipto position:<n>",
+        "Jump straight to a queued track, dropping the ones before it",
+    ),
+    entry(
+        Category::Playback,
+        "/seek timestamp:<time>",
+        "Jump to a point in the playing track, e.g. `1:30` or `1h2m3s`",
+    ),
+    entry(
+        Category::Playback,
+        "/volume [percent]",
+        "Show or set the volume (0-200%, up to the server's cap) for this and later tracks",
+    ),
+    entry(
+        Category::Playback,
+        "/loop [mode]",
+        "Repeat the playing track or the whole queue, or stop repeating",
+    ),
+    entry(
+        Category::Playback,
+        "/cancel",
+        "Stop the track you asked for from downloading (DJs can stop anyone's)",
+    ),
+    entry(
+        Category::Playback,
+        "/move from:<n> to:<n>",
+        "Move a queued track to another place in line (DJs can move anyone's)",
+    ),
+    entry(
+        Category::Playback,
+        "/stop",
+        "Stop, clear the queue and leave the voice channel (Undo for 60s)",
+    ),
+    feature_entry(
+        Category::Playback,
+        "/filter karaoke",
+        "Toggle vocal reduction for tracks queued next (`8d`, `bassboost`, `loudnorm`, `show`, `clear`)",
+        Feature::Filters,
+    ),
+    feature_entry(
+        Category::Playback,
+        "/filter preset name:<name>",
+        "Switch to a saved set of filters (`save`, `presets`, `forget`)",
+        Feature::Filters,
+    ),
+    entry(
+        Category::Playback,
+        "/podcast subscribe url:<feed>",
+        "Follow a podcast; `/podcast latest` and `/podcast episodes` queue its episodes",
+    ),
+    entry(
+        Category::Playback,
+        "/library search query:<words>",
+        "Find tracks in the bot's local music library and pick ones to queue",
+    ),
+    entry(
+        Category::Playback,
+        "/lyrics",
+        "Show the lyrics of the playing track",
+    ),
+    entry(
+        Category::Queue,
+        "/queue show",
+        "See what's playing and up next, and upvote tracks",
+    ),
+    entry(
+        Category::Queue,
+        "/queue share",
+        "Get a token others can use with `/play share:` to copy this queue",
+    ),
+    entry(
+        Category::Queue,
+        "/queue dedupe",
+        "Remove tracks that are queued more than once",
+    ),
+    entry(
+        Category::Queue,
+        "/boost position:3",
+        "Spend your boost to move a track to the front",
+    ),
+    entry(
+        Category::Queue,
+        "/playlist import url:<spotify playlist>",
+        "Import a playlist to queue later with `/playlist show` (`list`, `delete`)",
+    ),
+    entry(
+        Category::Queue,
+        "/playlist save|load|add name:<name>",
+        "Save the queue as a playlist, queue a saved one, or add a track to one",
+    ),
+    entry(
+        Category::Settings,
+        "/theme color value:#5865F2",
+        "Restyle playback messages (`emoji`, `footer`, `show`, `reset`)",
+    ),
+    entry(
+        Category::Settings,
+        "/setup",
+        "Pick the announcement channel, DJ roles, volume and limits in one go",
+    ),
+    entry(
+        Category::Settings,
+        "/quiethours set start:22:00 end:07:00",
+        "Refuse or quieten playback during a daily window",
+    ),
+    entry(
+        Category::Settings,
+        "/approval on",
+        "Have moderators approve requests from non-DJs",
+    ),
+    entry(
+        Category::Settings,
+        "/dj add role:@DJ",
+        "Let a role skip approval",
+    ),
+    entry(
+        Category::Settings,
+        "/dj grant user:@someone hours:2",
+        "Make a member a DJ for a while",
+    ),
+    entry(
+        Category::Settings,
+        "/priority set role:@Supporter level:2",
+        "Put a role's requests ahead in the queue",
+    ),
+    entry(
+        Category::Settings,
+        "/block add track:<link>",
+        "Blacklist a track, or a title keyword with `/block keyword add`",
+    ),
+    entry(
+        Category::Settings,
+        "/musicban add user:@someone hours:24",
+        "Stop a member from using playback commands",
+    ),
+    entry(
+        Category::Settings,
+        "/feature list",
+        "See and toggle experimental features for this server",
+    ),
+    operator_entry(
+        "/announce message:<text>",
+        "Post a notice in every server with an active session",
+    ),
+    operator_entry(
+        "/maintenance on",
+        "Refuse new requests and let current tracks finish before a restart",
+    ),
+    entry(
+        Category::General,
+        "/mystats",
+        "Your request count, listening time and favourite track",
+    ),
+    entry(
+        Category::General,
+        "/wrapped year:2026",
+        "A year in review for this server or yourself",
+    ),
+    entry(
+        Category::General,
+        "/lastfm link",
+        "Scrobble your requests to Last.fm (or ListenBrainz with `/listenbrainz`)",
+    ),
+    entry(
+        Category::General,
+        "/voicedebug",
+        "Check whether stuttering audio is the network or the bot's host",
+    ),
+    entry(
+        Category::General,
+        "/about",
+        "Version, uptime and source code; `/invite` to add the bot elsewhere",
+    ),
+];
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("help").description("Show what the bot can do")
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd.guild_id.map(|g| g.to_string());
+    let (embed, components) = render(
+        Category::Playback,
+        guild_id.as_deref(),
+        &cmd.user.id.to_string(),
+    );
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(embed)
+                .components(components)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Switch the `/help` reply to the category picked in its menu
+pub async fn handle_menu(ctx: &SerenityContext, component: &ComponentInteraction) -> Result<()> {
+    let ComponentInteractionDataKind::StringSelect { values } = &component.data.kind else {
+        return Err(anyhow!("expected a string select"));
+    };
+    let category = values
+        .first()
+        .and_then(|v| Category::from_key(v))
+        .ok_or_else(|| anyhow!("unknown help category"))?;
+    let guild_id = component.guild_id.map(|g| g.to_string());
+    let (embed, components) = render(
+        category,
+        guild_id.as_deref(),
+        &component.user.id.to_string(),
+    );
+    component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .components(components),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+fn render(
+    category: Category,
+    guild_id: Option<&str>,
+    user_id: &str,
+) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let operator = is_bot_operator(user_id);
+    let mut db_conn = establish_connection();
+    let lines: Vec<String> = ENTRIES
+        .iter()
+        .filter(|e| e.category == category)
+        .filter(|e| operator || !e.operator_only)
+        .filter(|e| match (e.feature, guild_id) {
+            (Some(feature), Some(guild_id)) => {
+                features::is_enabled(&mut db_conn, feature, guild_id)
+            }
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .map(|e| format!("`{}`\n{}", e.usage, e.description))
+        .collect();
+    let description = if lines.is_empty() {
+        "Nothing here is available to you.".to_string()
+    } else {
+        lines.join("\n\n")
+    };
+    let embed = CreateEmbed::new()
+        .title(format!("Help: {}", category.label()))
+        .description(description)
+        .colour(0x1db954);
+
+    let options = Category::ALL
+        .into_iter()
+        .filter(|c| *c != Category::Admin || operator)
+        .map(|c| {
+            CreateSelectMenuOption::new(c.label(), c.key())
+                .description(c.description())
+                .default_selection(c == category)
+        })
+        .collect();
+    let menu = CreateSelectMenu::new(HELP_MENU_ID, CreateSelectMenuKind::String { options })
+        .placeholder("Pick a category");
+    (embed, vec![CreateActionRow::SelectMenu(menu)])
+}
@@ -0,0 +1,41 @@
+use anyhow::Result;
+use serenity::all::{
+    ApplicationId, CommandInteraction, Context as SerenityContext, CreateActionRow, CreateButton,
+    CreateCommand, CreateInteractionResponse, CreateInteractionResponseMessage, Permissions,
+};
+
+/// What the bot needs in a server: join and speak in voice, and post its embeds in text channels
+pub const INVITE_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::SEND_MESSAGES)
+    .union(Permissions::EMBED_LINKS)
+    .union(Permissions::CONNECT)
+    .union(Permissions::SPEAK);
+
+pub fn invite_url(app_id: ApplicationId) -> String {
+    format!(
+        "https://discord.com/api/oauth2/authorize?client_id={}&permissions={}&scope=bot%20applications.commands",
+        app_id,
+        INVITE_PERMISSIONS.bits()
+    )
+}
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("invite").description("Get a link to add the bot to another server")
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let url = invite_url(cmd.application_id);
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content("Add me to your server with the button below.")
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new_link(url).label("Invite"),
+                ])])
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
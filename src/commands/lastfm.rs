@@ -0,0 +1,124 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context as SerenityContext, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    EditInteractionResponse,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::{ScrobbleAccount, ScrobbleQueueEntry};
+use crate::scrobble::{ScrobbleService, lastfm};
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("lastfm")
+        .description("Scrobble the tracks you request to Last.fm")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "link",
+            "Connect your Last.fm account",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "verify",
+            "Finish linking after approving lyre on Last.fm",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "unlink",
+            "Disconnect your Last.fm account",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "status",
+            "Show your Last.fm link",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let Some(config) = lastfm::config() else {
+        return super::reject(ctx, cmd, "Last.fm scrobbling isn't configured on this bot").await;
+    };
+    let sub = cmd
+        .data
+        .options
+        .first()
+        .ok_or_else(|| anyhow!("missing subcommand"))?;
+    let user_id = cmd.user.id.to_string();
+    let service = ScrobbleService::LastFm.as_str();
+
+    // Everything here is private to the invoking user
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true)),
+    )
+    .await?;
+
+    let reply = match sub.name.as_str() {
+        "link" => {
+            let token = lastfm::request_token(&config).await?;
+            let url = lastfm::authorize_url(&config, &token);
+            lastfm::remember_pending_token(&user_id, token);
+            format!(
+                "1. [Approve lyre on Last.fm]({})\n2. Run `/lastfm verify` within the hour",
+                url
+            )
+        }
+        "verify" => match lastfm::take_pending_token(&user_id) {
+            None => "No link in progress — run `/lastfm link` first.".to_string(),
+            Some(token) => match lastfm::fetch_session(&config, &token).await {
+                Ok(session) => {
+                    let mut db_conn = establish_connection();
+                    ScrobbleAccount::link(
+                        &mut db_conn,
+                        &user_id,
+                        service,
+                        Some(&session.name),
+                        &session.key,
+                    )?;
+                    format!(
+                        "✅ Linked to Last.fm as **{}**. Tracks you request will be scrobbled.",
+                        session.name
+                    )
+                }
+                Err(e) => {
+                    tracing::info!("Last.fm session request failed for {}: {}", user_id, e);
+                    // Most likely not approved yet; keep the token so they can retry
+                    lastfm::remember_pending_token(&user_id, token);
+                    "❌ Last.fm hasn't confirmed the link yet. Approve lyre using the link from `/lastfm link`, then try again.".to_string()
+                }
+            },
+        },
+        "unlink" => {
+            let mut db_conn = establish_connection();
+            if ScrobbleAccount::unlink(&mut db_conn, &user_id, service)? {
+                "✅ Unlinked your Last.fm account.".to_string()
+            } else {
+                "You don't have a Last.fm account linked.".to_string()
+            }
+        }
+        "status" => {
+            let mut db_conn = establish_connection();
+            match ScrobbleAccount::find(&mut db_conn, &user_id, service)? {
+                Some(account) => {
+                    let pending =
+                        ScrobbleQueueEntry::count_for_user(&mut db_conn, &user_id, service)?;
+                    format!(
+                        "Linked to Last.fm as **{}**{}",
+                        account.username.as_deref().unwrap_or("unknown"),
+                        if pending > 0 {
+                            format!(" ({} scrobble(s) waiting to be sent)", pending)
+                        } else {
+                            String::new()
+                        }
+                    )
+                }
+                None => "You don't have a Last.fm account linked.".to_string(),
+            }
+        }
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+
+    cmd.edit_response(&ctx.http, EditInteractionResponse::new().content(reply))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context as SerenityContext,
+    CreateActionRow, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption, InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::{GuildSettings, LibraryTrack};
+use crate::policy::{check_not_draining, requires_approval};
+
+/// Custom ID of the menu of matches shown by `/library search`; picks are queued by
+/// [`super::pick::handle_pick_menu`] like tracks picked from a playlist
+pub const LIBRARY_MENU_ID: &str = "library:pick";
+/// Matches offered; Discord allows at most 25 options in a select menu
+const SEARCH_LIMIT: i64 = 25;
+/// Discord's limit on select option labels and descriptions
+const MAX_OPTION_LEN: usize = 100;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("library")
+        .description("Find and play tracks from the bot's local music library")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "search",
+                "Search the library by title, artist or album",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::String, "query", "Words to look for")
+                    .required(true),
+            ),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+    if !crate::library::enabled() {
+        return super::reject(ctx, cmd, "This bot doesn't have a local music library").await;
+    }
+
+    let query = args
+        .iter()
+        .find(|o| o.name == "query")
+        .and_then(|o| o.value.as_str())
+        .unwrap_or_default();
+    let words: Vec<String> = query.split_whitespace().map(str::to_string).collect();
+    if words.is_empty() {
+        return super::reject(ctx, cmd, "Give some words to search for").await;
+    }
+
+    let (settings, tracks) = {
+        let mut db_conn = establish_connection();
+        let settings = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+            .ok()
+            .flatten();
+        (
+            settings,
+            LibraryTrack::search(&mut db_conn, &words, SEARCH_LIMIT)?,
+        )
+    };
+    if let Err(e) = check_not_draining() {
+        return super::reject(ctx, cmd, &e.to_string()).await;
+    }
+    // Like playlist picks, several tracks at once can't go through track-by-track review
+    if requires_approval(settings.as_ref(), cmd.member.as_deref()) {
+        return super::reject(
+            ctx,
+            cmd,
+            "Requests need a moderator's approval here, so only DJs can queue from the library",
+        )
+        .await;
+    }
+    if tracks.is_empty() {
+        return super::reject(
+            ctx,
+            cmd,
+            &format!("Nothing in the library matches \"{}\"", query.trim()),
+        )
+        .await;
+    }
+
+    let options: Vec<CreateSelectMenuOption> = tracks
+        .iter()
+        .filter_map(|track| {
+            let id = track.id?;
+            let label = match &track.artist {
+                Some(artist) => format!("{} — {}", track.title, artist),
+                None => track.title.clone(),
+            };
+            let option =
+                CreateSelectMenuOption::new(truncate(&label), crate::library::track_url(id));
+            let details: Vec<String> = track
+                .album
+                .iter()
+                .cloned()
+                .chain(
+                    track
+                        .duration_secs
+                        .map(|secs| super::play::format_position(secs.max(0) as u64)),
+                )
+                .collect();
+            Some(if details.is_empty() {
+                option
+            } else {
+                option.description(truncate(&details.join(" · ")))
+            })
+        })
+        .collect();
+    let count = options.len();
+    let menu = CreateSelectMenu::new(LIBRARY_MENU_ID, CreateSelectMenuKind::String { options })
+        .placeholder("Choose tracks to queue")
+        .min_values(1)
+        .max_values(count as u8);
+
+    // Only the member who searched sees the menu, so only they can pick from it
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(format!(
+                    "Found {} track{} in the library{}:",
+                    count,
+                    if count == 1 { "" } else { "s" },
+                    if count as i64 >= SEARCH_LIMIT {
+                        " (only the first ones are shown; try more words)"
+                    } else {
+                        ""
+                    }
+                ))
+                .components(vec![CreateActionRow::SelectMenu(menu)])
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn truncate(text: &str) -> String {
+    text.chars().take(MAX_OPTION_LEN).collect()
+}
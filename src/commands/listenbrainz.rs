@@ -0,0 +1,124 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context as SerenityContext,
+    CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, EditInteractionResponse,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::{ScrobbleAccount, ScrobbleQueueEntry};
+use crate::scrobble::{ScrobbleService, listenbrainz};
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("listenbrainz")
+        .description("Submit the tracks you request to ListenBrainz")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "link",
+                "Connect your ListenBrainz account",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "token",
+                    "User token from listenbrainz.org/settings",
+                )
+                .required(true),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "unlink",
+            "Disconnect your ListenBrainz account",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "status",
+            "Show your ListenBrainz link",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let sub = cmd
+        .data
+        .options
+        .first()
+        .ok_or_else(|| anyhow!("missing subcommand"))?;
+    let user_id = cmd.user.id.to_string();
+    let service = ScrobbleService::ListenBrainz.as_str();
+
+    // Everything here is private to the invoking user, the token especially
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true)),
+    )
+    .await?;
+
+    let reply = match sub.name.as_str() {
+        "link" => {
+            let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+                return Err(anyhow!("expected subcommand"));
+            };
+            let token = args
+                .iter()
+                .find(|o| o.name == "token")
+                .and_then(|o| o.value.as_str())
+                .map(str::trim)
+                .unwrap_or_default();
+            match listenbrainz::validate_token(token).await {
+                Ok(Some(username)) => {
+                    let mut db_conn = establish_connection();
+                    ScrobbleAccount::link(
+                        &mut db_conn,
+                        &user_id,
+                        service,
+                        Some(&username),
+                        token,
+                    )?;
+                    format!(
+                        "✅ Linked to ListenBrainz as **{}**. Tracks you request will be submitted.",
+                        username
+                    )
+                }
+                Ok(None) => "❌ ListenBrainz didn't accept that token. Copy it from https://listenbrainz.org/settings/ and try again.".to_string(),
+                Err(e) => {
+                    tracing::info!("ListenBrainz token check failed for {}: {}", user_id, e);
+                    "❌ Couldn't reach ListenBrainz right now, please try again later.".to_string()
+                }
+            }
+        }
+        "unlink" => {
+            let mut db_conn = establish_connection();
+            if ScrobbleAccount::unlink(&mut db_conn, &user_id, service)? {
+                "✅ Unlinked your ListenBrainz account.".to_string()
+            } else {
+                "You don't have a ListenBrainz account linked.".to_string()
+            }
+        }
+        "status" => {
+            let mut db_conn = establish_connection();
+            match ScrobbleAccount::find(&mut db_conn, &user_id, service)? {
+                Some(account) => {
+                    let pending =
+                        ScrobbleQueueEntry::count_for_user(&mut db_conn, &user_id, service)?;
+                    format!(
+                        "Linked to ListenBrainz as **{}**{}",
+                        account.username.as_deref().unwrap_or("unknown"),
+                        if pending > 0 {
+                            format!(" ({} listen(s) waiting to be sent)", pending)
+                        } else {
+                            String::new()
+                        }
+                    )
+                }
+                None => "You don't have a ListenBrainz account linked.".to_string(),
+            }
+        }
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+
+    cmd.edit_response(&ctx.http, EditInteractionResponse::new().content(reply))
+        .await?;
+    Ok(())
+}
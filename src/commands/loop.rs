@@ -0,0 +1,94 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context as SerenityContext, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    InteractionContext, Permissions,
+};
+
+use crate::playback_state::{self, LoopMode};
+use crate::voice_manager;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("loop")
+        .description("Show or set whether the playing track or the whole queue repeats")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "mode",
+                "What to repeat; leave out to see the current mode",
+            )
+            .add_string_choice("track", "track")
+            .add_string_choice("queue", "queue")
+            .add_string_choice("off", "off"),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+
+    let mode = cmd
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == "mode")
+        .and_then(|o| o.value.as_str())
+        .and_then(LoopMode::from_key);
+    let Some(mode) = mode else {
+        let current = playback_state::loop_mode(&guild_id);
+        return respond(
+            ctx,
+            cmd,
+            format!("{} {}", icon(current), describe(current, "is")),
+        )
+        .await;
+    };
+
+    voice_manager::set_loop_mode(&guild_id, mode).await?;
+    tracing::info!(
+        "{} set looping to {} in guild {}",
+        cmd.user.id,
+        mode.as_str(),
+        guild_id
+    );
+    respond(
+        ctx,
+        cmd,
+        format!("{} {}", icon(mode), describe(mode, "is now")),
+    )
+    .await
+}
+
+fn icon(mode: LoopMode) -> &'static str {
+    match mode {
+        LoopMode::Off => "➡️",
+        LoopMode::Track => "🔂",
+        LoopMode::Queue => "🔁",
+    }
+}
+
+fn describe(mode: LoopMode, verb: &str) -> String {
+    match mode {
+        LoopMode::Off => format!("Looping {} off.", verb),
+        LoopMode::Track => format!("Looping {} on for the playing track.", verb),
+        LoopMode::Queue => format!(
+            "Looping {} on for the queue: tracks go back on the end once they finish.",
+            verb
+        ),
+    }
+}
+
+async fn respond(ctx: &SerenityContext, cmd: &CommandInteraction, content: String) -> Result<()> {
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content),
+        ),
+    )
+    .await?;
+    Ok(())
+}
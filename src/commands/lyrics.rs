@@ -0,0 +1,86 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, Context as SerenityContext, CreateCommand, CreateEmbedAuthor,
+    CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse,
+    InteractionContext,
+};
+
+use crate::playback_state;
+use crate::theme::{Icon, Theme};
+
+/// Discord's limit on an embed's description
+const MAX_DESCRIPTION_LEN: usize = 4096;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("lyrics")
+        .description("Show the lyrics of the playing track")
+        .contexts(vec![InteractionContext::Guild])
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(playing) = playback_state::now_playing(&guild_id) else {
+        return super::reject(ctx, cmd, "Nothing is playing").await;
+    };
+    let Some(title) = playing.title.clone().or_else(|| playing.url.clone()) else {
+        return super::reject(ctx, cmd, "The playing track has no title to look up").await;
+    };
+
+    // Only the member who asked sees them, so a long song doesn't bury the channel
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true)),
+    )
+    .await?;
+
+    let lyrics = match crate::lyrics::for_track(&title, playing.url.as_deref()).await {
+        Ok(lyrics) => lyrics,
+        Err(e) => {
+            tracing::warn!("Failed to look up lyrics for {:?}: {}", title, e);
+            cmd.edit_response(
+                &ctx.http,
+                EditInteractionResponse::new().content("❌ Couldn't reach the lyrics provider."),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let text = match crate::lyrics::text(&lyrics) {
+        Some(text) => text,
+        None if lyrics.instrumental => "*Instrumental*".to_string(),
+        None => {
+            cmd.edit_response(
+                &ctx.http,
+                EditInteractionResponse::new()
+                    .content(format!("❌ No lyrics found for **{}**.", title)),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let description = match text.char_indices().nth(MAX_DESCRIPTION_LEN - 1) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    };
+    let mut embed = Theme::for_guild(&guild_id)
+        .embed(
+            Icon::NowPlaying,
+            lyrics.title.as_deref().unwrap_or(&title),
+            0x9B59B6, // Amethyst
+        )
+        .description(description)
+        .field("Lyrics from", lyrics.source.clone(), true);
+    if let Some(artist) = &lyrics.artist {
+        embed = embed.author(CreateEmbedAuthor::new(artist));
+    }
+    cmd.edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().embeds(vec![embed]),
+    )
+    .await?;
+    Ok(())
+}
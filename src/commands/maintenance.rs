@@ -0,0 +1,109 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context as SerenityContext,
+    CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, EditInteractionResponse, InteractionContext, Permissions,
+};
+
+use crate::broadcast::{self, MAX_ANNOUNCEMENT_LEN};
+use crate::metrics::METRICS;
+use crate::policy::is_bot_operator;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("maintenance")
+        .description("Pause new playback before a restart (bot operators only)")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "on",
+                "Refuse new requests and let current tracks finish",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "announce",
+                    "Also post this notice in every server with an active session",
+                )
+                .max_length(MAX_ANNOUNCEMENT_LEN as u16),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "off",
+            "Accept new requests again",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "status",
+            "Show whether maintenance mode is on and how many sessions are left",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    if !is_bot_operator(&cmd.user.id.to_string()) {
+        return super::reject(
+            ctx,
+            cmd,
+            "Only the bot's operators can use maintenance mode",
+        )
+        .await;
+    }
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true)),
+    )
+    .await?;
+
+    let reply = match sub.name.as_str() {
+        "on" => {
+            METRICS.set_draining(true);
+            tracing::warn!("Maintenance mode turned on by {}", cmd.user.id);
+            let notice = args
+                .iter()
+                .find(|o| o.name == "announce")
+                .and_then(|o| o.value.as_str())
+                .map(str::trim)
+                .filter(|m| !m.is_empty());
+            let announced = match notice {
+                Some(notice) => match broadcast::announce(notice).await {
+                    Ok(report) => format!(" Notice sent to {} session(s).", report.sent),
+                    Err(e) => format!(" Couldn't send the notice: {}", e),
+                },
+                None => String::new(),
+            };
+            format!(
+                "🛠️ Maintenance mode is on: new requests are refused and readiness reports \"draining\". {} session(s) still active.{}",
+                METRICS.snapshot().active_voice_calls,
+                announced
+            )
+        }
+        "off" => {
+            METRICS.set_draining(false);
+            tracing::warn!("Maintenance mode turned off by {}", cmd.user.id);
+            "✅ Maintenance mode is off; new requests are accepted again.".to_string()
+        }
+        "status" => {
+            if METRICS.is_draining() {
+                format!(
+                    "🛠️ Maintenance mode is on; {} session(s) still active.",
+                    METRICS.snapshot().active_voice_calls
+                )
+            } else {
+                "Maintenance mode is off.".to_string()
+            }
+        }
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+    cmd.edit_response(&ctx.http, EditInteractionResponse::new().content(reply))
+        .await?;
+    Ok(())
+}
@@ -1,3 +1,186 @@
+use anyhow::Result;
+use serenity::all::{
+    Command, CommandInteraction, ComponentInteraction, Context as SerenityContext, CreateCommand,
+    CreateInteractionResponse, CreateInteractionResponseMessage, GuildId, Http, Member, UserId,
+};
+
+pub mod about;
+pub mod announce;
+pub mod approval;
+pub mod block;
+pub mod boost;
+pub mod cancel;
+pub mod dj;
+pub mod feature;
+pub mod filter;
+pub mod help;
+pub mod invite;
+pub mod lastfm;
+pub mod library;
+pub mod listenbrainz;
+pub mod r#loop;
+pub mod lyrics;
+pub mod maintenance;
+pub mod r#move;
+pub mod musicban;
+pub mod mystats;
 pub mod next;
+pub mod pause;
+pub mod pick;
 pub mod play;
+pub mod playlist;
+pub mod podcast;
+pub mod priority;
+pub mod queue;
+pub mod quiethours;
+pub mod resume;
+pub mod seek;
+pub mod setup;
+pub mod skipto;
 pub mod stop;
+#[cfg(test)]
+mod testing;
+pub mod theme;
+pub mod voicedebug;
+pub mod volume;
+pub mod wrapped;
+
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+use crate::policy::{PolicyError, check_command_roles, check_user_not_banned};
+
+/// Commands that control playback: refused to members banned with `/musicban`, and the ones a
+/// guild can lock to roles with `command_roles`
+pub const PLAYBACK_COMMANDS: &[&str] = &[
+    "play", "next", "skipto", "pause", "resume", "seek", "stop", "volume", "loop", "cancel",
+    "move", "podcast", "library", "boost", "filter",
+];
+
+/// Every slash command the bot offers
+pub fn definitions() -> Vec<CreateCommand> {
+    vec![
+        play::definition(),
+        next::definition(),
+        skipto::definition(),
+        pause::definition(),
+        resume::definition(),
+        seek::definition(),
+        volume::definition(),
+        r#loop::definition(),
+        cancel::definition(),
+        r#move::definition(),
+        stop::definition(),
+        block::definition(),
+        musicban::definition(),
+        mystats::definition(),
+        wrapped::definition(),
+        lastfm::definition(),
+        listenbrainz::definition(),
+        playlist::definition(),
+        podcast::definition(),
+        library::definition(),
+        lyrics::definition(),
+        queue::definition(),
+        boost::definition(),
+        priority::definition(),
+        setup::definition(),
+        dj::definition(),
+        approval::definition(),
+        quiethours::definition(),
+        feature::definition(),
+        theme::definition(),
+        filter::definition(),
+        help::definition(),
+        about::definition(),
+        invite::definition(),
+        voicedebug::definition(),
+        announce::definition(),
+        maintenance::definition(),
+    ]
+}
+
+/// Replace the bot's slash commands with [`definitions`], globally or, given `guild_id`, in that
+/// guild only, where changes show up at once instead of as Discord rolls them out. Commands no
+/// longer defined are removed. Returns the names registered.
+pub async fn register(http: &Http, guild_id: Option<GuildId>) -> Result<Vec<String>> {
+    let registered = match guild_id {
+        Some(guild_id) => guild_id.set_commands(http, definitions()).await?,
+        None => Command::set_global_commands(http, definitions()).await?,
+    };
+    Ok(registered.into_iter().map(|command| command.name).collect())
+}
+
+/// Reject the interaction if the invoking member is banned from playback or lacks the roles the
+/// guild locked the command to; returns whether to proceed
+pub async fn allow_playback(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<bool> {
+    let Some(guild_id) = cmd.guild_id else {
+        return Ok(true);
+    };
+    match check_playback(guild_id, cmd.user.id, &cmd.data.name, cmd.member.as_deref()) {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            reject(ctx, cmd, &e.to_string()).await?;
+            Ok(false)
+        }
+    }
+}
+
+/// [`allow_playback`] for a button that acts like `command`, e.g. undoing a `/stop`
+pub async fn allow_playback_button(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+    command: &str,
+) -> Result<bool> {
+    let Some(guild_id) = component.guild_id else {
+        return Ok(true);
+    };
+    match check_playback(
+        guild_id,
+        component.user.id,
+        command,
+        component.member.as_ref(),
+    ) {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            component
+                .create_response(
+                    &ctx.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content(format!("❌ {}", e))
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+            Ok(false)
+        }
+    }
+}
+
+fn check_playback(
+    guild_id: GuildId,
+    user_id: UserId,
+    command: &str,
+    member: Option<&Member>,
+) -> Result<(), PolicyError> {
+    let mut db_conn = establish_connection();
+    let settings = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+        .ok()
+        .flatten();
+    check_user_not_banned(&mut db_conn, &guild_id.to_string(), &user_id.to_string())
+        .and_then(|()| check_command_roles(settings.as_ref(), command, member))
+}
+
+/// Answer a not-yet-acknowledged interaction with an ephemeral error, e.g. after input validation
+pub async fn reject(ctx: &SerenityContext, cmd: &CommandInteraction, message: &str) -> Result<()> {
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(format!("❌ {}", message))
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context as SerenityContext, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::{CurrentQueue, GuildSettings};
+use crate::policy::is_dj;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("move")
+        .description("Move a queued track to another place in line")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "from",
+                "Queue position of the track, as shown by /queue show",
+            )
+            .min_int_value(1)
+            .required(true),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "to",
+                "Position to move it to; 1 plays it next",
+            )
+            .min_int_value(1)
+            .required(true),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let option = |name: &str| {
+        cmd.data
+            .options
+            .iter()
+            .find(|o| o.name == name)
+            .and_then(|o| o.value.as_i64())
+            .ok_or_else(|| anyhow!("missing {}", name))
+    };
+    let (from, to) = (option("from")?, option("to")?);
+
+    let (settings, mut pending) = {
+        let mut db_conn = establish_connection();
+        let settings = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+            .ok()
+            .flatten();
+        let pending: Vec<CurrentQueue> =
+            CurrentQueue::get_guild_queue(&mut db_conn, &guild_id.to_string())?
+                .into_iter()
+                .filter(|e| e.position > 0)
+                .collect();
+        (settings, pending)
+    };
+    let Some(index) = pending.iter().position(|e| e.position as i64 == from) else {
+        return super::reject(
+            ctx,
+            cmd,
+            &format!("There's no track waiting at position {}", from),
+        )
+        .await;
+    };
+    // Anyone can move their own request; moving other people's is up to DJs
+    if pending[index].added_by != cmd.user.id.to_string()
+        && !is_dj(settings.as_ref(), cmd.member.as_deref())
+    {
+        return super::reject(ctx, cmd, "Only DJs can move tracks other people queued").await;
+    }
+
+    let entry = pending.remove(index);
+    let target = (to as usize).min(pending.len() + 1) - 1;
+    let title = entry.title.clone().unwrap_or_else(|| entry.url.clone());
+    pending.insert(target, entry);
+
+    let ids: Vec<i32> = pending.iter().filter_map(|e| e.id).collect();
+    CurrentQueue::reorder_pending(&mut establish_connection(), &guild_id.to_string(), &ids)?;
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    if let Some(call_lock) = manager.get(guild_id) {
+        let uuids: Vec<String> = pending
+            .iter()
+            .filter_map(|e| e.track_uuid.clone())
+            .collect();
+        super::queue::apply_live_order(&call_lock, &uuids).await;
+    }
+    tracing::info!(
+        "{} moved queue position {} to {} in guild {}",
+        cmd.user.id,
+        from,
+        target + 1,
+        guild_id
+    );
+
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(format!(
+                "↕️ Moved **{}** to position {}",
+                title,
+                target + 1
+            )),
+        ),
+    )
+    .await?;
+    Ok(())
+}
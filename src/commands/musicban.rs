@@ -0,0 +1,175 @@
+use anyhow::{Result, anyhow};
+use chrono::{Duration, Utc};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext, Permissions,
+    UserId,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::MusicBan;
+
+/// Longest timed ban, one year; omit `hours` for a permanent ban
+const MAX_BAN_HOURS: u64 = 24 * 365;
+const LIST_LIMIT: usize = 25;
+
+pub fn definition() -> CreateCommand {
+    let member = || {
+        CreateCommandOption::new(CommandOptionType::User, "user", "Member to ban or unban")
+            .required(true)
+    };
+    CreateCommand::new("musicban")
+        .description("Stop members from using music commands on this server")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "add", "Ban a member")
+                .add_sub_option(member())
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "hours",
+                        "Lift the ban automatically after this many hours",
+                    )
+                    .min_int_value(1)
+                    .max_int_value(MAX_BAN_HOURS),
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "reason",
+                    "Shown to the member when they try to use music commands",
+                )),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "Lift a ban")
+                .add_sub_option(member()),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show active bans",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    let mut db_conn = establish_connection();
+    let reply = match sub.name.as_str() {
+        "add" => {
+            let user_id = user_arg(args)?;
+            if user_id == cmd.user.id {
+                return super::reject(ctx, cmd, "You can't ban yourself").await;
+            }
+            let hours = args
+                .iter()
+                .find(|o| o.name == "hours")
+                .and_then(|o| o.value.as_i64());
+            let reason = args
+                .iter()
+                .find(|o| o.name == "reason")
+                .and_then(|o| o.value.as_str());
+            let expires_at = hours.map(|h| (Utc::now() + Duration::hours(h)).naive_utc());
+
+            MusicBan::ban(
+                &mut db_conn,
+                &guild_id,
+                &user_id.to_string(),
+                reason,
+                &cmd.user.id.to_string(),
+                expires_at,
+            )?;
+            match hours {
+                Some(h) => format!(
+                    "🔇 <@{}> can't use music commands for {} hour(s)",
+                    user_id, h
+                ),
+                None => format!("🔇 <@{}> can't use music commands until unbanned", user_id),
+            }
+        }
+        "remove" => {
+            let user_id = user_arg(args)?;
+            if MusicBan::unban(&mut db_conn, &guild_id, &user_id.to_string())? {
+                format!("✅ <@{}> can use music commands again", user_id)
+            } else {
+                format!("<@{}> isn't banned", user_id)
+            }
+        }
+        "list" => {
+            let bans = MusicBan::list_active_for_guild(&mut db_conn, &guild_id)?;
+            if bans.is_empty() {
+                "No members are banned from music commands.".to_string()
+            } else {
+                let lines: Vec<String> = bans
+                    .iter()
+                    .take(LIST_LIMIT)
+                    .map(|b| {
+                        let until = b
+                            .expires_at
+                            .map(|t| format!("until <t:{}:f>", t.and_utc().timestamp()))
+                            .unwrap_or_else(|| "permanent".to_string());
+                        match &b.reason {
+                            Some(reason) => format!("• <@{}> — {} — {}", b.user_id, until, reason),
+                            None => format!("• <@{}> — {}", b.user_id, until),
+                        }
+                    })
+                    .collect();
+                let mut embed = CreateEmbed::new()
+                    .title("🔇 Music Bans")
+                    .description(lines.join("\n"))
+                    .colour(0xed4245);
+                if bans.len() > LIST_LIMIT {
+                    embed = embed.footer(serenity::all::CreateEmbedFooter::new(format!(
+                        "Showing {} of {}",
+                        LIST_LIMIT,
+                        bans.len()
+                    )));
+                }
+                return respond(
+                    ctx,
+                    cmd,
+                    CreateInteractionResponseMessage::new().embed(embed),
+                )
+                .await;
+            }
+        }
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+
+    respond(
+        ctx,
+        cmd,
+        CreateInteractionResponseMessage::new().content(reply),
+    )
+    .await
+}
+
+fn user_arg(args: &[CommandDataOption]) -> Result<UserId> {
+    args.iter()
+        .find(|o| o.name == "user")
+        .and_then(|o| o.value.as_user_id())
+        .ok_or_else(|| anyhow!("missing user argument"))
+}
+
+async fn respond(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    message: CreateInteractionResponseMessage,
+) -> Result<()> {
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(message.ephemeral(true)),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction, Context as SerenityContext, CreateCommand, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::QueueHistory;
+use crate::stats::{format_listening_time, summarize};
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("mystats").description("Show your personal listening statistics")
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    // Scope to the current server when invoked in one, otherwise everything the user played
+    let guild_id = cmd.guild_id.map(|g| g.to_string());
+    let history = {
+        let mut db_conn = establish_connection();
+        QueueHistory::find_for_stats(
+            &mut db_conn,
+            guild_id.as_deref(),
+            Some(&cmd.user.id.to_string()),
+            None,
+        )?
+    };
+    let stats = summarize(&history, 1);
+
+    let embed = if stats.plays == 0 {
+        CreateEmbed::new()
+            .title("📊 Your Listening Stats")
+            .description("You haven't requested any tracks yet. Try `/play`!")
+            .colour(0x808080)
+    } else {
+        let most_played = stats
+            .top_tracks
+            .first()
+            .map(|t| {
+                format!(
+                    "[{}]({}) ({} play{})",
+                    t.title.as_deref().unwrap_or("Unknown"),
+                    t.url,
+                    t.plays,
+                    if t.plays == 1 { "" } else { "s" }
+                )
+            })
+            .unwrap_or_else(|| "—".to_string());
+        let favorite_hour = stats
+            .top_hour
+            .map(|h| format!("{:02}:00–{:02}:59 UTC", h, h))
+            .unwrap_or_else(|| "—".to_string());
+
+        CreateEmbed::new()
+            .title("📊 Your Listening Stats")
+            .field("Tracks requested", stats.plays.to_string(), true)
+            .field(
+                "Listening time",
+                format_listening_time(stats.total_seconds),
+                true,
+            )
+            .field("Favorite hour", favorite_hour, true)
+            .field("Most played", most_played, false)
+            .colour(0x1db954)
+            .footer(CreateEmbedFooter::new(if guild_id.is_some() {
+                "Stats for this server"
+            } else {
+                "Stats across all servers"
+            }))
+    };
+
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(embed)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
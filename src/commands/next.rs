@@ -1,12 +1,17 @@
+use crate::fade;
 use crate::metrics::METRICS;
+use crate::theme::{Icon, Theme};
 use anyhow::{Result, anyhow};
 use serenity::all::{
     CommandInteraction, Context as SerenityContext, CreateCommand, CreateEmbed,
-    CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext, Permissions,
 };
 
 pub fn definition() -> CreateCommand {
-    CreateCommand::new("next").description("Skip to the next queued track")
+    CreateCommand::new("next")
+        .description("Skip to the next queued track")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
 }
 
 pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
@@ -29,6 +34,7 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
         return Ok(());
     };
 
+    fade::fade_out(&call_lock).await;
     let call = call_lock.lock().await;
     let queue = call.queue();
     let res = queue.skip();
@@ -45,36 +51,15 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
             if queue_len_after == 0 {
                 // No more songs, disconnect
                 let _ = manager.remove(guild_id).await;
-
-                let embed = CreateEmbed::new()
-                    .title("⏭️ Queue Ended")
-                    .description("Skipped to next song, but the queue is now empty. Disconnected from voice channel.")
-                    .colour(0xFF6B6B); // Red
-
-                cmd.edit_response(
-                    &ctx.http,
-                    serenity::all::EditInteractionResponse::new().embeds(vec![embed]),
-                )
-                .await
-                .ok();
-                return Ok(());
-            } else {
-                let embed = CreateEmbed::new()
-                    .title("⏭️ Skipped to Next")
-                    .description(format!(
-                        "Now playing the next song. {} song(s) remaining in queue.",
-                        queue_len_after
-                    ))
-                    .colour(0x00FF7F); // Spring green
-
-                cmd.edit_response(
-                    &ctx.http,
-                    serenity::all::EditInteractionResponse::new().embeds(vec![embed]),
-                )
-                .await
-                .ok();
-                return Ok(());
             }
+            let embed = skipped_embed(&Theme::for_guild(&guild_id.to_string()), queue_len_after);
+            cmd.edit_response(
+                &ctx.http,
+                serenity::all::EditInteractionResponse::new().embeds(vec![embed]),
+            )
+            .await
+            .ok();
+            return Ok(());
         }
         Err(e) => format!("Nothing to skip: {e}"),
     };
@@ -87,3 +72,49 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
     .ok();
     Ok(())
 }
+
+/// The reply to a skip that left `remaining` tracks queued; with none left the bot has left voice
+fn skipped_embed(theme: &Theme, remaining: usize) -> CreateEmbed {
+    if remaining == 0 {
+        theme
+            .embed(Icon::Skip, "Queue Ended", 0xFF6B6B) // Red
+            .description(
+                "Skipped to next song, but the queue is now empty. Disconnected from voice channel.",
+            )
+    } else {
+        theme
+            .embed(Icon::Skip, "Skipped to Next", 0x00FF7F) // Spring green
+            .description(format!(
+                "Now playing the next song. {} song(s) remaining in queue.",
+                remaining
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::skipped_embed;
+    use crate::theme::Theme;
+
+    #[test]
+    fn skip_reply_says_what_is_left() {
+        let embed = serde_json::to_value(skipped_embed(&Theme::default(), 2)).unwrap();
+        assert!(
+            embed["title"]
+                .as_str()
+                .unwrap()
+                .ends_with("Skipped to Next")
+        );
+        assert!(
+            embed["description"]
+                .as_str()
+                .unwrap()
+                .contains("2 song(s) remaining"),
+            "{embed}"
+        );
+
+        let embed = serde_json::to_value(skipped_embed(&Theme::default(), 0)).unwrap();
+        assert!(embed["title"].as_str().unwrap().ends_with("Queue Ended"));
+        assert_eq!(embed["color"], 0xFF6B6B);
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, Context as SerenityContext, CreateCommand, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+use super::play::format_position;
+use crate::theme::{Icon, Theme};
+use crate::voice_manager;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("pause")
+        .description("Pause the playing track without losing the queue")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    set_paused(ctx, cmd, true).await
+}
+
+/// Pause or resume the guild's playing track for `/pause` and `/resume`
+pub(super) async fn set_paused(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    paused: bool,
+) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let Some(state) = voice_manager::set_paused(&guild_id.to_string(), paused).await? else {
+        return super::reject(ctx, cmd, "Nothing is playing.").await;
+    };
+
+    let title = state.title.as_deref().unwrap_or("the current track");
+    let position = format_position(state.position_secs);
+    let embed = if paused {
+        Theme::for_guild(&guild_id.to_string())
+            .embed(Icon::Pause, "Paused", 0xF1C40F) // Yellow
+            .description(format!(
+                "Paused **{}** at {}. Use `/resume` to carry on.",
+                title, position
+            ))
+    } else {
+        Theme::for_guild(&guild_id.to_string())
+            .embed(Icon::Resume, "Resumed", 0x1db954)
+            .description(format!("Playing **{}** from {}.", title, position))
+    };
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().embed(embed)),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,257 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, ComponentInteraction, ComponentInteractionDataKind,
+    Context as SerenityContext, CreateActionRow, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption, EditInteractionResponse,
+};
+
+use crate::audio::{PlaylistEntry, ytdlp_list_playlist};
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+use crate::metadata_cache;
+use crate::policy::{check_not_draining, check_source_allowed, requires_approval};
+use crate::validation::validate_media_url;
+
+/// Custom ID of the track picker shown by `/play pick:true`
+pub const PICK_MENU_ID: &str = "playlist:pick";
+/// Entries offered by the picker; Discord allows at most 25 options in a select menu
+const PICK_LIMIT: usize = 25;
+/// Discord's limit on select option labels and values
+const MAX_OPTION_LEN: usize = 100;
+/// Skipped tracks listed individually in the summary before collapsing into "and N more"
+const MAX_SKIPPED_LISTED: usize = 10;
+
+/// Whether `url` points at a playlist (or album/set) rather than a single track
+pub fn looks_like_playlist(url: &str) -> bool {
+    let Ok(parsed) = url::Url::parse(url.trim()) else {
+        return false;
+    };
+    parsed.query_pairs().any(|(key, _)| key == "list")
+        || parsed
+            .path_segments()
+            .into_iter()
+            .flatten()
+            .any(|segment| matches!(segment, "playlist" | "sets" | "album"))
+}
+
+/// Answer `/play url:<playlist> pick:true` with a menu of the playlist's first tracks; the ones
+/// the member picks are queued by [`handle_pick_menu`]
+pub async fn show_picker(ctx: &SerenityContext, cmd: &CommandInteraction, url: &str) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let parsed_url = match validate_media_url(url) {
+        Ok(parsed) => parsed,
+        Err(e) => return super::reject(ctx, cmd, &e.to_string()).await,
+    };
+    let settings = {
+        let mut db_conn = establish_connection();
+        GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+            .ok()
+            .flatten()
+    };
+    // Each picked track is checked again when it's queued
+    if let Err(e) =
+        check_not_draining().and_then(|_| check_source_allowed(&parsed_url, settings.as_ref()))
+    {
+        return super::reject(ctx, cmd, &e.to_string()).await;
+    }
+    // Several tracks at once can't sensibly go through track-by-track review
+    if requires_approval(settings.as_ref(), cmd.member.as_deref()) {
+        return super::reject(
+            ctx,
+            cmd,
+            "Requests need a moderator's approval here, so only DJs can queue from playlists",
+        )
+        .await;
+    }
+
+    // Only the member who asked sees the menu, so only they can pick from it
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true)),
+    )
+    .await?;
+
+    let playlist = match ytdlp_list_playlist(url.trim(), PICK_LIMIT).await {
+        Ok(playlist) => playlist,
+        Err(e) => {
+            tracing::info!("Failed to list playlist {}: {}", url, e);
+            cmd.edit_response(
+                &ctx.http,
+                EditInteractionResponse::new().content("❌ Couldn't read that playlist."),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    // Values carry the URL, so entries with URLs too long for one can't be offered
+    let entries: Vec<&PlaylistEntry> = playlist
+        .entries
+        .iter()
+        .filter(|e| e.url.len() <= MAX_OPTION_LEN)
+        .take(PICK_LIMIT)
+        .collect();
+    if entries.is_empty() {
+        cmd.edit_response(
+            &ctx.http,
+            EditInteractionResponse::new().content("❌ That playlist has no tracks to pick from."),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let options: Vec<CreateSelectMenuOption> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let title = entry.title.as_deref().unwrap_or(&entry.url);
+            let label: String = format!("{}. {}", i + 1, title)
+                .chars()
+                .take(MAX_OPTION_LEN)
+                .collect();
+            let option = CreateSelectMenuOption::new(label, entry.url.clone());
+            match entry.duration {
+                Some(secs) => option.description(super::play::format_position(secs as u64)),
+                None => option,
+            }
+        })
+        .collect();
+    let menu = CreateSelectMenu::new(PICK_MENU_ID, CreateSelectMenuKind::String { options })
+        .placeholder("Choose tracks to queue")
+        .min_values(1)
+        .max_values(entries.len() as u8);
+
+    let name = playlist.title.as_deref().unwrap_or("this playlist");
+    let more = if playlist.entries.len() > entries.len() {
+        " (only the first ones can be picked)"
+    } else {
+        ""
+    };
+    cmd.edit_response(
+        &ctx.http,
+        EditInteractionResponse::new()
+            .content(format!(
+                "Pick the tracks from **{}** to queue{}:",
+                name, more
+            ))
+            .components(vec![CreateActionRow::SelectMenu(menu)]),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Queue the tracks picked from a `/play pick:true` or `/library search` menu and post a summary
+/// in the channel
+pub async fn handle_pick_menu(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+) -> Result<()> {
+    let ComponentInteractionDataKind::StringSelect { values } = &component.data.kind else {
+        return Err(anyhow!("expected a string select"));
+    };
+    let guild_id = component
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?;
+    let urls = values.clone();
+    let from = if component.data.custom_id == super::library::LIBRARY_MENU_ID {
+        "the library"
+    } else {
+        "a playlist"
+    };
+
+    component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "Queueing {} track{}…",
+                        urls.len(),
+                        if urls.len() == 1 { "" } else { "s" }
+                    ))
+                    .components(Vec::new()),
+            ),
+        )
+        .await?;
+
+    let priority = {
+        let mut db_conn = establish_connection();
+        let settings = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+            .ok()
+            .flatten();
+        match (settings, &component.member) {
+            (Some(settings), Some(member)) => {
+                let roles: Vec<String> = member.roles.iter().map(|r| r.to_string()).collect();
+                settings.queue_priority_for(&roles)
+            }
+            _ => 0,
+        }
+    };
+    let (channel_id, user_id) = (component.channel_id, component.user.id);
+    if let Err(e) = super::play::join_member_channel(ctx, guild_id, user_id).await {
+        component
+            .edit_response(
+                &ctx.http,
+                EditInteractionResponse::new().content(format!("❌ Couldn't join voice: {}", e)),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    // Each track is downloaded before the next is queued; have their titles ready meanwhile
+    metadata_cache::prefetch(guild_id.to_string(), urls.clone());
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let mut queued = 0;
+    let mut skipped = Vec::new();
+    for url in &urls {
+        // The queue may have played out and disconnected while we were downloading
+        if manager.get(guild_id).is_none() {
+            break;
+        }
+        match super::play::enqueue_quietly(ctx, guild_id, channel_id, user_id, url, priority).await
+        {
+            Ok(_) => queued += 1,
+            Err(e) => {
+                tracing::info!("Skipped picked track {} in guild {}: {}", url, guild_id, e);
+                skipped.push(format!("{} ({})", url, e));
+            }
+        }
+    }
+
+    let _ = component
+        .edit_response(
+            &ctx.http,
+            EditInteractionResponse::new().content(format!(
+                "✅ Queued {} of {} picked track{}.",
+                queued,
+                urls.len(),
+                if urls.len() == 1 { "" } else { "s" }
+            )),
+        )
+        .await;
+
+    let mut description = format!(
+        "<@{}> queued {} track{} from {}",
+        user_id,
+        queued,
+        if queued == 1 { "" } else { "s" },
+        from
+    );
+    if !skipped.is_empty() {
+        description.push_str("\n\n**Skipped:**\n");
+        for line in skipped.iter().take(MAX_SKIPPED_LISTED) {
+            description.push_str(&format!("• {}\n", line));
+        }
+        if skipped.len() > MAX_SKIPPED_LISTED {
+            description.push_str(&format!("…and {} more", skipped.len() - MAX_SKIPPED_LISTED));
+        }
+    }
+    let embed = CreateEmbed::new()
+        .title("📥 Picked tracks queued")
+        .description(description)
+        .colour(0x1db954);
+    let _ = channel_id
+        .send_message(&ctx.http, CreateMessage::new().embeds(vec![embed]))
+        .await;
+    Ok(())
+}
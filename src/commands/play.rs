@@ -1,16 +1,57 @@
 use anyhow::{Result, anyhow};
+use serde_json::json;
 use serenity::all::{
-    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context as SerenityContext,
-    CreateCommand, CreateCommandOption, CreateEmbed, CreateMessage, EditInteractionResponse,
+    ButtonStyle, ChannelId, CommandInteraction, CommandOptionType, ComponentInteraction,
+    Context as SerenityContext, CreateActionRow, CreateButton, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    EditInteractionResponse, EditMessage, GuildId, InteractionContext, MessageId, Permissions,
+    UserId,
 };
 use serenity::async_trait;
-use songbird::{Event, EventContext, EventHandler as VoiceEventHandler, Songbird};
+use songbird::tracks::{LoopState, PlayMode, Track, TrackHandle};
+use songbird::{Call, Event, EventContext, EventHandler as VoiceEventHandler, Songbird};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
 
-use crate::audio::{DownloadProgress, spawn_download_mp3, ytdlp_extract_title};
+use crate::audio::{DownloadLane, DownloadPhase, DownloadProgress, DownloadResult, TrackMetadata};
+use crate::broadcast;
+use crate::capacity::{self, Admission};
 use crate::database::establish_connection;
-use crate::database::models::{CurrentQueue, QueueHistory, SongCache, VoiceConnection};
+use crate::database::models::{
+    CurrentQueue, DEFAULT_VOLUME, DownloadJob, GuildSettings, HistoryStatus, PlaybackBookmark,
+    QueueHistory, QueueStatus, SongCache, VoiceConnection,
+};
+use crate::downloads;
+use crate::fade;
+use crate::filters;
+use crate::gapless;
+use crate::guild_log::{self, LogEvent};
+use crate::hooks::{self, HookEvent};
 use crate::metrics::METRICS;
+use crate::playback_state::{self, LoopMode};
+use crate::policy::{
+    QuietHours, active_quiet_hours, check_duration, check_explicit_content, check_not_draining,
+    check_quiet_hours, check_source_allowed, check_title_keywords, check_track_not_blocked,
+    check_upload_in_guild, explicit_filter_enabled, max_duration_minutes, requires_approval,
+};
+use crate::scrobble::{
+    Listen, enqueue_listen, has_scrobble_accounts, is_scrobble_eligible, parse_listen,
+};
+use crate::source;
+use crate::stage;
+use crate::theme::{Icon, Theme};
+use crate::validation::validate_media_url;
+use crate::voice_state;
+
+/// Tracks at least this long (audiobooks, DJ sets, podcasts) get a resume bookmark when they're
+/// skipped or stopped part-way
+const BOOKMARK_MIN_DURATION_SECS: i32 = 20 * 60;
+/// Positions this close to the start or end of a track aren't worth bookmarking
+const BOOKMARK_MARGIN_SECS: u64 = 60;
+/// Custom ID prefix of the "Resume" button offered when a bookmarked track is queued; followed
+/// by `<track uuid>:<seconds>`
+pub const RESUME_BUTTON_PREFIX: &str = "resume:";
 
 struct TrackEndNotifier {
     guild_id: serenity::all::GuildId,
@@ -21,10 +62,71 @@ struct TrackEndNotifier {
 
 #[async_trait]
 impl VoiceEventHandler for TrackEndNotifier {
-    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        // With the queue looping, a track that played to the end goes back on the end of it
+        let mut requeued = false;
         // Advance the queue in database
         {
             let mut db_conn = establish_connection();
+            if let EventContext::Track([(state, handle), ..]) = ctx {
+                playback_state::track_ended(&self.guild_id.to_string(), &handle.uuid().to_string());
+                let status = match state.playing {
+                    PlayMode::End => HistoryStatus::Completed,
+                    PlayMode::Errored(_) => HistoryStatus::Failed,
+                    // Stopped without ever playing: taken out of the queue, or cleared by `/stop`
+                    _ if state.play_time.is_zero() => HistoryStatus::Removed,
+                    _ => HistoryStatus::Skipped,
+                };
+                if let Err(e) = QueueHistory::mark_finished(
+                    &mut db_conn,
+                    &self.guild_id.to_string(),
+                    &handle.uuid().to_string(),
+                    status,
+                ) {
+                    tracing::warn!("Failed to record track end in history: {}", e);
+                }
+                if status == HistoryStatus::Completed
+                    && playback_state::loop_mode(&self.guild_id.to_string()) == LoopMode::Queue
+                    && let Some(request) = QueueHistory::find_by_track(
+                        &mut db_conn,
+                        &self.guild_id.to_string(),
+                        &handle.uuid().to_string(),
+                    )
+                    .ok()
+                    .flatten()
+                {
+                    requeued = self.requeue(request);
+                }
+                if let PlayMode::Errored(e) = &state.playing {
+                    let request = QueueHistory::find_by_track(
+                        &mut db_conn,
+                        &self.guild_id.to_string(),
+                        &handle.uuid().to_string(),
+                    )
+                    .ok()
+                    .flatten();
+                    guild_log::record(
+                        self.guild_id,
+                        LogEvent::TrackFailed {
+                            title: request.as_ref().and_then(|r| r.title.clone()),
+                            url: request.map(|r| r.url).unwrap_or_default(),
+                            reason: e.to_string(),
+                        },
+                    );
+                }
+            }
+            // Tracks taken out of the queue before playing (e.g. by `/queue dedupe`) have no row
+            // left, and their ending mustn't advance past the track that's actually playing
+            if let EventContext::Track([(_, handle), ..]) = ctx
+                && !CurrentQueue::has_track(
+                    &mut db_conn,
+                    &self.guild_id.to_string(),
+                    &handle.uuid().to_string(),
+                )
+                .unwrap_or(true)
+            {
+                return None;
+            }
             if let Err(e) = CurrentQueue::advance_queue(&mut db_conn, &self.guild_id.to_string()) {
                 tracing::warn!("Failed to advance queue in database: {}", e);
             }
@@ -36,49 +138,296 @@ impl VoiceEventHandler for TrackEndNotifier {
             let queue_len = call.queue().len();
             drop(call);
 
-            if queue_len == 0 {
+            if queue_len == 0 && !requeued {
                 // Queue is empty, disconnect
                 let _ = self.manager.remove(self.guild_id).await;
+                stage::session_ended(self.guild_id).await;
+                guild_log::record(
+                    self.guild_id,
+                    LogEvent::AutoDisconnect {
+                        reason: "The queue finished, so I left the voice channel.".to_string(),
+                    },
+                );
 
                 // Update database to mark as not playing
-                {
-                    let mut db_conn = establish_connection();
-                    if let Err(e) = VoiceConnection::update_playing_status(
-                        &mut db_conn,
-                        &self.guild_id.to_string(),
-                        false,
-                        None,
-                    ) {
-                        tracing::warn!("Failed to update playing status on disconnect: {}", e);
-                    }
-                }
+                voice_state::set_playing(&self.guild_id.to_string(), false, None);
 
                 // Send a message to the channel
-                let embed = CreateEmbed::new()
-                    .title("🎵 Queue Finished")
+                let embed = Theme::for_guild(&self.guild_id.to_string())
+                    .embed(Icon::QueueFinished, "Queue Finished", 0x808080) // Gray
                     .description(
                         "All songs have finished playing. Disconnected from voice channel.",
-                    )
-                    .colour(0x808080); // Gray
+                    );
 
                 let _ = self
                     .channel_id
                     .send_message(&self.http, CreateMessage::new().embeds(vec![embed]))
                     .await;
             } else {
+                super::queue::reorder_by_votes(&call_lock, self.guild_id).await;
+
                 // Update database with next track info if available
                 let mut db_conn = establish_connection();
                 if let Ok(Some(next_track)) =
                     CurrentQueue::get_current_track(&mut db_conn, &self.guild_id.to_string())
-                    && let Err(e) = VoiceConnection::update_playing_status(
-                        &mut db_conn,
+                {
+                    voice_state::set_playing(
                         &self.guild_id.to_string(),
                         true,
                         next_track.title.as_deref(),
-                    )
+                    );
+                }
+            }
+        }
+        None
+    }
+}
+
+impl TrackEndNotifier {
+    /// Queue a finished request again in the background, as its requester; false if there's no
+    /// Discord context to do it with
+    fn requeue(&self, request: QueueHistory) -> bool {
+        let Some(ctx) = broadcast::context() else {
+            return false;
+        };
+        let Ok(user_id) = request.user_id.parse::<u64>() else {
+            return false;
+        };
+        let (guild_id, channel_id) = (self.guild_id, self.channel_id);
+        tokio::spawn(async move {
+            if let Err(e) = enqueue_quietly(
+                &ctx,
+                guild_id,
+                channel_id,
+                UserId::new(user_id),
+                &request.url,
+                0,
+            )
+            .await
+            {
+                tracing::warn!(
+                    "Failed to requeue {} for the queue loop in guild {}: {}",
+                    request.url,
+                    guild_id,
+                    e
+                );
+            }
+        });
+        true
+    }
+}
+
+/// Loops a track that starts while the guild is looping its track
+struct LoopOnStart {
+    guild_id: String,
+}
+
+#[async_trait]
+impl VoiceEventHandler for LoopOnStart {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx
+            && playback_state::loop_mode(&self.guild_id) == LoopMode::Track
+        {
+            for (state, handle) in tracks.iter() {
+                if state.loops == LoopState::Finite(0)
+                    && let Err(e) = handle.enable_loop()
+                {
+                    tracing::warn!("Failed to loop track in guild {}: {}", self.guild_id, e);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Puts the guild's playback position back to the start each time a looping track repeats
+struct RestartOnLoop {
+    guild_id: String,
+}
+
+#[async_trait]
+impl VoiceEventHandler for RestartOnLoop {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        playback_state::set_position(&self.guild_id, Duration::ZERO, false);
+        None
+    }
+}
+
+/// Records in queue history and the guild's playback state when the track begins playing
+struct RecordStart {
+    guild_id: String,
+    url: String,
+    title: String,
+    duration: Option<i32>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for RecordStart {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            let mut db_conn = establish_connection();
+            for (_, handle) in tracks.iter() {
+                let uuid = handle.uuid().to_string();
+                playback_state::track_started(
+                    &self.guild_id,
+                    &uuid,
+                    Some(&self.title),
+                    Some(&self.url),
+                    self.duration,
+                );
+                if let Err(e) = QueueHistory::mark_started(&mut db_conn, &self.guild_id, &uuid) {
+                    tracing::warn!("Failed to record track start in history: {}", e);
+                }
+                if let Err(e) = CurrentQueue::set_track_status(
+                    &mut db_conn,
+                    &self.guild_id,
+                    &uuid,
+                    QueueStatus::Playing,
+                ) {
+                    tracing::warn!("Failed to mark queue entry as playing: {}", e);
+                }
+            }
+            // Discord may take a while to answer; don't hold up the track
+            if let Ok(guild_id) = self.guild_id.parse::<u64>() {
+                let title = self.title.clone();
+                tokio::spawn(async move {
+                    stage::track_started(GuildId::new(guild_id), &title).await;
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Runs the `track_start` hooks when the track begins playing
+struct HookOnStart {
+    guild_id: String,
+    data: serde_json::Value,
+}
+
+#[async_trait]
+impl VoiceEventHandler for HookOnStart {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        hooks::emit(HookEvent::TrackStart, &self.guild_id, self.data.clone());
+        None
+    }
+}
+
+/// Queues a scrobble for the requester once the track has been played long enough
+struct ScrobbleOnEnd {
+    user_id: String,
+    listen: Listen,
+}
+
+#[async_trait]
+impl VoiceEventHandler for ScrobbleOnEnd {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx
+            && let Some((state, _)) = tracks.first()
+            && is_scrobble_eligible(state.play_time, self.listen.duration)
+        {
+            let played_at = chrono::Utc::now().naive_utc()
+                - chrono::Duration::from_std(state.play_time).unwrap_or_default();
+            enqueue_listen(&self.user_id, &self.listen, played_at);
+        }
+        None
+    }
+}
+
+/// The volume the guild's quiet hours cap tracks at right now, if they're on and cap it
+pub(crate) fn quiet_hours_cap(guild_id: &str) -> Option<f32> {
+    let settings = {
+        let mut db_conn = establish_connection();
+        GuildSettings::find_by_guild_id(&mut db_conn, guild_id)
+            .ok()
+            .flatten()
+    };
+    match active_quiet_hours(settings.as_ref(), chrono::Utc::now()) {
+        Some(QuietHours::CapVolume(cap)) => Some(cap),
+        _ => None,
+    }
+}
+
+/// Turns a track down when it starts during the guild's quiet hours, if they cap the volume
+struct QuietHoursVolume {
+    guild_id: String,
+}
+
+#[async_trait]
+impl VoiceEventHandler for QuietHoursVolume {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx
+            && let Some(cap) = quiet_hours_cap(&self.guild_id)
+        {
+            for (state, handle) in tracks.iter() {
+                if state.volume > cap
+                    && let Err(e) = handle.set_volume(cap)
+                {
+                    tracing::warn!("Failed to apply quiet hours volume: {}", e);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Raises a track queued silent up to its volume once it starts playing
+struct FadeIn {
+    guild_id: String,
+    volume: f32,
+}
+
+#[async_trait]
+impl VoiceEventHandler for FadeIn {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx
+            && let Some((_, handle)) = tracks.first()
+        {
+            let target = match quiet_hours_cap(&self.guild_id) {
+                Some(cap) => self.volume.min(cap),
+                None => self.volume,
+            };
+            let handle = (*handle).clone();
+            tokio::spawn(async move { fade::ramp(&handle, 0.0, target).await });
+        }
+        None
+    }
+}
+
+/// Saves where a long track was left off when it's skipped or stopped, and forgets the bookmark
+/// once the track plays to the end
+struct BookmarkOnEnd {
+    guild_id: String,
+    url: String,
+    duration: i32,
+}
+
+#[async_trait]
+impl VoiceEventHandler for BookmarkOnEnd {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx
+            && let Some((state, _)) = tracks.first()
+        {
+            let position = state.position.as_secs();
+            let mut db_conn = establish_connection();
+            let result = match state.playing {
+                PlayMode::Stop
+                    if position >= BOOKMARK_MARGIN_SECS
+                        && position + BOOKMARK_MARGIN_SECS < self.duration as u64 =>
                 {
-                    tracing::warn!("Failed to update playing status with next track: {}", e);
+                    PlaybackBookmark::save(
+                        &mut db_conn,
+                        &self.guild_id,
+                        &self.url,
+                        position as i32,
+                        Some(self.duration),
+                    )
                 }
+                PlayMode::End => PlaybackBookmark::remove(&mut db_conn, &self.guild_id, &self.url),
+                _ => Ok(0),
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to update bookmark for {}: {}", self.url, e);
             }
         }
         None
@@ -86,31 +435,152 @@ impl VoiceEventHandler for TrackEndNotifier {
 }
 
 pub fn definition() -> CreateCommand {
-    let opt =
-        CreateCommandOption::new(CommandOptionType::String, "url", "URL to play").required(true);
+    let opt = CreateCommandOption::new(CommandOptionType::String, "url", "URL to play");
+    let resume = CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        "resume",
+        "Continue a long track from where it was last skipped or stopped",
+    );
+    let share = CreateCommandOption::new(
+        CommandOptionType::String,
+        "share",
+        "Import a queue shared with /queue share, by its token",
+    );
+    let pick = CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        "pick",
+        "For a playlist link, choose which of its first 25 tracks to queue",
+    );
     CreateCommand::new("play")
         .description("Queue and play audio from a URL")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
         .add_option(opt)
+        .add_option(resume)
+        .add_option(share)
+        .add_option(pick)
 }
 
 pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    match play_request(cmd) {
+        PlayRequest::Share(token) => super::queue::import_share(ctx, cmd, token).await,
+        PlayRequest::Pick(url) => super::pick::show_picker(ctx, cmd, url).await,
+        PlayRequest::Url { url, resume } => play_url(ctx, cmd, url, resume).await,
+        PlayRequest::Missing => {
+            super::reject(
+                ctx,
+                cmd,
+                "Give a `url` to play, or a `share` token to import",
+            )
+            .await
+        }
+    }
+}
+
+/// What a `/play` invocation asks for
+#[derive(Debug, PartialEq)]
+enum PlayRequest<'a> {
+    /// Import a shared queue
+    Share(&'a str),
+    /// Choose tracks from a playlist before queueing them
+    Pick(&'a str),
+    Url {
+        url: &'a str,
+        resume: bool,
+    },
+    Missing,
+}
+
+fn play_request(cmd: &CommandInteraction) -> PlayRequest<'_> {
+    let option = |name: &str| cmd.data.options.iter().find(|o| o.name == name);
+    if let Some(token) = option("share").and_then(|o| o.value.as_str()) {
+        return PlayRequest::Share(token);
+    }
+    let Some(url) = option("url").and_then(|o| o.value.as_str()) else {
+        return PlayRequest::Missing;
+    };
+    let flag = |name: &str| {
+        option(name)
+            .and_then(|o| o.value.as_bool())
+            .unwrap_or(false)
+    };
+    if flag("pick") && super::pick::looks_like_playlist(url) {
+        return PlayRequest::Pick(url);
+    }
+    PlayRequest::Url {
+        url,
+        resume: flag("resume"),
+    }
+}
+
+/// Run the full `/play` pipeline (policy checks, voice join, download, queue bookkeeping) for
+/// `url` on behalf of the invoking member. Commands that resolve a URL themselves, like
+/// `/podcast`, call this before acknowledging their interaction. With `resume`, playback starts
+/// from the guild's bookmark for `url` if there is one.
+pub async fn play_url(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    url: &str,
+    resume: bool,
+) -> Result<()> {
     // Log some diagnostic information
     tracing::info!(
-        "Processing /play command for user {} in guild {:?}",
+        "Processing /{} command for user {} in guild {:?}",
+        cmd.data.name,
         cmd.user.id,
         cmd.guild_id
     );
 
-    let url = match cmd.data.options.first() {
-        Some(option) => match &option.value {
-            CommandDataOptionValue::String(url) => url,
-            _ => return Err(anyhow!("expected string URL")),
-        },
-        None => return Err(anyhow!("missing URL argument")),
+    let parsed_url = match validate_media_url(url) {
+        Ok(parsed) => parsed,
+        Err(e) => return super::reject(ctx, cmd, &e.to_string()).await,
     };
+    let url = url.trim();
 
     let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in guild"))?;
 
+    // Enforce the operator/guild source allowlist and the guild's track blacklist
+    // before touching yt-dlp
+    let settings = {
+        let mut db_conn = establish_connection();
+        let settings = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+            .ok()
+            .flatten();
+        if let Err(e) = check_not_draining()
+            .and_then(|_| check_quiet_hours(settings.as_ref()))
+            .and_then(|_| check_source_allowed(&parsed_url, settings.as_ref()))
+            .and_then(|_| check_track_not_blocked(&mut db_conn, &guild_id.to_string(), &parsed_url))
+            .and_then(|_| check_upload_in_guild(&mut db_conn, &guild_id.to_string(), &parsed_url))
+        {
+            return super::reject(ctx, cmd, &e.to_string()).await;
+        }
+        settings
+    };
+
+    let priority = super::queue::requester_priority(settings.as_ref(), cmd);
+
+    // In moderation mode, requests from non-DJs wait for a moderator instead of playing
+    if requires_approval(settings.as_ref(), cmd.member.as_deref()) {
+        return super::approval::submit(ctx, cmd, url, priority, settings.as_ref()).await;
+    }
+
+    // Starting a new voice session needs a free slot when the operator caps concurrent sessions
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    if manager.get(guild_id).is_none()
+        && let Admission::Waiting { position } =
+            capacity::admit(guild_id, cmd.channel_id, manager.iter().count())
+    {
+        return super::reject(
+            ctx,
+            cmd,
+            &format!(
+                "The bot is at capacity right now and you are #{} in line. I'll post here when a slot opens up.",
+                position
+            ),
+        )
+        .await;
+    }
+
     // Check bot's permissions first
     let bot_id = ctx.cache.current_user().id;
     {
@@ -128,6 +598,41 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
     // Defer response immediately to give us more time
     cmd.defer(&ctx.http).await?;
 
+    // Screen the track before joining voice or downloading anything
+    let explicit_filter = explicit_filter_enabled(settings.as_ref());
+    let screened = if explicit_filter || max_duration_minutes(&parsed_url).is_some() {
+        let metadata = match source::extract_metadata(url).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                tracing::warn!("Failed to fetch metadata for content checks: {}", e);
+                cmd.edit_response(
+                    &ctx.http,
+                    EditInteractionResponse::new()
+                        .content("❌ Couldn't check this track against the server's content rules"),
+                )
+                .await?;
+                return Ok(());
+            }
+        };
+        let checked = if explicit_filter {
+            check_explicit_content(&metadata)
+        } else {
+            Ok(())
+        }
+        .and_then(|_| check_duration(&parsed_url, &metadata));
+        if let Err(e) = checked {
+            cmd.edit_response(
+                &ctx.http,
+                EditInteractionResponse::new().content(format!("❌ {}", e)),
+            )
+            .await?;
+            return Ok(());
+        }
+        Some(metadata)
+    } else {
+        None
+    };
+
     // Get the user's voice channel
     let channel_id = {
         let guild = ctx
@@ -180,131 +685,659 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
         }
     }
 
-    let manager = songbird::get(ctx).await.unwrap().clone();
     // Only count a connection if we weren't already connected
     let is_new = manager.get(guild_id).is_none();
 
     // Check if we're already connected to avoid unnecessary joins
     let call_lock = if let Some(existing_call) = manager.get(guild_id) {
+        // Discord keeps a bot in one voice channel per server, so there's one session per guild
+        // and a second channel can't have its own queue
+        let session_channel = existing_call
+            .lock()
+            .await
+            .current_channel()
+            .map(|c| ChannelId::new(c.0.get()));
+        if let Some(session_channel) = session_channel.filter(|c| *c != channel_id) {
+            // Already deferred, so the refusal replaces the "thinking…" message
+            cmd.edit_response(
+                &ctx.http,
+                EditInteractionResponse::new().content(format!(
+                    "❌ I'm already playing in <#{}>; I can only be in one voice channel per server, so join that one to add to its queue.",
+                    session_channel
+                )),
+            )
+            .await?;
+            return Ok(());
+        }
         tracing::info!(
             "Already connected to voice channel in guild {}, reusing connection",
             guild_id
         );
         existing_call
     } else {
-        // Retry voice channel joining with exponential backoff
-        let mut attempts = 0;
-        let max_attempts = 5; // Increased from 3 to 5
-
-        loop {
-            tracing::info!(
-                "Attempting to join voice channel {} in guild {} (attempt {}/{})",
-                channel_id,
-                guild_id,
-                attempts + 1,
-                max_attempts
-            );
-
-            match manager.join(guild_id, channel_id).await {
-                Ok(call_lock) => {
-                    tracing::info!(
-                        "Successfully joined voice channel after {} attempt(s)",
-                        attempts + 1
-                    );
-
-                    // Update database to track voice connection
-                    let mut db_conn = establish_connection();
-                    if let Err(e) = VoiceConnection::create_or_update(
-                        &mut db_conn,
-                        &guild_id.to_string(),
-                        Some(&channel_id.to_string()),
-                    ) {
-                        tracing::warn!("Failed to update database with voice connection: {}", e);
-                    }
-
-                    break call_lock;
-                }
-                Err(e) => {
-                    attempts += 1;
-                    if attempts >= max_attempts {
-                        return Err(anyhow!(
-                            "failed to join voice channel after {} attempts: {}. This may be due to network issues, Discord API problems, or insufficient bot permissions.",
-                            max_attempts,
-                            e
-                        ));
-                    }
-
-                    let delay_ms = std::cmp::min(5000, 1000 * (2_u64.pow(attempts as u32 - 1))); // Exponential backoff with cap at 5s
-                    tracing::warn!(
-                        "Voice channel join attempt {} failed: {}. Retrying in {}ms...",
-                        attempts,
-                        e,
-                        delay_ms
-                    );
-
-                    // Wait before retrying (exponential backoff with cap)
-                    tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
-                }
-            }
-        }
+        join_with_retry(&manager, guild_id, channel_id).await?
     };
 
-    if is_new {
-        METRICS.inc_connections();
-    } else {
+    if !is_new {
         // Update last activity for existing connection
-        let mut db_conn = establish_connection();
-        if let Err(e) = VoiceConnection::update_last_activity(&mut db_conn, &guild_id.to_string()) {
-            tracing::warn!("Failed to update last activity for voice connection: {}", e);
-        }
+        voice_state::touch(&guild_id.to_string());
     }
 
+    // Remember the download until it's queued, so a restart doesn't orphan its message
+    let _job = RecordedJob::record(ctx, cmd, guild_id, url, priority).await;
+
     // Start download in background and stream progress to the deferred message
-    let (mut rx, handle) = spawn_download_mp3(url.to_string());
+    let (mut rx, handle) = source::fetch(url.to_string(), DownloadLane::Now);
+    let in_flight = downloads::register(
+        &guild_id.to_string(),
+        url,
+        &cmd.user.id.to_string(),
+        handle.abort_handle(),
+    );
 
     // Check song cache first for title and metadata
     let mut db_conn = establish_connection();
-    let cached_title = SongCache::find_by_url(&mut db_conn, url)
+    let cached = SongCache::find_by_url(&mut db_conn, url)
         .ok()
         .flatten()
         .map(|cached| {
             tracing::info!("Using cached title for {}: {}", url, cached.title);
             // Update last accessed time
             let _ = SongCache::update_last_accessed(&mut db_conn, url);
-            cached.title
+            (cached.title, cached.duration)
         });
 
-    // Try to get song title - use cache if available, otherwise extract in parallel
-    let title_future = if cached_title.is_some() {
-        None // We already have the title
+    // Try to get title and duration - use cache or screening if available, otherwise extract
+    // in parallel
+    let metadata_future = if cached.is_some() || screened.is_some() {
+        None // We already have them
     } else {
-        Some(ytdlp_extract_title(url))
+        Some(source::extract_metadata(url))
     };
 
     // Progress loop: update message periodically while downloading
-    while let Some(DownloadProgress { percent }) = rx.recv().await {
-        let bar = text_bar(percent);
+    let cancel_button = CreateActionRow::Buttons(vec![
+        CreateButton::new(format!(
+            "{}{}",
+            super::cancel::CANCEL_BUTTON_PREFIX,
+            in_flight.id()
+        ))
+        .label("Cancel")
+        .style(ButtonStyle::Danger),
+    ]);
+    while let Some(progress) = rx.recv().await {
+        downloads::publish(&guild_id.to_string(), url, &progress);
+        let components = if progress.phase == DownloadPhase::Ready {
+            vec![]
+        } else {
+            vec![cancel_button.clone()]
+        };
         let _ = cmd
             .edit_response(
                 &ctx.http,
                 EditInteractionResponse::new()
-                    .content(format!("Downloading… {} {}%", bar, percent)),
+                    .content(progress_message(&progress))
+                    .components(components),
             )
             .await;
     }
 
-    // Download finished
-    let input_path = handle
+    // Download finished, or was cancelled with the button, `/cancel` or the API
+    let joined = handle.await;
+    if joined.as_ref().is_err_and(|e| e.is_cancelled()) {
+        tracing::info!("Download of {} in guild {} was cancelled", url, guild_id);
+        if is_new {
+            leave_unused_session(&manager, guild_id).await;
+        }
+        cmd.edit_response(
+            &ctx.http,
+            EditInteractionResponse::new()
+                .content(format!("🚫 Cancelled downloading <{}>", url))
+                .components(vec![]),
+        )
+        .await?;
+        return Ok(());
+    }
+    let download = downloads::outcome(joined)?;
+
+    // Get actual title and duration (cached or extracted)
+    let metadata = match metadata_future {
+        Some(future) => future.await.ok(),
+        None => screened,
+    };
+    let (title, duration) = match (cached, &metadata) {
+        (Some(cached), _) => cached,
+        (None, Some(metadata)) => (metadata.title.clone(), metadata.duration_secs()),
+        (None, None) => ("Unknown".to_string(), None),
+    };
+
+    // Keyword blocks need the title, so they can only be checked once it's resolved
+    if let Err(e) = check_title_keywords(&title, settings.as_ref()) {
+        tracing::info!(
+            "Rejected {} in guild {} from user {}: title {:?} matched a blocked keyword",
+            url,
+            guild_id,
+            cmd.user.id,
+            title
+        );
+        if is_new {
+            leave_unused_session(&manager, guild_id).await;
+        }
+        cmd.edit_response(
+            &ctx.http,
+            EditInteractionResponse::new().content(format!("❌ {}", e)),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let track = enqueue_ready(
+        ctx,
+        &call_lock,
+        &manager,
+        guild_id,
+        cmd.channel_id,
+        cmd.user.id,
+        ReadyTrack {
+            url,
+            download,
+            title: &title,
+            duration,
+            metadata: metadata.as_ref(),
+            priority,
+        },
+    )
+    .await?;
+
+    // Pick up where a long track was left off, or offer to
+    let bookmark = {
+        let mut db_conn = establish_connection();
+        PlaybackBookmark::find(&mut db_conn, &guild_id.to_string(), url)
+            .ok()
+            .flatten()
+    };
+    let mut description = title.clone();
+    if priority > 0 {
+        description.push_str("\n⭐ Priority request, queued ahead of regular requests");
+    }
+    let mut components = Vec::new();
+    if let Some(bookmark) = &bookmark {
+        let position = format_position(bookmark.position as u64);
+        if resume {
+            // Fire and forget: the seek is applied once the input is ready
+            let _ = track.seek(Duration::from_secs(bookmark.position as u64));
+            description.push_str(&format!("\n⏩ Resuming from {}", position));
+        } else {
+            components.push(CreateActionRow::Buttons(vec![
+                CreateButton::new(format!(
+                    "{}{}:{}",
+                    RESUME_BUTTON_PREFIX,
+                    track.uuid(),
+                    bookmark.position
+                ))
+                .label(format!("Resume from {}", position))
+                .emoji('⏩')
+                .style(ButtonStyle::Secondary),
+            ]));
+        }
+    }
+
+    // Send success message
+    let theme = Theme::for_guild(&guild_id.to_string());
+    let position = track
+        .get_info()
         .await
-        .map_err(|e| anyhow!("download task panicked: {e}"))??;
+        .map_err(|e| anyhow!("failed to get track info: {e}"))?
+        .position;
+    let embed = theme
+        .embed(Icon::NowPlaying, "Now Playing", 0x1db954) // Spotify green
+        .description(description)
+        .url(url)
+        .footer(theme.footer_with(&format!(
+            "Queue position: {:?} | Duration: Streaming",
+            position
+        )));
+
+    cmd.edit_response(
+        &ctx.http,
+        EditInteractionResponse::new()
+            .content("")
+            .embeds(vec![embed])
+            .components(components),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Join `channel_id`, retrying with exponential backoff, and record the connection
+async fn join_with_retry(
+    manager: &Songbird,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<Arc<Mutex<Call>>> {
+    crate::metrics::watch_voice_connection(manager, guild_id).await;
+    crate::voice_manager::apply_voice_flags(manager, guild_id).await;
+
+    // Retry voice channel joining with exponential backoff
+    let mut attempts = 0;
+    let max_attempts = 5; // Increased from 3 to 5
+
+    loop {
+        tracing::info!(
+            "Attempting to join voice channel {} in guild {} (attempt {}/{})",
+            channel_id,
+            guild_id,
+            attempts + 1,
+            max_attempts
+        );
+
+        match manager.join(guild_id, channel_id).await {
+            Ok(call_lock) => {
+                tracing::info!(
+                    "Successfully joined voice channel after {} attempt(s)",
+                    attempts + 1
+                );
+                {
+                    let mut call = call_lock.lock().await;
+                    crate::voice_stats::install(&mut call, guild_id);
+                    crate::voice_permissions::install(&mut call, guild_id);
+                }
+
+                // Update database to track voice connection
+                let mut db_conn = establish_connection();
+                if let Err(e) = VoiceConnection::create_or_update(
+                    &mut db_conn,
+                    &guild_id.to_string(),
+                    Some(&channel_id.to_string()),
+                ) {
+                    tracing::warn!("Failed to update database with voice connection: {}", e);
+                }
+
+                return Ok(call_lock);
+            }
+            Err(e) => {
+                attempts += 1;
+                if attempts >= max_attempts {
+                    return Err(anyhow!(
+                        "failed to join voice channel after {} attempts: {}. This may be due to network issues, Discord API problems, or insufficient bot permissions.",
+                        max_attempts,
+                        e
+                    ));
+                }
+
+                let delay_ms = std::cmp::min(5000, 1000 * (2_u64.pow(attempts as u32 - 1))); // Exponential backoff with cap at 5s
+                tracing::warn!(
+                    "Voice channel join attempt {} failed: {}. Retrying in {}ms...",
+                    attempts,
+                    e,
+                    delay_ms
+                );
+
+                // Wait before retrying (exponential backoff with cap)
+                tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+            }
+        }
+    }
+}
+
+/// Make sure the bot is in voice for `guild_id`, joining `user_id`'s voice channel if it isn't
+/// connected yet. Used when a track is queued on someone's behalf after the fact, e.g. an
+/// approved request.
+pub async fn join_member_channel(
+    ctx: &SerenityContext,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<()> {
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    if manager.get(guild_id).is_some() {
+        return Ok(());
+    }
+    let channel_id = ctx
+        .cache
+        .guild(guild_id)
+        .and_then(|guild| {
+            guild
+                .voice_states
+                .get(&user_id)
+                .and_then(|vs| vs.channel_id)
+        })
+        .ok_or_else(|| anyhow!("the requester is no longer in a voice channel"))?;
+    if !capacity::try_admit(guild_id, manager.iter().count()) {
+        return Err(anyhow!("the bot is at capacity right now"));
+    }
+    join_with_retry(&manager, guild_id, channel_id).await?;
+    Ok(())
+}
+
+/// Don't leave the bot idling in a channel it only joined for a track that isn't coming
+async fn leave_unused_session(manager: &Songbird, guild_id: GuildId) {
+    if manager.remove(guild_id).await.is_ok() {
+        let mut db_conn = establish_connection();
+        if let Err(e) = VoiceConnection::disconnect(&mut db_conn, &guild_id.to_string()) {
+            tracing::warn!(
+                "Failed to update database when disconnecting from voice: {}",
+                e
+            );
+        }
+    }
+}
+
+/// Downloads older than this when the bot comes back aren't restarted; the requester has most
+/// likely moved on
+const RESUME_WINDOW_MINUTES: i64 = 30;
+
+/// A `/play` download recorded in `download_jobs` for as long as this lives; see
+/// [`resume_interrupted_downloads`]
+struct RecordedJob(Option<i32>);
+
+impl RecordedJob {
+    async fn record(
+        ctx: &SerenityContext,
+        cmd: &CommandInteraction,
+        guild_id: GuildId,
+        url: &str,
+        priority: i32,
+    ) -> Self {
+        let message_id = cmd
+            .get_response(&ctx.http)
+            .await
+            .ok()
+            .map(|message| message.id.to_string());
+        match DownloadJob::create(
+            &mut establish_connection(),
+            &guild_id.to_string(),
+            &cmd.channel_id.to_string(),
+            &cmd.user.id.to_string(),
+            url,
+            priority,
+            message_id.as_deref(),
+        ) {
+            Ok(job) => RecordedJob(job.id),
+            Err(e) => {
+                tracing::warn!("Failed to record download job: {}", e);
+                RecordedJob(None)
+            }
+        }
+    }
+}
+
+impl Drop for RecordedJob {
+    fn drop(&mut self) {
+        if let Some(id) = self.0
+            && let Err(e) = DownloadJob::delete(&mut establish_connection(), id)
+        {
+            tracing::warn!("Failed to clear download job {}: {}", id, e);
+        }
+    }
+}
+
+/// Pick up the `/play` downloads a restart cut short. Each recent one is queued again (from the
+/// cache if it had finished) and its progress message updated, or a new message posted where
+/// it can't be edited; older ones are just marked as interrupted.
+pub async fn resume_interrupted_downloads(ctx: SerenityContext) {
+    let jobs = match DownloadJob::take_all(&mut establish_connection()) {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            tracing::warn!("Failed to load interrupted downloads: {}", e);
+            return;
+        }
+    };
+    if !jobs.is_empty() {
+        tracing::info!(
+            "Resuming {} download(s) interrupted by a restart",
+            jobs.len()
+        );
+    }
+    let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::minutes(RESUME_WINDOW_MINUTES);
+    for mut job in jobs {
+        let status = if job.created_at < cutoff {
+            format!(
+                "⌛ <@{}> the bot restarted while downloading {}; use `/play` to try again.",
+                job.user_id, job.url
+            )
+        } else {
+            post_job_status(
+                &ctx,
+                &mut job,
+                "🔄 The bot restarted mid-download; picking it up again…".to_string(),
+            )
+            .await;
+            match resume_job(&ctx, &job).await {
+                Ok(title) => format!("✅ Queued **{}** after a restart", title),
+                Err(e) => format!(
+                    "❌ <@{}> couldn't resume {} after a restart: {}",
+                    job.user_id, job.url, e
+                ),
+            }
+        };
+        post_job_status(&ctx, &mut job, status).await;
+    }
+}
+
+async fn resume_job(ctx: &SerenityContext, job: &DownloadJob) -> Result<String> {
+    let guild_id = GuildId::new(job.guild_id.parse()?);
+    let channel_id = ChannelId::new(job.channel_id.parse()?);
+    let user_id = UserId::new(job.user_id.parse()?);
+    join_member_channel(ctx, guild_id, user_id).await?;
+    enqueue_quietly(ctx, guild_id, channel_id, user_id, &job.url, job.priority).await
+}
+
+/// Show `content` in place of the job's progress message, or in a new message in its channel
+/// (which later updates then edit) if that one can't be edited
+async fn post_job_status(ctx: &SerenityContext, job: &mut DownloadJob, content: String) {
+    let Ok(channel_id) = job.channel_id.parse::<u64>().map(ChannelId::new) else {
+        return;
+    };
+    if let Some(message_id) = job
+        .message_id
+        .as_deref()
+        .and_then(|m| m.parse::<u64>().ok())
+    {
+        let edit = EditMessage::new()
+            .content(&content)
+            .embeds(vec![])
+            .components(vec![]);
+        match channel_id
+            .edit_message(&ctx.http, MessageId::new(message_id), edit)
+            .await
+        {
+            Ok(_) => return,
+            Err(e) => tracing::debug!("Couldn't edit interrupted download message: {}", e),
+        }
+    }
+    match channel_id.say(&ctx.http, content).await {
+        Ok(message) => job.message_id = Some(message.id.to_string()),
+        Err(e) => tracing::warn!("Failed to post resumed download status: {}", e),
+    }
+}
+
+/// Queue `url` behind whatever is already playing, with no interaction to report progress to
+/// (e.g. the rest of an imported share). The bot must already be in voice; the guild's source,
+/// blacklist and content rules apply as they do for `/play`, and `priority` places it as for
+/// `GuildSettings::queue_priority_for`. Returns the track title.
+pub async fn enqueue_quietly(
+    ctx: &SerenityContext,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    user_id: UserId,
+    url: &str,
+    priority: i32,
+) -> Result<String> {
+    let parsed_url = validate_media_url(url)?;
+    let url = url.trim();
+
+    let (settings, cached) = {
+        let mut db_conn = establish_connection();
+        let settings = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+            .ok()
+            .flatten();
+        check_not_draining()
+            .and_then(|_| check_quiet_hours(settings.as_ref()))
+            .and_then(|_| check_source_allowed(&parsed_url, settings.as_ref()))
+            .and_then(|_| check_track_not_blocked(&mut db_conn, &guild_id.to_string(), &parsed_url))
+            .and_then(|_| {
+                check_upload_in_guild(&mut db_conn, &guild_id.to_string(), &parsed_url)
+            })?;
+        let cached = SongCache::find_by_url(&mut db_conn, url)
+            .ok()
+            .flatten()
+            .map(|cached| (cached.title, cached.duration));
+        (settings, cached)
+    };
+
+    // Unlike `/play`, a track that can't be screened is skipped rather than reported
+    let explicit_filter = explicit_filter_enabled(settings.as_ref());
+    let screen = explicit_filter || max_duration_minutes(&parsed_url).is_some();
+    let metadata = if screen || cached.is_none() {
+        match source::extract_metadata(url).await {
+            Ok(metadata) => Some(metadata),
+            Err(e) if screen => return Err(e),
+            Err(e) => {
+                tracing::warn!("Failed to fetch metadata for {}: {}", url, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if let Some(metadata) = &metadata {
+        if explicit_filter {
+            check_explicit_content(metadata)?;
+        }
+        check_duration(&parsed_url, metadata)?;
+    }
+    let (title, duration) = match (cached, &metadata) {
+        (Some(cached), _) => cached,
+        (None, Some(metadata)) => (metadata.title.clone(), metadata.duration_secs()),
+        (None, None) => ("Unknown".to_string(), None),
+    };
+    check_title_keywords(&title, settings.as_ref())?;
+
+    // Nobody is waiting on it unless the guild has nothing else to play
+    let idle = CurrentQueue::get_current_track(&mut establish_connection(), &guild_id.to_string())
+        .map(|current| current.is_none())
+        .unwrap_or(false);
+    let lane = if idle {
+        DownloadLane::Now
+    } else {
+        DownloadLane::Background
+    };
+    let (mut rx, handle) = source::fetch(url.to_string(), lane);
+    let _in_flight = downloads::register(
+        &guild_id.to_string(),
+        url,
+        &user_id.to_string(),
+        handle.abort_handle(),
+    );
+    while let Some(progress) = rx.recv().await {
+        downloads::publish(&guild_id.to_string(), url, &progress);
+    }
+    let download = match downloads::outcome(handle.await) {
+        Ok(download) => download,
+        Err(e) => {
+            guild_log::record(
+                guild_id,
+                LogEvent::TrackFailed {
+                    title: Some(title),
+                    url: url.to_string(),
+                    reason: e.to_string(),
+                },
+            );
+            return Err(e);
+        }
+    };
+
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let call_lock = manager
+        .get(guild_id)
+        .ok_or_else(|| anyhow!("no longer connected to voice"))?;
+    enqueue_ready(
+        ctx,
+        &call_lock,
+        &manager,
+        guild_id,
+        channel_id,
+        user_id,
+        ReadyTrack {
+            url,
+            download,
+            title: &title,
+            duration,
+            metadata: metadata.as_ref(),
+            priority,
+        },
+    )
+    .await?;
+    Ok(title)
+}
+
+/// A downloaded track and what's known about it, ready to go into a guild's queue
+struct ReadyTrack<'a> {
+    url: &'a str,
+    download: DownloadResult,
+    title: &'a str,
+    /// From the extractor's metadata; the length probed from the download wins when known
+    duration: Option<i32>,
+    metadata: Option<&'a TrackMetadata>,
+    /// Queue priority from the requester's roles; see `GuildSettings::queue_priority_for`
+    priority: i32,
+}
+
+/// Enqueue a downloaded track with its end-of-track handlers, and record it in the queue,
+/// history and song cache tables
+async fn enqueue_ready(
+    ctx: &SerenityContext,
+    call_lock: &Arc<Mutex<Call>>,
+    manager: &Arc<Songbird>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    user_id: UserId,
+    ready: ReadyTrack<'_>,
+) -> Result<TrackHandle> {
+    let ReadyTrack {
+        url,
+        download,
+        title,
+        duration,
+        metadata,
+        priority,
+    } = ready;
+    let duration = download.duration_secs().or(duration);
+
+    // Play the guild's filtered rendering if it has filters on, falling back to the original
+    let enabled = filters::for_guild(&guild_id.to_string());
+    let input_path = match filters::apply(&download.path, &enabled).await {
+        Ok(path) => path,
+        Err(e) => {
+            tracing::warn!("Failed to apply audio filters in guild {}: {}", guild_id, e);
+            download.path.clone()
+        }
+    };
 
     // Create input from the downloaded file path using ffmpeg with specific parameters for consistent playback
     let source = songbird::input::File::new(input_path);
 
+    // Tracks start at the guild's volume from `/volume` or its settings
+    let volume = {
+        let mut db_conn = establish_connection();
+        GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+            .ok()
+            .flatten()
+            .map(|s| s.start_volume())
+            .unwrap_or(DEFAULT_VOLUME)
+    };
+
+    // Straight after a track of the same album it takes over without a gap; otherwise, with
+    // fades on, it starts silent and `FadeIn` raises it to that volume
+    let gapless = priority == 0 && gapless::continues_album(&guild_id.to_string(), url, metadata);
+    let fade_in = fade::enabled() && !gapless;
+    let start_volume = if fade_in { 0.0 } else { volume };
+
     // Now setup the track with a notifier for when it ends
     let track = {
         let mut call = call_lock.lock().await;
-        let track_handle = call.enqueue_input(source.into()).await;
+        let track_handle = call.enqueue(Track::from(source).volume(start_volume)).await;
 
         // Set track event handler
         track_handle
@@ -312,23 +1345,119 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
                 Event::Track(songbird::TrackEvent::End),
                 TrackEndNotifier {
                     guild_id,
-                    channel_id: cmd.channel_id,
+                    channel_id,
                     manager: manager.clone(),
                     http: ctx.http.clone(),
                 },
             )
             .map_err(|e| anyhow!("failed to add track event handler: {e}"))?;
 
+        if gapless {
+            tracing::debug!(
+                "Queued {} in guild {} to follow on gaplessly",
+                url,
+                guild_id
+            );
+            // Decode its start now rather than at the handoff
+            let _ = track_handle.make_playable();
+        }
+
+        if fade_in {
+            track_handle
+                .add_event(
+                    // Local timed events run on the track's own play time, so this fires as it
+                    // starts, whether it's first in the queue or waited its turn
+                    Event::Delayed(Duration::ZERO),
+                    FadeIn {
+                        guild_id: guild_id.to_string(),
+                        volume,
+                    },
+                )
+                .map_err(|e| anyhow!("failed to add fade-in handler: {e}"))?;
+        }
+
         track_handle
-    };
+            .add_event(
+                Event::Track(songbird::TrackEvent::Play),
+                QuietHoursVolume {
+                    guild_id: guild_id.to_string(),
+                },
+            )
+            .map_err(|e| anyhow!("failed to add quiet hours handler: {e}"))?;
 
-    // Get actual title (cached or extracted)
-    let title = if let Some(cached_title) = cached_title {
-        cached_title
-    } else if let Some(future) = title_future {
-        future.await.unwrap_or_else(|_| "Unknown".to_string())
-    } else {
-        "Unknown".to_string()
+        track_handle
+            .add_event(
+                Event::Track(songbird::TrackEvent::Play),
+                LoopOnStart {
+                    guild_id: guild_id.to_string(),
+                },
+            )
+            .map_err(|e| anyhow!("failed to add loop handler: {e}"))?;
+        track_handle
+            .add_event(
+                Event::Track(songbird::TrackEvent::Loop),
+                RestartOnLoop {
+                    guild_id: guild_id.to_string(),
+                },
+            )
+            .map_err(|e| anyhow!("failed to add loop handler: {e}"))?;
+
+        track_handle
+            .add_event(
+                Event::Track(songbird::TrackEvent::Play),
+                RecordStart {
+                    guild_id: guild_id.to_string(),
+                    url: url.to_string(),
+                    title: title.to_string(),
+                    duration,
+                },
+            )
+            .map_err(|e| anyhow!("failed to add start handler: {e}"))?;
+
+        let user_id = user_id.to_string();
+        if hooks::enabled() {
+            track_handle
+                .add_event(
+                    Event::Track(songbird::TrackEvent::Play),
+                    HookOnStart {
+                        guild_id: guild_id.to_string(),
+                        data: json!({
+                            "url": url,
+                            "title": title,
+                            "duration": duration,
+                            "requested_by": user_id,
+                        }),
+                    },
+                )
+                .map_err(|e| anyhow!("failed to add hook handler: {e}"))?;
+        }
+
+        if has_scrobble_accounts(&user_id)
+            && let Some(mut listen) = parse_listen(title, metadata)
+        {
+            listen.duration = listen.duration.or(duration);
+            track_handle
+                .add_event(
+                    Event::Track(songbird::TrackEvent::End),
+                    ScrobbleOnEnd { user_id, listen },
+                )
+                .map_err(|e| anyhow!("failed to add scrobble handler: {e}"))?;
+        }
+
+        if let Some(duration) = duration.filter(|d| *d >= BOOKMARK_MIN_DURATION_SECS) {
+            track_handle
+                .add_event(
+                    Event::Track(songbird::TrackEvent::End),
+                    BookmarkOnEnd {
+                        guild_id: guild_id.to_string(),
+                        url: url.to_string(),
+                        duration,
+                    },
+                )
+                .map_err(|e| anyhow!("failed to add bookmark handler: {e}"))?;
+        }
+
+        track_handle
     };
 
     // Log to queue history
@@ -336,10 +1465,11 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
     if let Err(e) = QueueHistory::create(
         &mut db_conn,
         &guild_id.to_string(),
-        &cmd.user.id.to_string(),
+        &user_id.to_string(),
         url,
-        Some(&title),
-        None,
+        Some(title),
+        duration,
+        Some(&track.uuid().to_string()),
     ) {
         tracing::warn!("Failed to log queue history: {}", e);
     } else {
@@ -348,60 +1478,164 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
     }
 
     // Add to current queue tracking
-    if let Err(e) = CurrentQueue::add_to_queue(
+    let added = CurrentQueue::add_to_queue(
         &mut db_conn,
         &guild_id.to_string(),
         url,
-        Some(&title),
-        None,
-        &cmd.user.id.to_string(),
-    ) {
-        tracing::warn!("Failed to add track to current queue: {}", e);
+        Some(title),
+        duration,
+        &user_id.to_string(),
+        Some(&track.uuid().to_string()),
+        priority,
+        QueueStatus::Ready,
+    );
+    if let Ok(entry) = &added {
+        // Into an idle guild the track starts at once, likely before this row existed
+        if entry.position == 0
+            && let Some(id) = entry.id
+            && let Err(e) = CurrentQueue::set_status(&mut db_conn, id, QueueStatus::Playing)
+        {
+            tracing::warn!("Failed to mark queue entry as playing: {}", e);
+        }
+        hooks::emit(
+            HookEvent::QueueAdd,
+            &guild_id.to_string(),
+            json!({
+                "url": url,
+                "title": title,
+                "duration": duration,
+                "requested_by": user_id.to_string(),
+                "position": entry.position,
+            }),
+        );
+    }
+    match added {
+        // Songbird appended the track; move it up to where the database put it
+        Ok(entry) if entry.priority > 0 => {
+            match CurrentQueue::get_guild_queue(&mut db_conn, &guild_id.to_string()) {
+                Ok(entries) => {
+                    let order: Vec<String> = entries
+                        .into_iter()
+                        .filter(|e| e.position > 0)
+                        .filter_map(|e| e.track_uuid)
+                        .collect();
+                    super::queue::apply_live_order(call_lock, &order).await;
+                }
+                Err(e) => tracing::warn!("Failed to load queue to place priority track: {}", e),
+            }
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to add track to current queue: {}", e),
     }
 
     // Update voice connection to mark as playing
-    if let Err(e) = VoiceConnection::update_playing_status(
+    voice_state::set_playing(&guild_id.to_string(), true, Some(title));
+    if let Err(e) = VoiceConnection::set_text_channel(
         &mut db_conn,
         &guild_id.to_string(),
-        true,
-        Some(&title),
+        &channel_id.to_string(),
     ) {
-        tracing::warn!("Failed to update playing status: {}", e);
+        tracing::warn!("Failed to record session text channel: {}", e);
     }
 
     // Update song cache
-    if let Err(e) = SongCache::create_or_update(&mut db_conn, url, &title, None, None, None, None) {
+    if let Err(e) = SongCache::create_or_update(
+        &mut db_conn,
+        url,
+        title,
+        duration,
+        None,
+        Some(&download.path.to_string_lossy()),
+        download.file_size_i32(),
+        download.format.as_deref(),
+    ) {
         tracing::warn!("Failed to update song cache: {}", e);
     }
 
-    // Send success message
-    let embed = CreateEmbed::new()
-        .title("🎵 Now Playing")
-        .description(&title)
-        .url(url)
-        .colour(0x1db954) // Spotify green
-        .footer(serenity::all::CreateEmbedFooter::new(format!(
-            "Queue position: {} | Duration: Streaming",
-            {
-                let info = track
-                    .get_info()
-                    .await
-                    .map_err(|e| anyhow!("failed to get track info: {e}"))?;
-                format!("{:?}", info.position)
-            }
-        )));
+    Ok(track)
+}
 
-    cmd.edit_response(
-        &ctx.http,
-        EditInteractionResponse::new()
-            .content("")
-            .embeds(vec![embed]),
-    )
-    .await?;
+/// Seek a queued track to its bookmark when someone presses the "Resume" button
+pub async fn handle_resume_button(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+) -> Result<()> {
+    let (track_id, position) = component
+        .data
+        .custom_id
+        .strip_prefix(RESUME_BUTTON_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .ok_or_else(|| anyhow!("malformed resume button id"))?;
+    let position: u64 = position.parse()?;
+    let guild_id = component
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?;
+    // Jumping ahead acts like /seek, so the same members are kept from it
+    if !super::allow_playback_button(ctx, component, "seek").await? {
+        return Ok(());
+    }
 
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let track = match manager.get(guild_id) {
+        Some(call_lock) => call_lock
+            .lock()
+            .await
+            .queue()
+            .current_queue()
+            .into_iter()
+            .find(|t| t.uuid().to_string() == track_id),
+        None => None,
+    };
+
+    let response = match track {
+        Some(track) => {
+            let _ = track.seek(Duration::from_secs(position));
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "⏩ <@{}> resumed from {}",
+                        component.user.id,
+                        format_position(position)
+                    ))
+                    .components(Vec::new()),
+            )
+        }
+        None => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content("❌ That track isn't in the queue anymore")
+                .ephemeral(true),
+        ),
+    };
+    component.create_response(&ctx.http, response).await?;
     Ok(())
 }
 
+/// `m:ss`, or `h:mm:ss` past an hour
+pub fn format_position(total_seconds: u64) -> String {
+    let (h, m, s) = (
+        total_seconds / 3600,
+        (total_seconds / 60) % 60,
+        total_seconds % 60,
+    );
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+fn progress_message(progress: &DownloadProgress) -> String {
+    match progress.phase {
+        DownloadPhase::Downloading => format!(
+            "Downloading… {} {}%",
+            text_bar(progress.percent),
+            progress.percent
+        ),
+        DownloadPhase::Converting => format!("Converting… {}", text_bar(100)),
+        DownloadPhase::Ready => "Ready, adding to the queue…".to_string(),
+    }
+}
+
 fn text_bar(percent: u8) -> String {
     // 20-wide bar
     let total = 20u8;
@@ -418,3 +1652,52 @@ fn text_bar(percent: u8) -> String {
     s.push(']');
     s
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PlayRequest, play_request};
+    use crate::commands::testing::command;
+    use serde_json::json;
+
+    #[test]
+    fn play_options_pick_what_to_do() {
+        let playlist = "https://www.youtube.com/watch?v=a&list=PL1";
+        let video = "https://www.youtube.com/watch?v=a";
+        let url = |value: &str| json!({ "name": "url", "type": 3, "value": value });
+        let flag = |name: &str| json!({ "name": name, "type": 5, "value": true });
+
+        let cmd = command("play", json!([url(video)]));
+        assert_eq!(
+            play_request(&cmd),
+            PlayRequest::Url {
+                url: video,
+                resume: false
+            }
+        );
+        let cmd = command("play", json!([url(video), flag("resume")]));
+        assert_eq!(
+            play_request(&cmd),
+            PlayRequest::Url {
+                url: video,
+                resume: true
+            }
+        );
+        let cmd = command("play", json!([url(playlist), flag("pick")]));
+        assert_eq!(play_request(&cmd), PlayRequest::Pick(playlist));
+        // Picking only applies to playlists
+        let cmd = command("play", json!([url(video), flag("pick")]));
+        assert_eq!(
+            play_request(&cmd),
+            PlayRequest::Url {
+                url: video,
+                resume: false
+            }
+        );
+        // A share token wins over a URL
+        let share = json!({ "name": "share", "type": 3, "value": "tok" });
+        let cmd = command("play", json!([share, url(video)]));
+        assert_eq!(play_request(&cmd), PlayRequest::Share("tok"));
+        let cmd = command("play", json!([]));
+        assert_eq!(play_request(&cmd), PlayRequest::Missing);
+    }
+}
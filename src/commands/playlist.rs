@@ -0,0 +1,760 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use futures_util::{StreamExt, stream};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse,
+    InteractionContext, RoleId,
+};
+
+use crate::audio::{SearchResult, ytdlp_search};
+use crate::database::establish_connection;
+use crate::database::models::saved_playlist::PlaylistTrackInput;
+use crate::database::models::{CurrentQueue, GuildSettings, SavedPlaylist};
+use crate::policy::{
+    check_command_roles_by, check_not_draining, check_quiet_hours, check_user_not_banned, is_dj,
+};
+use crate::spotify::{self, SpotifyTrack};
+use crate::validation::validate_media_url;
+use crate::{metadata_cache, source};
+
+pub const MAX_NAME_LEN: u16 = 100;
+const LIST_LIMIT: usize = 25;
+/// Searches run in parallel during an import
+const MATCH_CONCURRENCY: usize = 4;
+/// YouTube results considered per Spotify track
+const SEARCH_CANDIDATES: usize = 3;
+/// Minimum time between progress edits, to stay well clear of Discord's rate limits
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(3);
+/// Unmatched tracks listed in the import report
+const UNMATCHED_LIMIT: usize = 15;
+
+pub fn definition() -> CreateCommand {
+    let name = |description: &str| {
+        CreateCommandOption::new(CommandOptionType::String, "name", description)
+            .max_length(MAX_NAME_LEN)
+    };
+    CreateCommand::new("playlist")
+        .description("Manage this server's saved playlists")
+        .contexts(vec![InteractionContext::Guild])
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "import",
+                "Save a Spotify playlist, matching each track on YouTube",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::String, "url", "Spotify playlist link")
+                    .required(true),
+            )
+            .add_sub_option(name("Save under this name (defaults to the Spotify name)")),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "save",
+                "Save the current queue, playing track included, as a playlist",
+            )
+            .add_sub_option(name("Playlist name").required(true)),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "load",
+                "Queue every track of a saved playlist",
+            )
+            .add_sub_option(name("Playlist name").required(true)),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "add",
+                "Add a track to a playlist, creating it if needed",
+            )
+            .add_sub_option(name("Playlist name").required(true))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "url",
+                "Track link (defaults to the one playing)",
+            )),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show saved playlists",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "show",
+                "Show the tracks in a saved playlist",
+            )
+            .add_sub_option(name("Playlist name").required(true)),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "delete",
+                "Delete a saved playlist",
+            )
+            .add_sub_option(name("Playlist name").required(true)),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    match sub.name.as_str() {
+        "import" => handle_import(ctx, cmd, &guild_id, args).await,
+        "save" => handle_save(ctx, cmd, &guild_id, args).await,
+        "load" => handle_load(ctx, cmd, &guild_id, args).await,
+        "add" => handle_add(ctx, cmd, &guild_id, args).await,
+        "list" => {
+            let lines = {
+                let mut db_conn = establish_connection();
+                let playlists = SavedPlaylist::list_for_guild(&mut db_conn, &guild_id)?;
+                let mut lines = Vec::new();
+                for p in playlists.iter().take(LIST_LIMIT) {
+                    let count = p.track_count(&mut db_conn)?;
+                    lines.push(format!(
+                        "• **{}** — {} track{} (by <@{}>)",
+                        p.name,
+                        count,
+                        if count == 1 { "" } else { "s" },
+                        p.owner_id
+                    ));
+                }
+                lines
+            };
+            if lines.is_empty() {
+                return respond(
+                    ctx,
+                    cmd,
+                    CreateInteractionResponseMessage::new()
+                        .content("No playlists saved yet. Try `/playlist import`!"),
+                )
+                .await;
+            }
+            let embed = CreateEmbed::new()
+                .title("📜 Saved Playlists")
+                .description(lines.join("\n"))
+                .colour(0x1db954);
+            respond(
+                ctx,
+                cmd,
+                CreateInteractionResponseMessage::new().embed(embed),
+            )
+            .await
+        }
+        "show" => {
+            let name = string_arg(args, "name").unwrap_or_default();
+            let found = {
+                let mut db_conn = establish_connection();
+                match SavedPlaylist::find_by_name(&mut db_conn, &guild_id, name)? {
+                    Some(playlist) => {
+                        let tracks = playlist.tracks(&mut db_conn)?;
+                        Some((playlist, tracks))
+                    }
+                    None => None,
+                }
+            };
+            let Some((playlist, tracks)) = found else {
+                return super::reject(ctx, cmd, &format!("No playlist named `{}`", name)).await;
+            };
+            let lines: Vec<String> = tracks
+                .iter()
+                .take(LIST_LIMIT)
+                .map(|t| format!("{}. [{}]({})", t.position + 1, t.title, t.url))
+                .collect();
+            let mut embed = CreateEmbed::new()
+                .title(format!("📜 {}", playlist.name))
+                .description(if lines.is_empty() {
+                    "This playlist is empty.".to_string()
+                } else {
+                    lines.join("\n")
+                })
+                .colour(0x1db954);
+            if tracks.len() > LIST_LIMIT {
+                embed = embed.footer(CreateEmbedFooter::new(format!(
+                    "Showing {} of {}",
+                    LIST_LIMIT,
+                    tracks.len()
+                )));
+            }
+            respond(
+                ctx,
+                cmd,
+                CreateInteractionResponseMessage::new().embed(embed),
+            )
+            .await
+        }
+        "delete" => {
+            let name = string_arg(args, "name").unwrap_or_default();
+            let mut db_conn = establish_connection();
+            let Some(playlist) = SavedPlaylist::find_by_name(&mut db_conn, &guild_id, name)? else {
+                return super::reject(ctx, cmd, &format!("No playlist named `{}`", name)).await;
+            };
+            if !can_edit(cmd, &playlist) {
+                return super::reject(
+                    ctx,
+                    cmd,
+                    "Only the member who saved this playlist (or a server manager) can delete it",
+                )
+                .await;
+            }
+            SavedPlaylist::delete(&mut db_conn, &guild_id, name)?;
+            respond(
+                ctx,
+                cmd,
+                CreateInteractionResponseMessage::new()
+                    .content(format!("🗑️ Deleted playlist **{}**", playlist.name)),
+            )
+            .await
+        }
+        other => Err(anyhow!("unknown subcommand {other}")),
+    }
+}
+
+async fn handle_import(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    guild_id: &str,
+    args: &[CommandDataOption],
+) -> Result<()> {
+    let Some(config) = spotify::config() else {
+        return super::reject(ctx, cmd, "Spotify imports aren't configured on this bot").await;
+    };
+    let Some(playlist_id) = string_arg(args, "url").and_then(spotify::parse_playlist_id) else {
+        return super::reject(
+            ctx,
+            cmd,
+            "That doesn't look like a Spotify playlist link (https://open.spotify.com/playlist/...)",
+        )
+        .await;
+    };
+
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+    )
+    .await?;
+
+    let playlist = match spotify::fetch_playlist(&config, &playlist_id).await {
+        Ok(playlist) => playlist,
+        Err(e) => {
+            cmd.edit_response(
+                &ctx.http,
+                EditInteractionResponse::new()
+                    .content(format!("❌ Couldn't read that Spotify playlist: {}", e)),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let name = string_arg(args, "name")
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .unwrap_or(playlist.name.as_str())
+        .chars()
+        .take(MAX_NAME_LEN as usize)
+        .collect::<String>();
+    // Importing replaces a playlist of the same name, so it needs the same say as saving over it
+    if let Some(existing) =
+        SavedPlaylist::find_by_name(&mut establish_connection(), guild_id, &name)?
+        && !can_edit(cmd, &existing)
+    {
+        cmd.edit_response(
+            &ctx.http,
+            EditInteractionResponse::new().content(format!(
+                "❌ `{}` was saved by someone else; pick another name or ask a server manager",
+                name
+            )),
+        )
+        .await?;
+        return Ok(());
+    }
+    let total = playlist.tracks.len();
+
+    cmd.edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().content(format!(
+            "🔎 Matching **{}** tracks from **{}** on YouTube…",
+            total, playlist.name
+        )),
+    )
+    .await?;
+
+    let mut results = stream::iter(playlist.tracks.iter().cloned())
+        .map(|track| async move {
+            let found = find_match(&track).await;
+            (track, found)
+        })
+        .buffered(MATCH_CONCURRENCY);
+
+    let mut matched = Vec::with_capacity(total);
+    let mut unmatched = Vec::new();
+    let mut done = 0usize;
+    let mut last_progress = Instant::now();
+    while let Some((track, found)) = results.next().await {
+        done += 1;
+        match found {
+            Some(result) => matched.push(PlaylistTrackInput {
+                url: result.url,
+                title: result.title,
+                duration: result.duration.map(|d| d.round() as i32),
+            }),
+            None => unmatched.push(track.display_name()),
+        }
+        if last_progress.elapsed() >= PROGRESS_INTERVAL && done < total {
+            last_progress = Instant::now();
+            let _ = cmd
+                .edit_response(
+                    &ctx.http,
+                    EditInteractionResponse::new().content(format!(
+                        "🔎 Matching tracks from **{}** on YouTube… {}/{} ({} not found)",
+                        playlist.name,
+                        done,
+                        total,
+                        unmatched.len()
+                    )),
+                )
+                .await;
+        }
+    }
+
+    if matched.is_empty() {
+        cmd.edit_response(
+            &ctx.http,
+            EditInteractionResponse::new().content(format!(
+                "❌ None of the {} tracks in **{}** could be found on YouTube, nothing was saved.",
+                total, playlist.name
+            )),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    {
+        let mut db_conn = establish_connection();
+        SavedPlaylist::save(
+            &mut db_conn,
+            guild_id,
+            &name,
+            &cmd.user.id.to_string(),
+            string_arg(args, "url"),
+            &matched,
+        )?;
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("📜 Saved playlist: {}", name))
+        .description(format!(
+            "Imported **{}** of {} tracks from [{}]({}).",
+            matched.len(),
+            total,
+            playlist.name,
+            string_arg(args, "url").unwrap_or_default()
+        ))
+        .colour(0x1db954);
+    if !unmatched.is_empty() {
+        let mut listed: Vec<String> = unmatched
+            .iter()
+            .take(UNMATCHED_LIMIT)
+            .map(|t| format!("• {}", t))
+            .collect();
+        if unmatched.len() > UNMATCHED_LIMIT {
+            listed.push(format!("…and {} more", unmatched.len() - UNMATCHED_LIMIT));
+        }
+        embed = embed.field(
+            format!("Not found ({})", unmatched.len()),
+            listed.join("\n"),
+            false,
+        );
+    }
+    if playlist.truncated {
+        embed = embed.footer(CreateEmbedFooter::new(format!(
+            "Only the first {} tracks were imported",
+            spotify::MAX_IMPORT_TRACKS
+        )));
+    }
+
+    cmd.edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().content("").embed(embed),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Pick the top YouTube result whose length is close to the Spotify track's, so covers,
+/// extended mixes and hour-long loops don't get saved in place of the real song
+async fn find_match(track: &SpotifyTrack) -> Option<SearchResult> {
+    let candidates = match ytdlp_search(&track.search_query(), SEARCH_CANDIDATES).await {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            tracing::warn!(
+                "YouTube search for {:?} failed: {}",
+                track.search_query(),
+                e
+            );
+            return None;
+        }
+    };
+    let Some(expected) = track.duration_ms.map(|ms| ms as f64 / 1000.0) else {
+        return candidates.into_iter().next();
+    };
+    // Allow 10% or 15 seconds of difference, whichever is larger
+    let tolerance = (expected * 0.1).max(15.0);
+    candidates
+        .into_iter()
+        .find(|c| c.duration.is_none_or(|d| (d - expected).abs() <= tolerance))
+}
+
+/// Whether the member saved `playlist` or manages the server, so may change or delete it
+fn can_edit(cmd: &CommandInteraction, playlist: &SavedPlaylist) -> bool {
+    playlist.owner_id == cmd.user.id.to_string()
+        || cmd
+            .member
+            .as_ref()
+            .and_then(|m| m.permissions)
+            .is_some_and(|p| p.manage_guild())
+}
+
+async fn handle_save(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    guild_id: &str,
+    args: &[CommandDataOption],
+) -> Result<()> {
+    let name = string_arg(args, "name").unwrap_or_default().trim();
+    if name.is_empty() {
+        return super::reject(ctx, cmd, "Give the playlist a name").await;
+    }
+    let mut db_conn = establish_connection();
+    if let Some(existing) = SavedPlaylist::find_by_name(&mut db_conn, guild_id, name)?
+        && !can_edit(cmd, &existing)
+    {
+        return super::reject(
+            ctx,
+            cmd,
+            &format!(
+                "`{}` was saved by someone else; pick another name or ask a server manager",
+                name
+            ),
+        )
+        .await;
+    }
+    // Playing track first, then the rest in queue order
+    let tracks: Vec<PlaylistTrackInput> = CurrentQueue::get_guild_queue(&mut db_conn, guild_id)?
+        .into_iter()
+        .map(|entry| PlaylistTrackInput {
+            title: entry.title.unwrap_or_else(|| entry.url.clone()),
+            url: entry.url,
+            duration: entry.duration,
+        })
+        .collect();
+    if tracks.is_empty() {
+        return super::reject(ctx, cmd, "The queue is empty, so there's nothing to save").await;
+    }
+    SavedPlaylist::save(
+        &mut db_conn,
+        guild_id,
+        name,
+        &cmd.user.id.to_string(),
+        None,
+        &tracks,
+    )?;
+    respond(
+        ctx,
+        cmd,
+        CreateInteractionResponseMessage::new().content(format!(
+            "💾 Saved {} track{} as **{}**. Queue them again with `/playlist load`.",
+            tracks.len(),
+            if tracks.len() == 1 { "" } else { "s" },
+            name
+        )),
+    )
+    .await
+}
+
+async fn handle_load(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    guild_id: &str,
+    args: &[CommandDataOption],
+) -> Result<()> {
+    let name = string_arg(args, "name").unwrap_or_default();
+    let (found, settings) = {
+        let mut db_conn = establish_connection();
+        if let Err(e) = check_user_not_banned(&mut db_conn, guild_id, &cmd.user.id.to_string()) {
+            return super::reject(ctx, cmd, &e.to_string()).await;
+        }
+        let settings = GuildSettings::find_by_guild_id(&mut db_conn, guild_id)
+            .ok()
+            .flatten();
+        let roles = cmd
+            .member
+            .as_ref()
+            .map(|m| m.roles.as_slice())
+            .unwrap_or_default();
+        let dj = is_dj(settings.as_ref(), cmd.member.as_deref());
+        if let Err(message) = check_load(settings.as_ref(), roles, dj) {
+            return super::reject(ctx, cmd, &message).await;
+        }
+        let found = match SavedPlaylist::find_by_name(&mut db_conn, guild_id, name)? {
+            Some(playlist) => {
+                let tracks = playlist.tracks(&mut db_conn)?;
+                Some((playlist, tracks))
+            }
+            None => None,
+        };
+        (found, settings)
+    };
+    let Some((playlist, tracks)) = found else {
+        return super::reject(ctx, cmd, &format!("No playlist named `{}`", name)).await;
+    };
+    if tracks.is_empty() {
+        return super::reject(ctx, cmd, &format!("**{}** is empty", playlist.name)).await;
+    }
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+    )
+    .await?;
+
+    let guild = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let (channel_id, user_id) = (cmd.channel_id, cmd.user.id);
+    if let Err(e) = super::play::join_member_channel(ctx, guild, user_id).await {
+        cmd.edit_response(
+            &ctx.http,
+            EditInteractionResponse::new().content(format!("❌ Couldn't join voice: {}", e)),
+        )
+        .await?;
+        return Ok(());
+    }
+    let priority = match (settings, &cmd.member) {
+        (Some(settings), Some(member)) => {
+            let roles: Vec<String> = member.roles.iter().map(|r| r.to_string()).collect();
+            settings.queue_priority_for(&roles)
+        }
+        _ => 0,
+    };
+
+    let urls: Vec<String> = tracks.into_iter().map(|t| t.url).collect();
+    metadata_cache::prefetch(guild_id.to_string(), urls.clone());
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let mut queued = 0;
+    for url in &urls {
+        // The queue may have played out and disconnected while we were downloading
+        if manager.get(guild).is_none() {
+            break;
+        }
+        match super::play::enqueue_quietly(ctx, guild, channel_id, user_id, url, priority).await {
+            Ok(_) => queued += 1,
+            Err(e) => tracing::info!(
+                "Skipped playlist track {} in guild {}: {}",
+                url,
+                guild_id,
+                e
+            ),
+        }
+    }
+
+    cmd.edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().content(format!(
+            "📥 Queued {} of {} track{} from **{}**.",
+            queued,
+            urls.len(),
+            if urls.len() == 1 { "" } else { "s" },
+            playlist.name
+        )),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Refuse a `/playlist load` before the bot joins voice. Loading queues like `/play`, so its role
+/// lock, maintenance and quiet hours apply, and since a batch can't go through track-by-track
+/// review only DJs may load while requests need approval.
+fn check_load(
+    settings: Option<&GuildSettings>,
+    roles: &[RoleId],
+    is_dj: bool,
+) -> Result<(), String> {
+    check_command_roles_by(settings, "play", roles, || is_dj)
+        .and_then(|()| check_not_draining())
+        .and_then(|()| check_quiet_hours(settings))
+        .map_err(|e| e.to_string())?;
+    if settings.is_some_and(|s| s.require_approval) && !is_dj {
+        return Err(
+            "Requests need a moderator's approval here, so only DJs can load playlists".to_string(),
+        );
+    }
+    Ok(())
+}
+
+async fn handle_add(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    guild_id: &str,
+    args: &[CommandDataOption],
+) -> Result<()> {
+    let name = string_arg(args, "name").unwrap_or_default().trim();
+    if name.is_empty() {
+        return super::reject(ctx, cmd, "Give the playlist a name").await;
+    }
+    let existing = SavedPlaylist::find_by_name(&mut establish_connection(), guild_id, name)?;
+    if let Some(playlist) = &existing
+        && !can_edit(cmd, playlist)
+    {
+        return super::reject(
+            ctx,
+            cmd,
+            "Only the member who saved this playlist (or a server manager) can add to it",
+        )
+        .await;
+    }
+
+    let track = match string_arg(args, "url") {
+        Some(url) => {
+            if let Err(e) = validate_media_url(url) {
+                return super::reject(ctx, cmd, &e.to_string()).await;
+            }
+            cmd.create_response(
+                &ctx.http,
+                CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+            )
+            .await?;
+            let url = url.trim().to_string();
+            match source::extract_metadata(&url).await {
+                Ok(metadata) => PlaylistTrackInput {
+                    duration: metadata.duration_secs(),
+                    title: metadata.title,
+                    url,
+                },
+                Err(e) => {
+                    tracing::debug!("Couldn't look up {} for a playlist: {}", url, e);
+                    PlaylistTrackInput {
+                        title: url.clone(),
+                        url,
+                        duration: None,
+                    }
+                }
+            }
+        }
+        None => {
+            let Some(current) =
+                CurrentQueue::get_current_track(&mut establish_connection(), guild_id)?
+            else {
+                return super::reject(ctx, cmd, "Nothing is playing; give a track link").await;
+            };
+            cmd.create_response(
+                &ctx.http,
+                CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+            )
+            .await?;
+            PlaylistTrackInput {
+                title: current.title.unwrap_or_else(|| current.url.clone()),
+                url: current.url,
+                duration: current.duration,
+            }
+        }
+    };
+
+    let count = {
+        let mut db_conn = establish_connection();
+        match existing {
+            Some(playlist) => {
+                playlist.append(&mut db_conn, &track)?;
+                playlist.track_count(&mut db_conn)?
+            }
+            None => {
+                SavedPlaylist::save(
+                    &mut db_conn,
+                    guild_id,
+                    name,
+                    &cmd.user.id.to_string(),
+                    None,
+                    std::slice::from_ref(&track),
+                )?;
+                1
+            }
+        }
+    };
+    cmd.edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().content(format!(
+            "➕ Added **{}** to **{}** ({} track{}).",
+            track.title,
+            name,
+            count,
+            if count == 1 { "" } else { "s" }
+        )),
+    )
+    .await?;
+    Ok(())
+}
+
+fn string_arg<'a>(args: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    args.iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.value.as_str())
+}
+
+async fn respond(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    message: CreateInteractionResponseMessage,
+) -> Result<()> {
+    cmd.create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_load;
+    use crate::database::models::GuildSettings;
+    use serenity::all::RoleId;
+
+    fn settings() -> GuildSettings {
+        GuildSettings::create_or_update(&mut crate::database::test_connection(), "1").unwrap()
+    }
+
+    #[test]
+    fn loading_is_held_to_the_play_lock() {
+        let mut settings = settings();
+        settings.command_roles = Some(r#"{"play":["42"]}"#.to_string());
+
+        assert!(check_load(Some(&settings), &[RoleId::new(42)], false).is_ok());
+        assert!(check_load(Some(&settings), &[RoleId::new(7)], true).is_ok());
+        let refused = check_load(Some(&settings), &[RoleId::new(7)], false).unwrap_err();
+        assert!(refused.contains("/play"), "{refused}");
+        // Locking another command leaves loading alone
+        settings.command_roles = Some(r#"{"stop":["42"]}"#.to_string());
+        assert!(check_load(Some(&settings), &[], false).is_ok());
+    }
+
+    #[test]
+    fn only_djs_load_in_moderation_mode() {
+        let mut settings = settings();
+        assert!(check_load(Some(&settings), &[], false).is_ok());
+
+        settings.require_approval = true;
+        let refused = check_load(Some(&settings), &[], false).unwrap_err();
+        assert!(refused.contains("approval"), "{refused}");
+        assert!(check_load(Some(&settings), &[], true).is_ok());
+    }
+}
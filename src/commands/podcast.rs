@@ -0,0 +1,305 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse,
+    InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::{PodcastEpisode, PodcastSubscription};
+use crate::podcast::refresh_feed;
+use crate::stats::format_listening_time;
+use crate::validation::validate_media_url;
+
+/// Episodes shown by `/podcast episodes`, and so the highest `number` accepted
+const EPISODE_LIST_LIMIT: i64 = 15;
+
+pub fn definition() -> CreateCommand {
+    let podcast = || {
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "podcast",
+            "Podcast name (optional if the server only follows one)",
+        )
+    };
+    CreateCommand::new("podcast")
+        .description("Follow podcasts and play their episodes")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "subscribe",
+                "Follow a podcast by its RSS feed",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::String, "url", "RSS feed URL")
+                    .required(true),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "unsubscribe",
+                "Stop following a podcast",
+            )
+            .add_sub_option(podcast().required(true)),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "latest",
+                "Play the newest episode",
+            )
+            .add_sub_option(podcast()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "episodes",
+                "List recent episodes, or play one by number",
+            )
+            .add_sub_option(podcast())
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "number",
+                    "Episode number from the list to play",
+                )
+                .min_int_value(1)
+                .max_int_value(EPISODE_LIST_LIMIT as u64),
+            ),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    if sub.name == "subscribe" {
+        return handle_subscribe(ctx, cmd, &guild_id, args).await;
+    }
+
+    let subscriptions = {
+        let mut db_conn = establish_connection();
+        PodcastSubscription::list_for_guild(&mut db_conn, &guild_id)?
+    };
+    let subscription = match pick_subscription(&subscriptions, string_arg(args, "podcast")) {
+        Ok(subscription) => subscription,
+        Err(message) => return super::reject(ctx, cmd, &message).await,
+    };
+
+    match sub.name.as_str() {
+        "unsubscribe" => {
+            let can_manage = cmd
+                .member
+                .as_ref()
+                .and_then(|m| m.permissions)
+                .is_some_and(|p| p.manage_guild());
+            if subscription.subscribed_by != cmd.user.id.to_string() && !can_manage {
+                return super::reject(
+                    ctx,
+                    cmd,
+                    "Only the member who subscribed (or a server manager) can unsubscribe",
+                )
+                .await;
+            }
+            {
+                let mut db_conn = establish_connection();
+                PodcastSubscription::unsubscribe(&mut db_conn, &guild_id, &subscription.feed_url)?;
+            }
+            respond(
+                ctx,
+                cmd,
+                format!("✅ Unsubscribed from **{}**", subscription.title),
+            )
+            .await
+        }
+        "latest" => {
+            let latest = {
+                let mut db_conn = establish_connection();
+                PodcastEpisode::recent_for_feed(&mut db_conn, &subscription.feed_url, 1)?
+            };
+            match latest.first() {
+                Some(episode) => super::play::play_url(ctx, cmd, &episode.url, false).await,
+                None => {
+                    super::reject(
+                        ctx,
+                        cmd,
+                        &format!("No episodes of **{}** found yet", subscription.title),
+                    )
+                    .await
+                }
+            }
+        }
+        "episodes" => {
+            let episodes = {
+                let mut db_conn = establish_connection();
+                PodcastEpisode::recent_for_feed(
+                    &mut db_conn,
+                    &subscription.feed_url,
+                    EPISODE_LIST_LIMIT,
+                )?
+            };
+            if let Some(number) = args
+                .iter()
+                .find(|o| o.name == "number")
+                .and_then(|o| o.value.as_i64())
+            {
+                return match episodes.get(number as usize - 1) {
+                    Some(episode) => super::play::play_url(ctx, cmd, &episode.url, false).await,
+                    None => {
+                        super::reject(ctx, cmd, &format!("There's no episode #{}", number)).await
+                    }
+                };
+            }
+
+            if episodes.is_empty() {
+                return respond(
+                    ctx,
+                    cmd,
+                    format!("No episodes of **{}** found yet", subscription.title),
+                )
+                .await;
+            }
+            let lines: Vec<String> = episodes
+                .iter()
+                .enumerate()
+                .map(|(i, e)| {
+                    let mut details = Vec::new();
+                    if let Some(published) = e.published_at {
+                        details.push(published.format("%Y-%m-%d").to_string());
+                    }
+                    if let Some(duration) = e.duration {
+                        details.push(format_listening_time(duration as i64));
+                    }
+                    if details.is_empty() {
+                        format!("**{}.** {}", i + 1, e.title)
+                    } else {
+                        format!("**{}.** {} ({})", i + 1, e.title, details.join(", "))
+                    }
+                })
+                .collect();
+            let embed = CreateEmbed::new()
+                .title(format!("🎙️ {}", subscription.title))
+                .description(lines.join("\n"))
+                .footer(serenity::all::CreateEmbedFooter::new(
+                    "Play one with /podcast episodes number:<n>",
+                ))
+                .colour(0x8e44ad);
+            cmd.create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().embed(embed),
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        other => Err(anyhow!("unknown subcommand {other}")),
+    }
+}
+
+async fn handle_subscribe(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    guild_id: &str,
+    args: &[CommandDataOption],
+) -> Result<()> {
+    let feed_url = match validate_media_url(string_arg(args, "url").unwrap_or_default()) {
+        Ok(url) => url.to_string(),
+        Err(e) => return super::reject(ctx, cmd, &e.to_string()).await,
+    };
+
+    // Reading the feed goes through yt-dlp and can take a few seconds
+    cmd.defer(&ctx.http).await?;
+
+    let reply = match refresh_feed(&feed_url).await {
+        Ok((title, _)) => {
+            let mut db_conn = establish_connection();
+            PodcastSubscription::subscribe(
+                &mut db_conn,
+                guild_id,
+                &feed_url,
+                &title,
+                &cmd.user.id.to_string(),
+            )?;
+            let count = PodcastEpisode::count_for_feed(&mut db_conn, &feed_url)?;
+            format!(
+                "🎙️ Subscribed to **{}** ({} episode{}). Try `/podcast latest`!",
+                title,
+                count,
+                if count == 1 { "" } else { "s" }
+            )
+        }
+        Err(e) => {
+            tracing::info!("Failed to read podcast feed {}: {}", feed_url, e);
+            "❌ Couldn't read that feed. Make sure it's a podcast RSS feed URL.".to_string()
+        }
+    };
+
+    cmd.edit_response(&ctx.http, EditInteractionResponse::new().content(reply))
+        .await?;
+    Ok(())
+}
+
+/// Find the subscription a member means: an exact or partial title match, or the only one
+fn pick_subscription<'a>(
+    subscriptions: &'a [PodcastSubscription],
+    query: Option<&str>,
+) -> Result<&'a PodcastSubscription, String> {
+    let query = query
+        .map(|q| q.trim().to_lowercase())
+        .filter(|q| !q.is_empty());
+    match query {
+        None => match subscriptions {
+            [] => Err(
+                "This server doesn't follow any podcasts yet. Use `/podcast subscribe`".to_string(),
+            ),
+            [only] => Ok(only),
+            _ => Err(format!(
+                "This server follows several podcasts, pick one with `podcast:` ({})",
+                subscriptions
+                    .iter()
+                    .map(|s| s.title.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        },
+        Some(query) => subscriptions
+            .iter()
+            .find(|s| s.title.to_lowercase() == query || s.feed_url.to_lowercase() == query)
+            .or_else(|| {
+                subscriptions
+                    .iter()
+                    .find(|s| s.title.to_lowercase().contains(&query))
+            })
+            .ok_or_else(|| format!("This server doesn't follow a podcast matching `{}`", query)),
+    }
+}
+
+fn string_arg<'a>(args: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    args.iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.value.as_str())
+}
+
+async fn respond(ctx: &SerenityContext, cmd: &CommandInteraction, content: String) -> Result<()> {
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content),
+        ),
+    )
+    .await?;
+    Ok(())
+}
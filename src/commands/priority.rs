@@ -0,0 +1,160 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext, Permissions,
+    RoleId,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+use crate::policy::{MAX_PRIORITY_ROLES, MAX_QUEUE_PRIORITY};
+use crate::settings_events;
+
+pub fn definition() -> CreateCommand {
+    let role = || {
+        CreateCommandOption::new(CommandOptionType::Role, "role", "Role to configure")
+            .required(true)
+    };
+    CreateCommand::new("priority")
+        .description("Let members with certain roles queue ahead of regular requests")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "set",
+                "Give a role queue priority",
+            )
+            .add_sub_option(role())
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "level",
+                    "Higher levels queue ahead of lower ones (default 1)",
+                )
+                .min_int_value(1)
+                .max_int_value(MAX_QUEUE_PRIORITY as u64),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "remove",
+                "Take queue priority away from a role",
+            )
+            .add_sub_option(role()),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show roles with queue priority",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    let mut db_conn = establish_connection();
+    let settings = GuildSettings::create_or_update(&mut db_conn, &guild_id)?;
+    let mut roles = settings.priority_roles_map();
+
+    let reply = match sub.name.as_str() {
+        "set" => {
+            let role = role_arg(args).ok_or_else(|| anyhow!("missing role argument"))?;
+            let level = args
+                .iter()
+                .find(|o| o.name == "level")
+                .and_then(|o| o.value.as_i64())
+                .unwrap_or(1) as i32;
+            if !roles.contains_key(&role.to_string()) && roles.len() >= MAX_PRIORITY_ROLES {
+                return super::reject(
+                    ctx,
+                    cmd,
+                    &format!(
+                        "At most {} roles can have queue priority",
+                        MAX_PRIORITY_ROLES
+                    ),
+                )
+                .await;
+            }
+            roles.insert(role.to_string(), level);
+            GuildSettings::update_priority_roles(&mut db_conn, &guild_id, &roles)?;
+            settings_events::changed(&guild_id);
+            format!(
+                "⭐ Tracks requested by <@&{}> now queue at priority {}",
+                role, level
+            )
+        }
+        "remove" => {
+            let role = role_arg(args).ok_or_else(|| anyhow!("missing role argument"))?;
+            if roles.remove(&role.to_string()).is_some() {
+                GuildSettings::update_priority_roles(&mut db_conn, &guild_id, &roles)?;
+                settings_events::changed(&guild_id);
+                format!("✅ <@&{}> no longer has queue priority", role)
+            } else {
+                format!("<@&{}> doesn't have queue priority", role)
+            }
+        }
+        "list" => {
+            if roles.is_empty() {
+                "No roles have queue priority on this server.".to_string()
+            } else {
+                let mut entries: Vec<(&String, &i32)> = roles.iter().collect();
+                entries.sort_by(|a, b| b.1.cmp(a.1));
+                let embed = CreateEmbed::new()
+                    .title("⭐ Queue Priority")
+                    .description(
+                        entries
+                            .iter()
+                            .map(|(role, level)| format!("<@&{}> — priority {}", role, level))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    )
+                    .colour(0xf1c40f);
+                return respond(
+                    ctx,
+                    cmd,
+                    CreateInteractionResponseMessage::new().embed(embed),
+                )
+                .await;
+            }
+        }
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+
+    respond(
+        ctx,
+        cmd,
+        CreateInteractionResponseMessage::new().content(reply),
+    )
+    .await
+}
+
+fn role_arg(args: &[CommandDataOption]) -> Option<RoleId> {
+    args.iter()
+        .find(|o| o.name == "role")
+        .and_then(|o| o.value.as_role_id())
+}
+
+async fn respond(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    message: CreateInteractionResponseMessage,
+) -> Result<()> {
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(message.ephemeral(true)),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,685 @@
+use anyhow::{Result, anyhow};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use ring::rand::{SecureRandom, SystemRandom};
+use serenity::all::{
+    ButtonStyle, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    ComponentInteraction, Context as SerenityContext, CreateActionRow, CreateButton, CreateCommand,
+    CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateMessage, GuildId, InteractionContext,
+};
+use songbird::Call;
+use songbird::tracks::Queued;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::database::establish_connection;
+use crate::database::models::current_queue::VoteOutcome;
+use crate::database::models::{CurrentQueue, GuildSettings, QueueShare, QueueStatus};
+use crate::metadata_cache;
+use crate::policy::{check_user_not_banned, requires_approval};
+use crate::theme::{Icon, Theme};
+
+/// How long a `/queue share` token can be imported for
+const SHARE_TTL_HOURS: i64 = 24;
+/// Share tokens are this many characters from `SHARE_TOKEN_ALPHABET`
+const SHARE_TOKEN_LEN: usize = 10;
+/// Lowercase letters and digits minus the easily confused ones (0/o, 1/l/i)
+const SHARE_TOKEN_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+/// Fresh tokens tried when one clashes with a share that's still stored
+const SHARE_TOKEN_ATTEMPTS: usize = 3;
+/// Pending tracks listed, each with an upvote button, by `/queue show`
+const QUEUE_DISPLAY_LIMIT: usize = 10;
+/// Custom ID prefix of the upvote buttons on `/queue show`; followed by the queue entry ID
+pub const UPVOTE_BUTTON_PREFIX: &str = "upvote:";
+/// A track can overtake at most this many earlier requests each time the queue is reordered
+const MAX_VOTE_JUMP: usize = 3;
+/// Requests that have waited this long hold their place against upvoted tracks
+const VOTE_STARVATION_MINUTES: i64 = 30;
+/// Skipped tracks listed individually in the import summary before collapsing into "and N more"
+const MAX_SKIPPED_LISTED: usize = 10;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("queue")
+        .description("Work with the server's queue")
+        .contexts(vec![InteractionContext::Guild])
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "show",
+            "Show what's queued and upvote tracks to play them sooner",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "share",
+            "Export the current queue as a token any server can import with /play share",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "dedupe",
+            "Remove tracks that are already queued earlier",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(_) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    match sub.name.as_str() {
+        "show" => {
+            let entries = {
+                let mut db_conn = establish_connection();
+                CurrentQueue::get_guild_queue(&mut db_conn, &guild_id)?
+            };
+            let (embed, components) = queue_view(&entries, &Theme::for_guild(&guild_id));
+            cmd.create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(components),
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        "share" => {
+            let mut db_conn = establish_connection();
+            let tracks = CurrentQueue::get_guild_queue(&mut db_conn, &guild_id)?;
+            if tracks.is_empty() {
+                return super::reject(ctx, cmd, "There's nothing in the queue to share").await;
+            }
+
+            let expires_at =
+                chrono::Utc::now().naive_utc() + chrono::Duration::hours(SHARE_TTL_HOURS);
+            let mut attempt = 1;
+            let share = loop {
+                match QueueShare::create(
+                    &mut db_conn,
+                    &new_share_token()?,
+                    &guild_id,
+                    &cmd.user.id.to_string(),
+                    expires_at,
+                    &tracks,
+                ) {
+                    Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _))
+                        if attempt < SHARE_TOKEN_ATTEMPTS =>
+                    {
+                        attempt += 1;
+                    }
+                    result => break result?,
+                }
+            };
+
+            let embed = CreateEmbed::new()
+                .title("🔗 Queue shared")
+                .description(format!(
+                    "Import these {} track{} in any server with `/play share:{}`",
+                    tracks.len(),
+                    if tracks.len() == 1 { "" } else { "s" },
+                    share.token
+                ))
+                .field("Token", format!("`{}`", share.token), true)
+                .field(
+                    "Expires",
+                    format!("<t:{}:R>", share.expires_at.and_utc().timestamp()),
+                    true,
+                )
+                .colour(0x1db954);
+            cmd.create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().embed(embed),
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        "dedupe" => {
+            let banned = {
+                let mut db_conn = establish_connection();
+                check_user_not_banned(&mut db_conn, &guild_id, &cmd.user.id.to_string())
+            };
+            if let Err(e) = banned {
+                return super::reject(ctx, cmd, &e.to_string()).await;
+            }
+
+            let manager = songbird::get(ctx).await.unwrap().clone();
+            let call_lock = manager.get(GuildId::new(guild_id.parse()?));
+            let removed = dedupe(call_lock.as_ref(), &guild_id).await?;
+            let content = match removed {
+                0 => "Nothing in the queue is queued twice.".to_string(),
+                n => format!(
+                    "🧹 Removed {} duplicate track{} from the queue.",
+                    n,
+                    if n == 1 { "" } else { "s" }
+                ),
+            };
+            tracing::info!(
+                "{} removed {} duplicate(s) from the queue in guild {}",
+                cmd.user.id,
+                removed,
+                guild_id
+            );
+            cmd.create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().content(content),
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        other => Err(anyhow!("unknown subcommand {other}")),
+    }
+}
+
+/// Remove pending tracks whose URL is already queued earlier, from the database and, when the
+/// guild has a call, songbird's queue. Returns how many were removed.
+pub async fn dedupe(call_lock: Option<&Arc<Mutex<Call>>>, guild_id: &str) -> Result<usize> {
+    let removed = {
+        let mut db_conn = establish_connection();
+        CurrentQueue::remove_duplicates(&mut db_conn, guild_id)?
+    };
+    if let Some(call_lock) = call_lock {
+        let uuids: Vec<String> = removed
+            .iter()
+            .filter_map(|e| e.track_uuid.clone())
+            .collect();
+        remove_live(call_lock, &uuids).await;
+    }
+    Ok(removed.len())
+}
+
+/// Take the tracks with the given UUIDs out of songbird's queue and stop them. Their rows are
+/// already gone, so `TrackEndNotifier` ignores them ending.
+pub async fn remove_live(call_lock: &Arc<Mutex<Call>>, uuids: &[String]) {
+    if uuids.is_empty() {
+        return;
+    }
+    let call = call_lock.lock().await;
+    call.queue().modify_queue(|queue| {
+        queue.retain(|q| {
+            let keep = !uuids.contains(&q.uuid().to_string());
+            if !keep {
+                let _ = q.stop();
+            }
+            keep
+        })
+    });
+}
+
+/// Count an upvote from one of the buttons on `/queue show` and refresh the listing
+pub async fn handle_upvote_button(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+) -> Result<()> {
+    let entry_id: i32 = component
+        .data
+        .custom_id
+        .strip_prefix(UPVOTE_BUTTON_PREFIX)
+        .ok_or_else(|| anyhow!("malformed upvote button id"))?
+        .parse()?;
+    let guild_id = component
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let user_id = component.user.id.to_string();
+
+    let mut db_conn = establish_connection();
+    let outcome = match check_user_not_banned(&mut db_conn, &guild_id, &user_id) {
+        Ok(()) => CurrentQueue::upvote(&mut db_conn, &guild_id, entry_id, &user_id)?,
+        Err(e) => return reply_ephemeral(ctx, component, format!("❌ {}", e)).await,
+    };
+    let response = match outcome {
+        VoteOutcome::Counted(_) => {
+            let entries = CurrentQueue::get_guild_queue(&mut db_conn, &guild_id)?;
+            let (embed, components) = queue_view(&entries, &Theme::for_guild(&guild_id));
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .components(components),
+            )
+        }
+        VoteOutcome::AlreadyVoted => {
+            return reply_ephemeral(ctx, component, "You've already upvoted that track".into())
+                .await;
+        }
+        VoteOutcome::NotPending => {
+            return reply_ephemeral(
+                ctx,
+                component,
+                "That track is already playing or has left the queue".into(),
+            )
+            .await;
+        }
+    };
+    component.create_response(&ctx.http, response).await?;
+    Ok(())
+}
+
+async fn reply_ephemeral(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+    content: String,
+) -> Result<()> {
+    component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// The `/queue show` embed and its upvote buttons
+fn queue_view(entries: &[CurrentQueue], theme: &Theme) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let title_of = |e: &CurrentQueue| e.title.clone().unwrap_or_else(|| e.url.clone());
+    let Some((current, pending)) = entries.split_first() else {
+        let embed = theme
+            .embed(Icon::Queue, "Queue", 0x808080)
+            .description("The queue is empty. Add something with `/play`!");
+        return (embed, Vec::new());
+    };
+
+    let mut lines = vec![match current.status() {
+        QueueStatus::Playing | QueueStatus::Ready => {
+            format!("▶️ **Now playing:** {}", title_of(current))
+        }
+        _ => format!(
+            "⏳ **Up next:** {}{}",
+            title_of(current),
+            status_note(current.status())
+        ),
+    }];
+    if pending.is_empty() {
+        lines.push("\nNothing else is queued.".to_string());
+    } else {
+        lines.push(String::new());
+    }
+    for (i, entry) in pending.iter().take(QUEUE_DISPLAY_LIMIT).enumerate() {
+        let priority = if entry.priority > 0 { "⭐ " } else { "" };
+        let votes = match entry.votes {
+            0 => String::new(),
+            n => format!(" · 👍 {}", n),
+        };
+        lines.push(format!(
+            "**{}.** {}{}{}{}",
+            i + 1,
+            priority,
+            title_of(entry),
+            votes,
+            status_note(entry.status())
+        ));
+    }
+    if pending.len() > QUEUE_DISPLAY_LIMIT {
+        lines.push(format!("…and {} more", pending.len() - QUEUE_DISPLAY_LIMIT));
+    }
+
+    let buttons: Vec<CreateButton> = pending
+        .iter()
+        .take(QUEUE_DISPLAY_LIMIT)
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            entry.id.map(|id| {
+                CreateButton::new(format!("{}{}", UPVOTE_BUTTON_PREFIX, id))
+                    .label((i + 1).to_string())
+                    .emoji('👍')
+                    .style(ButtonStyle::Secondary)
+            })
+        })
+        .collect();
+    let components = buttons
+        .chunks(5)
+        .map(|row| CreateActionRow::Buttons(row.to_vec()))
+        .collect();
+
+    let embed = theme
+        .embed(Icon::Queue, "Queue", 0x1db954)
+        .description(lines.join("\n"));
+    let embed = if pending.is_empty() {
+        embed
+    } else {
+        let mut footer =
+            "Upvote a track to play it sooner; the queue is reordered when each track ends"
+                .to_string();
+        if pending.iter().any(|e| e.priority > 0) {
+            footer.push_str(" · ⭐ priority role request");
+        }
+        embed.footer(theme.footer_with(&footer))
+    };
+    (embed, components)
+}
+
+/// Why an entry isn't ready to play yet; empty once it is
+fn status_note(status: QueueStatus) -> &'static str {
+    match status {
+        QueueStatus::PendingDownload => " · 🕓 waiting to download",
+        QueueStatus::Downloading => " · ⏳ downloading",
+        QueueStatus::Failed => " · ⚠️ failed, will be skipped",
+        QueueStatus::Ready | QueueStatus::Playing => "",
+    }
+}
+
+/// Reorder a guild's pending tracks by upvotes, both in the database and in songbird's queue.
+/// Runs between tracks, so the order only shifts at natural boundaries.
+pub async fn reorder_by_votes(call_lock: &Arc<Mutex<Call>>, guild_id: GuildId) {
+    let guild_id = guild_id.to_string();
+    let uuids: Vec<String> = {
+        let mut db_conn = establish_connection();
+        let pending: Vec<CurrentQueue> =
+            match CurrentQueue::get_guild_queue(&mut db_conn, &guild_id) {
+                Ok(entries) => entries.into_iter().filter(|e| e.position > 0).collect(),
+                Err(e) => {
+                    tracing::warn!("Failed to load queue for vote reorder: {}", e);
+                    return;
+                }
+            };
+        if pending.iter().all(|e| e.votes == 0) {
+            return;
+        }
+        let order = vote_order(&pending, chrono::Utc::now().naive_utc());
+        if order.iter().zip(&pending).all(|(a, b)| a.id == b.id) {
+            return;
+        }
+        let ids: Vec<i32> = order.iter().filter_map(|e| e.id).collect();
+        if let Err(e) = CurrentQueue::reorder_pending(&mut db_conn, &guild_id, &ids) {
+            tracing::warn!("Failed to reorder queue by votes: {}", e);
+            return;
+        }
+        order.iter().filter_map(|e| e.track_uuid.clone()).collect()
+    };
+
+    apply_live_order(call_lock, &uuids).await;
+}
+
+/// Rearrange songbird's queue so the tracks with the given UUIDs play in that order. They're
+/// permuted within the slots they already occupy, so the playing track (and anything the
+/// database doesn't know about) stays where it is.
+pub async fn apply_live_order(call_lock: &Arc<Mutex<Call>>, uuids: &[String]) {
+    let call = call_lock.lock().await;
+    call.queue().modify_queue(|queue| {
+        let rank = |q: &Queued| uuids.iter().position(|u| *u == q.uuid().to_string());
+        let slots: Vec<usize> = queue
+            .iter()
+            .enumerate()
+            .filter(|(_, q)| rank(q).is_some())
+            .map(|(i, _)| i)
+            .collect();
+        let mut moved: Vec<Queued> = slots
+            .iter()
+            .rev()
+            .filter_map(|&i| queue.remove(i))
+            .collect();
+        moved.sort_by_key(|q| rank(q));
+        for (slot, track) in slots.into_iter().zip(moved) {
+            queue.insert(slot, track);
+        }
+    });
+}
+
+/// Queue priority of the member running `cmd`, from the guild's `priority_roles` setting
+pub fn requester_priority(settings: Option<&GuildSettings>, cmd: &CommandInteraction) -> i32 {
+    match (settings, &cmd.member) {
+        (Some(settings), Some(member)) => {
+            let roles: Vec<String> = member.roles.iter().map(|r| r.to_string()).collect();
+            settings.queue_priority_for(&roles)
+        }
+        _ => 0,
+    }
+}
+
+/// Order pending entries (given in queue order) by votes, most first. For fairness each pick
+/// only considers the next `MAX_VOTE_JUMP + 1` entries in line, and if any of those has been
+/// waiting `VOTE_STARVATION_MINUTES` the longest-waiting one goes next regardless of votes.
+fn vote_order(pending: &[CurrentQueue], now: chrono::NaiveDateTime) -> Vec<&CurrentQueue> {
+    let starving = chrono::Duration::minutes(VOTE_STARVATION_MINUTES);
+    let mut remaining: Vec<&CurrentQueue> = pending.iter().collect();
+    let mut order = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let window = &remaining[..remaining.len().min(MAX_VOTE_JUMP + 1)];
+        let pick = window
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| now - e.added_at >= starving)
+            .min_by_key(|(_, e)| e.added_at)
+            .or_else(|| {
+                // Votes never lift a track past a higher-priority one; earliest in line wins ties
+                window
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(i, e)| (e.priority, e.votes, std::cmp::Reverse(*i)))
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        order.push(remaining.remove(pick));
+    }
+    order
+}
+
+/// Queue the tracks behind a share token: the first through the normal `/play` pipeline (which
+/// joins voice and answers the interaction), the rest in the background with a summary posted to
+/// the channel once they're all in.
+pub async fn import_share(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    token: &str,
+) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let token = token.trim().to_lowercase();
+
+    let tracks = if is_share_token(&token) {
+        let mut db_conn = establish_connection();
+        match QueueShare::find_active(&mut db_conn, &token)? {
+            Some(share) => share.tracks(&mut db_conn)?,
+            None => Vec::new(),
+        }
+    } else {
+        Vec::new()
+    };
+    let Some((first, rest)) = tracks.split_first() else {
+        return super::reject(ctx, cmd, "That share token doesn't exist or has expired").await;
+    };
+
+    let settings = {
+        let mut db_conn = establish_connection();
+        GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+            .ok()
+            .flatten()
+    };
+    // A whole queue can't sensibly go through track-by-track review
+    if requires_approval(settings.as_ref(), cmd.member.as_deref()) {
+        return super::reject(
+            ctx,
+            cmd,
+            "Requests need a moderator's approval here, so only DJs can import shared queues",
+        )
+        .await;
+    }
+    let priority = requester_priority(settings.as_ref(), cmd);
+
+    super::play::play_url(ctx, cmd, &first.url, false).await?;
+    if rest.is_empty() {
+        return Ok(());
+    }
+
+    // `/play` answers rejections of the first track itself; only carry on if it got us into voice
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    if manager.get(guild_id).is_none() {
+        return Ok(());
+    }
+
+    let ctx = ctx.clone();
+    let rest = rest.to_vec();
+    let (channel_id, user_id) = (cmd.channel_id, cmd.user.id);
+    metadata_cache::prefetch(
+        guild_id.to_string(),
+        rest.iter().map(|track| track.url.clone()).collect(),
+    );
+    tokio::spawn(async move {
+        let mut queued = 0;
+        let mut skipped = Vec::new();
+        for track in &rest {
+            // The queue may have played out and disconnected while we were downloading
+            if manager.get(guild_id).is_none() {
+                break;
+            }
+            match super::play::enqueue_quietly(
+                &ctx, guild_id, channel_id, user_id, &track.url, priority,
+            )
+            .await
+            {
+                Ok(_) => queued += 1,
+                Err(e) => {
+                    tracing::info!(
+                        "Skipped shared track {} in guild {}: {}",
+                        track.url,
+                        guild_id,
+                        e
+                    );
+                    skipped.push(format!(
+                        "{} ({})",
+                        track.title.as_deref().unwrap_or(&track.url),
+                        e
+                    ));
+                }
+            }
+        }
+
+        let mut description = format!(
+            "Queued {} more of {} track{} from the shared queue",
+            queued,
+            rest.len(),
+            if rest.len() == 1 { "" } else { "s" }
+        );
+        if !skipped.is_empty() {
+            description.push_str("\n\n**Skipped:**\n");
+            for line in skipped.iter().take(MAX_SKIPPED_LISTED) {
+                description.push_str(&format!("• {}\n", line));
+            }
+            if skipped.len() > MAX_SKIPPED_LISTED {
+                description.push_str(&format!("…and {} more", skipped.len() - MAX_SKIPPED_LISTED));
+            }
+        }
+        let embed = CreateEmbed::new()
+            .title("📥 Shared queue imported")
+            .description(description)
+            .colour(0x1db954);
+        let _ = channel_id
+            .send_message(&ctx.http, CreateMessage::new().embeds(vec![embed]))
+            .await;
+    });
+
+    Ok(())
+}
+
+/// Whether `token` is shaped like one `/queue share` hands out
+pub fn is_share_token(token: &str) -> bool {
+    token.len() == SHARE_TOKEN_LEN && token.bytes().all(|b| SHARE_TOKEN_ALPHABET.contains(&b))
+}
+
+/// A fresh random share token, drawn from the OS's CSPRNG
+fn new_share_token() -> Result<String> {
+    // Bytes from here up would favour the start of the alphabet
+    let unbiased = 256 - 256 % SHARE_TOKEN_ALPHABET.len();
+    let rng = SystemRandom::new();
+    let mut token = String::with_capacity(SHARE_TOKEN_LEN);
+    let mut buf = [0u8; SHARE_TOKEN_LEN * 2];
+    while token.len() < SHARE_TOKEN_LEN {
+        rng.fill(&mut buf)
+            .map_err(|_| anyhow!("no randomness for share tokens"))?;
+        for &b in buf.iter().filter(|&&b| (b as usize) < unbiased) {
+            if token.len() == SHARE_TOKEN_LEN {
+                break;
+            }
+            token.push(SHARE_TOKEN_ALPHABET[b as usize % SHARE_TOKEN_ALPHABET.len()] as char);
+        }
+    }
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_tokens_are_well_formed_and_distinct() {
+        let tokens: std::collections::HashSet<String> = (0..1000)
+            .map(|_| new_share_token().unwrap())
+            .inspect(|token| assert!(is_share_token(token), "{token:?}"))
+            .collect();
+        assert_eq!(tokens.len(), 1000);
+    }
+
+    fn entry(position: i32, status: QueueStatus) -> CurrentQueue {
+        CurrentQueue {
+            id: Some(position + 100),
+            guild_id: "987654321".to_string(),
+            url: format!("https://example.com/{position}"),
+            title: Some(format!("Track {position}")),
+            duration: None,
+            position,
+            added_by: "123456789".to_string(),
+            added_at: chrono::Utc::now().naive_utc(),
+            votes: 0,
+            track_uuid: None,
+            priority: 0,
+            status: status.as_str().to_string(),
+        }
+    }
+
+    #[test]
+    fn queue_view_lists_tracks_with_upvote_buttons() {
+        let (embed, rows) = queue_view(&[], &Theme::default());
+        let embed = serde_json::to_value(embed).unwrap();
+        assert!(
+            embed["description"].as_str().unwrap().contains("empty"),
+            "{embed}"
+        );
+        assert!(rows.is_empty());
+
+        let mut entries = vec![entry(0, QueueStatus::Playing)];
+        entries.extend((1..=QUEUE_DISPLAY_LIMIT as i32 + 2).map(|i| entry(i, QueueStatus::Ready)));
+        entries[1].votes = 2;
+        entries[2].status = QueueStatus::Downloading.as_str().to_string();
+        let (embed, rows) = queue_view(&entries, &Theme::default());
+        let embed = serde_json::to_value(embed).unwrap();
+        let description = embed["description"].as_str().unwrap();
+        assert!(
+            description.starts_with("▶️ **Now playing:** Track 0"),
+            "{description}"
+        );
+        assert!(
+            description.contains("**1.** Track 1 · 👍 2"),
+            "{description}"
+        );
+        assert!(
+            description.contains("**2.** Track 2 · ⏳ downloading"),
+            "{description}"
+        );
+        assert!(description.contains("…and 2 more"), "{description}");
+
+        // One upvote button per listed track, five to a row
+        let rows = serde_json::to_value(rows).unwrap();
+        let buttons: Vec<&str> = rows
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|row| row["components"].as_array().unwrap())
+            .map(|button| button["custom_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(buttons.len(), QUEUE_DISPLAY_LIMIT);
+        assert_eq!(buttons[0], format!("{}101", UPVOTE_BUTTON_PREFIX));
+        assert_eq!(rows.as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+use crate::policy::{active_quiet_hours, normalize_timezone, parse_clock_time};
+use crate::settings_events;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("quiethours")
+        .description("Keep the bot quiet overnight")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "set",
+                "Set the daily quiet-hours window",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "start",
+                    "When quiet hours begin, as HH:MM (e.g. 22:00)",
+                )
+                .required(true),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "end",
+                    "When quiet hours end, as HH:MM (e.g. 07:00)",
+                )
+                .required(true),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "timezone",
+                "Your server's time zone, e.g. Europe/Berlin or America/New_York (default UTC)",
+            ))
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "volume",
+                    "Keep playing but cap the volume at this percent, instead of refusing playback",
+                )
+                .min_int_value(1)
+                .max_int_value(100),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "off",
+            "Turn quiet hours off",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "status",
+            "Show the quiet-hours window",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    let mut db_conn = establish_connection();
+    let settings = GuildSettings::create_or_update(&mut db_conn, &guild_id)?;
+
+    let reply = match sub.name.as_str() {
+        "set" => {
+            let start = string_arg(args, "start").unwrap_or_default();
+            let end = string_arg(args, "end").unwrap_or_default();
+            let (Some(start_time), Some(end_time)) =
+                (parse_clock_time(start), parse_clock_time(end))
+            else {
+                return super::reject(ctx, cmd, "Give start and end times as HH:MM, e.g. 22:00")
+                    .await;
+            };
+            if start_time == end_time {
+                return super::reject(ctx, cmd, "Quiet hours can't start and end at the same time")
+                    .await;
+            }
+            let Some(timezone) = normalize_timezone(string_arg(args, "timezone").unwrap_or("UTC"))
+            else {
+                return super::reject(
+                    ctx,
+                    cmd,
+                    "Give the time zone by name, like Europe/Berlin or America/New_York",
+                )
+                .await;
+            };
+            let volume = args
+                .iter()
+                .find(|o| o.name == "volume")
+                .and_then(|o| o.value.as_i64())
+                .map(|percent| percent as f32 / 100.0);
+            let start = start_time.format("%H:%M").to_string();
+            let end = end_time.format("%H:%M").to_string();
+            GuildSettings::update_quiet_hours(
+                &mut db_conn,
+                &guild_id,
+                Some((&start, &end)),
+                &timezone,
+                volume,
+            )?;
+            settings_events::changed(&guild_id);
+            format!(
+                "🌙 Quiet hours are now {}–{} ({} time): {}",
+                start,
+                end,
+                timezone,
+                describe_rule(volume)
+            )
+        }
+        "off" => {
+            GuildSettings::update_quiet_hours(
+                &mut db_conn,
+                &guild_id,
+                None,
+                &settings.quiet_hours_timezone,
+                None,
+            )?;
+            settings_events::changed(&guild_id);
+            "✅ Quiet hours are off".to_string()
+        }
+        "status" => match (&settings.quiet_hours_start, &settings.quiet_hours_end) {
+            (Some(start), Some(end)) => {
+                let now = if active_quiet_hours(Some(&settings), chrono::Utc::now()).is_some() {
+                    "in effect now"
+                } else {
+                    "not in effect right now"
+                };
+                format!(
+                    "🌙 Quiet hours are {}–{} ({} time), {}: {}",
+                    start,
+                    end,
+                    settings.quiet_hours_timezone,
+                    now,
+                    describe_rule(settings.quiet_hours_volume)
+                )
+            }
+            _ => "No quiet hours are set on this server.".to_string(),
+        },
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    };
+
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(reply)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn describe_rule(volume: Option<f32>) -> String {
+    match volume {
+        Some(volume) => format!(
+            "tracks play at no more than {}% volume",
+            (volume * 100.0).round()
+        ),
+        None => "new tracks can't be started".to_string(),
+    }
+}
+
+fn string_arg<'a>(args: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    args.iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.value.as_str())
+}
@@ -0,0 +1,15 @@
+use anyhow::Result;
+use serenity::all::{
+    CommandInteraction, Context as SerenityContext, CreateCommand, InteractionContext, Permissions,
+};
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("resume")
+        .description("Carry on playing a paused track")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    super::pause::set_paused(ctx, cmd, false).await
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context as SerenityContext, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    InteractionContext, Permissions,
+};
+
+use super::play::format_position;
+use crate::database::establish_connection;
+use crate::database::models::CurrentQueue;
+use crate::theme::{Icon, Theme};
+use crate::voice_manager;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("seek")
+        .description("Jump to a point in the playing track")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "timestamp",
+                "Where to jump to, e.g. 1:30, 1:02:03, 1h2m3s or 90",
+            )
+            .required(true)
+            .max_length(16),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let input = cmd
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == "timestamp")
+        .and_then(|o| o.value.as_str())
+        .ok_or_else(|| anyhow!("missing timestamp argument"))?;
+    let Some(position) = parse_timestamp(input) else {
+        return super::reject(
+            ctx,
+            cmd,
+            &format!(
+                "`{}` isn't a timestamp; try `1:30`, `1:02:03` or `1h2m3s`.",
+                input.trim()
+            ),
+        )
+        .await;
+    };
+
+    let Some(current) =
+        CurrentQueue::get_current_track(&mut establish_connection(), &guild_id.to_string())?
+    else {
+        return super::reject(ctx, cmd, "Nothing is playing.").await;
+    };
+    if let Some(duration) = current.duration
+        && position.as_secs() >= duration.max(0) as u64
+    {
+        return super::reject(
+            ctx,
+            cmd,
+            &format!(
+                "This track is only {} long.",
+                format_position(duration.max(0) as u64)
+            ),
+        )
+        .await;
+    }
+
+    let Some(state) = voice_manager::seek(&guild_id.to_string(), position).await? else {
+        return super::reject(ctx, cmd, "Nothing is playing.").await;
+    };
+
+    let title = state.title.as_deref().unwrap_or("the current track");
+    let embed = Theme::for_guild(&guild_id.to_string())
+        .embed(Icon::Seek, "Seeked", 0x3498DB) // Blue
+        .description(format!(
+            "**{}** is at {}{}.",
+            title,
+            format_position(state.position_secs),
+            if state.paused { " (paused)" } else { "" }
+        ));
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().embed(embed)),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Parse a position as `mm:ss`, `h:mm:ss`, unit form like `1h2m3s` or `90s`, or bare seconds
+fn parse_timestamp(input: &str) -> Option<Duration> {
+    let input = input.trim().to_ascii_lowercase();
+    if input.is_empty() {
+        return None;
+    }
+
+    if input.contains(':') {
+        let parts = input
+            .split(':')
+            .map(|p| {
+                (!p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))
+                    .then(|| p.parse::<u64>().ok())
+                    .flatten()
+            })
+            .collect::<Option<Vec<u64>>>()?;
+        let (h, m, s) = match parts[..] {
+            [m, s] => (0, m, s),
+            [h, m, s] if m < 60 => (h, m, s),
+            _ => return None,
+        };
+        if s >= 60 {
+            return None;
+        }
+        return Some(Duration::from_secs(h * 3600 + m * 60 + s));
+    }
+
+    if input.bytes().all(|b| b.is_ascii_digit()) {
+        return input.parse().ok().map(Duration::from_secs);
+    }
+
+    // Units must come largest first, each at most once
+    let mut total = 0u64;
+    let mut number = String::new();
+    let mut last_unit = u64::MAX;
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        if number.is_empty() || unit >= last_unit {
+            return None;
+        }
+        total = total.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+        last_unit = unit;
+    }
+    number.is_empty().then_some(Duration::from_secs(total))
+}
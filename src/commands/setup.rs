@@ -0,0 +1,468 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
+use serenity::all::{
+    ActionRowComponent, ButtonStyle, ChannelId, ChannelType, CommandInteraction,
+    ComponentInteraction, ComponentInteractionDataKind, Context as SerenityContext,
+    CreateActionRow, CreateButton, CreateCommand, CreateEmbed, CreateInputText,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateModal,
+    CreateSelectMenu, CreateSelectMenuKind, Guild, InputTextStyle, InteractionContext,
+    ModalInteraction, Permissions, RoleId,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::{DEFAULT_VOLUME, GuildSettings, GuildSetup};
+use crate::policy::MAX_DJ_ROLES;
+use crate::settings_events;
+use crate::validation::MAX_VOLUME;
+
+/// Prefix of every component and modal in the wizard; followed by the step, e.g. `setup:roles`
+pub const SETUP_PREFIX: &str = "setup:";
+const CHANNEL_MENU_ID: &str = "setup:channel";
+const ROLES_MENU_ID: &str = "setup:roles";
+const LIMITS_BUTTON_ID: &str = "setup:limits";
+const LIMITS_MODAL_ID: &str = "setup:limits_modal";
+const SAVE_BUTTON_ID: &str = "setup:save";
+const CANCEL_BUTTON_ID: &str = "setup:cancel";
+/// The "Run /setup" button on the message posted when the bot joins a server
+const START_BUTTON_ID: &str = "setup:start";
+const VOLUME_INPUT_ID: &str = "volume";
+const DISCONNECT_INPUT_ID: &str = "auto_disconnect";
+const QUEUE_INPUT_ID: &str = "max_queue";
+/// The same bounds PUT /api/guild-settings accepts
+const MAX_AUTO_DISCONNECT_MINUTES: i32 = 60;
+const MAX_QUEUE_SIZE: i32 = 100;
+/// Discord stops accepting responses to an interaction after 15 minutes, so a wizard left open
+/// longer can't be saved anyway
+const DRAFT_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Guild and admin running a wizard
+type DraftKey = (String, String);
+
+/// Wizards in progress, holding the choices made so far and when `/setup` was run
+static DRAFTS: Lazy<Mutex<HashMap<DraftKey, (GuildSetup, Instant)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("setup")
+        .description("Walk through the basic settings for this server")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+}
+
+/// Open the wizard, starting from the guild's current settings; nothing is written until Save
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let wizard = open_wizard(&guild_id, &cmd.user.id.to_string())?;
+    cmd.create_response(&ctx.http, CreateInteractionResponse::Message(wizard))
+        .await?;
+    Ok(())
+}
+
+/// A fresh wizard for `user_id`, as a private reply
+fn open_wizard(guild_id: &str, user_id: &str) -> Result<CreateInteractionResponseMessage> {
+    let draft = {
+        let mut db_conn = establish_connection();
+        match GuildSettings::find_by_guild_id(&mut db_conn, guild_id)? {
+            Some(settings) => GuildSetup {
+                dj_roles: settings.allowed_roles_list(),
+                announcement_channel_id: settings.announcement_channel_id,
+                default_volume: settings.default_volume,
+                auto_disconnect_minutes: settings.auto_disconnect_minutes,
+                max_queue_size: settings.max_queue_size,
+            },
+            // Column defaults, without creating a row until the admin saves
+            None => GuildSetup {
+                announcement_channel_id: None,
+                dj_roles: Vec::new(),
+                default_volume: DEFAULT_VOLUME,
+                auto_disconnect_minutes: 5,
+                max_queue_size: 50,
+            },
+        }
+    };
+
+    let (embed, components) = render(&draft, None);
+    store_draft(guild_id, user_id, draft, Instant::now());
+    Ok(CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .components(components)
+        .ephemeral(true))
+}
+
+/// Greet a server that just added the bot: make sure it has a settings row, and post
+/// quick-start instructions with a button that opens the wizard in its system channel (or its
+/// first text channel)
+pub async fn welcome(ctx: &SerenityContext, guild: &Guild) {
+    if let Err(e) =
+        GuildSettings::create_or_update(&mut establish_connection(), &guild.id.to_string())
+    {
+        tracing::warn!("Failed to create settings for guild {}: {}", guild.id, e);
+    }
+    let Some(channel_id) = crate::guild_limit::notice_channel(guild) else {
+        return;
+    };
+    let embed = CreateEmbed::new()
+        .title("👋 Thanks for adding Lyre")
+        .description(
+            "Join a voice channel and use `/play url:<link>` to start listening. Tracks queue up \
+             behind each other; `/queue show`, `/next` and `/stop` control playback.",
+        )
+        .field(
+            "Set it up",
+            "Server managers can press **Run /setup** (or use `/setup`) to pick an announcement \
+             channel, DJ roles, the default volume and queue limits. Everything works with the \
+             defaults too.",
+            false,
+        )
+        .field("More", "`/help` lists every command.", false)
+        .colour(0x5865f2);
+    let button = CreateButton::new(START_BUTTON_ID)
+        .label("Run /setup")
+        .emoji('⚙')
+        .style(ButtonStyle::Primary);
+    if let Err(e) = channel_id
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .embed(embed)
+                .components(vec![CreateActionRow::Buttons(vec![button])]),
+        )
+        .await
+    {
+        tracing::debug!("Couldn't post welcome message in guild {}: {}", guild.id, e);
+    }
+}
+
+/// Handle a select menu or button in the wizard
+pub async fn handle_component(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+) -> Result<()> {
+    let guild_id = component
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let user_id = component.user.id.to_string();
+    // The welcome message's button is public, so it checks what `/setup` would have
+    if component.data.custom_id == START_BUTTON_ID {
+        let can_manage = component
+            .member
+            .as_ref()
+            .and_then(|m| m.permissions)
+            .is_some_and(|p| p.manage_guild());
+        let response = if can_manage {
+            open_wizard(&guild_id, &user_id)?
+        } else {
+            CreateInteractionResponseMessage::new()
+                .content("Only members who can manage the server can run setup.")
+                .ephemeral(true)
+        };
+        component
+            .create_response(&ctx.http, CreateInteractionResponse::Message(response))
+            .await?;
+        return Ok(());
+    }
+    let Some((mut draft, started)) = take_draft(&guild_id, &user_id) else {
+        return update(ctx, component, expired(), Vec::new()).await;
+    };
+
+    let notice = match (component.data.custom_id.as_str(), &component.data.kind) {
+        (CHANNEL_MENU_ID, ComponentInteractionDataKind::ChannelSelect { values }) => {
+            draft.announcement_channel_id = values.first().map(|c| c.to_string());
+            None
+        }
+        (ROLES_MENU_ID, ComponentInteractionDataKind::RoleSelect { values }) => {
+            draft.dj_roles = values.iter().map(|r| r.to_string()).collect();
+            None
+        }
+        (LIMITS_BUTTON_ID, _) => {
+            let modal = limits_modal(&draft);
+            store_draft(&guild_id, &user_id, draft, started);
+            component
+                .create_response(&ctx.http, CreateInteractionResponse::Modal(modal))
+                .await?;
+            return Ok(());
+        }
+        (SAVE_BUTTON_ID, _) => {
+            let result = {
+                let mut db_conn = establish_connection();
+                GuildSettings::apply_setup(&mut db_conn, &guild_id, &draft)
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to save /setup for guild {}: {}", guild_id, e);
+                let (embed, components) =
+                    render(&draft, Some("❌ Couldn't save the settings; try again."));
+                store_draft(&guild_id, &user_id, draft, started);
+                return update(ctx, component, embed, components).await;
+            }
+            tracing::info!("{} saved /setup in guild {}", user_id, guild_id);
+            settings_events::changed(&guild_id);
+            let (embed, _) = render(&draft, None);
+            let embed = embed.title("✅ Setup saved").colour(0x1db954);
+            return update(ctx, component, embed, Vec::new()).await;
+        }
+        (CANCEL_BUTTON_ID, _) => {
+            let embed = CreateEmbed::new()
+                .title("Setup cancelled")
+                .description("Nothing was changed.")
+                .colour(0x808080);
+            return update(ctx, component, embed, Vec::new()).await;
+        }
+        (other, _) => return Err(anyhow!("unknown setup component {other}")),
+    };
+
+    let (embed, components) = render(&draft, notice);
+    store_draft(&guild_id, &user_id, draft, started);
+    update(ctx, component, embed, components).await
+}
+
+/// Handle the volume and limits modal opened from the wizard
+pub async fn handle_modal(ctx: &SerenityContext, modal: &ModalInteraction) -> Result<()> {
+    let guild_id = modal
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let user_id = modal.user.id.to_string();
+    let Some((mut draft, started)) = take_draft(&guild_id, &user_id) else {
+        return respond_modal(ctx, modal, expired(), Vec::new()).await;
+    };
+
+    let max_volume = {
+        let mut db_conn = establish_connection();
+        GuildSettings::find_by_guild_id(&mut db_conn, &guild_id)
+            .ok()
+            .flatten()
+            .map(|s| s.max_volume)
+            .unwrap_or(MAX_VOLUME)
+    };
+    let max_percent = (max_volume * 100.0).round() as i32;
+    let volume = input_number(modal, VOLUME_INPUT_ID).filter(|v| (0..=max_percent).contains(v));
+    let minutes = input_number(modal, DISCONNECT_INPUT_ID)
+        .filter(|m| (1..=MAX_AUTO_DISCONNECT_MINUTES).contains(m));
+    let size = input_number(modal, QUEUE_INPUT_ID).filter(|s| (1..=MAX_QUEUE_SIZE).contains(s));
+
+    let notice = match (volume, minutes, size) {
+        (Some(volume), Some(minutes), Some(size)) => {
+            draft.default_volume = volume as f32 / 100.0;
+            draft.auto_disconnect_minutes = minutes;
+            draft.max_queue_size = size;
+            None
+        }
+        _ => Some(format!(
+            "❌ Volume must be 0-{}%, auto-disconnect 1-{} minutes and the queue limit 1-{} tracks.",
+            max_percent, MAX_AUTO_DISCONNECT_MINUTES, MAX_QUEUE_SIZE
+        )),
+    };
+    let (embed, components) = render(&draft, notice.as_deref());
+    store_draft(&guild_id, &user_id, draft, started);
+    respond_modal(ctx, modal, embed, components).await
+}
+
+fn render(draft: &GuildSetup, notice: Option<&str>) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let channel = draft
+        .announcement_channel_id
+        .as_ref()
+        .map(|c| format!("<#{}>", c))
+        .unwrap_or_else(|| "Where each session is started".to_string());
+    let roles = if draft.dj_roles.is_empty() {
+        "None; only members who can manage the server".to_string()
+    } else {
+        draft
+            .dj_roles
+            .iter()
+            .map(|r| format!("<@&{}>", r))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut embed = CreateEmbed::new()
+        .title("⚙️ Server setup")
+        .description(
+            notice
+                .unwrap_or("Pick the settings below, then press **Save** to apply them together."),
+        )
+        .field("Announcement channel", channel, false)
+        .field("DJ roles", roles, false)
+        .field(
+            "Default volume",
+            format!("{}%", (draft.default_volume * 100.0).round()),
+            true,
+        )
+        .field(
+            "Auto-disconnect",
+            format!("{} min", draft.auto_disconnect_minutes),
+            true,
+        )
+        .field(
+            "Max queue",
+            format!("{} tracks", draft.max_queue_size),
+            true,
+        )
+        .colour(0x5865f2);
+    if notice.is_none() {
+        embed = embed.footer(serenity::all::CreateEmbedFooter::new(
+            "Closes after 15 minutes without saving",
+        ));
+    }
+
+    let default_channels = draft
+        .announcement_channel_id
+        .as_ref()
+        .and_then(|c| c.parse::<u64>().ok())
+        .map(|c| vec![ChannelId::new(c)]);
+    let channel_menu = CreateSelectMenu::new(
+        CHANNEL_MENU_ID,
+        CreateSelectMenuKind::Channel {
+            channel_types: Some(vec![ChannelType::Text, ChannelType::News]),
+            default_channels,
+        },
+    )
+    .placeholder("Announcement channel")
+    .min_values(0)
+    .max_values(1);
+
+    let default_roles: Vec<RoleId> = draft
+        .dj_roles
+        .iter()
+        .filter_map(|r| r.parse::<u64>().ok())
+        .map(RoleId::new)
+        .collect();
+    let roles_menu = CreateSelectMenu::new(
+        ROLES_MENU_ID,
+        CreateSelectMenuKind::Role {
+            default_roles: Some(default_roles),
+        },
+    )
+    .placeholder("DJ roles")
+    .min_values(0)
+    .max_values(MAX_DJ_ROLES as u8);
+
+    let buttons = vec![
+        CreateButton::new(LIMITS_BUTTON_ID)
+            .label("Volume & limits…")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(SAVE_BUTTON_ID)
+            .label("Save")
+            .style(ButtonStyle::Success),
+        CreateButton::new(CANCEL_BUTTON_ID)
+            .label("Cancel")
+            .style(ButtonStyle::Danger),
+    ];
+    let components = vec![
+        CreateActionRow::SelectMenu(channel_menu),
+        CreateActionRow::SelectMenu(roles_menu),
+        CreateActionRow::Buttons(buttons),
+    ];
+    (embed, components)
+}
+
+fn limits_modal(draft: &GuildSetup) -> CreateModal {
+    let input = |id: &str, label: &str, value: String| {
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Short, label, id)
+                .value(value)
+                .max_length(3),
+        )
+    };
+    CreateModal::new(LIMITS_MODAL_ID, "Volume & limits").components(vec![
+        input(
+            VOLUME_INPUT_ID,
+            "Default volume (%)",
+            (draft.default_volume * 100.0).round().to_string(),
+        ),
+        input(
+            DISCONNECT_INPUT_ID,
+            "Leave after this many idle minutes",
+            draft.auto_disconnect_minutes.to_string(),
+        ),
+        input(
+            QUEUE_INPUT_ID,
+            "Most tracks in the queue",
+            draft.max_queue_size.to_string(),
+        ),
+    ])
+}
+
+fn input_number(modal: &ModalInteraction, id: &str) -> Option<i32> {
+    modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|c| match c {
+            ActionRowComponent::InputText(input) if input.custom_id == id => input.value.as_ref(),
+            _ => None,
+        })
+        .and_then(|v| v.trim().trim_end_matches('%').parse().ok())
+}
+
+fn expired() -> CreateEmbed {
+    CreateEmbed::new()
+        .title("Setup expired")
+        .description("Run `/setup` again to pick up where you left off.")
+        .colour(0x808080)
+}
+
+/// Put a wizard's choices back for its next step; `started` is when `/setup` was run, so the
+/// draft expires along with its interaction token
+fn store_draft(guild_id: &str, user_id: &str, draft: GuildSetup, started: Instant) {
+    let mut drafts = DRAFTS.lock().unwrap();
+    drafts.retain(|_, (_, started)| started.elapsed() < DRAFT_TTL);
+    drafts.insert(
+        (guild_id.to_string(), user_id.to_string()),
+        (draft, started),
+    );
+}
+
+fn take_draft(guild_id: &str, user_id: &str) -> Option<(GuildSetup, Instant)> {
+    let key = (guild_id.to_string(), user_id.to_string());
+    DRAFTS
+        .lock()
+        .unwrap()
+        .remove(&key)
+        .filter(|(_, started)| started.elapsed() < DRAFT_TTL)
+}
+
+async fn update(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+    embed: CreateEmbed,
+    components: Vec<CreateActionRow>,
+) -> Result<()> {
+    component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .components(components),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+async fn respond_modal(
+    ctx: &SerenityContext,
+    modal: &ModalInteraction,
+    embed: CreateEmbed,
+    components: Vec<CreateActionRow>,
+) -> Result<()> {
+    modal
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .components(components),
+            ),
+        )
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use crate::database::establish_connection;
+use crate::database::models::CurrentQueue;
+use crate::fade;
+use crate::metrics::METRICS;
+use crate::theme::{Icon, Theme};
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context as SerenityContext, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    EditInteractionResponse, InteractionContext, Permissions,
+};
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("skipto")
+        .description("Skip straight to a queued track, dropping the ones before it")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "position",
+                "Queue position of the track, as shown by /queue show",
+            )
+            .min_int_value(1)
+            .required(true),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let position = cmd
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == "position")
+        .and_then(|o| o.value.as_i64())
+        .ok_or_else(|| anyhow!("missing position"))?;
+
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let Some(call_lock) = manager.get(guild_id) else {
+        return super::reject(ctx, cmd, "Not connected.").await;
+    };
+    let target = CurrentQueue::get_guild_queue(&mut establish_connection(), &guild_id.to_string())?
+        .into_iter()
+        .find(|e| e.position > 0 && e.position as i64 == position);
+    let Some(target) = target else {
+        return super::reject(
+            ctx,
+            cmd,
+            &format!("There's no track waiting at position {}", position),
+        )
+        .await;
+    };
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+    )
+    .await
+    .ok();
+
+    // Drop everything in between first, so skipping the playing track lands on the target
+    let removed = CurrentQueue::remove_ahead_of(
+        &mut establish_connection(),
+        &guild_id.to_string(),
+        target.position,
+    )?;
+    let uuids: Vec<String> = removed
+        .iter()
+        .filter_map(|e| e.track_uuid.clone())
+        .collect();
+    super::queue::remove_live(&call_lock, &uuids).await;
+    METRICS.dec_queue(removed.len());
+
+    fade::fade_out(&call_lock).await;
+    if call_lock.lock().await.queue().skip().is_ok() {
+        METRICS.dec_queue(1);
+    }
+    tracing::info!(
+        "{} skipped to queue position {} in guild {}, dropping {} track(s)",
+        cmd.user.id,
+        position,
+        guild_id,
+        removed.len()
+    );
+
+    let title = target.title.unwrap_or(target.url);
+    let mut description = format!("Now playing **{}**.", title);
+    if !removed.is_empty() {
+        description.push_str(&format!(
+            " Dropped {} track{} in between.",
+            removed.len(),
+            if removed.len() == 1 { "" } else { "s" }
+        ));
+    }
+    let embed = Theme::for_guild(&guild_id.to_string())
+        .embed(Icon::Skip, "Skipped Ahead", 0x00FF7F) // Spring green
+        .description(description);
+    cmd.edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().embeds(vec![embed]),
+    )
+    .await
+    .ok();
+    Ok(())
+}
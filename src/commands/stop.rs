@@ -1,14 +1,48 @@
+use crate::capacity;
 use crate::database::establish_connection;
-use crate::database::models::VoiceConnection;
+use crate::database::models::{CurrentQueue, VoiceConnection};
+use crate::fade;
 use crate::metrics::METRICS;
+use crate::policy::check_not_draining;
+use crate::stage;
+use crate::voice_manager;
 use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
 use serenity::all::{
-    CommandInteraction, Context as SerenityContext, CreateCommand, CreateInteractionResponse,
-    CreateInteractionResponseMessage, EditInteractionResponse,
+    ButtonStyle, ChannelId, CommandInteraction, ComponentInteraction, Context as SerenityContext,
+    CreateActionRow, CreateButton, CreateCommand, CreateInteractionResponse,
+    CreateInteractionResponseMessage, EditInteractionResponse, GuildId, InteractionContext,
+    Permissions, UserId,
 };
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const UNDO_BUTTON_PREFIX: &str = "stop_undo:";
+
+/// How long after `/stop` the "Undo" button still restores the queue
+const UNDO_WINDOW: Duration = Duration::from_secs(60);
+const MAX_CLEARED_LISTED: usize = 5;
+
+/// What `/stop` cleared in each guild, kept until it's undone, expires or another `/stop`
+/// replaces it
+static SNAPSHOTS: Lazy<Mutex<HashMap<GuildId, StopSnapshot>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct StopSnapshot {
+    /// The `/stop` interaction, so an older confirmation's button can't restore a newer snapshot
+    stop_id: String,
+    taken_at: Instant,
+    voice_channel: Option<ChannelId>,
+    text_channel: ChannelId,
+    tracks: Vec<CurrentQueue>,
+}
 
 pub fn definition() -> CreateCommand {
-    CreateCommand::new("stop").description("Stop playback and clear the queue")
+    CreateCommand::new("stop")
+        .description("Stop playback and clear the queue")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
 }
 
 pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
@@ -30,7 +64,15 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
         .ok();
         return Ok(());
     };
+    // Take the snapshot before stopping: each stopped track advances the stored queue
+    let tracks = CurrentQueue::get_guild_queue(&mut establish_connection(), &guild_id.to_string())
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to snapshot queue before /stop: {}", e);
+            Vec::new()
+        });
+    fade::fade_out(&call_lock).await;
     let mut call = call_lock.lock().await;
+    let voice_channel = call.current_channel().map(|c| ChannelId::new(c.0.get()));
     // Adjust metrics with current queue length if we can get it
     let qlen = call.queue().len();
     if qlen > 0 {
@@ -41,9 +83,8 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
     drop(call);
     // Also disconnect from the voice channel
     let manager_clone = manager.clone();
+    stage::session_ended(guild_id).await;
     if manager_clone.remove(guild_id).await.is_ok() {
-        METRICS.dec_connections();
-
         // Update database to remove voice connection tracking
         let mut db_conn = establish_connection();
         if let Err(e) = VoiceConnection::disconnect(&mut db_conn, &guild_id.to_string()) {
@@ -54,11 +95,259 @@ pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<(
         }
     }
 
+    if tracks.is_empty() {
+        cmd.edit_response(
+            &ctx.http,
+            EditInteractionResponse::new().content("Stopped, cleared queue, and disconnected."),
+        )
+        .await
+        .ok();
+        return Ok(());
+    }
+
+    let summary = cleared_summary(&tracks);
+    if let Ok(mut snapshots) = SNAPSHOTS.lock() {
+        snapshots.retain(|_, snapshot| snapshot.taken_at.elapsed() < UNDO_WINDOW);
+        snapshots.insert(
+            guild_id,
+            StopSnapshot {
+                stop_id: cmd.id.to_string(),
+                taken_at: Instant::now(),
+                voice_channel,
+                text_channel: cmd.channel_id,
+                tracks,
+            },
+        );
+    }
+    let undo = CreateButton::new(format!("{}{}", UNDO_BUTTON_PREFIX, cmd.id))
+        .label("Undo")
+        .emoji('↩')
+        .style(ButtonStyle::Secondary);
     cmd.edit_response(
         &ctx.http,
-        EditInteractionResponse::new().content("Stopped, cleared queue, and disconnected."),
+        EditInteractionResponse::new()
+            .content(format!(
+                "⏹️ Stopped and disconnected. {}\nPress Undo within {} seconds to bring the queue back.",
+                summary,
+                UNDO_WINDOW.as_secs()
+            ))
+            .components(vec![CreateActionRow::Buttons(vec![undo])]),
     )
     .await
     .ok();
+
+    // Take the button away once it can no longer do anything
+    let (ctx, cmd) = (ctx.clone(), cmd.clone());
+    tokio::spawn(async move {
+        tokio::time::sleep(UNDO_WINDOW).await;
+        let still_pending = SNAPSHOTS.lock().is_ok_and(|snapshots| {
+            snapshots
+                .get(&guild_id)
+                .is_some_and(|snapshot| snapshot.stop_id == cmd.id.to_string())
+        });
+        if still_pending {
+            cmd.edit_response(
+                &ctx.http,
+                EditInteractionResponse::new()
+                    .content(format!("⏹️ Stopped and disconnected. {}", summary))
+                    .components(Vec::new()),
+            )
+            .await
+            .ok();
+        }
+    });
+    Ok(())
+}
+
+/// "Cleared 12 tracks (48:10): • First • Second … and 7 more"
+fn cleared_summary(tracks: &[CurrentQueue]) -> String {
+    let total_secs: i64 = tracks
+        .iter()
+        .filter_map(|t| t.duration)
+        .map(i64::from)
+        .sum();
+    let mut summary = format!(
+        "Cleared {} track{}",
+        tracks.len(),
+        if tracks.len() == 1 { "" } else { "s" }
+    );
+    if total_secs > 0 {
+        summary.push_str(&format!(
+            " ({})",
+            super::play::format_position(total_secs as u64)
+        ));
+    }
+    summary.push(':');
+    for track in tracks.iter().take(MAX_CLEARED_LISTED) {
+        summary.push_str(&format!(
+            "\n• {}",
+            track.title.as_deref().unwrap_or(&track.url)
+        ));
+    }
+    if tracks.len() > MAX_CLEARED_LISTED {
+        summary.push_str(&format!(
+            "\n…and {} more",
+            tracks.len() - MAX_CLEARED_LISTED
+        ));
+    }
+    summary
+}
+
+/// Restore the queue a `/stop` cleared when someone presses its "Undo" button: the bot rejoins
+/// the voice channel it left (or the presser's) and queues the tracks again in their old order.
+/// The track that was playing starts over from the beginning.
+pub async fn handle_undo_button(
+    ctx: &SerenityContext,
+    component: &ComponentInteraction,
+) -> Result<()> {
+    let stop_id = component
+        .data
+        .custom_id
+        .strip_prefix(UNDO_BUTTON_PREFIX)
+        .ok_or_else(|| anyhow!("malformed undo button id"))?;
+    let guild_id = component
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?;
+    // Undoing restarts playback, so it's held to what /stop and /play are
+    if !super::allow_playback_button(ctx, component, "stop").await? {
+        return Ok(());
+    }
+    if let Err(e) = check_not_draining() {
+        component
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!("❌ {}", e))
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let snapshot = SNAPSHOTS.lock().ok().and_then(|mut snapshots| {
+        let current = snapshots.get(&guild_id).is_some_and(|snapshot| {
+            snapshot.stop_id == stop_id && snapshot.taken_at.elapsed() < UNDO_WINDOW
+        });
+        if current {
+            snapshots.remove(&guild_id)
+        } else {
+            None
+        }
+    });
+    let Some(snapshot) = snapshot else {
+        component
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("❌ It's too late to undo that stop")
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+        return Ok(());
+    };
+
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    // Someone may have started something new since; add the old queue behind it
+    if manager.get(guild_id).is_none() {
+        let channel_id = snapshot.voice_channel.or_else(|| {
+            ctx.cache.guild(guild_id).and_then(|guild| {
+                guild
+                    .voice_states
+                    .get(&component.user.id)
+                    .and_then(|vs| vs.channel_id)
+            })
+        });
+        let joined = match channel_id {
+            Some(_) if !capacity::try_admit(guild_id, manager.iter().count()) => {
+                Err(anyhow!("the bot is at capacity right now"))
+            }
+            Some(channel_id) => voice_manager::join_voice_channel(ctx, guild_id, channel_id).await,
+            None => Err(anyhow!("there's no voice channel to rejoin")),
+        };
+        if let Err(e) = joined {
+            // Leave it for another try (e.g. from a voice channel) while the window lasts
+            if let Ok(mut snapshots) = SNAPSHOTS.lock() {
+                snapshots.entry(guild_id).or_insert(snapshot);
+            }
+            component
+                .create_response(
+                    &ctx.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content(format!("❌ Couldn't restore the queue: {}", e))
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+            return Ok(());
+        }
+    }
+
+    component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "↩️ <@{}> undid the stop; restoring {} track{}…",
+                        component.user.id,
+                        snapshot.tracks.len(),
+                        if snapshot.tracks.len() == 1 { "" } else { "s" }
+                    ))
+                    .components(Vec::new()),
+            ),
+        )
+        .await?;
+
+    let (ctx, component) = (ctx.clone(), component.clone());
+    tokio::spawn(async move {
+        let mut restored = 0;
+        for track in &snapshot.tracks {
+            // The restored queue may have played out and disconnected while we were downloading
+            if manager.get(guild_id).is_none() {
+                break;
+            }
+            let user_id = track
+                .added_by
+                .parse()
+                .map(UserId::new)
+                .unwrap_or(component.user.id);
+            match super::play::enqueue_quietly(
+                &ctx,
+                guild_id,
+                snapshot.text_channel,
+                user_id,
+                &track.url,
+                track.priority,
+            )
+            .await
+            {
+                Ok(_) => restored += 1,
+                Err(e) => tracing::info!(
+                    "Couldn't restore {} in guild {} after /stop: {}",
+                    track.url,
+                    guild_id,
+                    e
+                ),
+            }
+        }
+        component
+            .edit_response(
+                &ctx.http,
+                EditInteractionResponse::new().content(format!(
+                    "↩️ <@{}> undid the stop; restored {} of {} track{}",
+                    component.user.id,
+                    restored,
+                    snapshot.tracks.len(),
+                    if snapshot.tracks.len() == 1 { "" } else { "s" }
+                )),
+            )
+            .await
+            .ok();
+    });
     Ok(())
 }
@@ -0,0 +1,37 @@
+//! Fake interactions for testing command handlers' logic without a Discord connection
+
+use serde_json::{Value, json};
+use serenity::all::CommandInteraction;
+
+/// Guild the fake interactions come from
+pub const GUILD_ID: u64 = 987654321;
+/// Member who invokes them
+pub const USER_ID: u64 = 123456789;
+
+/// A `/name` invocation with `options` as Discord sends them, e.g.
+/// `[{ "name": "url", "type": 3, "value": "https://…" }]`
+pub fn command(name: &str, options: Value) -> CommandInteraction {
+    serde_json::from_value(json!({
+        "id": "1",
+        "application_id": "2",
+        "type": 2,
+        "token": "fake-token",
+        "version": 1,
+        "guild_id": GUILD_ID.to_string(),
+        "channel_id": "3",
+        "data": { "id": "4", "name": name, "type": 1, "options": options },
+        "member": {
+            "user": { "id": USER_ID.to_string(), "username": "tester", "discriminator": "0" },
+            "roles": [],
+            "joined_at": "2024-01-01T00:00:00Z",
+            "deaf": false,
+            "mute": false,
+            "flags": 0,
+            "permissions": "0",
+        },
+        "locale": "en-US",
+        "app_permissions": "0",
+        "entitlements": [],
+    }))
+    .expect("fake interaction doesn't deserialize")
+}
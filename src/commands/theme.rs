@@ -0,0 +1,169 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    Context as SerenityContext, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+use crate::settings_events;
+use crate::theme::{EmojiSet, Icon, MAX_FOOTER_LEN, Theme, format_hex_color, parse_hex_color};
+
+pub fn definition() -> CreateCommand {
+    let emoji = EmojiSet::ALL.into_iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "set", "Which icons to use")
+            .required(true),
+        |option, set| option.add_string_choice(set.key(), set.key()),
+    );
+    CreateCommand::new("theme")
+        .description("Change how the bot's playback messages look in this server")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "show",
+            "Preview the current theme",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "color",
+                "Set the accent color",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "value",
+                    "Hex color like #5865F2, or \"default\" for the built-in colors",
+                )
+                .required(true),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "emoji", "Set the emoji set")
+                .add_sub_option(emoji),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "footer",
+                "Set text shown under playback messages",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "text",
+                    "Footer text; leave out to remove it",
+                )
+                .max_length(MAX_FOOTER_LEN as u16),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "reset",
+            "Go back to the default look",
+        ))
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let Some(sub) = cmd.data.options.first() else {
+        return Err(anyhow!("missing subcommand"));
+    };
+    let CommandDataOptionValue::SubCommand(args) = &sub.value else {
+        return Err(anyhow!("expected subcommand"));
+    };
+
+    let mut theme = Theme::for_guild(&guild_id);
+    match sub.name.as_str() {
+        "show" => return respond(ctx, cmd, "Here's how playback messages look:", &theme).await,
+        "color" => {
+            let value = string_arg(args, "value").unwrap_or_default().trim();
+            theme.accent = if value.eq_ignore_ascii_case("default") {
+                None
+            } else {
+                match parse_hex_color(value) {
+                    Some(colour) => Some(colour),
+                    None => {
+                        return super::reject(
+                            ctx,
+                            cmd,
+                            "Colors look like `#5865F2`, or use `default`",
+                        )
+                        .await;
+                    }
+                }
+            };
+        }
+        "emoji" => {
+            theme.emoji = string_arg(args, "set")
+                .and_then(EmojiSet::from_key)
+                .ok_or_else(|| anyhow!("missing or unknown emoji set"))?;
+        }
+        "footer" => {
+            theme.footer = string_arg(args, "text")
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string);
+        }
+        "reset" => theme = Theme::default(),
+        other => return Err(anyhow!("unknown subcommand {other}")),
+    }
+
+    {
+        let mut db_conn = establish_connection();
+        GuildSettings::create_or_update(&mut db_conn, &guild_id)?;
+        GuildSettings::update_theme(
+            &mut db_conn,
+            &guild_id,
+            theme.accent.map(|c| c as i32),
+            theme.emoji.key(),
+            theme.footer.as_deref(),
+        )?;
+    }
+    settings_events::changed(&guild_id);
+    tracing::info!("{} changed the theme in guild {}", cmd.user.id, guild_id);
+    respond(ctx, cmd, "✅ Theme updated:", &theme).await
+}
+
+/// Reply with `content` and a sample embed in `theme`
+async fn respond(
+    ctx: &SerenityContext,
+    cmd: &CommandInteraction,
+    content: &str,
+    theme: &Theme,
+) -> Result<()> {
+    let preview = theme
+        .embed(Icon::NowPlaying, "Now Playing", 0x1db954)
+        .description("**Example track**")
+        .field(
+            "Accent",
+            theme
+                .accent
+                .map(format_hex_color)
+                .unwrap_or_else(|| "default".to_string()),
+            true,
+        )
+        .field("Emoji", theme.emoji.key(), true);
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .embed(preview)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn string_arg<'a>(args: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    args.iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.value.as_str())
+}
@@ -0,0 +1,91 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, Context as SerenityContext, CreateCommand, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, InteractionContext,
+};
+
+use crate::voice_stats;
+
+/// Loss above this share of packets points at the network
+const HIGH_LOSS: f64 = 0.02;
+/// Jitter above this many milliseconds points at the network
+const HIGH_JITTER_MS: f64 = 30.0;
+/// More than this share of late voice ticks points at the host
+const HIGH_LATE_TICKS: f64 = 0.01;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("voicedebug")
+        .description("Show voice connection quality, to work out why audio is stuttering")
+        .contexts(vec![InteractionContext::Guild])
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let Some(stats) = voice_stats::for_guild(guild_id) else {
+        return super::reject(
+            ctx,
+            cmd,
+            "I haven't been in a voice channel here since I started",
+        )
+        .await;
+    };
+
+    let late_share = if stats.ticks == 0 {
+        0.0
+    } else {
+        stats.late_ticks as f64 / stats.ticks as f64
+    };
+    let network_trouble = stats.fraction_lost > HIGH_LOSS || stats.jitter_ms > HIGH_JITTER_MS;
+    let host_trouble = late_share > HIGH_LATE_TICKS;
+    let verdict = match (network_trouble, host_trouble) {
+        (true, true) => "Both the network and this bot's host look strained.",
+        (true, false) => {
+            "Packets are getting lost or delayed on the way to Discord: a network problem."
+        }
+        (false, true) => "The bot's host is falling behind: a CPU or load problem on our side.",
+        (false, false) if stats.reports == 0 => {
+            "No problems on the host. Discord hasn't sent a network report yet, so check again in a minute."
+        }
+        (false, false) => "No problems detected on the network or the host.",
+    };
+
+    let network = if stats.reports == 0 {
+        "No reports from Discord yet".to_string()
+    } else {
+        format!(
+            "Loss: {:.1}% (total {})\nJitter: {:.1} ms\nReports: {}",
+            stats.fraction_lost * 100.0,
+            stats.cumulative_lost,
+            stats.jitter_ms,
+            stats.reports
+        )
+    };
+    let host = format!(
+        "Late ticks: {} of {} ({:.2}%)\nLongest gap: {} ms\nReconnects: {}",
+        stats.late_ticks,
+        stats.ticks,
+        late_share * 100.0,
+        stats.longest_tick_gap_ms,
+        stats.reconnects
+    );
+    let embed = CreateEmbed::new()
+        .title("🔧 Voice Diagnostics")
+        .description(verdict)
+        .field("Network", network, true)
+        .field("Host", host, true)
+        .colour(if network_trouble || host_trouble {
+            0xf39c12
+        } else {
+            0x1db954
+        });
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(embed)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use anyhow::{Result, anyhow};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context as SerenityContext, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    InteractionContext, Permissions,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::{DEFAULT_VOLUME, GuildSettings};
+use crate::validation::MAX_VOLUME;
+use crate::voice_manager;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("volume")
+        .description("Show or set how loud the bot plays, for this track and the ones after it")
+        .contexts(vec![InteractionContext::Guild])
+        .default_member_permissions(Permissions::CONNECT)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "percent",
+                "New volume, where 100 is the source level; leave out to see the current one",
+            )
+            .min_int_value(0)
+            .max_int_value((MAX_VOLUME * 100.0) as u64),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd
+        .guild_id
+        .ok_or_else(|| anyhow!("not in a guild"))?
+        .to_string();
+    let settings = GuildSettings::find_by_guild_id(&mut establish_connection(), &guild_id)?;
+    let (volume, max_volume) = settings
+        .as_ref()
+        .map(|s| (s.start_volume(), s.max_volume))
+        .unwrap_or((DEFAULT_VOLUME, MAX_VOLUME));
+
+    let percent = cmd
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == "percent")
+        .and_then(|o| o.value.as_i64());
+    let Some(percent) = percent else {
+        return respond(
+            ctx,
+            cmd,
+            format!(
+                "🔊 The volume is {}% (this server allows up to {}%).",
+                as_percent(volume),
+                as_percent(max_volume)
+            ),
+        )
+        .await;
+    };
+
+    let new_volume = percent as f32 / 100.0;
+    if new_volume > max_volume {
+        return super::reject(
+            ctx,
+            cmd,
+            &format!(
+                "This server caps the volume at {}%; ask an admin to raise `max_volume` to go louder.",
+                as_percent(max_volume)
+            ),
+        )
+        .await;
+    }
+    voice_manager::set_volume(&guild_id, new_volume).await?;
+    tracing::info!(
+        "{} set the volume to {}% in guild {}",
+        cmd.user.id,
+        percent,
+        guild_id
+    );
+    respond(
+        ctx,
+        cmd,
+        format!(
+            "{} Volume set to {}% for this track and the ones after it.",
+            if new_volume < volume { "🔉" } else { "🔊" },
+            percent
+        ),
+    )
+    .await
+}
+
+fn as_percent(volume: f32) -> i64 {
+    (volume * 100.0).round() as i64
+}
+
+async fn respond(ctx: &SerenityContext, cmd: &CommandInteraction, content: String) -> Result<()> {
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content),
+        ),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, Utc};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context as SerenityContext, CreateCommand,
+    CreateCommandOption, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+
+use crate::database::establish_connection;
+use crate::database::models::QueueHistory;
+use crate::stats::{MIN_WRAPPED_YEAR, format_listening_time, summarize, year_bounds};
+
+/// Entries shown in each ranking
+const TOP_N: usize = 5;
+
+pub fn definition() -> CreateCommand {
+    CreateCommand::new("wrapped")
+        .description("Year-in-review recap of this server's (or your) listening")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "scope", "Whose recap to show")
+                .add_string_choice("This server", "server")
+                .add_string_choice("Just me", "me"),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "year", "Defaults to this year")
+                .min_int_value(MIN_WRAPPED_YEAR as u64)
+                .max_int_value(Utc::now().year() as u64),
+        )
+}
+
+pub async fn handle(ctx: &SerenityContext, cmd: &CommandInteraction) -> Result<()> {
+    let guild_id = cmd.guild_id.ok_or_else(|| anyhow!("not in a guild"))?;
+    let personal = cmd
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == "scope")
+        .and_then(|o| o.value.as_str())
+        == Some("me");
+    let year = cmd
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == "year")
+        .and_then(|o| o.value.as_i64())
+        .map(|y| y as i32)
+        .unwrap_or_else(|| Utc::now().year());
+    let period = year_bounds(year).ok_or_else(|| anyhow!("invalid year {year}"))?;
+
+    let user_id = cmd.user.id.to_string();
+    let history = {
+        let mut db_conn = establish_connection();
+        QueueHistory::find_for_stats(
+            &mut db_conn,
+            Some(&guild_id.to_string()),
+            personal.then_some(user_id.as_str()),
+            Some(period),
+        )?
+    };
+    let stats = summarize(&history, TOP_N);
+
+    let title = if personal {
+        format!("🎁 Your {} Wrapped", year)
+    } else {
+        let name = guild_id
+            .name(&ctx.cache)
+            .unwrap_or_else(|| "This Server".to_string());
+        format!("🎁 {} — {} Wrapped", name, year)
+    };
+
+    let embed = if stats.plays == 0 {
+        CreateEmbed::new()
+            .title(title)
+            .description(format!("No tracks were played in {}.", year))
+            .colour(0x808080)
+    } else {
+        let top_tracks = stats
+            .top_tracks
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                format!(
+                    "{}. [{}]({}) — {} play{}",
+                    i + 1,
+                    t.title.as_deref().unwrap_or("Unknown"),
+                    t.url,
+                    t.plays,
+                    if t.plays == 1 { "" } else { "s" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let busiest_day = stats
+            .busiest_day
+            .as_ref()
+            .map(|d| format!("{} ({} tracks)", d.date.format("%B %-d"), d.plays))
+            .unwrap_or_else(|| "—".to_string());
+
+        let mut embed = CreateEmbed::new()
+            .title(title)
+            .field("Tracks played", stats.plays.to_string(), true)
+            .field(
+                "Time listened",
+                format_listening_time(stats.total_seconds),
+                true,
+            )
+            .field("Busiest day", busiest_day, true)
+            .field("Top tracks", top_tracks, false)
+            .colour(0x1db954);
+        if !personal {
+            let top_requesters = stats
+                .top_requesters
+                .iter()
+                .enumerate()
+                .map(|(i, r)| format!("{}. <@{}> — {} tracks", i + 1, r.user_id, r.plays))
+                .collect::<Vec<_>>()
+                .join("\n");
+            embed = embed.field("Top requesters", top_requesters, false);
+        }
+        embed.footer(CreateEmbedFooter::new("Times are in UTC"))
+    };
+
+    cmd.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(embed)
+                .ephemeral(personal),
+        ),
+    )
+    .await?;
+    Ok(())
+}
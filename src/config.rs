@@ -0,0 +1,120 @@
+use anyhow::{Result, anyhow};
+use once_cell::sync::{Lazy, OnceCell};
+use std::collections::HashMap;
+use std::env::VarError;
+use std::sync::RwLock;
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry, layer::SubscriberExt, reload, util::SubscriberInitExt,
+};
+
+/// File re-read on reload; defaults to the `.env` loaded at startup
+const CONFIG_FILE_ENV: &str = "LYRE_CONFIG_FILE";
+const DEFAULT_CONFIG_FILE: &str = ".env";
+/// Key whose value sets the log filter, as at startup
+const LOG_FILTER_KEY: &str = "RUST_LOG";
+
+/// Values from the config file as of the last reload; they take precedence over the process
+/// environment so edits apply without a restart
+static OVERRIDES: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+static LOG_FILTER: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+
+/// Read a tunable, preferring the config file as last reloaded over the process environment.
+/// Use this instead of `std::env::var` for settings that should follow a reload.
+pub fn var(key: &str) -> Result<String, VarError> {
+    if let Ok(overrides) = OVERRIDES.read()
+        && let Some(value) = overrides.get(key)
+    {
+        return Ok(value.clone());
+    }
+    std::env::var(key)
+}
+
+/// Install the global tracing subscriber with a log filter that `reload` can swap out. With the
+/// `console` feature, tokio-console's layer is added alongside; the filter only applies to logs,
+/// so the console still sees the runtime's spans.
+pub fn init_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
+    let subscriber =
+        tracing_subscriber::registry().with(tracing_subscriber::fmt::layer().with_filter(filter));
+    #[cfg(feature = "console")]
+    let subscriber = subscriber.with(console_subscriber::spawn());
+    subscriber.init();
+    let _ = LOG_FILTER.set(handle);
+}
+
+/// Re-read the config file and apply it. Returns the names (not values, which may be secrets)
+/// of the keys whose effective value changed. Startup-only settings such as the Discord token,
+/// database URL and HTTP bind address are read once and still need a restart.
+pub fn reload() -> Result<Vec<String>> {
+    let path = std::env::var(CONFIG_FILE_ENV).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+    let mut fresh = HashMap::new();
+    for item in dotenvy::from_path_iter(&path)
+        .map_err(|e| anyhow!("failed to open config file {}: {}", path, e))?
+    {
+        let (key, value) = item.map_err(|e| anyhow!("failed to parse {}: {}", path, e))?;
+        fresh.insert(key, value);
+    }
+
+    if let Some(filter) = fresh.get(LOG_FILTER_KEY) {
+        let filter = EnvFilter::try_new(filter)
+            .map_err(|e| anyhow!("invalid {} value: {}", LOG_FILTER_KEY, e))?;
+        if let Some(handle) = LOG_FILTER.get() {
+            handle
+                .reload(filter)
+                .map_err(|e| anyhow!("failed to apply log filter: {}", e))?;
+        }
+    }
+
+    let mut overrides = OVERRIDES
+        .write()
+        .map_err(|_| anyhow!("config lock poisoned"))?;
+    let mut changed: Vec<String> = fresh
+        .iter()
+        .filter(|(key, value)| {
+            let current = overrides
+                .get(*key)
+                .cloned()
+                .or_else(|| std::env::var(key).ok());
+            current.as_ref() != Some(*value)
+        })
+        .map(|(key, _)| key.clone())
+        .collect();
+    // Keys dropped from the file fall back to the process environment
+    changed.extend(
+        overrides
+            .keys()
+            .filter(|key| !fresh.contains_key(*key))
+            .cloned(),
+    );
+    changed.sort();
+    *overrides = fresh;
+    Ok(changed)
+}
+
+/// Reload the config file whenever the process receives SIGHUP
+pub fn spawn_sighup_listener() {
+    tokio::spawn(async {
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        {
+            Ok(signal) => signal,
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGHUP: {}", e);
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            match reload() {
+                Ok(changed) => tracing::info!(
+                    "Reloaded configuration on SIGHUP; changed: {}",
+                    if changed.is_empty() {
+                        "nothing".to_string()
+                    } else {
+                        changed.join(", ")
+                    }
+                ),
+                Err(e) => tracing::error!("Failed to reload configuration: {}", e),
+            }
+        }
+    });
+}
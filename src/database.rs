@@ -1,14 +1,77 @@
+use anyhow::{Context, Result, anyhow};
+use diesel::connection::SimpleConnection;
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
+use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
+use once_cell::sync::Lazy;
 use std::env;
+use std::path::PathBuf;
 
-pub fn establish_connection() -> SqliteConnection {
+/// How long a connection waits for a lock held by another before giving up
+const BUSY_TIMEOUT_MS: u32 = 5000;
+
+/// Every migration in `migrations/`, applied at startup by [`init`]
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// `DATABASE_URL`, or a SQLite file in the user's cache directory when it isn't set
+static DATABASE_URL: Lazy<String> = Lazy::new(|| {
     dotenvy::dotenv().ok();
+    env::var("DATABASE_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .or_else(|| default_path().map(|p| p.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "lyre.db".to_string())
+});
+
+fn default_path() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("lyre").join("lyre.db"))
+}
+
+pub fn database_url() -> &'static str {
+    &DATABASE_URL
+}
+
+/// Create the database if it doesn't exist and bring its schema up to date; returns how many
+/// migrations were applied. Run once at startup, before anything connects.
+pub fn init() -> Result<usize> {
+    let url = database_url();
+    // Plain file paths get their directory created; URIs and `:memory:` are left alone
+    if !url.starts_with("file:")
+        && url != ":memory:"
+        && let Some(dir) = PathBuf::from(url)
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("cannot create database directory {}", dir.display()))?;
+    }
+    let mut conn = SqliteConnection::establish(url)
+        .map_err(|e| anyhow!("cannot connect to {}: {}", url, e))?;
+    let applied = conn
+        .run_pending_migrations(MIGRATIONS)
+        .map_err(|e| anyhow!("migrating {} failed: {}", url, e))?;
+    Ok(applied.len())
+}
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+pub fn establish_connection() -> SqliteConnection {
+    let database_url = database_url();
+
+    let mut conn = SqliteConnection::establish(database_url)
+        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url));
+    // Wait for another connection's lock instead of failing with "database is locked"
+    if let Err(e) = conn.batch_execute(&format!("PRAGMA busy_timeout = {};", BUSY_TIMEOUT_MS)) {
+        tracing::warn!("Failed to set the SQLite busy timeout: {}", e);
+    }
+    conn
+}
 
-    SqliteConnection::establish(&database_url)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
+/// A migrated in-memory database for unit tests, which can't share `DATABASE_URL`
+#[cfg(test)]
+pub fn test_connection() -> SqliteConnection {
+    let mut conn = SqliteConnection::establish(":memory:").expect("in-memory database");
+    conn.run_pending_migrations(MIGRATIONS)
+        .expect("migrations apply to a fresh database");
+    conn
 }
 
 #[path = "database/models/mod.rs"]
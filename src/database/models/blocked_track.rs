@@ -0,0 +1,86 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::blocked_tracks;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = blocked_tracks)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BlockedTrack {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub track_key: String,
+    pub reason: Option<String>,
+    pub blocked_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = blocked_tracks)]
+pub struct NewBlockedTrack {
+    pub guild_id: String,
+    pub track_key: String,
+    pub reason: Option<String>,
+    pub blocked_by: String,
+}
+
+impl BlockedTrack {
+    /// Block a track in a guild; returns false if it was already blocked
+    pub fn add(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        track_key: &str,
+        reason: Option<&str>,
+        blocked_by: &str,
+    ) -> QueryResult<bool> {
+        let new_block = NewBlockedTrack {
+            guild_id: guild_id.to_string(),
+            track_key: track_key.to_string(),
+            reason: reason.map(|s| s.to_string()),
+            blocked_by: blocked_by.to_string(),
+        };
+
+        let inserted = diesel::insert_or_ignore_into(blocked_tracks::table)
+            .values(&new_block)
+            .execute(conn)?;
+        Ok(inserted > 0)
+    }
+
+    /// Unblock a track; returns false if it wasn't blocked
+    pub fn remove(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        track_key: &str,
+    ) -> QueryResult<bool> {
+        let deleted = diesel::delete(blocked_tracks::table)
+            .filter(blocked_tracks::guild_id.eq(guild_id))
+            .filter(blocked_tracks::track_key.eq(track_key))
+            .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    pub fn find(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        track_key: &str,
+    ) -> QueryResult<Option<BlockedTrack>> {
+        blocked_tracks::table
+            .filter(blocked_tracks::guild_id.eq(guild_id))
+            .filter(blocked_tracks::track_key.eq(track_key))
+            .select(BlockedTrack::as_select())
+            .first::<BlockedTrack>(conn)
+            .optional()
+    }
+
+    pub fn list_for_guild(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+    ) -> QueryResult<Vec<BlockedTrack>> {
+        blocked_tracks::table
+            .filter(blocked_tracks::guild_id.eq(guild_id))
+            .order(blocked_tracks::created_at.desc())
+            .select(BlockedTrack::as_select())
+            .load::<BlockedTrack>(conn)
+    }
+}
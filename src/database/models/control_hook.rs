@@ -0,0 +1,242 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::{control_hook_calls, control_hooks};
+
+/// Calls kept in each hook's audit trail
+const MAX_CALLS_KEPT: i64 = 50;
+
+/// A signed webhook that queues a predefined track or saved playlist in its guild
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = control_hooks)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ControlHook {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub name: String,
+    pub token: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub url: Option<String>,
+    pub playlist: Option<String>,
+    pub voice_channel_id: String,
+    pub rate_limit_per_minute: i32,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    /// Why the hook no longer runs; `None` while it's active
+    pub disabled_reason: Option<String>,
+}
+
+/// What a hook should do and where, as given when it's created
+#[derive(Debug, Clone)]
+pub struct NewControlHookInput {
+    pub guild_id: String,
+    pub name: String,
+    pub url: Option<String>,
+    pub playlist: Option<String>,
+    pub voice_channel_id: String,
+    pub rate_limit_per_minute: i32,
+    pub created_by: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = control_hooks)]
+struct NewControlHook {
+    guild_id: String,
+    name: String,
+    token: String,
+    secret: String,
+    url: Option<String>,
+    playlist: Option<String>,
+    voice_channel_id: String,
+    rate_limit_per_minute: i32,
+    created_by: String,
+}
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = control_hook_calls)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ControlHookCall {
+    pub id: Option<i32>,
+    pub hook_id: i32,
+    pub outcome: String,
+    pub detail: Option<String>,
+    pub remote_addr: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = control_hook_calls)]
+struct NewControlHookCall {
+    hook_id: i32,
+    outcome: String,
+    detail: Option<String>,
+    remote_addr: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookCallOutcome {
+    Accepted,
+    BadSignature,
+    RateLimited,
+    Failed,
+}
+
+impl HookCallOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HookCallOutcome::Accepted => "accepted",
+            HookCallOutcome::BadSignature => "bad_signature",
+            HookCallOutcome::RateLimited => "rate_limited",
+            HookCallOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// `bytes` random bytes from the OS's CSPRNG, hex encoded
+fn random_hex(bytes: usize) -> Result<String, ring::error::Unspecified> {
+    let mut buf = vec![0u8; bytes];
+    SystemRandom::new().fill(&mut buf)?;
+    Ok(hex::encode(buf))
+}
+
+impl ControlHook {
+    /// Create a hook with a fresh token and signing secret
+    pub fn create(
+        conn: &mut SqliteConnection,
+        input: NewControlHookInput,
+    ) -> QueryResult<ControlHook> {
+        let (token, secret) = random_hex(16)
+            .and_then(|token| Ok((token, random_hex(32)?)))
+            .map_err(|_| {
+                diesel::result::Error::QueryBuilderError("no randomness for hook secrets".into())
+            })?;
+        diesel::insert_into(control_hooks::table)
+            .values(&NewControlHook {
+                guild_id: input.guild_id,
+                name: input.name,
+                token,
+                secret,
+                url: input.url,
+                playlist: input.playlist,
+                voice_channel_id: input.voice_channel_id,
+                rate_limit_per_minute: input.rate_limit_per_minute,
+                created_by: input.created_by,
+            })
+            .returning(ControlHook::as_returning())
+            .get_result(conn)
+    }
+
+    pub fn find_by_token(
+        conn: &mut SqliteConnection,
+        token: &str,
+    ) -> QueryResult<Option<ControlHook>> {
+        control_hooks::table
+            .filter(control_hooks::token.eq(token))
+            .select(ControlHook::as_select())
+            .first::<ControlHook>(conn)
+            .optional()
+    }
+
+    pub fn find(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        id: i32,
+    ) -> QueryResult<Option<ControlHook>> {
+        control_hooks::table
+            .filter(control_hooks::guild_id.eq(guild_id))
+            .filter(control_hooks::id.eq(id))
+            .select(ControlHook::as_select())
+            .first::<ControlHook>(conn)
+            .optional()
+    }
+
+    pub fn list_for_guild(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+    ) -> QueryResult<Vec<ControlHook>> {
+        control_hooks::table
+            .filter(control_hooks::guild_id.eq(guild_id))
+            .order(control_hooks::name.asc())
+            .select(ControlHook::as_select())
+            .load::<ControlHook>(conn)
+    }
+
+    /// Delete a hook and its audit trail; returns false if the guild has no such hook
+    pub fn delete(conn: &mut SqliteConnection, guild_id: &str, id: i32) -> QueryResult<bool> {
+        conn.transaction(|conn| {
+            let deleted = diesel::delete(control_hooks::table)
+                .filter(control_hooks::guild_id.eq(guild_id))
+                .filter(control_hooks::id.eq(id))
+                .execute(conn)?;
+            // SQLite doesn't enforce the cascade unless foreign keys are switched on per connection
+            if deleted > 0 {
+                diesel::delete(control_hook_calls::table)
+                    .filter(control_hook_calls::hook_id.eq(id))
+                    .execute(conn)?;
+            }
+            Ok(deleted > 0)
+        })
+    }
+
+    /// Stop the hook from running, keeping it (and its audit trail) listed with the reason
+    pub fn disable(&self, conn: &mut SqliteConnection, reason: &str) -> QueryResult<usize> {
+        diesel::update(control_hooks::table)
+            .filter(control_hooks::id.eq(self.id))
+            .set(control_hooks::disabled_reason.eq(reason))
+            .execute(conn)
+    }
+
+    /// Add a call to the hook's audit trail, keeping only the most recent ones
+    pub fn record_call(
+        &self,
+        conn: &mut SqliteConnection,
+        outcome: HookCallOutcome,
+        detail: Option<&str>,
+        remote_addr: Option<&str>,
+    ) -> QueryResult<()> {
+        let hook_id = self.id.unwrap_or_default();
+        diesel::insert_into(control_hook_calls::table)
+            .values(&NewControlHookCall {
+                hook_id,
+                outcome: outcome.as_str().to_string(),
+                detail: detail.map(str::to_string),
+                remote_addr: remote_addr.map(str::to_string),
+            })
+            .execute(conn)?;
+        // The newest call that falls off the end of the trail, if there is one
+        let oldest_dropped = control_hook_calls::table
+            .filter(control_hook_calls::hook_id.eq(hook_id))
+            .order(control_hook_calls::id.desc())
+            .offset(MAX_CALLS_KEPT)
+            .select(control_hook_calls::id)
+            .first::<Option<i32>>(conn)
+            .optional()?
+            .flatten();
+        if let Some(cutoff) = oldest_dropped {
+            diesel::delete(control_hook_calls::table)
+                .filter(control_hook_calls::hook_id.eq(hook_id))
+                .filter(control_hook_calls::id.le(cutoff))
+                .execute(conn)?;
+        }
+        if outcome == HookCallOutcome::Accepted {
+            diesel::update(control_hooks::table)
+                .filter(control_hooks::id.eq(hook_id))
+                .set(control_hooks::last_used_at.eq(Utc::now().naive_utc()))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// The hook's audit trail, newest first
+    pub fn recent_calls(&self, conn: &mut SqliteConnection) -> QueryResult<Vec<ControlHookCall>> {
+        control_hook_calls::table
+            .filter(control_hook_calls::hook_id.eq(self.id.unwrap_or_default()))
+            .order(control_hook_calls::id.desc())
+            .select(ControlHookCall::as_select())
+            .load::<ControlHookCall>(conn)
+    }
+}
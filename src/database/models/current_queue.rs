@@ -1,8 +1,9 @@
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
-use crate::database::schema::current_queue;
+use crate::database::schema::{current_queue, queue_votes};
 
 #[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
 #[diesel(table_name = current_queue)]
@@ -16,6 +17,48 @@ pub struct CurrentQueue {
     pub position: i32,
     pub added_by: String,
     pub added_at: NaiveDateTime,
+    pub votes: i32,
+    pub track_uuid: Option<String>,
+    pub priority: i32,
+    /// A [`QueueStatus`] key
+    pub status: String,
+}
+
+/// How far an entry has got towards playing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueStatus {
+    /// Queued without its audio; it's fetched when its turn comes
+    PendingDownload,
+    Downloading,
+    /// Downloaded and waiting its turn
+    #[default]
+    Ready,
+    Playing,
+    /// Couldn't be downloaded or played, and will be skipped
+    Failed,
+}
+
+impl QueueStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QueueStatus::PendingDownload => "pending_download",
+            QueueStatus::Downloading => "downloading",
+            QueueStatus::Ready => "ready",
+            QueueStatus::Playing => "playing",
+            QueueStatus::Failed => "failed",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "pending_download" => Some(QueueStatus::PendingDownload),
+            "downloading" => Some(QueueStatus::Downloading),
+            "ready" => Some(QueueStatus::Ready),
+            "playing" => Some(QueueStatus::Playing),
+            "failed" => Some(QueueStatus::Failed),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Insertable)]
@@ -27,9 +70,33 @@ pub struct NewCurrentQueue {
     pub duration: Option<i32>,
     pub position: i32,
     pub added_by: String,
+    pub track_uuid: Option<String>,
+    pub priority: i32,
+    pub status: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = queue_votes)]
+struct NewQueueVote {
+    queue_entry_id: i32,
+    user_id: String,
+}
+
+/// What happened when a listener upvoted a queue entry
+#[derive(Debug, PartialEq)]
+pub enum VoteOutcome {
+    /// The vote was recorded; the entry's new tally
+    Counted(i32),
+    AlreadyVoted,
+    /// The entry is playing or gone, so there's nothing to move up
+    NotPending,
 }
 
 impl CurrentQueue {
+    pub fn status(&self) -> QueueStatus {
+        QueueStatus::from_key(&self.status).unwrap_or_default()
+    }
+
     pub fn get_guild_queue(
         conn: &mut SqliteConnection,
         guild_id: &str,
@@ -53,6 +120,24 @@ impl CurrentQueue {
             .optional()
     }
 
+    /// Whether the guild's queue has an entry for the songbird track `track_uuid`
+    pub fn has_track(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        track_uuid: &str,
+    ) -> QueryResult<bool> {
+        diesel::select(diesel::dsl::exists(
+            current_queue::table
+                .filter(current_queue::guild_id.eq(guild_id))
+                .filter(current_queue::track_uuid.eq(track_uuid)),
+        ))
+        .get_result(conn)
+    }
+
+    /// Append a track to the guild's queue. Tracks with a `priority` above 0 go ahead of every
+    /// pending track of lower priority, but behind the playing track and anything already queued
+    /// at the same or a higher priority.
+    #[allow(clippy::too_many_arguments)]
     pub fn add_to_queue(
         conn: &mut SqliteConnection,
         guild_id: &str,
@@ -60,39 +145,83 @@ impl CurrentQueue {
         title: Option<&str>,
         duration: Option<i32>,
         added_by: &str,
+        track_uuid: Option<&str>,
+        priority: i32,
+        status: QueueStatus,
     ) -> QueryResult<CurrentQueue> {
-        // Get the next position
-        let next_position = current_queue::table
-            .filter(current_queue::guild_id.eq(guild_id))
-            .select(current_queue::position)
-            .order(current_queue::position.desc())
-            .first::<i32>(conn)
-            .optional()?
-            .map(|pos| pos + 1)
-            .unwrap_or(0);
-
-        let new_queue_item = NewCurrentQueue {
-            guild_id: guild_id.to_string(),
-            url: url.to_string(),
-            title: title.map(|s| s.to_string()),
-            duration,
-            position: next_position,
-            added_by: added_by.to_string(),
-        };
-
-        diesel::insert_into(current_queue::table)
-            .values(&new_queue_item)
-            .execute(conn)?;
+        conn.transaction(|conn| {
+            // Get the next position
+            let next_position = current_queue::table
+                .filter(current_queue::guild_id.eq(guild_id))
+                .select(current_queue::position)
+                .order(current_queue::position.desc())
+                .first::<i32>(conn)
+                .optional()?
+                .map(|pos| pos + 1)
+                .unwrap_or(0);
 
-        // Return the inserted item
-        current_queue::table
-            .filter(current_queue::guild_id.eq(guild_id))
-            .filter(current_queue::position.eq(next_position))
-            .select(CurrentQueue::as_select())
-            .first::<CurrentQueue>(conn)
+            let position = if priority > 0 && next_position > 1 {
+                let after = current_queue::table
+                    .filter(current_queue::guild_id.eq(guild_id))
+                    .filter(current_queue::position.gt(0))
+                    .filter(current_queue::priority.ge(priority))
+                    .select(current_queue::position)
+                    .order(current_queue::position.desc())
+                    .first::<i32>(conn)
+                    .optional()?
+                    .unwrap_or(0);
+                after + 1
+            } else {
+                next_position
+            };
+
+            if position < next_position {
+                // Shift the tail down one, via negative positions so UNIQUE(guild_id, position)
+                // holds at every step
+                diesel::update(current_queue::table)
+                    .filter(current_queue::guild_id.eq(guild_id))
+                    .filter(current_queue::position.ge(position))
+                    .set(current_queue::position.eq(current_queue::position * -1 - 2))
+                    .execute(conn)?;
+                diesel::update(current_queue::table)
+                    .filter(current_queue::guild_id.eq(guild_id))
+                    .filter(current_queue::position.lt(0))
+                    .set(current_queue::position.eq(current_queue::position * -1 - 1))
+                    .execute(conn)?;
+            }
+
+            let new_queue_item = NewCurrentQueue {
+                guild_id: guild_id.to_string(),
+                url: url.to_string(),
+                title: title.map(|s| s.to_string()),
+                duration,
+                position,
+                added_by: added_by.to_string(),
+                track_uuid: track_uuid.map(|s| s.to_string()),
+                priority,
+                status: status.as_str().to_string(),
+            };
+
+            diesel::insert_into(current_queue::table)
+                .values(&new_queue_item)
+                .returning(CurrentQueue::as_returning())
+                .get_result(conn)
+        })
     }
 
     pub fn advance_queue(conn: &mut SqliteConnection, guild_id: &str) -> QueryResult<()> {
+        // Row IDs get reused, so drop the finished track's votes along with it
+        diesel::delete(queue_votes::table)
+            .filter(
+                queue_votes::queue_entry_id.nullable().eq_any(
+                    current_queue::table
+                        .filter(current_queue::guild_id.eq(guild_id))
+                        .filter(current_queue::position.eq(0))
+                        .select(current_queue::id),
+                ),
+            )
+            .execute(conn)?;
+
         // Remove current track (position 0)
         diesel::delete(current_queue::table)
             .filter(current_queue::guild_id.eq(guild_id))
@@ -108,8 +237,196 @@ impl CurrentQueue {
         Ok(())
     }
 
-    #[allow(dead_code)]
+    pub fn set_status(
+        conn: &mut SqliteConnection,
+        entry_id: i32,
+        status: QueueStatus,
+    ) -> QueryResult<usize> {
+        diesel::update(current_queue::table)
+            .filter(current_queue::id.eq(entry_id))
+            .set(current_queue::status.eq(status.as_str()))
+            .execute(conn)
+    }
+
+    /// Like [`CurrentQueue::set_status`], for the entry holding the songbird track `track_uuid`
+    pub fn set_track_status(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        track_uuid: &str,
+        status: QueueStatus,
+    ) -> QueryResult<usize> {
+        diesel::update(current_queue::table)
+            .filter(current_queue::guild_id.eq(guild_id))
+            .filter(current_queue::track_uuid.eq(track_uuid))
+            .set(current_queue::status.eq(status.as_str()))
+            .execute(conn)
+    }
+
+    /// Fill in the title and duration of the guild's entries for `url` that were queued without
+    /// them
+    pub fn fill_in_metadata(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        url: &str,
+        title: &str,
+        duration: Option<i32>,
+    ) -> QueryResult<usize> {
+        diesel::update(current_queue::table)
+            .filter(current_queue::guild_id.eq(guild_id))
+            .filter(current_queue::url.eq(url))
+            .filter(current_queue::title.is_null())
+            .set((
+                current_queue::title.eq(title),
+                current_queue::duration.eq(duration),
+            ))
+            .execute(conn)
+    }
+
+    /// Replace an entry's duration with the one probed from its downloaded file
+    pub fn set_duration(
+        conn: &mut SqliteConnection,
+        entry_id: i32,
+        duration: i32,
+    ) -> QueryResult<usize> {
+        diesel::update(current_queue::table)
+            .filter(current_queue::id.eq(entry_id))
+            .set(current_queue::duration.eq(Some(duration)))
+            .execute(conn)
+    }
+
+    /// Record `user_id`'s upvote on a pending entry of `guild_id`'s queue
+    pub fn upvote(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        entry_id: i32,
+        user_id: &str,
+    ) -> QueryResult<VoteOutcome> {
+        conn.transaction(|conn| {
+            let pending = current_queue::table
+                .filter(current_queue::id.eq(entry_id))
+                .filter(current_queue::guild_id.eq(guild_id))
+                .filter(current_queue::position.gt(0))
+                .select(current_queue::id)
+                .first::<Option<i32>>(conn)
+                .optional()?;
+            if pending.is_none() {
+                return Ok(VoteOutcome::NotPending);
+            }
+
+            let inserted = diesel::insert_or_ignore_into(queue_votes::table)
+                .values(&NewQueueVote {
+                    queue_entry_id: entry_id,
+                    user_id: user_id.to_string(),
+                })
+                .execute(conn)?;
+            if inserted == 0 {
+                return Ok(VoteOutcome::AlreadyVoted);
+            }
+
+            let votes = diesel::update(current_queue::table)
+                .filter(current_queue::id.eq(entry_id))
+                .set(current_queue::votes.eq(current_queue::votes + 1))
+                .returning(current_queue::votes)
+                .get_result::<i32>(conn)?;
+            Ok(VoteOutcome::Counted(votes))
+        })
+    }
+
+    /// Rewrite the positions of a guild's pending entries (1, 2, …) to follow `entry_ids`
+    pub fn reorder_pending(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        entry_ids: &[i32],
+    ) -> QueryResult<()> {
+        conn.transaction(|conn| {
+            // Park the entries on negative positions first so UNIQUE(guild_id, position) holds
+            // at every step
+            for (i, entry_id) in entry_ids.iter().enumerate() {
+                diesel::update(current_queue::table)
+                    .filter(current_queue::id.eq(entry_id))
+                    .filter(current_queue::guild_id.eq(guild_id))
+                    .set(current_queue::position.eq(-(i as i32) - 2))
+                    .execute(conn)?;
+            }
+            diesel::update(current_queue::table)
+                .filter(current_queue::guild_id.eq(guild_id))
+                .filter(current_queue::position.lt(0))
+                .set(current_queue::position.eq(current_queue::position * -1 - 1))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Delete pending entries whose URL is already queued earlier in the pending queue, keeping
+    /// the earliest of each, and close the gaps they leave. Returns the removed entries.
+    pub fn remove_duplicates(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+    ) -> QueryResult<Vec<CurrentQueue>> {
+        conn.transaction(|conn| {
+            let mut seen = HashSet::new();
+            let (kept, removed): (Vec<CurrentQueue>, Vec<CurrentQueue>) =
+                Self::get_guild_queue(conn, guild_id)?
+                    .into_iter()
+                    .filter(|e| e.position > 0)
+                    .partition(|e| seen.insert(e.url.clone()));
+            if removed.is_empty() {
+                return Ok(removed);
+            }
+
+            let removed_ids: Vec<i32> = removed.iter().filter_map(|e| e.id).collect();
+            diesel::delete(queue_votes::table)
+                .filter(queue_votes::queue_entry_id.eq_any(&removed_ids))
+                .execute(conn)?;
+            diesel::delete(current_queue::table)
+                .filter(current_queue::id.eq_any(removed.iter().map(|e| e.id).collect::<Vec<_>>()))
+                .execute(conn)?;
+            let kept_ids: Vec<i32> = kept.iter().filter_map(|e| e.id).collect();
+            Self::reorder_pending(conn, guild_id, &kept_ids)?;
+            Ok(removed)
+        })
+    }
+
+    /// Delete the pending entries queued ahead of `position` and close the gap, so the entry at
+    /// `position` plays next. Returns the removed entries.
+    pub fn remove_ahead_of(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        position: i32,
+    ) -> QueryResult<Vec<CurrentQueue>> {
+        conn.transaction(|conn| {
+            let (removed, kept): (Vec<CurrentQueue>, Vec<CurrentQueue>) =
+                Self::get_guild_queue(conn, guild_id)?
+                    .into_iter()
+                    .filter(|e| e.position > 0)
+                    .partition(|e| e.position < position);
+            if removed.is_empty() {
+                return Ok(removed);
+            }
+
+            let removed_ids: Vec<i32> = removed.iter().filter_map(|e| e.id).collect();
+            diesel::delete(queue_votes::table)
+                .filter(queue_votes::queue_entry_id.eq_any(&removed_ids))
+                .execute(conn)?;
+            diesel::delete(current_queue::table)
+                .filter(current_queue::id.eq_any(&removed_ids))
+                .execute(conn)?;
+            let kept_ids: Vec<i32> = kept.iter().filter_map(|e| e.id).collect();
+            Self::reorder_pending(conn, guild_id, &kept_ids)?;
+            Ok(removed)
+        })
+    }
+
     pub fn clear_guild_queue(conn: &mut SqliteConnection, guild_id: &str) -> QueryResult<usize> {
+        diesel::delete(queue_votes::table)
+            .filter(
+                queue_votes::queue_entry_id.nullable().eq_any(
+                    current_queue::table
+                        .filter(current_queue::guild_id.eq(guild_id))
+                        .select(current_queue::id),
+                ),
+            )
+            .execute(conn)?;
         diesel::delete(current_queue::table)
             .filter(current_queue::guild_id.eq(guild_id))
             .execute(conn)
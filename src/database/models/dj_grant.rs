@@ -0,0 +1,89 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::dj_grants;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = dj_grants)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct DjGrant {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub user_id: String,
+    pub granted_by: String,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = dj_grants)]
+struct NewDjGrant {
+    guild_id: String,
+    user_id: String,
+    granted_by: String,
+    expires_at: NaiveDateTime,
+}
+
+impl DjGrant {
+    /// Make a member a DJ until `expires_at`, replacing any grant they already hold and clearing
+    /// out grants that have run out
+    pub fn grant(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        user_id: &str,
+        granted_by: &str,
+        expires_at: NaiveDateTime,
+    ) -> QueryResult<usize> {
+        diesel::delete(dj_grants::table)
+            .filter(dj_grants::expires_at.le(Utc::now().naive_utc()))
+            .execute(conn)?;
+
+        let new_grant = NewDjGrant {
+            guild_id: guild_id.to_string(),
+            user_id: user_id.to_string(),
+            granted_by: granted_by.to_string(),
+            expires_at,
+        };
+        diesel::replace_into(dj_grants::table)
+            .values(&new_grant)
+            .execute(conn)
+    }
+
+    /// End a grant early; returns false if the member didn't hold an active one
+    pub fn revoke(conn: &mut SqliteConnection, guild_id: &str, user_id: &str) -> QueryResult<bool> {
+        let deleted = diesel::delete(dj_grants::table)
+            .filter(dj_grants::guild_id.eq(guild_id))
+            .filter(dj_grants::user_id.eq(user_id))
+            .filter(dj_grants::expires_at.gt(Utc::now().naive_utc()))
+            .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// The member's grant, if they hold one that hasn't expired
+    pub fn find_active(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        user_id: &str,
+    ) -> QueryResult<Option<DjGrant>> {
+        dj_grants::table
+            .filter(dj_grants::guild_id.eq(guild_id))
+            .filter(dj_grants::user_id.eq(user_id))
+            .filter(dj_grants::expires_at.gt(Utc::now().naive_utc()))
+            .select(DjGrant::as_select())
+            .first::<DjGrant>(conn)
+            .optional()
+    }
+
+    pub fn list_active_for_guild(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+    ) -> QueryResult<Vec<DjGrant>> {
+        dj_grants::table
+            .filter(dj_grants::guild_id.eq(guild_id))
+            .filter(dj_grants::expires_at.gt(Utc::now().naive_utc()))
+            .order(dj_grants::expires_at.asc())
+            .select(DjGrant::as_select())
+            .load::<DjGrant>(conn)
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::download_jobs;
+
+/// A `/play` download in progress, kept until the track is queued or the attempt fails
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = download_jobs)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct DownloadJob {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub channel_id: String,
+    pub user_id: String,
+    pub url: String,
+    pub priority: i32,
+    pub message_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = download_jobs)]
+struct NewDownloadJob {
+    guild_id: String,
+    channel_id: String,
+    user_id: String,
+    url: String,
+    priority: i32,
+    message_id: Option<String>,
+}
+
+impl DownloadJob {
+    pub fn create(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        channel_id: &str,
+        user_id: &str,
+        url: &str,
+        priority: i32,
+        message_id: Option<&str>,
+    ) -> QueryResult<DownloadJob> {
+        diesel::insert_into(download_jobs::table)
+            .values(&NewDownloadJob {
+                guild_id: guild_id.to_string(),
+                channel_id: channel_id.to_string(),
+                user_id: user_id.to_string(),
+                url: url.to_string(),
+                priority,
+                message_id: message_id.map(str::to_string),
+            })
+            .returning(DownloadJob::as_returning())
+            .get_result(conn)
+    }
+
+    pub fn delete(conn: &mut SqliteConnection, id: i32) -> QueryResult<usize> {
+        diesel::delete(download_jobs::table)
+            .filter(download_jobs::id.eq(id))
+            .execute(conn)
+    }
+
+    /// Remove every recorded job and return them, oldest first, so each is picked up once
+    pub fn take_all(conn: &mut SqliteConnection) -> QueryResult<Vec<DownloadJob>> {
+        conn.transaction(|conn| {
+            let jobs = download_jobs::table
+                .order(download_jobs::created_at.asc())
+                .select(DownloadJob::as_select())
+                .load::<DownloadJob>(conn)?;
+            diesel::delete(download_jobs::table).execute(conn)?;
+            Ok(jobs)
+        })
+    }
+}
@@ -0,0 +1,75 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::feature_flags;
+
+/// Scope of flags that apply to every guild without an override of its own
+pub const GLOBAL_SCOPE: &str = "global";
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = feature_flags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FeatureFlag {
+    pub id: Option<i32>,
+    pub flag: String,
+    /// `GLOBAL_SCOPE`, or the guild ID the override applies to
+    pub scope: String,
+    pub enabled: bool,
+    pub updated_by: String,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = feature_flags)]
+struct NewFeatureFlag {
+    flag: String,
+    scope: String,
+    enabled: bool,
+    updated_by: String,
+}
+
+impl FeatureFlag {
+    /// Turn `flag` on or off for `scope`, replacing any earlier setting there
+    pub fn set(
+        conn: &mut SqliteConnection,
+        flag: &str,
+        scope: &str,
+        enabled: bool,
+        updated_by: &str,
+    ) -> QueryResult<usize> {
+        diesel::replace_into(feature_flags::table)
+            .values(&NewFeatureFlag {
+                flag: flag.to_string(),
+                scope: scope.to_string(),
+                enabled,
+                updated_by: updated_by.to_string(),
+            })
+            .execute(conn)
+    }
+
+    /// Drop the setting for `flag` in `scope` so it falls back to the next level; returns
+    /// false if there wasn't one
+    pub fn clear(conn: &mut SqliteConnection, flag: &str, scope: &str) -> QueryResult<bool> {
+        let deleted = diesel::delete(feature_flags::table)
+            .filter(feature_flags::flag.eq(flag))
+            .filter(feature_flags::scope.eq(scope))
+            .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// Every flag set globally or for `guild_id`
+    pub fn for_guild(conn: &mut SqliteConnection, guild_id: &str) -> QueryResult<Vec<FeatureFlag>> {
+        feature_flags::table
+            .filter(feature_flags::scope.eq_any([GLOBAL_SCOPE, guild_id]))
+            .select(FeatureFlag::as_select())
+            .load(conn)
+    }
+
+    pub fn global(conn: &mut SqliteConnection) -> QueryResult<Vec<FeatureFlag>> {
+        feature_flags::table
+            .filter(feature_flags::scope.eq(GLOBAL_SCOPE))
+            .select(FeatureFlag::as_select())
+            .load(conn)
+    }
+}
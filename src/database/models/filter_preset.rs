@@ -0,0 +1,90 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::filter_presets;
+
+/// A named set of audio filters a guild saved to switch to in one go
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = filter_presets)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FilterPreset {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub name: String,
+    /// JSON array of filter names
+    pub filters: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = filter_presets)]
+struct NewFilterPreset<'a> {
+    guild_id: &'a str,
+    name: &'a str,
+    filters: String,
+    created_by: &'a str,
+}
+
+impl FilterPreset {
+    /// Save a preset, replacing any existing preset of the same name
+    pub fn save(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        name: &str,
+        filters: &[String],
+        created_by: &str,
+    ) -> QueryResult<FilterPreset> {
+        let filters = serde_json::to_string(filters).unwrap_or_else(|_| "[]".to_string());
+        conn.transaction(|conn| {
+            Self::delete(conn, guild_id, name)?;
+            diesel::insert_into(filter_presets::table)
+                .values(&NewFilterPreset {
+                    guild_id,
+                    name,
+                    filters,
+                    created_by,
+                })
+                .returning(FilterPreset::as_returning())
+                .get_result(conn)
+        })
+    }
+
+    pub fn find_by_name(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        name: &str,
+    ) -> QueryResult<Option<FilterPreset>> {
+        filter_presets::table
+            .filter(filter_presets::guild_id.eq(guild_id))
+            .filter(filter_presets::name.eq(name))
+            .select(FilterPreset::as_select())
+            .first::<FilterPreset>(conn)
+            .optional()
+    }
+
+    pub fn list_for_guild(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+    ) -> QueryResult<Vec<FilterPreset>> {
+        filter_presets::table
+            .filter(filter_presets::guild_id.eq(guild_id))
+            .order(filter_presets::name.asc())
+            .select(FilterPreset::as_select())
+            .load::<FilterPreset>(conn)
+    }
+
+    /// Returns false if no such preset exists
+    pub fn delete(conn: &mut SqliteConnection, guild_id: &str, name: &str) -> QueryResult<bool> {
+        diesel::delete(filter_presets::table)
+            .filter(filter_presets::guild_id.eq(guild_id))
+            .filter(filter_presets::name.eq(name))
+            .execute(conn)
+            .map(|deleted| deleted > 0)
+    }
+
+    pub fn filters_list(&self) -> Vec<String> {
+        serde_json::from_str(&self.filters).unwrap_or_default()
+    }
+}
@@ -1,9 +1,13 @@
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 use crate::database::schema::guild_settings;
 
+/// Volume tracks start at in guilds that haven't picked one (the column's default)
+pub const DEFAULT_VOLUME: f32 = 0.5;
+
 #[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
 #[diesel(table_name = guild_settings)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
@@ -16,6 +20,27 @@ pub struct GuildSettings {
     pub blocked_domains: Option<String>, // JSON array
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub allowed_domains: Option<String>, // JSON array
+    pub explicit_filter: bool,
+    pub blocked_keywords: Option<String>, // JSON array
+    pub priority_roles: Option<String>,   // JSON object of role ID -> queue priority
+    pub require_approval: bool,
+    pub approval_channel_id: Option<String>,
+    pub quiet_hours_start: Option<String>, // HH:MM, local to quiet_hours_timezone
+    pub quiet_hours_end: Option<String>,
+    pub quiet_hours_volume: Option<f32>, // cap during quiet hours; None refuses playback
+    pub max_volume: f32,
+    pub embed_color: Option<i32>, // 0xRRGGBB accent for playback embeds
+    pub emoji_set: String,
+    pub embed_footer: Option<String>,
+    pub audio_filters: Option<String>, // JSON array of filter names
+    pub announcement_channel_id: Option<String>,
+    pub self_deafen: Option<bool>, // None follows LYRE_SELF_DEAFEN
+    pub stage_topic: bool,
+    pub listening_party_event: bool,
+    pub command_roles: Option<String>, // JSON object of command name -> role IDs
+    pub log_channel_id: Option<String>,
+    pub quiet_hours_timezone: String, // IANA name, e.g. Europe/Berlin
 }
 
 #[derive(Insertable)]
@@ -27,6 +52,17 @@ pub struct NewGuildSettings {
     pub max_queue_size: Option<i32>,
     pub allowed_roles: Option<String>,
     pub blocked_domains: Option<String>,
+    pub allowed_domains: Option<String>,
+}
+
+/// The settings the `/setup` wizard walks admins through
+#[derive(Debug, Clone)]
+pub struct GuildSetup {
+    pub announcement_channel_id: Option<String>,
+    pub dj_roles: Vec<String>,
+    pub default_volume: f32,
+    pub auto_disconnect_minutes: i32,
+    pub max_queue_size: i32,
 }
 
 impl GuildSettings {
@@ -41,6 +77,7 @@ impl GuildSettings {
             max_queue_size: None,
             allowed_roles: None,
             blocked_domains: None,
+            allowed_domains: None,
         };
 
         diesel::insert_into(guild_settings::table)
@@ -59,6 +96,7 @@ impl GuildSettings {
     ) -> QueryResult<Option<GuildSettings>> {
         guild_settings::table
             .filter(guild_settings::guild_id.eq(guild_id))
+            .select(GuildSettings::as_select())
             .first::<GuildSettings>(conn)
             .optional()
     }
@@ -77,6 +115,28 @@ impl GuildSettings {
             .execute(conn)
     }
 
+    /// Set the guild's volume ceiling, lowering the default volume to match if it was above it
+    pub fn update_max_volume(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        max_volume: f32,
+    ) -> QueryResult<usize> {
+        conn.transaction(|conn| {
+            diesel::update(guild_settings::table)
+                .filter(guild_settings::guild_id.eq(guild_id))
+                .filter(guild_settings::default_volume.gt(max_volume))
+                .set(guild_settings::default_volume.eq(max_volume))
+                .execute(conn)?;
+            diesel::update(guild_settings::table)
+                .filter(guild_settings::guild_id.eq(guild_id))
+                .set((
+                    guild_settings::max_volume.eq(max_volume),
+                    guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+                ))
+                .execute(conn)
+        })
+    }
+
     pub fn update_auto_disconnect(
         conn: &mut SqliteConnection,
         guild_id: &str,
@@ -91,6 +151,173 @@ impl GuildSettings {
             .execute(conn)
     }
 
+    pub fn update_allowed_domains(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        domains: &[String],
+    ) -> QueryResult<usize> {
+        // An empty list clears the per-guild allowlist
+        let json = if domains.is_empty() {
+            None
+        } else {
+            serde_json::to_string(domains).ok()
+        };
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::allowed_domains.eq(json),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn update_blocked_keywords(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        keywords: &[String],
+    ) -> QueryResult<usize> {
+        let json = if keywords.is_empty() {
+            None
+        } else {
+            serde_json::to_string(keywords).ok()
+        };
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::blocked_keywords.eq(json),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn update_priority_roles(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        roles: &BTreeMap<String, i32>,
+    ) -> QueryResult<usize> {
+        let json = if roles.is_empty() {
+            None
+        } else {
+            serde_json::to_string(roles).ok()
+        };
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::priority_roles.eq(json),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn update_explicit_filter(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        enabled: bool,
+    ) -> QueryResult<usize> {
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::explicit_filter.eq(enabled),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn update_allowed_roles(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        roles: &[String],
+    ) -> QueryResult<usize> {
+        let json = if roles.is_empty() {
+            None
+        } else {
+            serde_json::to_string(roles).ok()
+        };
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::allowed_roles.eq(json),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    /// Turn moderation mode on or off; `channel_id` is where review messages are posted
+    pub fn update_approval(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        enabled: bool,
+        channel_id: Option<&str>,
+    ) -> QueryResult<usize> {
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::require_approval.eq(enabled),
+                guild_settings::approval_channel_id.eq(channel_id),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    /// Set the quiet-hours window, or clear it with `window: None`
+    pub fn update_quiet_hours(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        window: Option<(&str, &str)>,
+        timezone: &str,
+        volume: Option<f32>,
+    ) -> QueryResult<usize> {
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::quiet_hours_start.eq(window.map(|(start, _)| start)),
+                guild_settings::quiet_hours_end.eq(window.map(|(_, end)| end)),
+                guild_settings::quiet_hours_timezone.eq(timezone),
+                guild_settings::quiet_hours_volume.eq(volume),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    /// Replace the filters applied to the guild's newly queued tracks; see `crate::filters`
+    pub fn update_audio_filters(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        filters: &[String],
+    ) -> QueryResult<usize> {
+        let json = if filters.is_empty() {
+            None
+        } else {
+            serde_json::to_string(filters).ok()
+        };
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::audio_filters.eq(json),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    /// Replace the guild's embed theme; see `crate::theme`
+    pub fn update_theme(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        color: Option<i32>,
+        emoji_set: &str,
+        footer: Option<&str>,
+    ) -> QueryResult<usize> {
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::embed_color.eq(color),
+                guild_settings::emoji_set.eq(emoji_set),
+                guild_settings::embed_footer.eq(footer),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
     pub fn update_max_queue_size(
         conn: &mut SqliteConnection,
         guild_id: &str,
@@ -104,4 +331,160 @@ impl GuildSettings {
             ))
             .execute(conn)
     }
+
+    /// Where the bot posts notices for the guild; `None` posts them where the session was started
+    pub fn update_announcement_channel(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        channel_id: Option<&str>,
+    ) -> QueryResult<usize> {
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::announcement_channel_id.eq(channel_id),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    /// Where the bot posts operational events for the guild's admins; `None` posts none
+    pub fn update_log_channel(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        channel_id: Option<&str>,
+    ) -> QueryResult<usize> {
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::log_channel_id.eq(channel_id),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn update_self_deafen(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        enabled: bool,
+    ) -> QueryResult<usize> {
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::self_deafen.eq(enabled),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn update_stage_announcements(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        stage_topic: bool,
+        listening_party_event: bool,
+    ) -> QueryResult<usize> {
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::stage_topic.eq(stage_topic),
+                guild_settings::listening_party_event.eq(listening_party_event),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn update_command_roles(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        roles: &BTreeMap<String, Vec<String>>,
+    ) -> QueryResult<usize> {
+        let json = if roles.is_empty() {
+            None
+        } else {
+            serde_json::to_string(roles).ok()
+        };
+        diesel::update(guild_settings::table)
+            .filter(guild_settings::guild_id.eq(guild_id))
+            .set((
+                guild_settings::command_roles.eq(json),
+                guild_settings::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    /// Write everything chosen in the `/setup` wizard at once, so a failure leaves the guild's
+    /// settings as they were
+    pub fn apply_setup(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        setup: &GuildSetup,
+    ) -> QueryResult<()> {
+        conn.transaction(|conn| {
+            Self::create_or_update(conn, guild_id)?;
+            Self::update_announcement_channel(
+                conn,
+                guild_id,
+                setup.announcement_channel_id.as_deref(),
+            )?;
+            Self::update_allowed_roles(conn, guild_id, &setup.dj_roles)?;
+            Self::update_volume(conn, guild_id, setup.default_volume)?;
+            Self::update_auto_disconnect(conn, guild_id, setup.auto_disconnect_minutes)?;
+            Self::update_max_queue_size(conn, guild_id, setup.max_queue_size)?;
+            Ok(())
+        })
+    }
+
+    /// The volume new tracks start at: the guild's default, kept under its cap
+    pub fn start_volume(&self) -> f32 {
+        self.default_volume.min(self.max_volume)
+    }
+
+    pub fn allowed_roles_list(&self) -> Vec<String> {
+        parse_json_list(self.allowed_roles.as_deref())
+    }
+
+    pub fn blocked_domains_list(&self) -> Vec<String> {
+        parse_json_list(self.blocked_domains.as_deref())
+    }
+
+    pub fn allowed_domains_list(&self) -> Vec<String> {
+        parse_json_list(self.allowed_domains.as_deref())
+    }
+
+    pub fn audio_filters_list(&self) -> Vec<String> {
+        parse_json_list(self.audio_filters.as_deref())
+    }
+
+    pub fn blocked_keywords_list(&self) -> Vec<String> {
+        parse_json_list(self.blocked_keywords.as_deref())
+    }
+
+    pub fn priority_roles_map(&self) -> BTreeMap<String, i32> {
+        self.priority_roles
+            .as_deref()
+            .and_then(|s| serde_json::from_str(s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn command_roles_map(&self) -> BTreeMap<String, Vec<String>> {
+        self.command_roles
+            .as_deref()
+            .and_then(|s| serde_json::from_str(s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Queue priority for a member with `role_ids`: the highest of their roles' priorities, or 0
+    pub fn queue_priority_for(&self, role_ids: &[String]) -> i32 {
+        let roles = self.priority_roles_map();
+        role_ids
+            .iter()
+            .filter_map(|id| roles.get(id))
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+fn parse_json_list(raw: Option<&str>) -> Vec<String> {
+    raw.and_then(|s| serde_json::from_str::<Vec<String>>(s).ok())
+        .unwrap_or_default()
 }
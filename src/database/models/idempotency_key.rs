@@ -0,0 +1,56 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::idempotency_keys;
+
+/// How long a key's outcome is kept for replays
+pub const IDEMPOTENCY_TTL_HOURS: i64 = 24;
+
+/// The response an API request sent with an `Idempotency-Key` got, kept so a retry with the
+/// same key gets it again instead of repeating the request
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = idempotency_keys)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct IdempotencyKey {
+    pub guild_id: String,
+    pub user_id: String,
+    pub idempotency_key: String,
+    pub request: String,
+    pub status: i32,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+}
+
+fn cutoff() -> NaiveDateTime {
+    Utc::now().naive_utc() - Duration::hours(IDEMPOTENCY_TTL_HOURS)
+}
+
+impl IdempotencyKey {
+    /// The stored outcome for `key`, unless it has expired
+    pub fn find(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        user_id: &str,
+        key: &str,
+    ) -> QueryResult<Option<IdempotencyKey>> {
+        idempotency_keys::table
+            .filter(idempotency_keys::guild_id.eq(guild_id))
+            .filter(idempotency_keys::user_id.eq(user_id))
+            .filter(idempotency_keys::idempotency_key.eq(key))
+            .filter(idempotency_keys::created_at.gt(cutoff()))
+            .select(IdempotencyKey::as_select())
+            .first::<IdempotencyKey>(conn)
+            .optional()
+    }
+
+    /// Store an outcome, clearing out expired ones (an expired key may be used again)
+    pub fn record(conn: &mut SqliteConnection, record: &IdempotencyKey) -> QueryResult<usize> {
+        diesel::delete(idempotency_keys::table)
+            .filter(idempotency_keys::created_at.le(cutoff()))
+            .execute(conn)?;
+        diesel::replace_into(idempotency_keys::table)
+            .values(record)
+            .execute(conn)
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::library_tracks;
+
+/// An audio file in the local library, as last indexed
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = library_tracks)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LibraryTrack {
+    pub id: Option<i32>,
+    /// Relative to the library folder
+    pub path: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_secs: Option<i32>,
+    pub modified_at: NaiveDateTime,
+    pub indexed_at: NaiveDateTime,
+}
+
+/// What the indexer read from one file
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = library_tracks)]
+pub struct NewLibraryTrack {
+    pub path: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_secs: Option<i32>,
+    pub modified_at: NaiveDateTime,
+}
+
+impl LibraryTrack {
+    /// Add a file, or refresh its tags if it's already indexed; its ID stays the same either way
+    pub fn upsert(conn: &mut SqliteConnection, track: &NewLibraryTrack) -> QueryResult<usize> {
+        diesel::insert_into(library_tracks::table)
+            .values(track)
+            .on_conflict(library_tracks::path)
+            .do_update()
+            .set((
+                library_tracks::title.eq(&track.title),
+                library_tracks::artist.eq(&track.artist),
+                library_tracks::album.eq(&track.album),
+                library_tracks::duration_secs.eq(track.duration_secs),
+                library_tracks::modified_at.eq(track.modified_at),
+                library_tracks::indexed_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn find(conn: &mut SqliteConnection, id: i32) -> QueryResult<Option<LibraryTrack>> {
+        library_tracks::table
+            .filter(library_tracks::id.eq(id))
+            .select(LibraryTrack::as_select())
+            .first::<LibraryTrack>(conn)
+            .optional()
+    }
+
+    /// Every indexed path with the modification time it was read at
+    pub fn modified_times(
+        conn: &mut SqliteConnection,
+    ) -> QueryResult<Vec<(String, NaiveDateTime)>> {
+        library_tracks::table
+            .select((library_tracks::path, library_tracks::modified_at))
+            .load(conn)
+    }
+
+    /// Forget files that are no longer in the library
+    pub fn remove_paths(conn: &mut SqliteConnection, paths: &[String]) -> QueryResult<usize> {
+        diesel::delete(library_tracks::table)
+            .filter(library_tracks::path.eq_any(paths))
+            .execute(conn)
+    }
+
+    /// Tracks whose title, artist or album contains every one of `words` (case-insensitively),
+    /// by artist, album and title
+    pub fn search(
+        conn: &mut SqliteConnection,
+        words: &[String],
+        limit: i64,
+    ) -> QueryResult<Vec<LibraryTrack>> {
+        let mut query = library_tracks::table.into_boxed();
+        for word in words {
+            let pattern = format!(
+                "%{}%",
+                word.replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            );
+            query = query.filter(
+                library_tracks::title
+                    .like(pattern.clone())
+                    .escape('\\')
+                    .or(library_tracks::artist.like(pattern.clone()).escape('\\'))
+                    .or(library_tracks::album.like(pattern).escape('\\')),
+            );
+        }
+        query
+            .order((
+                library_tracks::artist.asc(),
+                library_tracks::album.asc(),
+                library_tracks::title.asc(),
+            ))
+            .limit(limit)
+            .select(LibraryTrack::as_select())
+            .load::<LibraryTrack>(conn)
+    }
+}
@@ -0,0 +1,76 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::lyrics;
+
+/// Hours before a song the provider had no lyrics for is asked about again
+pub const MISS_RETRY_HOURS: i64 = 24;
+
+/// A song's lyrics as the provider returned them, or the record that it had none
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = lyrics)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Lyrics {
+    pub id: Option<i32>,
+    pub lookup_key: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub plain_lyrics: Option<String>,
+    /// LRC, one `[mm:ss.xx] line` per line
+    pub synced_lyrics: Option<String>,
+    pub instrumental: bool,
+    pub source: String,
+    pub fetched_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = lyrics)]
+pub struct NewLyrics {
+    pub lookup_key: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub plain_lyrics: Option<String>,
+    pub synced_lyrics: Option<String>,
+    pub instrumental: bool,
+    pub source: String,
+}
+
+impl Lyrics {
+    /// The cached answer for `lookup_key`, unless it's a miss old enough to ask again
+    pub fn find_fresh(
+        conn: &mut SqliteConnection,
+        lookup_key: &str,
+    ) -> QueryResult<Option<Lyrics>> {
+        let found = lyrics::table
+            .filter(lyrics::lookup_key.eq(lookup_key))
+            .select(Lyrics::as_select())
+            .first::<Lyrics>(conn)
+            .optional()?;
+        let retry_before = Utc::now().naive_utc() - Duration::hours(MISS_RETRY_HOURS);
+        Ok(found.filter(|l| l.is_found() || l.fetched_at > retry_before))
+    }
+
+    pub fn save(conn: &mut SqliteConnection, new: &NewLyrics) -> QueryResult<Lyrics> {
+        diesel::insert_into(lyrics::table)
+            .values(new)
+            .on_conflict(lyrics::lookup_key)
+            .do_update()
+            .set((
+                lyrics::title.eq(&new.title),
+                lyrics::artist.eq(&new.artist),
+                lyrics::plain_lyrics.eq(&new.plain_lyrics),
+                lyrics::synced_lyrics.eq(&new.synced_lyrics),
+                lyrics::instrumental.eq(new.instrumental),
+                lyrics::source.eq(&new.source),
+                lyrics::fetched_at.eq(Utc::now().naive_utc()),
+            ))
+            .returning(Lyrics::as_returning())
+            .get_result(conn)
+    }
+
+    /// Whether the provider knew the song, with words or as an instrumental
+    pub fn is_found(&self) -> bool {
+        self.instrumental || self.plain_lyrics.is_some() || self.synced_lyrics.is_some()
+    }
+}
@@ -1,12 +1,48 @@
+pub mod blocked_track;
+pub mod control_hook;
 pub mod current_queue;
+pub mod dj_grant;
+pub mod download_job;
+pub mod feature_flag;
+pub mod filter_preset;
 pub mod guild_settings;
+pub mod idempotency_key;
+pub mod library_track;
+pub mod lyrics;
+pub mod music_ban;
+pub mod pending_request;
+pub mod playback_bookmark;
+pub mod playback_state;
+pub mod podcast;
 pub mod queue_history;
+pub mod queue_share;
+pub mod saved_playlist;
+pub mod scrobble;
 pub mod song_cache;
+pub mod upload;
 pub mod voice_connections;
 
 // Re-export all models for convenience
-pub use current_queue::CurrentQueue;
-pub use guild_settings::GuildSettings;
-pub use queue_history::QueueHistory;
+pub use blocked_track::BlockedTrack;
+pub use control_hook::{ControlHook, ControlHookCall, HookCallOutcome, NewControlHookInput};
+pub use current_queue::{CurrentQueue, QueueStatus};
+pub use dj_grant::DjGrant;
+pub use download_job::DownloadJob;
+pub use feature_flag::FeatureFlag;
+pub use filter_preset::FilterPreset;
+pub use guild_settings::{DEFAULT_VOLUME, GuildSettings, GuildSetup};
+pub use idempotency_key::IdempotencyKey;
+pub use library_track::LibraryTrack;
+pub use lyrics::Lyrics;
+pub use music_ban::MusicBan;
+pub use pending_request::PendingRequest;
+pub use playback_bookmark::PlaybackBookmark;
+pub use playback_state::PlaybackStateRecord;
+pub use podcast::{PodcastEpisode, PodcastSubscription};
+pub use queue_history::{HistoryStatus, QueueHistory};
+pub use queue_share::QueueShare;
+pub use saved_playlist::SavedPlaylist;
+pub use scrobble::{ScrobbleAccount, ScrobbleQueueEntry};
 pub use song_cache::SongCache;
+pub use upload::Upload;
 pub use voice_connections::VoiceConnection;
@@ -0,0 +1,98 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::music_bans;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = music_bans)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MusicBan {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub user_id: String,
+    pub reason: Option<String>,
+    pub banned_by: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = music_bans)]
+pub struct NewMusicBan {
+    pub guild_id: String,
+    pub user_id: String,
+    pub reason: Option<String>,
+    pub banned_by: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl MusicBan {
+    /// Ban a member from playback commands, replacing any existing ban for them
+    pub fn ban(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        user_id: &str,
+        reason: Option<&str>,
+        banned_by: &str,
+        expires_at: Option<NaiveDateTime>,
+    ) -> QueryResult<usize> {
+        let new_ban = NewMusicBan {
+            guild_id: guild_id.to_string(),
+            user_id: user_id.to_string(),
+            reason: reason.map(|s| s.to_string()),
+            banned_by: banned_by.to_string(),
+            expires_at,
+        };
+
+        diesel::replace_into(music_bans::table)
+            .values(&new_ban)
+            .execute(conn)
+    }
+
+    /// Lift a ban; returns false if the member wasn't banned
+    pub fn unban(conn: &mut SqliteConnection, guild_id: &str, user_id: &str) -> QueryResult<bool> {
+        let deleted = diesel::delete(music_bans::table)
+            .filter(music_bans::guild_id.eq(guild_id))
+            .filter(music_bans::user_id.eq(user_id))
+            .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// The member's ban, if one exists and hasn't expired
+    pub fn find_active(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        user_id: &str,
+    ) -> QueryResult<Option<MusicBan>> {
+        let now = Utc::now().naive_utc();
+        music_bans::table
+            .filter(music_bans::guild_id.eq(guild_id))
+            .filter(music_bans::user_id.eq(user_id))
+            .filter(
+                music_bans::expires_at
+                    .is_null()
+                    .or(music_bans::expires_at.gt(now)),
+            )
+            .select(MusicBan::as_select())
+            .first::<MusicBan>(conn)
+            .optional()
+    }
+
+    pub fn list_active_for_guild(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+    ) -> QueryResult<Vec<MusicBan>> {
+        let now = Utc::now().naive_utc();
+        music_bans::table
+            .filter(music_bans::guild_id.eq(guild_id))
+            .filter(
+                music_bans::expires_at
+                    .is_null()
+                    .or(music_bans::expires_at.gt(now)),
+            )
+            .order(music_bans::created_at.desc())
+            .select(MusicBan::as_select())
+            .load::<MusicBan>(conn)
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::pending_requests;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = pending_requests)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PendingRequest {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub channel_id: String,
+    pub user_id: String,
+    pub url: String,
+    pub priority: i32,
+    pub review_channel_id: Option<String>,
+    pub review_message_id: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = pending_requests)]
+struct NewPendingRequest {
+    guild_id: String,
+    channel_id: String,
+    user_id: String,
+    url: String,
+    priority: i32,
+    expires_at: NaiveDateTime,
+}
+
+impl PendingRequest {
+    /// Record a request awaiting approval, clearing out ones that expired while the bot was down
+    pub fn create(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        channel_id: &str,
+        user_id: &str,
+        url: &str,
+        priority: i32,
+        expires_at: NaiveDateTime,
+    ) -> QueryResult<PendingRequest> {
+        diesel::delete(pending_requests::table)
+            .filter(pending_requests::expires_at.le(chrono::Utc::now().naive_utc()))
+            .execute(conn)?;
+
+        diesel::insert_into(pending_requests::table)
+            .values(&NewPendingRequest {
+                guild_id: guild_id.to_string(),
+                channel_id: channel_id.to_string(),
+                user_id: user_id.to_string(),
+                url: url.to_string(),
+                priority,
+                expires_at,
+            })
+            .returning(PendingRequest::as_returning())
+            .get_result(conn)
+    }
+
+    pub fn set_review_message(
+        conn: &mut SqliteConnection,
+        id: i32,
+        channel_id: &str,
+        message_id: &str,
+    ) -> QueryResult<usize> {
+        diesel::update(pending_requests::table)
+            .filter(pending_requests::id.eq(id))
+            .set((
+                pending_requests::review_channel_id.eq(channel_id),
+                pending_requests::review_message_id.eq(message_id),
+            ))
+            .execute(conn)
+    }
+
+    /// Remove a request from the pending set and return it, so only one moderator (or the
+    /// expiry timer) gets to decide it. Expired requests are removed but not returned.
+    pub fn take(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        id: i32,
+    ) -> QueryResult<Option<PendingRequest>> {
+        let request = diesel::delete(pending_requests::table)
+            .filter(pending_requests::id.eq(id))
+            .filter(pending_requests::guild_id.eq(guild_id))
+            .returning(PendingRequest::as_returning())
+            .get_result::<PendingRequest>(conn)
+            .optional()?;
+        Ok(request.filter(|r| r.expires_at > chrono::Utc::now().naive_utc()))
+    }
+
+    /// Remove a request the expiry timer is due to reject, whether or not its deadline has
+    /// technically passed yet
+    pub fn take_expired(
+        conn: &mut SqliteConnection,
+        id: i32,
+    ) -> QueryResult<Option<PendingRequest>> {
+        diesel::delete(pending_requests::table)
+            .filter(pending_requests::id.eq(id))
+            .returning(PendingRequest::as_returning())
+            .get_result::<PendingRequest>(conn)
+            .optional()
+    }
+
+    pub fn count_for_guild(conn: &mut SqliteConnection, guild_id: &str) -> QueryResult<i64> {
+        pending_requests::table
+            .filter(pending_requests::guild_id.eq(guild_id))
+            .filter(pending_requests::expires_at.gt(chrono::Utc::now().naive_utc()))
+            .count()
+            .get_result(conn)
+    }
+}
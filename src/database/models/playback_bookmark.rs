@@ -0,0 +1,76 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::playback_bookmarks;
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = playback_bookmarks)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PlaybackBookmark {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub url: String,
+    pub position: i32,
+    pub duration: Option<i32>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = playback_bookmarks)]
+pub struct NewPlaybackBookmark {
+    pub guild_id: String,
+    pub url: String,
+    pub position: i32,
+    pub duration: Option<i32>,
+}
+
+impl PlaybackBookmark {
+    /// Remember where playback of `url` stopped, replacing any earlier bookmark
+    pub fn save(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        url: &str,
+        position: i32,
+        duration: Option<i32>,
+    ) -> QueryResult<usize> {
+        let new_bookmark = NewPlaybackBookmark {
+            guild_id: guild_id.to_string(),
+            url: url.to_string(),
+            position,
+            duration,
+        };
+
+        diesel::insert_into(playback_bookmarks::table)
+            .values(&new_bookmark)
+            .on_conflict((playback_bookmarks::guild_id, playback_bookmarks::url))
+            .do_update()
+            .set((
+                playback_bookmarks::position.eq(position),
+                playback_bookmarks::duration.eq(duration),
+                playback_bookmarks::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn find(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        url: &str,
+    ) -> QueryResult<Option<PlaybackBookmark>> {
+        playback_bookmarks::table
+            .filter(playback_bookmarks::guild_id.eq(guild_id))
+            .filter(playback_bookmarks::url.eq(url))
+            .select(PlaybackBookmark::as_select())
+            .first::<PlaybackBookmark>(conn)
+            .optional()
+    }
+
+    /// Forget the bookmark once the track has been played to the end
+    pub fn remove(conn: &mut SqliteConnection, guild_id: &str, url: &str) -> QueryResult<usize> {
+        diesel::delete(playback_bookmarks::table)
+            .filter(playback_bookmarks::guild_id.eq(guild_id))
+            .filter(playback_bookmarks::url.eq(url))
+            .execute(conn)
+    }
+}
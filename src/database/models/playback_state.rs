@@ -0,0 +1,44 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::playback_state;
+
+/// A guild's playback state as last synced from memory; the live copy is in
+/// [`crate::playback_state`]
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = playback_state)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PlaybackStateRecord {
+    pub guild_id: String,
+    pub track_uuid: Option<String>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub duration: Option<i32>,
+    pub started_at: Option<NaiveDateTime>,
+    pub paused_at: Option<NaiveDateTime>,
+    pub paused_secs: i32,
+    pub seek_offset_secs: i32,
+    pub loop_mode: String,
+    pub updated_at: NaiveDateTime,
+}
+
+impl PlaybackStateRecord {
+    pub fn save(conn: &mut SqliteConnection, record: &PlaybackStateRecord) -> QueryResult<usize> {
+        diesel::replace_into(playback_state::table)
+            .values(record)
+            .execute(conn)
+    }
+
+    pub fn delete(conn: &mut SqliteConnection, guild_id: &str) -> QueryResult<usize> {
+        diesel::delete(playback_state::table)
+            .filter(playback_state::guild_id.eq(guild_id))
+            .execute(conn)
+    }
+
+    pub fn all(conn: &mut SqliteConnection) -> QueryResult<Vec<PlaybackStateRecord>> {
+        playback_state::table
+            .select(PlaybackStateRecord::as_select())
+            .load::<PlaybackStateRecord>(conn)
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::{podcast_episodes, podcast_subscriptions};
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = podcast_subscriptions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PodcastSubscription {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub feed_url: String,
+    pub title: String,
+    pub subscribed_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = podcast_subscriptions)]
+pub struct NewPodcastSubscription {
+    pub guild_id: String,
+    pub feed_url: String,
+    pub title: String,
+    pub subscribed_by: String,
+}
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = podcast_episodes)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PodcastEpisode {
+    pub id: Option<i32>,
+    pub feed_url: String,
+    pub url: String,
+    pub title: String,
+    pub duration: Option<i32>,
+    pub published_at: Option<NaiveDateTime>,
+    pub fetched_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = podcast_episodes)]
+pub struct NewPodcastEpisode {
+    pub feed_url: String,
+    pub url: String,
+    pub title: String,
+    pub duration: Option<i32>,
+    pub published_at: Option<NaiveDateTime>,
+}
+
+impl PodcastSubscription {
+    /// Subscribe a guild to a feed, refreshing the stored title if it already was
+    pub fn subscribe(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        feed_url: &str,
+        title: &str,
+        subscribed_by: &str,
+    ) -> QueryResult<usize> {
+        let new_subscription = NewPodcastSubscription {
+            guild_id: guild_id.to_string(),
+            feed_url: feed_url.to_string(),
+            title: title.to_string(),
+            subscribed_by: subscribed_by.to_string(),
+        };
+
+        diesel::insert_into(podcast_subscriptions::table)
+            .values(&new_subscription)
+            .on_conflict((
+                podcast_subscriptions::guild_id,
+                podcast_subscriptions::feed_url,
+            ))
+            .do_update()
+            .set(podcast_subscriptions::title.eq(title))
+            .execute(conn)
+    }
+
+    /// Returns false if the guild wasn't subscribed to the feed
+    pub fn unsubscribe(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        feed_url: &str,
+    ) -> QueryResult<bool> {
+        let deleted = diesel::delete(podcast_subscriptions::table)
+            .filter(podcast_subscriptions::guild_id.eq(guild_id))
+            .filter(podcast_subscriptions::feed_url.eq(feed_url))
+            .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    pub fn list_for_guild(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+    ) -> QueryResult<Vec<PodcastSubscription>> {
+        podcast_subscriptions::table
+            .filter(podcast_subscriptions::guild_id.eq(guild_id))
+            .order(podcast_subscriptions::title.asc())
+            .select(PodcastSubscription::as_select())
+            .load::<PodcastSubscription>(conn)
+    }
+
+    /// Every feed at least one guild is subscribed to
+    pub fn distinct_feeds(conn: &mut SqliteConnection) -> QueryResult<Vec<String>> {
+        podcast_subscriptions::table
+            .select(podcast_subscriptions::feed_url)
+            .distinct()
+            .load::<String>(conn)
+    }
+}
+
+impl PodcastEpisode {
+    /// Store episodes not seen before; returns how many were new
+    pub fn insert_new(
+        conn: &mut SqliteConnection,
+        episodes: &[NewPodcastEpisode],
+    ) -> QueryResult<usize> {
+        let mut inserted = 0;
+        for episode in episodes {
+            inserted += diesel::insert_or_ignore_into(podcast_episodes::table)
+                .values(episode)
+                .execute(conn)?;
+        }
+        Ok(inserted)
+    }
+
+    /// Most recently published episodes first
+    pub fn recent_for_feed(
+        conn: &mut SqliteConnection,
+        feed_url: &str,
+        limit: i64,
+    ) -> QueryResult<Vec<PodcastEpisode>> {
+        podcast_episodes::table
+            .filter(podcast_episodes::feed_url.eq(feed_url))
+            .order((
+                podcast_episodes::published_at.desc(),
+                podcast_episodes::id.desc(),
+            ))
+            .limit(limit)
+            .select(PodcastEpisode::as_select())
+            .load::<PodcastEpisode>(conn)
+    }
+
+    pub fn count_for_feed(conn: &mut SqliteConnection, feed_url: &str) -> QueryResult<i64> {
+        podcast_episodes::table
+            .filter(podcast_episodes::feed_url.eq(feed_url))
+            .count()
+            .get_result(conn)
+    }
+}
@@ -14,7 +14,43 @@ pub struct QueueHistory {
     pub url: String,
     pub title: Option<String>,
     pub duration: Option<i32>,
+    /// When the request was queued; kept alongside `queued_at` for older rows and stats
     pub played_at: NaiveDateTime,
+    /// Songbird track the request was queued as
+    pub track_uuid: Option<String>,
+    pub queued_at: Option<NaiveDateTime>,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    /// A [`HistoryStatus`] key; `None` for rows recorded before statuses were
+    pub status: Option<String>,
+}
+
+/// Where a request is in its lifecycle, or how it ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryStatus {
+    Queued,
+    Playing,
+    /// Played to the end
+    Completed,
+    /// Stopped partway through, by a skip or `/stop`
+    Skipped,
+    /// Taken out of the queue before it started
+    Removed,
+    /// Couldn't be played
+    Failed,
+}
+
+impl HistoryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HistoryStatus::Queued => "queued",
+            HistoryStatus::Playing => "playing",
+            HistoryStatus::Completed => "completed",
+            HistoryStatus::Skipped => "skipped",
+            HistoryStatus::Removed => "removed",
+            HistoryStatus::Failed => "failed",
+        }
+    }
 }
 
 #[derive(Insertable)]
@@ -25,9 +61,14 @@ pub struct NewQueueHistory {
     pub url: String,
     pub title: Option<String>,
     pub duration: Option<i32>,
+    pub track_uuid: Option<String>,
+    pub queued_at: Option<NaiveDateTime>,
+    pub status: Option<String>,
 }
 
 impl QueueHistory {
+    /// Record a request as it's queued; [`Self::mark_started`] and [`Self::mark_finished`]
+    /// fill in the rest of its lifecycle by `track_uuid`
     pub fn create(
         conn: &mut SqliteConnection,
         guild_id: &str,
@@ -35,6 +76,7 @@ impl QueueHistory {
         url: &str,
         title: Option<&str>,
         duration: Option<i32>,
+        track_uuid: Option<&str>,
     ) -> QueryResult<usize> {
         let new_history = NewQueueHistory {
             guild_id: guild_id.to_string(),
@@ -42,6 +84,9 @@ impl QueueHistory {
             url: url.to_string(),
             title: title.map(|s| s.to_string()),
             duration,
+            track_uuid: track_uuid.map(|s| s.to_string()),
+            queued_at: Some(chrono::Utc::now().naive_utc()),
+            status: Some(HistoryStatus::Queued.as_str().to_string()),
         };
 
         diesel::insert_into(queue_history::table)
@@ -49,15 +94,100 @@ impl QueueHistory {
             .execute(conn)
     }
 
+    /// Note that the track started playing; later starts (after a pause) keep the first time
+    pub fn mark_started(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        track_uuid: &str,
+    ) -> QueryResult<usize> {
+        diesel::update(queue_history::table)
+            .filter(queue_history::guild_id.eq(guild_id))
+            .filter(queue_history::track_uuid.eq(track_uuid))
+            .filter(queue_history::started_at.is_null())
+            .set((
+                queue_history::started_at.eq(chrono::Utc::now().naive_utc()),
+                queue_history::status.eq(HistoryStatus::Playing.as_str()),
+            ))
+            .execute(conn)
+    }
+
+    /// Note how the track ended; only the first end is recorded
+    pub fn mark_finished(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        track_uuid: &str,
+        status: HistoryStatus,
+    ) -> QueryResult<usize> {
+        diesel::update(queue_history::table)
+            .filter(queue_history::guild_id.eq(guild_id))
+            .filter(queue_history::track_uuid.eq(track_uuid))
+            .filter(queue_history::finished_at.is_null())
+            .set((
+                queue_history::finished_at.eq(chrono::Utc::now().naive_utc()),
+                queue_history::status.eq(status.as_str()),
+            ))
+            .execute(conn)
+    }
+
+    /// The request queued as `track_uuid`
+    pub fn find_by_track(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        track_uuid: &str,
+    ) -> QueryResult<Option<QueueHistory>> {
+        queue_history::table
+            .filter(queue_history::guild_id.eq(guild_id))
+            .filter(queue_history::track_uuid.eq(track_uuid))
+            .select(QueueHistory::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Seconds the track was listened to: from start to finish, no longer than the track, or its
+    /// whole duration when the row predates lifecycle timestamps
+    pub fn listened_secs(&self) -> i64 {
+        let duration = self.duration.map(|d| d.max(0) as i64);
+        match (self.started_at, self.finished_at) {
+            (Some(started), Some(finished)) => {
+                let played = (finished - started).num_seconds().max(0);
+                duration.map_or(played, |d| played.min(d))
+            }
+            (None, Some(_)) => 0,
+            _ => duration.unwrap_or(0),
+        }
+    }
+
+    /// The guild's history, newest first, narrowed to titles containing every one of
+    /// `title_words` (case-insensitively) and to requests by `user_id`
     pub fn get_recent_for_guild(
         conn: &mut SqliteConnection,
         guild_id: &str,
+        title_words: &[String],
+        user_id: Option<&str>,
         limit: i64,
+        offset: i64,
     ) -> QueryResult<Vec<QueueHistory>> {
-        queue_history::table
+        let mut query = queue_history::table
             .filter(queue_history::guild_id.eq(guild_id))
+            .into_boxed();
+        if let Some(user_id) = user_id {
+            query = query.filter(queue_history::user_id.eq(user_id.to_string()));
+        }
+        for word in title_words {
+            let escaped = word
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            query = query.filter(
+                queue_history::title
+                    .like(format!("%{}%", escaped))
+                    .escape('\\'),
+            );
+        }
+        query
             .order(queue_history::played_at.desc())
             .limit(limit)
+            .offset(offset)
             .load::<QueueHistory>(conn)
     }
 
@@ -65,11 +195,39 @@ impl QueueHistory {
         conn: &mut SqliteConnection,
         user_id: &str,
         limit: i64,
+        offset: i64,
     ) -> QueryResult<Vec<QueueHistory>> {
         queue_history::table
             .filter(queue_history::user_id.eq(user_id))
             .order(queue_history::played_at.desc())
             .limit(limit)
+            .offset(offset)
+            .load::<QueueHistory>(conn)
+    }
+
+    /// All history matching the given guild/user, oldest first, for computing listening stats.
+    /// `period` is a half-open `[start, end)` range on `played_at`.
+    pub fn find_for_stats(
+        conn: &mut SqliteConnection,
+        guild_id: Option<&str>,
+        user_id: Option<&str>,
+        period: Option<(NaiveDateTime, NaiveDateTime)>,
+    ) -> QueryResult<Vec<QueueHistory>> {
+        let mut query = queue_history::table.into_boxed();
+        if let Some((start, end)) = period {
+            query = query
+                .filter(queue_history::played_at.ge(start))
+                .filter(queue_history::played_at.lt(end));
+        }
+        if let Some(guild_id) = guild_id {
+            query = query.filter(queue_history::guild_id.eq(guild_id));
+        }
+        if let Some(user_id) = user_id {
+            query = query.filter(queue_history::user_id.eq(user_id));
+        }
+        query
+            .order(queue_history::played_at.asc())
+            .select(QueueHistory::as_select())
             .load::<QueueHistory>(conn)
     }
 
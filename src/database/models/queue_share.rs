@@ -0,0 +1,132 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::CurrentQueue;
+use crate::database::schema::{queue_share_tracks, queue_shares};
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = queue_shares)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct QueueShare {
+    pub id: Option<i32>,
+    pub token: String,
+    pub guild_id: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = queue_shares)]
+struct NewQueueShare {
+    token: String,
+    guild_id: String,
+    created_by: String,
+    expires_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = queue_share_tracks)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct QueueShareTrack {
+    pub id: Option<i32>,
+    pub share_id: i32,
+    pub position: i32,
+    pub url: String,
+    pub title: Option<String>,
+    pub duration: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = queue_share_tracks)]
+struct NewQueueShareTrack {
+    share_id: i32,
+    position: i32,
+    url: String,
+    title: Option<String>,
+    duration: Option<i32>,
+}
+
+impl QueueShare {
+    /// Snapshot `tracks` (in queue order) under `token`, clearing out expired shares first
+    pub fn create(
+        conn: &mut SqliteConnection,
+        token: &str,
+        guild_id: &str,
+        created_by: &str,
+        expires_at: NaiveDateTime,
+        tracks: &[CurrentQueue],
+    ) -> QueryResult<QueueShare> {
+        conn.transaction(|conn| {
+            Self::delete_expired(conn)?;
+
+            let share = diesel::insert_into(queue_shares::table)
+                .values(&NewQueueShare {
+                    token: token.to_string(),
+                    guild_id: guild_id.to_string(),
+                    created_by: created_by.to_string(),
+                    expires_at,
+                })
+                .returning(QueueShare::as_returning())
+                .get_result(conn)?;
+            let share_id = share.id.unwrap_or_default();
+
+            let rows: Vec<NewQueueShareTrack> = tracks
+                .iter()
+                .enumerate()
+                .map(|(i, t)| NewQueueShareTrack {
+                    share_id,
+                    position: i as i32,
+                    url: t.url.clone(),
+                    title: t.title.clone(),
+                    duration: t.duration,
+                })
+                .collect();
+            diesel::insert_into(queue_share_tracks::table)
+                .values(&rows)
+                .execute(conn)?;
+
+            Ok(share)
+        })
+    }
+
+    /// Look up a share by token, ignoring ones that have expired
+    pub fn find_active(
+        conn: &mut SqliteConnection,
+        token: &str,
+    ) -> QueryResult<Option<QueueShare>> {
+        queue_shares::table
+            .filter(queue_shares::token.eq(token))
+            .filter(queue_shares::expires_at.gt(chrono::Utc::now().naive_utc()))
+            .select(QueueShare::as_select())
+            .first::<QueueShare>(conn)
+            .optional()
+    }
+
+    pub fn tracks(&self, conn: &mut SqliteConnection) -> QueryResult<Vec<QueueShareTrack>> {
+        queue_share_tracks::table
+            .filter(queue_share_tracks::share_id.eq(self.id.unwrap_or_default()))
+            .order(queue_share_tracks::position.asc())
+            .select(QueueShareTrack::as_select())
+            .load::<QueueShareTrack>(conn)
+    }
+
+    fn delete_expired(conn: &mut SqliteConnection) -> QueryResult<usize> {
+        let expired = queue_shares::table
+            .filter(queue_shares::expires_at.le(chrono::Utc::now().naive_utc()))
+            .select(queue_shares::id)
+            .load::<Option<i32>>(conn)?;
+        if expired.is_empty() {
+            return Ok(0);
+        }
+        let ids: Vec<i32> = expired.into_iter().flatten().collect();
+        // SQLite doesn't enforce the cascade unless foreign keys are switched on per connection
+        diesel::delete(queue_share_tracks::table)
+            .filter(queue_share_tracks::share_id.eq_any(&ids))
+            .execute(conn)?;
+        diesel::delete(queue_shares::table)
+            .filter(queue_shares::id.eq_any(ids.into_iter().map(Some)))
+            .execute(conn)
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::{saved_playlist_tracks, saved_playlists};
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = saved_playlists)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SavedPlaylist {
+    pub id: Option<i32>,
+    pub guild_id: String,
+    pub name: String,
+    pub owner_id: String,
+    pub source_url: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = saved_playlists)]
+pub struct NewSavedPlaylist {
+    pub guild_id: String,
+    pub name: String,
+    pub owner_id: String,
+    pub source_url: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = saved_playlist_tracks)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SavedPlaylistTrack {
+    pub id: Option<i32>,
+    pub playlist_id: i32,
+    pub position: i32,
+    pub url: String,
+    pub title: String,
+    pub duration: Option<i32>,
+}
+
+/// A track to store in a playlist, in playlist order
+#[derive(Debug, Clone)]
+pub struct PlaylistTrackInput {
+    pub url: String,
+    pub title: String,
+    pub duration: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = saved_playlist_tracks)]
+struct NewSavedPlaylistTrack {
+    playlist_id: i32,
+    position: i32,
+    url: String,
+    title: String,
+    duration: Option<i32>,
+}
+
+impl SavedPlaylist {
+    /// Save a playlist with its tracks, replacing any existing playlist of the same name
+    pub fn save(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        name: &str,
+        owner_id: &str,
+        source_url: Option<&str>,
+        tracks: &[PlaylistTrackInput],
+    ) -> QueryResult<SavedPlaylist> {
+        conn.transaction(|conn| {
+            Self::delete(conn, guild_id, name)?;
+
+            let playlist = diesel::insert_into(saved_playlists::table)
+                .values(&NewSavedPlaylist {
+                    guild_id: guild_id.to_string(),
+                    name: name.to_string(),
+                    owner_id: owner_id.to_string(),
+                    source_url: source_url.map(|s| s.to_string()),
+                })
+                .returning(SavedPlaylist::as_returning())
+                .get_result(conn)?;
+            let playlist_id = playlist.id.unwrap_or_default();
+
+            let rows: Vec<NewSavedPlaylistTrack> = tracks
+                .iter()
+                .enumerate()
+                .map(|(i, t)| NewSavedPlaylistTrack {
+                    playlist_id,
+                    position: i as i32,
+                    url: t.url.clone(),
+                    title: t.title.clone(),
+                    duration: t.duration,
+                })
+                .collect();
+            diesel::insert_into(saved_playlist_tracks::table)
+                .values(&rows)
+                .execute(conn)?;
+
+            Ok(playlist)
+        })
+    }
+
+    /// Delete a playlist and its tracks; returns false if no such playlist exists
+    pub fn delete(conn: &mut SqliteConnection, guild_id: &str, name: &str) -> QueryResult<bool> {
+        let Some(existing) = Self::find_by_name(conn, guild_id, name)? else {
+            return Ok(false);
+        };
+        // SQLite doesn't enforce the cascade unless foreign keys are switched on per connection
+        diesel::delete(saved_playlist_tracks::table)
+            .filter(saved_playlist_tracks::playlist_id.eq(existing.id.unwrap_or_default()))
+            .execute(conn)?;
+        diesel::delete(saved_playlists::table)
+            .filter(saved_playlists::id.eq(existing.id))
+            .execute(conn)?;
+        Ok(true)
+    }
+
+    pub fn find_by_name(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        name: &str,
+    ) -> QueryResult<Option<SavedPlaylist>> {
+        saved_playlists::table
+            .filter(saved_playlists::guild_id.eq(guild_id))
+            .filter(saved_playlists::name.eq(name))
+            .select(SavedPlaylist::as_select())
+            .first::<SavedPlaylist>(conn)
+            .optional()
+    }
+
+    pub fn list_for_guild(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+    ) -> QueryResult<Vec<SavedPlaylist>> {
+        saved_playlists::table
+            .filter(saved_playlists::guild_id.eq(guild_id))
+            .order(saved_playlists::name.asc())
+            .select(SavedPlaylist::as_select())
+            .load::<SavedPlaylist>(conn)
+    }
+
+    pub fn tracks(&self, conn: &mut SqliteConnection) -> QueryResult<Vec<SavedPlaylistTrack>> {
+        saved_playlist_tracks::table
+            .filter(saved_playlist_tracks::playlist_id.eq(self.id.unwrap_or_default()))
+            .order(saved_playlist_tracks::position.asc())
+            .select(SavedPlaylistTrack::as_select())
+            .load::<SavedPlaylistTrack>(conn)
+    }
+
+    /// Add a track to the end of the playlist
+    pub fn append(
+        &self,
+        conn: &mut SqliteConnection,
+        track: &PlaylistTrackInput,
+    ) -> QueryResult<usize> {
+        let position = self.track_count(conn)? as i32;
+        diesel::insert_into(saved_playlist_tracks::table)
+            .values(&NewSavedPlaylistTrack {
+                playlist_id: self.id.unwrap_or_default(),
+                position,
+                url: track.url.clone(),
+                title: track.title.clone(),
+                duration: track.duration,
+            })
+            .execute(conn)
+    }
+
+    pub fn track_count(&self, conn: &mut SqliteConnection) -> QueryResult<i64> {
+        saved_playlist_tracks::table
+            .filter(saved_playlist_tracks::playlist_id.eq(self.id.unwrap_or_default()))
+            .count()
+            .get_result(conn)
+    }
+}
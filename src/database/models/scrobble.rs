@@ -0,0 +1,175 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::{scrobble_accounts, scrobble_queue};
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = scrobble_accounts)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ScrobbleAccount {
+    pub id: Option<i32>,
+    pub user_id: String,
+    pub service: String,
+    pub username: Option<String>,
+    #[serde(skip_serializing)]
+    pub token: String,
+    pub enabled: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = scrobble_accounts)]
+pub struct NewScrobbleAccount {
+    pub user_id: String,
+    pub service: String,
+    pub username: Option<String>,
+    pub token: String,
+    pub enabled: bool,
+}
+
+impl ScrobbleAccount {
+    /// Link (or re-link) a user's account for `service`, enabling submissions
+    pub fn link(
+        conn: &mut SqliteConnection,
+        user_id: &str,
+        service: &str,
+        username: Option<&str>,
+        token: &str,
+    ) -> QueryResult<usize> {
+        let new_account = NewScrobbleAccount {
+            user_id: user_id.to_string(),
+            service: service.to_string(),
+            username: username.map(|s| s.to_string()),
+            token: token.to_string(),
+            enabled: true,
+        };
+
+        diesel::replace_into(scrobble_accounts::table)
+            .values(&new_account)
+            .execute(conn)
+    }
+
+    /// Remove a linked account and anything still queued for it; returns false if none was linked
+    pub fn unlink(conn: &mut SqliteConnection, user_id: &str, service: &str) -> QueryResult<bool> {
+        diesel::delete(scrobble_queue::table)
+            .filter(scrobble_queue::user_id.eq(user_id))
+            .filter(scrobble_queue::service.eq(service))
+            .execute(conn)?;
+        let deleted = diesel::delete(scrobble_accounts::table)
+            .filter(scrobble_accounts::user_id.eq(user_id))
+            .filter(scrobble_accounts::service.eq(service))
+            .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    pub fn find(
+        conn: &mut SqliteConnection,
+        user_id: &str,
+        service: &str,
+    ) -> QueryResult<Option<ScrobbleAccount>> {
+        scrobble_accounts::table
+            .filter(scrobble_accounts::user_id.eq(user_id))
+            .filter(scrobble_accounts::service.eq(service))
+            .select(ScrobbleAccount::as_select())
+            .first::<ScrobbleAccount>(conn)
+            .optional()
+    }
+
+    /// Accounts that should receive this user's listens
+    pub fn enabled_for_user(
+        conn: &mut SqliteConnection,
+        user_id: &str,
+    ) -> QueryResult<Vec<ScrobbleAccount>> {
+        scrobble_accounts::table
+            .filter(scrobble_accounts::user_id.eq(user_id))
+            .filter(scrobble_accounts::enabled.eq(true))
+            .select(ScrobbleAccount::as_select())
+            .load::<ScrobbleAccount>(conn)
+    }
+}
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = scrobble_queue)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ScrobbleQueueEntry {
+    pub id: Option<i32>,
+    pub user_id: String,
+    pub service: String,
+    pub artist: String,
+    pub track: String,
+    pub duration: Option<i32>,
+    pub played_at: NaiveDateTime,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = scrobble_queue)]
+pub struct NewScrobbleQueueEntry {
+    pub user_id: String,
+    pub service: String,
+    pub artist: String,
+    pub track: String,
+    pub duration: Option<i32>,
+    pub played_at: NaiveDateTime,
+}
+
+impl ScrobbleQueueEntry {
+    pub fn enqueue(
+        conn: &mut SqliteConnection,
+        entry: &NewScrobbleQueueEntry,
+    ) -> QueryResult<usize> {
+        diesel::insert_into(scrobble_queue::table)
+            .values(entry)
+            .execute(conn)
+    }
+
+    /// Entries whose next attempt is due, oldest listens first
+    pub fn due(conn: &mut SqliteConnection, limit: i64) -> QueryResult<Vec<ScrobbleQueueEntry>> {
+        scrobble_queue::table
+            .filter(scrobble_queue::next_attempt_at.le(Utc::now().naive_utc()))
+            .order(scrobble_queue::played_at.asc())
+            .limit(limit)
+            .select(ScrobbleQueueEntry::as_select())
+            .load::<ScrobbleQueueEntry>(conn)
+    }
+
+    pub fn count_for_user(
+        conn: &mut SqliteConnection,
+        user_id: &str,
+        service: &str,
+    ) -> QueryResult<i64> {
+        scrobble_queue::table
+            .filter(scrobble_queue::user_id.eq(user_id))
+            .filter(scrobble_queue::service.eq(service))
+            .count()
+            .get_result(conn)
+    }
+
+    /// Drop an entry once it's been accepted (or given up on)
+    pub fn remove(conn: &mut SqliteConnection, id: i32) -> QueryResult<usize> {
+        diesel::delete(scrobble_queue::table)
+            .filter(scrobble_queue::id.eq(id))
+            .execute(conn)
+    }
+
+    /// Record a failed submission and push the next attempt back
+    pub fn reschedule(
+        conn: &mut SqliteConnection,
+        id: i32,
+        attempts: i32,
+        next_attempt_at: NaiveDateTime,
+        error: &str,
+    ) -> QueryResult<usize> {
+        diesel::update(scrobble_queue::table)
+            .filter(scrobble_queue::id.eq(id))
+            .set((
+                scrobble_queue::attempts.eq(attempts),
+                scrobble_queue::next_attempt_at.eq(next_attempt_at),
+                scrobble_queue::last_error.eq(error),
+            ))
+            .execute(conn)
+    }
+}
@@ -16,6 +16,10 @@ pub struct SongCache {
     pub file_size: Option<i32>,
     pub last_accessed: NaiveDateTime,
     pub created_at: NaiveDateTime,
+    pub format: Option<String>,
+    /// yt-dlp metadata for the URL, as [`crate::audio::TrackMetadata`] JSON
+    pub metadata_json: Option<String>,
+    pub metadata_fetched_at: Option<NaiveDateTime>,
 }
 
 #[derive(Insertable)]
@@ -27,9 +31,11 @@ pub struct NewSongCache {
     pub thumbnail_url: Option<String>,
     pub file_path: Option<String>,
     pub file_size: Option<i32>,
+    pub format: Option<String>,
 }
 
 impl SongCache {
+    #[allow(clippy::too_many_arguments)]
     pub fn create_or_update(
         conn: &mut SqliteConnection,
         url: &str,
@@ -38,6 +44,7 @@ impl SongCache {
         thumbnail_url: Option<&str>,
         file_path: Option<&str>,
         file_size: Option<i32>,
+        format: Option<&str>,
     ) -> QueryResult<usize> {
         let new_cache = NewSongCache {
             url: url.to_string(),
@@ -46,6 +53,7 @@ impl SongCache {
             thumbnail_url: thumbnail_url.map(|s| s.to_string()),
             file_path: file_path.map(|s| s.to_string()),
             file_size,
+            format: format.map(|s| s.to_string()),
         };
 
         diesel::insert_into(song_cache::table)
@@ -58,6 +66,7 @@ impl SongCache {
                 song_cache::thumbnail_url.eq(&new_cache.thumbnail_url),
                 song_cache::file_path.eq(&new_cache.file_path),
                 song_cache::file_size.eq(&new_cache.file_size),
+                song_cache::format.eq(&new_cache.format),
                 song_cache::last_accessed.eq(chrono::Utc::now().naive_utc()),
             ))
             .execute(conn)
@@ -70,6 +79,48 @@ impl SongCache {
             .optional()
     }
 
+    /// The metadata cached for `url` if it was fetched after `fresh_after`
+    pub fn find_metadata(
+        conn: &mut SqliteConnection,
+        url: &str,
+        fresh_after: NaiveDateTime,
+    ) -> QueryResult<Option<String>> {
+        song_cache::table
+            .filter(song_cache::url.eq(url))
+            .filter(song_cache::metadata_fetched_at.gt(fresh_after))
+            .select(song_cache::metadata_json.assume_not_null())
+            .filter(song_cache::metadata_json.is_not_null())
+            .first(conn)
+            .optional()
+    }
+
+    /// Cache the metadata yt-dlp returned for `url`, creating its entry if the URL hasn't been
+    /// downloaded yet
+    pub fn store_metadata(
+        conn: &mut SqliteConnection,
+        url: &str,
+        title: &str,
+        duration: Option<i32>,
+        metadata_json: &str,
+    ) -> QueryResult<usize> {
+        let now = chrono::Utc::now().naive_utc();
+        diesel::insert_into(song_cache::table)
+            .values((
+                song_cache::url.eq(url),
+                song_cache::title.eq(title),
+                song_cache::duration.eq(duration),
+                song_cache::metadata_json.eq(metadata_json),
+                song_cache::metadata_fetched_at.eq(now),
+            ))
+            .on_conflict(song_cache::url)
+            .do_update()
+            .set((
+                song_cache::metadata_json.eq(metadata_json),
+                song_cache::metadata_fetched_at.eq(now),
+            ))
+            .execute(conn)
+    }
+
     pub fn update_last_accessed(conn: &mut SqliteConnection, url: &str) -> QueryResult<usize> {
         diesel::update(song_cache::table)
             .filter(song_cache::url.eq(url))
@@ -77,6 +128,28 @@ impl SongCache {
             .execute(conn)
     }
 
+    /// Point entries cached at `from` to the file's new place
+    pub fn move_file(conn: &mut SqliteConnection, from: &str, to: &str) -> QueryResult<usize> {
+        diesel::update(song_cache::table)
+            .filter(song_cache::file_path.eq(from))
+            .set(song_cache::file_path.eq(to))
+            .execute(conn)
+    }
+
+    /// URL and file of every entry that records where its download is kept
+    pub fn with_files(conn: &mut SqliteConnection) -> QueryResult<Vec<(String, String)>> {
+        song_cache::table
+            .filter(song_cache::file_path.is_not_null())
+            .select((song_cache::url, song_cache::file_path.assume_not_null()))
+            .load(conn)
+    }
+
+    pub fn delete_by_url(conn: &mut SqliteConnection, url: &str) -> QueryResult<usize> {
+        diesel::delete(song_cache::table)
+            .filter(song_cache::url.eq(url))
+            .execute(conn)
+    }
+
     pub fn cleanup_old_entries(
         conn: &mut SqliteConnection,
         days_to_keep: i32,
@@ -90,10 +163,8 @@ impl SongCache {
     }
 
     pub fn get_cache_size(conn: &mut SqliteConnection) -> QueryResult<i64> {
-        use diesel::dsl::sum;
-
         song_cache::table
-            .select(sum(song_cache::file_size))
+            .select(diesel::dsl::sum(song_cache::file_size))
             .first::<Option<i64>>(conn)
             .map(|result| result.unwrap_or(0))
     }
@@ -0,0 +1,121 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::database::schema::uploads;
+
+/// Hours an unfinished upload is kept for its client to resume
+pub const UPLOAD_RESUME_HOURS: i64 = 24;
+
+/// An audio file uploaded to a guild through the chunked upload API
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = uploads)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Upload {
+    pub id: String,
+    pub guild_id: String,
+    pub user_id: String,
+    pub filename: String,
+    pub size: i32,
+    pub received: i32,
+    pub content_type: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+impl Upload {
+    pub fn create(conn: &mut SqliteConnection, upload: &Upload) -> QueryResult<usize> {
+        diesel::insert_into(uploads::table)
+            .values(upload)
+            .execute(conn)
+    }
+
+    pub fn find(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        id: &str,
+    ) -> QueryResult<Option<Upload>> {
+        uploads::table
+            .filter(uploads::guild_id.eq(guild_id))
+            .filter(uploads::id.eq(id))
+            .select(Upload::as_select())
+            .first::<Upload>(conn)
+            .optional()
+    }
+
+    /// Finished upload `id`, whichever guild it belongs to
+    pub fn find_completed(conn: &mut SqliteConnection, id: &str) -> QueryResult<Option<Upload>> {
+        uploads::table
+            .filter(uploads::id.eq(id))
+            .filter(uploads::completed_at.is_not_null())
+            .select(Upload::as_select())
+            .first::<Upload>(conn)
+            .optional()
+    }
+
+    /// The guild's finished uploads, newest first
+    pub fn list_completed(conn: &mut SqliteConnection, guild_id: &str) -> QueryResult<Vec<Upload>> {
+        uploads::table
+            .filter(uploads::guild_id.eq(guild_id))
+            .filter(uploads::completed_at.is_not_null())
+            .order(uploads::created_at.desc())
+            .select(Upload::as_select())
+            .load::<Upload>(conn)
+    }
+
+    /// Bytes the guild's uploads take up or have reserved, finished or not
+    pub fn guild_usage(conn: &mut SqliteConnection, guild_id: &str) -> QueryResult<i64> {
+        uploads::table
+            .filter(uploads::guild_id.eq(guild_id))
+            .select(diesel::dsl::sum(uploads::size))
+            .first::<Option<i64>>(conn)
+            .map(Option::unwrap_or_default)
+    }
+
+    pub fn set_received(
+        conn: &mut SqliteConnection,
+        id: &str,
+        received: i32,
+    ) -> QueryResult<usize> {
+        diesel::update(uploads::table)
+            .filter(uploads::id.eq(id))
+            .set(uploads::received.eq(received))
+            .execute(conn)
+    }
+
+    pub fn complete(
+        conn: &mut SqliteConnection,
+        id: &str,
+        content_type: &str,
+    ) -> QueryResult<usize> {
+        diesel::update(uploads::table)
+            .filter(uploads::id.eq(id))
+            .set((
+                uploads::content_type.eq(content_type),
+                uploads::completed_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+
+    pub fn delete(conn: &mut SqliteConnection, id: &str) -> QueryResult<usize> {
+        diesel::delete(uploads::table)
+            .filter(uploads::id.eq(id))
+            .execute(conn)
+    }
+
+    /// Forget unfinished uploads nobody resumed in time, returning them so their partial files
+    /// can be removed too
+    pub fn take_abandoned(conn: &mut SqliteConnection) -> QueryResult<Vec<Upload>> {
+        let cutoff = Utc::now().naive_utc() - Duration::hours(UPLOAD_RESUME_HOURS);
+        let abandoned = uploads::table
+            .filter(uploads::completed_at.is_null())
+            .filter(uploads::created_at.le(cutoff))
+            .select(Upload::as_select())
+            .load::<Upload>(conn)?;
+        diesel::delete(uploads::table)
+            .filter(uploads::completed_at.is_null())
+            .filter(uploads::created_at.le(cutoff))
+            .execute(conn)?;
+        Ok(abandoned)
+    }
+}
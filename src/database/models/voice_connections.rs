@@ -14,6 +14,8 @@ pub struct VoiceConnection {
     pub last_activity: NaiveDateTime,
     pub current_track_title: Option<String>,
     pub is_playing: bool,
+    /// Text channel the session was last used from, for notices about it
+    pub text_channel_id: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -121,6 +123,18 @@ impl VoiceConnection {
             .execute(conn)
     }
 
+    /// Remember the text channel the guild's session is being used from
+    pub fn set_text_channel(
+        conn: &mut SqliteConnection,
+        guild_id: &str,
+        text_channel_id: &str,
+    ) -> QueryResult<usize> {
+        diesel::update(voice_connections::table)
+            .filter(voice_connections::guild_id.eq(guild_id))
+            .set(voice_connections::text_channel_id.eq(text_channel_id))
+            .execute(conn)
+    }
+
     /// Update playing status and current track
     pub fn update_playing_status(
         conn: &mut SqliteConnection,
@@ -1,5 +1,45 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    blocked_tracks (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        track_key -> Text,
+        reason -> Nullable<Text>,
+        blocked_by -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    control_hook_calls (id) {
+        id -> Nullable<Integer>,
+        hook_id -> Integer,
+        outcome -> Text,
+        detail -> Nullable<Text>,
+        remote_addr -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    control_hooks (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        name -> Text,
+        token -> Text,
+        secret -> Text,
+        url -> Nullable<Text>,
+        playlist -> Nullable<Text>,
+        voice_channel_id -> Text,
+        rate_limit_per_minute -> Integer,
+        created_by -> Text,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        disabled_reason -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     current_queue (id) {
         id -> Nullable<Integer>,
@@ -10,6 +50,56 @@ diesel::table! {
         position -> Integer,
         added_by -> Text,
         added_at -> Timestamp,
+        votes -> Integer,
+        track_uuid -> Nullable<Text>,
+        priority -> Integer,
+        status -> Text,
+    }
+}
+
+diesel::table! {
+    dj_grants (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        user_id -> Text,
+        granted_by -> Text,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    download_jobs (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        channel_id -> Text,
+        user_id -> Text,
+        url -> Text,
+        priority -> Integer,
+        message_id -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    feature_flags (id) {
+        id -> Nullable<Integer>,
+        flag -> Text,
+        scope -> Text,
+        enabled -> Bool,
+        updated_by -> Text,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    filter_presets (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        name -> Text,
+        filters -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
     }
 }
 
@@ -23,6 +113,143 @@ diesel::table! {
         blocked_domains -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        allowed_domains -> Nullable<Text>,
+        explicit_filter -> Bool,
+        blocked_keywords -> Nullable<Text>,
+        priority_roles -> Nullable<Text>,
+        require_approval -> Bool,
+        approval_channel_id -> Nullable<Text>,
+        quiet_hours_start -> Nullable<Text>,
+        quiet_hours_end -> Nullable<Text>,
+        quiet_hours_volume -> Nullable<Float>,
+        max_volume -> Float,
+        embed_color -> Nullable<Integer>,
+        emoji_set -> Text,
+        embed_footer -> Nullable<Text>,
+        audio_filters -> Nullable<Text>,
+        announcement_channel_id -> Nullable<Text>,
+        self_deafen -> Nullable<Bool>,
+        stage_topic -> Bool,
+        listening_party_event -> Bool,
+        command_roles -> Nullable<Text>,
+        log_channel_id -> Nullable<Text>,
+        quiet_hours_timezone -> Text,
+    }
+}
+
+diesel::table! {
+    idempotency_keys (guild_id, user_id, idempotency_key) {
+        guild_id -> Text,
+        user_id -> Text,
+        idempotency_key -> Text,
+        request -> Text,
+        status -> Integer,
+        body -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    library_tracks (id) {
+        id -> Nullable<Integer>,
+        path -> Text,
+        title -> Text,
+        artist -> Nullable<Text>,
+        album -> Nullable<Text>,
+        duration_secs -> Nullable<Integer>,
+        modified_at -> Timestamp,
+        indexed_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    lyrics (id) {
+        id -> Nullable<Integer>,
+        lookup_key -> Text,
+        title -> Nullable<Text>,
+        artist -> Nullable<Text>,
+        plain_lyrics -> Nullable<Text>,
+        synced_lyrics -> Nullable<Text>,
+        instrumental -> Bool,
+        source -> Text,
+        fetched_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    music_bans (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        user_id -> Text,
+        reason -> Nullable<Text>,
+        banned_by -> Text,
+        expires_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    pending_requests (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        channel_id -> Text,
+        user_id -> Text,
+        url -> Text,
+        priority -> Integer,
+        review_channel_id -> Nullable<Text>,
+        review_message_id -> Nullable<Text>,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    playback_bookmarks (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        url -> Text,
+        position -> Integer,
+        duration -> Nullable<Integer>,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    playback_state (guild_id) {
+        guild_id -> Text,
+        track_uuid -> Nullable<Text>,
+        title -> Nullable<Text>,
+        url -> Nullable<Text>,
+        duration -> Nullable<Integer>,
+        started_at -> Nullable<Timestamp>,
+        paused_at -> Nullable<Timestamp>,
+        paused_secs -> Integer,
+        seek_offset_secs -> Integer,
+        loop_mode -> Text,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    podcast_episodes (id) {
+        id -> Nullable<Integer>,
+        feed_url -> Text,
+        url -> Text,
+        title -> Text,
+        duration -> Nullable<Integer>,
+        published_at -> Nullable<Timestamp>,
+        fetched_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    podcast_subscriptions (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        feed_url -> Text,
+        title -> Text,
+        subscribed_by -> Text,
+        created_at -> Timestamp,
     }
 }
 
@@ -35,6 +262,91 @@ diesel::table! {
         title -> Nullable<Text>,
         duration -> Nullable<Integer>,
         played_at -> Timestamp,
+        track_uuid -> Nullable<Text>,
+        queued_at -> Nullable<Timestamp>,
+        started_at -> Nullable<Timestamp>,
+        finished_at -> Nullable<Timestamp>,
+        status -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    queue_share_tracks (id) {
+        id -> Nullable<Integer>,
+        share_id -> Integer,
+        position -> Integer,
+        url -> Text,
+        title -> Nullable<Text>,
+        duration -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    queue_shares (id) {
+        id -> Nullable<Integer>,
+        token -> Text,
+        guild_id -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    queue_votes (id) {
+        id -> Nullable<Integer>,
+        queue_entry_id -> Integer,
+        user_id -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    saved_playlist_tracks (id) {
+        id -> Nullable<Integer>,
+        playlist_id -> Integer,
+        position -> Integer,
+        url -> Text,
+        title -> Text,
+        duration -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    saved_playlists (id) {
+        id -> Nullable<Integer>,
+        guild_id -> Text,
+        name -> Text,
+        owner_id -> Text,
+        source_url -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scrobble_accounts (id) {
+        id -> Nullable<Integer>,
+        user_id -> Text,
+        service -> Text,
+        username -> Nullable<Text>,
+        token -> Text,
+        enabled -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scrobble_queue (id) {
+        id -> Nullable<Integer>,
+        user_id -> Text,
+        service -> Text,
+        artist -> Text,
+        track -> Text,
+        duration -> Nullable<Integer>,
+        played_at -> Timestamp,
+        attempts -> Integer,
+        next_attempt_at -> Timestamp,
+        last_error -> Nullable<Text>,
     }
 }
 
@@ -48,6 +360,23 @@ diesel::table! {
         file_size -> Nullable<Integer>,
         last_accessed -> Timestamp,
         created_at -> Timestamp,
+        format -> Nullable<Text>,
+        metadata_json -> Nullable<Text>,
+        metadata_fetched_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    uploads (id) {
+        id -> Text,
+        guild_id -> Text,
+        user_id -> Text,
+        filename -> Text,
+        size -> Integer,
+        received -> Integer,
+        content_type -> Nullable<Text>,
+        created_at -> Timestamp,
+        completed_at -> Nullable<Timestamp>,
     }
 }
 
@@ -59,13 +388,38 @@ diesel::table! {
         last_activity -> Timestamp,
         current_track_title -> Nullable<Text>,
         is_playing -> Bool,
+        text_channel_id -> Nullable<Text>,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
+    blocked_tracks,
+    control_hook_calls,
+    control_hooks,
     current_queue,
+    dj_grants,
+    download_jobs,
+    feature_flags,
+    filter_presets,
     guild_settings,
+    idempotency_keys,
+    library_tracks,
+    lyrics,
+    music_bans,
+    pending_requests,
+    playback_bookmarks,
+    playback_state,
+    podcast_episodes,
+    podcast_subscriptions,
     queue_history,
+    queue_share_tracks,
+    queue_shares,
+    queue_votes,
+    saved_playlist_tracks,
+    saved_playlists,
+    scrobble_accounts,
+    scrobble_queue,
     song_cache,
+    uploads,
     voice_connections,
 );
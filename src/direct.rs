@@ -0,0 +1,348 @@
+//! Direct links to audio files (`.mp3`, `.ogg`, `.flac`, or a Nextcloud/ownCloud share's
+//! download link), fetched over HTTP instead of through yt-dlp so self-hosted file servers
+//! work. The server has to say it's sending audio, the file has to fit under a size limit, and
+//! its first bytes have to look like audio, before it's converted and cached like any download.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use futures_util::future::BoxFuture;
+use once_cell::sync::Lazy;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE, HeaderMap};
+use ring::digest::{SHA256, digest};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use url::Url;
+
+use crate::audio::{
+    self, DownloadLane, DownloadPhase, DownloadProgress, DownloadResult, JobDir, TrackMetadata,
+};
+use crate::source::{Download, MediaSource};
+use crate::validation::{is_private_ip, validate_media_url};
+
+/// Largest direct file fetched, in MB
+const MAX_FILE_MB_ENV: &str = "LYRE_DIRECT_MAX_MB";
+const DEFAULT_MAX_FILE_MB: u64 = 200;
+/// File extensions treated as direct audio links
+const AUDIO_EXTENSIONS: [&str; 5] = ["mp3", "ogg", "oga", "opus", "flac"];
+/// Cache folder for direct files, in place of a yt-dlp extractor name
+const SOURCE: &str = "direct";
+/// Redirects followed before giving up
+const MAX_REDIRECTS: usize = 5;
+
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent(concat!(
+            "lyre-bot/",
+            env!("CARGO_PKG_VERSION"),
+            " (+https://github.com/mbround18/lyre)"
+        ))
+        // Host names are only connected to at public addresses; links and redirects to private
+        // IP literals are refused by `validate_media_url`
+        .dns_resolver(Arc::new(PublicOnlyResolver))
+        // Each hop is held to the same rules as the link itself, so a redirect can't reach a
+        // private address
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if validate_media_url(attempt.url().as_str()).is_err() {
+                attempt.error("redirected to an address that isn't allowed")
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+        .expect("client")
+});
+
+/// Resolves host names for direct downloads, dropping private addresses, so neither an
+/// internal name (`nas.internal`) nor a public one pointing at 10/8, 127/8 or 169.254/16 lets a
+/// link reach the bot's own network
+struct PublicOnlyResolver;
+
+impl Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| !is_private_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn max_file_bytes() -> u64 {
+    crate::config::var(MAX_FILE_MB_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|mb| *mb > 0)
+        .unwrap_or(DEFAULT_MAX_FILE_MB)
+        * 1024
+        * 1024
+}
+
+/// Whether `url` points straight at an audio file rather than a page yt-dlp has to read
+pub fn is_direct_link(url: &str) -> bool {
+    let Ok(url) = Url::parse(url.trim()) else {
+        return false;
+    };
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    match segments.as_slice() {
+        // Nextcloud and ownCloud public shares: /s/<token>/download
+        [.., "s", _, "download"] => true,
+        [.., last] => last
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())),
+        [] => false,
+    }
+}
+
+/// Content types a file server may send audio as; anything else (a login page, a share's
+/// preview page) is refused
+fn is_audio_type(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) else {
+        // Nothing declared; the file's first bytes are checked instead
+        return true;
+    };
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime.starts_with("audio/")
+        || matches!(
+            mime.as_str(),
+            "application/ogg" | "application/octet-stream" | "binary/octet-stream"
+        )
+}
+
+/// Refuse a response that isn't audio or is bigger than allowed
+fn check_headers(headers: &HeaderMap) -> Result<Option<u64>> {
+    if !is_audio_type(headers) {
+        return Err(anyhow!(
+            "that link doesn't lead to an audio file (the server sent {})",
+            headers
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("something else")
+        ));
+    }
+    let size = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if let Some(size) = size
+        && size > max_file_bytes()
+    {
+        return Err(anyhow!(
+            "the file is {} MB, more than the {} MB allowed",
+            size / 1024 / 1024,
+            max_file_bytes() / 1024 / 1024
+        ));
+    }
+    Ok(size)
+}
+
+/// The file name a server suggests in `Content-Disposition`, or the link's last path segment
+fn file_name(url: &str, headers: &HeaderMap) -> Option<String> {
+    let disposition = headers
+        .get(CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let suggested = disposition.split(';').find_map(|part| {
+        let (key, value) = part.trim().split_once('=')?;
+        match key.trim().to_ascii_lowercase().as_str() {
+            // RFC 5987: filename*=UTF-8''<percent-encoded>
+            "filename*" => value.split("''").nth(1).map(percent_decode),
+            "filename" => Some(value.trim_matches('"').to_string()),
+            _ => None,
+        }
+    });
+    suggested
+        .or_else(|| {
+            Url::parse(url)
+                .ok()?
+                .path_segments()?
+                .rfind(|s| !s.is_empty())
+                .map(percent_decode)
+        })
+        .filter(|name| !name.trim().is_empty())
+}
+
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Cache name for `url`; the link is all there is to identify the file by
+fn media_id(url: &str) -> String {
+    hex::encode(digest(&SHA256, url.trim().as_bytes()))
+}
+
+/// Direct audio links, fetched with HTTP and converted to cached MP3s
+pub struct DirectFile;
+
+impl MediaSource for DirectFile {
+    fn name(&self) -> &'static str {
+        "direct"
+    }
+
+    fn handles(&self, url: &str) -> bool {
+        is_direct_link(url)
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<TrackMetadata>> {
+        Box::pin(async move {
+            let response = HTTP.head(url.trim()).send().await?;
+            // Some file servers don't answer HEAD; the download checks the same things
+            let headers = if response.status().is_success() {
+                check_headers(response.headers())?;
+                response.headers().clone()
+            } else {
+                HeaderMap::new()
+            };
+            let name = file_name(url, &headers).unwrap_or_else(|| url.trim().to_string());
+            let title = match name.rsplit_once('.') {
+                Some((stem, ext))
+                    if AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) =>
+                {
+                    stem.to_string()
+                }
+                _ => name,
+            };
+            Ok(TrackMetadata {
+                title,
+                id: Some(media_id(url)),
+                age_limit: None,
+                // Only known once the file is probed
+                duration: None,
+                artist: None,
+                track: None,
+                album: None,
+                uploader: None,
+                extractor_key: Some(SOURCE.to_string()),
+            })
+        })
+    }
+
+    /// Direct files don't compete for yt-dlp's download lanes, so `_lane` is unused
+    fn fetch(&self, url: String, _lane: DownloadLane) -> Download {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(async move {
+            let id = media_id(&url);
+            if let Some(cached) = audio::cached_download(SOURCE, &id).await {
+                let _ = tx.send(DownloadProgress {
+                    phase: DownloadPhase::Ready,
+                    percent: 100,
+                });
+                return Ok(cached);
+            }
+            let result = download(&url, &id, &tx).await;
+            if result.is_ok() {
+                let _ = tx.send(DownloadProgress {
+                    phase: DownloadPhase::Ready,
+                    percent: 100,
+                });
+            }
+            result
+        });
+        (rx, handle)
+    }
+}
+
+async fn download(
+    url: &str,
+    id: &str,
+    tx: &mpsc::UnboundedSender<DownloadProgress>,
+) -> Result<DownloadResult> {
+    let mut response = HTTP.get(url.trim()).send().await?.error_for_status()?;
+    let size = check_headers(response.headers())?;
+    let max = max_file_bytes();
+
+    let dir = JobDir::create().await?;
+    let path = dir.path().join("download");
+    let mut file = fs::File::create(&path).await?;
+    let mut received: u64 = 0;
+    let mut head = Vec::new();
+    let mut last_percent = None;
+    while let Some(chunk) = response.chunk().await? {
+        received += chunk.len() as u64;
+        // The declared length can be missing or wrong, so count what actually arrives
+        if received > max {
+            return Err(anyhow!(
+                "the file is more than the {} MB allowed",
+                max / 1024 / 1024
+            ));
+        }
+        if head.len() < 16 {
+            head.extend_from_slice(&chunk[..chunk.len().min(16 - head.len())]);
+            if head.len() >= 12 && crate::uploads::sniff_audio(&head).is_none() {
+                return Err(anyhow!("that link doesn't lead to an audio file"));
+            }
+        }
+        file.write_all(&chunk).await?;
+        if let Some(size) = size.filter(|s| *s > 0) {
+            let percent = (received * 100 / size).min(100) as u8;
+            if last_percent != Some(percent) {
+                last_percent = Some(percent);
+                let _ = tx.send(DownloadProgress {
+                    phase: DownloadPhase::Downloading,
+                    percent,
+                });
+            }
+        }
+    }
+    file.flush().await?;
+    drop(file);
+    if crate::uploads::sniff_audio(&head).is_none() {
+        return Err(anyhow!("that link doesn't lead to an audio file"));
+    }
+
+    let _ = tx.send(DownloadProgress {
+        phase: DownloadPhase::Converting,
+        percent: 100,
+    });
+    audio::cache_converted(&path, SOURCE, id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PublicOnlyResolver;
+    use reqwest::dns::{Name, Resolve};
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn names_resolving_to_private_addresses_are_refused() {
+        let name = Name::from_str("localhost").unwrap();
+        assert!(PublicOnlyResolver.resolve(name).await.is_err());
+    }
+}
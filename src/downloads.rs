@@ -0,0 +1,141 @@
+//! Progress of the downloads in flight, fanned out to API clients following a guild's downloads
+//! (see `/api/downloads/{guild_id}/events`). Whoever drives a download publishes its progress
+//! here along with the guild it's for; with nobody subscribed the events are simply dropped.
+//! Downloads are also registered while they run, so operators can list them and cancel one
+//! that's stuck or far too long.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Result, anyhow};
+use chrono::{NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::task::{AbortHandle, JoinError};
+
+use crate::audio::{DownloadPhase, DownloadProgress, DownloadResult};
+
+/// Events a slow subscriber can fall behind by before it starts missing them
+const CHANNEL_CAPACITY: usize = 256;
+
+static EVENTS: Lazy<broadcast::Sender<DownloadEvent>> =
+    Lazy::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static IN_FLIGHT: Lazy<Mutex<HashMap<u64, Entry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadEvent {
+    pub guild_id: String,
+    pub url: String,
+    pub phase: DownloadPhase,
+    pub percent: u8,
+}
+
+/// A download that's running or waiting its turn
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadJobInfo {
+    pub id: u64,
+    pub guild_id: String,
+    pub url: String,
+    /// User who asked for the track
+    pub requested_by: String,
+    /// `None` while it's queued behind other downloads or hasn't reported progress yet
+    pub phase: Option<DownloadPhase>,
+    pub percent: u8,
+    pub started_at: NaiveDateTime,
+}
+
+struct Entry {
+    info: DownloadJobInfo,
+    abort: AbortHandle,
+}
+
+pub fn publish(guild_id: &str, url: &str, progress: &DownloadProgress) {
+    if let Ok(mut in_flight) = IN_FLIGHT.lock() {
+        for entry in in_flight
+            .values_mut()
+            .filter(|entry| entry.info.guild_id == guild_id && entry.info.url == url)
+        {
+            entry.info.phase = Some(progress.phase);
+            entry.info.percent = progress.percent;
+        }
+    }
+    let _ = EVENTS.send(DownloadEvent {
+        guild_id: guild_id.to_string(),
+        url: url.to_string(),
+        phase: progress.phase,
+        percent: progress.percent,
+    });
+}
+
+pub fn subscribe() -> broadcast::Receiver<DownloadEvent> {
+    EVENTS.subscribe()
+}
+
+/// Keeps a download listed as in flight until dropped
+pub struct InFlight {
+    id: u64,
+}
+
+impl InFlight {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = IN_FLIGHT.lock() {
+            in_flight.remove(&self.id);
+        }
+    }
+}
+
+/// List the download of `url` that `requested_by` asked for in `guild_id` as in flight,
+/// cancellable through `abort`, for as long as the returned guard is held
+pub fn register(guild_id: &str, url: &str, requested_by: &str, abort: AbortHandle) -> InFlight {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let info = DownloadJobInfo {
+        id,
+        guild_id: guild_id.to_string(),
+        url: url.to_string(),
+        requested_by: requested_by.to_string(),
+        phase: None,
+        percent: 0,
+        started_at: Utc::now().naive_utc(),
+    };
+    if let Ok(mut in_flight) = IN_FLIGHT.lock() {
+        in_flight.insert(id, Entry { info, abort });
+    }
+    InFlight { id }
+}
+
+/// Every download in flight, oldest first
+pub fn list() -> Vec<DownloadJobInfo> {
+    let mut jobs: Vec<DownloadJobInfo> = IN_FLIGHT
+        .lock()
+        .map(|in_flight| in_flight.values().map(|entry| entry.info.clone()).collect())
+        .unwrap_or_default();
+    jobs.sort_by_key(|job| job.id);
+    jobs
+}
+
+/// Stop download `id`, killing its yt-dlp; whoever is waiting on it sees it fail. Returns what
+/// was cancelled, or `None` if no such download is in flight.
+pub fn cancel(id: u64) -> Option<DownloadJobInfo> {
+    let entry = IN_FLIGHT.lock().ok()?.remove(&id)?;
+    entry.abort.abort();
+    Some(entry.info)
+}
+
+/// The outcome of a joined download task, a cancelled one counting as a failed download
+pub fn outcome(joined: Result<Result<DownloadResult>, JoinError>) -> Result<DownloadResult> {
+    match joined {
+        Ok(result) => result,
+        Err(e) if e.is_cancelled() => Err(anyhow!("the download was cancelled")),
+        Err(e) => Err(anyhow!("download task panicked: {e}")),
+    }
+}
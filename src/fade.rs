@@ -0,0 +1,63 @@
+//! Short volume ramps at track boundaries: each track fades in as it starts, and the playing one
+//! fades out before a skip or stop cuts it off, instead of popping in and out mid-chorus.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use songbird::Call;
+use songbird::tracks::{PlayMode, TrackHandle};
+use tokio::sync::Mutex;
+
+/// Length of each fade in milliseconds; 0 turns fading off
+const FADE_MS_ENV: &str = "LYRE_FADE_MS";
+const DEFAULT_FADE_MS: u64 = 250;
+/// Longest fade allowed, since a skip waits for its fade-out
+const MAX_FADE_MS: u64 = 3000;
+/// How often the volume is stepped during a fade
+const STEP: Duration = Duration::from_millis(20);
+
+/// How long a fade lasts, zero when fading is off
+pub fn duration() -> Duration {
+    let ms = crate::config::var(FADE_MS_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_FADE_MS)
+        .min(MAX_FADE_MS);
+    Duration::from_millis(ms)
+}
+
+pub fn enabled() -> bool {
+    !duration().is_zero()
+}
+
+/// Step `track` from `from` to `to` over the fade duration
+pub async fn ramp(track: &TrackHandle, from: f32, to: f32) {
+    let steps = (duration().as_millis() / STEP.as_millis()).max(1) as u32;
+    for step in 1..=steps {
+        let volume = from + (to - from) * step as f32 / steps as f32;
+        // The track ended or was stopped meanwhile; nothing left to fade
+        if track.set_volume(volume).is_err() {
+            return;
+        }
+        if step < steps {
+            tokio::time::sleep(STEP).await;
+        }
+    }
+}
+
+/// Fade the call's playing track out, returning once it's silent. The caller then skips or
+/// stops it as usual; a paused track is left as it is.
+pub async fn fade_out(call_lock: &Arc<Mutex<Call>>) {
+    if !enabled() {
+        return;
+    }
+    let Some(track) = call_lock.lock().await.queue().current() else {
+        return;
+    };
+    let Ok(info) = track.get_info().await else {
+        return;
+    };
+    if info.playing == PlayMode::Play {
+        ramp(&track, info.volume, 0.0).await;
+    }
+}
@@ -0,0 +1,90 @@
+use diesel::sqlite::SqliteConnection;
+use serde::Serialize;
+
+use crate::database::models::FeatureFlag;
+use crate::database::models::feature_flag::GLOBAL_SCOPE;
+
+/// Experimental capabilities that can be switched on at runtime, globally or per guild, with
+/// `/feature` or the admin API. Everything is off unless a flag says otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Streaming,
+    Autoplay,
+    Filters,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 3] = [Feature::Streaming, Feature::Autoplay, Feature::Filters];
+
+    /// Name stored in the database and used by commands and the API
+    pub fn key(self) -> &'static str {
+        match self {
+            Feature::Streaming => "streaming",
+            Feature::Autoplay => "autoplay",
+            Feature::Filters => "filters",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Feature::Streaming => "Start playback while a track is still downloading",
+            Feature::Autoplay => "Queue related tracks when the queue runs out",
+            Feature::Filters => "Audio filters and effects",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Feature> {
+        Feature::ALL.into_iter().find(|f| f.key() == key)
+    }
+}
+
+/// A feature's settings at each level and the result for one guild
+#[derive(Debug, Serialize)]
+pub struct FeatureState {
+    pub flag: &'static str,
+    pub description: &'static str,
+    pub global: Option<bool>,
+    /// Override for the guild, which wins over the global setting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild: Option<bool>,
+    pub enabled: bool,
+}
+
+/// Whether `feature` is on for `guild_id`: its guild override if any, else the global setting
+pub fn is_enabled(conn: &mut SqliteConnection, feature: Feature, guild_id: &str) -> bool {
+    let flags = FeatureFlag::for_guild(conn, guild_id).unwrap_or_default();
+    resolve(&flags, feature, Some(guild_id)).enabled
+}
+
+/// The state of every feature, for `guild_id` or (with `None`) globally
+pub fn states(
+    conn: &mut SqliteConnection,
+    guild_id: Option<&str>,
+) -> diesel::QueryResult<Vec<FeatureState>> {
+    let flags = match guild_id {
+        Some(guild_id) => FeatureFlag::for_guild(conn, guild_id)?,
+        None => FeatureFlag::global(conn)?,
+    };
+    Ok(Feature::ALL
+        .into_iter()
+        .map(|feature| resolve(&flags, feature, guild_id))
+        .collect())
+}
+
+fn resolve(flags: &[FeatureFlag], feature: Feature, guild_id: Option<&str>) -> FeatureState {
+    let setting = |scope: &str| {
+        flags
+            .iter()
+            .find(|f| f.flag == feature.key() && f.scope == scope)
+            .map(|f| f.enabled)
+    };
+    let global = setting(GLOBAL_SCOPE);
+    let guild = guild_id.and_then(setting);
+    FeatureState {
+        flag: feature.key(),
+        description: feature.description(),
+        global,
+        guild,
+        enabled: guild.or(global).unwrap_or(false),
+    }
+}
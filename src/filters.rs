@@ -0,0 +1,261 @@
+//! Audio filters a guild can switch on with `/filter`. Each one is an ffmpeg audio filter; the
+//! enabled ones are rendered into a filtered copy of a track's download before it's queued, so
+//! a change applies from the next track that's queued.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use anyhow::{Context, Result, anyhow};
+use tokio::fs;
+use tokio::process::Command as TokioCommand;
+
+use crate::audio::ensure_ffmpeg;
+use crate::database::establish_connection;
+use crate::database::models::{FilterPreset, GuildSettings};
+use crate::features::{self, Feature};
+use crate::settings_events;
+
+/// Strongest custom bass boost, in dB; more than this mostly adds distortion
+pub const MAX_BASS_GAIN_DB: i32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Cancels what's mixed equally into both channels, which is usually the lead vocal
+    Karaoke,
+    /// Lifts the low end by a preset or custom amount
+    BassBoost(BassBoost),
+    /// "8D" audio: the mix slowly circles between the left and right ear
+    EightD,
+    /// Evens out loudness, so quiet and loud tracks play at about the same level
+    Loudnorm,
+}
+
+/// How much `/filter bassboost` lifts the low end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BassBoost {
+    Low,
+    Medium,
+    High,
+    /// Gain in dB, 1 to [`MAX_BASS_GAIN_DB`]
+    Custom(i32),
+}
+
+impl BassBoost {
+    pub const PRESETS: [BassBoost; 3] = [BassBoost::Low, BassBoost::Medium, BassBoost::High];
+
+    /// Preset name, or the gain in dB for a custom boost
+    pub fn key(self) -> String {
+        match self {
+            BassBoost::Low => "low".to_string(),
+            BassBoost::Medium => "medium".to_string(),
+            BassBoost::High => "high".to_string(),
+            BassBoost::Custom(gain) => gain.to_string(),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        if let Some(preset) = Self::PRESETS.into_iter().find(|p| p.key() == key) {
+            return Some(preset);
+        }
+        key.parse()
+            .ok()
+            .filter(|gain| (1..=MAX_BASS_GAIN_DB).contains(gain))
+            .map(BassBoost::Custom)
+    }
+
+    pub fn gain_db(self) -> i32 {
+        match self {
+            BassBoost::Low => 4,
+            BassBoost::Medium => 8,
+            BassBoost::High => 12,
+            BassBoost::Custom(gain) => gain,
+        }
+    }
+}
+
+impl Filter {
+    /// Name stored in guild settings and accepted by the API, e.g. `karaoke` or `bassboost:high`
+    pub fn key(self) -> String {
+        match self {
+            Filter::Karaoke => "karaoke".to_string(),
+            Filter::BassBoost(boost) => format!("bassboost:{}", boost.key()),
+            Filter::EightD => "8d".to_string(),
+            Filter::Loudnorm => "loudnorm".to_string(),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "karaoke" => Some(Filter::Karaoke),
+            "8d" => Some(Filter::EightD),
+            "loudnorm" => Some(Filter::Loudnorm),
+            _ => key
+                .strip_prefix("bassboost:")
+                .and_then(BassBoost::from_key)
+                .map(Filter::BassBoost),
+        }
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            Filter::Karaoke => "Vocal reduction for sing-alongs".to_string(),
+            Filter::BassBoost(boost) => format!("Bass boosted by {} dB", boost.gain_db()),
+            Filter::EightD => "Sound slowly rotating around your head".to_string(),
+            Filter::Loudnorm => "Loudness evened out between tracks".to_string(),
+        }
+    }
+
+    /// Position in the ffmpeg chain; a guild has at most one filter of each kind
+    fn rank(self) -> u8 {
+        match self {
+            Filter::Karaoke => 0,
+            Filter::BassBoost(_) => 1,
+            Filter::EightD => 2,
+            // Last, so it levels whatever the other filters did
+            Filter::Loudnorm => 3,
+        }
+    }
+
+    /// Whether `other` is the same kind of filter, whatever its settings
+    pub fn same_kind(self, other: Filter) -> bool {
+        self.rank() == other.rank()
+    }
+
+    /// The ffmpeg `-af` expression for this filter
+    fn ffmpeg(self) -> String {
+        match self {
+            // Subtracting each channel from the other removes centre-panned audio
+            Filter::Karaoke => "pan=stereo|c0=c0-c1|c1=c1-c0".to_string(),
+            // A wide peaking band around 60 Hz, limited so the extra gain doesn't clip
+            Filter::BassBoost(boost) => format!(
+                "equalizer=f=60:t=o:w=2:g={},alimiter=limit=0.95",
+                boost.gain_db()
+            ),
+            // One left-right sweep every 8 seconds, the two channels half a cycle apart
+            Filter::EightD => "apulsator=hz=0.125".to_string(),
+            // EBU R128 single pass, aiming for the -16 LUFS streaming services use
+            Filter::Loudnorm => "loudnorm=I=-16:TP=-1.5:LRA=11".to_string(),
+        }
+    }
+}
+
+/// Parse filter names into the order they're applied, skipping unknown names and keeping the
+/// last of any filter given more than once
+pub fn parse_list(names: &[String]) -> Vec<Filter> {
+    let mut filters: Vec<Filter> = Vec::new();
+    for filter in names.iter().filter_map(|n| Filter::from_key(n)) {
+        filters.retain(|f| !f.same_kind(filter));
+        filters.push(filter);
+    }
+    filters.sort_by_key(|f| f.rank());
+    filters
+}
+
+/// Whether the guild may use filters at all; see [`Feature::Filters`]
+pub fn available(guild_id: &str) -> bool {
+    let mut db_conn = establish_connection();
+    features::is_enabled(&mut db_conn, Feature::Filters, guild_id)
+}
+
+/// The filters `guild_id` has enabled, in the order they're applied; none while the `filters`
+/// feature is off
+pub fn for_guild(guild_id: &str) -> Vec<Filter> {
+    let mut db_conn = establish_connection();
+    if !features::is_enabled(&mut db_conn, Feature::Filters, guild_id) {
+        return Vec::new();
+    }
+    let names = GuildSettings::find_by_guild_id(&mut db_conn, guild_id)
+        .ok()
+        .flatten()
+        .map(|s| s.audio_filters_list())
+        .unwrap_or_default();
+    parse_list(&names)
+}
+
+/// Persist `filters` as the guild's enabled set
+pub fn save_for_guild(guild_id: &str, filters: &[Filter]) -> Result<()> {
+    let mut filters = filters.to_vec();
+    filters.sort_by_key(|f| f.rank());
+    let names: Vec<String> = filters.into_iter().map(Filter::key).collect();
+    let mut db_conn = establish_connection();
+    GuildSettings::create_or_update(&mut db_conn, guild_id)?;
+    GuildSettings::update_audio_filters(&mut db_conn, guild_id, &names)?;
+    settings_events::changed(guild_id);
+    Ok(())
+}
+
+/// Switch `guild_id` to its preset `name`, returning the filters now on, or `None` if it has no
+/// such preset
+pub fn activate_preset(guild_id: &str, name: &str) -> Result<Option<Vec<Filter>>> {
+    let Some(preset) = FilterPreset::find_by_name(&mut establish_connection(), guild_id, name)?
+    else {
+        return Ok(None);
+    };
+    let filters = parse_list(&preset.filters_list());
+    save_for_guild(guild_id, &filters)?;
+    Ok(Some(filters))
+}
+
+/// Render `input` through `filters` and return the filtered file, or `input` itself when there
+/// are none. Results sit next to the download and are reused for the same filter set.
+pub async fn apply(input: &Path, filters: &[Filter]) -> Result<PathBuf> {
+    if filters.is_empty() {
+        return Ok(input.to_path_buf());
+    }
+    let output = filtered_path(input, filters)?;
+    if fs::try_exists(&output).await.unwrap_or(false) {
+        return Ok(output);
+    }
+
+    let ffmpeg = ensure_ffmpeg().await?;
+    let chain = filters
+        .iter()
+        .map(|f| f.ffmpeg())
+        .collect::<Vec<_>>()
+        .join(",");
+    // Render to a temporary name so a half-written file is never picked up as cached
+    let partial = output.with_extension("part.mp3");
+    let out = TokioCommand::new(&ffmpeg)
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-y")
+        .arg("-i")
+        .arg(input)
+        .arg("-af")
+        .arg(&chain)
+        .arg("-ar")
+        .arg("48000")
+        .arg("-ac")
+        .arg("2")
+        .arg(&partial)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("running ffmpeg to apply filters")?;
+    if !out.status.success() {
+        let _ = fs::remove_file(&partial).await;
+        return Err(anyhow!(
+            "ffmpeg failed applying {}: {}",
+            chain,
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    fs::rename(&partial, &output).await?;
+    Ok(output)
+}
+
+/// `<stem>.<filter>-<filter>.mp3` beside the unfiltered download, with `bassboost:8` as
+/// `bassboost8` to keep the name valid everywhere
+fn filtered_path(input: &Path, filters: &[Filter]) -> Result<PathBuf> {
+    let stem = input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| anyhow!("download has no file name: {}", input.display()))?;
+    let keys = filters
+        .iter()
+        .map(|f| f.key().replace(':', ""))
+        .collect::<Vec<_>>()
+        .join("-");
+    Ok(input.with_file_name(format!("{}.{}.mp3", stem, keys)))
+}
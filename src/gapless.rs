@@ -0,0 +1,45 @@
+//! Seamless handoff between consecutive tracks of one album, so live albums and DJ mixes split
+//! into tracks play without seams: the next track is decoded ahead of time and starts at full
+//! volume instead of fading in. Tracks count as one album when their extractor metadata names
+//! the same album and artist.
+
+use crate::audio::TrackMetadata;
+use crate::database::establish_connection;
+use crate::database::models::CurrentQueue;
+use crate::metadata_cache;
+
+/// Which album `metadata` says its track is from, as a key to compare tracks by
+fn album_key(metadata: &TrackMetadata) -> Option<String> {
+    let album = metadata.album.as_deref()?.trim();
+    let artist = metadata
+        .artist
+        .as_deref()
+        .or(metadata.uploader.as_deref())
+        .unwrap_or_default()
+        .trim();
+    (!album.is_empty()).then(|| format!("{}\n{}", artist, album).to_lowercase())
+}
+
+/// Whether the track at `url` continues the album of the last track in `guild_id`'s queue, so it
+/// should follow it without a gap. Call before the track is added to the queue.
+pub fn continues_album(guild_id: &str, url: &str, metadata: Option<&TrackMetadata>) -> bool {
+    let album = match metadata {
+        Some(metadata) => album_key(metadata),
+        None => metadata_cache::cached(url).as_ref().and_then(album_key),
+    };
+    let Some(album) = album else {
+        return false;
+    };
+    let queue = match CurrentQueue::get_guild_queue(&mut establish_connection(), guild_id) {
+        Ok(queue) => queue,
+        Err(e) => {
+            tracing::warn!("Failed to load queue of guild {}: {}", guild_id, e);
+            return false;
+        }
+    };
+    queue
+        .last()
+        .and_then(|previous| metadata_cache::cached(&previous.url))
+        .and_then(|previous| album_key(&previous))
+        .is_some_and(|previous| previous == album)
+}
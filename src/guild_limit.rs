@@ -0,0 +1,74 @@
+use serenity::all::{ChannelId, ChannelType, Context, CreateMessage, Guild};
+
+/// Most guilds the bot stays in (e.g. `10` for a self-hosted instance). Unset or `0` means no
+/// limit. Guilds the bot was already in are never made to leave.
+const MAX_GUILDS_ENV: &str = "LYRE_MAX_GUILDS";
+/// Discord user IDs (comma-separated) whose servers may always add the bot, limit or not
+const EXEMPT_OWNERS_ENV: &str = "LYRE_GUILD_LIMIT_EXEMPT_OWNERS";
+
+pub fn max_guilds() -> Option<usize> {
+    crate::config::var(MAX_GUILDS_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+}
+
+/// Whether servers owned by `user_id` are let in past the limit. The bot's operators always are.
+fn is_exempt_owner(user_id: &str) -> bool {
+    crate::policy::is_bot_operator(user_id)
+        || crate::config::var(EXEMPT_OWNERS_ENV)
+            .map(|ids| ids.split(',').any(|id| id.trim() == user_id))
+            .unwrap_or(false)
+}
+
+/// Where to tell a server something unprompted: its system channel, or else its first text
+/// channel
+pub fn notice_channel(guild: &Guild) -> Option<ChannelId> {
+    guild.system_channel_id.or_else(|| {
+        guild
+            .channels
+            .values()
+            .filter(|c| c.kind == ChannelType::Text)
+            .min_by_key(|c| c.position)
+            .map(|c| c.id)
+    })
+}
+
+/// Called when the bot is added to `guild`: if that takes it past `LYRE_MAX_GUILDS` and the
+/// owner isn't exempt, explain why in the server and leave it. Returns whether the bot stays.
+pub async fn check_new_guild(ctx: &Context, guild: &Guild) -> bool {
+    let Some(limit) = max_guilds() else {
+        return true;
+    };
+    // The cache already counts the new guild
+    let guild_count = ctx.cache.guild_count();
+    if guild_count <= limit || is_exempt_owner(&guild.owner_id.to_string()) {
+        return true;
+    }
+    tracing::info!(
+        "Leaving newly joined guild {} ({}): in {} guilds, limit is {}",
+        guild.name,
+        guild.id,
+        guild_count,
+        limit
+    );
+
+    if let Some(channel_id) = notice_channel(guild) {
+        let notice = format!(
+            "👋 This Lyre instance is limited to {} servers and has reached that limit, so it's \
+             leaving this one. Ask whoever runs it to raise the limit or exempt your server's \
+             owner, or host your own instance.",
+            limit
+        );
+        if let Err(e) = channel_id
+            .send_message(&ctx.http, CreateMessage::new().content(notice))
+            .await
+        {
+            tracing::debug!("Couldn't explain guild limit in {}: {}", guild.id, e);
+        }
+    }
+    if let Err(e) = guild.id.leave(&ctx.http).await {
+        tracing::warn!("Failed to leave guild {} over the limit: {}", guild.id, e);
+    }
+    false
+}
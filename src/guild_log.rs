@@ -0,0 +1,128 @@
+//! Operational events posted as embeds to a guild's log channel, if it has picked one, so its
+//! admins can see why something went wrong without access to the bot's own logs.
+
+use serenity::all::{ChannelId, CreateEmbed, CreateEmbedFooter, CreateMessage, GuildId, Timestamp};
+
+use crate::broadcast;
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+
+/// Something that happened in a guild that its admins may want to know about
+#[derive(Debug, Clone)]
+pub enum LogEvent {
+    /// A track errored while playing or couldn't be fetched
+    TrackFailed {
+        title: Option<String>,
+        url: String,
+        reason: String,
+    },
+    /// The bot lost permissions it needs in its voice channel and paused
+    PermissionsMissing {
+        channel_id: ChannelId,
+        missing: String,
+    },
+    /// The permissions are back and playback carried on
+    PermissionsRestored { channel_id: ChannelId },
+    /// The bot left voice on its own
+    AutoDisconnect { reason: String },
+    /// A playing track stopped moving and was restarted, or skipped if that didn't help
+    PlaybackStalled {
+        title: Option<String>,
+        position_secs: u64,
+        recovered: bool,
+    },
+}
+
+impl LogEvent {
+    fn embed(&self) -> CreateEmbed {
+        let embed = match self {
+            LogEvent::TrackFailed { title, url, reason } => CreateEmbed::new()
+                .title("⚠️ Track failed")
+                .colour(0xE74C3C)
+                .field(
+                    "Track",
+                    match title {
+                        Some(title) => format!("[{}]({})", title, url),
+                        None => url.clone(),
+                    },
+                    false,
+                )
+                .field("Reason", truncate(reason, 1000), false),
+            LogEvent::PermissionsMissing {
+                channel_id,
+                missing,
+            } => CreateEmbed::new()
+                .title("🔒 Missing voice permissions")
+                .colour(0xE67E22)
+                .description("Playback is paused until they're given back.")
+                .field("Channel", format!("<#{}>", channel_id), true)
+                .field("Missing", missing.clone(), true),
+            LogEvent::PermissionsRestored { channel_id } => CreateEmbed::new()
+                .title("🔓 Voice permissions restored")
+                .colour(0x2ECC71)
+                .description(format!("Playback carried on in <#{}>.", channel_id)),
+            LogEvent::AutoDisconnect { reason } => CreateEmbed::new()
+                .title("👋 Left voice")
+                .colour(0x808080)
+                .description(reason.clone()),
+            LogEvent::PlaybackStalled {
+                title,
+                position_secs,
+                recovered,
+            } => CreateEmbed::new()
+                .title("🧊 Playback stalled")
+                .colour(0xF1C40F)
+                .description(if *recovered {
+                    "The track stopped moving, so it was restarted from where it got to."
+                } else {
+                    "The track stopped moving and couldn't be restarted, so it was skipped."
+                })
+                .field(
+                    "Track",
+                    title.clone().unwrap_or_else(|| "Unknown".to_string()),
+                    true,
+                )
+                .field(
+                    "At",
+                    crate::commands::play::format_position(*position_secs),
+                    true,
+                ),
+        };
+        embed
+            .footer(CreateEmbedFooter::new("Lyre log"))
+            .timestamp(Timestamp::now())
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Post `event` to `guild_id`'s log channel in the background. Does nothing if the guild has no
+/// log channel or the bot isn't connected to Discord.
+pub fn record(guild_id: GuildId, event: LogEvent) {
+    let Some(http) = broadcast::http() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let channel_id =
+            GuildSettings::find_by_guild_id(&mut establish_connection(), &guild_id.to_string())
+                .ok()
+                .flatten()
+                .and_then(|s| s.log_channel_id)
+                .and_then(|id| id.parse::<u64>().ok())
+                .map(ChannelId::new);
+        let Some(channel_id) = channel_id else {
+            return;
+        };
+        if let Err(e) = channel_id
+            .send_message(&http, CreateMessage::new().embeds(vec![event.embed()]))
+            .await
+        {
+            tracing::debug!("Couldn't post to log channel of guild {}: {}", guild_id, e);
+        }
+    });
+}
@@ -0,0 +1,254 @@
+//! Operator scripts run on bot events, for custom rules without forking the crate.
+//!
+//! Set `LYRE_HOOKS_DIR` to a directory of scripts named after the event they handle:
+//! `track_start`, `queue_add` or `user_join`, optionally followed by `.` or `-` and anything
+//! (`track_start.rhai`, `queue_add-tagger.py`). Scripts ending in `.rhai` run in the embedded
+//! [Rhai](https://rhai.rs) engine, which gives them the event as the `event` map and a
+//! `say(text)` function that posts to the guild's music channel. Other executables get the
+//! event as one JSON object on stdin and in the `LYRE_EVENT` variable, and any stdout line of
+//! the form `{"say": "..."}` is posted the same way. An event's scripts run one after another
+//! in the background, and each is stopped after `HOOK_TIMEOUT`.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Dynamic, Engine, Scope};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as TokioCommand;
+
+const HOOKS_DIR_ENV: &str = "LYRE_HOOKS_DIR";
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Bounds on what one Rhai run may use, so a runaway script can't take the bot's memory
+const RHAI_MAX_OPERATIONS: u64 = 10_000_000;
+const RHAI_MAX_STRING_SIZE: usize = 64 * 1024;
+const RHAI_MAX_COLLECTION_SIZE: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+pub enum HookEvent {
+    /// A track began playing
+    TrackStart,
+    /// A track was added to a guild's queue
+    QueueAdd,
+    /// Someone joined the voice channel the bot is playing in
+    UserJoin,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::TrackStart => "track_start",
+            HookEvent::QueueAdd => "queue_add",
+            HookEvent::UserJoin => "user_join",
+        }
+    }
+}
+
+/// What a script may ask the bot to do, one JSON object per stdout line
+#[derive(Deserialize)]
+struct HookAction {
+    say: Option<String>,
+}
+
+fn hooks_dir() -> Option<PathBuf> {
+    crate::config::var(HOOKS_DIR_ENV)
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// Whether any hooks are configured, so callers can skip building payloads
+pub fn enabled() -> bool {
+    hooks_dir().is_some()
+}
+
+/// Run the scripts for `event` in the background with `data` (a JSON object) plus the event
+/// name and guild ID as input
+pub fn emit(event: HookEvent, guild_id: &str, data: Value) {
+    let Some(dir) = hooks_dir() else {
+        return;
+    };
+    let mut payload = json!({ "event": event.name(), "guild_id": guild_id });
+    if let (Some(payload), Value::Object(data)) = (payload.as_object_mut(), data) {
+        payload.extend(data);
+    }
+    let guild_id = guild_id.to_string();
+    tokio::spawn(async move {
+        for script in scripts(&dir, event) {
+            if let Err(e) = run(&script, event, &guild_id, &payload).await {
+                tracing::warn!("Hook {} failed: {}", script.display(), e);
+            }
+        }
+    });
+}
+
+/// Executables in `dir` for `event`, in name order
+fn scripts(dir: &Path, event: HookEvent) -> Vec<PathBuf> {
+    let name = event.name();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        tracing::warn!("Hooks directory {} can't be read", dir.display());
+        return Vec::new();
+    };
+    let mut scripts: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            file_name == name
+                || file_name
+                    .strip_prefix(name)
+                    .is_some_and(|rest| rest.starts_with(['.', '-']))
+        })
+        .map(|entry| entry.path())
+        .filter(|path| is_rhai(path) || is_executable(path))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+fn is_rhai(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "rhai") && path.is_file()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+async fn run(script: &Path, event: HookEvent, guild_id: &str, payload: &Value) -> Result<()> {
+    let said = if is_rhai(script) {
+        run_rhai(script, payload).await?
+    } else {
+        run_executable(script, event, payload).await?
+    };
+    for message in said.iter().filter(|m| !m.trim().is_empty()) {
+        if let Err(e) = crate::broadcast::post(guild_id, message).await {
+            tracing::warn!("Couldn't post {} hook message: {}", event.name(), e);
+        }
+    }
+    Ok(())
+}
+
+/// Run a `.rhai` script in the embedded engine, returning what it asked to `say`
+async fn run_rhai(script: &Path, payload: &Value) -> Result<Vec<String>> {
+    let source = tokio::fs::read_to_string(script)
+        .await
+        .map_err(|e| anyhow!("failed to read: {}", e))?;
+    let input = payload.to_string();
+    tokio::task::spawn_blocking(move || eval_rhai(&source, &input))
+        .await
+        .map_err(|e| anyhow!("panicked: {}", e))?
+}
+
+/// Evaluate `source` with `event` (a JSON object) in scope. Scripts can't import modules or
+/// reach the filesystem, and are stopped once they run past `HOOK_TIMEOUT` or their limits.
+fn eval_rhai(source: &str, event: &str) -> Result<Vec<String>> {
+    let said = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .set_max_operations(RHAI_MAX_OPERATIONS)
+        .set_max_string_size(RHAI_MAX_STRING_SIZE)
+        .set_max_array_size(RHAI_MAX_COLLECTION_SIZE)
+        .set_max_map_size(RHAI_MAX_COLLECTION_SIZE)
+        .on_print(|text| tracing::info!("Hook printed: {}", text))
+        .on_debug(|text, _, pos| tracing::debug!("Hook debug at {}: {}", pos, text));
+    let deadline = Instant::now() + HOOK_TIMEOUT;
+    engine.on_progress(move |_| {
+        (Instant::now() > deadline)
+            .then(|| Dynamic::from(format!("timed out after {}s", HOOK_TIMEOUT.as_secs())))
+    });
+    let sink = Rc::clone(&said);
+    engine.register_fn("say", move |text: &str| {
+        sink.borrow_mut().push(text.to_string())
+    });
+
+    let event = engine
+        .parse_json(event, true)
+        .map_err(|e| anyhow!("bad event: {}", e))?;
+    let mut scope = Scope::new();
+    scope.push_constant("event", event);
+    engine
+        .run_with_scope(&mut scope, source)
+        .map_err(|e| anyhow!("{}", e))?;
+    Ok(said.take())
+}
+
+async fn run_executable(script: &Path, event: HookEvent, payload: &Value) -> Result<Vec<String>> {
+    let input = payload.to_string();
+    // Only the event and PATH: the bot's own environment holds its Discord token and API secrets
+    let mut command = TokioCommand::new(script);
+    command.env_clear().env("LYRE_EVENT", &input);
+    if let Some(path) = std::env::var_os("PATH") {
+        command.env("PATH", path);
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("failed to start: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A script that ignores its input may exit before reading it
+        let _ = stdin.write_all(input.as_bytes()).await;
+    }
+    let out = tokio::time::timeout(HOOK_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow!("timed out after {}s", HOOK_TIMEOUT.as_secs()))??;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "exited with {}: {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+
+    let mut said = Vec::new();
+    for line in String::from_utf8_lossy(&out.stdout).lines() {
+        match serde_json::from_str::<HookAction>(line) {
+            Ok(HookAction { say: Some(message) }) => said.push(message),
+            _ => tracing::debug!("{} hook output: {}", event.name(), line),
+        }
+    }
+    Ok(said)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::eval_rhai;
+
+    #[test]
+    fn rhai_scripts_see_the_event_and_can_say_things() {
+        let said = eval_rhai(
+            r#"
+                if event.requested_by == "42" {
+                    say(`Now playing ${event.title} (${event.duration}s)`);
+                }
+                print("not posted");
+            "#,
+            r#"{"event": "track_start", "title": "Song", "duration": 200, "requested_by": "42"}"#,
+        )
+        .unwrap();
+        assert_eq!(said, ["Now playing Song (200s)"]);
+    }
+
+    #[test]
+    fn rhai_scripts_cant_run_away_or_import() {
+        assert!(eval_rhai("loop {}", "{}").is_err());
+        assert!(eval_rhai(r#"import "/etc/passwd" as secrets;"#, "{}").is_err());
+        assert!(eval_rhai(r#"let s = "x"; loop { s += s; }"#, "{}").is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! A local music library: audio files under `LYRE_LIBRARY_DIR`, indexed with their tags so
+//! `/library search` can find them. Library tracks are queued as `library:<id>` and play
+//! straight from disk, so they need no network access at all.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDateTime};
+use futures_util::future::BoxFuture;
+use tokio::sync::mpsc;
+use walkdir::WalkDir;
+
+use crate::audio::{self, DownloadLane, DownloadPhase, DownloadProgress, TrackMetadata};
+use crate::database::establish_connection;
+use crate::database::models::LibraryTrack;
+use crate::database::models::library_track::NewLibraryTrack;
+use crate::source::{Download, MediaSource};
+
+/// Folder to index; the library is off without it
+const LIBRARY_DIR_ENV: &str = "LYRE_LIBRARY_DIR";
+/// File extensions indexed; ffmpeg converts any of them for playback
+const AUDIO_EXTENSIONS: [&str; 9] = [
+    "mp3", "flac", "ogg", "oga", "opus", "m4a", "aac", "wav", "wma",
+];
+/// How often the folder is walked again for new, changed and deleted files
+const REINDEX_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Prefix of a library track's URL, `library:<id>`
+const SCHEME: &str = "library";
+/// Cache folder for converted library files, in place of a yt-dlp extractor name
+const SOURCE: &str = "library";
+
+/// The library folder, if one is configured
+pub fn dir() -> Option<PathBuf> {
+    let dir = crate::config::var(LIBRARY_DIR_ENV).ok()?;
+    let dir = PathBuf::from(dir.trim());
+    if dir.as_os_str().is_empty() {
+        return None;
+    }
+    if dir.is_absolute() {
+        Some(dir)
+    } else {
+        std::env::current_dir().ok().map(|cwd| cwd.join(dir))
+    }
+}
+
+pub fn enabled() -> bool {
+    dir().is_some()
+}
+
+/// The URL library track `id` is queued as
+pub fn track_url(id: i32) -> String {
+    format!("{}:{}", SCHEME, id)
+}
+
+/// The library track a `library:<id>` URL names
+pub fn track_id(url: &str) -> Option<i32> {
+    url.trim()
+        .strip_prefix(SCHEME)?
+        .strip_prefix(':')?
+        .parse::<i32>()
+        .ok()
+        .filter(|id| *id > 0)
+}
+
+/// Index the library now and then every hour. Does nothing without `LYRE_LIBRARY_DIR`.
+pub fn spawn_indexer() {
+    let Some(root) = dir() else {
+        return;
+    };
+    tokio::spawn(async move {
+        loop {
+            match index(&root).await {
+                Ok(report) if report.updated + report.removed > 0 => tracing::info!(
+                    "Library index of {}: {} files read, {} removed, {} in total",
+                    root.display(),
+                    report.updated,
+                    report.removed,
+                    report.total
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to index library {}: {}", root.display(), e),
+            }
+            tokio::time::sleep(REINDEX_INTERVAL).await;
+        }
+    });
+}
+
+/// What one indexing pass changed
+#[derive(Debug, Default)]
+struct IndexReport {
+    updated: usize,
+    removed: usize,
+    total: usize,
+}
+
+/// An audio file found in the library
+struct LibraryFile {
+    /// Relative to the library folder, with `/` between folders
+    relative: String,
+    path: PathBuf,
+    modified_at: NaiveDateTime,
+}
+
+/// Walk `root`, read the tags of files that are new or changed since the last pass and forget
+/// files that have gone
+async fn index(root: &Path) -> Result<IndexReport> {
+    let walk_root = root.to_path_buf();
+    let files = tokio::task::spawn_blocking(move || find_audio_files(&walk_root)).await??;
+    let known: HashMap<String, NaiveDateTime> =
+        LibraryTrack::modified_times(&mut establish_connection())?
+            .into_iter()
+            .collect();
+
+    let mut report = IndexReport {
+        total: files.len(),
+        ..Default::default()
+    };
+    for file in &files {
+        if known.get(&file.relative) == Some(&file.modified_at) {
+            continue;
+        }
+        let track = read_track(file).await;
+        LibraryTrack::upsert(&mut establish_connection(), &track)?;
+        report.updated += 1;
+    }
+
+    let present: HashSet<&str> = files.iter().map(|f| f.relative.as_str()).collect();
+    let gone: Vec<String> = known
+        .into_keys()
+        .filter(|path| !present.contains(path.as_str()))
+        .collect();
+    if !gone.is_empty() {
+        report.removed = LibraryTrack::remove_paths(&mut establish_connection(), &gone)?;
+    }
+    Ok(report)
+}
+
+fn find_audio_files(root: &Path) -> Result<Vec<LibraryFile>> {
+    if !root.is_dir() {
+        return Err(anyhow!("{} isn't a folder", root.display()));
+    }
+    let mut files = Vec::new();
+    for entry in WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+    {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::debug!("Skipping part of the library: {}", e);
+                continue;
+            }
+        };
+        let is_audio = entry.path().extension().is_some_and(|ext| {
+            AUDIO_EXTENSIONS.contains(&ext.to_string_lossy().to_ascii_lowercase().as_str())
+        });
+        if !entry.file_type().is_file() || !is_audio {
+            continue;
+        }
+        // Paths are stored as text, so names that aren't UTF-8 can't be indexed
+        let Some(relative) = entry.path().strip_prefix(root).ok().and_then(|relative| {
+            relative
+                .components()
+                .map(|c| c.as_os_str().to_str())
+                .collect::<Option<Vec<_>>>()
+        }) else {
+            tracing::debug!("Skipping {}: not a UTF-8 path", entry.path().display());
+            continue;
+        };
+        let Some(modified_at) = entry
+            .metadata()
+            .ok()
+            .and_then(|m| m.modified().ok())
+            .and_then(to_timestamp)
+        else {
+            continue;
+        };
+        files.push(LibraryFile {
+            relative: relative.join("/"),
+            path: entry.path().to_path_buf(),
+            modified_at,
+        });
+    }
+    Ok(files)
+}
+
+/// Whole seconds, so the time survives being stored and compared on the next pass
+fn to_timestamp(time: SystemTime) -> Option<NaiveDateTime> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    DateTime::from_timestamp(i64::try_from(secs).ok()?, 0).map(|t| t.naive_utc())
+}
+
+/// `file`'s tags, falling back to its name for anything untagged. Names like
+/// `Artist - Title.mp3` give the artist too.
+async fn read_track(file: &LibraryFile) -> NewLibraryTrack {
+    let tags = audio::probe_tags(&file.path).await.unwrap_or_else(|e| {
+        tracing::debug!("Couldn't read tags of {}: {}", file.path.display(), e);
+        audio::FileTags::default()
+    });
+    let stem = file
+        .path
+        .file_stem()
+        .map(|s| s.to_string_lossy().trim().to_string())
+        .unwrap_or_default();
+    let (name_artist, name_title) = match stem.split_once(" - ") {
+        Some((artist, title)) if !artist.trim().is_empty() && !title.trim().is_empty() => {
+            (Some(artist.trim().to_string()), title.trim().to_string())
+        }
+        _ => (None, stem.clone()),
+    };
+    let (title, artist) = match tags.title {
+        Some(title) => (title, tags.artist),
+        None => (name_title, tags.artist.or(name_artist)),
+    };
+    NewLibraryTrack {
+        path: file.relative.clone(),
+        title,
+        artist,
+        album: tags.album,
+        duration_secs: tags.duration.map(|d| d.round() as i32),
+        modified_at: file.modified_at,
+    }
+}
+
+/// Where library track `track` is on disk, if it's still there and still inside the library
+fn file_path(track: &LibraryTrack) -> Result<PathBuf> {
+    let root = dir().ok_or_else(|| anyhow!("the local library is turned off"))?;
+    let path = root.join(&track.path);
+    let inside = match (path.canonicalize(), root.canonicalize()) {
+        (Ok(path), Ok(root)) => path.starts_with(root),
+        _ => false,
+    };
+    if !inside || !path.is_file() {
+        return Err(anyhow!("{} is no longer in the library", track.path));
+    }
+    Ok(path)
+}
+
+fn find_track(url: &str) -> Result<LibraryTrack> {
+    let id = track_id(url).ok_or_else(|| anyhow!("not a library track: {}", url))?;
+    LibraryTrack::find(&mut establish_connection(), id)?
+        .ok_or_else(|| anyhow!("there's no track {} in the library", id))
+}
+
+/// Tracks from the local library, converted into the cache on first play so the library
+/// folder itself is only ever read
+pub struct LibrarySource;
+
+impl MediaSource for LibrarySource {
+    fn name(&self) -> &'static str {
+        "library"
+    }
+
+    fn handles(&self, url: &str) -> bool {
+        track_id(url).is_some()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<TrackMetadata>> {
+        Box::pin(async move {
+            let track = find_track(url)?;
+            Ok(TrackMetadata {
+                id: track.id.map(|id| id.to_string()),
+                age_limit: None,
+                duration: track.duration_secs.map(f64::from),
+                artist: track.artist,
+                track: Some(track.title.clone()),
+                album: track.album,
+                uploader: None,
+                extractor_key: Some(SOURCE.to_string()),
+                title: track.title,
+            })
+        })
+    }
+
+    /// Nothing is downloaded, so `_lane` is unused
+    fn fetch(&self, url: String, _lane: DownloadLane) -> Download {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(async move {
+            let track = find_track(&url)?;
+            // A file edited since it was last played is converted again
+            let cache_id = format!(
+                "{}-{}",
+                track.id.unwrap_or_default(),
+                track.modified_at.and_utc().timestamp()
+            );
+            let result = match audio::cached_download(SOURCE, &cache_id).await {
+                Some(cached) => Ok(cached),
+                None => {
+                    let path = file_path(&track)?;
+                    let _ = tx.send(DownloadProgress {
+                        phase: DownloadPhase::Converting,
+                        percent: 100,
+                    });
+                    audio::cache_converted(&path, SOURCE, &cache_id).await
+                }
+            };
+            if result.is_ok() {
+                let _ = tx.send(DownloadProgress {
+                    phase: DownloadPhase::Ready,
+                    percent: 100,
+                });
+            }
+            result
+        });
+        (rx, handle)
+    }
+}
@@ -0,0 +1,245 @@
+//! Lyrics for the playing track, from LRCLIB or another server with its API
+//! (`LYRE_LYRICS_URL`). Answers are cached in the `lyrics` table, songs it had none for
+//! included, so each song is only looked up once in a while.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use url::Url;
+
+use crate::audio::TrackMetadata;
+use crate::database::establish_connection;
+use crate::database::models::Lyrics;
+use crate::database::models::lyrics::NewLyrics;
+
+/// Base URL of an LRCLIB-compatible lyrics server
+const LYRICS_URL_ENV: &str = "LYRE_LYRICS_URL";
+const DEFAULT_LYRICS_URL: &str = "https://lrclib.net";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        // LRCLIB asks clients to say who they are
+        .user_agent("lyre-bot/0.1 (+https://github.com/mbround18/lyre)")
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("client")
+});
+
+fn base_url() -> String {
+    crate::config::var(LYRICS_URL_ENV)
+        .ok()
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| DEFAULT_LYRICS_URL.to_string())
+}
+
+/// Host of the lyrics server, to credit where lyrics came from
+fn source_name(base: &str) -> String {
+    Url::parse(base)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| base.to_string())
+}
+
+/// A track as LRCLIB's `/api/get` and `/api/search` return it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProviderTrack {
+    track_name: Option<String>,
+    artist_name: Option<String>,
+    #[serde(default)]
+    instrumental: bool,
+    plain_lyrics: Option<String>,
+    synced_lyrics: Option<String>,
+}
+
+impl ProviderTrack {
+    fn has_lyrics(&self) -> bool {
+        self.instrumental || self.plain_lyrics.is_some() || self.synced_lyrics.is_some()
+    }
+}
+
+/// The song to ask the provider for
+#[derive(Debug, Clone)]
+struct Query {
+    title: String,
+    artist: Option<String>,
+    duration: Option<f64>,
+}
+
+impl Query {
+    /// The song the extractor names, if it does (YouTube Music, Bandcamp, tagged library
+    /// files); otherwise the track's title with "(Official Video)" and the like dropped, and an
+    /// "Artist - " prefix taken as the artist
+    fn for_track(title: &str, metadata: Option<&TrackMetadata>) -> Query {
+        let artist = metadata
+            .and_then(|m| m.artist.as_deref())
+            // yt-dlp lists several artists comma-separated; the first is the one lyrics go by
+            .and_then(|a| a.split(',').next())
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty());
+        let duration = metadata.and_then(|m| m.duration);
+        if let Some(track) = metadata
+            .and_then(|m| m.track.as_deref())
+            .filter(|t| !t.trim().is_empty())
+        {
+            return Query {
+                title: track.trim().to_string(),
+                artist,
+                duration,
+            };
+        }
+        let cleaned = without_brackets(title);
+        match cleaned.split_once(" - ") {
+            Some((name, song)) if !name.trim().is_empty() && !song.trim().is_empty() => Query {
+                title: song.trim().to_string(),
+                artist: Some(name.trim().to_string()),
+                duration,
+            },
+            _ => Query {
+                title: cleaned,
+                artist,
+                duration,
+            },
+        }
+    }
+
+    /// Cache key: the same song asked for twice finds the first answer
+    fn lookup_key(&self) -> String {
+        format!(
+            "{}|{}",
+            self.artist.as_deref().unwrap_or_default().to_lowercase(),
+            self.title.to_lowercase()
+        )
+    }
+}
+
+/// `title` without anything in brackets ("(Official Video)", "[HD]") and with single spaces
+fn without_brackets(title: &str) -> String {
+    let mut kept = String::with_capacity(title.len());
+    let mut depth = 0usize;
+    for c in title.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => kept.push(c),
+            _ => {}
+        }
+    }
+    let kept = kept.split_whitespace().collect::<Vec<_>>().join(" ");
+    if kept.is_empty() {
+        title.trim().to_string()
+    } else {
+        kept
+    }
+}
+
+/// Lyrics for a track titled `title` (queued from `url`, if known), from the cache or the
+/// provider. The answer may be that there are none; see [`Lyrics::is_found`].
+pub async fn for_track(title: &str, url: Option<&str>) -> Result<Lyrics> {
+    let metadata = match url {
+        Some(url) => crate::source::extract_metadata(url).await.ok(),
+        None => None,
+    };
+    let query = Query::for_track(title, metadata.as_ref());
+    let lookup_key = query.lookup_key();
+    if let Some(cached) = Lyrics::find_fresh(&mut establish_connection(), &lookup_key)? {
+        return Ok(cached);
+    }
+
+    let base = base_url();
+    let found = ask_provider(&base, &query).await?;
+    if found.is_none() {
+        tracing::debug!("No lyrics for {:?}", query);
+    }
+    let new = match found {
+        Some(track) => NewLyrics {
+            lookup_key,
+            title: track.track_name.or(Some(query.title)),
+            artist: track.artist_name.or(query.artist),
+            plain_lyrics: track.plain_lyrics,
+            synced_lyrics: track.synced_lyrics,
+            instrumental: track.instrumental,
+            source: source_name(&base),
+        },
+        None => NewLyrics {
+            lookup_key,
+            title: Some(query.title),
+            artist: query.artist,
+            plain_lyrics: None,
+            synced_lyrics: None,
+            instrumental: false,
+            source: source_name(&base),
+        },
+    };
+    Ok(Lyrics::save(&mut establish_connection(), &new)?)
+}
+
+/// An exact match by artist, title and length if the artist is known, else the best search hit
+async fn ask_provider(base: &str, query: &Query) -> Result<Option<ProviderTrack>> {
+    if let Some(artist) = &query.artist {
+        let mut url = Url::parse(&format!("{}/api/get", base))?;
+        url.query_pairs_mut()
+            .append_pair("track_name", &query.title)
+            .append_pair("artist_name", artist);
+        if let Some(duration) = query.duration {
+            url.query_pairs_mut()
+                .append_pair("duration", &(duration.round() as u64).to_string());
+        }
+        let response = HTTP.get(url).send().await?;
+        // A miss here may still be found by searching, e.g. when the lengths differ
+        if response.status() != StatusCode::NOT_FOUND {
+            let track: ProviderTrack = response.error_for_status()?.json().await?;
+            if track.has_lyrics() {
+                return Ok(Some(track));
+            }
+        }
+    }
+
+    let terms = match &query.artist {
+        Some(artist) => format!("{} {}", artist, query.title),
+        None => query.title.clone(),
+    };
+    let mut url = Url::parse(&format!("{}/api/search", base))?;
+    url.query_pairs_mut().append_pair("q", &terms);
+    let results: Vec<ProviderTrack> = HTTP
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(results.into_iter().find(ProviderTrack::has_lyrics))
+}
+
+/// The words to show, without synced lyrics' timestamps when only those were found
+pub fn text(lyrics: &Lyrics) -> Option<String> {
+    if let Some(plain) = lyrics
+        .plain_lyrics
+        .as_deref()
+        .filter(|l| !l.trim().is_empty())
+    {
+        return Some(plain.trim().to_string());
+    }
+    let synced = lyrics.synced_lyrics.as_deref()?;
+    let lines: Vec<&str> = synced
+        .lines()
+        .map(|line| {
+            let mut line = line.trim();
+            // "[01:23.45] words", possibly with several timestamps
+            while line.starts_with('[')
+                && let Some(end) = line.find(']')
+            {
+                line = line[end + 1..].trim_start();
+            }
+            line
+        })
+        .collect();
+    let joined = lines.join("\n");
+    let joined = joined.trim();
+    (!joined.is_empty()).then(|| joined.to_string())
+}
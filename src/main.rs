@@ -1,8 +1,8 @@
 use anyhow::Result;
 use serenity::{
     all::{
-        Command as AppCommand, Context as SerenityContext, GatewayIntents, Interaction,
-        Permissions, Ready,
+        Context as SerenityContext, GatewayIntents, Guild, GuildChannel, GuildId, Interaction,
+        Ready, Role, VoiceState,
     },
     async_trait,
 };
@@ -14,12 +14,46 @@ mod api;
 mod audio;
 mod auth;
 mod bot_bridge;
+mod broadcast;
+mod cache_cleanup;
+mod capacity;
 mod commands;
+mod config;
 mod database;
+mod direct;
+mod downloads;
 mod env;
+mod fade;
+mod features;
+mod filters;
+mod gapless;
+mod guild_limit;
+mod guild_log;
+mod hooks;
+mod library;
+mod lyrics;
+mod metadata_cache;
 mod metrics;
 mod middleware;
+mod playback_state;
+mod podcast;
+mod policy;
+mod preflight;
+mod scrobble;
+mod settings_events;
+mod simulate;
+mod source;
+mod spotify;
+mod stage;
+mod stats;
+mod theme;
+mod uploads;
+mod validation;
 mod voice_manager;
+mod voice_permissions;
+mod voice_state;
+mod voice_stats;
+mod watchdog;
 mod web_api;
 
 struct Handler;
@@ -49,14 +83,9 @@ impl serenity::prelude::EventHandler for Handler {
             }
         }
 
-        // Log an invite URL with minimal required voice permissions
-        let perms = Permissions::CONNECT | Permissions::SPEAK;
+        // Log an invite URL; users can also get it with /invite
         if let Ok(app) = ctx.http.get_current_application_info().await {
-            let invite = format!(
-                "https://discord.com/api/oauth2/authorize?client_id={}&permissions={}&scope=bot%20applications.commands",
-                app.id,
-                perms.bits()
-            );
+            let invite = commands::invite::invite_url(app.id);
             info!(
                 "Invite this bot: {} (app_id={}, user_id={})",
                 invite, app.id, ready.user.id
@@ -67,25 +96,32 @@ impl serenity::prelude::EventHandler for Handler {
         if let Ok(dir) = crate::audio::resolved_download_base_dir() {
             info!("Download cache dir: {}", dir.display());
         }
-        info!("Commands: /play url:<link>, /next, /stop");
         info!(
-            "Tunables: LYRE_MIX_MODE=mono|stereo, LYRE_BITRATE=16000..192000, LYRE_PREROLL_MS=0..30000, DOWNLOAD_FOLDER=path"
+            "Commands: /help, /about, /invite, /play url:<link> [resume] [pick] | share:<token>, /queue show|share|dedupe, /boost position:<n>, /priority set|remove|list, /setup, /dj add|remove|grant|revoke|list, /approval on|off|status, /quiethours set|off|status, /feature enable|disable|reset|list, /theme show|color|emoji|footer|reset, /filter karaoke|8d|bassboost|loudnorm|show|clear|preset|save|presets|forget, /announce, /maintenance on|off|status, /next, /skipto position:<n>, /pause, /resume, /seek timestamp:<mm:ss|1h2m3s>, /stop, /volume [percent], /loop [track|queue|off], /cancel, /move from:<n> to:<n>, /block add|remove|list|keyword, /musicban add|remove|list, /mystats, /wrapped, /lastfm, /listenbrainz, /playlist import|save|load|add|list|show|delete, /podcast subscribe|unsubscribe|latest|episodes, /library search, /lyrics, /voicedebug"
+        );
+        info!(
+            "Tunables: LYRE_MIX_MODE=mono|stereo, LYRE_BITRATE=16000..192000, LYRE_PREROLL_MS=0..30000, LYRE_FADE_MS=0..3000, LYRE_STALL_SECS=N, DOWNLOAD_FOLDER=path"
         );
 
         // Register global slash commands
-        for def in [
-            commands::play::definition(),
-            commands::next::definition(),
-            commands::stop::definition(),
-        ] {
-            if let Err(e) = AppCommand::create_global_command(&ctx.http, def).await {
-                error!("failed to register global command: {e:?}");
-            }
+        if let Err(e) = commands::register(&ctx.http, None).await {
+            error!("failed to register global commands: {e:?}");
         }
 
         // Mark ready for probes once we've registered commands
         metrics::METRICS.set_ready(true);
 
+        if let Some(manager) = songbird::get(&ctx).await {
+            // Let the HTTP API reach Discord for operator announcements
+            broadcast::install(&ctx, manager.clone());
+            // Keep voice connection records in line with the calls the bot really has
+            tokio::spawn(voice_manager::reconcile_connections(manager.clone()));
+            // Hand freed voice session slots to guilds waiting for one
+            capacity::spawn_waitlist_worker(ctx.http.clone(), manager.clone());
+            // Restart tracks whose input stalls instead of sitting there silent
+            watchdog::spawn_watchdog(manager);
+        }
+
         // Start background task to process voice channel join requests from API
         let ctx_clone = ctx.clone();
         tokio::spawn(async move {
@@ -93,8 +129,129 @@ impl serenity::prelude::EventHandler for Handler {
         });
     }
 
+    async fn cache_ready(&self, ctx: SerenityContext, _guilds: Vec<GuildId>) {
+        // Requesters' voice states are cached by now, so downloads cut short by a restart can
+        // rejoin their channels
+        tokio::spawn(commands::play::resume_interrupted_downloads(ctx));
+    }
+
+    async fn guild_create(&self, ctx: SerenityContext, guild: Guild, is_new: Option<bool>) {
+        // Only guilds that just added the bot; the rest arrive on every start
+        if is_new == Some(true) && guild_limit::check_new_guild(&ctx, &guild).await {
+            commands::setup::welcome(&ctx, &guild).await;
+        }
+    }
+
+    async fn channel_update(
+        &self,
+        ctx: SerenityContext,
+        _old: Option<GuildChannel>,
+        new: GuildChannel,
+    ) {
+        voice_permissions::check(&ctx, new.guild_id).await;
+    }
+
+    async fn guild_role_update(&self, ctx: SerenityContext, _old: Option<Role>, new: Role) {
+        voice_permissions::check(&ctx, new.guild_id).await;
+    }
+
+    async fn voice_state_update(
+        &self,
+        ctx: SerenityContext,
+        old: Option<VoiceState>,
+        new: VoiceState,
+    ) {
+        if !hooks::enabled() || new.user_id == ctx.cache.current_user().id {
+            return;
+        }
+        let (Some(guild_id), Some(channel_id)) = (new.guild_id, new.channel_id) else {
+            return;
+        };
+        if old.and_then(|old| old.channel_id) == Some(channel_id) {
+            return;
+        }
+        // Only joins to the channel the bot is playing in
+        let Some(manager) = songbird::get(&ctx).await else {
+            return;
+        };
+        let Some(call_lock) = manager.get(guild_id) else {
+            return;
+        };
+        let bot_channel = call_lock.lock().await.current_channel();
+        if bot_channel.map(|c| c.0.get()) == Some(channel_id.get()) {
+            hooks::emit(
+                hooks::HookEvent::UserJoin,
+                &guild_id.to_string(),
+                serde_json::json!({
+                    "user_id": new.user_id.to_string(),
+                    "channel_id": channel_id.to_string(),
+                }),
+            );
+        }
+    }
+
     async fn interaction_create(&self, ctx: SerenityContext, interaction: Interaction) {
+        if let Interaction::Component(component) = &interaction {
+            let custom_id = component.data.custom_id.as_str();
+            if custom_id.starts_with(commands::play::RESUME_BUTTON_PREFIX) {
+                if let Err(why) = commands::play::handle_resume_button(&ctx, component).await {
+                    error!("resume button failed: {why:?}");
+                }
+            } else if custom_id.starts_with(commands::cancel::CANCEL_BUTTON_PREFIX) {
+                if let Err(why) = commands::cancel::handle_cancel_button(&ctx, component).await {
+                    error!("cancel button failed: {why:?}");
+                }
+            } else if custom_id.starts_with(commands::queue::UPVOTE_BUTTON_PREFIX) {
+                if let Err(why) = commands::queue::handle_upvote_button(&ctx, component).await {
+                    error!("upvote button failed: {why:?}");
+                }
+            } else if custom_id.starts_with(commands::stop::UNDO_BUTTON_PREFIX) {
+                if let Err(why) = commands::stop::handle_undo_button(&ctx, component).await {
+                    error!("stop undo button failed: {why:?}");
+                }
+            } else if custom_id == commands::pick::PICK_MENU_ID
+                || custom_id == commands::library::LIBRARY_MENU_ID
+            {
+                if let Err(why) = commands::pick::handle_pick_menu(&ctx, component).await {
+                    error!("playlist pick failed: {why:?}");
+                }
+            } else if custom_id.starts_with(commands::setup::SETUP_PREFIX) {
+                if let Err(why) = commands::setup::handle_component(&ctx, component).await {
+                    error!("setup wizard failed: {why:?}");
+                }
+            } else if custom_id == commands::help::HELP_MENU_ID {
+                if let Err(why) = commands::help::handle_menu(&ctx, component).await {
+                    error!("help menu failed: {why:?}");
+                }
+            } else if custom_id.starts_with(commands::approval::APPROVAL_BUTTON_PREFIX)
+                && let Err(why) = commands::approval::handle_review_button(&ctx, component).await
+            {
+                error!("approval button failed: {why:?}");
+            }
+            return;
+        }
+        if let Interaction::Modal(modal) = &interaction {
+            if modal
+                .data
+                .custom_id
+                .starts_with(commands::setup::SETUP_PREFIX)
+                && let Err(why) = commands::setup::handle_modal(&ctx, modal).await
+            {
+                error!("setup wizard failed: {why:?}");
+            }
+            return;
+        }
         if let Interaction::Command(cmd) = interaction {
+            if commands::PLAYBACK_COMMANDS.contains(&cmd.data.name.as_str()) {
+                match commands::allow_playback(&ctx, &cmd).await {
+                    Ok(true) => {}
+                    Ok(false) => return,
+                    Err(why) => {
+                        error!("music ban check failed: {why:?}");
+                        return;
+                    }
+                }
+            }
             match cmd.data.name.as_str() {
                 "play" => {
                     if let Err(why) = commands::play::handle(&ctx, &cmd).await {
@@ -106,11 +263,181 @@ impl serenity::prelude::EventHandler for Handler {
                         error!("/next failed: {why:?}");
                     }
                 }
+                "skipto" => {
+                    if let Err(why) = commands::skipto::handle(&ctx, &cmd).await {
+                        error!("/skipto failed: {why:?}");
+                    }
+                }
+                "pause" => {
+                    if let Err(why) = commands::pause::handle(&ctx, &cmd).await {
+                        error!("/pause failed: {why:?}");
+                    }
+                }
+                "resume" => {
+                    if let Err(why) = commands::resume::handle(&ctx, &cmd).await {
+                        error!("/resume failed: {why:?}");
+                    }
+                }
+                "seek" => {
+                    if let Err(why) = commands::seek::handle(&ctx, &cmd).await {
+                        error!("/seek failed: {why:?}");
+                    }
+                }
+                "volume" => {
+                    if let Err(why) = commands::volume::handle(&ctx, &cmd).await {
+                        error!("/volume failed: {why:?}");
+                    }
+                }
+                "loop" => {
+                    if let Err(why) = commands::r#loop::handle(&ctx, &cmd).await {
+                        error!("/loop failed: {why:?}");
+                    }
+                }
+                "cancel" => {
+                    if let Err(why) = commands::cancel::handle(&ctx, &cmd).await {
+                        error!("/cancel failed: {why:?}");
+                    }
+                }
+                "move" => {
+                    if let Err(why) = commands::r#move::handle(&ctx, &cmd).await {
+                        error!("/move failed: {why:?}");
+                    }
+                }
                 "stop" => {
                     if let Err(why) = commands::stop::handle(&ctx, &cmd).await {
                         error!("/stop failed: {why:?}");
                     }
                 }
+                "block" => {
+                    if let Err(why) = commands::block::handle(&ctx, &cmd).await {
+                        error!("/block failed: {why:?}");
+                    }
+                }
+                "musicban" => {
+                    if let Err(why) = commands::musicban::handle(&ctx, &cmd).await {
+                        error!("/musicban failed: {why:?}");
+                    }
+                }
+                "mystats" => {
+                    if let Err(why) = commands::mystats::handle(&ctx, &cmd).await {
+                        error!("/mystats failed: {why:?}");
+                    }
+                }
+                "wrapped" => {
+                    if let Err(why) = commands::wrapped::handle(&ctx, &cmd).await {
+                        error!("/wrapped failed: {why:?}");
+                    }
+                }
+                "lastfm" => {
+                    if let Err(why) = commands::lastfm::handle(&ctx, &cmd).await {
+                        error!("/lastfm failed: {why:?}");
+                    }
+                }
+                "listenbrainz" => {
+                    if let Err(why) = commands::listenbrainz::handle(&ctx, &cmd).await {
+                        error!("/listenbrainz failed: {why:?}");
+                    }
+                }
+                "playlist" => {
+                    if let Err(why) = commands::playlist::handle(&ctx, &cmd).await {
+                        error!("/playlist failed: {why:?}");
+                    }
+                }
+                "podcast" => {
+                    if let Err(why) = commands::podcast::handle(&ctx, &cmd).await {
+                        error!("/podcast failed: {why:?}");
+                    }
+                }
+                "library" => {
+                    if let Err(why) = commands::library::handle(&ctx, &cmd).await {
+                        error!("/library failed: {why:?}");
+                    }
+                }
+                "lyrics" => {
+                    if let Err(why) = commands::lyrics::handle(&ctx, &cmd).await {
+                        error!("/lyrics failed: {why:?}");
+                    }
+                }
+                "queue" => {
+                    if let Err(why) = commands::queue::handle(&ctx, &cmd).await {
+                        error!("/queue failed: {why:?}");
+                    }
+                }
+                "boost" => {
+                    if let Err(why) = commands::boost::handle(&ctx, &cmd).await {
+                        error!("/boost failed: {why:?}");
+                    }
+                }
+                "priority" => {
+                    if let Err(why) = commands::priority::handle(&ctx, &cmd).await {
+                        error!("/priority failed: {why:?}");
+                    }
+                }
+                "setup" => {
+                    if let Err(why) = commands::setup::handle(&ctx, &cmd).await {
+                        error!("/setup failed: {why:?}");
+                    }
+                }
+                "dj" => {
+                    if let Err(why) = commands::dj::handle(&ctx, &cmd).await {
+                        error!("/dj failed: {why:?}");
+                    }
+                }
+                "approval" => {
+                    if let Err(why) = commands::approval::handle(&ctx, &cmd).await {
+                        error!("/approval failed: {why:?}");
+                    }
+                }
+                "quiethours" => {
+                    if let Err(why) = commands::quiethours::handle(&ctx, &cmd).await {
+                        error!("/quiethours failed: {why:?}");
+                    }
+                }
+                "feature" => {
+                    if let Err(why) = commands::feature::handle(&ctx, &cmd).await {
+                        error!("/feature failed: {why:?}");
+                    }
+                }
+                "theme" => {
+                    if let Err(why) = commands::theme::handle(&ctx, &cmd).await {
+                        error!("/theme failed: {why:?}");
+                    }
+                }
+                "filter" => {
+                    if let Err(why) = commands::filter::handle(&ctx, &cmd).await {
+                        error!("/filter failed: {why:?}");
+                    }
+                }
+                "help" => {
+                    if let Err(why) = commands::help::handle(&ctx, &cmd).await {
+                        error!("/help failed: {why:?}");
+                    }
+                }
+                "voicedebug" => {
+                    if let Err(why) = commands::voicedebug::handle(&ctx, &cmd).await {
+                        error!("/voicedebug failed: {why:?}");
+                    }
+                }
+                "about" => {
+                    if let Err(why) = commands::about::handle(&ctx, &cmd).await {
+                        error!("/about failed: {why:?}");
+                    }
+                }
+                "invite" => {
+                    if let Err(why) = commands::invite::handle(&ctx, &cmd).await {
+                        error!("/invite failed: {why:?}");
+                    }
+                }
+                "announce" => {
+                    if let Err(why) = commands::announce::handle(&ctx, &cmd).await {
+                        error!("/announce failed: {why:?}");
+                    }
+                }
+                "maintenance" => {
+                    if let Err(why) = commands::maintenance::handle(&ctx, &cmd).await {
+                        error!("/maintenance failed: {why:?}");
+                    }
+                }
                 _ => {}
             }
         }
@@ -120,43 +447,43 @@ impl serenity::prelude::EventHandler for Handler {
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenvy::dotenv().ok();
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
-
-    let token = env::read_discord_token()?;
-
-    // Start background metrics scanners
-    metrics::spawn_download_size_scanner();
-
-    let intents = GatewayIntents::non_privileged() | GatewayIntents::GUILD_VOICE_STATES;
-    // Tune Songbird to reduce chance of audio hiccups under load.
-    // - preallocated_tracks: avoid runtime allocations when queueing
-    // - use_softclip(false): small (~3%) perf win; safe since we set volume <= 1.0 and play one track at a time
-    // Keep stereo mixing by default to preserve quality.
-    let voice_cfg = {
-        let mix = match std::env::var("LYRE_MIX_MODE").as_deref() {
-            Ok("mono") => MixMode::Mono,
-            _ => MixMode::Stereo,
-        };
+    config::init_logging();
+    config::spawn_sighup_listener();
 
-        VoiceConfig::default()
-            .preallocated_tracks(2)
-            .use_softclip(false)
-            .mix_mode(mix)
-            // Increase gateway timeout to handle slow connections (60 seconds for very slow networks)
-            .gateway_timeout(Some(std::time::Duration::from_secs(60)))
+    // In simulation mode there's no Discord connection, so no token is needed
+    let simulate = simulate::enabled();
+    let token = if simulate {
+        None
+    } else {
+        Some(env::read_discord_token()?)
     };
 
-    let mut client = serenity::Client::builder(token, intents)
-        .event_handler(Handler)
-        .register_songbird_from_config(voice_cfg)
-        .await?;
+    // Validate the environment up front; `lyre --check` prints the report and exits
+    let report = preflight::run(token.as_deref()).await;
+    report.log();
+    if std::env::args().any(|arg| arg == "--check") {
+        std::process::exit(if report.should_abort() { 1 } else { 0 });
+    }
+    if report.should_abort() {
+        anyhow::bail!("preflight checks failed; see the report above");
+    }
+
+    // Older versions kept every download in one folder
+    match audio::migrate_flat_cache().await {
+        Ok(0) => {}
+        Ok(moved) => info!("Moved {} cached downloads into per-source folders", moved),
+        Err(e) => tracing::warn!("Failed to reorganize the download cache: {}", e),
+    }
 
-    // Initial startup info will be logged in the ready event handler
+    // Start background metrics scanners
+    metrics::spawn_download_size_scanner();
+    metrics::spawn_process_sampler();
+    scrobble::spawn_scrobble_worker();
+    podcast::spawn_feed_refresher();
+    playback_state::spawn_sync();
+    voice_state::spawn_flusher();
+    cache_cleanup::spawn_orphan_cleanup();
+    library::spawn_indexer();
 
     // Run the HTTP server and Discord client concurrently with signal handling
     let http_bind = std::env::var("LYRE_HTTP_BIND").ok();
@@ -165,11 +492,48 @@ async fn main() -> Result<()> {
         actix_web::rt::System::new().block_on(web_api::run_http(http_bind))
     });
 
-    let discord_task = tokio::spawn(async move {
-        if let Err(why) = client.start_autosharded().await {
-            error!("Client error: {why:?}");
+    let discord_task = match token {
+        Some(token) => {
+            let intents = GatewayIntents::non_privileged() | GatewayIntents::GUILD_VOICE_STATES;
+            // Tune Songbird to reduce chance of audio hiccups under load.
+            // - preallocated_tracks: avoid runtime allocations when queueing
+            // - softclip stays on: guilds may allow volumes up to 2.0, which would otherwise clip harshly
+            // Keep stereo mixing by default to preserve quality.
+            let voice_cfg = {
+                let mix = match std::env::var("LYRE_MIX_MODE").as_deref() {
+                    Ok("mono") => MixMode::Mono,
+                    _ => MixMode::Stereo,
+                };
+
+                VoiceConfig::default()
+                    .preallocated_tracks(2)
+                    .mix_mode(mix)
+                    // Increase gateway timeout to handle slow connections (60 seconds for very slow networks)
+                    .gateway_timeout(Some(std::time::Duration::from_secs(60)))
+            };
+
+            let mut client = serenity::Client::builder(token, intents)
+                .event_handler(Handler)
+                .register_songbird_from_config(voice_cfg)
+                .await?;
+
+            // Let the HTTP API report on the shards
+            broadcast::install_shards(client.shard_manager.clone());
+
+            // Initial startup info will be logged in the ready event handler
+
+            tokio::spawn(async move {
+                if let Err(why) = client.start_autosharded().await {
+                    error!("Client error: {why:?}");
+                }
+            })
         }
-    });
+        None => {
+            simulate::spawn_voice_layer();
+            metrics::METRICS.set_ready(true);
+            tokio::spawn(std::future::pending::<()>())
+        }
+    };
 
     // Set up signal handling
     let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
@@ -190,6 +554,9 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Don't lose the last few seconds of buffered session state
+    voice_state::flush();
+
     info!("Shutdown complete");
     Ok(())
 }
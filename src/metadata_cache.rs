@@ -0,0 +1,122 @@
+//! yt-dlp metadata kept in the song cache, so a URL is looked up with one `--dump-json` call
+//! and `/play`, the queue API and the download that names the cached file all share the
+//! answer until it goes stale.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+
+use crate::audio::{self, TrackMetadata};
+use crate::database::establish_connection;
+use crate::database::models::{CurrentQueue, SongCache};
+
+/// Hours looked-up metadata is reused before yt-dlp is asked again
+const TTL_HOURS_ENV: &str = "LYRE_METADATA_TTL_HOURS";
+const DEFAULT_TTL_HOURS: i64 = 24;
+/// Most lookups a playlist prefetch runs at once
+const PREFETCH_CONCURRENCY: usize = 4;
+
+/// One lock per URL being looked up, so `/play` and its download don't both run yt-dlp
+static IN_FLIGHT: Lazy<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn ttl() -> Duration {
+    let hours = crate::config::var(TTL_HOURS_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|h| *h > 0)
+        .unwrap_or(DEFAULT_TTL_HOURS);
+    Duration::hours(hours)
+}
+
+/// `url`'s metadata from the song cache if it's fresh, otherwise from yt-dlp (and cached)
+pub async fn lookup(url: &str) -> Result<TrackMetadata> {
+    if let Some(metadata) = cached(url) {
+        return Ok(metadata);
+    }
+    let lock = IN_FLIGHT
+        .lock()
+        .map(|mut in_flight| in_flight.entry(url.to_string()).or_default().clone())
+        .unwrap_or_default();
+    let _looking_up = lock.lock().await;
+    // Someone else may have looked it up while this waited
+    if let Some(metadata) = cached(url) {
+        return Ok(metadata);
+    }
+    let result = audio::ytdlp_extract_metadata(url).await;
+    if let Ok(mut in_flight) = IN_FLIGHT.lock() {
+        in_flight.remove(url);
+    }
+    let metadata = result?;
+    store(url, &metadata);
+    Ok(metadata)
+}
+
+/// Look up the metadata of a playlist's entries in the background, a few at a time, so it's
+/// cached by the time each one is queued. Entries of `guild_id`'s queue still without a title
+/// get theirs as the answers arrive.
+pub fn prefetch(guild_id: String, urls: Vec<String>) {
+    tokio::spawn(async move {
+        futures_util::stream::iter(urls)
+            .for_each_concurrent(PREFETCH_CONCURRENCY, |url| {
+                let guild_id = guild_id.clone();
+                async move {
+                    let metadata = match crate::source::extract_metadata(&url).await {
+                        Ok(metadata) => metadata,
+                        Err(e) => {
+                            tracing::debug!("Couldn't prefetch metadata for {}: {}", url, e);
+                            return;
+                        }
+                    };
+                    if let Err(e) = CurrentQueue::fill_in_metadata(
+                        &mut establish_connection(),
+                        &guild_id,
+                        &url,
+                        &metadata.title,
+                        metadata.duration_secs(),
+                    ) {
+                        tracing::warn!("Failed to fill in queued track {}: {}", url, e);
+                    }
+                }
+            })
+            .await;
+    });
+}
+
+/// `url`'s metadata if the song cache has it fresh, without ever running yt-dlp
+pub fn cached(url: &str) -> Option<TrackMetadata> {
+    let fresh_after = Utc::now().naive_utc() - ttl();
+    let json = match SongCache::find_metadata(&mut establish_connection(), url, fresh_after) {
+        Ok(json) => json?,
+        Err(e) => {
+            tracing::warn!("Failed to read cached metadata for {}: {}", url, e);
+            return None;
+        }
+    };
+    serde_json::from_str(&json)
+        .inspect_err(|e| tracing::debug!("Ignoring unreadable cached metadata for {}: {}", url, e))
+        .ok()
+}
+
+fn store(url: &str, metadata: &TrackMetadata) {
+    let json = match serde_json::to_string(metadata) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::warn!("Failed to serialize metadata for {}: {}", url, e);
+            return;
+        }
+    };
+    if let Err(e) = SongCache::store_metadata(
+        &mut establish_connection(),
+        url,
+        &metadata.title,
+        metadata.duration_secs(),
+        &json,
+    ) {
+        tracing::warn!("Failed to cache metadata for {}: {}", url, e);
+    }
+}
@@ -1,24 +1,72 @@
 use std::{
-    sync::Arc,
+    collections::{BTreeMap, HashSet},
     sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
 use once_cell::sync::Lazy;
+use serenity::all::GuildId;
+use serenity::async_trait;
+use songbird::{CoreEvent, Event, EventContext, EventHandler as VoiceEventHandler, Songbird};
 
 use crate::audio;
 
 pub static METRICS: Lazy<Arc<Metrics>> = Lazy::new(|| Arc::new(Metrics::new()));
 
+/// Upper bounds (seconds) of the HTTP latency histogram buckets; `+Inf` is implied.
+pub const HTTP_LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct HttpRouteStats {
+    /// Request count keyed by HTTP status code
+    pub statuses: BTreeMap<u16, u64>,
+    /// Non-cumulative bucket counts aligned with `HTTP_LATENCY_BUCKETS`
+    pub buckets: [u64; HTTP_LATENCY_BUCKETS.len()],
+    pub sum_secs: f64,
+    pub count: u64,
+}
+
+/// Resource usage of the bot process, sampled by `spawn_process_sampler`. Fields read from
+/// `/proc` stay `None` on platforms without it.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessStats {
+    pub rss_bytes: Option<u64>,
+    pub cpu_seconds: Option<f64>,
+    /// Share of one core used since the previous sample (1.0 = a full core)
+    pub cpu_usage: Option<f64>,
+    pub open_fds: Option<u64>,
+    pub tokio_workers: usize,
+    pub tokio_alive_tasks: usize,
+    pub tokio_global_queue_depth: usize,
+    /// Share of the last sample interval each worker spent polling tasks
+    pub tokio_worker_busy_ratio: Vec<f64>,
+    /// Workers busy for (nearly) the whole interval, which usually means a task is blocking
+    /// the thread instead of yielding, e.g. a synchronous Diesel query
+    pub tokio_blocked_workers: usize,
+    /// Worst and average lateness of the scheduler lag probe over the last interval
+    pub scheduler_lag_max_secs: f64,
+    pub scheduler_lag_mean_secs: f64,
+}
+
 #[derive(Debug)]
 pub struct Metrics {
     start: Instant,
     ready: AtomicBool,
-    active_voice_calls: AtomicUsize,
-    connected_guilds: AtomicUsize,
+    /// Maintenance mode: refusing new playback while current tracks finish
+    draining: AtomicBool,
+    /// Guilds whose voice driver is connected, kept by [`watch_voice_connection`]
+    voice_connected: Mutex<HashSet<GuildId>>,
     total_queue_len: AtomicUsize,
     downloads_bytes: AtomicU64,
     downloads_files: AtomicU64,
+    /// Cached files and bytes per source folder of the download cache
+    download_sources: Mutex<BTreeMap<String, DownloadUsage>>,
+    /// Keyed by (method, route pattern)
+    http_routes: Mutex<BTreeMap<(String, String), HttpRouteStats>>,
+    process: Mutex<ProcessStats>,
 }
 
 impl Metrics {
@@ -26,11 +74,14 @@ impl Metrics {
         Self {
             start: Instant::now(),
             ready: AtomicBool::new(false),
-            active_voice_calls: AtomicUsize::new(0),
-            connected_guilds: AtomicUsize::new(0),
+            draining: AtomicBool::new(false),
+            voice_connected: Mutex::new(HashSet::new()),
             total_queue_len: AtomicUsize::new(0),
             downloads_bytes: AtomicU64::new(0),
             downloads_files: AtomicU64::new(0),
+            download_sources: Mutex::new(BTreeMap::new()),
+            http_routes: Mutex::new(BTreeMap::new()),
+            process: Mutex::new(ProcessStats::default()),
         }
     }
 
@@ -41,21 +92,21 @@ impl Metrics {
         self.ready.load(Ordering::Relaxed)
     }
 
-    pub fn inc_connections(&self) {
-        self.active_voice_calls.fetch_add(1, Ordering::Relaxed);
-        self.connected_guilds.fetch_add(1, Ordering::Relaxed);
+    pub fn set_draining(&self, v: bool) {
+        self.draining.store(v, Ordering::Relaxed);
     }
-    pub fn dec_connections(&self) {
-        let _ = self
-            .active_voice_calls
-            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
-                Some(x.saturating_sub(1))
-            });
-        let _ = self
-            .connected_guilds
-            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
-                Some(x.saturating_sub(1))
-            });
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    fn set_voice_connected(&self, guild_id: GuildId, connected: bool) {
+        if let Ok(mut guilds) = self.voice_connected.lock() {
+            if connected {
+                guilds.insert(guild_id);
+            } else {
+                guilds.remove(&guild_id);
+            }
+        }
     }
 
     pub fn inc_queue(&self, n: usize) {
@@ -69,56 +120,162 @@ impl Metrics {
             });
     }
 
-    pub fn set_downloads(&self, files: u64, bytes: u64) {
+    pub fn set_downloads(&self, files: u64, bytes: u64, sources: BTreeMap<String, DownloadUsage>) {
         self.downloads_files.store(files, Ordering::Relaxed);
         self.downloads_bytes.store(bytes, Ordering::Relaxed);
+        if let Ok(mut current) = self.download_sources.lock() {
+            *current = sources;
+        }
+    }
+
+    pub fn set_process(&self, stats: ProcessStats) {
+        if let Ok(mut process) = self.process.lock() {
+            *process = stats;
+        }
+    }
+
+    pub fn observe_http_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if let Ok(mut routes) = self.http_routes.lock() {
+            let stats = routes
+                .entry((method.to_string(), route.to_string()))
+                .or_default();
+            *stats.statuses.entry(status).or_insert(0) += 1;
+            if let Some(idx) = HTTP_LATENCY_BUCKETS.iter().position(|le| secs <= *le) {
+                stats.buckets[idx] += 1;
+            }
+            stats.sum_secs += secs;
+            stats.count += 1;
+        }
     }
 
     pub fn snapshot(&self) -> MetricsSnapshot {
+        // The bot holds at most one call per guild, so the two gauges agree
+        let voice_connected = self
+            .voice_connected
+            .lock()
+            .map(|guilds| guilds.len())
+            .unwrap_or(0);
         MetricsSnapshot {
             uptime_secs: self.start.elapsed().as_secs(),
             ready: self.is_ready(),
-            active_voice_calls: self.active_voice_calls.load(Ordering::Relaxed),
-            connected_guilds: self.connected_guilds.load(Ordering::Relaxed),
+            draining: self.is_draining(),
+            active_voice_calls: voice_connected,
+            connected_guilds: voice_connected,
             total_queue_len: self.total_queue_len.load(Ordering::Relaxed),
             downloads_bytes: self.downloads_bytes.load(Ordering::Relaxed),
             downloads_files: self.downloads_files.load(Ordering::Relaxed),
+            download_sources: self
+                .download_sources
+                .lock()
+                .map(|sources| sources.clone())
+                .unwrap_or_default(),
+            http_routes: self
+                .http_routes
+                .lock()
+                .map(|routes| routes.clone())
+                .unwrap_or_default(),
+            process: self
+                .process
+                .lock()
+                .map(|process| process.clone())
+                .unwrap_or_default(),
         }
     }
 }
 
+/// What one source folder of the download cache holds
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DownloadUsage {
+    pub files: u64,
+    pub bytes: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct MetricsSnapshot {
     pub uptime_secs: u64,
     pub ready: bool,
+    pub draining: bool,
     pub active_voice_calls: usize,
     pub connected_guilds: usize,
     pub total_queue_len: usize,
     pub downloads_bytes: u64,
     pub downloads_files: u64,
+    pub download_sources: BTreeMap<String, DownloadUsage>,
+    pub http_routes: BTreeMap<(String, String), HttpRouteStats>,
+    pub process: ProcessStats,
+}
+
+/// Count the guild's voice connection from its driver's connect, reconnect and disconnect
+/// events, so the gauges follow joins however they happen and notice Discord dropping the bot.
+/// Call before joining: the handlers go on the guild's call as it's created, so they see the
+/// first connect. A call that already exists is being watched.
+pub async fn watch_voice_connection(manager: &Songbird, guild_id: GuildId) {
+    if manager.get(guild_id).is_some() {
+        return;
+    }
+    let call_lock = manager.get_or_insert(guild_id);
+    let mut call = call_lock.lock().await;
+    for event in [
+        CoreEvent::DriverConnect,
+        CoreEvent::DriverReconnect,
+        CoreEvent::DriverDisconnect,
+    ] {
+        call.add_global_event(Event::Core(event), ConnectionWatcher { guild_id });
+    }
+}
+
+struct ConnectionWatcher {
+    guild_id: GuildId,
+}
+
+#[async_trait]
+impl VoiceEventHandler for ConnectionWatcher {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        match ctx {
+            EventContext::DriverConnect(_) | EventContext::DriverReconnect(_) => {
+                METRICS.set_voice_connected(self.guild_id, true)
+            }
+            EventContext::DriverDisconnect(_) => METRICS.set_voice_connected(self.guild_id, false),
+            _ => {}
+        }
+        None
+    }
 }
 
 pub fn spawn_download_size_scanner() {
-    // Periodically scan DOWNLOAD_FOLDER or cache fallback for file count and total size.
+    // Periodically scan DOWNLOAD_FOLDER or cache fallback for file count and total size, overall
+    // and per source folder (`youtube/`, `mixcloud/`, ...).
     tokio::spawn(async {
         loop {
             let mut files: u64 = 0;
             let mut bytes: u64 = 0;
+            let mut sources: BTreeMap<String, DownloadUsage> = BTreeMap::new();
             if let Ok(root) = audio::resolved_download_base_dir() {
-                // Iterative DFS to avoid recursive async
-                let mut stack = vec![root];
-                while let Some(dir) = stack.pop() {
+                // Iterative DFS to avoid recursive async; each folder carries the source folder
+                // it's under, if any
+                let mut stack = vec![(root, None::<String>)];
+                while let Some((dir, source)) = stack.pop() {
                     if let Ok(mut rd) = tokio::fs::read_dir(&dir).await {
                         while let Ok(Some(ent)) = rd.next_entry().await {
                             match ent.file_type().await {
                                 Ok(ft) if ft.is_file() => {
+                                    let len = ent.metadata().await.map(|m| m.len()).unwrap_or(0);
                                     files += 1;
-                                    if let Ok(meta) = ent.metadata().await {
-                                        bytes = bytes.saturating_add(meta.len());
+                                    bytes = bytes.saturating_add(len);
+                                    if let Some(source) = &source {
+                                        let usage = sources.entry(source.clone()).or_default();
+                                        usage.files += 1;
+                                        usage.bytes = usage.bytes.saturating_add(len);
                                     }
                                 }
                                 Ok(ft) if ft.is_dir() => {
-                                    stack.push(ent.path());
+                                    let name = ent.file_name().to_string_lossy().to_string();
+                                    // Hidden folders (running downloads) aren't a source
+                                    let source = source
+                                        .clone()
+                                        .or_else(|| (!name.starts_with('.')).then_some(name));
+                                    stack.push((ent.path(), source));
                                 }
                                 _ => {}
                             }
@@ -126,8 +283,135 @@ pub fn spawn_download_size_scanner() {
                     }
                 }
             }
-            METRICS.set_downloads(files, bytes);
+            METRICS.set_downloads(files, bytes, sources);
             tokio::time::sleep(Duration::from_secs(30)).await;
         }
     });
 }
+
+/// Kernel clock ticks per second used by `/proc/<pid>/stat` times; 100 on every mainstream
+/// Linux architecture
+pub const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+const PROCESS_SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+/// A worker busy for at least this share of a sample interval is counted as blocked
+const BLOCKED_WORKER_BUSY_RATIO: f64 = 0.95;
+/// How often the lag probe asks the runtime to wake it
+const LAG_PROBE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Lag probe results since the sampler last read them, in microseconds
+static LAG_MAX_US: AtomicU64 = AtomicU64::new(0);
+static LAG_SUM_US: AtomicU64 = AtomicU64::new(0);
+static LAG_SAMPLES: AtomicU64 = AtomicU64::new(0);
+
+/// Repeatedly sleep `LAG_PROBE_INTERVAL` and record how late each wake-up is. Steady lag
+/// means the runtime's workers are blocked by synchronous work.
+fn spawn_scheduler_lag_probe() {
+    tokio::spawn(async {
+        loop {
+            let asked_at = Instant::now();
+            tokio::time::sleep(LAG_PROBE_INTERVAL).await;
+            let late_us = asked_at
+                .elapsed()
+                .saturating_sub(LAG_PROBE_INTERVAL)
+                .as_micros() as u64;
+            LAG_MAX_US.fetch_max(late_us, Ordering::Relaxed);
+            LAG_SUM_US.fetch_add(late_us, Ordering::Relaxed);
+            LAG_SAMPLES.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+}
+
+pub fn spawn_process_sampler() {
+    // Periodically sample our own memory, CPU, file descriptors and Tokio runtime load, so
+    // operators can size containers without a sidecar exporter.
+    spawn_scheduler_lag_probe();
+    tokio::spawn(async {
+        let mut previous: Option<(f64, Instant)> = None;
+        let mut busy_before: Vec<Duration> = Vec::new();
+        loop {
+            let cpu_seconds = read_cpu_seconds().await;
+            let now = Instant::now();
+            let cpu_usage = match (cpu_seconds, previous) {
+                (Some(cpu), Some((prev_cpu, prev_at))) => {
+                    let wall = now.duration_since(prev_at).as_secs_f64();
+                    (wall > 0.0).then(|| ((cpu - prev_cpu) / wall).max(0.0))
+                }
+                _ => None,
+            };
+            previous = cpu_seconds.map(|cpu| (cpu, now));
+
+            let runtime = tokio::runtime::Handle::current().metrics();
+            let busy_now: Vec<Duration> = (0..runtime.num_workers())
+                .map(|w| runtime.worker_total_busy_duration(w))
+                .collect();
+            let worker_busy_ratio: Vec<f64> = busy_now
+                .iter()
+                .zip(busy_before.iter())
+                .map(|(now, before)| {
+                    now.saturating_sub(*before).as_secs_f64()
+                        / PROCESS_SAMPLE_INTERVAL.as_secs_f64()
+                })
+                .collect();
+            busy_before = busy_now;
+
+            let lag_samples = LAG_SAMPLES.swap(0, Ordering::Relaxed);
+            let lag_sum_us = LAG_SUM_US.swap(0, Ordering::Relaxed);
+            let lag_max_us = LAG_MAX_US.swap(0, Ordering::Relaxed);
+            METRICS.set_process(ProcessStats {
+                rss_bytes: read_rss_bytes().await,
+                cpu_seconds,
+                cpu_usage,
+                open_fds: count_open_fds().await,
+                tokio_workers: runtime.num_workers(),
+                tokio_alive_tasks: runtime.num_alive_tasks(),
+                tokio_global_queue_depth: runtime.global_queue_depth(),
+                tokio_blocked_workers: worker_busy_ratio
+                    .iter()
+                    .filter(|ratio| **ratio >= BLOCKED_WORKER_BUSY_RATIO)
+                    .count(),
+                tokio_worker_busy_ratio: worker_busy_ratio,
+                scheduler_lag_max_secs: lag_max_us as f64 / 1_000_000.0,
+                scheduler_lag_mean_secs: if lag_samples > 0 {
+                    lag_sum_us as f64 / lag_samples as f64 / 1_000_000.0
+                } else {
+                    0.0
+                },
+            });
+            tokio::time::sleep(PROCESS_SAMPLE_INTERVAL).await;
+        }
+    });
+}
+
+async fn read_rss_bytes() -> Option<u64> {
+    let status = tokio::fs::read_to_string("/proc/self/status").await.ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+async fn read_cpu_seconds() -> Option<f64> {
+    let stat = tokio::fs::read_to_string("/proc/self/stat").await.ok()?;
+    // The command name may contain spaces, so count fields from after its closing paren:
+    // state is field 3, utime and stime are fields 14 and 15
+    let fields: Vec<&str> = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .collect();
+    let utime = fields.get(11)?.parse::<u64>().ok()?;
+    let stime = fields.get(12)?.parse::<u64>().ok()?;
+    Some((utime + stime) as f64 / CLOCK_TICKS_PER_SEC)
+}
+
+async fn count_open_fds() -> Option<u64> {
+    let mut entries = tokio::fs::read_dir("/proc/self/fd").await.ok()?;
+    let mut count = 0;
+    while let Ok(Some(_)) = entries.next_entry().await {
+        count += 1;
+    }
+    Some(count)
+}
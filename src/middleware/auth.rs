@@ -8,7 +8,9 @@ use std::{
     rc::Rc,
 };
 
-use crate::auth::{AuthenticatedUser, get_user_guilds, validate_discord_token};
+use crate::api::error::ApiError;
+use crate::api::health::bears_metrics_token;
+use crate::auth::{AuthenticatedUser, RateLimited, authenticate, demo_user_for};
 
 pub struct AuthMiddleware;
 
@@ -53,7 +55,9 @@ where
         Box::pin(async move {
             // Skip authentication for certain paths
             let path = req.path();
-            if should_skip_auth(path) {
+            if should_skip_auth(path)
+                || (path == "/k8s/metrics" && bears_metrics_token(req.headers()))
+            {
                 return service.call(req).await;
             }
 
@@ -67,19 +71,22 @@ where
                             req.extensions_mut().insert(user);
                             service.call(req).await
                         }
-                        Err(e) => {
-                            tracing::warn!("Token validation failed: {}", e);
-                            Err(actix_web::error::ErrorUnauthorized(
-                                "Invalid or expired token",
-                            ))
-                        }
+                        Err(e) => match e.downcast_ref::<RateLimited>() {
+                            // The token may be fine; Discord just won't say so right now
+                            Some(limited) => Err(ApiError::from(limited).into()),
+                            None => {
+                                tracing::warn!("Token validation failed: {}", e);
+                                Err(
+                                    ApiError::InvalidToken("Invalid or expired token".to_string())
+                                        .into(),
+                                )
+                            }
+                        },
                     }
                 }
                 None => {
                     tracing::warn!("No authorization token found in request to {}", path);
-                    Err(actix_web::error::ErrorUnauthorized(
-                        "Missing authorization token",
-                    ))
+                    Err(ApiError::Unauthorized("Missing authorization token".to_string()).into())
                 }
             }
         })
@@ -90,11 +97,16 @@ fn should_skip_auth(path: &str) -> bool {
     // Skip authentication for these paths
     path.starts_with("/static")
         || path.starts_with("/auth")
+        // Only the probes; metrics need an operator or the scrape token
+        || path == "/k8s/livez"
+        || path == "/k8s/readyz"
         || path.starts_with("/api/health")
         || path.starts_with("/api/livez")
         || path.starts_with("/api/readyz")
         || path.starts_with("/api/dev/test-token")
         || path.starts_with("/api/auth/validate")
+        // Signed with the hook's own secret instead
+        || path.starts_with("/api/hooks/")
         || path == "/"
         || path == "/favicon.ico"
 }
@@ -108,15 +120,18 @@ fn extract_token_from_request(req: &ServiceRequest) -> Option<String> {
         .map(|s| s.to_string())
 }
 
-async fn validate_token_and_get_user(
-    token: &str,
-) -> Result<AuthenticatedUser, Box<dyn std::error::Error>> {
-    // Validate real Discord token
-    let user = validate_discord_token(token).await.map_err(|e| {
-        tracing::warn!("Discord token validation error: {}", e);
-        e
-    })?;
-    let guilds = get_user_guilds(token).await?;
+async fn validate_token_and_get_user(token: &str) -> anyhow::Result<AuthenticatedUser> {
+    // Without Discord there's nothing to validate against; simulation mode accepts demo tokens
+    if crate::simulate::enabled() {
+        return if token.starts_with("demo_") {
+            Ok(demo_user_for(token))
+        } else {
+            Err(anyhow::anyhow!(
+                "only demo_ tokens are accepted in simulation mode"
+            ))
+        };
+    }
 
-    Ok(AuthenticatedUser { user, guilds })
+    // Validate real Discord token; users and guilds are cached for a few minutes
+    authenticate(token).await
 }
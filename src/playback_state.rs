@@ -0,0 +1,259 @@
+//! What each guild is playing, kept in memory by the playback subsystem so API reads can answer
+//! without locking the Songbird call. The position isn't stored as such: it's worked out from
+//! when the track started, how long it has been paused and how far it has been seeked. Changes
+//! are copied to the `playback_state` table every few seconds, and loop modes survive restarts
+//! from there.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+
+use crate::database::establish_connection;
+use crate::database::models::PlaybackStateRecord;
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+static STATES: Lazy<Mutex<HashMap<String, GuildPlayback>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Guilds changed since the last sync
+static DIRTY: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    #[default]
+    Off,
+    /// Repeat the playing track
+    Track,
+    /// Requeue each track once it has played
+    Queue,
+}
+
+impl LoopMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LoopMode::Off => "off",
+            LoopMode::Track => "track",
+            LoopMode::Queue => "queue",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "off" => Some(LoopMode::Off),
+            "track" => Some(LoopMode::Track),
+            "queue" => Some(LoopMode::Queue),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NowPlaying {
+    pub track_uuid: String,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub duration: Option<i32>,
+    pub started_at: NaiveDateTime,
+    /// When the current pause began
+    pub paused_at: Option<NaiveDateTime>,
+    /// Length of earlier pauses, in total
+    pub paused_secs: i64,
+    /// Net jump from seeking, forwards or backwards
+    pub seek_offset_secs: i64,
+}
+
+impl NowPlaying {
+    pub fn paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// How far into the track playback is at `now`
+    pub fn position_secs(&self, now: NaiveDateTime) -> u64 {
+        let current_pause = self
+            .paused_at
+            .map(|paused_at| (now - paused_at).num_seconds())
+            .unwrap_or(0);
+        let played = (now - self.started_at).num_seconds() - self.paused_secs - current_pause
+            + self.seek_offset_secs;
+        played.max(0) as u64
+    }
+}
+
+#[derive(Debug, Default)]
+struct GuildPlayback {
+    now_playing: Option<NowPlaying>,
+    loop_mode: LoopMode,
+}
+
+fn update(guild_id: &str, change: impl FnOnce(&mut GuildPlayback)) {
+    if let Ok(mut states) = STATES.lock() {
+        change(states.entry(guild_id.to_string()).or_default());
+    }
+    if let Ok(mut dirty) = DIRTY.lock() {
+        dirty.insert(guild_id.to_string());
+    }
+}
+
+/// Note that `track_uuid` started playing; a track resuming after a pause is left as it is
+pub fn track_started(
+    guild_id: &str,
+    track_uuid: &str,
+    title: Option<&str>,
+    url: Option<&str>,
+    duration: Option<i32>,
+) {
+    update(guild_id, |state| {
+        if state
+            .now_playing
+            .as_ref()
+            .is_some_and(|playing| playing.track_uuid == track_uuid)
+        {
+            return;
+        }
+        state.now_playing = Some(NowPlaying {
+            track_uuid: track_uuid.to_string(),
+            title: title.map(str::to_string),
+            url: url.map(str::to_string),
+            duration,
+            started_at: Utc::now().naive_utc(),
+            paused_at: None,
+            paused_secs: 0,
+            seek_offset_secs: 0,
+        });
+    });
+}
+
+/// Forget the playing track once `track_uuid` ends; a track that was never started (or was
+/// already replaced) leaves the state alone
+pub fn track_ended(guild_id: &str, track_uuid: &str) {
+    update(guild_id, |state| {
+        if state
+            .now_playing
+            .as_ref()
+            .is_some_and(|playing| playing.track_uuid == track_uuid)
+        {
+            state.now_playing = None;
+        }
+    });
+}
+
+/// Line the playing track up with what the player reports after a pause, resume or seek
+pub fn set_position(guild_id: &str, position: Duration, paused: bool) {
+    let now = Utc::now().naive_utc();
+    update(guild_id, |state| {
+        let Some(playing) = state.now_playing.as_mut() else {
+            return;
+        };
+        match (playing.paused_at, paused) {
+            (Some(paused_at), false) => {
+                playing.paused_secs += (now - paused_at).num_seconds();
+                playing.paused_at = None;
+            }
+            (None, true) => playing.paused_at = Some(now),
+            _ => {}
+        }
+        playing.seek_offset_secs += position.as_secs() as i64 - playing.position_secs(now) as i64;
+    });
+}
+
+/// The guild's playing track, if any
+pub fn now_playing(guild_id: &str) -> Option<NowPlaying> {
+    STATES
+        .lock()
+        .ok()?
+        .get(guild_id)
+        .and_then(|state| state.now_playing.clone())
+}
+
+pub fn loop_mode(guild_id: &str) -> LoopMode {
+    STATES
+        .lock()
+        .ok()
+        .and_then(|states| states.get(guild_id).map(|state| state.loop_mode))
+        .unwrap_or_default()
+}
+
+/// Change what the guild repeats; it's saved with the rest of the state on the next sync
+pub fn set_loop_mode(guild_id: &str, mode: LoopMode) {
+    update(guild_id, |state| state.loop_mode = mode);
+}
+
+/// Restore loop modes saved before a restart, then copy changes to the database every few
+/// seconds. Nothing is playing after a restart, so saved tracks are dropped.
+pub fn spawn_sync() {
+    tokio::spawn(async {
+        match PlaybackStateRecord::all(&mut establish_connection()) {
+            Ok(records) => {
+                for record in records {
+                    let loop_mode = LoopMode::from_key(&record.loop_mode).unwrap_or_default();
+                    update(&record.guild_id, |state| state.loop_mode = loop_mode);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to restore playback state: {}", e),
+        }
+
+        loop {
+            sync();
+            tokio::time::sleep(SYNC_INTERVAL).await;
+        }
+    });
+}
+
+fn sync() {
+    let guilds: Vec<String> = match DIRTY.lock() {
+        Ok(mut dirty) => dirty.drain().collect(),
+        Err(_) => return,
+    };
+    if guilds.is_empty() {
+        return;
+    }
+    let mut db_conn = establish_connection();
+    for guild_id in guilds {
+        let record = {
+            let Ok(mut states) = STATES.lock() else {
+                return;
+            };
+            match states.get(&guild_id) {
+                Some(state) if state.now_playing.is_some() || state.loop_mode != LoopMode::Off => {
+                    Some(to_record(&guild_id, state))
+                }
+                // Idle guilds with nothing worth keeping aren't stored
+                _ => {
+                    states.remove(&guild_id);
+                    None
+                }
+            }
+        };
+        let result = match &record {
+            Some(record) => PlaybackStateRecord::save(&mut db_conn, record),
+            None => PlaybackStateRecord::delete(&mut db_conn, &guild_id),
+        };
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to sync playback state for guild {}: {}",
+                guild_id,
+                e
+            );
+        }
+    }
+}
+
+fn to_record(guild_id: &str, state: &GuildPlayback) -> PlaybackStateRecord {
+    let playing = state.now_playing.as_ref();
+    PlaybackStateRecord {
+        guild_id: guild_id.to_string(),
+        track_uuid: playing.map(|p| p.track_uuid.clone()),
+        title: playing.and_then(|p| p.title.clone()),
+        url: playing.and_then(|p| p.url.clone()),
+        duration: playing.and_then(|p| p.duration),
+        started_at: playing.map(|p| p.started_at),
+        paused_at: playing.and_then(|p| p.paused_at),
+        paused_secs: playing.map_or(0, |p| p.paused_secs as i32),
+        seek_offset_secs: playing.map_or(0, |p| p.seek_offset_secs as i32),
+        loop_mode: state.loop_mode.as_str().to_string(),
+        updated_at: Utc::now().naive_utc(),
+    }
+}
@@ -0,0 +1,80 @@
+//! Podcast feeds: reading RSS feeds into the `podcast_episodes` cache and keeping subscribed
+//! feeds fresh in the background.
+
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::DateTime;
+
+use crate::audio::ytdlp_extract_feed;
+use crate::database::establish_connection;
+use crate::database::models::podcast::NewPodcastEpisode;
+use crate::database::models::{PodcastEpisode, PodcastSubscription};
+use crate::validation::validate_media_url;
+
+/// How often subscribed feeds are re-read for new episodes
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Read a feed and store any new episodes; returns the feed title and how many were new
+pub async fn refresh_feed(feed_url: &str) -> Result<(String, usize)> {
+    let feed = ytdlp_extract_feed(feed_url).await?;
+    let episodes: Vec<NewPodcastEpisode> = feed
+        .entries
+        .into_iter()
+        // Enclosures are played through yt-dlp like any other link, so hold them to the same rules
+        .filter(|entry| validate_media_url(&entry.url).is_ok())
+        .map(|entry| NewPodcastEpisode {
+            feed_url: feed_url.to_string(),
+            title: entry
+                .title
+                .filter(|t| !t.trim().is_empty())
+                .unwrap_or_else(|| "Untitled episode".to_string()),
+            duration: entry.duration.map(|d| d.round() as i32),
+            published_at: entry
+                .timestamp
+                .and_then(|ts| DateTime::from_timestamp(ts, 0))
+                .map(|t| t.naive_utc()),
+            url: entry.url,
+        })
+        .collect();
+    if episodes.is_empty() {
+        return Err(anyhow!("no playable episodes found in feed"));
+    }
+
+    let title = feed
+        .title
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| feed_url.to_string());
+    let mut conn = establish_connection();
+    let new = PodcastEpisode::insert_new(&mut conn, &episodes)?;
+    Ok((title, new))
+}
+
+/// Periodically re-read every subscribed feed so `/podcast latest` sees new episodes
+pub fn spawn_feed_refresher() {
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+            let feeds = {
+                let mut conn = establish_connection();
+                PodcastSubscription::distinct_feeds(&mut conn)
+            };
+            let feeds = match feeds {
+                Ok(feeds) => feeds,
+                Err(e) => {
+                    tracing::warn!("Failed to load podcast subscriptions: {}", e);
+                    continue;
+                }
+            };
+            for feed_url in feeds {
+                match refresh_feed(&feed_url).await {
+                    Ok((_, new)) if new > 0 => {
+                        tracing::info!("Found {} new episode(s) in {}", new, feed_url)
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Failed to refresh podcast feed {}: {}", feed_url, e),
+                }
+            }
+        }
+    });
+}
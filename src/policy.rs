@@ -0,0 +1,859 @@
+use chrono::{DateTime, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use diesel::SqliteConnection;
+use serenity::all::{GuildId, Member, RoleId, UserId};
+use thiserror::Error;
+use url::Url;
+
+use crate::audio::TrackMetadata;
+use crate::database::establish_connection;
+use crate::database::models::{BlockedTrack, DjGrant, GuildSettings, MusicBan, Upload};
+use crate::validation::validate_media_url;
+
+/// Comma-separated list of media hosts allowed in every guild (e.g. `youtube.com,youtu.be`).
+/// Unset or empty means any host is allowed.
+const ALLOWED_HOSTS_ENV: &str = "LYRE_ALLOWED_HOSTS";
+/// Comma-separated override for the title keywords the explicit-content filter rejects
+const EXPLICIT_KEYWORDS_ENV: &str = "LYRE_EXPLICIT_KEYWORDS";
+/// Longest track, in minutes, accepted from music sources. Unset means no limit.
+const MAX_DURATION_ENV: &str = "LYRE_MAX_DURATION_MINUTES";
+/// Separate limit for long-form sources (Twitch VODs, Mixcloud shows), which are routinely
+/// hours long and would otherwise trip `LYRE_MAX_DURATION_MINUTES`. Unset means no limit.
+const MAX_LONG_FORM_DURATION_ENV: &str = "LYRE_MAX_LONG_FORM_MINUTES";
+/// Comma-separated Discord user IDs of the bot's operators, who may use admin-only endpoints
+/// and change bot-wide settings
+const ADMIN_USERS_ENV: &str = "LYRE_ADMIN_USER_IDS";
+const DEFAULT_EXPLICIT_KEYWORDS: &[&str] = &[
+    "explicit",
+    "nsfw",
+    "uncensored",
+    "dirty version",
+    "parental advisory",
+];
+
+/// Reasons a track request is refused before anything is downloaded or queued
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PolicyError {
+    #[error("Links from {host} aren't allowed here. Allowed sources: {}", allowed.join(", "))]
+    SourceNotAllowed { host: String, allowed: Vec<String> },
+    #[error("This track was blocked by the server's explicit-content filter ({reason})")]
+    ExplicitContent { reason: String },
+    #[error("This track has been blocked on this server{}", reason.as_ref().map(|r| format!(" ({r})")).unwrap_or_default())]
+    TrackBlocked {
+        track_key: String,
+        reason: Option<String>,
+    },
+    #[error("This track's title contains a keyword blocked on this server (\"{keyword}\")")]
+    TitleBlocked { keyword: String },
+    #[error("This track is longer than the {max_minutes} minute limit")]
+    TooLong { max_minutes: u64 },
+    #[error(
+        "You're banned from using music commands on this server{}{}",
+        expires_at.map(|t| format!(" until {} UTC", t.format("%Y-%m-%d %H:%M"))).unwrap_or_default(),
+        reason.as_ref().map(|r| format!(" ({r})")).unwrap_or_default()
+    )]
+    UserBanned {
+        expires_at: Option<NaiveDateTime>,
+        reason: Option<String>,
+    },
+    #[error("It's quiet hours on this server; playback resumes at {ends_at} ({timezone} time)")]
+    QuietHours { ends_at: String, timezone: String },
+    #[error(
+        "Lyre is about to restart for maintenance, so new requests are paused; anything already playing will finish. Try again in a few minutes!"
+    )]
+    Maintenance,
+    #[error("Only members with one of this server's roles for /{command} can use it")]
+    CommandLocked { command: String },
+    #[error("There's no finished upload {id} on this server")]
+    UploadNotFound { id: String },
+}
+
+impl PolicyError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::SourceNotAllowed { .. } => "source_not_allowed",
+            Self::ExplicitContent { .. } => "explicit_content",
+            Self::TrackBlocked { .. } => "track_blocked",
+            Self::TitleBlocked { .. } => "title_blocked",
+            Self::TooLong { .. } => "too_long",
+            Self::UserBanned { .. } => "user_banned",
+            Self::QuietHours { .. } => "quiet_hours",
+            Self::Maintenance => "maintenance",
+            Self::CommandLocked { .. } => "command_locked",
+            Self::UploadNotFound { .. } => "upload_not_found",
+        }
+    }
+
+    pub fn details(&self) -> serde_json::Value {
+        match self {
+            Self::SourceNotAllowed { host, allowed } => {
+                serde_json::json!({ "host": host, "allowed": allowed })
+            }
+            Self::ExplicitContent { reason } => serde_json::json!({ "reason": reason }),
+            Self::TrackBlocked { track_key, reason } => {
+                serde_json::json!({ "track_key": track_key, "reason": reason })
+            }
+            Self::TitleBlocked { keyword } => serde_json::json!({ "keyword": keyword }),
+            Self::TooLong { max_minutes } => serde_json::json!({ "max_minutes": max_minutes }),
+            Self::UserBanned { expires_at, reason } => {
+                serde_json::json!({ "expires_at": expires_at, "reason": reason })
+            }
+            Self::QuietHours { ends_at, timezone } => {
+                serde_json::json!({ "ends_at": ends_at, "timezone": timezone })
+            }
+            Self::Maintenance => serde_json::json!({}),
+            Self::CommandLocked { command } => serde_json::json!({ "command": command }),
+            Self::UploadNotFound { id } => serde_json::json!({ "id": id }),
+        }
+    }
+}
+
+/// Refuse new playback while the bot is in maintenance mode and draining for a restart
+pub fn check_not_draining() -> Result<(), PolicyError> {
+    if crate::metrics::METRICS.is_draining() {
+        return Err(PolicyError::Maintenance);
+    }
+    Ok(())
+}
+
+/// Operator-wide allowlist from the environment
+pub fn global_allowed_hosts() -> Vec<String> {
+    crate::config::var(ALLOWED_HOSTS_ENV)
+        .map(|raw| parse_host_list(&raw))
+        .unwrap_or_default()
+}
+
+/// Split a comma/whitespace separated host list into normalized entries
+pub fn parse_host_list(raw: &str) -> Vec<String> {
+    raw.split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(normalize_host)
+        .collect()
+}
+
+/// Lowercase a host entry and strip wildcard/dot prefixes (`*.youtube.com` → `youtube.com`)
+pub fn normalize_host(entry: &str) -> Option<String> {
+    let host = entry
+        .trim()
+        .trim_start_matches("*.")
+        .trim_matches('.')
+        .to_ascii_lowercase();
+    if host.is_empty() { None } else { Some(host) }
+}
+
+/// True if `host` is `allowed` itself or one of its subdomains
+fn host_matches(host: &str, allowed: &str) -> bool {
+    host == allowed
+        || host
+            .strip_suffix(allowed)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Check `url` against the global allowlist and, if configured, the guild's own allowlist.
+/// A host must satisfy both lists when both are set.
+pub fn check_source_allowed(
+    url: &Url,
+    settings: Option<&GuildSettings>,
+) -> Result<(), PolicyError> {
+    // The allowlists are about sites on the internet; the local library is the operator's own
+    // and uploads were screened when they came in
+    if crate::library::track_id(url.as_str()).is_some()
+        || crate::uploads::track_id(url.as_str()).is_some()
+    {
+        return Ok(());
+    }
+    let host = url
+        .host_str()
+        .unwrap_or_default()
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+
+    let global = global_allowed_hosts();
+    let guild: Vec<String> = settings
+        .map(|s| {
+            s.allowed_domains_list()
+                .iter()
+                .filter_map(|d| normalize_host(d))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for list in [&global, &guild] {
+        if !list.is_empty() && !list.iter().any(|allowed| host_matches(&host, allowed)) {
+            return Err(PolicyError::SourceNotAllowed {
+                host,
+                allowed: effective_allowlist(&global, &guild),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Hosts a user can actually play from, for listing in rejection messages
+fn effective_allowlist(global: &[String], guild: &[String]) -> Vec<String> {
+    match (global.is_empty(), guild.is_empty()) {
+        (_, true) => global.to_vec(),
+        (true, false) => guild.to_vec(),
+        (false, false) => guild
+            .iter()
+            .filter(|g| global.iter().any(|allowed| host_matches(g, allowed)))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Whether the guild wants tracks screened by `check_explicit_content`
+pub fn explicit_filter_enabled(settings: Option<&GuildSettings>) -> bool {
+    settings.is_some_and(|s| s.explicit_filter)
+}
+
+fn explicit_keywords() -> Vec<String> {
+    match crate::config::var(EXPLICIT_KEYWORDS_ENV) {
+        Ok(raw) if !raw.trim().is_empty() => raw
+            .split(',')
+            .map(|k| k.trim().to_string())
+            .filter(|k| !k.is_empty())
+            .collect(),
+        _ => DEFAULT_EXPLICIT_KEYWORDS
+            .iter()
+            .map(|k| k.to_string())
+            .collect(),
+    }
+}
+
+/// Reject age-restricted tracks and titles containing an explicit keyword
+pub fn check_explicit_content(metadata: &TrackMetadata) -> Result<(), PolicyError> {
+    if metadata.is_age_restricted() {
+        return Err(PolicyError::ExplicitContent {
+            reason: "age-restricted upload".to_string(),
+        });
+    }
+    if let Some(keyword) = explicit_keywords()
+        .iter()
+        .find(|k| title_contains_keyword(&metadata.title, k))
+    {
+        return Err(PolicyError::ExplicitContent {
+            reason: format!("title matches \"{}\"", keyword),
+        });
+    }
+    Ok(())
+}
+
+fn minutes_from_env(key: &str) -> Option<u64> {
+    crate::config::var(key)
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u64>().ok())
+        .filter(|minutes| *minutes > 0)
+}
+
+/// Duration limit (in minutes) that applies to `url`, if any
+pub fn max_duration_minutes(url: &Url) -> Option<u64> {
+    if is_long_form_source(url) {
+        minutes_from_env(MAX_LONG_FORM_DURATION_ENV)
+    } else {
+        minutes_from_env(MAX_DURATION_ENV)
+    }
+}
+
+/// Reject tracks over the operator's duration limit. Live streams report no duration and
+/// are let through.
+pub fn check_duration(url: &Url, metadata: &TrackMetadata) -> Result<(), PolicyError> {
+    let (Some(max_minutes), Some(duration)) = (max_duration_minutes(url), metadata.duration) else {
+        return Ok(());
+    };
+    if duration > (max_minutes * 60) as f64 {
+        return Err(PolicyError::TooLong { max_minutes });
+    }
+    Ok(())
+}
+
+/// Most keywords a guild may ban, and the longest keyword accepted
+pub const MAX_BLOCKED_KEYWORDS: usize = 100;
+pub const MAX_KEYWORD_LEN: usize = 64;
+
+/// Most roles a guild may give queue priority, and the highest priority level
+pub const MAX_PRIORITY_ROLES: usize = 25;
+pub const MAX_QUEUE_PRIORITY: i32 = 10;
+
+/// Most roles a guild may mark as DJ roles
+pub const MAX_DJ_ROLES: usize = 25;
+
+/// Whether `user_id` is one of the operators listed in `LYRE_ADMIN_USER_IDS`
+pub fn is_bot_operator(user_id: &str) -> bool {
+    crate::config::var(ADMIN_USERS_ENV)
+        .map(|ids| ids.split(',').any(|id| id.trim() == user_id))
+        .unwrap_or(false)
+}
+
+/// DJs are members who can manage the server, hold one of the guild's DJ roles
+/// (`allowed_roles`) or were given a temporary grant with `/dj grant`. They skip the approval
+/// queue when moderation mode is on.
+pub fn is_dj(settings: Option<&GuildSettings>, member: Option<&Member>) -> bool {
+    let Some(member) = member else {
+        return false;
+    };
+    is_dj_by(
+        &mut establish_connection(),
+        settings,
+        &member.guild_id.to_string(),
+        &member.user.id.to_string(),
+        member.permissions.is_some_and(|p| p.manage_guild()),
+        &member.roles,
+    )
+}
+
+/// [`is_dj`] for a user known by ID, e.g. from the HTTP API
+fn is_dj_by(
+    db_conn: &mut SqliteConnection,
+    settings: Option<&GuildSettings>,
+    guild_id: &str,
+    user_id: &str,
+    manages_guild: bool,
+    roles: &[RoleId],
+) -> bool {
+    if manages_guild {
+        return true;
+    }
+    let dj_roles = settings.map(|s| s.allowed_roles_list()).unwrap_or_default();
+    if roles
+        .iter()
+        .any(|role| dj_roles.contains(&role.to_string()))
+    {
+        return true;
+    }
+    match DjGrant::find_active(db_conn, guild_id, user_id) {
+        Ok(grant) => grant.is_some(),
+        Err(e) => {
+            tracing::warn!("Failed to check DJ grant for user {}: {}", user_id, e);
+            false
+        }
+    }
+}
+
+/// Whether `user_id` is a DJ in `guild_id`, for callers without an interaction's member (the
+/// HTTP API, control hooks). Roles and permissions are looked up through Discord; without a
+/// connection only `manages_guild` (from the caller's OAuth guild list) and grants count.
+pub async fn user_is_dj(
+    settings: Option<&GuildSettings>,
+    guild_id: &str,
+    user_id: &str,
+    manages_guild: bool,
+) -> bool {
+    let Some((manages_guild, roles)) = member_standing(guild_id, user_id, manages_guild).await
+    else {
+        return false;
+    };
+    is_dj_by(
+        &mut establish_connection(),
+        settings,
+        guild_id,
+        user_id,
+        manages_guild,
+        &roles,
+    )
+}
+
+/// [`check_command_roles`] for a user known by ID, e.g. from the HTTP API, whose roles are
+/// looked up as [`user_is_dj`] does
+pub async fn check_user_command_roles(
+    settings: Option<&GuildSettings>,
+    command: &str,
+    guild_id: &str,
+    user_id: &str,
+    manages_guild: bool,
+) -> Result<(), PolicyError> {
+    let locked_to = settings
+        .map(|s| s.command_roles_map())
+        .and_then(|mut map| map.remove(command))
+        .unwrap_or_default();
+    if locked_to.is_empty() {
+        return Ok(());
+    }
+    let (manages_guild, roles) = member_standing(guild_id, user_id, manages_guild)
+        .await
+        .unwrap_or((manages_guild, Vec::new()));
+    if may_use_command(&locked_to, &roles, || {
+        is_dj_by(
+            &mut establish_connection(),
+            settings,
+            guild_id,
+            user_id,
+            manages_guild,
+            &roles,
+        )
+    }) {
+        Ok(())
+    } else {
+        Err(PolicyError::CommandLocked {
+            command: command.to_string(),
+        })
+    }
+}
+
+/// Whether `user_id` can manage `guild_id` and which roles they hold, looked up through
+/// Discord when the bot is connected. `None` if either ID isn't a snowflake.
+async fn member_standing(
+    guild_id: &str,
+    user_id: &str,
+    manages_guild: bool,
+) -> Option<(bool, Vec<RoleId>)> {
+    let (Ok(guild), Ok(user)) = (guild_id.parse::<u64>(), user_id.parse::<u64>()) else {
+        return None;
+    };
+    let (guild, user) = (GuildId::new(guild), UserId::new(user));
+    let mut manages_guild = manages_guild;
+    let mut roles = Vec::new();
+    if let Some(ctx) = crate::broadcast::context() {
+        match guild.member(&ctx, user).await {
+            Ok(member) => {
+                // Server-wide permissions come from the member's roles and @everyone, whose ID
+                // is the guild's
+                if let Some(cached) = ctx.cache.guild(guild) {
+                    let everyone = RoleId::new(guild.get());
+                    manages_guild |= cached.owner_id == user
+                        || member
+                            .roles
+                            .iter()
+                            .chain([&everyone])
+                            .filter_map(|role| cached.roles.get(role))
+                            .any(|role| {
+                                role.permissions.administrator() || role.permissions.manage_guild()
+                            });
+                }
+                roles = member.roles;
+            }
+            Err(e) => tracing::warn!(
+                "Failed to look up member {} in guild {}: {}",
+                user,
+                guild,
+                e
+            ),
+        }
+    }
+    Some((manages_guild, roles))
+}
+
+/// Whether a request from `member` must wait for a moderator before it's queued
+pub fn requires_approval(settings: Option<&GuildSettings>, member: Option<&Member>) -> bool {
+    settings.is_some_and(|s| s.require_approval) && !is_dj(settings, member)
+}
+
+/// Lowercase, trim and de-duplicate a guild's banned keyword list, preserving order
+pub fn normalize_keywords(keywords: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for keyword in keywords {
+        let keyword = keyword.trim().to_lowercase();
+        if !keyword.is_empty() && !out.contains(&keyword) {
+            out.push(keyword);
+        }
+    }
+    out
+}
+
+/// Whether `check_title_keywords` needs a title for this guild
+pub fn has_blocked_keywords(settings: Option<&GuildSettings>) -> bool {
+    settings.is_some_and(|s| !s.blocked_keywords_list().is_empty())
+}
+
+/// Reject titles containing one of the guild's banned keywords, catching renamed re-uploads
+/// that slip past the URL blacklist
+pub fn check_title_keywords(
+    title: &str,
+    settings: Option<&GuildSettings>,
+) -> Result<(), PolicyError> {
+    let Some(settings) = settings else {
+        return Ok(());
+    };
+    match settings
+        .blocked_keywords_list()
+        .into_iter()
+        .find(|k| title_contains_keyword(title, k))
+    {
+        Some(keyword) => Err(PolicyError::TitleBlocked { keyword }),
+        None => Ok(()),
+    }
+}
+
+/// Case-insensitive whole-word match, so "nsfw" matches "Song (NSFW)" but not "nsfwish"
+pub fn title_contains_keyword(title: &str, keyword: &str) -> bool {
+    let keyword = normalize_words(keyword);
+    if keyword.trim().is_empty() {
+        return false;
+    }
+    normalize_words(title).contains(&keyword)
+}
+
+/// Lowercase and collapse punctuation to single spaces, padded so word boundaries are spaces
+fn normalize_words(text: &str) -> String {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    format!(" {} ", words.join(" "))
+}
+
+/// Canonical key used to blacklist a track, so different URL forms of the same upload match.
+///
+/// YouTube links (`watch?v=`, `youtu.be/`, `shorts/`, `embed/`, `live/`) collapse to
+/// `youtube:<video id>`, Twitch VODs and clips to `twitch:v<id>`/`twitch-clip:<slug>`,
+/// Mixcloud shows to `mixcloud:<user>/<show>`, library tracks stay `library:<id>` and uploads
+/// `upload:<id>`; anything
+/// else becomes its host and path without `www.`, query or fragment.
+pub fn track_key(url: &Url) -> String {
+    if let Some(id) = youtube_video_id(url) {
+        return format!("youtube:{id}");
+    }
+    match twitch_media(url) {
+        Some(TwitchMedia::Vod(id)) => return format!("twitch:v{id}"),
+        Some(TwitchMedia::Clip(slug)) => return format!("twitch-clip:{slug}"),
+        None => {}
+    }
+    if let Some(show) = mixcloud_show(url) {
+        return format!("mixcloud:{show}");
+    }
+    if let Some(id) = crate::library::track_id(url.as_str()) {
+        return crate::library::track_url(id);
+    }
+    if let Some(id) = crate::uploads::track_id(url.as_str()) {
+        return crate::uploads::track_url(id);
+    }
+    let host = url
+        .host_str()
+        .unwrap_or_default()
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    format!("{}{}", host, url.path().trim_end_matches('/'))
+}
+
+/// Resolve user input for `/block` (a media URL or a bare YouTube video ID) to a track key
+pub fn parse_track_ref(input: &str) -> Option<String> {
+    let input = input.trim();
+    if is_youtube_id(input) {
+        return Some(format!("youtube:{input}"));
+    }
+    validate_media_url(input).ok().map(|url| track_key(&url))
+}
+
+fn youtube_video_id(url: &Url) -> Option<String> {
+    let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    let mut segments = url.path_segments()?.filter(|s| !s.is_empty());
+    let id = if host == "youtu.be" {
+        segments.next().map(str::to_string)
+    } else if host_matches(&host, "youtube.com") || host_matches(&host, "youtube-nocookie.com") {
+        match segments.next() {
+            Some("watch") => url
+                .query_pairs()
+                .find(|(k, _)| k == "v")
+                .map(|(_, v)| v.into_owned()),
+            Some("shorts" | "embed" | "live" | "v") => segments.next().map(str::to_string),
+            _ => None,
+        }
+    } else {
+        None
+    };
+    id.filter(|id| is_youtube_id(id))
+}
+
+enum TwitchMedia {
+    Vod(String),
+    Clip(String),
+}
+
+/// Recognize `twitch.tv/videos/<id>`, `twitch.tv/<channel>/clip/<slug>` and
+/// `clips.twitch.tv/<slug>` (including `www.`/`m.` hosts)
+fn twitch_media(url: &Url) -> Option<TwitchMedia> {
+    let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    if host == "clips.twitch.tv" {
+        return match segments.as_slice() {
+            [slug] => Some(TwitchMedia::Clip(slug.to_string())),
+            _ => None,
+        };
+    }
+    if !host_matches(&host, "twitch.tv") {
+        return None;
+    }
+    match segments.as_slice() {
+        ["videos", id] => {
+            let id = id.trim_start_matches('v');
+            (!id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+                .then(|| TwitchMedia::Vod(id.to_string()))
+        }
+        [_, "clip", slug] => Some(TwitchMedia::Clip(slug.to_string())),
+        _ => None,
+    }
+}
+
+/// `<user>/<show>` for a Mixcloud show URL
+fn mixcloud_show(url: &Url) -> Option<String> {
+    let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    if !host_matches(&host, "mixcloud.com") {
+        return None;
+    }
+    match url
+        .path_segments()?
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [user, show] => Some(format!("{}/{}", user.to_lowercase(), show.to_lowercase())),
+        _ => None,
+    }
+}
+
+/// Sources whose uploads are whole streams or DJ sets rather than single songs
+pub fn is_long_form_source(url: &Url) -> bool {
+    matches!(twitch_media(url), Some(TwitchMedia::Vod(_))) || mixcloud_show(url).is_some()
+}
+
+fn is_youtube_id(s: &str) -> bool {
+    s.len() == 11
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Reject `url` if a moderator has blacklisted it in this guild
+pub fn check_track_not_blocked(
+    conn: &mut SqliteConnection,
+    guild_id: &str,
+    url: &Url,
+) -> Result<(), PolicyError> {
+    let key = track_key(url);
+    match BlockedTrack::find(conn, guild_id, &key) {
+        Ok(Some(block)) => Err(PolicyError::TrackBlocked {
+            track_key: key,
+            reason: block.reason,
+        }),
+        Ok(None) => Ok(()),
+        Err(e) => {
+            tracing::warn!("Failed to check track blacklist for {}: {}", key, e);
+            Ok(())
+        }
+    }
+}
+
+/// Reject `upload:<id>` URLs naming anything but one of this guild's finished uploads
+pub fn check_upload_in_guild(
+    conn: &mut SqliteConnection,
+    guild_id: &str,
+    url: &Url,
+) -> Result<(), PolicyError> {
+    let Some(id) = crate::uploads::track_id(url.as_str()) else {
+        return Ok(());
+    };
+    match Upload::find(conn, guild_id, id) {
+        Ok(Some(upload)) if upload.completed_at.is_some() => Ok(()),
+        Ok(_) => Err(PolicyError::UploadNotFound { id: id.to_string() }),
+        Err(e) => {
+            tracing::warn!("Failed to look up upload {}: {}", id, e);
+            Err(PolicyError::UploadNotFound { id: id.to_string() })
+        }
+    }
+}
+
+/// Reject members a moderator has barred from playback in this guild
+pub fn check_user_not_banned(
+    conn: &mut SqliteConnection,
+    guild_id: &str,
+    user_id: &str,
+) -> Result<(), PolicyError> {
+    match MusicBan::find_active(conn, guild_id, user_id) {
+        Ok(Some(ban)) => Err(PolicyError::UserBanned {
+            expires_at: ban.expires_at,
+            reason: ban.reason,
+        }),
+        Ok(None) => Ok(()),
+        Err(e) => {
+            tracing::warn!("Failed to check music ban for user {}: {}", user_id, e);
+            Ok(())
+        }
+    }
+}
+
+/// Reject members without one of the roles a guild locked `command` to. DJs (including members
+/// who can manage the server and temporary DJs) always pass.
+pub fn check_command_roles(
+    settings: Option<&GuildSettings>,
+    command: &str,
+    member: Option<&Member>,
+) -> Result<(), PolicyError> {
+    let roles = member.map(|m| m.roles.as_slice()).unwrap_or_default();
+    check_command_roles_by(settings, command, roles, || is_dj(settings, member))
+}
+
+/// [`check_command_roles`] for a member with `roles` whose DJ standing the caller works out,
+/// e.g. once for several checks; `is_dj` is only asked when no role matches
+pub fn check_command_roles_by(
+    settings: Option<&GuildSettings>,
+    command: &str,
+    roles: &[RoleId],
+    is_dj: impl FnOnce() -> bool,
+) -> Result<(), PolicyError> {
+    let locked_to = settings
+        .map(|s| s.command_roles_map())
+        .and_then(|mut map| map.remove(command))
+        .unwrap_or_default();
+    if may_use_command(&locked_to, roles, is_dj) {
+        Ok(())
+    } else {
+        Err(PolicyError::CommandLocked {
+            command: command.to_string(),
+        })
+    }
+}
+
+/// Whether a member with `roles` may use a command locked to `locked_to`; `is_dj` is only asked
+/// when no role matches
+fn may_use_command(locked_to: &[String], roles: &[RoleId], is_dj: impl FnOnce() -> bool) -> bool {
+    locked_to.is_empty()
+        || roles
+            .iter()
+            .any(|role| locked_to.contains(&role.to_string()))
+        || is_dj()
+}
+
+/// What a guild's quiet hours call for while they're in effect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuietHours {
+    /// Don't start playing anything new
+    Refuse,
+    /// Play tracks no louder than this volume (0.0-1.0)
+    CapVolume(f32),
+}
+
+/// Parse an `HH:MM` quiet-hours boundary
+pub fn parse_clock_time(input: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(input.trim(), "%H:%M").ok()
+}
+
+/// Parse an IANA time zone name such as `Europe/Berlin` or `UTC`, ignoring case, and return
+/// its canonical spelling
+pub fn normalize_timezone(input: &str) -> Option<String> {
+    let input = input.trim();
+    let zone = input.parse::<Tz>().ok().or_else(|| {
+        chrono_tz::TZ_VARIANTS
+            .iter()
+            .copied()
+            .find(|zone| zone.name().eq_ignore_ascii_case(input))
+    })?;
+    Some(zone.name().to_string())
+}
+
+/// The guild's quiet-hours rule if `now` falls inside its window. The window may wrap past
+/// midnight (e.g. 22:00-07:00).
+pub fn active_quiet_hours(
+    settings: Option<&GuildSettings>,
+    now: DateTime<Utc>,
+) -> Option<QuietHours> {
+    let settings = settings?;
+    let start = parse_clock_time(settings.quiet_hours_start.as_deref()?)?;
+    let end = parse_clock_time(settings.quiet_hours_end.as_deref()?)?;
+    let zone = settings.quiet_hours_timezone.parse::<Tz>().ok()?;
+    if !in_window(start, end, zone, now) {
+        return None;
+    }
+    Some(match settings.quiet_hours_volume {
+        Some(volume) => QuietHours::CapVolume(volume),
+        None => QuietHours::Refuse,
+    })
+}
+
+/// Refuse to start playback during quiet hours, unless the guild only caps the volume
+pub fn check_quiet_hours(settings: Option<&GuildSettings>) -> Result<(), PolicyError> {
+    match (settings, active_quiet_hours(settings, Utc::now())) {
+        (Some(settings), Some(QuietHours::Refuse)) => Err(PolicyError::QuietHours {
+            ends_at: settings.quiet_hours_end.clone().unwrap_or_default(),
+            timezone: settings.quiet_hours_timezone.clone(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `now` is between `start` and `end` on `zone`'s clocks, daylight saving included
+fn in_window(start: NaiveTime, end: NaiveTime, zone: Tz, now: DateTime<Utc>) -> bool {
+    let local = now.with_timezone(&zone).time();
+    if start <= end {
+        local >= start && local < end
+    } else {
+        local >= start || local < end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{in_window, is_dj_by, may_use_command, normalize_timezone};
+    use crate::database::models::DjGrant;
+    use chrono::{DateTime, Duration, NaiveTime, Utc};
+    use serenity::all::RoleId;
+
+    #[test]
+    fn timezones_are_matched_ignoring_case() {
+        for (input, expected) in [
+            ("UTC", "UTC"),
+            ("Europe/Berlin", "Europe/Berlin"),
+            ("europe/berlin", "Europe/Berlin"),
+            (" America/New_York ", "America/New_York"),
+            ("etc/gmt-2", "Etc/GMT-2"),
+        ] {
+            assert_eq!(
+                normalize_timezone(input).as_deref(),
+                Some(expected),
+                "{input}"
+            );
+        }
+        for input in ["", "+02:00", "UTC+2", "Europe/", "Mars/Olympus", "é"] {
+            assert_eq!(normalize_timezone(input), None, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn quiet_hours_follow_daylight_saving() {
+        let start = NaiveTime::from_hms_opt(22, 0, 0).unwrap();
+        let end = NaiveTime::from_hms_opt(7, 0, 0).unwrap();
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let berlin = chrono_tz::Europe::Berlin;
+
+        // 22:30 in Berlin is 21:30 UTC in winter but 20:30 UTC in summer
+        assert!(in_window(start, end, berlin, at("2026-01-15T21:30:00Z")));
+        assert!(!in_window(start, end, berlin, at("2026-01-15T20:30:00Z")));
+        assert!(in_window(start, end, berlin, at("2026-07-15T20:30:00Z")));
+        // 06:30 in Berlin, still inside a window that wraps past midnight
+        assert!(in_window(start, end, berlin, at("2026-07-15T04:30:00Z")));
+        assert!(!in_window(start, end, berlin, at("2026-07-15T05:30:00Z")));
+    }
+
+    #[test]
+    fn djs_pass_command_locks() {
+        let locked_to = vec!["42".to_string()];
+        let other_role = [RoleId::new(7)];
+
+        assert!(may_use_command(&[], &[], || false));
+        assert!(may_use_command(&locked_to, &[RoleId::new(42)], || false));
+        assert!(!may_use_command(&locked_to, &other_role, || false));
+        // A DJ role that isn't one of the command's roles still unlocks it
+        assert!(may_use_command(&locked_to, &other_role, || true));
+    }
+
+    #[test]
+    fn temporary_djs_count_until_their_grant_expires() {
+        let mut conn = crate::database::test_connection();
+        let now = Utc::now().naive_utc();
+        DjGrant::grant(&mut conn, "1", "active", "admin", now + Duration::hours(1)).unwrap();
+        DjGrant::grant(&mut conn, "1", "expired", "admin", now - Duration::hours(1)).unwrap();
+
+        assert!(is_dj_by(&mut conn, None, "1", "active", false, &[]));
+        assert!(!is_dj_by(&mut conn, None, "1", "expired", false, &[]));
+        // Grants are per guild
+        assert!(!is_dj_by(&mut conn, None, "2", "active", false, &[]));
+
+        // So only the live grant opens commands locked to roles the member doesn't hold
+        let locked_to = ["333".to_string()];
+        assert!(may_use_command(&locked_to, &[], || {
+            is_dj_by(&mut conn, None, "1", "active", false, &[])
+        }));
+        assert!(!may_use_command(&locked_to, &[], || {
+            is_dj_by(&mut conn, None, "1", "expired", false, &[])
+        }));
+    }
+}
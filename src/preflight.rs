@@ -0,0 +1,279 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use tokio::process::Command as TokioCommand;
+use tracing::{error, info, warn};
+
+/// Set to `1` to refuse to start when any check fails, not just the ones the bot can't run
+/// without at all
+const STRICT_ENV: &str = "LYRE_PREFLIGHT_STRICT";
+/// Warn when the download folder's filesystem has less free space than this
+const MIN_FREE_DISK_BYTES: u64 = 1024 * 1024 * 1024;
+const DISCORD_ME_URL: &str = "https://discord.com/api/v10/users/@me";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Pass => "PASS",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// Whether the bot can't do anything useful while this check fails
+    pub essential: bool,
+}
+
+impl Check {
+    fn new(name: &'static str, essential: bool, result: Result<(Status, String)>) -> Self {
+        let (status, detail) = result.unwrap_or_else(|e| (Status::Fail, format!("{:#}", e)));
+        Self {
+            name,
+            status,
+            detail,
+            essential,
+        }
+    }
+}
+
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Whether startup should stop: an essential check failed, or any check failed in strict mode
+    pub fn should_abort(&self) -> bool {
+        let strict = crate::config::var(STRICT_ENV).as_deref() == Ok("1");
+        self.checks
+            .iter()
+            .any(|c| c.status == Status::Fail && (c.essential || strict))
+    }
+
+    /// Log one line per check followed by a summary
+    pub fn log(&self) {
+        for check in &self.checks {
+            let line = format!(
+                "Preflight [{}] {}: {}",
+                check.status.label(),
+                check.name,
+                check.detail
+            );
+            match check.status {
+                Status::Pass => info!("{}", line),
+                Status::Warn => warn!("{}", line),
+                Status::Fail => error!("{}", line),
+            }
+        }
+        let count = |status| self.checks.iter().filter(|c| c.status == status).count();
+        let summary = format!(
+            "Preflight: {} passed, {} warnings, {} failed",
+            count(Status::Pass),
+            count(Status::Warn),
+            count(Status::Fail)
+        );
+        if self.should_abort() {
+            error!("{}; refusing to start", summary);
+        } else if count(Status::Fail) > 0 {
+            warn!("{}; starting degraded, playback may not work", summary);
+        } else {
+            info!("{}", summary);
+        }
+    }
+}
+
+/// Check everything the bot depends on before connecting to Discord, so misconfiguration shows
+/// up as one clear report at startup instead of a panic in the middle of a command
+pub async fn run(token: Option<&str>) -> Report {
+    let (database, downloads, ytdlp, ffmpeg, discord) = tokio::join!(
+        async { Check::new("database", true, check_database()) },
+        async { Check::new("download folder", false, check_download_folder().await) },
+        async { Check::new("yt-dlp", false, check_ytdlp().await) },
+        async { Check::new("ffmpeg", false, check_ffmpeg().await) },
+        async {
+            let result = match token {
+                Some(token) => check_discord_token(token).await,
+                None => Ok((Status::Pass, "skipped in simulation mode".to_string())),
+            };
+            Check::new("discord token", true, result)
+        },
+    );
+    Report {
+        checks: vec![database, downloads, ytdlp, ffmpeg, discord],
+    }
+}
+
+/// Creates the database if needed and applies pending migrations, then checks it answers
+fn check_database() -> Result<(Status, String)> {
+    let applied = crate::database::init()?;
+    let url = crate::database::database_url();
+    let mut conn = SqliteConnection::establish(url)
+        .map_err(|e| anyhow!("cannot connect to {}: {}", url, e))?;
+    diesel::sql_query("SELECT 1")
+        .execute(&mut conn)
+        .map_err(|e| anyhow!("connected to {} but a test query failed: {}", url, e))?;
+    let source = if std::env::var("DATABASE_URL").is_ok() {
+        ""
+    } else {
+        " (default location; set DATABASE_URL to choose another)"
+    };
+    Ok((
+        Status::Pass,
+        format!(
+            "connected to {}{}, {} migration(s) applied",
+            url, source, applied
+        ),
+    ))
+}
+
+async fn check_download_folder() -> Result<(Status, String)> {
+    let dir = crate::audio::resolved_download_base_dir()?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| anyhow!("cannot create {}: {}", dir.display(), e))?;
+    let probe = dir.join(".lyre-preflight");
+    tokio::fs::write(&probe, b"ok")
+        .await
+        .map_err(|e| anyhow!("{} is not writable: {}", dir.display(), e))?;
+    let _ = tokio::fs::remove_file(&probe).await;
+
+    match free_disk_bytes(&dir).await {
+        Some(free) if free < MIN_FREE_DISK_BYTES => Ok((
+            Status::Warn,
+            format!(
+                "{} is writable but only {} MiB free",
+                dir.display(),
+                free / (1024 * 1024)
+            ),
+        )),
+        Some(free) => Ok((
+            Status::Pass,
+            format!(
+                "{} is writable, {} MiB free",
+                dir.display(),
+                free / (1024 * 1024)
+            ),
+        )),
+        None => Ok((
+            Status::Pass,
+            format!("{} is writable (free space unknown)", dir.display()),
+        )),
+    }
+}
+
+/// Free space on the filesystem holding `dir`, from POSIX `df`
+async fn free_disk_bytes(dir: &Path) -> Option<u64> {
+    let out = TokioCommand::new("df")
+        .arg("-Pk")
+        .arg(dir)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let available_kib: u64 = stdout
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()?;
+    Some(available_kib * 1024)
+}
+
+async fn check_ytdlp() -> Result<(Status, String)> {
+    let path = crate::audio::ensure_yt_dlp()
+        .await
+        .map_err(|e| anyhow!("not on PATH and could not be downloaded: {}", e))?;
+    let version = command_version(path.as_os_str(), "--version").await?;
+    Ok((Status::Pass, format!("{} ({})", version, path.display())))
+}
+
+async fn check_ffmpeg() -> Result<(Status, String)> {
+    // yt-dlp needs ffmpeg to convert downloads to MP3
+    let path = crate::audio::ensure_ffmpeg()
+        .await
+        .map_err(|e| anyhow!("not on PATH and could not be downloaded: {}", e))?;
+    let version = command_version(path.as_os_str(), "-version").await?;
+    Ok((Status::Pass, format!("{} ({})", version, path.display())))
+}
+
+/// First line of `<program> <flag>`, for version checks
+pub async fn command_version(program: &std::ffi::OsStr, flag: &str) -> Result<String> {
+    let out = tokio::time::timeout(
+        CHECK_TIMEOUT,
+        TokioCommand::new(program)
+            .arg(flag)
+            .stdin(Stdio::null())
+            .output(),
+    )
+    .await
+    .map_err(|_| anyhow!("timed out running {}", flag))?
+    .map_err(|e| anyhow!("failed to run: {}", e))?;
+    if !out.status.success() {
+        return Err(anyhow!("exited with {}", out.status));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
+
+async fn check_discord_token(token: &str) -> Result<(Status, String)> {
+    let resp = reqwest::Client::new()
+        .get(DISCORD_ME_URL)
+        .header(reqwest::header::AUTHORIZATION, format!("Bot {}", token))
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await;
+    let resp = match resp {
+        Ok(resp) => resp,
+        // Discord being unreachable doesn't mean the token is bad; the gateway client retries
+        Err(e) => {
+            return Ok((
+                Status::Warn,
+                format!("could not reach Discord to verify the token: {}", e),
+            ));
+        }
+    };
+    match resp.status() {
+        reqwest::StatusCode::UNAUTHORIZED => {
+            Err(anyhow!("Discord rejected the token (401 Unauthorized)"))
+        }
+        status if status.is_success() => {
+            let name = resp
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|me| me["username"].as_str().map(str::to_string))
+                .unwrap_or_else(|| "unknown".to_string());
+            Ok((Status::Pass, format!("valid, bot user {}", name)))
+        }
+        status => Ok((
+            Status::Warn,
+            format!("could not verify the token: Discord answered {}", status),
+        )),
+    }
+}
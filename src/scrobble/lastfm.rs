@@ -0,0 +1,181 @@
+//! Last.fm API client: desktop-style auth (token → user approval → session) and scrobbling.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use super::HTTP;
+use super::md5::md5_hex;
+use crate::database::models::ScrobbleQueueEntry;
+
+const API_ROOT: &str = "https://ws.audioscrobbler.com/2.0/";
+const AUTH_URL: &str = "https://www.last.fm/api/auth/";
+/// Last.fm auth tokens are valid for 60 minutes
+const TOKEN_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Auth tokens handed out by `/lastfm link`, waiting for `/lastfm verify`, keyed by Discord user
+static PENDING: Lazy<Mutex<HashMap<String, (String, Instant)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct LastFmConfig {
+    pub api_key: String,
+    pub api_secret: String,
+}
+
+/// API credentials from `LASTFM_API_KEY`/`LASTFM_API_SECRET`; `None` disables Last.fm
+pub fn config() -> Option<LastFmConfig> {
+    let api_key = std::env::var("LASTFM_API_KEY")
+        .ok()
+        .filter(|v| !v.is_empty())?;
+    let api_secret = std::env::var("LASTFM_API_SECRET")
+        .ok()
+        .filter(|v| !v.is_empty())?;
+    Some(LastFmConfig {
+        api_key,
+        api_secret,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Session {
+    pub name: String,
+    pub key: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct SessionResponse {
+    session: Session,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: i64,
+    message: String,
+}
+
+/// `api_sig`: md5 of every parameter (except `format`) sorted by name, then the shared secret
+fn sign(params: &[(&str, String)], secret: &str) -> String {
+    let mut sorted: Vec<&(&str, String)> = params.iter().collect();
+    sorted.sort_by_key(|(name, _)| *name);
+    let mut payload = String::new();
+    for (name, value) in sorted {
+        payload.push_str(name);
+        payload.push_str(value);
+    }
+    payload.push_str(secret);
+    md5_hex(payload.as_bytes())
+}
+
+/// Call a signed API method, returning the JSON body or Last.fm's error message
+async fn call(
+    config: &LastFmConfig,
+    method: &str,
+    mut params: Vec<(&str, String)>,
+    post: bool,
+) -> Result<serde_json::Value> {
+    params.push(("method", method.to_string()));
+    params.push(("api_key", config.api_key.clone()));
+    let api_sig = sign(&params, &config.api_secret);
+    params.push(("api_sig", api_sig));
+    params.push(("format", "json".to_string()));
+
+    let request = if post {
+        HTTP.post(API_ROOT).form(&params)
+    } else {
+        HTTP.get(API_ROOT).query(&params)
+    };
+    let body: serde_json::Value = request
+        .send()
+        .await
+        .with_context(|| format!("calling Last.fm {method}"))?
+        .json()
+        .await
+        .with_context(|| format!("parsing Last.fm {method} response"))?;
+
+    if let Ok(err) = serde_json::from_value::<ErrorResponse>(body.clone()) {
+        return Err(anyhow!(
+            "Last.fm {} failed ({}): {}",
+            method,
+            err.error,
+            err.message
+        ));
+    }
+    Ok(body)
+}
+
+/// Start linking: fetch an unauthorized token for the user to approve
+pub async fn request_token(config: &LastFmConfig) -> Result<String> {
+    let body = call(config, "auth.getToken", Vec::new(), false).await?;
+    let parsed: TokenResponse = serde_json::from_value(body).context("unexpected auth.getToken")?;
+    Ok(parsed.token)
+}
+
+/// Page where the user grants lyre access for `token`
+pub fn authorize_url(config: &LastFmConfig, token: &str) -> String {
+    format!("{}?api_key={}&token={}", AUTH_URL, config.api_key, token)
+}
+
+/// Finish linking: exchange an approved token for a permanent session key
+pub async fn fetch_session(config: &LastFmConfig, token: &str) -> Result<Session> {
+    let body = call(
+        config,
+        "auth.getSession",
+        vec![("token", token.to_string())],
+        false,
+    )
+    .await?;
+    let parsed: SessionResponse =
+        serde_json::from_value(body).context("unexpected auth.getSession")?;
+    Ok(parsed.session)
+}
+
+pub async fn scrobble(
+    config: &LastFmConfig,
+    session_key: &str,
+    entry: &ScrobbleQueueEntry,
+) -> Result<()> {
+    let mut params = vec![
+        ("artist", entry.artist.clone()),
+        ("track", entry.track.clone()),
+        (
+            "timestamp",
+            entry.played_at.and_utc().timestamp().to_string(),
+        ),
+        ("sk", session_key.to_string()),
+    ];
+    if let Some(duration) = entry.duration {
+        params.push(("duration", duration.to_string()));
+    }
+    let body = call(config, "track.scrobble", params, true).await?;
+
+    // Last.fm accepts the request but may still ignore the scrobble (e.g. too old); retrying
+    // won't change that, so just note it.
+    if body["scrobbles"]["@attr"]["ignored"].as_i64().unwrap_or(0) > 0 {
+        tracing::info!(
+            "Last.fm ignored scrobble of {} - {}",
+            entry.artist,
+            entry.track
+        );
+    }
+    Ok(())
+}
+
+pub fn remember_pending_token(user_id: &str, token: String) {
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|_, (_, issued)| issued.elapsed() < TOKEN_TTL);
+    pending.insert(user_id.to_string(), (token, Instant::now()));
+}
+
+pub fn take_pending_token(user_id: &str) -> Option<String> {
+    let (token, issued) = PENDING.lock().unwrap().remove(user_id)?;
+    (issued.elapsed() < TOKEN_TTL).then_some(token)
+}
@@ -0,0 +1,69 @@
+//! ListenBrainz API client: user-token validation and listen submission.
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::HTTP;
+use crate::database::models::ScrobbleQueueEntry;
+
+const API_ROOT: &str = "https://api.listenbrainz.org/1";
+
+#[derive(Deserialize)]
+struct ValidateResponse {
+    valid: bool,
+    #[serde(default)]
+    user_name: Option<String>,
+}
+
+/// Check a user token (from https://listenbrainz.org/settings/), returning the account name
+pub async fn validate_token(token: &str) -> Result<Option<String>> {
+    let response: ValidateResponse = HTTP
+        .get(format!("{}/validate-token", API_ROOT))
+        .header("Authorization", format!("Token {}", token))
+        .send()
+        .await
+        .context("calling ListenBrainz validate-token")?
+        .json()
+        .await
+        .context("parsing ListenBrainz validate-token response")?;
+    Ok(response
+        .valid
+        .then(|| response.user_name.unwrap_or_default()))
+}
+
+pub async fn submit_listen(token: &str, entry: &ScrobbleQueueEntry) -> Result<()> {
+    let mut additional_info = json!({ "submission_client": "lyre" });
+    if let Some(duration) = entry.duration {
+        additional_info["duration"] = json!(duration);
+    }
+    let body = json!({
+        "listen_type": "single",
+        "payload": [{
+            "listened_at": entry.played_at.and_utc().timestamp(),
+            "track_metadata": {
+                "artist_name": entry.artist,
+                "track_name": entry.track,
+                "additional_info": additional_info,
+            },
+        }],
+    });
+
+    let response = HTTP
+        .post(format!("{}/submit-listens", API_ROOT))
+        .header("Authorization", format!("Token {}", token))
+        .json(&body)
+        .send()
+        .await
+        .context("calling ListenBrainz submit-listens")?;
+    let status = response.status();
+    if !status.is_success() {
+        let detail = response.text().await.unwrap_or_default();
+        return Err(anyhow!(
+            "ListenBrainz submit-listens failed ({}): {}",
+            status,
+            detail
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,309 @@
+//! Scrobbling: submitting what users listen to to external services such as Last.fm and
+//! ListenBrainz.
+//!
+//! Listens are written to the `scrobble_queue` table when a track finishes and submitted by a
+//! background worker, so a service outage only delays submissions instead of losing them.
+
+pub mod lastfm;
+pub mod listenbrainz;
+mod md5;
+
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::{NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+
+use crate::audio::TrackMetadata;
+use crate::database::establish_connection;
+use crate::database::models::scrobble::NewScrobbleQueueEntry;
+use crate::database::models::{ScrobbleAccount, ScrobbleQueueEntry};
+
+/// How often the worker looks for due submissions
+const WORKER_INTERVAL: Duration = Duration::from_secs(30);
+/// Most entries submitted per worker pass
+const WORKER_BATCH: i64 = 50;
+/// Give up on an entry after this many failed submissions
+const MAX_ATTEMPTS: i32 = 8;
+
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent(concat!(
+            "lyre-bot/",
+            env!("CARGO_PKG_VERSION"),
+            " (+https://github.com/mbround18/lyre)"
+        ))
+        .timeout(Duration::from_secs(15))
+        .build()
+        .expect("client")
+});
+
+/// External services a user can link with `/lastfm` and `/listenbrainz`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrobbleService {
+    LastFm,
+    ListenBrainz,
+}
+
+impl ScrobbleService {
+    /// Value stored in the `service` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LastFm => "lastfm",
+            Self::ListenBrainz => "listenbrainz",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Option<Self> {
+        match value {
+            "lastfm" => Some(Self::LastFm),
+            "listenbrainz" => Some(Self::ListenBrainz),
+            _ => None,
+        }
+    }
+}
+
+/// A track as the scrobbling services want it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listen {
+    pub artist: String,
+    pub track: String,
+    pub duration: Option<i32>,
+}
+
+/// Words that mark a bracketed title segment as upload noise, e.g. "(Official Video)"
+const TITLE_NOISE: &[&str] = &[
+    "official",
+    "video",
+    "audio",
+    "lyric",
+    "lyrics",
+    "visualizer",
+    "visualiser",
+    "hd",
+    "hq",
+    "4k",
+    "mv",
+];
+
+/// Work out artist and track name for a played track.
+///
+/// Extractor-provided `artist`/`track` win; otherwise `Artist - Title` style video titles are
+/// split, falling back to the uploader (minus YouTube's " - Topic"/"VEVO" suffixes) as artist.
+/// Streams and DJ sets (Twitch VODs, Mixcloud shows) aren't a single track and are skipped.
+pub fn parse_listen(title: &str, metadata: Option<&TrackMetadata>) -> Option<Listen> {
+    if metadata.is_some_and(|m| m.is_long_form()) {
+        return None;
+    }
+    let duration = metadata.and_then(|m| m.duration_secs());
+
+    if let Some(m) = metadata
+        && let (Some(artist), Some(track)) = (&m.artist, &m.track)
+        && !artist.trim().is_empty()
+        && !track.trim().is_empty()
+    {
+        return Some(Listen {
+            artist: artist.trim().to_string(),
+            track: track.trim().to_string(),
+            duration,
+        });
+    }
+
+    let cleaned = strip_title_noise(title);
+    for separator in [" - ", " – ", " — "] {
+        if let Some((artist, track)) = cleaned.split_once(separator) {
+            let (artist, track) = (artist.trim(), track.trim());
+            if !artist.is_empty() && !track.is_empty() {
+                return Some(Listen {
+                    artist: artist.to_string(),
+                    track: track.to_string(),
+                    duration,
+                });
+            }
+        }
+    }
+
+    let uploader = metadata.and_then(|m| m.uploader.as_deref())?;
+    let artist = uploader
+        .trim()
+        .trim_end_matches(" - Topic")
+        .trim_end_matches("VEVO")
+        .trim();
+    if artist.is_empty() || cleaned.is_empty() {
+        return None;
+    }
+    Some(Listen {
+        artist: artist.to_string(),
+        track: cleaned,
+        duration,
+    })
+}
+
+/// Drop `(...)`/`[...]` segments made up of upload noise like "Official Music Video"
+fn strip_title_noise(title: &str) -> String {
+    let mut out = String::with_capacity(title.len());
+    let mut rest = title;
+    while let Some(start) = rest.find(['(', '[']) {
+        let close = if rest[start..].starts_with('(') {
+            ')'
+        } else {
+            ']'
+        };
+        let Some(len) = rest[start..].find(close) else {
+            break;
+        };
+        let inner = rest[start + 1..start + len].to_lowercase();
+        let noisy = inner
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| TITLE_NOISE.contains(&word));
+        out.push_str(&rest[..start]);
+        if !noisy {
+            out.push_str(&rest[start..=start + len]);
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Scrobbling services only count a listen once the track was played for half its length or
+/// four minutes, whichever comes first, and never for tracks under 30 seconds.
+pub fn is_scrobble_eligible(play_time: Duration, duration: Option<i32>) -> bool {
+    const FOUR_MINUTES: Duration = Duration::from_secs(240);
+    match duration {
+        Some(secs) if secs < 30 => false,
+        Some(secs) => play_time >= Duration::from_secs(secs as u64 / 2).min(FOUR_MINUTES),
+        None => play_time >= FOUR_MINUTES,
+    }
+}
+
+/// Whether the user has any linked, enabled account, so callers can skip scrobble bookkeeping
+pub fn has_scrobble_accounts(user_id: &str) -> bool {
+    let mut conn = establish_connection();
+    ScrobbleAccount::enabled_for_user(&mut conn, user_id).is_ok_and(|accounts| !accounts.is_empty())
+}
+
+/// Queue a finished listen for every service the user has linked
+pub fn enqueue_listen(user_id: &str, listen: &Listen, played_at: NaiveDateTime) {
+    let mut conn = establish_connection();
+    let accounts = match ScrobbleAccount::enabled_for_user(&mut conn, user_id) {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            tracing::warn!("Failed to load scrobble accounts for {}: {}", user_id, e);
+            return;
+        }
+    };
+    for account in accounts {
+        let entry = NewScrobbleQueueEntry {
+            user_id: user_id.to_string(),
+            service: account.service,
+            artist: listen.artist.clone(),
+            track: listen.track.clone(),
+            duration: listen.duration,
+            played_at,
+        };
+        if let Err(e) = ScrobbleQueueEntry::enqueue(&mut conn, &entry) {
+            tracing::warn!("Failed to queue scrobble for {}: {}", user_id, e);
+        }
+    }
+}
+
+/// Periodically submit due entries from `scrobble_queue`, retrying failures with backoff
+pub fn spawn_scrobble_worker() {
+    tokio::spawn(async {
+        loop {
+            process_due().await;
+            tokio::time::sleep(WORKER_INTERVAL).await;
+        }
+    });
+}
+
+async fn process_due() {
+    let due = {
+        let mut conn = establish_connection();
+        ScrobbleQueueEntry::due(&mut conn, WORKER_BATCH)
+    };
+    let due = match due {
+        Ok(due) => due,
+        Err(e) => {
+            tracing::warn!("Failed to load scrobble queue: {}", e);
+            return;
+        }
+    };
+
+    for entry in due {
+        let Some(id) = entry.id else { continue };
+        let account = {
+            let mut conn = establish_connection();
+            ScrobbleAccount::find(&mut conn, &entry.user_id, &entry.service)
+        };
+        let account = match account {
+            Ok(Some(account)) if account.enabled => account,
+            Ok(_) => {
+                // Unlinked or opted out since the listen was queued
+                let mut conn = establish_connection();
+                let _ = ScrobbleQueueEntry::remove(&mut conn, id);
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load scrobble account: {}", e);
+                continue;
+            }
+        };
+
+        let result = submit(&account, &entry).await;
+        let mut conn = establish_connection();
+        match result {
+            Ok(()) => {
+                let _ = ScrobbleQueueEntry::remove(&mut conn, id);
+            }
+            Err(e) => {
+                let attempts = entry.attempts + 1;
+                if attempts >= MAX_ATTEMPTS {
+                    tracing::warn!(
+                        "Giving up on {} scrobble for {} after {} attempts: {}",
+                        entry.service,
+                        entry.user_id,
+                        attempts,
+                        e
+                    );
+                    let _ = ScrobbleQueueEntry::remove(&mut conn, id);
+                } else {
+                    tracing::info!(
+                        "{} scrobble for {} failed (attempt {}): {}",
+                        entry.service,
+                        entry.user_id,
+                        attempts,
+                        e
+                    );
+                    let next = Utc::now().naive_utc() + retry_delay(attempts);
+                    let _ = ScrobbleQueueEntry::reschedule(
+                        &mut conn,
+                        id,
+                        attempts,
+                        next,
+                        &e.to_string(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Exponential backoff: 2, 4, 8 … minutes
+fn retry_delay(attempts: i32) -> chrono::Duration {
+    chrono::Duration::minutes(1i64 << attempts.clamp(1, 10))
+}
+
+async fn submit(account: &ScrobbleAccount, entry: &ScrobbleQueueEntry) -> Result<()> {
+    match ScrobbleService::from_db(&account.service) {
+        Some(ScrobbleService::LastFm) => {
+            let config = lastfm::config().ok_or_else(|| anyhow!("Last.fm is not configured"))?;
+            lastfm::scrobble(&config, &account.token, entry).await
+        }
+        Some(ScrobbleService::ListenBrainz) => {
+            listenbrainz::submit_listen(&account.token, entry).await
+        }
+        None => Err(anyhow!("unknown scrobble service {}", account.service)),
+    }
+}
@@ -0,0 +1,63 @@
+//! Guild settings changes, fanned out to API clients following a guild's settings (see
+//! `/api/guild-settings/{guild_id}/events`) and applied to the guild's live session right away.
+//! Whatever saves a guild's settings, the API or a slash command, reports it here afterwards.
+
+use once_cell::sync::Lazy;
+use serenity::all::GuildId;
+use tokio::sync::broadcast;
+
+use crate::broadcast as discord;
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+
+/// Changes a slow subscriber can fall behind by before it starts missing them
+const CHANNEL_CAPACITY: usize = 64;
+
+static EVENTS: Lazy<broadcast::Sender<SettingsChanged>> =
+    Lazy::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+#[derive(Debug, Clone)]
+pub struct SettingsChanged {
+    pub guild_id: String,
+}
+
+/// Tell subscribers `guild_id`'s settings changed and bring its live session in line with them
+pub fn changed(guild_id: &str) {
+    let _ = EVENTS.send(SettingsChanged {
+        guild_id: guild_id.to_string(),
+    });
+    let guild_id = guild_id.to_string();
+    tokio::spawn(async move { apply_live(&guild_id).await });
+}
+
+pub fn subscribe() -> broadcast::Receiver<SettingsChanged> {
+    EVENTS.subscribe()
+}
+
+/// Turn the guild's queued tracks down to its volume cap, if it's now below them. The
+/// announcement channel is looked up whenever something is posted, so it needs nothing here.
+async fn apply_live(guild_id: &str) {
+    let (Some(manager), Ok(id)) = (discord::voice_manager(), guild_id.parse::<u64>()) else {
+        return;
+    };
+    let Some(call_lock) = manager.get(GuildId::new(id)) else {
+        return;
+    };
+    let Some(settings) = GuildSettings::find_by_guild_id(&mut establish_connection(), guild_id)
+        .ok()
+        .flatten()
+    else {
+        return;
+    };
+    let tracks = call_lock.lock().await.queue().current_queue();
+    for track in tracks {
+        let Ok(info) = track.get_info().await else {
+            continue;
+        };
+        if info.volume > settings.max_volume
+            && let Err(e) = track.set_volume(settings.max_volume)
+        {
+            tracing::warn!("Failed to apply volume cap in guild {}: {}", guild_id, e);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::audio::DownloadLane;
+use crate::database::establish_connection;
+use crate::database::models::{
+    CurrentQueue, HistoryStatus, QueueHistory, QueueStatus, SongCache, VoiceConnection,
+};
+use crate::downloads;
+use crate::hooks::{self, HookEvent};
+use crate::playback_state;
+use crate::source;
+use crate::voice_state;
+
+/// Set to `1` to run without Discord: the HTTP API, database and download pipeline run as
+/// usual, while a mock voice layer "joins" channels and "plays" queued tracks
+const SIMULATE_ENV: &str = "LYRE_SIMULATE";
+/// Simulated tracks end after their real duration or this long, whichever is shorter, so
+/// queues keep moving while testing
+const MAX_SIMULATED_TRACK: Duration = Duration::from_secs(30);
+const TICK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Guilds with a simulated track playing
+static PLAYING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+/// How far each guild's simulated track has got, for pausing and reporting its position
+static PROGRESS: Lazy<Mutex<HashMap<String, Progress>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Progress {
+    position: Duration,
+    paused: bool,
+}
+
+pub fn enabled() -> bool {
+    crate::config::var(SIMULATE_ENV).as_deref() == Ok("1")
+}
+
+/// Pause or resume the guild's simulated track and return its position, or `None` if nothing
+/// is playing
+pub fn set_paused(guild_id: &str, paused: bool) -> Option<Duration> {
+    let mut progress = PROGRESS.lock().ok()?;
+    let track = progress.get_mut(guild_id)?;
+    track.paused = paused;
+    Some(track.position)
+}
+
+/// Move the guild's simulated track to `position`; returns whether it's paused, or `None` if
+/// nothing is playing
+pub fn seek(guild_id: &str, position: Duration) -> Option<bool> {
+    let mut progress = PROGRESS.lock().ok()?;
+    let track = progress.get_mut(guild_id)?;
+    track.position = position;
+    Some(track.paused)
+}
+
+/// Stand in for the Discord gateway and Songbird: treat every voice connection record as
+/// joined and play each guild's `current_queue` in order, downloading tracks for real
+pub fn spawn_voice_layer() {
+    tokio::spawn(async {
+        {
+            let mut db_conn = establish_connection();
+            if let Err(e) = VoiceConnection::clear_all_connections(&mut db_conn) {
+                error!("Failed to clear voice connection records: {}", e);
+            }
+        }
+        info!("Simulation mode: mock voice layer running, no Discord connection");
+
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let connections = {
+                let mut db_conn = establish_connection();
+                match VoiceConnection::get_all_connected(&mut db_conn) {
+                    Ok(connections) => connections,
+                    Err(e) => {
+                        error!("Failed to fetch simulated voice connections: {}", e);
+                        continue;
+                    }
+                }
+            };
+            for connection in connections {
+                let guild_id = connection.guild_id;
+                let newly_playing = PLAYING
+                    .lock()
+                    .map(|mut playing| playing.insert(guild_id.clone()))
+                    .unwrap_or(false);
+                if !newly_playing {
+                    continue;
+                }
+                tokio::spawn(async move {
+                    if let Err(e) = play_next(&guild_id).await {
+                        warn!("Simulated playback failed in guild {}: {}", guild_id, e);
+                    }
+                    if let Ok(mut playing) = PLAYING.lock() {
+                        playing.remove(&guild_id);
+                    }
+                });
+            }
+        }
+    });
+}
+
+/// Play the guild's current track, if any, until it ends or is skipped or cleared
+async fn play_next(guild_id: &str) -> Result<()> {
+    let track = {
+        let mut db_conn = establish_connection();
+        CurrentQueue::get_current_track(&mut db_conn, guild_id)?
+    };
+    let Some(mut track) = track else {
+        return Ok(());
+    };
+    let entry_id = track.id.ok_or_else(|| anyhow!("queue entry has no id"))?;
+    let title = track.title.clone().unwrap_or_else(|| track.url.clone());
+
+    // Exercise the real download pipeline; a failed download skips the track as /play would
+    CurrentQueue::set_status(
+        &mut establish_connection(),
+        entry_id,
+        QueueStatus::Downloading,
+    )?;
+    let (mut rx, handle) = source::fetch(track.url.clone(), DownloadLane::Now);
+    let _in_flight =
+        downloads::register(guild_id, &track.url, &track.added_by, handle.abort_handle());
+    while let Some(progress) = rx.recv().await {
+        downloads::publish(guild_id, &track.url, &progress);
+    }
+    let download = downloads::outcome(handle.await);
+
+    match download {
+        Ok(download) => {
+            info!(
+                "Simulation: playing \"{}\" in guild {} ({})",
+                title,
+                guild_id,
+                download.path.display()
+            );
+            // Tracked before the guild shows as playing, so it can be paused straight away
+            if let Ok(mut progress) = PROGRESS.lock() {
+                progress.insert(guild_id.to_string(), Progress::default());
+            }
+            {
+                let mut db_conn = establish_connection();
+                // The file's own length beats the extractor's estimate
+                if let Some(duration) = download.duration_secs() {
+                    track.duration = Some(duration);
+                    CurrentQueue::set_duration(&mut db_conn, entry_id, duration)?;
+                }
+                if let Err(e) = SongCache::create_or_update(
+                    &mut db_conn,
+                    &track.url,
+                    &title,
+                    track.duration,
+                    None,
+                    Some(&download.path.to_string_lossy()),
+                    download.file_size_i32(),
+                    download.format.as_deref(),
+                ) {
+                    warn!("Simulation: failed to update song cache: {}", e);
+                }
+                record_history(&mut db_conn, guild_id, &track)?;
+                CurrentQueue::set_status(&mut db_conn, entry_id, QueueStatus::Playing)?;
+                QueueHistory::mark_started(&mut db_conn, guild_id, &history_uuid(entry_id))?;
+                playback_state::track_started(
+                    guild_id,
+                    &history_uuid(entry_id),
+                    track.title.as_deref(),
+                    Some(&track.url),
+                    track.duration,
+                );
+                voice_state::set_playing(guild_id, true, Some(&title));
+            }
+            hooks::emit(
+                HookEvent::TrackStart,
+                guild_id,
+                serde_json::json!({
+                    "url": track.url,
+                    "title": track.title,
+                    "duration": track.duration,
+                    "requested_by": track.added_by,
+                }),
+            );
+            let length = track
+                .duration
+                .map(|secs| Duration::from_secs(secs.max(1) as u64))
+                .unwrap_or(MAX_SIMULATED_TRACK)
+                .min(MAX_SIMULATED_TRACK);
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                if !still_current(guild_id, entry_id) {
+                    info!("Simulation: \"{}\" skipped in guild {}", title, guild_id);
+                    return finish(guild_id, &track, HistoryStatus::Skipped);
+                }
+                // A paused track holds its position
+                let position = PROGRESS
+                    .lock()
+                    .ok()
+                    .and_then(|mut progress| {
+                        let track = progress.get_mut(guild_id)?;
+                        if !track.paused {
+                            track.position += Duration::from_secs(1);
+                        }
+                        Some(track.position)
+                    })
+                    .unwrap_or(length);
+                if position >= length {
+                    break;
+                }
+            }
+            info!("Simulation: \"{}\" finished in guild {}", title, guild_id);
+            finish(guild_id, &track, HistoryStatus::Completed)
+        }
+        Err(e) => {
+            warn!("Simulation: failed to download {}: {}", track.url, e);
+            let mut db_conn = establish_connection();
+            CurrentQueue::set_status(&mut db_conn, entry_id, QueueStatus::Failed)?;
+            record_history(&mut db_conn, guild_id, &track)?;
+            finish(guild_id, &track, HistoryStatus::Failed)
+        }
+    }
+}
+
+/// Whether `entry_id` is still the guild's playing track (not skipped, cleared or disconnected)
+fn still_current(guild_id: &str, entry_id: i32) -> bool {
+    let mut db_conn = establish_connection();
+    VoiceConnection::is_connected(&mut db_conn, guild_id)
+        && CurrentQueue::get_current_track(&mut db_conn, guild_id)
+            .ok()
+            .flatten()
+            .and_then(|track| track.id)
+            == Some(entry_id)
+}
+
+/// Simulated tracks have no Songbird handle, so their history rows and playback state are keyed
+/// by queue entry
+fn history_uuid(entry_id: i32) -> String {
+    format!("simulated-{}", entry_id)
+}
+
+fn record_history(
+    db_conn: &mut diesel::SqliteConnection,
+    guild_id: &str,
+    track: &CurrentQueue,
+) -> Result<()> {
+    QueueHistory::create(
+        db_conn,
+        guild_id,
+        &track.added_by,
+        &track.url,
+        track.title.as_deref(),
+        track.duration,
+        track.id.map(history_uuid).as_deref(),
+    )?;
+    Ok(())
+}
+
+/// Mark the guild idle and record how `track` ended; unless it was skipped (which already moved
+/// the queue on), advance past it if it's still the current track
+fn finish(guild_id: &str, track: &CurrentQueue, status: HistoryStatus) -> Result<()> {
+    if let Ok(mut progress) = PROGRESS.lock() {
+        progress.remove(guild_id);
+    }
+    let mut db_conn = establish_connection();
+    if let Some(id) = track.id {
+        playback_state::track_ended(guild_id, &history_uuid(id));
+        QueueHistory::mark_finished(&mut db_conn, guild_id, &history_uuid(id), status)?;
+        if status != HistoryStatus::Skipped && still_current(guild_id, id) {
+            CurrentQueue::advance_queue(&mut db_conn, guild_id)?;
+        }
+    }
+    voice_state::set_playing(guild_id, false, None);
+    Ok(())
+}
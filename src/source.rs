@@ -0,0 +1,84 @@
+//! Where tracks come from. A [`MediaSource`] turns a URL into metadata and a local audio file
+//! for the player; `/play`, the queue API and the simulated voice layer go through [`for_url`]
+//! so new kinds of source only need an implementation and a line in [`SOURCES`].
+
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use once_cell::sync::Lazy;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::audio::{self, DownloadLane, DownloadProgress, DownloadResult, TrackMetadata};
+use crate::direct::DirectFile;
+use crate::library::LibrarySource;
+use crate::uploads::UploadSource;
+
+/// A fetch in progress: progress updates, then the finished file and what was probed from it
+pub type Download = (
+    mpsc::UnboundedReceiver<DownloadProgress>,
+    JoinHandle<Result<DownloadResult>>,
+);
+
+pub trait MediaSource: Send + Sync {
+    /// Short name for logs
+    fn name(&self) -> &'static str;
+
+    /// Whether this source knows how to play `url`
+    fn handles(&self, url: &str) -> bool;
+
+    /// Title, duration and the other details used for screening and display
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<TrackMetadata>>;
+
+    /// Start fetching `url` into a file the player can open, in `lane` if it has to compete
+    /// with other downloads
+    fn fetch(&self, url: String, lane: DownloadLane) -> Download;
+}
+
+/// yt-dlp, which covers every site it has an extractor for and downloads to cached MP3s
+pub struct YtDlp;
+
+impl MediaSource for YtDlp {
+    fn name(&self) -> &'static str {
+        "yt-dlp"
+    }
+
+    fn handles(&self, _url: &str) -> bool {
+        true
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<TrackMetadata>> {
+        Box::pin(crate::metadata_cache::lookup(url))
+    }
+
+    fn fetch(&self, url: String, lane: DownloadLane) -> Download {
+        audio::spawn_download_mp3(url, lane)
+    }
+}
+
+/// Sources in the order they're tried; yt-dlp handles anything, so it stays last
+static SOURCES: Lazy<Vec<Box<dyn MediaSource>>> = Lazy::new(|| {
+    vec![
+        Box::new(LibrarySource),
+        Box::new(UploadSource),
+        Box::new(DirectFile),
+        Box::new(YtDlp),
+    ]
+});
+
+/// The first source that handles `url`
+pub fn for_url(url: &str) -> &'static dyn MediaSource {
+    let source = SOURCES
+        .iter()
+        .find(|source| source.handles(url))
+        .unwrap_or_else(|| SOURCES.last().expect("at least one media source"));
+    tracing::debug!("Using the {} source for {}", source.name(), url);
+    source.as_ref()
+}
+
+pub async fn extract_metadata(url: &str) -> Result<TrackMetadata> {
+    for_url(url).metadata(url).await
+}
+
+pub fn fetch(url: String, lane: DownloadLane) -> Download {
+    for_url(&url).fetch(url, lane)
+}
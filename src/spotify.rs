@@ -0,0 +1,221 @@
+//! Spotify Web API client, used to read playlists for `/playlist import`.
+//!
+//! Uses the client-credentials flow, so only public (or collaborative shared) playlists can be
+//! read. Credentials come from `SPOTIFY_CLIENT_ID`/`SPOTIFY_CLIENT_SECRET`.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use url::Url;
+
+const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+const API_ROOT: &str = "https://api.spotify.com/v1";
+/// Largest page the playlist tracks endpoint returns
+const PAGE_SIZE: usize = 100;
+/// Stop paging after this many tracks so one import can't run for hours
+pub const MAX_IMPORT_TRACKS: usize = 500;
+
+static HTTP: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent("lyre-bot/0.1 (+https://github.com/)")
+        .timeout(Duration::from_secs(15))
+        .build()
+        .expect("client")
+});
+
+/// Access token and when it stops being valid
+static TOKEN: Lazy<Mutex<Option<(String, Instant)>>> = Lazy::new(|| Mutex::new(None));
+
+pub struct SpotifyConfig {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// API credentials from the environment; `None` disables Spotify imports
+pub fn config() -> Option<SpotifyConfig> {
+    let client_id = std::env::var("SPOTIFY_CLIENT_ID")
+        .ok()
+        .filter(|v| !v.is_empty())?;
+    let client_secret = std::env::var("SPOTIFY_CLIENT_SECRET")
+        .ok()
+        .filter(|v| !v.is_empty())?;
+    Some(SpotifyConfig {
+        client_id,
+        client_secret,
+    })
+}
+
+/// Pull the playlist ID out of an `open.spotify.com/playlist/<id>` link or `spotify:playlist:<id>`
+pub fn parse_playlist_id(input: &str) -> Option<String> {
+    let input = input.trim();
+    let id = if let Some(id) = input.strip_prefix("spotify:playlist:") {
+        id.to_string()
+    } else {
+        let url = Url::parse(input).ok()?;
+        if url.host_str()? != "open.spotify.com" {
+            return None;
+        }
+        // Localized links look like /intl-de/playlist/<id>
+        let mut segments = url.path_segments()?.skip_while(|s| *s != "playlist");
+        segments.next()?;
+        segments.next()?.to_string()
+    };
+    (!id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric())).then_some(id)
+}
+
+#[derive(Debug, Clone)]
+pub struct SpotifyTrack {
+    pub name: String,
+    pub artists: Vec<String>,
+    pub duration_ms: Option<u64>,
+}
+
+impl SpotifyTrack {
+    /// Query used to find the track on YouTube
+    pub fn search_query(&self) -> String {
+        match self.artists.first() {
+            Some(artist) => format!("{} - {}", artist, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        if self.artists.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} – {}", self.artists.join(", "), self.name)
+        }
+    }
+}
+
+pub struct SpotifyPlaylist {
+    pub name: String,
+    pub tracks: Vec<SpotifyTrack>,
+    /// Whether the playlist had more than `MAX_IMPORT_TRACKS` tracks
+    pub truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct PlaylistResponse {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct TracksPage {
+    items: Vec<TracksItem>,
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TracksItem {
+    /// Null for tracks removed from Spotify
+    track: Option<TrackObject>,
+}
+
+#[derive(Deserialize)]
+struct TrackObject {
+    name: String,
+    #[serde(default)]
+    artists: Vec<ArtistObject>,
+    duration_ms: Option<u64>,
+    /// Podcast episodes can appear in playlists too; they have no YouTube match to find
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ArtistObject {
+    name: String,
+}
+
+async fn access_token(config: &SpotifyConfig) -> Result<String> {
+    if let Some((token, expires)) = TOKEN.lock().unwrap().as_ref()
+        && Instant::now() < *expires
+    {
+        return Ok(token.clone());
+    }
+
+    let response: TokenResponse = HTTP
+        .post(TOKEN_URL)
+        .basic_auth(&config.client_id, Some(&config.client_secret))
+        .form(&[("grant_type", "client_credentials")])
+        .send()
+        .await
+        .context("requesting Spotify access token")?
+        .error_for_status()
+        .context("Spotify rejected the client credentials")?
+        .json()
+        .await
+        .context("parsing Spotify token response")?;
+
+    // Renew a minute early so a token never expires mid-import
+    let expires = Instant::now() + Duration::from_secs(response.expires_in.saturating_sub(60));
+    *TOKEN.lock().unwrap() = Some((response.access_token.clone(), expires));
+    Ok(response.access_token)
+}
+
+async fn get_json<T: for<'de> Deserialize<'de>>(config: &SpotifyConfig, url: &str) -> Result<T> {
+    let token = access_token(config).await?;
+    let response = HTTP
+        .get(url)
+        .bearer_auth(token)
+        .send()
+        .await
+        .context("calling Spotify")?;
+    match response.status().as_u16() {
+        404 => Err(anyhow!("playlist not found (it may be private)")),
+        status if status >= 400 => Err(anyhow!("Spotify returned HTTP {}", status)),
+        _ => response.json().await.context("parsing Spotify response"),
+    }
+}
+
+/// Fetch a playlist's name and tracks, following pagination up to `MAX_IMPORT_TRACKS`
+pub async fn fetch_playlist(config: &SpotifyConfig, playlist_id: &str) -> Result<SpotifyPlaylist> {
+    let playlist: PlaylistResponse = get_json(
+        config,
+        &format!("{}/playlists/{}?fields=name", API_ROOT, playlist_id),
+    )
+    .await?;
+
+    let mut tracks = Vec::new();
+    let mut next = Some(format!(
+        "{}/playlists/{}/tracks?limit={}&fields=next,items(track(name,type,duration_ms,artists(name)))",
+        API_ROOT, playlist_id, PAGE_SIZE
+    ));
+    let mut truncated = false;
+    while let Some(url) = next.take() {
+        let page: TracksPage = get_json(config, &url).await?;
+        for item in page.items {
+            let Some(track) = item.track else { continue };
+            if track.kind.as_deref().is_some_and(|k| k != "track") {
+                continue;
+            }
+            tracks.push(SpotifyTrack {
+                name: track.name,
+                artists: track.artists.into_iter().map(|a| a.name).collect(),
+                duration_ms: track.duration_ms,
+            });
+        }
+        if tracks.len() >= MAX_IMPORT_TRACKS {
+            truncated = tracks.len() > MAX_IMPORT_TRACKS || page.next.is_some();
+            tracks.truncate(MAX_IMPORT_TRACKS);
+            break;
+        }
+        next = page.next;
+    }
+
+    Ok(SpotifyPlaylist {
+        name: playlist.name,
+        tracks,
+        truncated,
+    })
+}
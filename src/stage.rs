@@ -0,0 +1,221 @@
+//! Announcing the playing track outside the chat: as the topic of the Stage channel the bot is
+//! playing in, and, for guilds that turn it on, in a "listening party" scheduled event kept open
+//! for the length of the session. Both are best-effort; missing permissions are logged and
+//! playback carries on.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serenity::all::{
+    ChannelId, ChannelType, Context, CreateScheduledEvent, CreateStageInstance, EditScheduledEvent,
+    EditStageInstance, GuildId, ScheduledEventId, ScheduledEventStatus, ScheduledEventType,
+    Timestamp,
+};
+
+use crate::broadcast;
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+
+/// Discord's limit on stage topics
+const MAX_TOPIC_LEN: usize = 120;
+const PARTY_EVENT_NAME: &str = "Listening party";
+
+/// What the bot has put up in each guild this session, so it can update and take it down
+static SESSIONS: Lazy<Mutex<HashMap<GuildId, StageSession>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Default, Clone)]
+struct StageSession {
+    last_title: Option<String>,
+    /// A stage the bot opened itself, rather than one someone else was running
+    opened_stage: Option<ChannelId>,
+    party_event: Option<ScheduledEventId>,
+}
+
+fn session(guild_id: GuildId) -> StageSession {
+    SESSIONS
+        .lock()
+        .ok()
+        .and_then(|sessions| sessions.get(&guild_id).cloned())
+        .unwrap_or_default()
+}
+
+fn save_session(guild_id: GuildId, session: StageSession) {
+    if let Ok(mut sessions) = SESSIONS.lock() {
+        sessions.insert(guild_id, session);
+    }
+}
+
+/// Announce `title` as the guild's playing track, per its settings
+pub async fn track_started(guild_id: GuildId, title: &str) {
+    let Some(ctx) = broadcast::context() else {
+        return;
+    };
+    let settings =
+        GuildSettings::find_by_guild_id(&mut establish_connection(), &guild_id.to_string())
+            .ok()
+            .flatten();
+    let (stage_topic, party_event) = settings
+        .map(|s| (s.stage_topic, s.listening_party_event))
+        .unwrap_or((true, false));
+    if !stage_topic && !party_event {
+        return;
+    }
+
+    let mut session = session(guild_id);
+    // Resuming after a pause starts the same track again
+    if session.last_title.as_deref() == Some(title) {
+        return;
+    }
+    let Some(channel_id) = voice_channel(&ctx, guild_id).await else {
+        return;
+    };
+    let is_stage = ctx.cache.guild(guild_id).is_some_and(|guild| {
+        guild
+            .channels
+            .get(&channel_id)
+            .is_some_and(|channel| channel.kind == ChannelType::Stage)
+    });
+
+    if stage_topic && is_stage {
+        set_stage_topic(&ctx, channel_id, title, &mut session).await;
+    }
+    if party_event {
+        update_party_event(&ctx, guild_id, channel_id, is_stage, title, &mut session).await;
+    }
+    session.last_title = Some(title.to_string());
+    save_session(guild_id, session);
+}
+
+/// Take down what the session put up: close a stage the bot opened and end its party event
+pub async fn session_ended(guild_id: GuildId) {
+    let Some(session) = SESSIONS
+        .lock()
+        .ok()
+        .and_then(|mut sessions| sessions.remove(&guild_id))
+    else {
+        return;
+    };
+    let Some(ctx) = broadcast::context() else {
+        return;
+    };
+    if let Some(channel_id) = session.opened_stage
+        && let Err(e) = channel_id.delete_stage_instance(&ctx.http).await
+    {
+        tracing::debug!("Couldn't close stage in guild {}: {}", guild_id, e);
+    }
+    if let Some(event_id) = session.party_event
+        && let Err(e) = guild_id
+            .edit_scheduled_event(
+                &ctx,
+                event_id,
+                EditScheduledEvent::new().status(ScheduledEventStatus::Completed),
+            )
+            .await
+    {
+        tracing::debug!("Couldn't end listening party in guild {}: {}", guild_id, e);
+    }
+}
+
+async fn voice_channel(ctx: &Context, guild_id: GuildId) -> Option<ChannelId> {
+    let manager = songbird::get(ctx).await?;
+    let call_lock = manager.get(guild_id)?;
+    let channel = call_lock.lock().await.current_channel()?;
+    Some(ChannelId::new(channel.0.get()))
+}
+
+fn topic_for(title: &str) -> String {
+    let topic = format!("🎶 {}", title);
+    if topic.chars().count() <= MAX_TOPIC_LEN {
+        return topic;
+    }
+    let mut shortened: String = topic.chars().take(MAX_TOPIC_LEN - 1).collect();
+    shortened.push('…');
+    shortened
+}
+
+async fn set_stage_topic(
+    ctx: &Context,
+    channel_id: ChannelId,
+    title: &str,
+    session: &mut StageSession,
+) {
+    let topic = topic_for(title);
+    let edited = channel_id
+        .edit_stage_instance(ctx, EditStageInstance::new().topic(&topic))
+        .await;
+    if edited.is_ok() {
+        return;
+    }
+    // Nobody has the stage open yet, so open it
+    match channel_id
+        .create_stage_instance(ctx, CreateStageInstance::new(&topic))
+        .await
+    {
+        Ok(_) => session.opened_stage = Some(channel_id),
+        Err(e) => tracing::warn!("Couldn't set the stage topic in {}: {}", channel_id, e),
+    }
+}
+
+async fn update_party_event(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    is_stage: bool,
+    title: &str,
+    session: &mut StageSession,
+) {
+    let description = format!("Now playing: {}", title);
+    if let Some(event_id) = session.party_event {
+        match guild_id
+            .edit_scheduled_event(
+                ctx,
+                event_id,
+                EditScheduledEvent::new().description(&description),
+            )
+            .await
+        {
+            Ok(_) => return,
+            // Ended or deleted by someone; open a new one
+            Err(e) => tracing::debug!("Listening party event {} is gone: {}", event_id, e),
+        }
+    }
+
+    let kind = if is_stage {
+        ScheduledEventType::StageInstance
+    } else {
+        ScheduledEventType::Voice
+    };
+    // Events can't be created in the past, so schedule it a moment ahead and start it at once
+    let start = Timestamp::from_unix_timestamp(chrono::Utc::now().timestamp() + 60)
+        .unwrap_or_else(|_| Timestamp::now());
+    let created = guild_id
+        .create_scheduled_event(
+            ctx,
+            CreateScheduledEvent::new(kind, PARTY_EVENT_NAME, start)
+                .channel_id(channel_id)
+                .description(&description),
+        )
+        .await;
+    match created {
+        Ok(event) => {
+            session.party_event = Some(event.id);
+            if let Err(e) = guild_id
+                .edit_scheduled_event(
+                    ctx,
+                    event.id,
+                    EditScheduledEvent::new().status(ScheduledEventStatus::Active),
+                )
+                .await
+            {
+                tracing::debug!("Couldn't start listening party in {}: {}", guild_id, e);
+            }
+        }
+        Err(e) => tracing::warn!(
+            "Couldn't create listening party event in guild {}: {}",
+            guild_id,
+            e
+        ),
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use serde::Serialize;
+
+use crate::database::models::QueueHistory;
+
+/// Aggregate listening figures computed from `queue_history` rows
+#[derive(Debug, Default, Serialize)]
+pub struct ListeningStats {
+    pub plays: usize,
+    /// Time spent listening: each track's start-to-finish time where it was recorded, else its
+    /// duration (tracks without either don't contribute)
+    pub total_seconds: i64,
+    /// Most requested tracks, most plays first
+    pub top_tracks: Vec<TrackPlays>,
+    /// Members who requested the most tracks, most plays first
+    pub top_requesters: Vec<RequesterPlays>,
+    /// UTC hour of day (0-23) with the most requests
+    pub top_hour: Option<u32>,
+    /// UTC date with the most requests
+    pub busiest_day: Option<DayPlays>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackPlays {
+    pub url: String,
+    pub title: Option<String>,
+    pub plays: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequesterPlays {
+    pub user_id: String,
+    pub plays: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DayPlays {
+    pub date: NaiveDate,
+    pub plays: usize,
+}
+
+/// Summarize `history` (oldest first), keeping the `top_n` entries of each ranking
+pub fn summarize(history: &[QueueHistory], top_n: usize) -> ListeningStats {
+    let mut per_track: HashMap<&str, TrackPlays> = HashMap::new();
+    let mut per_user: HashMap<&str, usize> = HashMap::new();
+    let mut per_day: HashMap<NaiveDate, usize> = HashMap::new();
+    let mut per_hour = [0usize; 24];
+    let mut total_seconds = 0i64;
+
+    for entry in history {
+        let track = per_track
+            .entry(entry.url.as_str())
+            .or_insert_with(|| TrackPlays {
+                url: entry.url.clone(),
+                title: None,
+                plays: 0,
+            });
+        track.plays += 1;
+        // Prefer the most recent known title, since history is oldest first
+        if entry.title.is_some() {
+            track.title = entry.title.clone();
+        }
+        *per_user.entry(entry.user_id.as_str()).or_default() += 1;
+        *per_day.entry(entry.played_at.date()).or_default() += 1;
+        per_hour[entry.played_at.hour() as usize] += 1;
+        total_seconds += entry.listened_secs();
+    }
+
+    let mut top_tracks: Vec<TrackPlays> = per_track.into_values().collect();
+    top_tracks.sort_by(|a, b| b.plays.cmp(&a.plays).then_with(|| a.url.cmp(&b.url)));
+    top_tracks.truncate(top_n);
+
+    let mut top_requesters: Vec<RequesterPlays> = per_user
+        .into_iter()
+        .map(|(user_id, plays)| RequesterPlays {
+            user_id: user_id.to_string(),
+            plays,
+        })
+        .collect();
+    top_requesters.sort_by(|a, b| {
+        b.plays
+            .cmp(&a.plays)
+            .then_with(|| a.user_id.cmp(&b.user_id))
+    });
+    top_requesters.truncate(top_n);
+
+    let top_hour = (0..24u32)
+        .filter(|h| per_hour[*h as usize] > 0)
+        .max_by_key(|h| (per_hour[*h as usize], std::cmp::Reverse(*h)));
+
+    // Ties go to the earlier day
+    let busiest_day = per_day
+        .into_iter()
+        .max_by_key(|(date, plays)| (*plays, std::cmp::Reverse(*date)))
+        .map(|(date, plays)| DayPlays { date, plays });
+
+    ListeningStats {
+        plays: history.len(),
+        total_seconds,
+        top_tracks,
+        top_requesters,
+        top_hour,
+        busiest_day,
+    }
+}
+
+/// Earliest year `/wrapped` accepts; Discord (and so any history) doesn't predate it
+pub const MIN_WRAPPED_YEAR: i32 = 2015;
+
+/// Half-open UTC range covering the calendar year, for `QueueHistory::find_for_stats`
+pub fn year_bounds(year: i32) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let start = NaiveDate::from_ymd_opt(year, 1, 1)?.and_hms_opt(0, 0, 0)?;
+    let end = NaiveDate::from_ymd_opt(year + 1, 1, 1)?.and_hms_opt(0, 0, 0)?;
+    Some((start, end))
+}
+
+/// Human-readable listening time, e.g. `3h 12m` or `45m`
+pub fn format_listening_time(total_seconds: i64) -> String {
+    let minutes = total_seconds / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
@@ -0,0 +1,142 @@
+//! Per-guild look of playback embeds: an accent colour, an emoji set and extra footer text.
+//! Guilds that haven't set a theme get the colours and emoji the embeds always had.
+
+use serenity::all::{CreateEmbed, CreateEmbedFooter};
+
+use crate::database::establish_connection;
+use crate::database::models::GuildSettings;
+
+/// Longest custom footer, well under Discord's 2048 character footer limit
+pub const MAX_FOOTER_LEN: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmojiSet {
+    /// The emoji the embeds have always used
+    Classic,
+    /// Plain symbols for servers that find emoji noisy
+    Minimal,
+    /// No icons at all
+    None,
+}
+
+impl EmojiSet {
+    pub const ALL: [EmojiSet; 3] = [EmojiSet::Classic, EmojiSet::Minimal, EmojiSet::None];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            EmojiSet::Classic => "classic",
+            EmojiSet::Minimal => "minimal",
+            EmojiSet::None => "none",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|set| set.key() == key)
+    }
+
+    fn icon(self, icon: Icon) -> &'static str {
+        match (self, icon) {
+            (EmojiSet::None, _) => "",
+            (EmojiSet::Classic, Icon::NowPlaying | Icon::QueueFinished) => "🎵",
+            (EmojiSet::Classic, Icon::Skip) => "⏭️",
+            (EmojiSet::Classic, Icon::Queue) => "📜",
+            (EmojiSet::Classic, Icon::Pause) => "⏸️",
+            (EmojiSet::Classic, Icon::Resume) => "▶️",
+            (EmojiSet::Classic, Icon::Seek) => "⏩",
+            (EmojiSet::Minimal, Icon::NowPlaying) => "♪",
+            (EmojiSet::Minimal, Icon::QueueFinished) => "■",
+            (EmojiSet::Minimal, Icon::Skip) => "»",
+            (EmojiSet::Minimal, Icon::Queue) => "≡",
+            (EmojiSet::Minimal, Icon::Pause) => "‖",
+            (EmojiSet::Minimal, Icon::Resume) => "▸",
+            (EmojiSet::Minimal, Icon::Seek) => "↦",
+        }
+    }
+}
+
+/// What an embed is about, which picks its icon
+#[derive(Debug, Clone, Copy)]
+pub enum Icon {
+    NowPlaying,
+    QueueFinished,
+    Skip,
+    Queue,
+    Pause,
+    Resume,
+    Seek,
+}
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub accent: Option<u32>,
+    pub emoji: EmojiSet,
+    pub footer: Option<String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            accent: None,
+            emoji: EmojiSet::Classic,
+            footer: None,
+        }
+    }
+}
+
+impl From<&GuildSettings> for Theme {
+    fn from(settings: &GuildSettings) -> Self {
+        Self {
+            accent: settings.embed_color.map(|c| c as u32),
+            emoji: EmojiSet::from_key(&settings.emoji_set).unwrap_or(EmojiSet::Classic),
+            footer: settings.embed_footer.clone(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn for_guild(guild_id: &str) -> Self {
+        let mut db_conn = establish_connection();
+        GuildSettings::find_by_guild_id(&mut db_conn, guild_id)
+            .ok()
+            .flatten()
+            .map(|settings| Theme::from(&settings))
+            .unwrap_or_default()
+    }
+
+    /// An embed titled `title` with this theme's icon, colour and footer; `colour` is used
+    /// unless the guild picked an accent
+    pub fn embed(&self, icon: Icon, title: &str, colour: u32) -> CreateEmbed {
+        let title = match self.emoji.icon(icon) {
+            "" => title.to_string(),
+            icon => format!("{} {}", icon, title),
+        };
+        let embed = CreateEmbed::new()
+            .title(title)
+            .colour(self.accent.unwrap_or(colour));
+        match &self.footer {
+            Some(footer) => embed.footer(CreateEmbedFooter::new(footer)),
+            None => embed,
+        }
+    }
+
+    /// A footer reading `text` followed by the guild's footer, for embeds with their own
+    pub fn footer_with(&self, text: &str) -> CreateEmbedFooter {
+        match &self.footer {
+            Some(footer) => CreateEmbedFooter::new(format!("{} • {}", text, footer)),
+            None => CreateEmbedFooter::new(text),
+        }
+    }
+}
+
+/// Parse `#RRGGBB` (the `#` is optional) into a colour
+pub fn parse_hex_color(value: &str) -> Option<u32> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+pub fn format_hex_color(colour: u32) -> String {
+    format!("#{:06X}", colour)
+}
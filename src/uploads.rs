@@ -0,0 +1,232 @@
+//! Where uploaded audio files live and what's let in: per-file and per-guild size limits, a
+//! check that the bytes really are audio, and an optional malware scanner run on each file.
+//! Finished uploads are played as `upload:<id>` through [`UploadSource`].
+
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use futures_util::future::BoxFuture;
+use ring::rand::{SecureRandom, SystemRandom};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+use crate::audio::{self, DownloadLane, DownloadPhase, DownloadProgress, TrackMetadata};
+use crate::database::establish_connection;
+use crate::database::models::Upload;
+use crate::source::{Download, MediaSource};
+
+/// Folder uploaded files are kept in, one subfolder per guild. Defaults to
+/// `$XDG_CACHE_HOME/lyre/uploads`.
+const UPLOAD_FOLDER_ENV: &str = "LYRE_UPLOAD_FOLDER";
+/// Largest single upload, in MB
+const MAX_FILE_MB_ENV: &str = "LYRE_UPLOAD_MAX_FILE_MB";
+/// Most MB of uploads each guild may keep, counting unfinished ones
+const GUILD_QUOTA_MB_ENV: &str = "LYRE_UPLOAD_GUILD_QUOTA_MB";
+/// Command run on each finished upload with the file's path appended (e.g. `clamdscan
+/// --no-summary`); a non-zero exit rejects the file
+const SCANNER_ENV: &str = "LYRE_UPLOAD_SCANNER";
+
+/// URL scheme of finished uploads, and the cache folder they're converted into
+const SCHEME: &str = "upload";
+
+const DEFAULT_MAX_FILE_MB: u64 = 100;
+const DEFAULT_GUILD_QUOTA_MB: u64 = 1024;
+/// Most bytes accepted in one chunk; clients send larger files in several
+pub const MAX_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+fn megabytes(key: &str, default: u64) -> u64 {
+    let mb = crate::config::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|mb| *mb > 0)
+        .unwrap_or(default);
+    // Sizes are stored as 32-bit integers
+    (mb * 1024 * 1024).min(i32::MAX as u64)
+}
+
+pub fn max_file_bytes() -> u64 {
+    megabytes(MAX_FILE_MB_ENV, DEFAULT_MAX_FILE_MB)
+}
+
+pub fn guild_quota_bytes() -> u64 {
+    megabytes(GUILD_QUOTA_MB_ENV, DEFAULT_GUILD_QUOTA_MB)
+}
+
+fn base_dir() -> Result<PathBuf> {
+    if let Ok(dir) = crate::config::var(UPLOAD_FOLDER_ENV) {
+        let dir = PathBuf::from(dir);
+        return Ok(if dir.is_absolute() {
+            dir
+        } else {
+            std::env::current_dir()?.join(dir)
+        });
+    }
+    let cache =
+        dirs::cache_dir().ok_or_else(|| anyhow!("no cache dir available on this system"))?;
+    Ok(cache.join("lyre").join("uploads"))
+}
+
+/// A fresh upload ID; it names the file on disk, so it's random hex and nothing the client picks
+pub fn new_id() -> Result<String> {
+    let mut buf = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut buf)
+        .map_err(|_| anyhow!("no randomness for upload IDs"))?;
+    Ok(hex::encode(buf))
+}
+
+/// The URL upload `id` is queued as
+pub fn track_url(id: &str) -> String {
+    format!("{}:{}", SCHEME, id)
+}
+
+/// The upload an `upload:<id>` URL names; IDs are always the 32 hex digits from [`new_id`]
+pub fn track_id(url: &str) -> Option<&str> {
+    url.trim()
+        .strip_prefix(SCHEME)?
+        .strip_prefix(':')
+        .filter(|id| id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Where upload `id` of `guild_id` is stored once it's complete
+pub fn file_path(guild_id: &str, id: &str) -> Result<PathBuf> {
+    Ok(base_dir()?.join(guild_id).join(id))
+}
+
+/// Where upload `id` of `guild_id` collects its chunks until it's complete
+pub fn partial_path(guild_id: &str, id: &str) -> Result<PathBuf> {
+    Ok(base_dir()?.join(guild_id).join(format!("{}.part", id)))
+}
+
+/// The audio format `head` (a file's first bytes) starts like, as a MIME type. Anything else,
+/// executables and archives included, is turned away.
+pub fn sniff_audio(head: &[u8]) -> Option<&'static str> {
+    match head {
+        [b'I', b'D', b'3', ..] => Some("audio/mpeg"),
+        // MPEG audio frame sync
+        [0xFF, second, ..] if second & 0xE0 == 0xE0 => Some("audio/mpeg"),
+        [b'O', b'g', b'g', b'S', ..] => Some("audio/ogg"),
+        [b'f', b'L', b'a', b'C', ..] => Some("audio/flac"),
+        [
+            b'R',
+            b'I',
+            b'F',
+            b'F',
+            _,
+            _,
+            _,
+            _,
+            b'W',
+            b'A',
+            b'V',
+            b'E',
+            ..,
+        ] => Some("audio/wav"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("audio/mp4"),
+        // Matroska/WebM
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => Some("audio/webm"),
+        _ => None,
+    }
+}
+
+/// Run the configured scanner on a finished upload; `Err` carries why it was rejected
+pub async fn scan(path: &std::path::Path) -> Result<()> {
+    let Ok(command) = crate::config::var(SCANNER_ENV) else {
+        return Ok(());
+    };
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        return Ok(());
+    };
+    let output = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .output()
+        .await
+        .map_err(|e| anyhow!("couldn't run the upload scanner: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    tracing::warn!(
+        "Upload scanner rejected {}: {}",
+        path.display(),
+        String::from_utf8_lossy(&output.stdout).trim()
+    );
+    Err(anyhow!("the upload scanner flagged this file"))
+}
+
+fn find_upload(url: &str) -> Result<Upload> {
+    let id = track_id(url).ok_or_else(|| anyhow!("not an upload: {}", url))?;
+    Upload::find_completed(&mut establish_connection(), id)?
+        .ok_or_else(|| anyhow!("there's no finished upload {}", id))
+}
+
+/// Finished uploads, converted into the cache on first play. Which guild may play one is
+/// checked by [`crate::policy::check_upload_in_guild`] before it's queued.
+pub struct UploadSource;
+
+impl MediaSource for UploadSource {
+    fn name(&self) -> &'static str {
+        "upload"
+    }
+
+    fn handles(&self, url: &str) -> bool {
+        track_id(url).is_some()
+    }
+
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<TrackMetadata>> {
+        Box::pin(async move {
+            let upload = find_upload(url)?;
+            let path = file_path(&upload.guild_id, &upload.id)?;
+            let tags = audio::probe_tags(&path).await.unwrap_or_else(|e| {
+                tracing::debug!("Couldn't read tags of upload {}: {}", upload.id, e);
+                audio::FileTags::default()
+            });
+            let stem = std::path::Path::new(&upload.filename)
+                .file_stem()
+                .map(|s| s.to_string_lossy().trim().to_string())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| upload.filename.clone());
+            let title = tags.title.unwrap_or(stem);
+            Ok(TrackMetadata {
+                id: Some(upload.id),
+                age_limit: None,
+                duration: tags.duration,
+                artist: tags.artist,
+                track: Some(title.clone()),
+                album: tags.album,
+                uploader: None,
+                extractor_key: Some(SCHEME.to_string()),
+                title,
+            })
+        })
+    }
+
+    /// Nothing is downloaded, so `_lane` is unused
+    fn fetch(&self, url: String, _lane: DownloadLane) -> Download {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(async move {
+            let upload = find_upload(&url)?;
+            // Uploads never change once finished, so the ID alone names the converted file
+            let result = match audio::cached_download(SCHEME, &upload.id).await {
+                Some(cached) => Ok(cached),
+                None => {
+                    let path = file_path(&upload.guild_id, &upload.id)?;
+                    let _ = tx.send(DownloadProgress {
+                        phase: DownloadPhase::Converting,
+                        percent: 100,
+                    });
+                    audio::cache_converted(&path, SCHEME, &upload.id).await
+                }
+            };
+            if result.is_ok() {
+                let _ = tx.send(DownloadProgress {
+                    phase: DownloadPhase::Ready,
+                    percent: 100,
+                });
+            }
+            result
+        });
+        (rx, handle)
+    }
+}
@@ -0,0 +1,606 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use thiserror::Error;
+use url::{Host, Url};
+
+/// Longest URL accepted from users before it is handed to yt-dlp
+pub const MAX_URL_LEN: usize = 2048;
+/// Discord snowflakes are u64s, so never more than 20 decimal digits
+const MAX_SNOWFLAKE_DIGITS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ValidationError {
+    #[error("{field} must be a Discord ID (digits only)")]
+    InvalidSnowflake { field: &'static str },
+    #[error("URL must be at most {max} characters")]
+    UrlTooLong { max: usize },
+    #[error("URL is not valid: {reason}")]
+    MalformedUrl { reason: String },
+    #[error("Only http and https URLs are supported (got {scheme})")]
+    UnsupportedScheme { scheme: String },
+    #[error("URL must include a host")]
+    MissingHost,
+    #[error("URLs pointing at local or private addresses are not allowed")]
+    PrivateHost,
+    #[error("{field} contains an invalid host name: {host}")]
+    InvalidHost { field: &'static str, host: String },
+    #[error("{field} entries must be 1 to {max} characters: {entry}")]
+    InvalidEntry {
+        field: &'static str,
+        entry: String,
+        max: usize,
+    },
+    #[error("{field} may contain at most {max} entries")]
+    TooManyEntries { field: &'static str, max: usize },
+    #[error("{field} must be between {min} and {max}")]
+    OutOfRange {
+        field: &'static str,
+        min: String,
+        max: String,
+    },
+    #[error("{field} must be {expected}")]
+    InvalidFormat {
+        field: &'static str,
+        expected: &'static str,
+    },
+}
+
+impl ValidationError {
+    /// Name of the offending input, when the error is tied to a single field
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            Self::InvalidSnowflake { field }
+            | Self::InvalidHost { field, .. }
+            | Self::InvalidEntry { field, .. }
+            | Self::TooManyEntries { field, .. }
+            | Self::OutOfRange { field, .. }
+            | Self::InvalidFormat { field, .. } => Some(field),
+            Self::UrlTooLong { .. }
+            | Self::MalformedUrl { .. }
+            | Self::UnsupportedScheme { .. }
+            | Self::MissingHost
+            | Self::PrivateHost => Some("url"),
+        }
+    }
+}
+
+/// Implemented by request bodies and query strings that check themselves after deserializing
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationError>;
+}
+
+/// Parse a Discord snowflake (guild, channel, user or role ID)
+pub fn validate_snowflake(field: &'static str, value: &str) -> Result<u64, ValidationError> {
+    let err = || ValidationError::InvalidSnowflake { field };
+    if value.is_empty()
+        || value.len() > MAX_SNOWFLAKE_DIGITS
+        || !value.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(err());
+    }
+    match value.parse::<u64>() {
+        Ok(0) | Err(_) => Err(err()),
+        Ok(id) => Ok(id),
+    }
+}
+
+/// Check a user-supplied media URL before it reaches yt-dlp.
+///
+/// Only absolute http(s) URLs with a public host are allowed, so users can't point the bot at
+/// local files or services on the host's private network.
+pub fn validate_media_url(raw: &str) -> Result<Url, ValidationError> {
+    let raw = raw.trim();
+    if raw.len() > MAX_URL_LEN {
+        return Err(ValidationError::UrlTooLong { max: MAX_URL_LEN });
+    }
+
+    let url = Url::parse(raw).map_err(|e| ValidationError::MalformedUrl {
+        reason: e.to_string(),
+    })?;
+
+    // Tracks in the local library, when there is one
+    if crate::library::track_id(url.as_str()).is_some() && crate::library::enabled() {
+        return Ok(url);
+    }
+    // Files uploaded to a guild; which guild may play one is checked when it's queued
+    if crate::uploads::track_id(url.as_str()).is_some() {
+        return Ok(url);
+    }
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ValidationError::UnsupportedScheme {
+            scheme: url.scheme().to_string(),
+        });
+    }
+
+    match url.host() {
+        None => return Err(ValidationError::MissingHost),
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            if domain == "localhost" || domain.ends_with(".localhost") || domain.ends_with(".local")
+            {
+                return Err(ValidationError::PrivateHost);
+            }
+        }
+        Some(Host::Ipv4(ip)) => {
+            if is_private_ip(IpAddr::V4(ip)) {
+                return Err(ValidationError::PrivateHost);
+            }
+        }
+        Some(Host::Ipv6(ip)) => {
+            if is_private_ip(IpAddr::V6(ip)) {
+                return Err(ValidationError::PrivateHost);
+            }
+        }
+    }
+
+    Ok(url)
+}
+
+/// Whether `ip` is loopback, link-local, on a private network or otherwise not on the internet
+pub fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_private_v4(v4),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_private_v4(v4);
+            }
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_unique_local()
+                || v6.is_unicast_link_local()
+        }
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // Carrier-grade NAT (100.64.0.0/10)
+        || (ip.octets()[0] == 100 && (ip.octets()[1] & 0xC0) == 64)
+}
+
+/// Check a list of bare host names such as `youtube.com` (no scheme, path or port)
+pub fn validate_host_list(
+    field: &'static str,
+    hosts: &[String],
+    max: usize,
+) -> Result<(), ValidationError> {
+    if hosts.len() > max {
+        return Err(ValidationError::TooManyEntries { field, max });
+    }
+    for host in hosts {
+        let bare = host.trim().trim_start_matches("*.");
+        match Host::parse(bare) {
+            Ok(Host::Domain(d)) if !d.is_empty() && !d.contains(['/', ':']) => {}
+            _ => {
+                return Err(ValidationError::InvalidHost {
+                    field,
+                    host: host.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check a list of short free-text entries, such as banned title keywords
+pub fn validate_keyword_list(
+    field: &'static str,
+    keywords: &[String],
+    max_entries: usize,
+    max_len: usize,
+) -> Result<(), ValidationError> {
+    if keywords.len() > max_entries {
+        return Err(ValidationError::TooManyEntries {
+            field,
+            max: max_entries,
+        });
+    }
+    for keyword in keywords {
+        let len = keyword.trim().chars().count();
+        if len == 0 || len > max_len {
+            return Err(ValidationError::InvalidEntry {
+                field,
+                entry: keyword.clone(),
+                max: max_len,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Loudest volume that can be set or allowed, twice the source level
+pub const MAX_VOLUME: f32 = 2.0;
+
+/// Volume is a linear gain where 1.0 is the source level
+pub fn validate_volume(field: &'static str, volume: f32) -> Result<f32, ValidationError> {
+    validate_range(field, volume, 0.0, MAX_VOLUME)
+}
+
+pub fn validate_range<T>(
+    field: &'static str,
+    value: T,
+    min: T,
+    max: T,
+) -> Result<T, ValidationError>
+where
+    T: PartialOrd + std::fmt::Display + Copy,
+{
+    // Written as a negated range check so NaN is rejected too
+    if !(value >= min && value <= max) {
+        return Err(ValidationError::OutOfRange {
+            field,
+            min: min.to_string(),
+            max: max.to_string(),
+        });
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Resolve optional `limit`/`offset` query parameters, rejecting values outside `1..=max_limit`
+/// and negative offsets rather than silently clamping them.
+pub fn validate_pagination(
+    limit: Option<i64>,
+    offset: Option<i64>,
+    default_limit: i64,
+    max_limit: i64,
+) -> Result<Pagination, ValidationError> {
+    let limit = validate_range("limit", limit.unwrap_or(default_limit), 1, max_limit)?;
+    let offset = validate_range("offset", offset.unwrap_or(0), 0, i64::MAX)?;
+    Ok(Pagination { limit, offset })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn snowflakes_are_bounded_digit_strings() {
+        assert_eq!(validate_snowflake("guild_id", "1"), Ok(1));
+        assert_eq!(
+            validate_snowflake("guild_id", "18446744073709551615"),
+            Ok(u64::MAX)
+        );
+        for bad in [
+            "",
+            "0",
+            "00000000000000000000",
+            "18446744073709551616",
+            "123456789012345678901",
+            "-1",
+            "+1",
+            " 1",
+            "1 ",
+            "12a",
+            "١٢٣",
+            "１２３",
+            "1e5",
+        ] {
+            assert_eq!(
+                validate_snowflake("guild_id", bad),
+                Err(ValidationError::InvalidSnowflake { field: "guild_id" }),
+                "{bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn media_urls_need_http_and_a_public_host() {
+        for good in [
+            "https://www.youtube.com/watch?v=abc",
+            "http://example.com/song.mp3",
+            "  https://soundcloud.com/a/b  ",
+            "https://8.8.8.8/x",
+            "https://[2001:4860:4860::8888]/x",
+        ] {
+            assert!(validate_media_url(good).is_ok(), "{good}");
+        }
+        for (bad, expected) in [
+            (
+                "file:///etc/passwd",
+                ValidationError::UnsupportedScheme {
+                    scheme: "file".to_string(),
+                },
+            ),
+            (
+                "ftp://example.com/a",
+                ValidationError::UnsupportedScheme {
+                    scheme: "ftp".to_string(),
+                },
+            ),
+            (
+                "javascript:alert(1)",
+                ValidationError::UnsupportedScheme {
+                    scheme: "javascript".to_string(),
+                },
+            ),
+            ("http://localhost/a", ValidationError::PrivateHost),
+            ("http://api.localhost/a", ValidationError::PrivateHost),
+            ("http://printer.local./a", ValidationError::PrivateHost),
+            ("http://127.0.0.1/a", ValidationError::PrivateHost),
+            ("http://127.1/a", ValidationError::PrivateHost),
+            ("http://2130706433/a", ValidationError::PrivateHost),
+            ("http://10.0.0.5/a", ValidationError::PrivateHost),
+            ("http://172.16.0.1/a", ValidationError::PrivateHost),
+            ("http://192.168.1.1/a", ValidationError::PrivateHost),
+            (
+                "http://169.254.169.254/latest",
+                ValidationError::PrivateHost,
+            ),
+            ("http://100.64.0.1/a", ValidationError::PrivateHost),
+            ("http://0.0.0.0/a", ValidationError::PrivateHost),
+            ("http://255.255.255.255/a", ValidationError::PrivateHost),
+            ("http://[::1]/a", ValidationError::PrivateHost),
+            ("http://[::]/a", ValidationError::PrivateHost),
+            ("http://[fd00::1]/a", ValidationError::PrivateHost),
+            ("http://[fe80::1]/a", ValidationError::PrivateHost),
+            ("http://[::ffff:127.0.0.1]/a", ValidationError::PrivateHost),
+            ("http://[::ffff:10.0.0.1]/a", ValidationError::PrivateHost),
+        ] {
+            assert_eq!(validate_media_url(bad), Err(expected), "{bad}");
+        }
+        for bad in [
+            "",
+            "not a url",
+            "https://",
+            "//example.com/a",
+            "http://exa mple.com",
+        ] {
+            assert!(
+                matches!(
+                    validate_media_url(bad),
+                    Err(ValidationError::MalformedUrl { .. } | ValidationError::MissingHost)
+                ),
+                "{bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn length_caps_hold_for_multibyte_text() {
+        // The URL cap is in bytes, so a URL of multibyte characters hits it sooner
+        let prefix = "https://example.com/";
+        let fits = format!("{}{}", prefix, "a".repeat(MAX_URL_LEN - prefix.len()));
+        assert!(validate_media_url(&fits).is_ok());
+        let too_long = format!("{}{}", prefix, "é".repeat(MAX_URL_LEN / 2));
+        assert_eq!(
+            validate_media_url(&too_long),
+            Err(ValidationError::UrlTooLong { max: MAX_URL_LEN })
+        );
+
+        // Keyword lengths are in characters, so multibyte keywords get the same room
+        let max_len = 5;
+        for ok in ["abcde", "ééééé", "日本語の歌", "🎵🎵🎵🎵🎵", "  abc  "] {
+            assert!(
+                validate_keyword_list("blocked_keywords", &[ok.to_string()], 10, max_len).is_ok(),
+                "{ok:?}"
+            );
+        }
+        for bad in [
+            "abcdef",
+            "éééééé",
+            "日本語の歌だ",
+            "🎵🎵🎵🎵🎵🎵",
+            "",
+            "   ",
+        ] {
+            assert!(
+                matches!(
+                    validate_keyword_list("blocked_keywords", &[bad.to_string()], 10, max_len),
+                    Err(ValidationError::InvalidEntry { .. })
+                ),
+                "{bad:?}"
+            );
+        }
+        let many = vec!["x".to_string(); 11];
+        assert_eq!(
+            validate_keyword_list("blocked_keywords", &many, 10, max_len),
+            Err(ValidationError::TooManyEntries {
+                field: "blocked_keywords",
+                max: 10
+            })
+        );
+    }
+
+    #[test]
+    fn host_lists_take_bare_host_names() {
+        let hosts = |list: &[&str]| list.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+        assert!(
+            validate_host_list(
+                "allowed_domains",
+                &hosts(&["youtube.com", "*.bandcamp.com"]),
+                5
+            )
+            .is_ok()
+        );
+        for bad in [
+            "https://youtube.com",
+            "youtube.com/watch",
+            "youtube.com:443",
+            "",
+            "exa mple.com",
+        ] {
+            assert!(
+                validate_host_list("allowed_domains", &hosts(&[bad]), 5).is_err(),
+                "{bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn floats_outside_the_range_or_not_numbers_are_refused() {
+        for ok in [0.0, 0.5, 1.0, MAX_VOLUME, f32::MIN_POSITIVE] {
+            assert_eq!(validate_volume("volume", ok), Ok(ok));
+        }
+        for bad in [
+            f32::NAN,
+            -f32::NAN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            -0.01,
+            MAX_VOLUME + 0.01,
+            f32::MAX,
+            f32::MIN,
+        ] {
+            assert!(
+                matches!(
+                    validate_volume("volume", bad),
+                    Err(ValidationError::OutOfRange {
+                        field: "volume",
+                        ..
+                    })
+                ),
+                "{bad}"
+            );
+        }
+        assert!(validate_range("seconds", f64::NAN, 0.0, 60.0).is_err());
+        assert!(validate_range("seconds", f64::INFINITY, 0.0, f64::MAX).is_err());
+        assert!(validate_range("seconds", 60.0, 0.0, 60.0).is_ok());
+    }
+
+    #[test]
+    fn pagination_defaults_and_bounds() {
+        assert_eq!(
+            validate_pagination(None, None, 20, 100),
+            Ok(Pagination {
+                limit: 20,
+                offset: 0
+            })
+        );
+        assert_eq!(
+            validate_pagination(Some(1), Some(i64::MAX), 20, 100),
+            Ok(Pagination {
+                limit: 1,
+                offset: i64::MAX
+            })
+        );
+        assert_eq!(
+            validate_pagination(Some(100), Some(0), 20, 100).map(|p| p.limit),
+            Ok(100)
+        );
+        // Out-of-range values are refused rather than clamped
+        for (limit, offset) in [
+            (Some(0), None),
+            (Some(-1), None),
+            (Some(101), None),
+            (Some(i64::MIN), None),
+            (Some(i64::MAX), None),
+            (None, Some(-1)),
+            (None, Some(i64::MIN)),
+        ] {
+            assert!(
+                matches!(
+                    validate_pagination(limit, offset, 20, 100),
+                    Err(ValidationError::OutOfRange { .. })
+                ),
+                "{limit:?} {offset:?}"
+            );
+        }
+    }
+
+    /// Hosts and addresses `validate_media_url` must refuse, whatever path follows them
+    const REFUSED_URLS: &[&str] = &[
+        "file:///etc/passwd",
+        "ftp://example.com",
+        "javascript:alert(1)",
+        "http://localhost",
+        "http://api.localhost",
+        "http://printer.local",
+        "http://127.0.0.1",
+        "http://2130706433",
+        "http://10.0.0.5",
+        "http://192.168.1.1",
+        "http://169.254.169.254",
+        "http://[::1]",
+        "http://[fd00::1]",
+        "http://[::ffff:10.0.0.1]",
+    ];
+
+    proptest! {
+        #[test]
+        fn any_input_is_checked_without_panicking(input in any::<String>()) {
+            let _ = validate_snowflake("guild_id", &input);
+            let _ = validate_keyword_list("blocked_keywords", std::slice::from_ref(&input), 10, 5);
+            let _ = validate_host_list("allowed_domains", std::slice::from_ref(&input), 5);
+            for candidate in [
+                input.clone(),
+                format!("https://{input}"),
+                format!("http://x.com/{input}"),
+            ] {
+                if let Ok(url) = validate_media_url(&candidate) {
+                    prop_assert!(matches!(url.scheme(), "http" | "https"), "{:?}", candidate);
+                    prop_assert!(url.host().is_some(), "{:?}", candidate);
+                }
+            }
+        }
+
+        #[test]
+        fn every_nonzero_id_round_trips(id in 1u64..) {
+            prop_assert_eq!(validate_snowflake("guild_id", &id.to_string()), Ok(id));
+        }
+
+        #[test]
+        fn ids_with_anything_but_digits_stay_rejected(
+            before in "[0-9]{0,10}",
+            other in "[^0-9]",
+            after in "[0-9]{0,9}",
+        ) {
+            prop_assert_eq!(
+                validate_snowflake("guild_id", &format!("{before}{other}{after}")),
+                Err(ValidationError::InvalidSnowflake { field: "guild_id" })
+            );
+        }
+
+        #[test]
+        fn public_http_urls_are_accepted(
+            scheme in "https?",
+            host in "[a-z][a-z0-9]{0,20}\\.(com|org|net|fm)",
+            rest in "(/[A-Za-z0-9_~-]{0,12}){0,4}(\\?[a-z]{1,5}=[A-Za-z0-9]{0,10})?",
+        ) {
+            let url = validate_media_url(&format!("{scheme}://{host}{rest}"));
+            prop_assert_eq!(url.as_ref().ok().and_then(Url::host_str), Some(host.as_str()));
+        }
+
+        #[test]
+        fn refused_urls_stay_refused_whatever_follows(
+            base in prop::sample::select(REFUSED_URLS),
+            rest in "(/[A-Za-z0-9_.~%-]{0,12}){0,4}(\\?[a-z]{1,5}=[A-Za-z0-9]{0,10})?(#[a-z]{0,8})?",
+        ) {
+            let url = format!("{base}{rest}");
+            prop_assert!(validate_media_url(&url).is_err(), "{:?}", url);
+        }
+
+        #[test]
+        fn keywords_are_capped_in_characters_not_bytes(
+            fits in "\\s{0,3}[^\\s]{1,5}\\s{0,3}",
+            too_long in "\\s{0,3}[^\\s]{6,30}\\s{0,3}",
+            blank in "\\s{0,5}",
+        ) {
+            let check = |keyword: &str| {
+                validate_keyword_list("blocked_keywords", &[keyword.to_string()], 10, 5)
+            };
+            prop_assert!(check(&fits).is_ok(), "{:?}", fits);
+            prop_assert!(
+                matches!(check(&too_long), Err(ValidationError::InvalidEntry { .. })),
+                "{:?}",
+                too_long
+            );
+            prop_assert!(
+                matches!(check(&blank), Err(ValidationError::InvalidEntry { .. })),
+                "{:?}",
+                blank
+            );
+        }
+    }
+}
@@ -1,9 +1,72 @@
 use anyhow::{Result, anyhow};
+use serde::Serialize;
 use serenity::all::{ChannelId, Context as SerenityContext, GuildId};
+use songbird::Songbird;
+use songbird::tracks::PlayMode;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{error, info, warn};
 
-use crate::database::{establish_connection, models::VoiceConnection};
+use crate::broadcast;
+use crate::capacity;
+use crate::database::{
+    establish_connection,
+    models::{CurrentQueue, GuildSettings, VoiceConnection},
+};
+use crate::playback_state::LoopMode;
+use crate::policy::check_not_draining;
+
+/// Operator default for whether the bot deafens itself in voice; `0` keeps it undeafened
+const SELF_DEAFEN_ENV: &str = "LYRE_SELF_DEAFEN";
+
+/// Connection records newer than this may be join requests from the API that haven't been
+/// acted on yet
+const PENDING_JOIN_WINDOW_MINUTES: i64 = 5;
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The guild's playing track after a pause, resume or seek
+#[derive(Debug, Serialize)]
+pub struct PlaybackState {
+    pub paused: bool,
+    pub title: Option<String>,
+    pub position_secs: u64,
+}
+
+/// Whether the bot deafens itself, given the guild's own choice if it made one. Deafened is the
+/// default: the bot never listens, and Discord then stops sending it the channel's audio.
+pub fn self_deafen_for(guild_choice: Option<bool>) -> bool {
+    guild_choice.unwrap_or_else(|| crate::config::var(SELF_DEAFEN_ENV).as_deref() != Ok("0"))
+}
+
+fn self_deafen(guild_id: GuildId) -> bool {
+    let mut db_conn = establish_connection();
+    let choice = GuildSettings::find_by_guild_id(&mut db_conn, &guild_id.to_string())
+        .ok()
+        .flatten()
+        .and_then(|settings| settings.self_deafen);
+    self_deafen_for(choice)
+}
+
+/// Set the guild's call to the voice flags it should have. Run before joining, so the bot
+/// arrives deafened instead of deafening a moment later.
+pub async fn apply_voice_flags(manager: &Songbird, guild_id: GuildId) {
+    let deaf = self_deafen(guild_id);
+    let call_lock = manager.get_or_insert(guild_id);
+    let mut call = call_lock.lock().await;
+    if call.is_deaf() != deaf
+        && let Err(e) = call.deafen(deaf).await
+    {
+        warn!("Failed to set self-deafen in guild {}: {}", guild_id, e);
+    }
+}
+
+/// Re-apply the guild's voice flags after its settings change, if the bot is in voice there
+pub async fn refresh_voice_flags(manager: &Songbird, guild_id: GuildId) {
+    if manager.get(guild_id).is_some() {
+        apply_voice_flags(manager, guild_id).await;
+    }
+}
 
 /// Join a voice channel with retry logic
 pub async fn join_voice_channel(
@@ -22,6 +85,9 @@ pub async fn join_voice_channel(
         return Ok(());
     }
 
+    crate::metrics::watch_voice_connection(&manager, guild_id).await;
+    apply_voice_flags(&manager, guild_id).await;
+
     // Retry voice channel joining with exponential backoff
     let mut attempts = 0;
     let max_attempts = 5;
@@ -36,11 +102,16 @@ pub async fn join_voice_channel(
         );
 
         match manager.join(guild_id, channel_id).await {
-            Ok(_call_lock) => {
+            Ok(call_lock) => {
                 info!(
                     "Successfully joined voice channel after {} attempt(s)",
                     attempts + 1
                 );
+                {
+                    let mut call = call_lock.lock().await;
+                    crate::voice_stats::install(&mut call, guild_id);
+                    crate::voice_permissions::install(&mut call, guild_id);
+                }
 
                 // Update database to track voice connection
                 let mut db_conn = establish_connection();
@@ -135,14 +206,29 @@ pub async fn process_voice_requests(ctx: Arc<SerenityContext>) {
                     continue;
                 }
 
-                // Check if this is a recent request (within last 5 minutes)
-                let now = chrono::Utc::now().naive_utc();
-                let request_age = now.signed_duration_since(request.connected_at);
-                if request_age.num_minutes() > 5 {
+                // Check if this is a recent request
+                if !is_pending_join(&request) {
                     // This is an old connection record, not a new join request
                     continue;
                 }
 
+                // A new session takes a slot as one from /play does, and none start during
+                // maintenance; moving an existing session needs neither
+                if manager.get(guild_id).is_none()
+                    && (check_not_draining().is_err()
+                        || !capacity::try_admit(guild_id, manager.iter().count()))
+                {
+                    warn!(
+                        "Dropped API request to join voice channel {} in guild {}: at capacity or draining",
+                        channel_id, guild_id
+                    );
+                    let mut db_conn = establish_connection();
+                    if let Err(e) = VoiceConnection::delete(&mut db_conn, &request.guild_id) {
+                        error!("Failed to clean up dropped voice request: {}", e);
+                    }
+                    continue;
+                }
+
                 // Attempt to join the voice channel
                 match join_voice_channel(&ctx, guild_id, channel_id).await {
                     Ok(()) => {
@@ -170,3 +256,259 @@ pub async fn process_voice_requests(ctx: Arc<SerenityContext>) {
         }
     }
 }
+
+fn is_pending_join(record: &VoiceConnection) -> bool {
+    let age = chrono::Utc::now()
+        .naive_utc()
+        .signed_duration_since(record.connected_at);
+    age.num_minutes() <= PENDING_JOIN_WINDOW_MINUTES
+}
+
+/// Background task that keeps `voice_connections` in line with the calls Songbird actually
+/// has: records for guilds the bot isn't in are removed, calls without a record get one, and
+/// records naming the wrong channel (e.g. after the bot was moved) are corrected. Records that
+/// may still be pending join requests are left for [`process_voice_requests`].
+pub async fn reconcile_connections(manager: Arc<Songbird>) {
+    let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let mut live = HashMap::new();
+        for (guild_id, call_lock) in manager.iter() {
+            if let Some(channel) = call_lock.lock().await.current_channel() {
+                live.insert(guild_id.to_string(), channel.0.to_string());
+            }
+        }
+
+        let mut db_conn = establish_connection();
+        let records = match VoiceConnection::get_all_connected(&mut db_conn) {
+            Ok(records) => records,
+            Err(e) => {
+                error!("Failed to fetch voice connections to reconcile: {}", e);
+                continue;
+            }
+        };
+
+        for record in &records {
+            if is_pending_join(record) {
+                continue;
+            }
+            let result = match live.get(&record.guild_id) {
+                None => {
+                    info!(
+                        "Removing voice connection record for guild {}: not in voice",
+                        record.guild_id
+                    );
+                    VoiceConnection::delete(&mut db_conn, &record.guild_id).map(|_| ())
+                }
+                Some(channel) if record.channel_id.as_ref() != Some(channel) => {
+                    info!(
+                        "Voice connection record for guild {} names channel {:?}, but the bot is in {}",
+                        record.guild_id, record.channel_id, channel
+                    );
+                    VoiceConnection::create_or_update(&mut db_conn, &record.guild_id, Some(channel))
+                        .map(|_| ())
+                }
+                Some(_) => Ok(()),
+            };
+            if let Err(e) = result {
+                warn!(
+                    "Failed to reconcile voice connection for guild {}: {}",
+                    record.guild_id, e
+                );
+            }
+        }
+
+        for (guild_id, channel) in &live {
+            if records.iter().any(|r| &r.guild_id == guild_id) {
+                continue;
+            }
+            info!(
+                "Adding missing voice connection record for guild {} in channel {}",
+                guild_id, channel
+            );
+            if let Err(e) = VoiceConnection::create_or_update(&mut db_conn, guild_id, Some(channel))
+            {
+                warn!(
+                    "Failed to record voice connection for guild {}: {}",
+                    guild_id, e
+                );
+            }
+        }
+    }
+}
+
+/// Pause or resume the guild's playing track; `None` when nothing is playing
+pub async fn set_paused(guild_id: &str, paused: bool) -> Result<Option<PlaybackState>> {
+    let position = if crate::simulate::enabled() {
+        match crate::simulate::set_paused(guild_id, paused) {
+            Some(position) => position,
+            None => return Ok(None),
+        }
+    } else {
+        let manager =
+            broadcast::voice_manager().ok_or_else(|| anyhow!("not connected to Discord"))?;
+        let Some(call_lock) = manager.get(GuildId::new(guild_id.parse()?)) else {
+            return Ok(None);
+        };
+        let Some(track) = call_lock.lock().await.queue().current() else {
+            return Ok(None);
+        };
+        if paused {
+            track.pause()?;
+        } else {
+            track.play()?;
+        }
+        // The track handles its commands in order, so this reports the new state
+        track.get_info().await?.position
+    };
+
+    crate::playback_state::set_position(guild_id, position, paused);
+    let mut db_conn = establish_connection();
+    let title = CurrentQueue::get_current_track(&mut db_conn, guild_id)?.and_then(|t| t.title);
+    crate::voice_state::set_playing(guild_id, !paused, title.as_deref());
+    info!(
+        "{} playback in guild {}",
+        if paused { "Paused" } else { "Resumed" },
+        guild_id
+    );
+    Ok(Some(PlaybackState {
+        paused,
+        title,
+        position_secs: position.as_secs(),
+    }))
+}
+
+/// Set the guild's volume: saved as the volume its tracks start at, and applied to the ones
+/// already queued (no louder than its quiet hours allow)
+pub async fn set_volume(guild_id: &str, volume: f32) -> Result<()> {
+    {
+        let mut db_conn = establish_connection();
+        GuildSettings::create_or_update(&mut db_conn, guild_id)?;
+        GuildSettings::update_volume(&mut db_conn, guild_id, volume)?;
+    }
+    crate::settings_events::changed(guild_id);
+
+    if let Some(manager) = broadcast::voice_manager()
+        && let Some(call_lock) = manager.get(GuildId::new(guild_id.parse()?))
+    {
+        let live = match crate::commands::play::quiet_hours_cap(guild_id) {
+            Some(cap) => volume.min(cap),
+            None => volume,
+        };
+        for track in call_lock.lock().await.queue().current_queue() {
+            // Tracks that ended meanwhile have nothing to turn up or down
+            let _ = track.set_volume(live);
+        }
+    }
+    info!("Set volume to {:.2} in guild {}", volume, guild_id);
+    Ok(())
+}
+
+/// Set what the guild repeats. Looping the track applies to the one playing now and each one
+/// after it as it starts; looping the queue requeues tracks as they finish.
+pub async fn set_loop_mode(guild_id: &str, mode: LoopMode) -> Result<()> {
+    crate::playback_state::set_loop_mode(guild_id, mode);
+
+    if let Some(manager) = broadcast::voice_manager()
+        && let Some(call_lock) = manager.get(GuildId::new(guild_id.parse()?))
+        && let Some(track) = call_lock.lock().await.queue().current()
+    {
+        let result = if mode == LoopMode::Track {
+            track.enable_loop()
+        } else {
+            track.disable_loop()
+        };
+        if let Err(e) = result {
+            warn!("Failed to change looping in guild {}: {}", guild_id, e);
+        }
+    }
+    info!("Set loop mode to {} in guild {}", mode.as_str(), guild_id);
+    Ok(())
+}
+
+/// Move the guild's playing track to `position` and report where it landed; `None` when
+/// nothing is playing
+pub async fn seek(guild_id: &str, position: Duration) -> Result<Option<PlaybackState>> {
+    let (position, paused) = if crate::simulate::enabled() {
+        match crate::simulate::seek(guild_id, position) {
+            Some(paused) => (position, paused),
+            None => return Ok(None),
+        }
+    } else {
+        let manager =
+            broadcast::voice_manager().ok_or_else(|| anyhow!("not connected to Discord"))?;
+        let Some(call_lock) = manager.get(GuildId::new(guild_id.parse()?)) else {
+            return Ok(None);
+        };
+        let Some(track) = call_lock.lock().await.queue().current() else {
+            return Ok(None);
+        };
+        let position = track.seek_async(position).await?;
+        let paused = track.get_info().await?.playing == PlayMode::Pause;
+        (position, paused)
+    };
+
+    crate::playback_state::set_position(guild_id, position, paused);
+    let mut db_conn = establish_connection();
+    let title = CurrentQueue::get_current_track(&mut db_conn, guild_id)?.and_then(|t| t.title);
+    Ok(Some(PlaybackState {
+        paused,
+        title,
+        position_secs: position.as_secs(),
+    }))
+}
+
+/// Fade out and skip the guild's playing track, leaving voice once the queue has run dry as
+/// `/next` does; `false` when the bot isn't in voice there
+pub async fn skip(guild_id: &str) -> Result<bool> {
+    let manager = broadcast::voice_manager().ok_or_else(|| anyhow!("not connected to Discord"))?;
+    let guild = GuildId::new(guild_id.parse()?);
+    let Some(call_lock) = manager.get(guild) else {
+        return Ok(false);
+    };
+    crate::fade::fade_out(&call_lock).await;
+    let remaining = {
+        let call = call_lock.lock().await;
+        if call.queue().skip().is_ok() {
+            crate::metrics::METRICS.dec_queue(1);
+        }
+        call.queue().len()
+    };
+    if remaining == 0 {
+        let _ = manager.remove(guild).await;
+    }
+    info!("Skipped the playing track in guild {}", guild_id);
+    Ok(true)
+}
+
+/// Fade out the playing track, then stop playback, clear the queue and leave voice as `/stop`
+/// does; `false` when the bot isn't in voice there
+pub async fn stop(guild_id: &str) -> Result<bool> {
+    let manager = broadcast::voice_manager().ok_or_else(|| anyhow!("not connected to Discord"))?;
+    let guild = GuildId::new(guild_id.parse()?);
+    let Some(call_lock) = manager.get(guild) else {
+        return Ok(false);
+    };
+    crate::fade::fade_out(&call_lock).await;
+    {
+        let mut call = call_lock.lock().await;
+        let queued = call.queue().len();
+        if queued > 0 {
+            crate::metrics::METRICS.dec_queue(queued);
+        }
+        call.stop();
+    }
+    crate::stage::session_ended(guild).await;
+    if manager.remove(guild).await.is_ok()
+        && let Err(e) = VoiceConnection::disconnect(&mut establish_connection(), guild_id)
+    {
+        warn!(
+            "Failed to update database when disconnecting from voice: {}",
+            e
+        );
+    }
+    info!("Stopped playback in guild {}", guild_id);
+    Ok(true)
+}
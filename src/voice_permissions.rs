@@ -0,0 +1,197 @@
+//! Noticing when the bot loses what it needs in its voice channel mid-session (someone edits a
+//! role or the channel's overwrites), so it pauses and says which permission is gone instead of
+//! erroring through the queue track by track. Playback resumes once they're back.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use serenity::all::{ChannelId, Context, GuildId, Permissions};
+use serenity::async_trait;
+use songbird::{
+    Call, CoreEvent, Event, EventContext, EventHandler as VoiceEventHandler, TrackEvent,
+};
+
+use crate::broadcast;
+use crate::commands::invite::invite_url;
+use crate::guild_log::{self, LogEvent};
+
+/// What playing in a voice channel takes
+pub const VOICE_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::CONNECT)
+    .union(Permissions::SPEAK);
+/// How often a paused guild is checked again, for changes Discord doesn't send the bot (its own
+/// roles changing needs the privileged members intent)
+const RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Guilds paused for missing permissions, and which ones
+static PAUSED: Lazy<Mutex<HashMap<GuildId, Permissions>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Check permissions whenever a track fails or the voice connection drops. Call after each join.
+pub fn install(call: &mut Call, guild_id: GuildId) {
+    call.add_global_event(Event::Track(TrackEvent::Error), Watcher { guild_id });
+    call.add_global_event(
+        Event::Core(CoreEvent::DriverDisconnect),
+        Watcher { guild_id },
+    );
+}
+
+struct Watcher {
+    guild_id: GuildId,
+}
+
+#[async_trait]
+impl VoiceEventHandler for Watcher {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if let Some(ctx) = broadcast::context() {
+            let guild_id = self.guild_id;
+            tokio::spawn(async move { check(&ctx, guild_id).await });
+        }
+        None
+    }
+}
+
+/// Pause `guild_id`'s session and say so if the bot is missing a voice permission in its
+/// channel, or resume it if it was paused for that and they're back. Does nothing without a
+/// session.
+pub async fn check(ctx: &Context, guild_id: GuildId) {
+    let Some(manager) = songbird::get(ctx).await else {
+        return;
+    };
+    let Some(call_lock) = manager.get(guild_id) else {
+        if let Ok(mut paused) = PAUSED.lock() {
+            paused.remove(&guild_id);
+        }
+        return;
+    };
+    let Some(channel_id) = call_lock
+        .lock()
+        .await
+        .current_channel()
+        .map(|c| ChannelId::new(c.0.get()))
+    else {
+        return;
+    };
+    let Some(missing) = missing_permissions(ctx, guild_id, channel_id).await else {
+        return;
+    };
+    let previously = PAUSED
+        .lock()
+        .ok()
+        .and_then(|paused| paused.get(&guild_id).copied());
+
+    match previously {
+        None if missing.is_empty() => {}
+        Some(_) if missing.is_empty() => {
+            if let Ok(mut paused) = PAUSED.lock() {
+                paused.remove(&guild_id);
+            }
+            if let Err(e) = call_lock.lock().await.queue().resume() {
+                tracing::warn!(
+                    "Failed to resume guild {} after permissions: {}",
+                    guild_id,
+                    e
+                );
+            }
+            tracing::info!("Voice permissions are back in guild {}", guild_id);
+            guild_log::record(guild_id, LogEvent::PermissionsRestored { channel_id });
+            notify(
+                guild_id,
+                "▶️ I can play in the voice channel again, so the queue is carrying on."
+                    .to_string(),
+            )
+            .await;
+        }
+        Some(already) if already == missing => {}
+        _ => {
+            let first_time = previously.is_none();
+            if let Ok(mut paused) = PAUSED.lock() {
+                paused.insert(guild_id, missing);
+            }
+            if let Err(e) = call_lock.lock().await.queue().pause() {
+                tracing::warn!(
+                    "Failed to pause guild {} without permissions: {}",
+                    guild_id,
+                    e
+                );
+            }
+            let names = missing.get_permission_names();
+            let plural = if names.len() == 1 { "" } else { "s" };
+            let names = names.join(", ");
+            tracing::warn!(
+                "Missing {} in voice channel {} of guild {}; paused",
+                names,
+                channel_id,
+                guild_id
+            );
+            guild_log::record(
+                guild_id,
+                LogEvent::PermissionsMissing {
+                    channel_id,
+                    missing: names.clone(),
+                },
+            );
+            let mut message = format!(
+                "⏸️ Playback is paused: I no longer have the **{}** permission{} in <#{}>. \
+                 Give it back to my role or in the channel's permissions and I'll carry on where \
+                 I left off.",
+                names, plural, channel_id
+            );
+            if let Some(app_id) = ctx.http.application_id() {
+                message.push_str(&format!(
+                    "\nOr re-invite me with the permissions I need: <{}>",
+                    invite_url(app_id)
+                ));
+            }
+            notify(guild_id, message).await;
+            if first_time {
+                spawn_recheck(ctx.clone(), guild_id);
+            }
+        }
+    }
+}
+
+/// Voice permissions the bot lacks in `channel_id`, or `None` if it can't tell
+async fn missing_permissions(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Option<Permissions> {
+    // The cached member can be stale (role changes need the members intent), so ask Discord
+    let bot_id = ctx.cache.current_user().id;
+    let member = match ctx.http.get_member(guild_id, bot_id).await {
+        Ok(member) => member,
+        Err(e) => {
+            tracing::debug!("Couldn't fetch own member in guild {}: {}", guild_id, e);
+            return None;
+        }
+    };
+    let guild = ctx.cache.guild(guild_id)?;
+    let channel = guild.channels.get(&channel_id)?;
+    let permissions = guild.user_permissions_in(channel, &member);
+    Some(VOICE_PERMISSIONS.difference(permissions))
+}
+
+async fn notify(guild_id: GuildId, message: String) {
+    if let Err(e) = broadcast::post(&guild_id.to_string(), &message).await {
+        tracing::debug!("Couldn't report permissions in guild {}: {}", guild_id, e);
+    }
+}
+
+/// Keep checking a paused guild until it's playing again or its session is gone
+fn spawn_recheck(ctx: Context, guild_id: GuildId) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(RECHECK_INTERVAL).await;
+            check(&ctx, guild_id).await;
+            let still_paused = PAUSED
+                .lock()
+                .is_ok_and(|paused| paused.contains_key(&guild_id));
+            if !still_paused {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,147 @@
+//! Write-behind for the `voice_connections` bookkeeping that every track event touches (whether
+//! the guild is playing, what, and when it was last active). Updates are coalesced per guild in
+//! memory and written in one transaction every couple of seconds, and on shutdown, so track
+//! events don't each take the SQLite write lock the HTTP API is waiting on. Readers that need
+//! the newest state check [`pending_playing`] before the table. Queue positions aren't buffered:
+//! they're read back straight after they change.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use diesel::Connection;
+use once_cell::sync::Lazy;
+
+use crate::database::establish_connection;
+use crate::database::models::VoiceConnection;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+static PENDING: Lazy<Mutex<HashMap<String, Pending>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// What's waiting to be written for a guild; the latest update wins
+#[derive(Debug, Default)]
+struct Pending {
+    /// Whether it's playing and the title, if that changed
+    playing: Option<(bool, Option<String>)>,
+}
+
+fn record(guild_id: &str, change: impl FnOnce(&mut Pending)) {
+    if let Ok(mut pending) = PENDING.lock() {
+        change(pending.entry(guild_id.to_string()).or_default());
+    }
+}
+
+/// Note whether the guild is playing and what; written with its activity time on the next flush
+pub fn set_playing(guild_id: &str, is_playing: bool, title: Option<&str>) {
+    record(guild_id, |pending| {
+        pending.playing = Some((is_playing, title.map(str::to_string)))
+    });
+}
+
+/// Note that the guild's session was just used
+pub fn touch(guild_id: &str) {
+    record(guild_id, |_| {});
+}
+
+/// Whether the guild is playing according to an update not yet written, if there is one
+pub fn pending_playing(guild_id: &str) -> Option<bool> {
+    PENDING
+        .lock()
+        .ok()?
+        .get(guild_id)
+        .and_then(|pending| pending.playing.as_ref().map(|(playing, _)| *playing))
+}
+
+/// Write the buffered updates every few seconds. Safe to call more than once.
+pub fn spawn_flusher() {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(FLUSH_INTERVAL).await;
+            flush();
+        }
+    });
+}
+
+/// Write every buffered update now, in one transaction. Guilds whose connection record is gone
+/// (they left voice meanwhile) have nothing to update.
+pub fn flush() {
+    let pending: Vec<(String, Pending)> = match PENDING.lock() {
+        Ok(mut pending) => pending.drain().collect(),
+        Err(_) => return,
+    };
+    if pending.is_empty() {
+        return;
+    }
+    let mut db_conn = establish_connection();
+    let result = db_conn.transaction(|conn| {
+        for (guild_id, update) in &pending {
+            match &update.playing {
+                Some((is_playing, title)) => {
+                    VoiceConnection::update_playing_status(
+                        conn,
+                        guild_id,
+                        *is_playing,
+                        title.as_deref(),
+                    )?;
+                }
+                None => {
+                    VoiceConnection::update_last_activity(conn, guild_id)?;
+                }
+            }
+        }
+        diesel::QueryResult::Ok(())
+    });
+    if let Err(e) = result {
+        tracing::warn!(
+            "Failed to write voice state for {} guild(s), will retry: {}",
+            pending.len(),
+            e
+        );
+        requeue(pending);
+    }
+}
+
+/// Put updates that couldn't be written back for the next flush. Anything recorded for a guild
+/// since they were taken is newer, so it wins; they only fill in what it leaves unset.
+fn requeue(failed: Vec<(String, Pending)>) {
+    let Ok(mut pending) = PENDING.lock() else {
+        return;
+    };
+    for (guild_id, update) in failed {
+        let newer = pending.entry(guild_id).or_default();
+        if newer.playing.is_none() {
+            newer.playing = update.playing;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pending, pending_playing, requeue, set_playing, touch};
+
+    #[test]
+    fn failed_writes_are_requeued_behind_newer_updates() {
+        let failed = |playing: bool| Pending {
+            playing: Some((playing, Some("Old".to_string()))),
+        };
+        // Stopped since the failed write said it was playing, so that stays
+        set_playing("requeue-1", false, None);
+        // Only touched since, so the failed update is still the latest word
+        touch("requeue-2");
+        requeue(vec![
+            ("requeue-1".to_string(), failed(true)),
+            ("requeue-2".to_string(), failed(true)),
+            ("requeue-3".to_string(), failed(false)),
+        ]);
+
+        assert_eq!(pending_playing("requeue-1"), Some(false));
+        assert_eq!(pending_playing("requeue-2"), Some(true));
+        assert_eq!(pending_playing("requeue-3"), Some(false));
+    }
+}
@@ -0,0 +1,170 @@
+//! Per-call voice quality telemetry, to tell network trouble from a struggling host when
+//! someone reports stutter.
+//!
+//! - Packet loss and jitter come from the RTCP reports Discord sends about the bot's stream.
+//! - Tick gaps come from Songbird's 20ms voice ticks: when they arrive late, the bot's own
+//!   runtime was too busy to keep up.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serenity::all::GuildId;
+use serenity::async_trait;
+use songbird::{Call, CoreEvent, Event, EventContext, EventHandler as VoiceEventHandler};
+
+/// Songbird ticks every 20ms; a gap this long means at least one tick was missed
+const LATE_TICK: Duration = Duration::from_millis(40);
+/// RTP clock rate of Discord's Opus stream, for converting jitter to milliseconds
+const RTP_CLOCK_HZ: f64 = 48_000.0;
+
+const RTCP_SENDER_REPORT: u8 = 200;
+const RTCP_RECEIVER_REPORT: u8 = 201;
+const REPORT_BLOCK_LEN: usize = 24;
+const SENDER_INFO_LEN: usize = 20;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CallStats {
+    /// RTCP report blocks received about the bot's stream
+    pub reports: u64,
+    /// Share of packets lost since the previous report, 0.0–1.0
+    pub fraction_lost: f64,
+    /// Packets lost over the whole session, as last reported
+    pub cumulative_lost: i64,
+    pub jitter_ms: f64,
+    pub ticks: u64,
+    /// Ticks that arrived after `LATE_TICK`, i.e. stalls on this host
+    pub late_ticks: u64,
+    pub longest_tick_gap_ms: u64,
+    pub reconnects: u64,
+    #[serde(skip)]
+    ssrc: Option<u32>,
+    #[serde(skip)]
+    last_tick: Option<Instant>,
+    #[serde(skip)]
+    session: u64,
+}
+
+static STATS: Lazy<Mutex<HashMap<GuildId, CallStats>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static SESSIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Start collecting telemetry for a newly joined call, replacing the guild's previous stats
+pub fn install(call: &mut Call, guild_id: GuildId) {
+    let session = SESSIONS.fetch_add(1, Ordering::Relaxed) + 1;
+    if let Ok(mut stats) = STATS.lock() {
+        stats.insert(
+            guild_id,
+            CallStats {
+                session,
+                ..CallStats::default()
+            },
+        );
+    }
+    for event in [
+        CoreEvent::DriverConnect,
+        CoreEvent::DriverReconnect,
+        CoreEvent::VoiceTick,
+        CoreEvent::RtcpPacket,
+    ] {
+        call.add_global_event(Event::Core(event), Collector { guild_id, session });
+    }
+}
+
+/// The guild's stats for its current (or last) call
+pub fn for_guild(guild_id: GuildId) -> Option<CallStats> {
+    STATS.lock().ok()?.get(&guild_id).cloned()
+}
+
+pub fn all() -> Vec<(GuildId, CallStats)> {
+    STATS
+        .lock()
+        .map(|stats| stats.iter().map(|(g, s)| (*g, s.clone())).collect())
+        .unwrap_or_default()
+}
+
+struct Collector {
+    guild_id: GuildId,
+    /// Handlers from an earlier join of the same guild are ignored
+    session: u64,
+}
+
+#[async_trait]
+impl VoiceEventHandler for Collector {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let Ok(mut all) = STATS.lock() else {
+            return None;
+        };
+        let stats = all.get_mut(&self.guild_id)?;
+        if stats.session != self.session {
+            return None;
+        }
+        match ctx {
+            EventContext::DriverConnect(data) => stats.ssrc = Some(data.ssrc),
+            EventContext::DriverReconnect(data) => {
+                stats.ssrc = Some(data.ssrc);
+                stats.reconnects += 1;
+                stats.last_tick = None;
+            }
+            EventContext::VoiceTick(_) => {
+                let now = Instant::now();
+                if let Some(last) = stats.last_tick.replace(now) {
+                    let gap = now.duration_since(last);
+                    if gap >= LATE_TICK {
+                        stats.late_ticks += 1;
+                    }
+                    stats.longest_tick_gap_ms =
+                        stats.longest_tick_gap_ms.max(gap.as_millis() as u64);
+                }
+                stats.ticks += 1;
+            }
+            EventContext::RtcpPacket(data) => {
+                let ssrc = stats.ssrc?;
+                let body = data.packet.get(
+                    8 + data.payload_offset..data.packet.len().saturating_sub(data.payload_end_pad),
+                );
+                if let Some(block) = body.and_then(|body| report_for(&data.packet, body, ssrc)) {
+                    stats.reports += 1;
+                    stats.fraction_lost = block.fraction_lost;
+                    stats.cumulative_lost = block.cumulative_lost;
+                    stats.jitter_ms = block.jitter_ms;
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+struct ReportBlock {
+    fraction_lost: f64,
+    cumulative_lost: i64,
+    jitter_ms: f64,
+}
+
+/// The report block about `ssrc` in a sender or receiver report, if there is one.
+/// `packet` is the whole RTCP packet (for its header) and `body` the decrypted part after it.
+fn report_for(packet: &[u8], body: &[u8], ssrc: u32) -> Option<ReportBlock> {
+    let count = usize::from(*packet.first()? & 0x1f);
+    let blocks = match *packet.get(1)? {
+        RTCP_SENDER_REPORT => body.get(SENDER_INFO_LEN..)?,
+        RTCP_RECEIVER_REPORT => body,
+        _ => return None,
+    };
+    blocks
+        .chunks_exact(REPORT_BLOCK_LEN)
+        .take(count)
+        .find(|block| u32::from_be_bytes([block[0], block[1], block[2], block[3]]) == ssrc)
+        .map(|block| {
+            // Cumulative loss is a signed 24-bit count
+            let lost = i32::from_be_bytes([block[5], block[6], block[7], 0]) >> 8;
+            let jitter = u32::from_be_bytes([block[12], block[13], block[14], block[15]]);
+            ReportBlock {
+                fraction_lost: f64::from(block[4]) / 256.0,
+                cumulative_lost: i64::from(lost),
+                jitter_ms: f64::from(jitter) / RTP_CLOCK_HZ * 1000.0,
+            }
+        })
+}
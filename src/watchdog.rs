@@ -0,0 +1,141 @@
+//! Noticing tracks that say they're playing but have stopped moving (a stalled download or
+//! decoder), so the session doesn't hang in silence: the input is restarted from where it got
+//! to, and if that doesn't take, the track is skipped. Either way the guild's log channel hears
+//! about it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use serenity::all::GuildId;
+use songbird::Songbird;
+use songbird::tracks::{PlayMode, TrackHandle};
+
+use crate::database::establish_connection;
+use crate::database::models::CurrentQueue;
+use crate::guild_log::{self, LogEvent};
+
+/// Seconds a playing track's position may stand still before it counts as stalled
+const STALL_SECS_ENV: &str = "LYRE_STALL_SECS";
+const DEFAULT_STALL_SECS: u64 = 15;
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Where a guild's playing track was last seen, and since when it hasn't moved
+struct Sample {
+    track: String,
+    position: Duration,
+    since: Instant,
+    /// Whether the input was already restarted for this stall
+    restarted: bool,
+}
+
+fn stall_after() -> Duration {
+    let secs = crate::config::var(STALL_SECS_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_STALL_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Check every call's playing track for stalls. Safe to call on every `ready`.
+pub fn spawn_watchdog(manager: Arc<Songbird>) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut samples: HashMap<GuildId, Sample> = HashMap::new();
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let stall_after = stall_after();
+            let mut seen = Vec::new();
+            for (guild_id, call_lock) in manager.iter() {
+                let guild_id = GuildId::new(guild_id.0.get());
+                let Some(track) = call_lock.lock().await.queue().current() else {
+                    continue;
+                };
+                let Ok(info) = track.get_info().await else {
+                    continue;
+                };
+                if info.playing != PlayMode::Play {
+                    continue;
+                }
+                seen.push(guild_id);
+                let sample = samples.entry(guild_id).or_insert(Sample {
+                    track: track.uuid().to_string(),
+                    position: info.position,
+                    since: Instant::now(),
+                    restarted: false,
+                });
+                if sample.track != track.uuid().to_string() || sample.position != info.position {
+                    *sample = Sample {
+                        track: track.uuid().to_string(),
+                        position: info.position,
+                        since: Instant::now(),
+                        restarted: false,
+                    };
+                    continue;
+                }
+                if sample.since.elapsed() < stall_after {
+                    continue;
+                }
+                let restarted = sample.restarted;
+                sample.restarted = true;
+                sample.since = Instant::now();
+                recover(guild_id, &track, info.position, restarted).await;
+            }
+            // Forget guilds that left voice or aren't playing
+            samples.retain(|guild_id, _| seen.contains(guild_id));
+        }
+    });
+}
+
+/// Restart a stalled track's input from `position`, or skip it if that was already tried
+async fn recover(guild_id: GuildId, track: &TrackHandle, position: Duration, restarted: bool) {
+    let title = CurrentQueue::get_current_track(&mut establish_connection(), &guild_id.to_string())
+        .ok()
+        .flatten()
+        .and_then(|entry| entry.title);
+    let recovered = if restarted {
+        false
+    } else {
+        // Seeking makes songbird reopen and decode the input from there
+        match tokio::time::timeout(stall_after(), track.seek_async(position)).await {
+            Ok(Ok(_)) => true,
+            Ok(Err(e)) => {
+                tracing::warn!(
+                    "Couldn't restart stalled track in guild {}: {}",
+                    guild_id,
+                    e
+                );
+                false
+            }
+            Err(_) => false,
+        }
+    };
+    if !recovered && let Err(e) = track.stop() {
+        tracing::warn!("Couldn't skip stalled track in guild {}: {}", guild_id, e);
+    }
+    tracing::warn!(
+        "Track stalled at {}s in guild {}; {}",
+        position.as_secs(),
+        guild_id,
+        if recovered {
+            "restarted it"
+        } else {
+            "skipped it"
+        }
+    );
+    guild_log::record(
+        guild_id,
+        LogEvent::PlaybackStalled {
+            title,
+            position_secs: position.as_secs(),
+            recovered,
+        },
+    );
+}
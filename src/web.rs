@@ -30,10 +30,10 @@ async fn metrics() -> impl Responder {
             "# HELP lyre_ready 1 if ready, 0 otherwise\n",
             "# TYPE lyre_ready gauge\n",
             "lyre_ready {}\n",
-            "# HELP lyre_active_voice_calls Number of active voice calls\n",
+            "# HELP lyre_active_voice_calls Number of connected voice calls\n",
             "# TYPE lyre_active_voice_calls gauge\n",
             "lyre_active_voice_calls {}\n",
-            "# HELP lyre_connected_guilds Number of connected guilds (approx)\n",
+            "# HELP lyre_connected_guilds Number of guilds with a connected voice call\n",
             "# TYPE lyre_connected_guilds gauge\n",
             "lyre_connected_guilds {}\n",
             "# HELP lyre_total_queue_len Total tracks enqueued across calls (approx)\n",
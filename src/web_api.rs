@@ -1,15 +1,23 @@
 use actix_files as fs;
-use actix_web::{App, HttpServer, middleware::Logger};
+use actix_web::{App, HttpServer, middleware::Logger, web};
 use std::net::Ipv4Addr;
 
-use crate::middleware::AuthMiddleware;
+use crate::api::error::ApiError;
+use crate::middleware::{AuthMiddleware, RequestMetrics};
 
 use crate::api::{
-    add_to_queue, cleanup_old_data, clear_queue, dashboard_redirect, get_cache_stats,
-    get_guild_settings, get_guilds, get_maintenance_stats, get_queue, get_recent_tracks,
-    get_song_info, get_test_token, get_user_history, health_metrics, join_voice_channel, livez,
-    next_track, oauth_callback, readyz, search_songs, set_volume, skip_track, stop_playback,
-    update_guild_settings, validate_auth,
+    activate_filter_preset, add_to_queue, announce, cancel_download, capture_cpu_profile,
+    capture_profile, check_permissions, cleanup_old_data, clear_queue, create_hook, create_upload,
+    dashboard_redirect, dedupe_queue, delete_filter_preset, delete_hook, delete_playlist,
+    delete_upload, download_events, dump_tasks, get_cache_stats, get_feature_flags,
+    get_guild_settings, get_guilds, get_lyrics, get_maintenance_mode, get_maintenance_stats,
+    get_overview, get_playlist, get_queue, get_recent_tracks, get_share, get_song_info,
+    get_test_token, get_tools, get_upload, get_user_history, get_wrapped, guild_settings_events,
+    health_metrics, join_voice_channel, list_downloads, list_filter_presets, list_hooks,
+    list_playlists, list_uploads, livez, next_track, oauth_callback, pause_playback, readyz,
+    reload_config, resume_playback, save_filter_preset, save_playlist, search_songs, seek_playback,
+    set_maintenance_mode, set_volume, skip_track, stop_playback, sync_commands, trigger_hook,
+    update_feature_flag, update_guild_settings, update_tools, upload_chunk, validate_auth,
 };
 
 pub async fn run_http(bind: Option<String>) -> std::io::Result<()> {
@@ -17,10 +25,22 @@ pub async fn run_http(bind: Option<String>) -> std::io::Result<()> {
 
     HttpServer::new(|| {
         App::new()
+            // Report malformed bodies, queries and paths with the same error shape as handlers
+            .app_data(web::JsonConfig::default().error_handler(|err, _| {
+                ApiError::invalid_input(format!("Invalid JSON body: {}", err)).into()
+            }))
+            .app_data(web::QueryConfig::default().error_handler(|err, _| {
+                ApiError::invalid_input(format!("Invalid query string: {}", err)).into()
+            }))
+            .app_data(web::PathConfig::default().error_handler(|err, _| {
+                ApiError::invalid_input(format!("Invalid path parameter: {}", err)).into()
+            }))
             // Add authentication middleware
             .wrap(AuthMiddleware)
             // Add request logging
             .wrap(Logger::default())
+            // Record per-route request metrics (outermost so auth rejections are counted too)
+            .wrap(RequestMetrics)
             // Health endpoints (no auth required)
             .service(livez)
             .service(readyz)
@@ -35,12 +55,23 @@ pub async fn run_http(bind: Option<String>) -> std::io::Result<()> {
             // API endpoints
             .service(validate_auth)
             .service(get_guilds)
+            .service(check_permissions)
+            .service(get_overview)
             .service(get_queue)
             .service(add_to_queue)
             .service(skip_track)
             .service(clear_queue)
+            .service(dedupe_queue)
+            .service(list_downloads)
+            .service(cancel_download)
+            .service(download_events)
+            .service(guild_settings_events)
+            .service(get_share)
             .service(next_track)
             .service(stop_playback)
+            .service(pause_playback)
+            .service(resume_playback)
+            .service(seek_playback)
             .service(set_volume)
             .service(join_voice_channel)
             .service(search_songs)
@@ -50,10 +81,42 @@ pub async fn run_http(bind: Option<String>) -> std::io::Result<()> {
             .service(get_guild_settings)
             .service(get_cache_stats)
             .service(update_guild_settings)
+            .service(get_wrapped)
             // Maintenance endpoints
             .service(get_maintenance_stats)
             .service(cleanup_old_data)
+            .service(capture_profile)
+            .service(capture_cpu_profile)
+            .service(dump_tasks)
+            .service(reload_config)
+            .service(get_feature_flags)
+            .service(update_feature_flag)
+            .service(announce)
+            .service(get_maintenance_mode)
+            .service(set_maintenance_mode)
+            .service(get_tools)
+            .service(update_tools)
+            .service(sync_commands)
             .service(get_user_history)
+            // Signed control hooks
+            .service(list_hooks)
+            .service(create_hook)
+            .service(delete_hook)
+            .service(trigger_hook)
+            .service(list_uploads)
+            .service(create_upload)
+            .service(get_upload)
+            .service(upload_chunk)
+            .service(delete_upload)
+            .service(list_playlists)
+            .service(get_playlist)
+            .service(save_playlist)
+            .service(delete_playlist)
+            .service(list_filter_presets)
+            .service(save_filter_preset)
+            .service(delete_filter_preset)
+            .service(activate_filter_preset)
+            .service(get_lyrics)
     })
     .bind(bind_addr)?
     .workers(1)
@@ -241,6 +241,13 @@ function displayUserInfo(user) {
     `;
 }
 
+// Track titles and channel names come from other members, so never trust them as HTML
+function escapeHtml(value) {
+  const div = document.createElement("div");
+  div.textContent = String(value);
+  return div.innerHTML.replace(/"/g, "&quot;").replace(/'/g, "&#39;");
+}
+
 function displayUserGuilds(guilds) {
   // Store all guilds globally for use in modals
   allUserGuilds = guilds;
@@ -268,9 +275,9 @@ function displayUserGuilds(guilds) {
       (guild) => `
         <div class="guild-card" data-guild-id="${guild.id}">
             <div class="guild-info">
-                <div class="guild-icon">${guild.name[0].toUpperCase()}</div>
+                <div class="guild-icon">${escapeHtml(guild.name[0].toUpperCase())}</div>
                 <div>
-                    <strong>${guild.name}</strong>
+                    <strong>${escapeHtml(guild.name)}</strong>
                     <div style="color: var(--text-muted); font-size: 12px;">
                         ${guild.owner ? "Owner" : "Member"} • ID: ${guild.id}
                     </div>
@@ -278,7 +285,8 @@ function displayUserGuilds(guilds) {
             </div>
             <div style="color: var(--discord-green); font-size: 12px;">
                 ✅ Bot Connected
-                ${guild.voice_channel ? `<br>🔊 ${guild.voice_channel}` : ""}
+                ${guild.voice_channel ? `<br>🔊 ${escapeHtml(guild.voice_channel)}` : ""}
+                ${guild.current_track ? `<br>▶️ ${escapeHtml(guild.current_track)}` : ""}
                 ${guild.queue_length > 0 ? `<br>🎵 ${guild.queue_length} in queue` : ""}
             </div>
         </div>
@@ -0,0 +1,261 @@
+//! Operator-only endpoints
+
+mod common;
+
+use std::time::Duration;
+
+use common::{DEMO_GUILD, DEMO_USER, Lyre, VOICE_CHANNEL, current_title};
+use serde_json::{Value, json};
+
+fn flag<'a>(states: &'a Value, name: &str) -> &'a Value {
+    states["data"]
+        .as_array()
+        .and_then(|flags| flags.iter().find(|f| f["flag"] == name))
+        .unwrap_or_else(|| panic!("no {} flag in {}", name, states))
+}
+
+#[tokio::test]
+async fn feature_flags_resolve_guild_over_global() {
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+    let guild_flags = format!("/api/admin/feature-flags?guild_id={}", DEMO_GUILD);
+
+    let (status, states) = lyre.get(&guild_flags).await;
+    assert_eq!(status, 200, "{}", states);
+    assert_eq!(flag(&states, "autoplay")["enabled"], false);
+
+    let (status, _) = lyre
+        .put(
+            "/api/admin/feature-flags",
+            json!({ "flag": "autoplay", "enabled": true }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    let (_, states) = lyre.get(&guild_flags).await;
+    assert_eq!(flag(&states, "autoplay")["global"], true);
+    assert_eq!(flag(&states, "autoplay")["enabled"], true);
+
+    lyre.put(
+        "/api/admin/feature-flags",
+        json!({ "flag": "autoplay", "guild_id": DEMO_GUILD, "enabled": false }),
+    )
+    .await;
+    let (_, states) = lyre.get(&guild_flags).await;
+    assert_eq!(flag(&states, "autoplay")["enabled"], false);
+
+    // Clearing the override falls back to the global setting
+    lyre.put(
+        "/api/admin/feature-flags",
+        json!({ "flag": "autoplay", "guild_id": DEMO_GUILD, "enabled": null }),
+    )
+    .await;
+    let (_, states) = lyre.get(&guild_flags).await;
+    assert!(flag(&states, "autoplay")["guild"].is_null());
+    assert_eq!(flag(&states, "autoplay")["enabled"], true);
+}
+
+#[tokio::test]
+async fn unknown_feature_flag_is_rejected() {
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+
+    let (status, body) = lyre
+        .put(
+            "/api/admin/feature-flags",
+            json!({ "flag": "teleport", "enabled": true }),
+        )
+        .await;
+    assert_eq!(status, 400, "{}", body);
+}
+
+#[tokio::test]
+async fn admin_endpoints_require_an_operator() {
+    let lyre = Lyre::start().await;
+
+    let (status, _) = lyre.get("/api/admin/feature-flags").await;
+    assert_eq!(status, 403);
+}
+
+#[tokio::test]
+async fn metrics_need_an_operator_or_the_scrape_token() {
+    let lyre = Lyre::start_with(&[("LYRE_METRICS_TOKEN", "scrape-secret")]).await;
+
+    let (status, _) = lyre.get("/k8s/livez").await;
+    assert_eq!(status, 200);
+    let (status, _) = lyre.get("/k8s/metrics").await;
+    assert_eq!(status, 403);
+    let (status, _) = lyre
+        .request_as("scrape-wrong", reqwest::Method::GET, "/k8s/metrics", None)
+        .await;
+    assert_eq!(status, 401);
+    let (status, _) = lyre
+        .request_as("scrape-secret", reqwest::Method::GET, "/k8s/metrics", None)
+        .await;
+    assert_eq!(status, 200);
+
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+    let (status, _) = lyre.get("/k8s/metrics").await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn debug_profiles_are_for_operators_when_enabled() {
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+    let (status, _) = lyre.get("/api/admin/debug/cpu?seconds=1").await;
+    assert_eq!(status, 404);
+
+    let lyre = Lyre::start_with(&[("LYRE_DEBUG_ENDPOINTS", "1")]).await;
+    let (status, _) = lyre.get("/api/admin/debug/cpu?seconds=1").await;
+    assert_eq!(status, 403);
+
+    let lyre = Lyre::start_with(&[
+        ("LYRE_ADMIN_USER_IDS", DEMO_USER),
+        ("LYRE_DEBUG_ENDPOINTS", "1"),
+    ])
+    .await;
+    // Keep the bot busy until the profile is back, so it has samples to draw
+    let queue = format!("/api/queue/{}", DEMO_GUILD);
+    let profile = lyre.stream("/api/admin/debug/cpu?seconds=2");
+    tokio::pin!(profile);
+    let profile = loop {
+        tokio::select! {
+            profile = &mut profile => break profile,
+            _ = lyre.get(&queue) => {}
+        }
+    };
+    let svg = profile.text().await.expect("unreadable profile");
+    assert!(svg.contains("<svg"), "{}", svg);
+    // Default builds leave task dumps out
+    let (status, body) = lyre.get("/api/admin/debug/tasks").await;
+    assert_eq!(status, 404, "{}", body);
+}
+
+#[tokio::test]
+async fn announcements_are_validated_and_need_discord() {
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+
+    let (status, _) = lyre
+        .post("/api/admin/announce", json!({ "message": "   " }))
+        .await;
+    assert_eq!(status, 400);
+
+    // Simulation mode has no Discord connection to send through
+    let (status, body) = lyre
+        .post(
+            "/api/admin/announce",
+            json!({ "message": "Restarting in 5 minutes" }),
+        )
+        .await;
+    assert_eq!(status, 502, "{}", body);
+}
+
+#[tokio::test]
+async fn maintenance_mode_refuses_new_tracks_and_drains() {
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+
+    let (status, body) = lyre
+        .put("/api/admin/maintenance", json!({ "enabled": true }))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["enabled"], true);
+
+    let (status, body) = lyre.get("/k8s/readyz").await;
+    assert_eq!(status, 503);
+    assert_eq!(body["status"], "draining");
+
+    let (status, body) = lyre.play("https://www.youtube.com/watch?v=late").await;
+    assert_eq!(status, 403, "{}", body);
+    assert_eq!(body["error"]["code"], "maintenance");
+    let (status, body) = lyre
+        .post(
+            &format!("/api/control/{}/join", DEMO_GUILD),
+            json!({ "channel_id": VOICE_CHANNEL }),
+        )
+        .await;
+    assert_eq!(status, 403, "{}", body);
+    assert_eq!(body["error"]["code"], "maintenance");
+
+    lyre.put("/api/admin/maintenance", json!({ "enabled": false }))
+        .await;
+    let (status, _) = lyre.get("/k8s/readyz").await;
+    assert_eq!(status, 200);
+    let (status, body) = lyre.play("https://www.youtube.com/watch?v=late").await;
+    assert_eq!(status, 200, "{}", body);
+}
+
+#[tokio::test]
+async fn tools_report_installed_versions() {
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+
+    let (status, body) = lyre.get("/api/admin/tools").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["yt_dlp"]["version"], "2099.01.01-fake");
+    assert!(body["data"]["yt_dlp_last_updated"].is_null(), "{}", body);
+    assert!(body["data"]["ffmpeg"]["version"].is_string(), "{}", body);
+}
+
+#[tokio::test]
+async fn stuck_downloads_can_be_cancelled() {
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+    let url = "https://www.youtube.com/watch?v=slow";
+    lyre.play(url).await;
+    lyre.play("https://www.youtube.com/watch?v=after").await;
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    let job = loop {
+        let (status, body) = lyre.get("/api/downloads").await;
+        assert_eq!(status, 200, "{}", body);
+        if let Some(job) = body["data"].as_array().and_then(|jobs| jobs.first()) {
+            break job.clone();
+        }
+        assert!(tokio::time::Instant::now() < deadline, "never listed");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    };
+    assert_eq!(job["url"], url);
+    assert_eq!(job["guild_id"], DEMO_GUILD);
+
+    let cancel = format!("/api/downloads/{}", job["id"]);
+    let (status, body) = lyre.delete(&cancel).await;
+    assert_eq!(status, 200, "{}", body);
+    let (status, _) = lyre.delete(&cancel).await;
+    assert_eq!(status, 404);
+
+    // The cancelled track fails and the next one plays well before the stuck one would finish
+    lyre.wait_for_queue(Duration::from_secs(10), |q| {
+        current_title(q) == Some("Fake track after")
+    })
+    .await;
+    // Its scratch directory went with it
+    let jobs = lyre.dir().join("downloads/.jobs");
+    let left: Vec<_> = std::fs::read_dir(&jobs)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    assert!(left.is_empty(), "{:?}", left);
+}
+
+#[tokio::test]
+async fn command_sync_needs_discord_and_an_operator() {
+    let lyre = Lyre::start().await;
+    let (status, _) = lyre.post("/api/admin/commands/sync", json!({})).await;
+    assert_eq!(status, 403);
+
+    // The simulated bot has no Discord connection to register with
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+    let (status, body) = lyre.post("/api/admin/commands/sync", json!({})).await;
+    assert_eq!(status, 409, "{}", body);
+    let (status, _) = lyre
+        .post("/api/admin/commands/sync", json!({ "guild_id": "nope" }))
+        .await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn permissions_check_needs_access_and_discord() {
+    let lyre = Lyre::start().await;
+    let (status, _) = lyre.get("/api/guilds/555555555/permissions-check").await;
+    assert_eq!(status, 403);
+
+    // The simulated bot has no Discord connection to look its permissions up with
+    let (status, body) = lyre
+        .get(&format!("/api/guilds/{}/permissions-check", DEMO_GUILD))
+        .await;
+    assert_eq!(status, 409, "{}", body);
+}
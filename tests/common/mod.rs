@@ -0,0 +1,326 @@
+//! Harness for end-to-end tests: runs the real `lyre` binary in simulation mode (no Discord,
+//! mock voice layer) against a fresh SQLite database (which it creates and migrates itself),
+//! with the fake `yt-dlp`, `ffmpeg` and `ffprobe` from `tests/fixtures/bin` on `PATH`, and
+//! talks to it over the HTTP API.
+
+#![allow(dead_code)]
+
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// The user and guild behind `demo_` tokens
+pub const DEMO_USER: &str = "123456789";
+pub const DEMO_GUILD: &str = "987654321";
+pub const VOICE_CHANNEL: &str = "111111111111111111";
+/// A token for an ordinary member of the demo guild: voice access, but no Manage Guild
+pub const MEMBER_TOKEN: &str = "demo_member_1";
+/// The user behind `MEMBER_TOKEN`
+pub const MEMBER_USER: &str = "223456789";
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct Lyre {
+    child: Child,
+    dir: PathBuf,
+    base_url: String,
+    client: reqwest::Client,
+    token: String,
+}
+
+impl Lyre {
+    /// Start a bot with an empty database and wait until its API is ready
+    pub async fn start() -> Self {
+        Self::start_with(&[]).await
+    }
+
+    /// Like `start`, with extra environment variables (e.g. `LYRE_ADMIN_USER_IDS`); an empty
+    /// value unsets the variable
+    pub async fn start_with(env: &[(&str, &str)]) -> Self {
+        let dir = scratch_dir();
+        let database = dir.join("lyre.db");
+
+        let port = free_port();
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let path = format!(
+            "{}:{}",
+            manifest.join("tests/fixtures/bin").display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+        let mut command = Command::new(env!("CARGO_BIN_EXE_lyre"));
+        command
+            // Run from the scratch dir so a developer's .env isn't picked up
+            .current_dir(&dir)
+            .env_clear()
+            .env("PATH", path)
+            .env("HOME", &dir)
+            .env("LYRE_SIMULATE", "1")
+            .env("DATABASE_URL", &database)
+            .env("DOWNLOAD_FOLDER", dir.join("downloads"))
+            .env("LYRE_HTTP_BIND", format!("127.0.0.1:{}", port))
+            .env("RUST_LOG", "warn")
+            .envs(env.iter().copied())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit());
+        for (key, _) in env.iter().filter(|(_, value)| value.is_empty()) {
+            command.env_remove(key);
+        }
+        let child = command.spawn().expect("failed to start lyre");
+
+        let mut lyre = Self {
+            child,
+            dir,
+            base_url: format!("http://127.0.0.1:{}", port),
+            client: reqwest::Client::new(),
+            token: String::new(),
+        };
+        lyre.wait_until_ready().await;
+        let token = lyre.get_public("/api/dev/test-token").await;
+        lyre.token = token["data"]["access_token"]
+            .as_str()
+            .expect("test token")
+            .to_string();
+        lyre
+    }
+
+    async fn wait_until_ready(&mut self) {
+        let started = Instant::now();
+        loop {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                panic!("lyre exited during startup with {}", status);
+            }
+            let ready = self
+                .client
+                .get(format!("{}/k8s/readyz", self.base_url))
+                .send()
+                .await
+                .is_ok_and(|resp| resp.status().is_success());
+            if ready {
+                return;
+            }
+            assert!(
+                started.elapsed() < STARTUP_TIMEOUT,
+                "lyre didn't become ready"
+            );
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    async fn get_public(&self, path: &str) -> Value {
+        self.client
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .expect("request failed")
+            .json()
+            .await
+            .expect("response wasn't JSON")
+    }
+
+    /// Scratch directory the bot runs in, which is also its `HOME`
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Ban `user_id` from playback in the demo guild, as `/musicban` does; the API can't, so
+    /// this writes to the bot's database directly
+    pub fn music_ban(&self, user_id: &str) {
+        use diesel::connection::SimpleConnection;
+        use diesel::{Connection, SqliteConnection};
+
+        let database = self.dir.join("lyre.db");
+        let mut conn = SqliteConnection::establish(&database.to_string_lossy())
+            .expect("can't open the bot's database");
+        conn.batch_execute(&format!(
+            "PRAGMA busy_timeout = 5000; \
+             INSERT INTO music_bans (guild_id, user_id, banned_by) VALUES ('{}', '{}', '{}');",
+            DEMO_GUILD, user_id, DEMO_USER
+        ))
+        .expect("ban wasn't recorded");
+    }
+
+    /// Send an authenticated request, returning the status and JSON body
+    pub async fn request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> (u16, Value) {
+        self.request_as(&self.token, method, path, body).await
+    }
+
+    pub async fn request_as(
+        &self,
+        token: &str,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> (u16, Value) {
+        let mut req = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path))
+            .bearer_auth(token);
+        if let Some(body) = body {
+            req = req.json(&body);
+        }
+        let resp = req.send().await.expect("request failed");
+        let status = resp.status().as_u16();
+        let body = resp.json().await.unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    /// POST `body` as-is with the given headers and no bearer token (e.g. a signed webhook)
+    pub async fn post_unauthenticated(
+        &self,
+        path: &str,
+        headers: &[(&str, String)],
+        body: &str,
+    ) -> (u16, Value) {
+        let mut req = self
+            .client
+            .post(format!("{}{}", self.base_url, path))
+            .body(body.to_string());
+        for (name, value) in headers {
+            req = req.header(*name, value);
+        }
+        let resp = req.send().await.expect("request failed");
+        let status = resp.status().as_u16();
+        let body = resp.json().await.unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    /// PATCH raw `body` with the given headers, authenticated (e.g. an upload chunk)
+    pub async fn patch_bytes(
+        &self,
+        path: &str,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> (u16, Value) {
+        let mut req = self
+            .client
+            .patch(format!("{}{}", self.base_url, path))
+            .bearer_auth(&self.token)
+            .body(body);
+        for (name, value) in headers {
+            req = req.header(*name, value);
+        }
+        let resp = req.send().await.expect("request failed");
+        let status = resp.status().as_u16();
+        let body = resp.json().await.unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    /// Open an authenticated streaming response (e.g. server-sent events) to read chunk by chunk
+    pub async fn stream(&self, path: &str) -> reqwest::Response {
+        let resp = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .expect("request failed");
+        assert_eq!(resp.status(), 200, "stream request to {} failed", path);
+        resp
+    }
+
+    pub async fn get(&self, path: &str) -> (u16, Value) {
+        self.request(reqwest::Method::GET, path, None).await
+    }
+
+    pub async fn post(&self, path: &str, body: Value) -> (u16, Value) {
+        self.request(reqwest::Method::POST, path, Some(body)).await
+    }
+
+    pub async fn put(&self, path: &str, body: Value) -> (u16, Value) {
+        self.request(reqwest::Method::PUT, path, Some(body)).await
+    }
+
+    pub async fn delete(&self, path: &str) -> (u16, Value) {
+        self.request(reqwest::Method::DELETE, path, None).await
+    }
+
+    /// Queue `url` in the demo guild, joining the test voice channel if needed
+    pub async fn play(&self, url: &str) -> (u16, Value) {
+        self.post(
+            &format!("/api/queue/{}/add", DEMO_GUILD),
+            serde_json::json!({ "url": url, "channel_id": VOICE_CHANNEL }),
+        )
+        .await
+    }
+
+    /// Like `play`, sent with an `Idempotency-Key`; also says whether the answer was a replay
+    pub async fn play_with_key(&self, url: &str, key: &str) -> (u16, Value, bool) {
+        let resp = self
+            .client
+            .post(format!("{}/api/queue/{}/add", self.base_url, DEMO_GUILD))
+            .bearer_auth(&self.token)
+            .header("Idempotency-Key", key)
+            .json(&serde_json::json!({ "url": url, "channel_id": VOICE_CHANNEL }))
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        let replayed = resp.headers().contains_key("idempotent-replayed");
+        let body = resp.json().await.unwrap_or(Value::Null);
+        (status, body, replayed)
+    }
+
+    /// The demo guild's queue as `/api/queue/{guild_id}` returns it
+    pub async fn queue(&self) -> Value {
+        let (status, body) = self.get(&format!("/api/queue/{}", DEMO_GUILD)).await;
+        assert_eq!(status, 200, "queue request failed: {}", body);
+        body["data"].clone()
+    }
+
+    /// Poll the demo guild's queue until `done` holds for it
+    pub async fn wait_for_queue(&self, timeout: Duration, done: impl Fn(&Value) -> bool) -> Value {
+        let started = Instant::now();
+        loop {
+            let queue = self.queue().await;
+            if done(&queue) {
+                return queue;
+            }
+            assert!(
+                started.elapsed() < timeout,
+                "queue never reached the expected state; last seen: {}",
+                queue
+            );
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+}
+
+impl Drop for Lyre {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Title of the track at the head of a queue, if any
+pub fn current_title(queue: &Value) -> Option<&str> {
+    queue["current_track"]["title"].as_str()
+}
+
+fn scratch_dir() -> PathBuf {
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "lyre-test-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("failed to create scratch dir");
+    dir
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("no free port")
+        .port()
+}
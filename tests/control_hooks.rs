@@ -0,0 +1,263 @@
+//! Signed control hooks triggered from outside Discord
+
+mod common;
+
+use common::{DEMO_GUILD, Lyre, MEMBER_TOKEN, VOICE_CHANNEL, current_title};
+use ring::hmac;
+use serde_json::json;
+
+/// Headers signing `body` with `secret` the way `/api/hooks/{token}/play` expects
+fn signed(secret: &str, body: &str) -> Vec<(&'static str, String)> {
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, format!("{}.{}", timestamp, body).as_bytes());
+    vec![
+        ("X-Lyre-Timestamp", timestamp),
+        ("X-Lyre-Signature", format!("sha256={}", hex::encode(tag))),
+    ]
+}
+
+#[tokio::test]
+async fn signed_hook_queues_its_track_within_its_limit() {
+    let lyre = Lyre::start().await;
+
+    let (status, created) = lyre
+        .post(
+            &format!("/api/guild-hooks/{}", DEMO_GUILD),
+            json!({
+                "name": "Stream deck",
+                "url": "https://www.youtube.com/watch?v=airhorn",
+                "voice_channel_id": VOICE_CHANNEL,
+                "rate_limit_per_minute": 3,
+            }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", created);
+    let secret = created["data"]["secret"].as_str().unwrap().to_string();
+    let trigger = created["data"]["trigger_path"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Unsigned, or signed with the wrong secret, is refused without running the hook
+    let (status, _) = lyre.post_unauthenticated(&trigger, &[], "{}").await;
+    assert_eq!(status, 401);
+    let (status, _) = lyre
+        .post_unauthenticated(&trigger, &signed("not-the-secret", "{}"), "{}")
+        .await;
+    assert_eq!(status, 401);
+    assert!(lyre.queue().await["current_track"].is_null());
+
+    let (status, body) = lyre
+        .post_unauthenticated(&trigger, &signed(&secret, "{}"), "{}")
+        .await;
+    assert_eq!(status, 202, "{}", body);
+    assert_eq!(body["data"]["tracks"], 1);
+    assert_eq!(
+        current_title(&lyre.queue().await),
+        Some("Fake track airhorn")
+    );
+
+    // Refused calls count against the limit too; once it's used up they're no longer recorded
+    let (status, body) = lyre
+        .post_unauthenticated(&trigger, &signed(&secret, "{}"), "{}")
+        .await;
+    assert_eq!(status, 429, "{}", body);
+    let (status, _) = lyre
+        .post_unauthenticated(&trigger, &signed("not-the-secret", "{}"), "{}")
+        .await;
+    assert_eq!(status, 401);
+    // A timestamp as far off as it gets is just refused
+    let (status, _) = lyre
+        .post_unauthenticated(
+            &trigger,
+            &[
+                ("X-Lyre-Timestamp", i64::MIN.to_string()),
+                ("X-Lyre-Signature", "sha256=00".to_string()),
+            ],
+            "{}",
+        )
+        .await;
+    assert_eq!(status, 401);
+
+    // Every call is in the audit trail, newest first, and the secret is never shown again
+    let (status, hooks) = lyre.get(&format!("/api/guild-hooks/{}", DEMO_GUILD)).await;
+    assert_eq!(status, 200, "{}", hooks);
+    let hook = &hooks["data"][0];
+    assert!(hook.get("secret").is_none(), "{}", hook);
+    let outcomes: Vec<&str> = hook["recent_calls"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|call| call["outcome"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        outcomes,
+        ["rate_limited", "accepted", "bad_signature", "bad_signature"]
+    );
+
+    let (status, _) = lyre
+        .delete(&format!("/api/guild-hooks/{}/{}", DEMO_GUILD, hook["id"]))
+        .await;
+    assert_eq!(status, 200);
+    let (status, _) = lyre
+        .post_unauthenticated(&trigger, &signed(&secret, "{}"), "{}")
+        .await;
+    assert_eq!(status, 401);
+}
+
+#[tokio::test]
+async fn hooks_respect_quiet_hours_before_joining_voice() {
+    let lyre = Lyre::start().await;
+    let (status, created) = lyre
+        .post(
+            &format!("/api/guild-hooks/{}", DEMO_GUILD),
+            json!({
+                "name": "Night owl",
+                "url": "https://www.youtube.com/watch?v=airhorn",
+                "voice_channel_id": VOICE_CHANNEL,
+            }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", created);
+    let now = chrono::Utc::now();
+    let (status, body) = lyre
+        .put(
+            "/api/guild-settings",
+            json!({
+                "guild_id": DEMO_GUILD,
+                "quiet_hours": {
+                    "start": (now - chrono::Duration::hours(1)).format("%H:%M").to_string(),
+                    "end": (now + chrono::Duration::hours(1)).format("%H:%M").to_string(),
+                    "timezone": "UTC",
+                    "volume": null,
+                },
+            }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let secret = created["data"]["secret"].as_str().unwrap();
+    let trigger = created["data"]["trigger_path"].as_str().unwrap();
+    let (status, body) = lyre
+        .post_unauthenticated(trigger, &signed(secret, "{}"), "{}")
+        .await;
+    assert_eq!(status, 403, "{}", body);
+    assert_eq!(body["error"]["code"], "quiet_hours");
+    let (_, guilds) = lyre.get("/api/guilds").await;
+    assert_eq!(guilds["data"][0]["connected"], false, "{}", guilds);
+}
+
+#[tokio::test]
+async fn hooks_stop_when_their_creator_could_no_longer_queue() {
+    let lyre = Lyre::start().await;
+    let hooks = format!("/api/guild-hooks/{}", DEMO_GUILD);
+    let hook = json!({
+        "name": "Member's deck",
+        "url": "https://www.youtube.com/watch?v=airhorn",
+        "voice_channel_id": VOICE_CHANNEL,
+    });
+
+    let (status, created) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::POST,
+            &hooks,
+            Some(hook.clone()),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", created);
+    let secret = created["data"]["secret"].as_str().unwrap().to_string();
+    let trigger = created["data"]["trigger_path"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Once requests need approval, a member who isn't a DJ can't queue through a hook either
+    let (status, body) = lyre
+        .put(
+            "/api/guild-settings",
+            json!({ "guild_id": DEMO_GUILD, "require_approval": true }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let (status, body) = lyre
+        .request_as(MEMBER_TOKEN, reqwest::Method::POST, &hooks, Some(hook))
+        .await;
+    assert_eq!(status, 403, "{}", body);
+
+    let (status, body) = lyre
+        .post_unauthenticated(&trigger, &signed(&secret, "{}"), "{}")
+        .await;
+    assert_eq!(status, 403, "{}", body);
+    assert!(lyre.queue().await["current_track"].is_null());
+
+    // It stays disabled even once approval is off again
+    lyre.put(
+        "/api/guild-settings",
+        json!({ "guild_id": DEMO_GUILD, "require_approval": false }),
+    )
+    .await;
+    let (status, _) = lyre
+        .post_unauthenticated(&trigger, &signed(&secret, "{}"), "{}")
+        .await;
+    assert_eq!(status, 403);
+    let (_, listed) = lyre.get(&hooks).await;
+    assert_eq!(
+        listed["data"][0]["disabled_reason"],
+        "requests need a DJ's approval and its creator isn't a DJ"
+    );
+}
+
+#[tokio::test]
+async fn only_a_hooks_creator_or_a_manager_can_delete_it() {
+    let lyre = Lyre::start().await;
+    let hooks = format!("/api/guild-hooks/{}", DEMO_GUILD);
+    let hook = |name: &str| {
+        json!({
+            "name": name,
+            "url": "https://www.youtube.com/watch?v=airhorn",
+            "voice_channel_id": VOICE_CHANNEL,
+        })
+    };
+
+    let (status, managers) = lyre.post(&hooks, hook("Manager's deck")).await;
+    assert_eq!(status, 200, "{}", managers);
+    let (status, members) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::POST,
+            &hooks,
+            Some(hook("Member's deck")),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", members);
+
+    let path = |created: &serde_json::Value| format!("{}/{}", hooks, created["data"]["id"]);
+    let (status, _) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::DELETE,
+            &path(&managers),
+            None,
+        )
+        .await;
+    assert_eq!(status, 403);
+    let (status, _) = lyre
+        .request_as(MEMBER_TOKEN, reqwest::Method::DELETE, &path(&members), None)
+        .await;
+    assert_eq!(status, 200);
+
+    // Managers can clear out anyone's hooks
+    let (status, members) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::POST,
+            &hooks,
+            Some(hook("Member's deck")),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", members);
+    let (status, _) = lyre.delete(&path(&members)).await;
+    assert_eq!(status, 200);
+}
@@ -0,0 +1,273 @@
+//! Guild settings as the dashboard sees them
+
+mod common;
+
+use std::time::Duration;
+
+use common::{DEMO_GUILD, DEMO_USER, Lyre, MEMBER_TOKEN, MEMBER_USER, VOICE_CHANNEL};
+use serde_json::json;
+
+#[tokio::test]
+async fn settings_changes_are_pushed() {
+    let lyre = Lyre::start().await;
+    let mut events = lyre
+        .stream(&format!("/api/guild-settings/{}/events", DEMO_GUILD))
+        .await;
+
+    let (status, body) = lyre
+        .put(
+            "/api/guild-settings",
+            json!({ "guild_id": DEMO_GUILD, "max_volume": 0.5 }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let mut received = String::new();
+    while !received.contains("event: settings") {
+        let chunk = tokio::time::timeout(Duration::from_secs(10), events.chunk())
+            .await
+            .expect("no settings events")
+            .expect("event stream failed")
+            .expect("event stream ended");
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+    assert!(received.contains("\"max_volume\":0.5"), "{}", received);
+    assert!(
+        received.contains(&format!("\"guild_id\":\"{}\"", DEMO_GUILD)),
+        "{}",
+        received
+    );
+}
+
+#[tokio::test]
+async fn volume_is_saved_up_to_the_cap() {
+    let lyre = Lyre::start().await;
+    let volume = format!("/api/control/{}/volume", DEMO_GUILD);
+    let settings = format!("/api/guild-settings?guild_id={}", DEMO_GUILD);
+    let set_volume_as_member = |value: f64| {
+        lyre.request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::PUT,
+            &volume,
+            Some(json!({ "volume": value })),
+        )
+    };
+
+    // Any member who can play music can turn it up or down...
+    let (status, body) = set_volume_as_member(0.3).await;
+    assert_eq!(status, 200, "{}", body);
+    let (_, body) = lyre.get(&settings).await;
+    assert_eq!(body["data"]["default_volume"], 0.3, "{}", body);
+
+    // ...but louder than the source needs a manager to raise the cap first
+    let (status, body) = set_volume_as_member(1.5).await;
+    assert_eq!(status, 400, "{}", body);
+    let (status, body) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::PUT,
+            "/api/guild-settings",
+            Some(json!({ "guild_id": DEMO_GUILD, "max_volume": 2.0 })),
+        )
+        .await;
+    assert_eq!(status, 403, "{}", body);
+    let (status, body) = set_volume_as_member(1.5).await;
+    assert_eq!(status, 400, "{}", body);
+
+    // The default demo token owns the guild
+    let (status, body) = lyre
+        .put(
+            "/api/guild-settings",
+            json!({ "guild_id": DEMO_GUILD, "max_volume": 2.0 }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let (status, body) = set_volume_as_member(1.5).await;
+    assert_eq!(status, 200, "{}", body);
+    let (_, body) = lyre.get(&settings).await;
+    assert_eq!(body["data"]["default_volume"], 1.5, "{}", body);
+}
+
+#[tokio::test]
+async fn filter_presets_switch_the_guilds_filters() {
+    let lyre = Lyre::start_with(&[("LYRE_ADMIN_USER_IDS", DEMO_USER)]).await;
+    let preset = format!("/api/filter-presets/{}/movie%20night", DEMO_GUILD);
+
+    let (status, body) = lyre
+        .put(&preset, json!({ "filters": ["bassboost:low", "loudnorm"] }))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        body["data"]["filters"],
+        json!(["bassboost:low", "loudnorm"])
+    );
+    let (status, _) = lyre.put(&preset, json!({ "filters": ["reverb"] })).await;
+    assert_eq!(status, 400);
+
+    // Filters have to be enabled for the server first
+    let activate = format!("{}/activate", preset);
+    let (status, _) = lyre.post(&activate, json!({})).await;
+    assert_eq!(status, 403);
+    lyre.put(
+        "/api/admin/feature-flags",
+        json!({ "flag": "filters", "guild_id": DEMO_GUILD, "enabled": true }),
+    )
+    .await;
+    let (status, body) = lyre.post(&activate, json!({})).await;
+    assert_eq!(status, 200, "{}", body);
+    let (_, settings) = lyre
+        .get(&format!("/api/guild-settings?guild_id={}", DEMO_GUILD))
+        .await;
+    assert_eq!(
+        settings["data"]["audio_filters"],
+        json!(["bassboost:low", "loudnorm"])
+    );
+
+    let (status, _) = lyre.delete(&preset).await;
+    assert_eq!(status, 200);
+    let (status, _) = lyre.post(&activate, json!({})).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn filter_presets_are_held_to_the_filter_command() {
+    let lyre = Lyre::start().await;
+    let preset = format!("/api/filter-presets/{}/movie%20night", DEMO_GUILD);
+    let (status, _) = lyre.put(&preset, json!({ "filters": ["loudnorm"] })).await;
+    assert_eq!(status, 200);
+    let as_member = |method: reqwest::Method| {
+        let body = (method == reqwest::Method::PUT).then(|| json!({ "filters": ["karaoke"] }));
+        lyre.request_as(MEMBER_TOKEN, method, &preset, body)
+    };
+
+    // Locked to a role the member doesn't have, like /filter itself
+    let (status, body) = lyre
+        .put(
+            "/api/guild-settings",
+            json!({ "guild_id": DEMO_GUILD, "command_roles": { "filter": ["222222222222222222"] } }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    for method in [reqwest::Method::PUT, reqwest::Method::DELETE] {
+        let (status, body) = as_member(method).await;
+        assert_eq!(status, 403, "{}", body);
+        assert_eq!(body["error"]["code"], "command_locked");
+    }
+
+    // Without the lock a banned member is still refused
+    lyre.put(
+        "/api/guild-settings",
+        json!({ "guild_id": DEMO_GUILD, "command_roles": {} }),
+    )
+    .await;
+    let (status, body) = as_member(reqwest::Method::PUT).await;
+    assert_eq!(status, 200, "{}", body);
+    lyre.music_ban(MEMBER_USER);
+    for method in [reqwest::Method::PUT, reqwest::Method::DELETE] {
+        let (status, body) = as_member(method).await;
+        assert_eq!(status, 403, "{}", body);
+        assert_eq!(body["error"]["code"], "user_banned");
+    }
+
+    let (_, body) = lyre
+        .get(&format!("/api/filter-presets/{}", DEMO_GUILD))
+        .await;
+    assert_eq!(body["data"][0]["filters"], json!(["karaoke"]), "{}", body);
+}
+
+#[tokio::test]
+async fn settings_are_only_for_the_guilds_managers() {
+    let lyre = Lyre::start().await;
+    let other_guild = "123123123123123123";
+
+    let (status, _) = lyre
+        .get(&format!("/api/guild-settings?guild_id={}", other_guild))
+        .await;
+    assert_eq!(status, 403);
+    let (status, _) = lyre
+        .put(
+            "/api/guild-settings",
+            json!({ "guild_id": other_guild, "explicit_filter": false }),
+        )
+        .await;
+    assert_eq!(status, 403);
+
+    // Members who can play music still can't see or change how the server is set up
+    let (status, _) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::GET,
+            &format!("/api/guild-settings?guild_id={}", DEMO_GUILD),
+            None,
+        )
+        .await;
+    assert_eq!(status, 403);
+    let (status, _) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::PUT,
+            "/api/guild-settings",
+            Some(json!({ "guild_id": DEMO_GUILD, "allowed_domains": ["evil.example"] })),
+        )
+        .await;
+    assert_eq!(status, 403);
+    let (status, body) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::GET,
+            &format!("/api/queue/{}", DEMO_GUILD),
+            None,
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+}
+
+#[tokio::test]
+async fn command_locks_hold_for_the_api() {
+    let lyre = Lyre::start().await;
+    let (status, body) = lyre
+        .put(
+            "/api/guild-settings",
+            json!({
+                "guild_id": DEMO_GUILD,
+                "command_roles": { "play": ["222222222222222222"], "volume": ["222222222222222222"] },
+            }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    // The member has none of the roles, so the dashboard is as closed to them as /play is
+    let (status, body) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::POST,
+            &format!("/api/queue/{}/add", DEMO_GUILD),
+            Some(json!({ "url": "https://www.youtube.com/watch?v=locked", "channel_id": VOICE_CHANNEL })),
+        )
+        .await;
+    assert_eq!(status, 403, "{}", body);
+    assert_eq!(body["error"]["code"], "command_locked");
+    let (status, body) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::PUT,
+            &format!("/api/control/{}/volume", DEMO_GUILD),
+            Some(json!({ "volume": 0.5 })),
+        )
+        .await;
+    assert_eq!(status, 403, "{}", body);
+    assert_eq!(body["error"]["code"], "command_locked");
+
+    // Managers count as DJs and get through
+    let (status, body) = lyre
+        .put(
+            &format!("/api/control/{}/volume", DEMO_GUILD),
+            json!({ "volume": 0.5 }),
+        )
+        .await;
+    assert_ne!(
+        body["error"]["code"], "command_locked",
+        "{} {}",
+        status, body
+    );
+}
@@ -0,0 +1,75 @@
+//! Operator hook scripts from `LYRE_HOOKS_DIR`
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{DEMO_GUILD, DEMO_USER, Lyre};
+
+#[tokio::test]
+async fn hooks_receive_queue_and_playback_events() {
+    let hooks = std::env::temp_dir().join(format!("lyre-hooks-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&hooks);
+    std::fs::create_dir_all(&hooks).unwrap();
+    let log = hooks.join("events.log");
+    let env_log = hooks.join("env.log");
+    for (name, body) in [
+        (
+            "queue_add",
+            format!("cat >> '{0}'\necho >> '{0}'", log.display()),
+        ),
+        (
+            "track_start.sh",
+            format!("cat >> '{0}'\necho >> '{0}'", log.display()),
+        ),
+        ("queue_add-env", format!("env > '{}'", env_log.display())),
+    ] {
+        let script = hooks.join(name);
+        std::fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+    // Not executable, so never run
+    std::fs::write(hooks.join("user_join"), "#!/bin/sh\nexit 1\n").unwrap();
+
+    let lyre = Lyre::start_with(&[
+        ("LYRE_HOOKS_DIR", hooks.to_str().unwrap()),
+        ("DISCORD_TOKEN", "not-for-hooks"),
+    ])
+    .await;
+    let (status, body) = lyre.play("https://www.youtube.com/watch?v=hooked").await;
+    assert_eq!(status, 200, "{}", body);
+
+    let started = Instant::now();
+    let events: Vec<serde_json::Value> = loop {
+        let events: Vec<serde_json::Value> = std::fs::read_to_string(&log)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        if events.len() >= 2 && env_log.exists() {
+            break events;
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(20),
+            "hooks didn't run; saw {:?}",
+            events
+        );
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    };
+    // Scripts get the event, not the bot's secrets
+    let env = std::fs::read_to_string(&env_log).unwrap_or_default();
+    let _ = std::fs::remove_dir_all(&hooks);
+    assert!(env.contains("LYRE_EVENT="), "{}", env);
+    assert!(!env.contains("not-for-hooks"), "{}", env);
+
+    let queue_add = events.iter().find(|e| e["event"] == "queue_add").unwrap();
+    assert_eq!(queue_add["guild_id"], DEMO_GUILD);
+    assert_eq!(queue_add["requested_by"], DEMO_USER);
+    assert_eq!(queue_add["title"], "Fake track hooked");
+    let track_start = events.iter().find(|e| e["event"] == "track_start").unwrap();
+    assert_eq!(track_start["url"], "https://www.youtube.com/watch?v=hooked");
+}
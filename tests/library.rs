@@ -0,0 +1,69 @@
+//! The local music library from `LYRE_LIBRARY_DIR`
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::Lyre;
+
+fn current_title(queue: &serde_json::Value) -> Option<&str> {
+    queue["current_track"]["title"].as_str()
+}
+
+#[tokio::test]
+async fn library_tracks_are_indexed_and_played_from_disk() {
+    let library = std::env::temp_dir().join(format!("lyre-library-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&library);
+    std::fs::create_dir_all(library.join("Some Album")).unwrap();
+    std::fs::write(
+        library.join("Some Album/Some Artist - Some Song.flac"),
+        b"fLaC not really",
+    )
+    .unwrap();
+    // Not audio, so not indexed
+    std::fs::write(library.join("cover.jpg"), b"jpeg").unwrap();
+
+    let lyre = Lyre::start_with(&[("LYRE_LIBRARY_DIR", library.to_str().unwrap())]).await;
+
+    // Unknown tracks are refused, so keep asking until the first pass has indexed the file
+    let started = Instant::now();
+    loop {
+        let (status, body) = lyre.play("library:1").await;
+        if status == 200 {
+            break;
+        }
+        assert_eq!(status, 404, "{}", body);
+        assert!(started.elapsed() < Duration::from_secs(10), "never indexed");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let (status, body) = lyre.play("library:2").await;
+    assert_eq!(status, 404, "{}", body);
+
+    // The untagged file goes by its name, and plays without yt-dlp or the network
+    let queue = lyre
+        .wait_for_queue(Duration::from_secs(10), |q| {
+            q["current_track"]["status"] == "playing"
+        })
+        .await;
+    assert_eq!(current_title(&queue), Some("Some Song"));
+    // Converted into the cache; nothing is written into the library
+    assert!(
+        lyre.dir()
+            .join("downloads/library")
+            .read_dir()
+            .is_ok_and(|mut files| files.next().is_some())
+    );
+    let entries: Vec<_> = std::fs::read_dir(library.join("Some Album"))
+        .unwrap()
+        .collect();
+    assert_eq!(entries.len(), 1);
+
+    let _ = std::fs::remove_dir_all(&library);
+}
+
+#[tokio::test]
+async fn library_urls_need_a_library() {
+    let lyre = Lyre::start().await;
+    let (status, body) = lyre.play("library:1").await;
+    assert_eq!(status, 400, "{}", body);
+}
@@ -0,0 +1,75 @@
+//! Lyrics from the provider in `LYRE_LYRICS_URL`
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use common::{DEMO_GUILD, Lyre};
+use serde_json::json;
+
+/// A stand-in for LRCLIB on a local port, where every search finds the same song. Returns its
+/// base URL and how many requests it has answered.
+fn fake_provider() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap_or(0);
+            counter.fetch_add(1, Ordering::SeqCst);
+            let track = r#"{"trackName":"Fake Song","artistName":"Fake Singer","instrumental":false,"plainLyrics":"la la la","syncedLyrics":"[00:00.50] la la la"}"#;
+            let body = if buf[..n].starts_with(b"GET /api/search") {
+                format!("[{}]", track)
+            } else {
+                track.to_string()
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    (base, requests)
+}
+
+#[tokio::test]
+async fn lyrics_of_the_playing_track_are_looked_up_once() {
+    let (provider, requests) = fake_provider();
+    let lyre = Lyre::start_with(&[("LYRE_LYRICS_URL", &provider)]).await;
+    let path = format!("/api/lyrics/{}", DEMO_GUILD);
+
+    let (status, body) = lyre.get(&path).await;
+    assert_eq!(status, 404, "{}", body);
+
+    lyre.play("https://www.youtube.com/watch?v=sung").await;
+    lyre.wait_for_queue(Duration::from_secs(20), |q| q["is_playing"] == true)
+        .await;
+    // Held so the track is still playing when asked about
+    let (status, body) = lyre
+        .post(&format!("/api/control/{}/pause", DEMO_GUILD), json!({}))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = lyre.get(&path).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["title"], "Fake Song");
+    assert_eq!(body["data"]["artist"], "Fake Singer");
+    assert_eq!(body["data"]["lyrics"], "la la la");
+    assert_eq!(body["data"]["synced_lyrics"], "[00:00.50] la la la");
+    assert_eq!(body["data"]["source"], "127.0.0.1");
+    let asked = requests.load(Ordering::SeqCst);
+    assert!(asked > 0);
+
+    // Cached from then on
+    let (status, body) = lyre.get(&path).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(requests.load(Ordering::SeqCst), asked);
+}
@@ -0,0 +1,436 @@
+//! End-to-end /play → queue → skip → stop flows against the simulated voice layer
+
+mod common;
+
+use std::time::Duration;
+
+use common::{DEMO_GUILD, Lyre, MEMBER_TOKEN, VOICE_CHANNEL, current_title};
+use serde_json::json;
+
+#[tokio::test]
+async fn play_queue_skip_stop() {
+    let lyre = Lyre::start().await;
+
+    let (status, body) = lyre.play("https://www.youtube.com/watch?v=first").await;
+    assert_eq!(status, 200, "{}", body);
+    let (status, body) = lyre.play("https://www.youtube.com/watch?v=second").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"], "Track added to queue at position 1");
+
+    let queue = lyre.queue().await;
+    assert_eq!(current_title(&queue), Some("Fake track first"));
+    assert_eq!(queue["queue"][0]["title"], "Fake track second");
+    // Simulated tracks are fetched when their turn comes
+    assert_eq!(queue["queue"][0]["status"], "pending_download");
+
+    let (status, _) = lyre
+        .post(&format!("/api/queue/{}/skip", DEMO_GUILD), json!({}))
+        .await;
+    assert_eq!(status, 200);
+    let queue = lyre.queue().await;
+    assert_eq!(current_title(&queue), Some("Fake track second"));
+    assert_eq!(queue["queue"].as_array().map(Vec::len), Some(0));
+
+    let (status, _) = lyre
+        .post(&format!("/api/control/{}/stop", DEMO_GUILD), json!({}))
+        .await;
+    assert_eq!(status, 200);
+    let queue = lyre.queue().await;
+    assert!(queue["current_track"].is_null(), "{}", queue);
+
+    let (_, guilds) = lyre.get("/api/guilds").await;
+    assert_eq!(guilds["data"][0]["id"], DEMO_GUILD);
+    assert_eq!(guilds["data"][0]["connected"], false);
+}
+
+#[tokio::test]
+async fn tracks_play_through_and_are_recorded() {
+    let lyre = Lyre::start().await;
+
+    lyre.play("https://www.youtube.com/watch?v=alpha").await;
+    lyre.play("https://www.youtube.com/watch?v=beta").await;
+
+    let queue = lyre
+        .wait_for_queue(Duration::from_secs(20), |q| q["is_playing"] == true)
+        .await;
+    assert_eq!(queue["current_track"]["status"], "playing", "{}", queue);
+    lyre.wait_for_queue(Duration::from_secs(30), |q| q["current_track"].is_null())
+        .await;
+
+    let (status, recent) = lyre
+        .get(&format!("/api/recent-tracks?guild_id={}", DEMO_GUILD))
+        .await;
+    assert_eq!(status, 200, "{}", recent);
+    let mut titles: Vec<&str> = recent["data"]
+        .as_array()
+        .expect("recent tracks")
+        .iter()
+        .filter_map(|t| t["title"].as_str())
+        .collect();
+    titles.sort();
+    assert_eq!(titles, ["Fake track alpha", "Fake track beta"]);
+    for track in recent["data"].as_array().unwrap() {
+        assert_eq!(track["status"], "completed", "{}", track);
+        assert!(track["started_at"].is_string(), "{}", track);
+        assert!(track["finished_at"].is_string(), "{}", track);
+    }
+
+    let recent = format!("/api/recent-tracks?guild_id={}", DEMO_GUILD);
+    let (_, found) = lyre.get(&format!("{}&q=TRACK%20alp", recent)).await;
+    assert_eq!(found["data"].as_array().map(Vec::len), Some(1), "{}", found);
+    assert_eq!(found["data"][0]["title"], "Fake track alpha");
+    let (_, found) = lyre.get(&format!("{}&q=100%25", recent)).await;
+    assert_eq!(found["data"].as_array().map(Vec::len), Some(0), "{}", found);
+    let (_, found) = lyre.get(&format!("{}&user_id=42", recent)).await;
+    assert_eq!(found["data"].as_array().map(Vec::len), Some(0), "{}", found);
+    let (status, _) = lyre.get(&format!("{}&user_id=someone", recent)).await;
+    assert_eq!(status, 400);
+
+    // Both downloads were probed and cached with their real size
+    let (_, cache) = lyre.get("/api/cache-stats").await;
+    assert_eq!(cache["data"]["total_songs"], 2, "{}", cache);
+    assert_eq!(cache["data"]["total_size_bytes"], 26, "{}", cache);
+    // ...in their source's folder
+    let downloads = lyre.dir().join("downloads");
+    assert!(downloads.join("youtube/alpha.mp3").exists());
+    assert!(!downloads.join("alpha.mp3").exists());
+}
+
+#[tokio::test]
+async fn download_phases_are_streamed() {
+    let lyre = Lyre::start().await;
+    let mut events = lyre
+        .stream(&format!("/api/downloads/{}/events", DEMO_GUILD))
+        .await;
+
+    lyre.play("https://www.youtube.com/watch?v=phases").await;
+
+    let mut received = String::new();
+    while !received.contains("\"phase\":\"ready\"") {
+        let chunk = tokio::time::timeout(Duration::from_secs(20), events.chunk())
+            .await
+            .expect("no download events")
+            .expect("event stream failed")
+            .expect("event stream ended");
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+    let position = |phase: &str| {
+        received
+            .find(&format!("\"phase\":\"{}\"", phase))
+            .unwrap_or_else(|| panic!("no {} event in {}", phase, received))
+    };
+    assert!(
+        position("downloading") < position("converting"),
+        "{}",
+        received
+    );
+    assert!(position("converting") < position("ready"), "{}", received);
+    assert!(received.contains("\"url\":\"https://www.youtube.com/watch?v=phases\""));
+}
+
+#[tokio::test]
+async fn failed_download_skips_to_next_track() {
+    let lyre = Lyre::start().await;
+
+    // Metadata lookups fail too, so the broken track is queued without a title
+    lyre.play("https://www.youtube.com/watch?v=unavailable")
+        .await;
+    lyre.play("https://www.youtube.com/watch?v=after").await;
+
+    lyre.wait_for_queue(Duration::from_secs(20), |q| {
+        current_title(q) == Some("Fake track after")
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn clear_empties_the_queue() {
+    let lyre = Lyre::start().await;
+
+    lyre.play("https://www.youtube.com/watch?v=one").await;
+    lyre.play("https://www.youtube.com/watch?v=two").await;
+
+    let (status, _) = lyre.delete(&format!("/api/queue/{}", DEMO_GUILD)).await;
+    assert_eq!(status, 200);
+    let queue = lyre.queue().await;
+    assert!(queue["current_track"].is_null(), "{}", queue);
+    assert_eq!(queue["queue"].as_array().map(Vec::len), Some(0));
+}
+
+#[tokio::test]
+async fn dedupe_keeps_the_first_of_each_url() {
+    let lyre = Lyre::start().await;
+
+    for id in ["now", "dup", "other", "dup", "dup"] {
+        lyre.play(&format!("https://www.youtube.com/watch?v={}", id))
+            .await;
+    }
+
+    let (status, body) = lyre
+        .post(&format!("/api/queue/{}/dedupe", DEMO_GUILD), json!({}))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["removed"], 2);
+    let queue = lyre.queue().await;
+    let titles: Vec<&str> = queue["queue"]
+        .as_array()
+        .expect("pending tracks")
+        .iter()
+        .filter_map(|t| t["title"].as_str())
+        .collect();
+    assert_eq!(titles, ["Fake track dup", "Fake track other"]);
+}
+
+#[tokio::test]
+async fn play_without_voice_channel_is_rejected() {
+    let lyre = Lyre::start().await;
+
+    let (status, body) = lyre
+        .post(
+            &format!("/api/queue/{}/add", DEMO_GUILD),
+            json!({ "url": "https://www.youtube.com/watch?v=lonely" }),
+        )
+        .await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(body["error"]["code"], "invalid_input");
+}
+
+#[tokio::test]
+async fn quiet_hours_refuse_before_joining_voice() {
+    let lyre = Lyre::start().await;
+    // On Berlin's clocks, so the window is checked in a zone with daylight saving
+    let now = chrono::Utc::now().with_timezone(&chrono_tz::Europe::Berlin);
+    let (status, body) = lyre
+        .put(
+            "/api/guild-settings",
+            json!({
+                "guild_id": DEMO_GUILD,
+                "quiet_hours": {
+                    "start": (now - chrono::Duration::hours(1)).format("%H:%M").to_string(),
+                    "end": (now + chrono::Duration::hours(1)).format("%H:%M").to_string(),
+                    "timezone": "Europe/Berlin",
+                    "volume": null,
+                },
+            }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = lyre.play("https://www.youtube.com/watch?v=late").await;
+    assert_eq!(status, 403, "{}", body);
+    assert_eq!(body["error"]["code"], "quiet_hours");
+    let (_, guilds) = lyre.get("/api/guilds").await;
+    assert_eq!(guilds["data"][0]["connected"], false, "{}", guilds);
+}
+
+#[tokio::test]
+async fn requests_from_non_djs_wait_for_approval() {
+    let lyre = Lyre::start().await;
+    let (status, body) = lyre
+        .put(
+            "/api/guild-settings",
+            json!({ "guild_id": DEMO_GUILD, "require_approval": true }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::POST,
+            &format!("/api/queue/{}/add", DEMO_GUILD),
+            Some(json!({
+                "url": "https://www.youtube.com/watch?v=held",
+                "channel_id": VOICE_CHANNEL,
+            })),
+        )
+        .await;
+    assert_eq!(status, 202, "{}", body);
+    let queue = lyre.queue().await;
+    assert!(queue["current_track"].is_null(), "{}", queue);
+    assert_eq!(
+        queue["queue"].as_array().map(Vec::len),
+        Some(0),
+        "{}",
+        queue
+    );
+
+    // The server's managers are DJs and queue directly
+    let (status, body) = lyre.play("https://www.youtube.com/watch?v=direct").await;
+    assert_eq!(status, 200, "{}", body);
+}
+
+#[tokio::test]
+async fn retried_adds_with_the_same_key_queue_once() {
+    let lyre = Lyre::start().await;
+    let url = "https://www.youtube.com/watch?v=once";
+
+    let (status, first, replayed) = lyre.play_with_key(url, "add-1").await;
+    assert_eq!(status, 200, "{}", first);
+    assert!(!replayed);
+    let (status, retry, replayed) = lyre.play_with_key(url, "add-1").await;
+    assert_eq!(status, 200, "{}", retry);
+    assert!(replayed);
+    assert_eq!(retry, first);
+    let queue = lyre.queue().await;
+    assert_eq!(
+        queue["queue"].as_array().map(Vec::len),
+        Some(0),
+        "{}",
+        queue
+    );
+
+    // A key belongs to the request it was first used for
+    let (status, body, _) = lyre
+        .play_with_key("https://www.youtube.com/watch?v=other", "add-1")
+        .await;
+    assert_eq!(status, 400, "{}", body);
+    let (status, body, replayed) = lyre.play_with_key(url, "add-2").await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(!replayed);
+    assert_eq!(body["data"], "Track added to queue at position 1");
+}
+
+#[tokio::test]
+async fn other_guilds_and_tokens_are_refused() {
+    let lyre = Lyre::start().await;
+
+    let (status, _) = lyre
+        .request_as(
+            "not-a-demo-token",
+            reqwest::Method::GET,
+            &format!("/api/queue/{}", DEMO_GUILD),
+            None,
+        )
+        .await;
+    assert_eq!(status, 401);
+
+    let (status, _) = lyre.get("/api/queue/123123123123123123").await;
+    assert_eq!(status, 403);
+}
+
+#[tokio::test]
+async fn pause_holds_the_track_until_resumed() {
+    let lyre = Lyre::start().await;
+
+    let (status, _) = lyre
+        .post(&format!("/api/control/{}/pause", DEMO_GUILD), json!({}))
+        .await;
+    assert_eq!(status, 404);
+
+    lyre.play("https://www.youtube.com/watch?v=held").await;
+    lyre.wait_for_queue(Duration::from_secs(20), |q| q["is_playing"] == true)
+        .await;
+    let (status, body) = lyre
+        .post(&format!("/api/control/{}/pause", DEMO_GUILD), json!({}))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["paused"], true);
+    assert_eq!(body["data"]["title"], "Fake track held");
+
+    // Well past the fake track's length, it's still waiting to be resumed
+    tokio::time::sleep(Duration::from_secs(4)).await;
+    let queue = lyre.queue().await;
+    assert_eq!(current_title(&queue), Some("Fake track held"));
+    assert_eq!(queue["is_playing"], false);
+
+    let (status, body) = lyre
+        .post(&format!("/api/control/{}/resume", DEMO_GUILD), json!({}))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["paused"], false);
+    lyre.wait_for_queue(Duration::from_secs(20), |q| q["current_track"].is_null())
+        .await;
+}
+
+#[tokio::test]
+async fn seek_moves_the_playing_track() {
+    let lyre = Lyre::start().await;
+
+    lyre.play("https://www.youtube.com/watch?v=long").await;
+    lyre.wait_for_queue(Duration::from_secs(20), |q| q["is_playing"] == true)
+        .await;
+    // Paused so the two-second fake track can't end mid-test
+    lyre.post(&format!("/api/control/{}/pause", DEMO_GUILD), json!({}))
+        .await;
+
+    let seek = format!("/api/control/{}/seek", DEMO_GUILD);
+    let (status, body) = lyre.put(&seek, json!({ "seconds": 1 })).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["position_secs"], 1);
+    assert_eq!(body["data"]["paused"], true);
+    let queue = lyre.queue().await;
+    assert_eq!(queue["paused"], true, "{}", queue);
+    assert_eq!(queue["elapsed_secs"], 1, "{}", queue);
+
+    let (status, _) = lyre.put(&seek, json!({ "seconds": 5 })).await;
+    assert_eq!(status, 400);
+    let (status, _) = lyre.put(&seek, json!({ "seconds": -1 })).await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn guild_list_reports_the_live_session() {
+    let lyre = Lyre::start().await;
+
+    lyre.play("https://www.youtube.com/watch?v=head").await;
+    lyre.play("https://www.youtube.com/watch?v=next").await;
+
+    let (status, guilds) = lyre.get("/api/guilds").await;
+    assert_eq!(status, 200, "{}", guilds);
+    let guild = &guilds["data"][0];
+    assert_eq!(guild["connected"], true);
+    assert_eq!(guild["voice_channel_id"], VOICE_CHANNEL);
+    // Without Discord there's no channel name to look up
+    assert_eq!(guild["voice_channel"], VOICE_CHANNEL);
+    assert_eq!(guild["current_track"], "Fake track head");
+    assert_eq!(guild["queue_length"], 1);
+}
+
+#[tokio::test]
+async fn overview_gathers_the_landing_page() {
+    let lyre = Lyre::start().await;
+
+    let (status, overview) = lyre.get("/api/overview").await;
+    assert_eq!(status, 200, "{}", overview);
+    assert_eq!(overview["data"]["sessions"], json!([]));
+
+    lyre.play("https://www.youtube.com/watch?v=head").await;
+
+    let (_, overview) = lyre.get("/api/overview").await;
+    let data = &overview["data"];
+    // Without Discord there's no bot account or shards to report
+    assert!(data["bot"].is_null(), "{}", data);
+    assert_eq!(data["shards"], json!([]));
+    assert_eq!(data["ready"], true);
+    assert!(data["uptime_secs"].is_u64(), "{}", data);
+    assert!(data["cache"]["total_songs"].is_i64(), "{}", data);
+    let session = &data["sessions"][0];
+    assert_eq!(session["id"], DEMO_GUILD);
+    assert_eq!(session["voice_channel_id"], VOICE_CHANNEL);
+    assert_eq!(session["current_track"], "Fake track head");
+}
+
+#[tokio::test]
+async fn metadata_is_looked_up_once_per_url() {
+    let log = std::env::temp_dir().join(format!("lyre-yt-dlp-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&log);
+    let lyre = Lyre::start_with(&[("FAKE_YT_DLP_LOG", log.to_str().unwrap())]).await;
+
+    lyre.play("https://www.youtube.com/watch?v=once").await;
+    lyre.wait_for_queue(Duration::from_secs(30), |q| q["current_track"].is_null())
+        .await;
+    lyre.play("https://www.youtube.com/watch?v=once").await;
+    lyre.wait_for_queue(Duration::from_secs(30), |q| q["current_track"].is_null())
+        .await;
+
+    // One metadata call serves both plays and names the cached file; the second play reuses
+    // the download too
+    let runs = std::fs::read_to_string(&log).unwrap_or_default();
+    let _ = std::fs::remove_file(&log);
+    let runs: Vec<&str> = runs
+        .lines()
+        .filter(|line| line.ends_with(" once"))
+        .collect();
+    assert_eq!(runs, ["metadata once", "download once"]);
+}
@@ -0,0 +1,59 @@
+//! Saved playlists through the API
+
+mod common;
+
+use common::{DEMO_GUILD, Lyre};
+use serde_json::json;
+
+#[tokio::test]
+async fn playlists_can_be_saved_replaced_and_deleted() {
+    let lyre = Lyre::start().await;
+    let path = format!("/api/playlists/{}/Road%20Trip", DEMO_GUILD);
+
+    let (status, body) = lyre
+        .put(
+            &path,
+            json!({ "tracks": [
+                { "url": "https://example.com/a.mp3", "title": "Song A", "duration": 180 },
+                { "url": "https://example.com/b.mp3" },
+            ] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["name"], "Road Trip");
+    assert_eq!(
+        body["data"]["tracks"][1]["title"],
+        "https://example.com/b.mp3"
+    );
+
+    let (status, body) = lyre.get(&format!("/api/playlists/{}", DEMO_GUILD)).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["data"][0]["track_count"], 2);
+
+    // Saving again replaces the tracks
+    let (status, _) = lyre
+        .put(
+            &path,
+            json!({ "tracks": [{ "url": "https://example.com/c.mp3", "title": "Song C" }] }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    let (_, body) = lyre.get(&path).await;
+    assert_eq!(body["data"]["tracks"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"]["tracks"][0]["title"], "Song C");
+
+    let (status, _) = lyre.put(&path, json!({ "tracks": [] })).await;
+    assert_eq!(status, 400);
+    let (status, _) = lyre
+        .put(
+            &path,
+            json!({ "tracks": [{ "url": "file:///etc/passwd" }] }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    let (status, _) = lyre.delete(&path).await;
+    assert_eq!(status, 200);
+    let (status, _) = lyre.get(&path).await;
+    assert_eq!(status, 404);
+}
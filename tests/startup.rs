@@ -0,0 +1,18 @@
+//! Starting the bot with as little configuration as possible
+
+mod common;
+
+use common::{Lyre, current_title};
+
+#[tokio::test]
+async fn creates_a_default_database_without_database_url() {
+    let lyre = Lyre::start_with(&[("DATABASE_URL", "")]).await;
+
+    let (status, body) = lyre.play("https://www.youtube.com/watch?v=fresh").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(current_title(&lyre.queue().await), Some("Fake track fresh"));
+
+    // HOME is the scratch dir and XDG_CACHE_HOME is unset, so the cache dir is ~/.cache
+    assert!(lyre.dir().join(".cache/lyre/lyre.db").exists());
+    assert!(!lyre.dir().join("lyre.db").exists());
+}
@@ -0,0 +1,167 @@
+//! Chunked, resumable uploads
+
+mod common;
+
+use std::time::Duration;
+
+use common::{DEMO_GUILD, Lyre, MEMBER_TOKEN, current_title};
+use serde_json::{Value, json};
+
+/// Send `chunk` of upload `id` starting at `offset`
+async fn send_chunk(lyre: &Lyre, id: &str, offset: usize, chunk: &[u8]) -> (u16, Value) {
+    lyre.patch_bytes(
+        &format!("/api/uploads/{}/{}", DEMO_GUILD, id),
+        &[("Upload-Offset", offset.to_string())],
+        chunk.to_vec(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn uploads_resume_and_only_keep_audio() {
+    let lyre = Lyre::start_with(&[("LYRE_UPLOAD_GUILD_QUOTA_MB", "1")]).await;
+
+    let mut file = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+    file.resize(4096, 0x55);
+    let (status, created) = lyre
+        .post(
+            &format!("/api/uploads/{}", DEMO_GUILD),
+            json!({ "filename": "intro.mp3", "size": file.len() }),
+        )
+        .await;
+    assert_eq!(status, 201, "{}", created);
+    let id = created["data"]["id"].as_str().unwrap().to_string();
+
+    let (status, body) = send_chunk(&lyre, &id, 0, &file[..1000]).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["received"], 1000);
+
+    // A retried or out-of-order chunk is refused, and the client can ask where to resume
+    let (status, _) = send_chunk(&lyre, &id, 0, &file[..1000]).await;
+    assert_eq!(status, 409);
+    let (_, progress) = lyre
+        .get(&format!("/api/uploads/{}/{}", DEMO_GUILD, id))
+        .await;
+    assert_eq!(progress["data"]["received"], 1000);
+    assert!(progress["data"]["completed_at"].is_null());
+
+    let (status, body) = send_chunk(&lyre, &id, 1000, &file[1000..]).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["content_type"], "audio/mpeg");
+    let (_, listed) = lyre.get(&format!("/api/uploads/{}", DEMO_GUILD)).await;
+    assert_eq!(listed["data"]["uploads"][0]["filename"], "intro.mp3");
+    assert_eq!(listed["data"]["used_bytes"], 4096);
+
+    // Something that isn't audio is dropped once it's all in
+    let script = b"#!/bin/sh\necho hi\n";
+    let (_, created) = lyre
+        .post(
+            &format!("/api/uploads/{}", DEMO_GUILD),
+            json!({ "filename": "song.mp3", "size": script.len() }),
+        )
+        .await;
+    let bogus = created["data"]["id"].as_str().unwrap().to_string();
+    let (status, body) = send_chunk(&lyre, &bogus, 0, script).await;
+    assert_eq!(status, 400, "{}", body);
+    let (status, _) = lyre
+        .get(&format!("/api/uploads/{}/{}", DEMO_GUILD, bogus))
+        .await;
+    assert_eq!(status, 404);
+
+    // The guild's quota counts what it already keeps
+    let (status, body) = lyre
+        .post(
+            &format!("/api/uploads/{}", DEMO_GUILD),
+            json!({ "filename": "album.flac", "size": 1024 * 1024 }),
+        )
+        .await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"]["details"]["used_bytes"], 4096);
+
+    let (status, _) = lyre
+        .delete(&format!("/api/uploads/{}/{}", DEMO_GUILD, id))
+        .await;
+    assert_eq!(status, 200);
+    let (_, listed) = lyre.get(&format!("/api/uploads/{}", DEMO_GUILD)).await;
+    assert_eq!(listed["data"]["used_bytes"], 0);
+}
+
+#[tokio::test]
+async fn finished_uploads_play_as_upload_urls() {
+    let lyre = Lyre::start().await;
+
+    let mut file = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+    file.resize(2048, 0x55);
+    let (_, created) = lyre
+        .post(
+            &format!("/api/uploads/{}", DEMO_GUILD),
+            json!({ "filename": "Intro Theme.mp3", "size": file.len() }),
+        )
+        .await;
+    let id = created["data"]["id"].as_str().unwrap().to_string();
+    let url = format!("upload:{}", id);
+
+    // Only finished uploads can be queued
+    let (status, _) = send_chunk(&lyre, &id, 0, &file[..1000]).await;
+    assert_eq!(status, 200);
+    let (status, body) = lyre.play(&url).await;
+    assert_eq!(status, 404, "{}", body);
+
+    let (status, _) = send_chunk(&lyre, &id, 1000, &file[1000..]).await;
+    assert_eq!(status, 200);
+    let (status, body) = lyre.play(&url).await;
+    assert_eq!(status, 200, "{}", body);
+
+    // Untagged, so it goes by its file name
+    let queue = lyre
+        .wait_for_queue(Duration::from_secs(10), |q| {
+            q["current_track"]["status"] == "playing"
+        })
+        .await;
+    assert_eq!(current_title(&queue), Some("Intro Theme"));
+
+    let (status, body) = lyre.play("upload:00000000000000000000000000000000").await;
+    assert_eq!(status, 404, "{}", body);
+}
+
+#[tokio::test]
+async fn only_the_uploader_or_a_manager_can_delete_an_upload() {
+    let lyre = Lyre::start().await;
+    let uploads = format!("/api/uploads/{}", DEMO_GUILD);
+    let start = json!({ "filename": "intro.mp3", "size": 4096 });
+
+    let (status, managers) = lyre.post(&uploads, start.clone()).await;
+    assert_eq!(status, 201, "{}", managers);
+    let (status, members) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::POST,
+            &uploads,
+            Some(start.clone()),
+        )
+        .await;
+    assert_eq!(status, 201, "{}", members);
+
+    let path = |created: &Value| format!("{}/{}", uploads, created["data"]["id"].as_str().unwrap());
+    let (status, _) = lyre
+        .request_as(
+            MEMBER_TOKEN,
+            reqwest::Method::DELETE,
+            &path(&managers),
+            None,
+        )
+        .await;
+    assert_eq!(status, 403);
+    let (status, _) = lyre
+        .request_as(MEMBER_TOKEN, reqwest::Method::DELETE, &path(&members), None)
+        .await;
+    assert_eq!(status, 200);
+
+    // Managers can clear out anyone's uploads
+    let (status, members) = lyre
+        .request_as(MEMBER_TOKEN, reqwest::Method::POST, &uploads, Some(start))
+        .await;
+    assert_eq!(status, 201, "{}", members);
+    let (status, _) = lyre.delete(&path(&members)).await;
+    assert_eq!(status, 200);
+}